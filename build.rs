@@ -0,0 +1,30 @@
+//! With the `embedded_params` feature, copies the serialized Jubjub
+//! parameters from `SAPLING_CRYPTO_PARAMS_DIR` into the build directory,
+//! so that `JubjubBls12::embedded` and `AltJubjubBn256::embedded` can
+//! include them. The files are produced by `examples/write_params.rs`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const PARAMS_FILES: [&str; 2] = ["jubjub_bls12.params", "alt_jubjub_bn256.params"];
+
+fn main() {
+    if env::var_os("CARGO_FEATURE_EMBEDDED_PARAMS").is_none() {
+        return;
+    }
+
+    println!("cargo:rerun-if-env-changed=SAPLING_CRYPTO_PARAMS_DIR");
+
+    let params_dir = env::var("SAPLING_CRYPTO_PARAMS_DIR")
+        .expect("SAPLING_CRYPTO_PARAMS_DIR must be set when the embedded_params feature is enabled");
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    for name in PARAMS_FILES.iter() {
+        let src = Path::new(&params_dir).join(name);
+        println!("cargo:rerun-if-changed={}", src.display());
+
+        fs::copy(&src, Path::new(&out_dir).join(name))
+            .unwrap_or_else(|e| panic!("can't copy {}: {}", src.display(), e));
+    }
+}
@@ -0,0 +1,24 @@
+//! Writes the precomputed Jubjub parameters to the given directory
+//! (the current one by default), e.g. to embed them with the
+//! `embedded_params` feature.
+
+extern crate sapling_crypto_ce as sapling_crypto;
+
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use sapling_crypto::jubjub::JubjubBls12;
+use sapling_crypto::alt_babyjubjub::AltJubjubBn256;
+
+fn main() {
+    let dir = env::args().nth(1).unwrap_or(".".to_string());
+    let dir = Path::new(&dir);
+
+    let file = File::create(dir.join("jubjub_bls12.params")).expect("can't create the file");
+    JubjubBls12::new().write(BufWriter::new(file)).expect("can't write the parameters");
+
+    let file = File::create(dir.join("alt_jubjub_bn256.params")).expect("can't create the file");
+    AltJubjubBn256::new().write(BufWriter::new(file)).expect("can't write the parameters");
+}
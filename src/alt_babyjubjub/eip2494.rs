@@ -0,0 +1,165 @@
+//! Conversion between this crate's representation of Baby Jubjub
+//! and the one defined in EIP-2494, that is used by circom and
+//! iden3 tooling.
+//!
+//! EIP-2494 uses the curve `ax^2 + y^2 = 1 + dx^2y^2` with
+//! `a = 168700` and `d = 168696`. Internally the isomorphic curve
+//! with `a' = -1` is used, and the points are mapped as
+//! `(x, y) -> (x / scaling, y)` with
+//! `scaling = 1911982854305225074381251344103329931637610209014896889891168275855466657090`,
+//! so that `a * scaling^2 = -1`. The `y` coordinate is the same
+//! in both representations, as is the group law.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::pairing::bn256::{
+    Bn256,
+    Fr
+};
+
+use super::{
+    AltJubjubBn256,
+    Unknown,
+    PrimeOrder,
+    edwards
+};
+
+/// Coefficient `a` of the EIP-2494 curve.
+pub const EIP2494_A: &'static str = "168700";
+
+/// Coefficient `d` of the EIP-2494 curve.
+pub const EIP2494_D: &'static str = "168696";
+
+/// Order of the prime order subgroup, that matches
+/// the modulus of the scalar field `Fs`.
+pub const EIP2494_SUBGROUP_ORDER: &'static str = "2736030358979909402780800718157159386076813972158567259200215660948447373041";
+
+const SCALING: &'static str = "1911982854305225074381251344103329931637610209014896889891168275855466657090";
+
+const GENERATOR_X: &'static str = "995203441582195749578291179787384436505546430278305826713579947235728471134";
+const GENERATOR_Y: &'static str = "5472060717959818805561601436314318772137091100104008585924551046643952123905";
+
+const BASE_POINT_X: &'static str = "5299619240641551281634865583518297030282874472190772894086521144482721001553";
+const BASE_POINT_Y: &'static str = "16950150798460657717958625567821834550301663161624707787222815936182638968203";
+
+fn scaling() -> Fr {
+    Fr::from_str(SCALING).unwrap()
+}
+
+/// Converts EIP-2494 coordinates into a point of this crate's curve.
+/// Returns `None` if the point is not on the curve.
+pub fn from_eip2494(
+    x: Fr,
+    y: Fr,
+    params: &AltJubjubBn256
+) -> Option<edwards::Point<Bn256, Unknown>>
+{
+    let mut x = x;
+    x.mul_assign(&scaling().inverse().unwrap());
+
+    edwards::Point::from_xy(x, y, params)
+}
+
+/// Returns EIP-2494 coordinates of the point.
+pub fn into_eip2494<Subgroup>(point: &edwards::Point<Bn256, Subgroup>) -> (Fr, Fr) {
+    let (mut x, y) = point.into_xy();
+    x.mul_assign(&scaling());
+
+    (x, y)
+}
+
+/// Generator of the full group, named `G` in EIP-2494.
+pub fn generator(params: &AltJubjubBn256) -> edwards::Point<Bn256, Unknown> {
+    from_eip2494(
+        Fr::from_str(GENERATOR_X).unwrap(),
+        Fr::from_str(GENERATOR_Y).unwrap(),
+        params
+    ).expect("generator is on the curve")
+}
+
+/// Generator of the prime order subgroup, `B = 8*G`,
+/// named the base point in EIP-2494.
+pub fn base_point(params: &AltJubjubBn256) -> edwards::Point<Bn256, PrimeOrder> {
+    from_eip2494(
+        Fr::from_str(BASE_POINT_X).unwrap(),
+        Fr::from_str(BASE_POINT_Y).unwrap(),
+        params
+    ).expect("base point is on the curve")
+     .as_prime_order(params)
+     .expect("base point is in the prime order subgroup")
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use rand::{XorShiftRng, SeedableRng};
+    use super::super::{AltJubjubBn256, edwards, Unknown};
+    use super::*;
+
+    fn point(x: &str, y: &str, params: &AltJubjubBn256) -> edwards::Point<Bn256, Unknown> {
+        from_eip2494(Fr::from_str(x).unwrap(), Fr::from_str(y).unwrap(), params).unwrap()
+    }
+
+    #[test]
+    fn test_scaling() {
+        let mut a = Fr::from_str(EIP2494_A).unwrap();
+        a.mul_assign(&scaling());
+        a.mul_assign(&scaling());
+        a.negate();
+
+        assert_eq!(a, Fr::one());
+    }
+
+    #[test]
+    fn test_base_point() {
+        let params = AltJubjubBn256::new();
+
+        let g = generator(&params);
+        let b = base_point(&params);
+
+        assert!(g.mul_by_cofactor(&params) == b);
+
+        let (x, y) = into_eip2494(&b);
+        assert_eq!(x, Fr::from_str(BASE_POINT_X).unwrap());
+        assert_eq!(y, Fr::from_str(BASE_POINT_Y).unwrap());
+    }
+
+    #[test]
+    fn test_addition_vectors() {
+        // test vectors from EIP-2494
+        let params = AltJubjubBn256::new();
+
+        let p1 = point(
+            "17777552123799933955779906779655732241715742912184938656739573121738514868268",
+            "2626589144620713026669568689430873010625803728049924121243784502389097019475",
+            &params
+        );
+        let p2 = point(
+            "16540640123574156134436876038791482806971768689494387082833631921987005038935",
+            "20819045374670962167435360035096875258406992893633759881276124905556507972311",
+            &params
+        );
+
+        let (x, y) = into_eip2494(&p1.add(&p2, &params));
+
+        assert_eq!(x, Fr::from_str("7916061937171219682591368294088513039687205273691143098332585753343424131937").unwrap());
+        assert_eq!(y, Fr::from_str("14035240266687799601661095864649209771790948434046947201833777492504781204499").unwrap());
+    }
+
+    #[test]
+    fn test_back_and_forth() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = AltJubjubBn256::new();
+
+        for _ in 0..100 {
+            let p = edwards::Point::<Bn256, Unknown>::rand(rng, &params);
+            let (x, y) = into_eip2494(&p);
+
+            assert!(from_eip2494(x, y, &params).unwrap() == p);
+        }
+    }
+}
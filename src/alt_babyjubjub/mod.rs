@@ -72,11 +72,48 @@ pub use super::jubjub::{
 /// This is an implementation of the scalar field for Jubjub.
 pub mod fs;
 
+/// Conversion helpers for the EIP-2494 representation of Baby Jubjub.
+pub mod eip2494;
+
 #[cfg(test)]
 pub mod tests;
 
 use super::group_hash::GroupHasher;
 
+use super::jubjub::serialization;
+
+use std::sync::{Arc, Once};
+
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+/// Returns the parameters for BN256, shared by all callers
+/// and computed on the first call. With the `embedded_params`
+/// feature they are loaded from the embedded tables instead.
+pub fn params() -> Arc<AltJubjubBn256> {
+    static INIT: Once = Once::new();
+    // only written by `call_once`, which completes before any read
+    static mut INSTANCE: Option<Arc<AltJubjubBn256>> = None;
+
+    INIT.call_once(|| {
+        #[cfg(feature = "embedded_params")]
+        let params = AltJubjubBn256::embedded().expect("embedded parameters are corrupted");
+        #[cfg(not(feature = "embedded_params"))]
+        let params = AltJubjubBn256::new();
+
+        unsafe {
+            INSTANCE = Some(Arc::new(params));
+        }
+    });
+
+    unsafe {
+        (*::std::ptr::addr_of!(INSTANCE)).clone().unwrap()
+    }
+}
+
 impl JubjubEngine for Bn256 {
     type Fs = self::fs::Fs;
     type Params = AltJubjubBn256;
@@ -567,6 +604,68 @@ impl AltJubjubBn256 {
 
         tmp_params
     }
+
+    /// Serializes the parameters together with the precomputed tables,
+    /// so that they can be loaded with `read` instead of recomputed.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut body = vec![];
+        serialization::write_fr(&mut body, &self.edwards_d)?;
+        serialization::write_fr(&mut body, &self.montgomery_a)?;
+        serialization::write_fr(&mut body, &self.montgomery_2a)?;
+        serialization::write_fr(&mut body, &self.scale)?;
+        serialization::write_points(&mut body, &self.pedersen_hash_generators)?;
+        serialization::write_vec(&mut body, &self.pedersen_hash_exp, |w, tables| {
+            serialization::write_vec(w, tables, |w, table| serialization::write_points(w, table))
+        })?;
+        serialization::write_vec(&mut body, &self.pedersen_circuit_generators, |w, windows| {
+            serialization::write_vec(w, windows, |w, coeffs| serialization::write_coordinates(w, coeffs))
+        })?;
+        serialization::write_points(&mut body, &self.fixed_base_generators)?;
+        serialization::write_vec(&mut body, &self.fixed_base_circuit_generators, |w, windows| {
+            serialization::write_vec(w, windows, |w, coeffs| serialization::write_coordinates(w, coeffs))
+        })?;
+
+        serialization::write_checked(writer, b"altjjbn2", &body)
+    }
+
+    /// Reads the parameters written by `write`. The checksum only
+    /// detects corruption: the points aren't validated, so the
+    /// input must come from a trusted source.
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let body = serialization::read_checked(reader, b"altjjbn2")?;
+        let mut reader = &body[..];
+
+        let params = AltJubjubBn256 {
+            edwards_d: serialization::read_fr(&mut reader)?,
+            montgomery_a: serialization::read_fr(&mut reader)?,
+            montgomery_2a: serialization::read_fr(&mut reader)?,
+            scale: serialization::read_fr(&mut reader)?,
+            pedersen_hash_generators: serialization::read_points(&mut reader)?,
+            pedersen_hash_exp: serialization::read_vec(&mut reader, |r| {
+                serialization::read_vec(r, |r| serialization::read_points(r))
+            })?,
+            pedersen_circuit_generators: serialization::read_vec(&mut reader, |r| {
+                serialization::read_vec(r, |r| serialization::read_coordinates(r))
+            })?,
+            fixed_base_generators: serialization::read_points(&mut reader)?,
+            fixed_base_circuit_generators: serialization::read_vec(&mut reader, |r| {
+                serialization::read_vec(r, |r| serialization::read_coordinates(r))
+            })?,
+        };
+
+        if !reader.is_empty() || params.fixed_base_generators.len() != FixedGenerators::Max as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed parameters"));
+        }
+
+        Ok(params)
+    }
+
+    /// Loads the parameters embedded at build time with the
+    /// `embedded_params` feature, see `build.rs`.
+    #[cfg(feature = "embedded_params")]
+    pub fn embedded() -> io::Result<Self> {
+        Self::read(&include_bytes!(concat!(env!("OUT_DIR"), "/alt_jubjub_bn256.params"))[..])
+    }
 }
 
 #[test]
@@ -597,6 +696,27 @@ fn test_jubjub_altbn256() {
     // assert!(p == q);
 }
 
+#[test]
+fn test_alt_jubjub_bn256_serialization() {
+    let params = AltJubjubBn256::new();
+
+    let mut serialized = vec![];
+    params.write(&mut serialized).unwrap();
+
+    let deserialized = AltJubjubBn256::read(&serialized[..]).unwrap();
+
+    assert!(params.pedersen_hash_generators == deserialized.pedersen_hash_generators);
+    assert!(params.pedersen_hash_exp == deserialized.pedersen_hash_exp);
+    assert!(params.fixed_base_generators == deserialized.fixed_base_generators);
+    assert_eq!(params.pedersen_circuit_generators, deserialized.pedersen_circuit_generators);
+    assert_eq!(params.fixed_base_circuit_generators, deserialized.fixed_base_circuit_generators);
+
+    // the parameter sets of different curves aren't interchangeable
+    let mut bls12 = vec![];
+    ::jubjub::JubjubBls12::new().write(&mut bls12).unwrap();
+    assert!(AltJubjubBn256::read(&bls12[..]).is_err());
+}
+
 #[test]
 fn test_generic_params() {
     use super::group_hash::BlakeHasher;
@@ -0,0 +1,362 @@
+//! Anemoi (https://eprint.iacr.org/2022/840) with the open Flystel
+//! S-box, parameters derived for any prime field as in the reference
+//! implementation.
+//!
+//! The state of `2 * l` elements is split into the columns `x` and
+//! `y`. A round adds the constants, applies the linear layer (the
+//! matrix `M` on `x` and on `y` rotated by one, followed by the
+//! Pseudo-Hadamard transform) and the Flystel on every `(x_i, y_i)`:
+//!
+//! ```text
+//! x -= g * y^2
+//! y -= x^(1/alpha)
+//! x += g * y^2 + g^-1
+//! ```
+//!
+//! where `g` is `F::multiplicative_generator()`. The linear layer is
+//! applied once more after the last round.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use rescue::{
+    exponent_inverse,
+    smallest_exponent,
+    log2_binomial
+};
+
+/// Decimal digits 1 to 100 of pi.
+const PI_0: &str = "1415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
+/// Decimal digits 101 to 200 of pi.
+const PI_1: &str = "8214808651328230664709384460955058223172535940812848111745028410270193852110555964462294895493038196";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnemoiParams<F: PrimeField> {
+    /// Number of columns `l`, the state holds `2 * l` elements.
+    pub num_columns: usize,
+    pub security_level: usize,
+    pub num_rounds: usize,
+    pub alpha: u64,
+    /// Little-endian limbs of `alpha^-1 mod (p - 1)`.
+    pub alpha_inv: Vec<u64>,
+    /// `g`, the coefficient of the quadratic functions.
+    pub beta: F,
+    /// `g^-1`
+    pub delta: F,
+    /// `l x l` matrix of the linear layer.
+    pub matrix: Vec<Vec<F>>,
+    /// Constants of the `x` and `y` columns, `l` per round.
+    pub round_constants_c: Vec<Vec<F>>,
+    pub round_constants_d: Vec<Vec<F>>
+}
+
+impl<F: PrimeField> AnemoiParams<F> {
+    pub fn state_width(&self) -> usize {
+        2 * self.num_columns
+    }
+}
+
+fn get_number_of_rounds(num_columns: usize, security_level: usize, alpha: u64) -> usize {
+    let kappa = match alpha {
+        3 => 1,
+        5 => 2,
+        7 => 4,
+        9 => 7,
+        11 => 9,
+        _ => panic!("unsupported S-box exponent")
+    };
+
+    let l = num_columns as u64;
+    let mut r = 0;
+    loop {
+        r += 1;
+        if 2.0 * log2_binomial(4 * l * r + kappa, 2 * l * r) >= security_level as f64 {
+            break;
+        }
+    }
+
+    // security margin and the cost of guessing
+    let r = r as usize + 2 + std::cmp::min(5, num_columns + 1);
+
+    std::cmp::max(8, r)
+}
+
+fn get_matrix<F: PrimeField>(num_columns: usize) -> Vec<Vec<F>> {
+    let one = F::one();
+    let g = F::multiplicative_generator();
+
+    let mut g_plus_one = g;
+    g_plus_one.add_assign(&one);
+
+    let mut g_squared_plus_one = g;
+    g_squared_plus_one.square();
+    g_squared_plus_one.add_assign(&one);
+
+    match num_columns {
+        1 => vec![vec![one]],
+        2 => vec![
+            vec![one, g],
+            vec![g, g_squared_plus_one]
+        ],
+        3 => vec![
+            vec![g_plus_one, one, g_plus_one],
+            vec![one, one, g],
+            vec![g, one, one]
+        ],
+        _ => panic!("only 1, 2 or 3 columns are supported")
+    }
+}
+
+fn get_round_constants<F: PrimeField>(
+    num_columns: usize,
+    num_rounds: usize,
+    alpha: u64,
+    beta: &F,
+    delta: &F
+) -> (Vec<Vec<F>>, Vec<Vec<F>>)
+{
+    let pi_0 = F::from_str(PI_0).unwrap();
+    let pi_1 = F::from_str(PI_1).unwrap();
+
+    let mut c = vec![];
+    let mut d = vec![];
+    for r in 0..num_rounds {
+        let pi_0_r = pi_0.pow(&[r as u64]);
+
+        let mut c_r = vec![];
+        let mut d_r = vec![];
+        for i in 0..num_columns {
+            let pi_1_i = pi_1.pow(&[i as u64]);
+
+            let mut pow_alpha = pi_0_r;
+            pow_alpha.add_assign(&pi_1_i);
+            let pow_alpha = pow_alpha.pow(&[alpha]);
+
+            let mut c_ri = pi_0_r;
+            c_ri.square();
+            c_ri.mul_assign(beta);
+            c_ri.add_assign(&pow_alpha);
+
+            let mut d_ri = pi_1_i;
+            d_ri.square();
+            d_ri.mul_assign(beta);
+            d_ri.add_assign(&pow_alpha);
+            d_ri.add_assign(delta);
+
+            c_r.push(c_ri);
+            d_r.push(d_ri);
+        }
+
+        c.push(c_r);
+        d.push(d_r);
+    }
+
+    (c, d)
+}
+
+/// Derives the parameters for `num_columns` in `1..=3` and the
+/// security level in bits with the smallest valid S-box exponent.
+pub fn generate_params<F: PrimeField>(num_columns: usize, security_level: usize) -> AnemoiParams<F> {
+    let alpha = smallest_exponent::<F>();
+    let alpha_inv = exponent_inverse::<F>(alpha).expect("the smallest exponent is valid");
+
+    let beta = F::multiplicative_generator();
+    let delta = beta.inverse().unwrap();
+
+    let num_rounds = get_number_of_rounds(num_columns, security_level, alpha);
+    let (round_constants_c, round_constants_d) = get_round_constants(num_columns, num_rounds, alpha, &beta, &delta);
+
+    AnemoiParams {
+        num_columns,
+        security_level,
+        num_rounds,
+        alpha,
+        alpha_inv,
+        beta,
+        delta,
+        matrix: get_matrix(num_columns),
+        round_constants_c,
+        round_constants_d
+    }
+}
+
+fn matrix_mul<F: PrimeField>(matrix: &[Vec<F>], column: &[F]) -> Vec<F> {
+    matrix.iter().map(|row| {
+        let mut acc = F::zero();
+        for (a, b) in row.iter().zip(column.iter()) {
+            let mut tmp = *a;
+            tmp.mul_assign(b);
+            acc.add_assign(&tmp);
+        }
+
+        acc
+    }).collect()
+}
+
+fn linear_layer<F: PrimeField>(params: &AnemoiParams<F>, state: &mut [F]) {
+    let l = params.num_columns;
+
+    let mut y_rotated = state[l..].to_vec();
+    y_rotated.rotate_left(1);
+
+    let x = matrix_mul(&params.matrix, &state[..l]);
+    let y = matrix_mul(&params.matrix, &y_rotated);
+
+    for i in 0..l {
+        let mut y_i = y[i];
+        y_i.add_assign(&x[i]);
+
+        let mut x_i = x[i];
+        x_i.add_assign(&y_i);
+
+        state[i] = x_i;
+        state[l + i] = y_i;
+    }
+}
+
+fn flystel<F: PrimeField>(params: &AnemoiParams<F>, x: &mut F, y: &mut F) {
+    let mut quad = *y;
+    quad.square();
+    quad.mul_assign(&params.beta);
+    x.sub_assign(&quad);
+
+    y.sub_assign(&x.pow(&params.alpha_inv));
+
+    let mut quad = *y;
+    quad.square();
+    quad.mul_assign(&params.beta);
+    x.add_assign(&quad);
+    x.add_assign(&params.delta);
+}
+
+/// The state is the `x` column followed by the `y` column.
+pub fn anemoi_permutation<F: PrimeField>(params: &AnemoiParams<F>, state: &mut [F]) {
+    let l = params.num_columns;
+    assert_eq!(state.len(), 2 * l);
+
+    for round in 0..params.num_rounds {
+        for i in 0..l {
+            state[i].add_assign(&params.round_constants_c[round][i]);
+            state[l + i].add_assign(&params.round_constants_d[round][i]);
+        }
+
+        linear_layer(params, state);
+
+        let (x, y) = state.split_at_mut(l);
+        for (x, y) in x.iter_mut().zip(y.iter_mut()) {
+            flystel(params, x, y);
+        }
+    }
+
+    linear_layer(params, state);
+}
+
+/// Jive compression of `2 * l` elements into `l`, the mode for Merkle
+/// nodes: `out_i = x_i + y_i + P(x, y)_i + P(x, y)_{l + i}`.
+pub fn anemoi_jive<F: PrimeField>(params: &AnemoiParams<F>, input: &[F]) -> Vec<F> {
+    let l = params.num_columns;
+    assert_eq!(input.len(), 2 * l);
+
+    let mut state = input.to_vec();
+    anemoi_permutation(params, &mut state);
+
+    (0..l).map(|i| {
+        let mut acc = input[i];
+        acc.add_assign(&input[l + i]);
+        acc.add_assign(&state[i]);
+        acc.add_assign(&state[l + i]);
+
+        acc
+    }).collect()
+}
+
+/// Sponge hash with a capacity of one element, padded like
+/// `rescue::rescue_hash`.
+pub fn anemoi_hash<F: PrimeField>(params: &AnemoiParams<F>, input: &[F]) -> F {
+    let rate = params.state_width() - 1;
+
+    let mut input = input.to_vec();
+    input.push(F::one());
+    while input.len() % rate != 0 {
+        input.push(F::zero());
+    }
+
+    let mut state = vec![F::zero(); params.state_width()];
+    for chunk in input.chunks(rate) {
+        for (s, el) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(el);
+        }
+        anemoi_permutation(params, &mut state);
+    }
+
+    state[0]
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::pairing::{bn256, bls12_381};
+    use super::*;
+
+    #[test]
+    fn test_round_numbers() {
+        // the 128 bit security levels of the reference
+        let params = generate_params::<bn256::Fr>(1, 128);
+        assert_eq!(params.alpha, 5);
+        assert_eq!(params.num_rounds, 21);
+
+        let params = generate_params::<bls12_381::Fr>(2, 128);
+        assert_eq!(params.alpha, 5);
+        assert_eq!(params.num_rounds, 14);
+        assert_eq!(params.round_constants_c.len(), 14);
+        assert_eq!(params.round_constants_d[0].len(), 2);
+    }
+
+    #[test]
+    fn test_flystel_is_invertible() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<bn256::Fr>(1, 128);
+
+        let (x, y): (bn256::Fr, bn256::Fr) = (rng.gen(), rng.gen());
+        let (mut u, mut v) = (x, y);
+        flystel(&params, &mut u, &mut v);
+
+        // the closed Flystel recovers the input
+        let mut t = v;
+        t.square();
+        t.mul_assign(&params.beta);
+        t.negate();
+        t.add_assign(&u);
+        t.sub_assign(&params.delta);
+
+        let mut y_back = v;
+        y_back.add_assign(&t.pow(&params.alpha_inv));
+        assert_eq!(y_back, y);
+
+        let mut x_back = y_back;
+        x_back.square();
+        x_back.mul_assign(&params.beta);
+        x_back.add_assign(&t);
+        assert_eq!(x_back, x);
+    }
+
+    #[test]
+    fn test_jive_and_hash() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<bn256::Fr>(1, 128);
+
+        let a: bn256::Fr = rng.gen();
+        let b: bn256::Fr = rng.gen();
+
+        let ab = anemoi_jive(&params, &[a, b]);
+        assert_eq!(ab.len(), 1);
+        assert!(ab != anemoi_jive(&params, &[b, a]));
+
+        let one = bn256::Fr::one();
+        assert!(anemoi_hash(&params, &[one]) != anemoi_hash(&params, &[one, bn256::Fr::zero()]));
+        assert!(anemoi_hash(&params, &[one]) != anemoi_hash(&params, &[bn256::Fr::from_str("2").unwrap()]));
+    }
+}
@@ -0,0 +1,163 @@
+//! Append-only Merkle tree of field elements, e.g. the deposits of a
+//! rollup, with batch insertion witnesses for
+//! `circuit::append_only_tree`.
+//!
+//! Nodes are hashed with `poseidon2::poseidon2_compress` and empty
+//! leaves are zero. A batch of `2^k` leaves is appended at an offset
+//! that is a multiple of `2^k`, i.e. it fills an empty subtree of
+//! height `k`, so the old and the new root share the path of that
+//! subtree.
+
+use bellman::pairing::ff::PrimeField;
+
+use poseidon2::{
+    Poseidon2Params,
+    poseidon2_compress
+};
+
+/// Position of an inserted batch: the index of its subtree among the
+/// subtrees of its height, and their authentication path from that
+/// height up.
+#[derive(Clone, Debug)]
+pub struct BatchInsertionWitness<F: PrimeField> {
+    pub subtree_index: u64,
+    pub path: Vec<F>
+}
+
+pub struct AppendOnlyTree<'a, F: PrimeField> {
+    params: &'a Poseidon2Params<F>,
+    depth: usize,
+    /// Filled nodes of every level starting from the leaves.
+    layers: Vec<Vec<F>>,
+    /// Roots of the empty subtrees of every height.
+    empty: Vec<F>
+}
+
+/// Roots of the empty subtrees of heights `0..=depth`.
+pub fn empty_roots<F: PrimeField>(params: &Poseidon2Params<F>, depth: usize) -> Vec<F> {
+    let mut empty = vec![F::zero()];
+    for i in 0..depth {
+        let node = poseidon2_compress(params, &empty[i], &empty[i]);
+        empty.push(node);
+    }
+
+    empty
+}
+
+impl<'a, F: PrimeField> AppendOnlyTree<'a, F> {
+    pub fn new(params: &'a Poseidon2Params<F>, depth: usize) -> Self {
+        assert!(depth < 64);
+
+        AppendOnlyTree {
+            params,
+            depth,
+            layers: vec![vec![]; depth + 1],
+            empty: empty_roots(params, depth)
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.depth, 0)
+    }
+
+    fn node(&self, level: usize, index: usize) -> F {
+        self.layers[level].get(index).cloned().unwrap_or(self.empty[level])
+    }
+
+    /// Authentication path of the node at `index` of `level`, up to
+    /// the root.
+    pub fn auth_path(&self, level: usize, index: u64) -> Vec<F> {
+        let mut index = index as usize;
+
+        (level..self.depth).map(|l| {
+            let sibling = self.node(l, index ^ 1);
+            index >>= 1;
+
+            sibling
+        }).collect()
+    }
+
+    fn push(&mut self, leaf: F) {
+        assert!(self.num_leaves() < 1 << self.depth, "tree is full");
+
+        let mut index = self.num_leaves();
+        let mut cur = leaf;
+        for level in 0..=self.depth {
+            if self.layers[level].len() == index {
+                self.layers[level].push(cur);
+            } else {
+                self.layers[level][index] = cur;
+            }
+
+            if level < self.depth {
+                let (left, right) = if index & 1 == 0 {
+                    (cur, self.node(level, index + 1))
+                } else {
+                    (self.node(level, index - 1), cur)
+                };
+                cur = poseidon2_compress(self.params, &left, &right);
+                index >>= 1;
+            }
+        }
+    }
+
+    /// Appends `leaves`, whose number must be a power of two dividing
+    /// the current number of leaves, returning the witness of the
+    /// batch.
+    pub fn append_batch(&mut self, leaves: &[F]) -> BatchInsertionWitness<F> {
+        assert!(leaves.len().is_power_of_two(), "batch size must be a power of two");
+        assert!(self.num_leaves() % leaves.len() == 0, "batch must fill an empty subtree");
+
+        let height = leaves.len().trailing_zeros() as usize;
+        assert!(height <= self.depth);
+
+        let subtree_index = (self.num_leaves() >> height) as u64;
+        let path = self.auth_path(height, subtree_index);
+
+        for leaf in leaves.iter() {
+            self.push(*leaf);
+        }
+
+        BatchInsertionWitness {
+            subtree_index,
+            path
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::ff::Field;
+    use bellman::pairing::bn256::Fr;
+    use poseidon2::{generate_params, merkle_root};
+    use circuit::test::fr;
+    use super::*;
+
+    #[test]
+    fn test_append_batch() {
+        let params = generate_params::<Fr>(3);
+        let mut tree = AppendOnlyTree::new(&params, 4);
+        assert_eq!(tree.root(), merkle_root(&params, &vec![Fr::zero(); 16]));
+
+        let mut leaves = vec![];
+        for &size in [2usize, 2, 4, 8].iter() {
+            let batch: Vec<Fr> = (0..size).map(|i| fr((leaves.len() + i + 1) as u64)).collect();
+            tree.append_batch(&batch);
+            leaves.extend(batch);
+
+            let mut padded = leaves.clone();
+            padded.resize(16, Fr::zero());
+            assert_eq!(tree.root(), merkle_root(&params, &padded));
+        }
+
+        assert_eq!(tree.num_leaves(), 16);
+    }
+}
@@ -20,6 +20,8 @@ use rand::{
 
 use std::marker::PhantomData;
 
+use fields::from_repr_ct;
+
 use std::io::{
     self,
     Write,
@@ -101,17 +103,19 @@ impl<E: JubjubEngine> Point<E, Unknown> {
         let x_sign = (y_repr.as_ref()[3] >> 63) == 1;
         y_repr.as_mut()[3] &= 0x7fffffffffffffff;
 
-        match E::Fr::from_repr(y_repr) {
-            Ok(y) => {
-                match Self::get_for_y(y, x_sign, params) {
-                    Some(p) => Ok(p),
-                    None => {
-                        Err(io::Error::new(io::ErrorKind::InvalidInput, "not on curve"))
-                    }
-                }
-            },
-            Err(_) => {
-                Err(io::Error::new(io::ErrorKind::InvalidInput, "y is not in field"))
+        // a non-canonical y decodes to zero and is only rejected after
+        // the point is recovered, not before
+        let (y, y_is_canonical) = from_repr_ct::<E::Fr>(y_repr);
+        let p = Self::get_for_y(y, x_sign, params);
+
+        if !y_is_canonical {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "y is not in field"));
+        }
+
+        match p {
+            Some(p) => Ok(p),
+            None => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "not on curve"))
             }
         }
     }
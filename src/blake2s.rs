@@ -0,0 +1,112 @@
+//! Native BLAKE2s with a reduced number of rounds, the counterpart of
+//! `circuit::blake2s::insecure_reduced_rounds_blake2s`. The full hash
+//! is `blake2_rfc::blake2s`.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use circuit::blake2s::SIGMA;
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A,
+    0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19
+];
+
+fn mixing_g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+fn compression(h: &mut [u32; 8], block: &[u8; 64], t: u64, f: bool, rounds: usize) {
+    let mut m = [0u32; 16];
+    LittleEndian::read_u32_into(block, &mut m);
+
+    let mut v = [0u32; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+
+    v[12] ^= t as u32;
+    v[13] ^= (t >> 32) as u32;
+    if f {
+        v[14] = !v[14];
+    }
+
+    for i in 0..rounds {
+        let s = &SIGMA[i % 10];
+
+        mixing_g(&mut v, 0, 4,  8, 12, m[s[ 0]], m[s[ 1]]);
+        mixing_g(&mut v, 1, 5,  9, 13, m[s[ 2]], m[s[ 3]]);
+        mixing_g(&mut v, 2, 6, 10, 14, m[s[ 4]], m[s[ 5]]);
+        mixing_g(&mut v, 3, 7, 11, 15, m[s[ 6]], m[s[ 7]]);
+
+        mixing_g(&mut v, 0, 5, 10, 15, m[s[ 8]], m[s[ 9]]);
+        mixing_g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mixing_g(&mut v, 2, 7,  8, 13, m[s[12]], m[s[13]]);
+        mixing_g(&mut v, 3, 4,  9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+/// BLAKE2s-256 without a key, with `rounds` rounds of the compression
+/// function instead of 10.
+///
+/// INSECURE: only meant for constraint count experiments and testing,
+/// the output is not BLAKE2s and has no security claims.
+pub fn insecure_reduced_rounds_blake2s(input: &[u8], personalization: &[u8], rounds: usize) -> [u8; 32] {
+    assert_eq!(personalization.len(), 8);
+    assert!(rounds >= 1 && rounds <= 10);
+
+    let mut h = IV;
+    h[0] ^= 0x01010000 ^ 32;
+    h[6] ^= LittleEndian::read_u32(&personalization[0..4]);
+    h[7] ^= LittleEndian::read_u32(&personalization[4..8]);
+
+    let num_blocks = ((input.len() + 63) / 64).max(1);
+    for i in 0..num_blocks {
+        let chunk = &input[(i * 64)..((i + 1) * 64).min(input.len())];
+        let mut block = [0u8; 64];
+        block[..chunk.len()].copy_from_slice(chunk);
+
+        if i + 1 < num_blocks {
+            compression(&mut h, &block, ((i + 1) * 64) as u64, false, rounds);
+        } else {
+            compression(&mut h, &block, input.len() as u64, true, rounds);
+        }
+    }
+
+    let mut out = [0u8; 32];
+    LittleEndian::write_u32_into(&h, &mut out);
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use blake2_rfc::blake2s::Blake2s;
+    use super::*;
+
+    #[test]
+    fn test_full_rounds() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &len in [0, 1, 63, 64, 65, 128, 200].iter() {
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut h = Blake2s::with_params(32, &[], &[], b"12345678");
+            h.update(&data);
+
+            assert_eq!(&insecure_reduced_rounds_blake2s(&data, b"12345678", 10)[..], h.finalize().as_ref());
+        }
+
+        assert!(insecure_reduced_rounds_blake2s(b"abc", b"12345678", 9) != insecure_reduced_rounds_blake2s(b"abc", b"12345678", 10));
+    }
+}
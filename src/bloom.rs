@@ -0,0 +1,113 @@
+//! Bloom filter of field elements, whose membership checks are proven
+//! with `circuit::bloom`.
+//!
+//! The filter has a power of two number of bits. The `i`-th position of
+//! an item is given by the low bits of `poseidon2_compress(item, i)`.
+//! The filter is committed to as its bits packed into field elements,
+//! `F::CAPACITY` little-endian bits per element as in
+//! `circuit::multipack::compute_multipacking`.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use poseidon2::{
+    Poseidon2Params,
+    poseidon2_compress
+};
+
+use util::fr_from_u64;
+
+pub struct BloomFilter<'a, F: PrimeField> {
+    params: &'a Poseidon2Params<F>,
+    bits: Vec<bool>,
+    num_hashes: usize
+}
+
+/// The `num_hashes` positions of `item` in a filter of `num_bits` bits.
+pub fn positions<F: PrimeField>(params: &Poseidon2Params<F>, num_bits: usize, num_hashes: usize, item: &F) -> Vec<usize> {
+    assert!(num_bits.is_power_of_two());
+
+    (0..num_hashes).map(|i| {
+        let hash = poseidon2_compress(params, item, &fr_from_u64(i as u64));
+
+        (hash.into_repr().as_ref()[0] as usize) & (num_bits - 1)
+    }).collect()
+}
+
+impl<'a, F: PrimeField> BloomFilter<'a, F> {
+    pub fn new(params: &'a Poseidon2Params<F>, num_bits: usize, num_hashes: usize) -> Self {
+        assert!(num_bits.is_power_of_two() && num_bits <= 1 << 32);
+        assert!(num_hashes > 0);
+
+        BloomFilter {
+            params,
+            bits: vec![false; num_bits],
+            num_hashes
+        }
+    }
+
+    pub fn num_hashes(&self) -> usize {
+        self.num_hashes
+    }
+
+    pub fn bits(&self) -> &[bool] {
+        &self.bits
+    }
+
+    pub fn insert(&mut self, item: &F) {
+        for position in positions(self.params, self.bits.len(), self.num_hashes, item) {
+            self.bits[position] = true;
+        }
+    }
+
+    /// Returns `false` if `item` was not inserted, and `true` if it was
+    /// or for a false positive.
+    pub fn contains(&self, item: &F) -> bool {
+        positions(self.params, self.bits.len(), self.num_hashes, item).into_iter().all(|p| self.bits[p])
+    }
+
+    /// The bits packed into field elements.
+    pub fn packed(&self) -> Vec<F> {
+        self.bits.chunks(F::CAPACITY as usize).map(|chunk| {
+            let mut packed = F::zero();
+            let mut coeff = F::one();
+            for &bit in chunk.iter() {
+                if bit {
+                    packed.add_assign(&coeff);
+                }
+                coeff.double();
+            }
+
+            packed
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use circuit::multipack::compute_multipacking;
+    use poseidon2::generate_params;
+    use circuit::test::fr;
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter() {
+        let params = generate_params::<Fr>(3);
+        let mut filter = BloomFilter::new(&params, 512, 3);
+
+        for i in 0..20 {
+            filter.insert(&fr(i));
+        }
+
+        for i in 0..20 {
+            assert!(filter.contains(&fr(i)));
+        }
+        assert!((20..100).any(|i| !filter.contains(&fr(i))));
+        assert!(filter.bits().iter().filter(|&&b| b).count() <= 60);
+
+        assert_eq!(filter.packed(), compute_multipacking::<Bn256>(filter.bits()));
+    }
+}
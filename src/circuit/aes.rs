@@ -0,0 +1,718 @@
+//! AES-128 over boolean gadgets, with the CTR and GCM modes.
+//!
+//! Bytes are represented by 8 booleans in the big-endian bit order
+//! and blocks by 128 booleans, so a byte string maps to bits the same
+//! way as for the SHA-256 gadget. Constant inputs (e.g. a fixed key)
+//! are propagated and cost no constraints.
+//!
+//! The S-box is evaluated by decoding the byte into a one-hot vector
+//! of 256 linear combinations (254 constraints) and taking the output
+//! bits as sums of the indicators (2 constraints per bit).
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::{
+    Assignment
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+pub const AES_BLOCK_SIZE: usize = 128;
+pub const AES_128_KEY_SIZE: usize = 128;
+pub const AES_128_ROUNDS: usize = 10;
+
+/// Size of the GCM nonce, only 96 bit nonces are supported.
+pub const GCM_NONCE_SIZE: usize = 96;
+pub const GCM_TAG_SIZE: usize = 128;
+
+const ROUND_CONSTANTS: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+type Byte = Vec<Boolean>;
+
+fn gf256_mul(a: u8, b: u8) -> u8 {
+    let mut a = a;
+    let mut b = b;
+    let mut res = 0u8;
+    while b != 0 {
+        if b & 1 == 1 {
+            res ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+
+    res
+}
+
+/// Computes the S-box as the multiplicative inverse
+/// in GF(2^8) followed by the affine transformation.
+fn sbox_table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for x in 0..256 {
+        let x = x as u8;
+        let inv = if x == 0 {
+            0
+        } else {
+            (1..=255u8).find(|&y| gf256_mul(x, y) == 1).expect("inverse exists")
+        };
+
+        table[x as usize] = inv ^ inv.rotate_left(1) ^ inv.rotate_left(2) ^ inv.rotate_left(3) ^ inv.rotate_left(4) ^ 0x63;
+    }
+
+    table
+}
+
+fn byte_value(byte: &[Boolean]) -> Option<u8> {
+    let mut value = 0u8;
+    for bit in byte.iter() {
+        value = (value << 1) | (bit.get_value()? as u8);
+    }
+
+    Some(value)
+}
+
+fn constant_byte(value: u8) -> Byte {
+    (0..8).rev().map(|i| Boolean::constant((value >> i) & 1 == 1)).collect()
+}
+
+fn xor_bits<E, CS>(
+    mut cs: CS,
+    a: &[Boolean],
+    b: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(a.len(), b.len());
+
+    a.iter().zip(b.iter()).enumerate().map(|(i, (a, b))| {
+        Boolean::xor(cs.namespace(|| format!("xor bit {}", i)), a, b)
+    }).collect()
+}
+
+/// XOR of many bits, computed from the sum of the bits
+/// and its decomposition if it is cheaper than a chain of XORs.
+fn xor_many<E, CS>(
+    mut cs: CS,
+    bits: &[Boolean]
+) -> Result<Boolean, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut negate = false;
+    let mut variables = vec![];
+    for bit in bits.iter() {
+        match *bit {
+            Boolean::Constant(c) => negate ^= c,
+            _ => variables.push(bit.clone())
+        }
+    }
+
+    let result = if variables.len() <= 4 {
+        let mut acc = Boolean::constant(false);
+        for (i, bit) in variables.iter().enumerate() {
+            acc = Boolean::xor(cs.namespace(|| format!("xor {}", i)), &acc, bit)?;
+        }
+
+        acc
+    } else {
+        // sum = parity + 2 * q
+        let count = variables.iter().map(|b| b.get_value().map(|v| v as usize)).fold(Some(0), |acc, v| {
+            match (acc, v) {
+                (Some(acc), Some(v)) => Some(acc + v),
+                _ => None
+            }
+        });
+
+        let parity = AllocatedBit::alloc(cs.namespace(|| "parity"), count.map(|c| c & 1 == 1))?;
+
+        let mut q_bits = vec![];
+        let mut max_q = variables.len() / 2;
+        let mut i = 0;
+        while max_q > 0 {
+            let bit = AllocatedBit::alloc(
+                cs.namespace(|| format!("quotient bit {}", i)),
+                count.map(|c| ((c / 2) >> i) & 1 == 1)
+            )?;
+            q_bits.push(bit);
+            max_q >>= 1;
+            i += 1;
+        }
+
+        let mut sum = Num::<E>::zero();
+        for bit in variables.iter() {
+            sum = sum.add_bool_with_coeff(CS::one(), bit, E::Fr::one());
+        }
+
+        let mut decomposition = Num::<E>::zero().add_bool_with_coeff(CS::one(), &Boolean::from(parity.clone()), E::Fr::one());
+        let mut coeff = E::Fr::one();
+        for bit in q_bits.iter() {
+            coeff.double();
+            decomposition = decomposition.add_bool_with_coeff(CS::one(), &Boolean::from(bit.clone()), coeff);
+        }
+
+        cs.enforce(
+            || "parity decomposition",
+            |_| sum.lc(E::Fr::one()),
+            |lc| lc + CS::one(),
+            |_| decomposition.lc(E::Fr::one())
+        );
+
+        Boolean::from(parity)
+    };
+
+    Ok(if negate { result.not() } else { result })
+}
+
+/// Applies the S-box to a single byte.
+fn sub_byte<E, CS>(
+    mut cs: CS,
+    byte: &[Boolean],
+    table: &[u8; 256]
+) -> Result<Byte, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(byte.len(), 8);
+
+    if byte.iter().all(|b| b.is_constant()) {
+        return Ok(constant_byte(table[byte_value(byte).unwrap() as usize]));
+    }
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    // indicators[x] = 1 iff the processed prefix of bits equals x
+    let mut indicators = vec![Num::<E>::zero().add_bool_with_coeff(CS::one(), &Boolean::constant(true), E::Fr::one())];
+    for (i, bit) in byte.iter().enumerate() {
+        let mut next = Vec::with_capacity(indicators.len() * 2);
+        for (j, e) in indicators.iter().enumerate() {
+            let p = if i == 0 {
+                Num::<E>::zero().add_bool_with_coeff(CS::one(), bit, E::Fr::one())
+            } else {
+                let p = AllocatedNum::alloc(cs.namespace(|| format!("indicator {} {}", i, j)), || {
+                    if *bit.get_value().get()? {
+                        Ok(*e.get_value().get()?)
+                    } else {
+                        Ok(E::Fr::zero())
+                    }
+                })?;
+
+                cs.enforce(
+                    || format!("indicator {} {} constraint", i, j),
+                    |_| e.lc(E::Fr::one()),
+                    |_| bit.lc(CS::one(), E::Fr::one()),
+                    |lc| lc + p.get_variable()
+                );
+
+                Num::from(p)
+            };
+
+            let mut not_p = p.clone();
+            not_p.scale(minus_one);
+            let mut e_and_not_bit = e.clone();
+            e_and_not_bit.add_assign(&not_p);
+
+            next.push(e_and_not_bit);
+            next.push(p);
+        }
+
+        indicators = next;
+    }
+
+    let value = byte_value(byte).map(|x| table[x as usize]);
+
+    let mut output = Vec::with_capacity(8);
+    for k in 0..8 {
+        let shift = 7 - k;
+
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("output bit {}", k)),
+            value.map(|v| (v >> shift) & 1 == 1)
+        )?;
+
+        let mut sum = Num::<E>::zero();
+        for (x, indicator) in indicators.iter().enumerate() {
+            if (table[x] >> shift) & 1 == 1 {
+                sum.add_assign(indicator);
+            }
+        }
+
+        cs.enforce(
+            || format!("output bit {} constraint", k),
+            |_| sum.lc(E::Fr::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + bit.get_variable()
+        );
+
+        output.push(Boolean::from(bit));
+    }
+
+    Ok(output)
+}
+
+/// Multiplication by `x` in GF(2^8).
+fn xtime<E, CS>(
+    mut cs: CS,
+    byte: &[Boolean]
+) -> Result<Byte, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let msb = &byte[0];
+
+    Ok(vec![
+        byte[1].clone(),
+        byte[2].clone(),
+        byte[3].clone(),
+        Boolean::xor(cs.namespace(|| "bit 4"), &byte[4], msb)?,
+        Boolean::xor(cs.namespace(|| "bit 3"), &byte[5], msb)?,
+        byte[6].clone(),
+        Boolean::xor(cs.namespace(|| "bit 1"), &byte[7], msb)?,
+        msb.clone()
+    ])
+}
+
+fn sub_bytes<E, CS>(
+    mut cs: CS,
+    state: &[Byte],
+    table: &[u8; 256]
+) -> Result<Vec<Byte>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    state.iter().enumerate().map(|(i, byte)| {
+        sub_byte(cs.namespace(|| format!("byte {}", i)), byte, table)
+    }).collect()
+}
+
+/// State is stored column by column.
+fn shift_rows(state: &[Byte]) -> Vec<Byte> {
+    (0..16).map(|i| {
+        let (col, row) = (i / 4, i % 4);
+        state[4 * ((col + row) % 4) + row].clone()
+    }).collect()
+}
+
+fn mix_columns<E, CS>(
+    mut cs: CS,
+    state: &[Byte]
+) -> Result<Vec<Byte>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut result = Vec::with_capacity(16);
+    for col in 0..4 {
+        let cs = &mut cs.namespace(|| format!("column {}", col));
+        let a = &state[(4 * col)..(4 * col + 4)];
+
+        // r_i = a_i ^ t ^ xtime(a_i ^ a_{i+1}), t = a_0 ^ a_1 ^ a_2 ^ a_3
+        let t = xor_bits(cs.namespace(|| "a0 ^ a1"), &a[0], &a[1])?;
+        let t = xor_bits(cs.namespace(|| "a0 ^ a1 ^ a2"), &t, &a[2])?;
+        let t = xor_bits(cs.namespace(|| "a0 ^ a1 ^ a2 ^ a3"), &t, &a[3])?;
+
+        for row in 0..4 {
+            let cs = &mut cs.namespace(|| format!("row {}", row));
+            let u = xor_bits(cs.namespace(|| "a_i ^ a_i+1"), &a[row], &a[(row + 1) % 4])?;
+            let u = xtime(cs.namespace(|| "xtime"), &u)?;
+            let r = xor_bits(cs.namespace(|| "a_i ^ t"), &a[row], &t)?;
+            let r = xor_bits(cs.namespace(|| "result"), &r, &u)?;
+
+            result.push(r);
+        }
+    }
+
+    Ok(result)
+}
+
+fn add_round_key<E, CS>(
+    cs: CS,
+    state: &[Byte],
+    round_key: &[Boolean]
+) -> Result<Vec<Byte>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let flat: Vec<Boolean> = state.iter().flat_map(|b| b.iter().cloned()).collect();
+    let res = xor_bits(cs, &flat, round_key)?;
+
+    Ok(res.chunks(8).map(|c| c.to_vec()).collect())
+}
+
+/// Expands the 128 bit key into the 11 round keys.
+pub fn aes128_expand_key<E, CS>(
+    mut cs: CS,
+    key: &[Boolean]
+) -> Result<Vec<Vec<Boolean>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(key.len(), AES_128_KEY_SIZE);
+
+    let table = sbox_table();
+
+    // words of 4 bytes
+    let mut words: Vec<Vec<Byte>> = key.chunks(32).map(|w| w.chunks(8).map(|b| b.to_vec()).collect()).collect();
+
+    for i in 4..(4 * (AES_128_ROUNDS + 1)) {
+        let cs = &mut cs.namespace(|| format!("word {}", i));
+
+        let mut temp = words[i - 1].clone();
+        if i % 4 == 0 {
+            // RotWord, SubWord and the round constant
+            temp.rotate_left(1);
+            temp = sub_bytes(cs.namespace(|| "sub word"), &temp, &table)?;
+            temp[0] = xor_bits(cs.namespace(|| "round constant"), &temp[0], &constant_byte(ROUND_CONSTANTS[i / 4 - 1]))?;
+        }
+
+        let mut word = Vec::with_capacity(4);
+        for j in 0..4 {
+            word.push(xor_bits(cs.namespace(|| format!("byte {}", j)), &words[i - 4][j], &temp[j])?);
+        }
+
+        words.push(word);
+    }
+
+    Ok(words.chunks(4).map(|rk| rk.iter().flat_map(|w| w.iter().flat_map(|b| b.iter().cloned())).collect()).collect())
+}
+
+/// Encrypts a single block with the expanded key.
+pub fn aes128_encrypt_block<E, CS>(
+    mut cs: CS,
+    round_keys: &[Vec<Boolean>],
+    block: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(round_keys.len(), AES_128_ROUNDS + 1);
+    assert_eq!(block.len(), AES_BLOCK_SIZE);
+
+    let table = sbox_table();
+
+    let state: Vec<Byte> = block.chunks(8).map(|b| b.to_vec()).collect();
+    let mut state = add_round_key(cs.namespace(|| "initial round key"), &state, &round_keys[0])?;
+
+    for round in 1..(AES_128_ROUNDS + 1) {
+        let cs = &mut cs.namespace(|| format!("round {}", round));
+
+        state = sub_bytes(cs.namespace(|| "sub bytes"), &state, &table)?;
+        state = shift_rows(&state);
+        if round != AES_128_ROUNDS {
+            state = mix_columns(cs.namespace(|| "mix columns"), &state)?;
+        }
+        state = add_round_key(cs.namespace(|| "add round key"), &state, &round_keys[round])?;
+    }
+
+    Ok(state.into_iter().flat_map(|b| b.into_iter()).collect())
+}
+
+fn counter_block(nonce: &[Boolean], counter: u32) -> Vec<Boolean> {
+    let mut block = nonce.to_vec();
+    block.extend((0..32).rev().map(|i| Boolean::constant((counter >> i) & 1 == 1)));
+
+    block
+}
+
+/// CTR mode with the counter block formed by the 96 bit nonce and
+/// a 32 bit big-endian counter starting from `initial_counter`, as
+/// in GCM. The input is truncated to whole bytes of the keystream.
+pub fn aes128_ctr<E, CS>(
+    mut cs: CS,
+    round_keys: &[Vec<Boolean>],
+    nonce: &[Boolean],
+    initial_counter: u32,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(nonce.len(), GCM_NONCE_SIZE);
+    assert!(input.len() % 8 == 0);
+
+    let mut output = Vec::with_capacity(input.len());
+    for (i, chunk) in input.chunks(AES_BLOCK_SIZE).enumerate() {
+        let cs = &mut cs.namespace(|| format!("block {}", i));
+
+        let counter = counter_block(nonce, initial_counter.wrapping_add(i as u32));
+        let keystream = aes128_encrypt_block(cs.namespace(|| "keystream"), round_keys, &counter)?;
+
+        output.extend(xor_bits(cs.namespace(|| "xor"), chunk, &keystream[..chunk.len()])?);
+    }
+
+    Ok(output)
+}
+
+/// Columns of the multiplication by `h` in GF(2^128) with
+/// the GCM bit order: the column `i` is `h * x^i`.
+fn gf128_multiplication_table<E, CS>(
+    mut cs: CS,
+    h: &[Boolean]
+) -> Result<Vec<Vec<Boolean>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut v = h.to_vec();
+    let mut columns = vec![v.clone()];
+
+    for i in 1..AES_BLOCK_SIZE {
+        let cs = &mut cs.namespace(|| format!("power {}", i));
+
+        // right shift, reducing by R = 11100001 || 0^120
+        let lsb = v[AES_BLOCK_SIZE - 1].clone();
+        let mut next = Vec::with_capacity(AES_BLOCK_SIZE);
+        next.push(lsb.clone());
+        for j in 1..AES_BLOCK_SIZE {
+            if j == 1 || j == 2 || j == 7 {
+                next.push(Boolean::xor(cs.namespace(|| format!("reduce bit {}", j)), &v[j - 1], &lsb)?);
+            } else {
+                next.push(v[j - 1].clone());
+            }
+        }
+
+        v = next;
+        columns.push(v.clone());
+    }
+
+    Ok(columns)
+}
+
+fn gf128_mul<E, CS>(
+    mut cs: CS,
+    x: &[Boolean],
+    table: &[Vec<Boolean>]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut result = Vec::with_capacity(AES_BLOCK_SIZE);
+    for j in 0..AES_BLOCK_SIZE {
+        let cs = &mut cs.namespace(|| format!("bit {}", j));
+
+        let mut terms = Vec::with_capacity(AES_BLOCK_SIZE);
+        for i in 0..AES_BLOCK_SIZE {
+            terms.push(Boolean::and(cs.namespace(|| format!("term {}", i)), &x[i], &table[i][j])?);
+        }
+
+        result.push(xor_many(cs.namespace(|| "sum"), &terms)?);
+    }
+
+    Ok(result)
+}
+
+fn ghash<E, CS>(
+    mut cs: CS,
+    h: &[Boolean],
+    aad: &[Boolean],
+    ciphertext: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let table = gf128_multiplication_table(cs.namespace(|| "multiplication table"), h)?;
+
+    let pad = |bits: &[Boolean]| -> Vec<Boolean> {
+        let mut padded = bits.to_vec();
+        while padded.len() % AES_BLOCK_SIZE != 0 {
+            padded.push(Boolean::constant(false));
+        }
+
+        padded
+    };
+
+    let mut input = pad(aad);
+    input.extend(pad(ciphertext));
+    for &len in [aad.len() as u64, ciphertext.len() as u64].iter() {
+        input.extend((0..64).rev().map(|i| Boolean::constant((len >> i) & 1 == 1)));
+    }
+
+    let mut x = vec![Boolean::constant(false); AES_BLOCK_SIZE];
+    for (i, block) in input.chunks(AES_BLOCK_SIZE).enumerate() {
+        let cs = &mut cs.namespace(|| format!("block {}", i));
+
+        let tmp = xor_bits(cs.namespace(|| "xor"), &x, block)?;
+        x = gf128_mul(cs.namespace(|| "multiply"), &tmp, &table)?;
+    }
+
+    Ok(x)
+}
+
+fn gcm_tag<E, CS>(
+    mut cs: CS,
+    round_keys: &[Vec<Boolean>],
+    nonce: &[Boolean],
+    aad: &[Boolean],
+    ciphertext: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let zero_block = vec![Boolean::constant(false); AES_BLOCK_SIZE];
+    let h = aes128_encrypt_block(cs.namespace(|| "hash key"), round_keys, &zero_block)?;
+
+    let s = ghash(cs.namespace(|| "ghash"), &h, aad, ciphertext)?;
+
+    let j0 = counter_block(nonce, 1);
+    let mask = aes128_encrypt_block(cs.namespace(|| "tag mask"), round_keys, &j0)?;
+
+    xor_bits(cs.namespace(|| "tag"), &s, &mask)
+}
+
+/// AES-128-GCM encryption, returns the ciphertext and the tag.
+pub fn aes128_gcm_encrypt<E, CS>(
+    mut cs: CS,
+    round_keys: &[Vec<Boolean>],
+    nonce: &[Boolean],
+    aad: &[Boolean],
+    plaintext: &[Boolean]
+) -> Result<(Vec<Boolean>, Vec<Boolean>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(aad.len() % 8 == 0);
+
+    let ciphertext = aes128_ctr(cs.namespace(|| "encryption"), round_keys, nonce, 2, plaintext)?;
+    let tag = gcm_tag(cs.namespace(|| "authentication"), round_keys, nonce, aad, &ciphertext)?;
+
+    Ok((ciphertext, tag))
+}
+
+/// AES-128-GCM decryption, enforces that the tag is valid
+/// and returns the plaintext.
+pub fn aes128_gcm_decrypt<E, CS>(
+    mut cs: CS,
+    round_keys: &[Vec<Boolean>],
+    nonce: &[Boolean],
+    aad: &[Boolean],
+    ciphertext: &[Boolean],
+    tag: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(aad.len() % 8 == 0);
+    assert_eq!(tag.len(), GCM_TAG_SIZE);
+
+    let expected_tag = gcm_tag(cs.namespace(|| "authentication"), round_keys, nonce, aad, ciphertext)?;
+    for (i, (a, b)) in expected_tag.iter().zip(tag.iter()).enumerate() {
+        Boolean::enforce_equal(cs.namespace(|| format!("tag bit {}", i)), a, b)?;
+    }
+
+    aes128_ctr(cs.namespace(|| "decryption"), round_keys, nonce, 2, ciphertext)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256};
+    use ::circuit::test::*;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use super::*;
+
+    fn constant_bits(bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().flat_map(|&b| (0..8).rev().map(move |i| Boolean::constant((b >> i) & 1 == 1))).collect()
+    }
+
+    fn alloc_bits<CS: ConstraintSystem<Bn256>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().enumerate().flat_map(|(i, &b)| (0..8).rev().map(move |j| (i, j, (b >> j) & 1 == 1)))
+            .map(|(i, j, bit)| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {} {}", i, j)), Some(bit)).unwrap())
+            }).collect()
+    }
+
+    fn into_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|b| byte_value(b).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_sbox() {
+        let table = sbox_table();
+        assert_eq!(table[0x00], 0x63);
+        assert_eq!(table[0x01], 0x7c);
+        assert_eq!(table[0x53], 0xed);
+        assert_eq!(table[0xff], 0x16);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        for x in [0x00u8, 0x53, 0xff].iter() {
+            let byte = alloc_bits(cs.namespace(|| format!("input {}", x)), &[*x]);
+            let out = sub_byte(cs.namespace(|| format!("sbox {}", x)), &byte, &table).unwrap();
+            assert_eq!(byte_value(&out), Some(table[*x as usize]));
+        }
+
+        assert!(cs.is_satisfied());
+        // booleanity of the inputs, the indicators and the output bits
+        assert_eq!(cs.num_constraints(), 3 * (8 + 254 + 16));
+    }
+
+    #[test]
+    fn test_fips197_vector() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let key = alloc_bits(cs.namespace(|| "key"), &hex!("000102030405060708090a0b0c0d0e0f"));
+        let plaintext = alloc_bits(cs.namespace(|| "plaintext"), &hex!("00112233445566778899aabbccddeeff"));
+
+        let round_keys = aes128_expand_key(cs.namespace(|| "key expansion"), &key).unwrap();
+        let ciphertext = aes128_encrypt_block(cs.namespace(|| "encrypt"), &round_keys, &plaintext).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&ciphertext), hex!("69c4e0d86a7b0430d8cdb78070b4c55a").to_vec());
+    }
+
+    #[test]
+    fn test_gcm_vectors() {
+        // test cases 1 and 2 from the GCM specification
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let key = constant_bits(&[0u8; 16]);
+        let nonce = constant_bits(&[0u8; 12]);
+
+        let round_keys = aes128_expand_key(cs.namespace(|| "key expansion"), &key).unwrap();
+
+        let (ciphertext, tag) = aes128_gcm_encrypt(cs.namespace(|| "empty"), &round_keys, &nonce, &[], &[]).unwrap();
+        assert!(ciphertext.is_empty());
+        assert_eq!(into_bytes(&tag), hex!("58e2fccefa7e3061367f1d57a4e7455a").to_vec());
+
+        let plaintext = alloc_bits(cs.namespace(|| "plaintext"), &[0u8; 16]);
+        let (ciphertext, tag) = aes128_gcm_encrypt(cs.namespace(|| "single block"), &round_keys, &nonce, &[], &plaintext).unwrap();
+        assert_eq!(into_bytes(&ciphertext), hex!("0388dace60b6a392f328c2b971b2fe78").to_vec());
+        assert_eq!(into_bytes(&tag), hex!("ab6e47d42cec13bdf53a67b21257bddf").to_vec());
+
+        let ciphertext = alloc_bits(cs.namespace(|| "ciphertext"), &hex!("0388dace60b6a392f328c2b971b2fe78"));
+        let tag = alloc_bits(cs.namespace(|| "tag"), &hex!("ab6e47d42cec13bdf53a67b21257bddf"));
+        let decrypted = aes128_gcm_decrypt(cs.namespace(|| "decrypt"), &round_keys, &nonce, &[], &ciphertext, &tag).unwrap();
+        assert_eq!(into_bytes(&decrypted), vec![0u8; 16]);
+
+        assert!(cs.is_satisfied());
+
+        // a wrong tag is rejected
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let round_keys = aes128_expand_key(cs.namespace(|| "key expansion"), &key).unwrap();
+        let ciphertext = alloc_bits(cs.namespace(|| "ciphertext"), &hex!("0388dace60b6a392f328c2b971b2fe78"));
+        let tag = alloc_bits(cs.namespace(|| "tag"), &hex!("ab6e47d42cec13bdf53a67b21257bdde"));
+        aes128_gcm_decrypt(cs.namespace(|| "decrypt"), &round_keys, &nonce, &[], &ciphertext, &tag).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
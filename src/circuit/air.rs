@@ -0,0 +1,449 @@
+//! Out-of-domain checks of a STARK verifier: evaluation of the AIR
+//! constraints and of the composition polynomial at a random point.
+//!
+//! A trace of `trace_width` columns over a radix-2 domain `H` of size
+//! `n` with generator `g` satisfies the AIR iff every transition
+//! constraint `C_k(T(x), T(gx))` vanishes on all rows but the last one,
+//! and every boundary constraint `T_col(g^row) = value` holds. The
+//! composition polynomial is
+//!
+//! `H(x) = sum_k a_k C_k(x) (x - g^(n-1)) / Z_H(x) + sum_b b_b (T_col(x) - value) / (x - g^row)`
+//!
+//! with the coefficients drawn from the transcript. Given the trace
+//! values at `z` and `gz` the gadgets recompute `H(z)`. The openings of
+//! the trace and of the composition polynomial at these points are
+//! left to the caller, e.g. checked by a FRI verifier.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::polynomial::{
+    Radix2Domain,
+    evaluate_vanishing_polynomial
+};
+
+/// Trace cell relative to the current row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Current(usize),
+    Next(usize)
+}
+
+/// Sum of `coeff * prod cells` terms.
+#[derive(Clone, Debug)]
+pub struct TransitionConstraint<F: PrimeField> {
+    pub terms: Vec<(F, Vec<Cell>)>
+}
+
+#[derive(Clone, Debug)]
+pub struct BoundaryConstraint<F: PrimeField> {
+    pub column: usize,
+    pub row: usize,
+    pub value: F
+}
+
+#[derive(Clone, Debug)]
+pub struct Air<E: Engine> {
+    pub trace_width: usize,
+    pub domain: Radix2Domain<E>,
+    pub transitions: Vec<TransitionConstraint<E::Fr>>,
+    pub boundaries: Vec<BoundaryConstraint<E::Fr>>
+}
+
+impl<F: PrimeField> TransitionConstraint<F> {
+    pub fn evaluate(&self, current: &[F], next: &[F]) -> F {
+        let mut acc = F::zero();
+        for &(ref coeff, ref cells) in self.terms.iter() {
+            let mut term = *coeff;
+            for cell in cells.iter() {
+                match *cell {
+                    Cell::Current(i) => term.mul_assign(&current[i]),
+                    Cell::Next(i) => term.mul_assign(&next[i])
+                }
+            }
+            acc.add_assign(&term);
+        }
+
+        acc
+    }
+}
+
+impl<E: Engine> Air<E> {
+    /// Number of composition coefficients: one per transition
+    /// constraint followed by one per boundary constraint.
+    pub fn num_coefficients(&self) -> usize {
+        self.transitions.len() + self.boundaries.len()
+    }
+
+    /// Computes `H(z)` from the trace values at `z` and `gz`, or `None`
+    /// if `z` is in the trace domain.
+    pub fn composition_at(
+        &self,
+        z: &E::Fr,
+        current: &[E::Fr],
+        next: &[E::Fr],
+        coefficients: &[E::Fr]
+    ) -> Option<E::Fr>
+    {
+        assert_eq!(current.len(), self.trace_width);
+        assert_eq!(next.len(), self.trace_width);
+        assert_eq!(coefficients.len(), self.num_coefficients());
+
+        let mut z_h = z.pow(&[self.domain.size as u64]);
+        z_h.sub_assign(&E::Fr::one());
+
+        let mut transitions = E::Fr::zero();
+        for (c, a) in self.transitions.iter().zip(coefficients.iter()) {
+            let mut tmp = c.evaluate(current, next);
+            tmp.mul_assign(a);
+            transitions.add_assign(&tmp);
+        }
+
+        let mut last_row = *z;
+        last_row.sub_assign(&self.domain.element(self.domain.size - 1));
+
+        let mut result = transitions;
+        result.mul_assign(&last_row);
+        result.mul_assign(&z_h.inverse()?);
+
+        for (b, coeff) in self.boundaries.iter().zip(coefficients[self.transitions.len()..].iter()) {
+            let mut denominator = *z;
+            denominator.sub_assign(&self.domain.element(b.row));
+
+            let mut tmp = current[b.column];
+            tmp.sub_assign(&b.value);
+            tmp.mul_assign(&denominator.inverse()?);
+            tmp.mul_assign(coeff);
+            result.add_assign(&tmp);
+        }
+
+        Some(result)
+    }
+}
+
+fn evaluate_transition<E, CS>(
+    mut cs: CS,
+    constraint: &TransitionConstraint<E::Fr>,
+    current: &[AllocatedNum<E>],
+    next: &[AllocatedNum<E>]
+) -> Result<Num<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut acc = Num::<E>::zero();
+    for (i, &(coeff, ref cells)) in constraint.terms.iter().enumerate() {
+        let mut cells = cells.iter().map(|cell| {
+            match *cell {
+                Cell::Current(i) => &current[i],
+                Cell::Next(i) => &next[i]
+            }
+        });
+
+        acc = match cells.next() {
+            Some(first) => {
+                let mut product = first.clone();
+                for (j, cell) in cells.enumerate() {
+                    product = product.mul(cs.namespace(|| format!("term {} product {}", i, j)), cell)?;
+                }
+
+                acc.add_number_with_coeff(&product, coeff)
+            },
+            None => acc.add_bool_with_coeff(CS::one(), &Boolean::constant(true), coeff)
+        };
+    }
+
+    Ok(acc)
+}
+
+/// Evaluates `H(z)` from the trace values at `z` and `gz`. Costs one
+/// constraint per transition and per multiplication in its terms, two
+/// per boundary constraint, plus `log n + 4`. `z` must not be in the
+/// trace domain.
+pub fn evaluate_composition<E, CS>(
+    mut cs: CS,
+    air: &Air<E>,
+    z: &AllocatedNum<E>,
+    current: &[AllocatedNum<E>],
+    next: &[AllocatedNum<E>],
+    coefficients: &[AllocatedNum<E>]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(current.len(), air.trace_width);
+    assert_eq!(next.len(), air.trace_width);
+    assert_eq!(coefficients.len(), air.num_coefficients());
+
+    let z_h = evaluate_vanishing_polynomial(cs.namespace(|| "vanishing polynomial"), &air.domain, z)?;
+
+    // sum_k a_k C_k(z)
+    let mut transitions = Num::<E>::zero();
+    for (k, (constraint, coeff)) in air.transitions.iter().zip(coefficients.iter()).enumerate() {
+        let c = evaluate_transition(cs.namespace(|| format!("transition {}", k)), constraint, current, next)?;
+
+        let term = AllocatedNum::alloc(cs.namespace(|| format!("transition {} term", k)), || {
+            let mut tmp = *c.get_value().get()?;
+            tmp.mul_assign(coeff.get_value().get()?);
+
+            Ok(tmp)
+        })?;
+
+        cs.enforce(
+            || format!("transition {} term constraint", k),
+            |_| c.lc(E::Fr::one()),
+            |lc| lc + coeff.get_variable(),
+            |lc| lc + term.get_variable()
+        );
+
+        transitions = transitions.add_number_with_coeff(&term, E::Fr::one());
+    }
+
+    // the transitions are not enforced on the last row
+    let last_row = air.domain.element(air.domain.size - 1);
+
+    let numerator = AllocatedNum::alloc(cs.namespace(|| "transitions numerator"), || {
+        let mut tmp = *z.get_value().get()?;
+        tmp.sub_assign(&last_row);
+        tmp.mul_assign(transitions.get_value().get()?);
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "transitions numerator constraint",
+        |_| transitions.lc(E::Fr::one()),
+        |lc| lc + z.get_variable() - (last_row, CS::one()),
+        |lc| lc + numerator.get_variable()
+    );
+
+    let quotient = AllocatedNum::alloc(cs.namespace(|| "transitions quotient"), || {
+        let inv = z_h.get_value().get()?.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+        let mut tmp = *numerator.get_value().get()?;
+        tmp.mul_assign(&inv);
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "transitions quotient constraint",
+        |lc| lc + quotient.get_variable(),
+        |lc| lc + z_h.get_variable(),
+        |lc| lc + numerator.get_variable()
+    );
+
+    let mut result = Num::from(quotient);
+
+    for (i, (b, coeff)) in air.boundaries.iter().zip(coefficients[air.transitions.len()..].iter()).enumerate() {
+        let point = air.domain.element(b.row);
+        let value = &current[b.column];
+
+        let term = AllocatedNum::alloc(cs.namespace(|| format!("boundary {} quotient", i)), || {
+            let mut denominator = *z.get_value().get()?;
+            denominator.sub_assign(&point);
+            let inv = denominator.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            let mut tmp = *value.get_value().get()?;
+            tmp.sub_assign(&b.value);
+            tmp.mul_assign(&inv);
+
+            Ok(tmp)
+        })?;
+
+        cs.enforce(
+            || format!("boundary {} quotient constraint", i),
+            |lc| lc + term.get_variable(),
+            |lc| lc + z.get_variable() - (point, CS::one()),
+            |lc| lc + value.get_variable() - (b.value, CS::one())
+        );
+
+        let scaled = term.mul(cs.namespace(|| format!("boundary {} term", i)), coeff)?;
+
+        result = result.add_number_with_coeff(&scaled, E::Fr::one());
+    }
+
+    let allocated = AllocatedNum::alloc(cs.namespace(|| "composition"), || {
+        Ok(*result.get_value().get()?)
+    })?;
+
+    cs.enforce(
+        || "composition constraint",
+        |_| result.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + allocated.get_variable()
+    );
+
+    Ok(allocated)
+}
+
+/// Enforces that the opened value of the composition polynomial at
+/// `z` matches the one recomputed from the trace values.
+pub fn enforce_composition<E, CS>(
+    mut cs: CS,
+    air: &Air<E>,
+    z: &AllocatedNum<E>,
+    current: &[AllocatedNum<E>],
+    next: &[AllocatedNum<E>],
+    coefficients: &[AllocatedNum<E>],
+    claimed: &AllocatedNum<E>
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let computed = evaluate_composition(cs.namespace(|| "evaluate"), air, z, current, next, coefficients)?;
+
+    cs.enforce(
+        || "enforce composition",
+        |lc| lc + computed.get_variable() - claimed.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use super::*;
+
+    fn minus_one() -> Fr {
+        let mut tmp = Fr::one();
+        tmp.negate();
+
+        tmp
+    }
+
+    /// Columns `(a, b)` with `a' = b`, `b' = a + b` and `a_0 = b_0 = 1`.
+    fn fibonacci_air(size: usize, result: Fr) -> Air<Bn256> {
+        let transitions = vec![
+            TransitionConstraint {
+                terms: vec![(Fr::one(), vec![Cell::Next(0)]), (minus_one(), vec![Cell::Current(1)])]
+            },
+            TransitionConstraint {
+                terms: vec![
+                    (Fr::one(), vec![Cell::Next(1)]),
+                    (minus_one(), vec![Cell::Current(0)]),
+                    (minus_one(), vec![Cell::Current(1)])
+                ]
+            }
+        ];
+
+        let boundaries = vec![
+            BoundaryConstraint { column: 0, row: 0, value: Fr::one() },
+            BoundaryConstraint { column: 1, row: 0, value: Fr::one() },
+            BoundaryConstraint { column: 1, row: size - 1, value: result }
+        ];
+
+        Air {
+            trace_width: 2,
+            domain: Radix2Domain::new(size).unwrap(),
+            transitions,
+            boundaries
+        }
+    }
+
+    fn barycentric(domain: &Radix2Domain<Bn256>, values: &[Fr], x: &Fr) -> Fr {
+        let mut z_h = x.pow(&[domain.size as u64]);
+        z_h.sub_assign(&Fr::one());
+
+        let mut sum = Fr::zero();
+        for (value, omega) in values.iter().zip(domain.elements().iter()) {
+            let mut denominator = *x;
+            denominator.sub_assign(omega);
+
+            let mut tmp = *value;
+            tmp.mul_assign(omega);
+            tmp.mul_assign(&denominator.inverse().unwrap());
+            sum.add_assign(&tmp);
+        }
+
+        sum.mul_assign(&z_h);
+        sum.mul_assign(&domain.size_inv);
+
+        sum
+    }
+
+    #[test]
+    fn test_fibonacci_composition() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let size = 8;
+        let mut columns = vec![vec![Fr::one()], vec![Fr::one()]];
+        for i in 1..size {
+            let (a, b) = (columns[0][i - 1], columns[1][i - 1]);
+            let mut sum = a;
+            sum.add_assign(&b);
+            columns[0].push(b);
+            columns[1].push(sum);
+        }
+
+        let air = fibonacci_air(size, fr(34));
+        assert_eq!(columns[1][size - 1], fr(34));
+
+        // the trace satisfies the constraints on the domain
+        for row in 0..(size - 1) {
+            let current = [columns[0][row], columns[1][row]];
+            let next = [columns[0][row + 1], columns[1][row + 1]];
+            for c in air.transitions.iter() {
+                assert!(c.evaluate(&current, &next).is_zero());
+            }
+        }
+
+        let z: Fr = rng.gen();
+        let mut gz = z;
+        gz.mul_assign(&air.domain.generator);
+
+        let current: Vec<Fr> = columns.iter().map(|c| barycentric(&air.domain, c, &z)).collect();
+        let next: Vec<Fr> = columns.iter().map(|c| barycentric(&air.domain, c, &gz)).collect();
+        let coefficients: Vec<Fr> = (0..air.num_coefficients()).map(|_| rng.gen()).collect();
+
+        let expected = air.composition_at(&z, &current, &next, &coefficients).unwrap();
+
+        for &(claimed, valid) in [(expected, true), (rng.gen(), false)].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let mut alloc = |name: &str, values: &[Fr]| -> Vec<AllocatedNum<Bn256>> {
+                values.iter().enumerate().map(|(i, v)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("{} {}", name, i)), || Ok(*v)).unwrap()
+                }).collect()
+            };
+
+            let z_var = alloc("z", &[z]).pop().unwrap();
+            let claimed_var = alloc("claimed", &[claimed]).pop().unwrap();
+            let current = alloc("current", &current);
+            let next = alloc("next", &next);
+            let coefficients = alloc("coefficients", &coefficients);
+
+            enforce_composition(cs.namespace(|| "composition"), &air, &z_var, &current, &next, &coefficients, &claimed_var).unwrap();
+
+            assert_eq!(cs.is_satisfied(), valid);
+            // 2 transition terms, 3 boundary quotients with their terms,
+            // 3 squarings and Z_H, numerator, quotient, result and check
+            assert_eq!(cs.num_constraints(), 2 + 3 * 2 + 3 + 1 + 4);
+        }
+    }
+}
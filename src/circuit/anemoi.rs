@@ -0,0 +1,284 @@
+//! Anemoi permutation, Jive compression and sponge hash for the
+//! parameters of `anemoi::generate_params`.
+//!
+//! The Flystel is constrained in its closed form: `x^(1/alpha)` is
+//! allocated and its power checked, so a round costs `l` times two
+//! squarings plus the power, e.g. 5 constraints per column for
+//! `alpha = 5`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::sbox::SBox;
+
+use anemoi::AnemoiParams;
+
+fn matrix_mul<E: Engine>(matrix: &[Vec<E::Fr>], column: &[Num<E>]) -> Vec<Num<E>> {
+    matrix.iter().map(|row| {
+        let mut acc = Num::<E>::zero();
+        for (coeff, s) in row.iter().zip(column.iter()) {
+            let mut term = s.clone();
+            term.scale(*coeff);
+            acc.add_assign(&term);
+        }
+
+        acc
+    }).collect()
+}
+
+fn linear_layer<E: Engine>(params: &AnemoiParams<E::Fr>, state: &mut [Num<E>]) {
+    let l = params.num_columns;
+
+    let mut y_rotated = state[l..].to_vec();
+    y_rotated.rotate_left(1);
+
+    let x = matrix_mul(&params.matrix, &state[..l]);
+    let y = matrix_mul(&params.matrix, &y_rotated);
+
+    for i in 0..l {
+        let mut y_i = y[i].clone();
+        y_i.add_assign(&x[i]);
+
+        let mut x_i = x[i].clone();
+        x_i.add_assign(&y_i);
+
+        state[i] = x_i;
+        state[l + i] = y_i;
+    }
+}
+
+fn square<E, CS>(mut cs: CS, x: &Num<E>) -> Result<Num<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let result = AllocatedNum::alloc(cs.namespace(|| "square"), || {
+        let mut tmp = *x.get_value().get()?;
+        tmp.square();
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "square constraint",
+        |_| x.lc(E::Fr::one()),
+        |_| x.lc(E::Fr::one()),
+        |lc| lc + result.get_variable()
+    );
+
+    Ok(Num::from(result))
+}
+
+fn flystel<E, CS>(
+    mut cs: CS,
+    params: &AnemoiParams<E::Fr>,
+    x: &Num<E>,
+    y: &Num<E>
+) -> Result<(Num<E>, Num<E>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    let mut minus_beta = params.beta;
+    minus_beta.negate();
+
+    // t = x - g * y^2
+    let mut t = square(cs.namespace(|| "y^2"), y)?;
+    t.scale(minus_beta);
+    t.add_assign(x);
+
+    // y' = y - t^(1/alpha)
+    let root = SBox::Power(params.alpha).apply_inverse(cs.namespace(|| "root"), &t)?;
+    let mut y_out = Num::from(root);
+    y_out.scale(minus_one);
+    y_out.add_assign(y);
+
+    // x' = t + g * y'^2 + g^-1
+    let mut x_out = square(cs.namespace(|| "y'^2"), &y_out)?;
+    x_out.scale(params.beta);
+    x_out.add_assign(&t);
+    x_out.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), params.delta);
+
+    Ok((x_out, y_out))
+}
+
+fn enforce_output<E, CS>(
+    mut cs: CS,
+    num: &Num<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let allocated = AllocatedNum::alloc(cs.namespace(|| "output"), || {
+        Ok(*num.get_value().get()?)
+    })?;
+
+    cs.enforce(
+        || "output constraint",
+        |_| num.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + allocated.get_variable()
+    );
+
+    Ok(allocated)
+}
+
+/// The state is the `x` column followed by the `y` column.
+pub fn anemoi_permutation<E, CS>(
+    mut cs: CS,
+    params: &AnemoiParams<E::Fr>,
+    state: &[Num<E>]
+) -> Result<Vec<Num<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let l = params.num_columns;
+    assert_eq!(state.len(), 2 * l);
+
+    let mut state = state.to_vec();
+    for round in 0..params.num_rounds {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+
+        for i in 0..l {
+            state[i].mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), params.round_constants_c[round][i]);
+            state[l + i].mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), params.round_constants_d[round][i]);
+        }
+
+        linear_layer(params, &mut state);
+
+        for i in 0..l {
+            let (x, y) = flystel(cs.namespace(|| format!("flystel {}", i)), params, &state[i], &state[l + i])?;
+            state[i] = x;
+            state[l + i] = y;
+        }
+    }
+
+    linear_layer(params, &mut state);
+
+    Ok(state)
+}
+
+/// Jive compression matching `anemoi::anemoi_jive`.
+pub fn anemoi_jive<E, CS>(
+    mut cs: CS,
+    params: &AnemoiParams<E::Fr>,
+    input: &[AllocatedNum<E>]
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let l = params.num_columns;
+    assert_eq!(input.len(), 2 * l);
+
+    let input: Vec<Num<E>> = input.iter().map(|el| Num::from(el.clone())).collect();
+    let state = anemoi_permutation(cs.namespace(|| "permutation"), params, &input)?;
+
+    (0..l).map(|i| {
+        let mut acc = input[i].clone();
+        acc.add_assign(&input[l + i]);
+        acc.add_assign(&state[i]);
+        acc.add_assign(&state[l + i]);
+
+        enforce_output(cs.namespace(|| format!("output {}", i)), &acc)
+    }).collect()
+}
+
+/// Sponge hash matching `anemoi::anemoi_hash`.
+pub fn anemoi_hash<E, CS>(
+    mut cs: CS,
+    params: &AnemoiParams<E::Fr>,
+    input: &[AllocatedNum<E>]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let rate = params.state_width() - 1;
+
+    let mut input: Vec<Num<E>> = input.iter().map(|el| Num::from(el.clone())).collect();
+    let mut one = Num::<E>::zero();
+    one.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), E::Fr::one());
+    input.push(one);
+    while input.len() % rate != 0 {
+        input.push(Num::zero());
+    }
+
+    let mut state = vec![Num::<E>::zero(); params.state_width()];
+    for (i, chunk) in input.chunks(rate).enumerate() {
+        for (s, el) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(el);
+        }
+        state = anemoi_permutation(cs.namespace(|| format!("permutation {}", i)), params, &state)?;
+    }
+
+    enforce_output(cs.namespace(|| "output"), &state[0])
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::bls12_381::{self, Bls12};
+    use ::circuit::test::*;
+    use ::anemoi::generate_params;
+    use super::*;
+
+    #[test]
+    fn test_anemoi_jive() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<Fr>(1, 128);
+
+        let input: Vec<Fr> = (0..2).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input_vars: Vec<_> = input.iter().enumerate().map(|(i, el)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap()
+        }).collect();
+
+        let output = anemoi_jive(cs.namespace(|| "jive"), &params, &input_vars).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(output[0].get_value().unwrap(), ::anemoi::anemoi_jive(&params, &input)[0]);
+
+        // 2 squarings and x^5 per round, and the output
+        assert_eq!(cs.num_constraints(), params.num_rounds * (2 + 3) + 1);
+    }
+
+    #[test]
+    fn test_anemoi_hash() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<bls12_381::Fr>(2, 128);
+
+        let input: Vec<bls12_381::Fr> = (0..5).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_vars: Vec<_> = input.iter().enumerate().map(|(i, el)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap()
+        }).collect();
+
+        let output = anemoi_hash(cs.namespace(|| "hash"), &params, &input_vars).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(output.get_value().unwrap(), ::anemoi::anemoi_hash(&params, &input));
+    }
+}
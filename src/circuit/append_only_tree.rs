@@ -0,0 +1,180 @@
+//! Batch insertion into `append_only_tree`.
+//!
+//! The batch fills the empty subtree at `subtree_index`, so the
+//! insertion is one authentication path checked twice: from the empty
+//! subtree to the old root and from the subtree of the new leaves to
+//! the new root. Costs `2^k - 1 + 2 (depth - k)` compressions for a
+//! batch of `2^k` leaves instead of `2^k` single updates.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::poseidon2::{
+    poseidon2_compress,
+    poseidon2_compress_nums
+};
+
+use super::indexed_merkle_tree::compute_root;
+
+use append_only_tree::empty_roots;
+
+use poseidon2::Poseidon2Params;
+
+/// Same as `compute_root` for a constant leaf, whose first hash only
+/// needs the position of the sibling to be witnessed.
+fn compute_root_from_constant<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    leaf: E::Fr,
+    index_bits: &[Boolean],
+    path: &[AllocatedNum<E>]
+) -> Result<Num<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let leaf = Num::constant(CS::one(), leaf);
+    if path.is_empty() {
+        return Ok(leaf);
+    }
+
+    // left = leaf + bit * (sibling - leaf), right = leaf + sibling - left
+    let left = AllocatedNum::alloc(cs.namespace(|| "left"), || {
+        if *index_bits[0].get_value().get()? {
+            Ok(*path[0].get_value().get()?)
+        } else {
+            Ok(*leaf.get_value().get()?)
+        }
+    })?;
+
+    cs.enforce(
+        || "conditional reversal",
+        |lc| lc + path[0].get_variable() - &leaf.lc(E::Fr::one()),
+        |_| index_bits[0].lc(CS::one(), E::Fr::one()),
+        |lc| lc + left.get_variable() - &leaf.lc(E::Fr::one())
+    );
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let right = leaf.clone()
+        .add_number_with_coeff(&path[0], E::Fr::one())
+        .add_number_with_coeff(&left, minus_one);
+
+    let node = poseidon2_compress_nums(cs.namespace(|| "node hash"), params, &Num::from(left), &right)?;
+    let root = compute_root(cs.namespace(|| "path"), params, &node, &index_bits[1..], &path[1..])?;
+
+    Ok(Num::from(root))
+}
+
+/// Enforces that appending `leaves` to the tree with `old_root` at
+/// the subtree with little-endian bits `subtree_index_bits` results in
+/// the returned root. `path` is the one of `BatchInsertionWitness`.
+/// The caller binds the index to the number of leaves before the
+/// insertion, e.g. by exposing both.
+pub fn insert_batch<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    old_root: &AllocatedNum<E>,
+    leaves: &[AllocatedNum<E>],
+    subtree_index_bits: &[Boolean],
+    path: &[AllocatedNum<E>]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(leaves.len().is_power_of_two(), "batch size must be a power of two");
+    let height = leaves.len().trailing_zeros() as usize;
+
+    let empty = empty_roots(params, height)[height];
+    let computed_old_root = compute_root_from_constant(cs.namespace(|| "old root"), params, empty, subtree_index_bits, path)?;
+    cs.enforce(
+        || "enforce old root",
+        |_| computed_old_root.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + old_root.get_variable()
+    );
+
+    let mut layer = leaves.to_vec();
+    let mut level = 0;
+    while layer.len() > 1 {
+        let mut next = vec![];
+        for (i, pair) in layer.chunks(2).enumerate() {
+            next.push(poseidon2_compress(
+                cs.namespace(|| format!("subtree level {} node {}", level, i)),
+                params,
+                &pair[0],
+                &pair[1]
+            )?);
+        }
+
+        layer = next;
+        level += 1;
+    }
+
+    compute_root(cs.namespace(|| "new root"), params, &layer[0], subtree_index_bits, path)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::test::*;
+    use ::poseidon2::generate_params;
+    use ::append_only_tree::AppendOnlyTree;
+    use super::*;
+
+    #[test]
+    fn test_insert_batch() {
+        let params = generate_params::<Fr>(3);
+        let depth = 5;
+        let mut tree = AppendOnlyTree::new(&params, depth);
+        tree.append_batch(&(1..9).map(fr).collect::<Vec<_>>());
+
+        let old_root = tree.root();
+        let batch: Vec<Fr> = (10..18).map(fr).collect();
+        let witness = tree.append_batch(&batch);
+        let new_root = tree.root();
+        assert_eq!(witness.subtree_index, 1);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let old = AllocatedNum::alloc(cs.namespace(|| "old root"), || Ok(old_root)).unwrap();
+        let leaves: Vec<_> = batch.iter().enumerate().map(|(i, leaf)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("leaf {}", i)), || Ok(*leaf)).unwrap()
+        }).collect();
+        let index_bits: Vec<_> = (0..(depth - 3)).map(|i| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("index bit {}", i)), Some((witness.subtree_index >> i) & 1 == 1)).unwrap())
+        }).collect();
+        let path: Vec<_> = witness.path.iter().enumerate().map(|(i, el)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("path {}", i)), || Ok(*el)).unwrap()
+        }).collect();
+
+        let root = insert_batch(cs.namespace(|| "insert"), &params, &old, &leaves, &index_bits, &path).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(root.get_value().unwrap(), new_root);
+
+        // the leaves can't overwrite the filled subtree
+        cs.set("index bit 0/boolean", Fr::zero());
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -9,9 +9,7 @@ use bellman::{
     ConstraintSystem
 };
 
-use super::{
-    Assignment
-};
+use super::Assignment;
 
 use super::num::{
     AllocatedNum,
@@ -31,13 +29,33 @@ use super::boolean::{AllocatedBit, Boolean};
 /// and permuted elements can be anything. Caller be responsible for validity
 /// if elements are unique or not
 pub fn prove_shuffle<E, CS>(
-    mut cs: CS,
+    cs: CS,
     original: &[AllocatedNum<E>],
     permuted: &[AllocatedNum<E>],
     permuted_order: &IntegerPermutation,
 ) -> Result<(), SynthesisError>
     where CS: ConstraintSystem<E>,
           E: Engine
+{
+    let original: Vec<Vec<Num<E>>> = original.iter().map(|e| vec![Num::from(e.clone())]).collect();
+    let permuted: Vec<Vec<AllocatedNum<E>>> = permuted.iter().map(|e| vec![e.clone()]).collect();
+
+    prove_shuffle_tuples(cs, &original, &permuted, permuted_order)
+}
+
+/// Same as `prove_shuffle` for tuples of elements, e.g. memory
+/// records. All the elements of a tuple are routed by the same
+/// switches, so the tuples are permuted as a whole. The original
+/// elements are linear combinations, so that constant components
+/// of the tuples don't need to be allocated.
+pub fn prove_shuffle_tuples<E, CS>(
+    mut cs: CS,
+    original: &[Vec<Num<E>>],
+    permuted: &[Vec<AllocatedNum<E>>],
+    permuted_order: &IntegerPermutation,
+) -> Result<(), SynthesisError>
+    where CS: ConstraintSystem<E>,
+          E: Engine
 {
     assert_eq!(original.len(), permuted.len());
     assert_eq!(original.len(), permuted_order.size());
@@ -53,30 +71,18 @@ pub fn prove_shuffle<E, CS>(
 
     let num_columns = AsWaksmanTopology::num_colunms(topology.size);
 
-    let mut permutation: Vec<Option<AllocatedNum<E>>> = original.iter().map(|e| Some(e.clone())).collect();
-
-    // let mut permutation: Vec<Option<AllocatedNum<E>>> = permuted.iter().map(|e| Some(e.clone())).collect();
+    let mut permutation: Vec<Option<Vec<Num<E>>>> = original.iter().map(|e| Some(e.clone())).collect();
 
     for column_idx in 0..num_columns {
         // this is just a bookkeeping variable and is deterministic
-        let mut result_of_this_column: Vec<Option<AllocatedNum<E>>> = vec![None; topology.size];
+        let mut result_of_this_column: Vec<Option<Vec<Num<E>>>> = vec![None; topology.size];
         for packet_idx in 0..topology.size {
             if topology.topology[column_idx][packet_idx].0 == topology.topology[column_idx][packet_idx].1 {
-                // straight switch, there is no need to allocate witness
+                // straight switch, the variables are just carried over
                 let routed_into_idx = topology.topology[column_idx][packet_idx].0;
-                let previous_level_variable = permutation.get(packet_idx);
-                let previous_level_variable = previous_level_variable.ok_or(SynthesisError::Unsatisfiable)?.as_ref();
-                let previous_level_variable = previous_level_variable.ok_or(SynthesisError::Unsatisfiable)?;
-                let new_variable_for_this_level = AllocatedNum::alloc(
-                    cs.namespace(|| format!("Straight line variable at column {} for packet {}", column_idx, packet_idx)),
-                    || {
-                        let value = *previous_level_variable.get_value().get()?;
-
-                        Ok(value)
-                    }
-                )?;
+                let previous_level_variable = permutation.get(packet_idx).ok_or(SynthesisError::Unsatisfiable)?.as_ref().ok_or(SynthesisError::Unsatisfiable)?;
 
-                result_of_this_column[routed_into_idx] = Some(new_variable_for_this_level);
+                result_of_this_column[routed_into_idx] = Some(previous_level_variable.clone());
             } else {
                 // validity check
                 let a = router.switches[column_idx].get(&packet_idx);
@@ -131,29 +137,20 @@ pub fn prove_shuffle<E, CS>(
                     switch_value
                 )?); 
 
-                let cross_value = AllocatedNum::alloc(
-                cs.namespace(|| format!("Cross variable at column {} for packet {} with pair {}", column_idx, packet_idx, another_idx)),
-                || {
-                    let value = *previous_level_pair.get_value().get()?;
-
-                    Ok(value)
-                })?;
-
-                let straight_value = AllocatedNum::alloc(
-                cs.namespace(|| format!("Straight variable at column {} for packet {} with pair {}", column_idx, packet_idx, another_idx)),
-                || {
-                    let value = *previous_level_variable.get_value().get()?;
-
-                    Ok(value)
-                })?;
-
-                // perform an actual switching 
-                let (next_level_straight, next_level_cross) = AllocatedNum::conditionally_reverse(
-                    cs.namespace(|| format!("Perform a switching at column {} for packets {} and {}", column_idx, packet_idx, another_idx)),
-                    &straight_value,
-                    &cross_value,
-                    &boolean_switch
-                )?;
+                // perform an actual switching of every element of the tuple
+                let mut next_level_straight = vec![];
+                let mut next_level_cross = vec![];
+                for (el_idx, (straight_value, cross_value)) in previous_level_variable.iter().zip(previous_level_pair.iter()).enumerate() {
+                    let (straight, cross) = conditionally_reverse(
+                        cs.namespace(|| format!("Perform a switching at column {} for packets {} and {}, element {}", column_idx, packet_idx, another_idx, el_idx)),
+                        straight_value,
+                        cross_value,
+                        &boolean_switch
+                    )?;
+
+                    next_level_straight.push(Num::from(straight));
+                    next_level_cross.push(Num::from(cross));
+                }
 
                 result_of_this_column[routed_into_straght] = Some(next_level_straight);
                 result_of_this_column[routed_into_cross] = Some(next_level_cross);
@@ -164,22 +161,73 @@ pub fn prove_shuffle<E, CS>(
     }
 
     // enforce an actual permutation
-    for (i, variable) in permutation.into_iter().enumerate() {
-        let variable = variable.ok_or(SynthesisError::Unsatisfiable)?;
-        let permuted = &permuted[i];
-
-        cs.enforce(
-            || format!("Enforce variable {} in permutation", i),
-            |lc| lc + variable.get_variable(),
-            |lc| lc + CS::one(),
-            |lc| lc + permuted.get_variable()
-        );
+    for (i, tuple) in permutation.into_iter().enumerate() {
+        let tuple = tuple.ok_or(SynthesisError::Unsatisfiable)?;
+        assert_eq!(tuple.len(), permuted[i].len());
+
+        for (j, (variable, permuted)) in tuple.iter().zip(permuted[i].iter()).enumerate() {
+            cs.enforce(
+                || format!("Enforce variable {} element {} in permutation", i, j),
+                |_| variable.lc(E::Fr::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + permuted.get_variable()
+            );
+        }
     }
 
 
     Ok(())
 }
 
+/// `AllocatedNum::conditionally_reverse` for linear combinations.
+fn conditionally_reverse<E, CS>(
+    mut cs: CS,
+    a: &Num<E>,
+    b: &Num<E>,
+    condition: &Boolean
+) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+    where CS: ConstraintSystem<E>,
+          E: Engine
+{
+    let c = AllocatedNum::alloc(
+        cs.namespace(|| "conditional reversal result 1"),
+        || {
+            if *condition.get_value().get()? {
+                Ok(*b.get_value().get()?)
+            } else {
+                Ok(*a.get_value().get()?)
+            }
+        }
+    )?;
+
+    cs.enforce(
+        || "first conditional reversal",
+        |_| a.lc(E::Fr::one()) - &b.lc(E::Fr::one()),
+        |_| condition.lc(CS::one(), E::Fr::one()),
+        |lc| lc + &a.lc(E::Fr::one()) - c.get_variable()
+    );
+
+    let d = AllocatedNum::alloc(
+        cs.namespace(|| "conditional reversal result 2"),
+        || {
+            if *condition.get_value().get()? {
+                Ok(*a.get_value().get()?)
+            } else {
+                Ok(*b.get_value().get()?)
+            }
+        }
+    )?;
+
+    cs.enforce(
+        || "second conditional reversal",
+        |_| b.lc(E::Fr::one()) - &a.lc(E::Fr::one()),
+        |_| condition.lc(CS::one(), E::Fr::one()),
+        |lc| lc + &b.lc(E::Fr::one()) - d.get_variable()
+    );
+
+    Ok((c, d))
+}
+
 #[cfg(test)]
 mod test {
     use bellman::{ConstraintSystem};
@@ -189,9 +237,10 @@ mod test {
     use ::circuit::test::*;
     use super::super::num::{
         AllocatedNum,
+        Num
     };
 
-    use super::{AsWaksmanRoute, AsWaksmanTopology, IntegerPermutation, prove_shuffle};
+    use super::{AsWaksmanRoute, AsWaksmanTopology, IntegerPermutation, prove_shuffle, prove_shuffle_tuples};
 
     #[test]
     fn test_as_waksman_gadget_positive() {
@@ -286,4 +335,99 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_as_waksman_gadget_bound_to_original() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        for size in 3..32 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let mut permutation = IntegerPermutation::new(size);
+            permutation.make_permutation(rng);
+
+            let original_vector = (0..size).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+            let mut permuted_vector = original_vector.clone();
+            for i in 0..size {
+                permuted_vector[permutation.elements[i]] = original_vector[i];
+            }
+
+            let mut original = vec![];
+            let mut permuted = vec![];
+
+            for(i, (o, p)) in original_vector.into_iter().zip(permuted_vector.into_iter()).enumerate() {
+                let o = AllocatedNum::alloc(cs.namespace(|| format!("original variable {}", i)), 
+                    || Ok(o)
+                ).unwrap();
+                let p = AllocatedNum::alloc(cs.namespace(|| format!("permuted variable {}", i)), 
+                    || Ok(p)
+                ).unwrap();
+
+                original.push(o);
+                permuted.push(p);
+            }
+
+            prove_shuffle(cs.namespace(|| "shuffle proof"), 
+                &original, 
+                &permuted, 
+                &permutation
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            // every original element must reach the permuted vector
+            for i in 0..size {
+                let path = format!("original variable {}/num", i);
+                let value = cs.get(&path);
+                cs.set(&path, Fr::rand(rng));
+                assert!(!cs.is_satisfied());
+                cs.set(&path, value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_as_waksman_gadget_tuples() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        for size in 3..32 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let mut permutation = IntegerPermutation::new(size);
+            permutation.make_permutation(rng);
+
+            let original_vector = (0..size).map(|_| (Fr::rand(rng), Fr::rand(rng))).collect::<Vec<_>>();
+            let mut permuted_vector = original_vector.clone();
+            for i in 0..size {
+                permuted_vector[permutation.elements[i]] = original_vector[i];
+            }
+
+            let mut original = vec![];
+            let mut permuted = vec![];
+
+            for(i, (o, p)) in original_vector.into_iter().zip(permuted_vector.into_iter()).enumerate() {
+                let o = vec![
+                    Num::from(AllocatedNum::alloc(cs.namespace(|| format!("original {} 0", i)), || Ok(o.0)).unwrap()),
+                    Num::from(AllocatedNum::alloc(cs.namespace(|| format!("original {} 1", i)), || Ok(o.1)).unwrap())
+                ];
+                let p = vec![
+                    AllocatedNum::alloc(cs.namespace(|| format!("permuted {} 0", i)), || Ok(p.0)).unwrap(),
+                    AllocatedNum::alloc(cs.namespace(|| format!("permuted {} 1", i)), || Ok(p.1)).unwrap()
+                ];
+
+                original.push(o);
+                permuted.push(p);
+            }
+
+            prove_shuffle_tuples(cs.namespace(|| "shuffle proof"), 
+                &original, 
+                &permuted, 
+                &permutation
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.find_unconstrained(), "");
+
+            // the network is bound to the original elements
+            let tampered = Fr::rand(rng);
+            cs.set("original 0 1/num", tampered);
+            assert!(!cs.is_satisfied());
+        }
+    }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use bellman::pairing::{
     Engine,
 };
 
-use bellman::pairing::ff::{Field};
+use bellman::pairing::ff::{Field, PrimeField};
 
 use bellman::{
     SynthesisError,
@@ -15,6 +15,7 @@ use super::{
 
 use super::num::{
     AllocatedNum,
+    BitsCache,
     Num
 };
 
@@ -157,6 +158,31 @@ impl<E: JubjubEngine> EdwardsPoint<E> {
         Ok(tmp)
     }
 
+    /// This converts the point into the encoding of
+    /// `edwards::Point::write`: the little-endian bytes of y
+    /// with the sign of x in the most significant bit. The bits
+    /// of every byte are least significant first.
+    pub fn to_bytes_le<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(E::Fr::NUM_BITS <= 255);
+
+        let x = self.x.into_bits_le_strict(
+            cs.namespace(|| "unpack x")
+        )?;
+
+        let mut bytes = self.y.to_bytes_le(
+            cs.namespace(|| "unpack y")
+        )?;
+
+        *bytes.last_mut().unwrap() = x[0].clone();
+
+        Ok(bytes)
+    }
+
     /// This 'witnesses' a point inside the constraint system.
     /// It guarantees the point is on the curve.
     pub fn witness<Order, CS>(
@@ -302,6 +328,23 @@ impl<E: JubjubEngine> EdwardsPoint<E> {
         Ok(result.get()?.clone())
     }
 
+    /// Same as `mul` for the bits of an allocated number, taken from
+    /// the `cache`, so a number multiplying several points is only
+    /// decomposed once.
+    pub fn mul_num<CS>(
+        &self,
+        mut cs: CS,
+        by: &AllocatedNum<E>,
+        cache: &mut BitsCache,
+        params: &E::Params
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let bits = cache.into_bits_le_strict(cs.namespace(|| "scalar bits"), by)?;
+
+        self.mul(cs.namespace(|| "multiplication"), &bits, params)
+    }
+
     pub fn interpret<CS>(
         mut cs: CS,
         x: &AllocatedNum<E>,
@@ -755,6 +798,7 @@ mod test {
         MontgomeryPoint,
         EdwardsPoint,
         AllocatedNum,
+        BitsCache,
         fixed_base_multiplication
     };
     use super::super::boolean::{
@@ -953,6 +997,58 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_edwards_multiplication_by_cached_num() {
+        let params = &JubjubBn256::new();
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let s = Fs::rand(rng);
+        let mut s_repr = <Fr as PrimeField>::Repr::default();
+        s_repr.as_mut().copy_from_slice(s.into_repr().as_ref());
+        let s_value = Fr::from_repr(s_repr).unwrap();
+
+        let decomposition_cost = {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let s_num = AllocatedNum::alloc(cs.namespace(|| "scalar"), || Ok(s_value)).unwrap();
+            s_num.into_bits_le_strict(cs.namespace(|| "decompose")).unwrap();
+
+            cs.num_constraints()
+        };
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut cache = BitsCache::new();
+        let s_num = AllocatedNum::alloc(cs.namespace(|| "scalar"), || Ok(s_value)).unwrap();
+
+        let mut costs = vec![];
+        for i in 0..2 {
+            let p = edwards::Point::<Bn256, _>::rand(rng, params);
+            let (x0, y0) = p.into_xy();
+            let (x1, y1) = p.mul(s, params).into_xy();
+
+            let p = EdwardsPoint {
+                x: AllocatedNum::alloc(cs.namespace(|| format!("x{}", i)), || Ok(x0)).unwrap(),
+                y: AllocatedNum::alloc(cs.namespace(|| format!("y{}", i)), || Ok(y0)).unwrap()
+            };
+
+            let before = cs.num_constraints();
+            let q = p.mul_num(
+                cs.namespace(|| format!("scalar mul {}", i)),
+                &s_num,
+                &mut cache,
+                params
+            ).unwrap();
+            costs.push(cs.num_constraints() - before);
+
+            assert_eq!(q.x.get_value().unwrap(), x1);
+            assert_eq!(q.y.get_value().unwrap(), y1);
+        }
+
+        assert!(cs.is_satisfied());
+
+        // the second multiplication reuses the bits of the scalar
+        assert_eq!(costs[1], costs[0] - decomposition_cost);
+    }
+
     #[test]
     fn test_conditionally_select() {
         let params = &JubjubBn256::new();
@@ -1087,6 +1183,30 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_to_bytes_le() {
+        let params = &JubjubBn256::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let p = edwards::Point::<Bn256, _>::rand(rng, params);
+            let mut expected = vec![];
+            p.write(&mut expected).unwrap();
+
+            let q = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p), params).unwrap();
+            let bits = q.to_bytes_le(cs.namespace(|| "to bytes")).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let bytes: Vec<u8> = bits.chunks(8).map(|byte| {
+                byte.iter().rev().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+            }).collect();
+            assert_eq!(bytes, expected);
+        }
+    }
+
     #[test]
     fn test_edwards_doubling() {
         let params = &JubjubBn256::new();
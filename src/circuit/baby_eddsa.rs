@@ -2,7 +2,7 @@ use bellman::pairing::{
     Engine,
 };
 
-use bellman::pairing::ff::{Field};
+use bellman::pairing::ff::{Field, PrimeField};
 
 use bellman::{
     SynthesisError,
@@ -15,6 +15,7 @@ use super::{
 
 use super::num::{
     AllocatedNum,
+    Num
 };
 
 use ::jubjub::{
@@ -40,6 +41,10 @@ use super::ecc::EdwardsPoint;
 
 use super::blake2s::{blake2s};
 
+use super::rescue::{rescue_hash, rescue_hash_to_bits};
+
+use ::rescue::RescueParams;
+
 #[derive(Clone)]
 pub struct EddsaSignature<E: JubjubEngine> {
     pub r: EdwardsPoint<E>,
@@ -327,8 +332,146 @@ impl <E: JubjubEngine>EddsaSignature<E> {
 
         return Ok(());
     }
+
+    /// Verifies a signature of `eddsa::PrivateKey::sign_raw_scalar`,
+    /// where the message is hashed as field elements with Rescue.
+    pub fn verify_raw_scalar<CS>(
+        &self,
+        cs: CS,
+        params: &E::Params,
+        rescue_params: &RescueParams<E::Fr>,
+        message: &[AllocatedNum<E>],
+        generator: EdwardsPoint<E>
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let message: Vec<Num<E>> = message.iter().map(|el| Num::from(el.clone())).collect();
+
+        self.verify_raw_scalar_if(cs, &Boolean::constant(true), params, rescue_params, &message, generator)
+    }
+
+    /// Same as `verify_raw_scalar` if `condition` is set, and no check
+    /// otherwise. `r` still has to be a point of large order. The
+    /// message is taken as linear combinations, so constant parts of it
+    /// don't need to be allocated.
+    pub fn verify_raw_scalar_if<CS>(
+        &self,
+        mut cs: CS,
+        condition: &Boolean,
+        params: &E::Params,
+        rescue_params: &RescueParams<E::Fr>,
+        message: &[Num<E>],
+        generator: EdwardsPoint<E>
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let scalar_bits = self.s.into_bits_le_strict(
+            cs.namespace(|| "Get S bits")
+        )?;
+
+        let sb = generator.mul(
+            cs.namespace(|| "S*B computation"),
+            &scalar_bits,
+            params
+        )?;
+
+        // only order of R is checked. Public key and generator can be guaranteed to be in proper group!
+        // by some other means for out particular case
+        self.r.assert_not_small_order(
+            cs.namespace(|| "R is in right order"),
+            &params
+        )?;
+
+        // h = Hash(R_X || PK_X || message)
+        let mut input = vec![Num::from(self.r.get_x().clone()), Num::from(self.pk.get_x().clone())];
+        input.extend(message.iter().cloned());
+
+        let h_bits = rescue_hash_to_bits(
+            cs.namespace(|| "Calculate EdDSA hash"),
+            rescue_params,
+            &input,
+            E::Fs::CAPACITY as usize
+        )?;
+
+        let pk_mul_hash = self.pk.mul(
+            cs.namespace(|| "Calculate h*PK"),
+            &h_bits,
+            params
+        )?;
+
+        let rhs = pk_mul_hash.add(
+            cs.namespace(|| "Make signature RHS"),
+            &self.r,
+            params
+        )?;
+
+        let rhs_x = rhs.get_x();
+        let rhs_y = rhs.get_y();
+
+        let sb_x = sb.get_x();
+        let sb_y = sb.get_y();
+
+        let one = CS::one();
+        cs.enforce(
+            || "check x coordinate of signature",
+            |lc| lc + rhs_x.get_variable() - sb_x.get_variable(),
+            |_| condition.lc(one, E::Fr::one()),
+            |lc| lc
+        );
+
+        cs.enforce(
+            || "check y coordinate of signature",
+            |lc| lc + rhs_y.get_variable() - sb_y.get_variable(),
+            |_| condition.lc(one, E::Fr::one()),
+            |lc| lc
+        );
+
+        return Ok(());
+    }
 } 
 
+/// The key `eddsa::PublicKey::aggregate` makes from `keys`.
+pub fn aggregate_public_keys<E, CS>(
+    mut cs: CS,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    keys: &[EdwardsPoint<E>]
+) -> Result<EdwardsPoint<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    assert!(!keys.is_empty());
+
+    let mut all = vec![];
+    for key in keys.iter() {
+        all.push(key.get_x().clone());
+        all.push(key.get_y().clone());
+    }
+    let l = rescue_hash(cs.namespace(|| "hash all keys"), rescue_params, &all)?;
+
+    let mut res: Option<EdwardsPoint<E>> = None;
+    for (i, key) in keys.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("key {}", i));
+
+        let input = [Num::from(l[0].clone()), Num::from(key.get_x().clone()), Num::from(key.get_y().clone())];
+        let a_bits = rescue_hash_to_bits(
+            cs.namespace(|| "coefficient"),
+            rescue_params,
+            &input,
+            E::Fs::CAPACITY as usize
+        )?;
+
+        let term = key.mul(cs.namespace(|| "a*PK"), &a_bits, params)?;
+
+        res = Some(match res {
+            Some(acc) => acc.add(cs.namespace(|| "add"), &term, params)?,
+            None => term
+        });
+    }
+
+    Ok(res.unwrap())
+}
+
 
 #[cfg(test)]
 mod test {
@@ -522,6 +665,79 @@ mod test {
         print!("EdDSA variant raw message signature takes constraints: {}\n", cs.num_constraints());
     }
 
+    #[test]
+    fn test_valid_raw_scalar_signatures() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let rescue_params = ::rescue::generate_params::<Fr>(3, 128);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let sk = PrivateKey::<Bn256>(rng.gen());
+        let vk = PublicKey::from_private(&sk, p_g, params);
+
+        let msg: Vec<Fr> = (0..4).map(|_| rng.gen()).collect();
+
+        let sig = sk.sign_raw_scalar(&msg, &mut rng, p_g, params, &rescue_params);
+        assert!(vk.verify_raw_scalar(&msg, &sig, p_g, params, &rescue_params));
+
+        let message: Vec<AllocatedNum<Bn256>> = msg.iter().enumerate().map(|(i, m)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("message {}", i)), || Ok(*m)).unwrap()
+        }).collect();
+
+        let mut sigs_bytes = [0u8; 32];
+        sig.s.into_repr().write_le(& mut sigs_bytes[..]).expect("get LE bytes of signature S");
+        let mut sigs_repr = <Fr as PrimeField>::Repr::from(0);
+        sigs_repr.read_le(&sigs_bytes[..]).expect("interpret S as field element representation");
+
+        let s = AllocatedNum::alloc(cs.namespace(|| "allocate s"), || {
+                Ok(Fr::from_repr(sigs_repr).unwrap())
+            }
+        ).unwrap();
+
+        let public_generator = params.generator(FixedGenerators::SpendingKeyGenerator).clone();
+
+        let generator = EdwardsPoint::witness(cs.namespace(|| "allocate public generator"), Some(public_generator), params).unwrap();
+
+        let r = EdwardsPoint::witness(cs.namespace(|| "allocate r"), Some(sig.r), params).unwrap();
+
+        let pk = EdwardsPoint::witness(cs.namespace(|| "allocate pk"), Some(vk.0), params).unwrap();
+
+        let signature = EddsaSignature{r, s, pk};
+        signature.verify_raw_scalar(cs.namespace(|| "verify signature"), params, &rescue_params, &message, generator).expect("succesfully generated verifying gadget");
+
+        assert!(cs.is_satisfied());
+        print!("EdDSA over field elements takes constraints: {}\n", cs.num_constraints());
+
+        cs.set("message 2/num", rng.gen());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_aggregate_public_keys() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let rescue_params = ::rescue::generate_params::<Fr>(3, 128);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let vks: Vec<PublicKey<Bn256>> = (0..3).map(|_| {
+            PublicKey::from_private(&PrivateKey::<Bn256>(rng.gen()), p_g, params)
+        }).collect();
+        let expected = PublicKey::aggregate(&vks, params, &rescue_params);
+
+        let keys: Vec<EdwardsPoint<Bn256>> = vks.iter().enumerate().map(|(i, vk)| {
+            EdwardsPoint::witness(cs.namespace(|| format!("allocate pk {}", i)), Some(vk.0.clone()), params).unwrap()
+        }).collect();
+
+        let aggregated = aggregate_public_keys(cs.namespace(|| "aggregate"), params, &rescue_params, &keys).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        let (x, y) = expected.0.into_xy();
+        assert_eq!(aggregated.get_x().get_value().unwrap(), x);
+        assert_eq!(aggregated.get_y().get_value().unwrap(), y);
+    }
+
 }
 
 
@@ -0,0 +1,531 @@
+//! Verification of a chain of Bitcoin block headers.
+//!
+//! A header is given as its 80 bytes in serialization order and a hash
+//! as the 32 bytes output by `sha256d`, every byte most significant bit
+//! first. As integers, both the hashes and the fields of the header
+//! are little-endian.
+//!
+//! The target is decoded from the compact `bits` field of the header as
+//! `mantissa * 256^(exponent - 3)`. Only the exponents from 19 to 32
+//! are supported, which covers every target of mainnet, testnet and
+//! regtest. The work of a header `2^256 / (target + 1)` then fits into
+//! 128 bits and is computed exactly. Retargeting is not checked, which
+//! is why the verifier has to compare the accumulated work.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::Assignment;
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::num::AllocatedNum;
+
+use super::comparison::alloc_packed_bits;
+
+use super::sha256::sha256d;
+
+use util::fr_from_u128;
+
+pub const HEADER_BITS: usize = 80 * 8;
+
+const MIN_EXPONENT: usize = 19;
+const MAX_EXPONENT: usize = 32;
+const MANTISSA_BITS: usize = 23;
+const WORK_BITS: usize = 128;
+
+/// A verified chain of headers.
+pub struct HeaderChain<E: Engine> {
+    /// Hash of the last header.
+    pub tip: Vec<Boolean>,
+    /// Sum of the work of the headers.
+    pub work: AllocatedNum<E>
+}
+
+fn pow2<F: PrimeField>(n: usize) -> F {
+    let mut tmp = F::one();
+    for _ in 0..n {
+        tmp.double();
+    }
+
+    tmp
+}
+
+fn le_bytes_value<F: PrimeField>(bytes: &[u8]) -> F {
+    let mut acc = F::zero();
+    for &byte in bytes.iter().rev() {
+        for _ in 0..8 {
+            acc.double();
+        }
+        acc.add_assign(&fr_from_u128(byte as u128));
+    }
+
+    acc
+}
+
+fn byte_value(bits: &[Boolean], i: usize) -> Option<u8> {
+    let mut byte = 0u8;
+    for bit in bits[(8 * i)..(8 * i + 8)].iter() {
+        byte = (byte << 1) | (bit.get_value()? as u8);
+    }
+
+    Some(byte)
+}
+
+/// The `i`-th byte scaled by `coeff`.
+fn byte_lc<E, CS>(bits: &[Boolean], i: usize, coeff: E::Fr) -> LinearCombination<E>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut coeff = coeff;
+    let mut lc = LinearCombination::zero();
+    for bit in bits[(8 * i)..(8 * i + 8)].iter().rev() {
+        lc = lc + &bit.lc(CS::one(), coeff);
+        coeff.double();
+    }
+
+    lc
+}
+
+/// Little-endian packing of the bytes `start..end`.
+fn bytes_lc<E, CS>(bits: &[Boolean], start: usize, end: usize) -> LinearCombination<E>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    (start..end).fold(LinearCombination::zero(), |lc, i| {
+        lc + &byte_lc::<E, CS>(bits, i, pow2(8 * (i - start)))
+    })
+}
+
+/// Allocates `term` if `selector` is one and zero otherwise, for a
+/// `selector` that is a boolean.
+fn select<E, CS>(
+    mut cs: CS,
+    selector: LinearCombination<E>,
+    selected: Option<bool>,
+    term: LinearCombination<E>,
+    value: Option<E::Fr>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let product = AllocatedNum::alloc(cs.namespace(|| "product"), || {
+        if *selected.get()? {
+            Ok(*value.get()?)
+        } else {
+            Ok(E::Fr::zero())
+        }
+    })?;
+
+    cs.enforce(
+        || "select",
+        |_| selector,
+        |_| term,
+        |lc| lc + product.get_variable()
+    );
+
+    Ok(product)
+}
+
+/// Sum of the selectors of the exponents satisfying `pred`, a boolean
+/// as exactly one selector is set.
+fn any_exponent<E, P>(selectors: &[AllocatedBit], pred: P) -> (LinearCombination<E>, Option<bool>)
+    where E: Engine,
+          P: Fn(usize) -> bool
+{
+    selectors.iter().enumerate().filter(|&(i, _)| pred(i + MIN_EXPONENT)).fold(
+        (LinearCombination::zero(), Some(false)),
+        |(lc, value), (_, bit)| (lc + bit.get_variable(), value.and_then(|v| bit.get_value().map(|b| v | b)))
+    )
+}
+
+/// Enforces that the `hash` of `header` meets the target given by the
+/// `bits` field of the header and returns the work of the header.
+pub fn check_proof_of_work<E, CS>(
+    mut cs: CS,
+    header: &[Boolean],
+    hash: &[Boolean]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(header.len(), HEADER_BITS);
+    assert_eq!(hash.len(), 256);
+
+    // bits is at bytes 72..76: the mantissa in the first three, the
+    // top bit of the third its sign, and the exponent in the last one
+    Boolean::enforce_equal(cs.namespace(|| "positive target"), &header[74 * 8], &Boolean::constant(false))?;
+
+    let mantissa = bytes_lc::<E, CS>(header, 72, 75);
+    let mantissa_value: Option<u128> = (72..75).rev().map(|i| byte_value(header, i)).fold(Some(0u128), |acc, byte| {
+        Some((acc? << 8) | byte? as u128)
+    });
+    let exponent_value = byte_value(header, 75).map(|e| e as usize);
+
+    let mut selectors = vec![];
+    let mut sum = LinearCombination::<E>::zero();
+    let mut weighted = LinearCombination::<E>::zero();
+    for e in MIN_EXPONENT..(MAX_EXPONENT + 1) {
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("exponent is {}", e)),
+            exponent_value.map(|value| value == e)
+        )?;
+
+        sum = sum + bit.get_variable();
+        weighted = weighted + (fr_from_u128(e as u128), bit.get_variable());
+        selectors.push(bit);
+    }
+
+    cs.enforce(
+        || "single exponent",
+        |lc| lc + &sum,
+        |lc| lc + CS::one(),
+        |lc| lc + CS::one()
+    );
+
+    cs.enforce(
+        || "exponent",
+        |lc| lc + &weighted,
+        |lc| lc + CS::one(),
+        |_| byte_lc::<E, CS>(header, 75, E::Fr::one())
+    );
+
+    let hash_bytes: Option<Vec<u8>> = (0..32).map(|i| byte_value(hash, i)).collect();
+
+    // the target is the mantissa shifted by exponent - 3 bytes, the
+    // hash is split into the bytes below, at and above the mantissa
+    let shift_value = exponent_value.map(|e| e.max(MIN_EXPONENT).min(MAX_EXPONENT) - 3);
+
+    let mut high = LinearCombination::<E>::zero();
+    for e in MIN_EXPONENT..(MAX_EXPONENT + 1) {
+        let (selected_lc, selected) = any_exponent(&selectors, |x| x == e);
+        let product = select(
+            cs.namespace(|| format!("hash at exponent {}", e)),
+            selected_lc,
+            selected,
+            bytes_lc::<E, CS>(hash, e - 3, e),
+            hash_bytes.as_ref().map(|h| le_bytes_value(&h[(e - 3)..e]))
+        )?;
+
+        high = high + product.get_variable();
+    }
+
+    for j in MIN_EXPONENT..32 {
+        let (above, _) = any_exponent(&selectors, |e| e <= j);
+
+        cs.enforce(
+            || format!("hash byte {} is zero", j),
+            |_| above,
+            |_| byte_lc::<E, CS>(hash, j, E::Fr::one()),
+            |lc| lc
+        );
+    }
+
+    let mut low = LinearCombination::<E>::zero();
+    for j in 0..(MAX_EXPONENT - 3) {
+        if j + 3 < MIN_EXPONENT {
+            low = low + &byte_lc::<E, CS>(hash, j, pow2(8 * j));
+        } else {
+            let (below_lc, below) = any_exponent(&selectors, |e| j + 3 < e);
+            let product = select(
+                cs.namespace(|| format!("hash byte {} below the mantissa", j)),
+                below_lc,
+                below,
+                byte_lc::<E, CS>(hash, j, E::Fr::one()),
+                hash_bytes.as_ref().map(|h| fr_from_u128(h[j] as u128))
+            )?;
+
+            low = low + (pow2(8 * j), product.get_variable());
+        }
+    }
+
+    // the hash is at most the target iff the bytes above the mantissa
+    // are zero and high + (low != 0) <= mantissa
+    let low_value = match (hash_bytes.as_ref(), shift_value) {
+        (Some(h), Some(shift)) => Some(le_bytes_value::<E::Fr>(&h[..shift])),
+        _ => None
+    };
+    let high_value = match (hash_bytes.as_ref(), shift_value) {
+        (Some(h), Some(shift)) => Some(le_bytes_value::<E::Fr>(&h[shift..(shift + 3)])),
+        _ => None
+    };
+
+    let low_is_nonzero = AllocatedBit::alloc(
+        cs.namespace(|| "low bytes are nonzero"),
+        low_value.map(|v| !v.is_zero())
+    )?;
+    let low_inverse = AllocatedNum::alloc(cs.namespace(|| "low bytes inverse"), || {
+        Ok(low_value.get()?.inverse().unwrap_or(E::Fr::zero()))
+    })?;
+
+    cs.enforce(
+        || "nonzero if invertible",
+        |lc| lc + &low,
+        |lc| lc + low_inverse.get_variable(),
+        |lc| lc + low_is_nonzero.get_variable()
+    );
+
+    cs.enforce(
+        || "zero unless nonzero",
+        |lc| lc + &low,
+        |lc| lc + CS::one() - low_is_nonzero.get_variable(),
+        |lc| lc
+    );
+
+    let mantissa_fr = mantissa_value.map(|m| fr_from_u128::<E::Fr>(m));
+    let slack_value = match (mantissa_fr, high_value, low_is_nonzero.get_value()) {
+        (Some(m), Some(high), Some(nonzero)) => {
+            let mut tmp = m;
+            tmp.sub_assign(&high);
+            if nonzero {
+                tmp.sub_assign(&E::Fr::one());
+            }
+
+            Some(tmp)
+        },
+        _ => None
+    };
+
+    alloc_packed_bits(
+        cs.namespace(|| "hash meets target"),
+        slack_value,
+        mantissa.clone() - &high - low_is_nonzero.get_variable(),
+        MANTISSA_BITS + 1
+    )?;
+
+    // with A = 2^256 / 256^(exponent - 3), work * mantissa + r = A for
+    // some 1 <= r <= mantissa gives the work 2^256 / (target + 1), as
+    // the work is less than 256^(exponent - 3)
+    let division = match (mantissa_value, exponent_value) {
+        (Some(m), Some(e)) => Some(if m > 0 && e >= MIN_EXPONENT && e <= MAX_EXPONENT {
+            let a_minus_one = u128::max_value() >> (8 * (e - MIN_EXPONENT));
+
+            (a_minus_one / m, a_minus_one % m + 1)
+        } else {
+            (0, 0)
+        }),
+        _ => None
+    };
+
+    let work = AllocatedNum::alloc(cs.namespace(|| "work"), || {
+        Ok(fr_from_u128(division.get()?.0))
+    })?;
+    let remainder = AllocatedNum::alloc(cs.namespace(|| "remainder"), || {
+        Ok(fr_from_u128(division.get()?.1))
+    })?;
+
+    alloc_packed_bits(
+        cs.namespace(|| "work range"),
+        work.get_value(),
+        LinearCombination::zero() + work.get_variable(),
+        WORK_BITS
+    )?;
+
+    let power = (MIN_EXPONENT..(MAX_EXPONENT + 1)).zip(selectors.iter()).fold(
+        LinearCombination::<E>::zero(),
+        |lc, (e, bit)| lc + (pow2(256 - 8 * (e - 3)), bit.get_variable())
+    );
+
+    cs.enforce(
+        || "work division",
+        |lc| lc + work.get_variable(),
+        |lc| lc + &mantissa,
+        |lc| lc + &power - remainder.get_variable()
+    );
+
+    alloc_packed_bits(
+        cs.namespace(|| "remainder is positive"),
+        remainder.get_value().map(|mut r| {
+            r.sub_assign(&E::Fr::one());
+
+            r
+        }),
+        LinearCombination::zero() + remainder.get_variable() - CS::one(),
+        MANTISSA_BITS + 1
+    )?;
+
+    alloc_packed_bits(
+        cs.namespace(|| "remainder is at most mantissa"),
+        match (mantissa_fr, remainder.get_value()) {
+            (Some(m), Some(r)) => {
+                let mut tmp = m;
+                tmp.sub_assign(&r);
+
+                Some(tmp)
+            },
+            _ => None
+        },
+        mantissa - remainder.get_variable(),
+        MANTISSA_BITS + 1
+    )?;
+
+    Ok(work)
+}
+
+/// Verifies that every header links to the hash of the previous one,
+/// the first one to `prev_hash`, and meets its target. Returns the hash
+/// of the last header and the total work of the headers.
+pub fn verify_header_chain<E, CS>(
+    mut cs: CS,
+    prev_hash: &[Boolean],
+    headers: &[Vec<Boolean>]
+) -> Result<HeaderChain<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(prev_hash.len(), 256);
+    assert!(!headers.is_empty());
+
+    let mut tip = prev_hash.to_vec();
+    let mut works = vec![];
+    for (i, header) in headers.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("header {}", i));
+        assert_eq!(header.len(), HEADER_BITS);
+
+        // the previous hash is at bytes 4..36
+        for (j, (a, b)) in header[32..288].iter().zip(tip.iter()).enumerate() {
+            Boolean::enforce_equal(cs.namespace(|| format!("prev hash bit {}", j)), a, b)?;
+        }
+
+        let hash = sha256d(cs.namespace(|| "hash"), header)?;
+        works.push(check_proof_of_work(cs.namespace(|| "proof of work"), header, &hash)?);
+
+        tip = hash;
+    }
+
+    let work = AllocatedNum::alloc(cs.namespace(|| "total work"), || {
+        let mut sum = E::Fr::zero();
+        for w in works.iter() {
+            sum.add_assign(w.get_value().get()?);
+        }
+
+        Ok(sum)
+    })?;
+
+    cs.enforce(
+        || "total work",
+        |lc| works.iter().fold(lc, |lc, w| lc + w.get_variable()),
+        |lc| lc + CS::one(),
+        |lc| lc + work.get_variable()
+    );
+
+    Ok(HeaderChain {
+        tip,
+        work
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use sha2::{Sha256, Digest};
+    use ::circuit::test::*;
+    use super::*;
+
+    fn alloc_bytes<CS: ConstraintSystem<Bn256>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().enumerate().flat_map(|(byte_i, &byte)| {
+            (0..8).rev().map(move |bit_i| (byte_i, bit_i, (byte >> bit_i) & 1u8 == 1u8))
+        }).map(|(byte_i, bit_i, bit)| {
+            AllocatedBit::alloc(cs.namespace(|| format!("bit {} {}", byte_i, bit_i)), Some(bit)).unwrap().into()
+        }).collect()
+    }
+
+    fn bits_of(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8)).collect()
+    }
+
+    fn sha256d_native(data: &[u8]) -> Vec<u8> {
+        Sha256::digest(&Sha256::digest(data)).to_vec()
+    }
+
+    /// Regtest header with the given previous hash, whose hash is below
+    /// the target if `valid`.
+    fn mine<R: Rng>(rng: &mut R, prev_hash: &[u8], valid: bool) -> Vec<u8> {
+        let mut header = vec![1, 0, 0, 0];
+        header.extend_from_slice(prev_hash);
+        header.extend((0..36).map(|_| rng.gen::<u8>()));
+        header.extend_from_slice(&[0xff, 0xff, 0x7f, 0x20]);
+        header.extend_from_slice(&[0; 4]);
+
+        loop {
+            let hash = sha256d_native(&header);
+            if (hash[31] < 0x7f) == valid && (hash[31] != 0x7f) {
+                return header;
+            }
+
+            header[76] = header[76].wrapping_add(1);
+        }
+    }
+
+    fn synthesize(prev_hash: &[u8], headers: &[Vec<u8>]) -> (TestConstraintSystem<Bn256>, HeaderChain<Bn256>) {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let prev_hash = alloc_bytes(cs.namespace(|| "prev hash"), prev_hash);
+        let headers: Vec<Vec<Boolean>> = headers.iter().enumerate().map(|(i, h)| {
+            alloc_bytes(cs.namespace(|| format!("header bytes {}", i)), h)
+        }).collect();
+
+        let chain = verify_header_chain(cs.namespace(|| "chain"), &prev_hash, &headers).unwrap();
+
+        (cs, chain)
+    }
+
+    #[test]
+    fn test_genesis_header() {
+        let genesis = hex!("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c");
+        let genesis_hash = hex!("6fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000");
+
+        let (cs, chain) = synthesize(&[0; 32], &[genesis.to_vec()]);
+
+        assert!(cs.is_satisfied());
+        assert_eq!(chain.tip.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), bits_of(&genesis_hash));
+        assert_eq!(chain.work.get_value().unwrap(), Fr::from_str("4295032833").unwrap());
+    }
+
+    #[test]
+    fn test_header_chain() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let start: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+        let mut headers = vec![];
+        let mut prev_hash = start.clone();
+        for _ in 0..3 {
+            let header = mine(rng, &prev_hash, true);
+            prev_hash = sha256d_native(&header);
+            headers.push(header);
+        }
+
+        let (cs, chain) = synthesize(&start, &headers);
+        assert!(cs.is_satisfied());
+        assert_eq!(chain.tip.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), bits_of(&prev_hash));
+        // the work of a regtest header is 2
+        assert_eq!(chain.work.get_value().unwrap(), Fr::from_str("6").unwrap());
+
+        // broken link
+        let mut unlinked = headers.clone();
+        unlinked[2] = mine(rng, &start, true);
+        let (cs, _) = synthesize(&start, &unlinked);
+        assert!(!cs.is_satisfied());
+
+        // hash above the target
+        let mut easy = headers.clone();
+        easy[2] = mine(rng, &sha256d_native(&headers[1]), false);
+        let (cs, _) = synthesize(&start, &easy);
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -55,7 +55,7 @@ const R4: usize = 7;
         ----------+-------------------------------------------------+
 */
 
-const SIGMA: [[usize; 16]; 10] = [
+pub(crate) const SIGMA: [[usize; 16]; 10] = [
     [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
     [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
     [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
@@ -168,7 +168,8 @@ fn blake2s_compression<E: Engine, CS: ConstraintSystem<E>>(
     h: &mut [UInt32],
     m: &[UInt32],
     t: u64,
-    f: bool
+    f: bool,
+    rounds: usize
 ) -> Result<(), SynthesisError>
 {
     assert_eq!(h.len(), 8);
@@ -205,7 +206,7 @@ fn blake2s_compression<E: Engine, CS: ConstraintSystem<E>>(
     {
         let mut cs = MultiEq::new(&mut cs);
 
-        for i in 0..10 {
+        for i in 0..rounds {
             let mut cs = cs.namespace(|| format!("round {}", i));
 
             let s = SIGMA[i % 10];
@@ -260,10 +261,37 @@ fn blake2s_compression<E: Engine, CS: ConstraintSystem<E>>(
 */
 
 pub fn blake2s<E: Engine, CS: ConstraintSystem<E>>(
-    mut cs: CS,
+    cs: CS,
     input: &[Boolean],
     personalization: &[u8]
 ) -> Result<Vec<Boolean>, SynthesisError>
+{
+    blake2s_with_rounds(cs, input, personalization, 10)
+}
+
+/// BLAKE2s with `rounds` rounds of the compression function instead
+/// of 10, matching `::blake2s::insecure_reduced_rounds_blake2s`.
+///
+/// INSECURE: only meant for constraint count experiments and testing,
+/// the output is not BLAKE2s and has no security claims.
+pub fn insecure_reduced_rounds_blake2s<E: Engine, CS: ConstraintSystem<E>>(
+    cs: CS,
+    input: &[Boolean],
+    personalization: &[u8],
+    rounds: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    assert!(rounds >= 1 && rounds <= 10);
+
+    blake2s_with_rounds(cs, input, personalization, rounds)
+}
+
+fn blake2s_with_rounds<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    input: &[Boolean],
+    personalization: &[u8],
+    rounds: usize
+) -> Result<Vec<Boolean>, SynthesisError>
 {
     use byteorder::{ByteOrder, LittleEndian};
 
@@ -306,13 +334,13 @@ pub fn blake2s<E: Engine, CS: ConstraintSystem<E>>(
     for (i, block) in blocks[0..blocks.len() - 1].iter().enumerate() {
         let cs = cs.namespace(|| format!("block {}", i));
 
-        blake2s_compression(cs, &mut h, block, ((i as u64) + 1) * 64, false)?;
+        blake2s_compression(cs, &mut h, block, ((i as u64) + 1) * 64, false, rounds)?;
     }
 
     {
         let cs = cs.namespace(|| "final block");
 
-        blake2s_compression(cs, &mut h, &blocks[blocks.len() - 1], (input.len() / 8) as u64, true)?;
+        blake2s_compression(cs, &mut h, &blocks[blocks.len() - 1], (input.len() / 8) as u64, true, rounds)?;
     }
 
     Ok(h.iter().flat_map(|b| b.into_bits()).collect())
@@ -324,7 +352,7 @@ mod test {
     use bellman::pairing::bls12_381::{Bls12};
     use ::circuit::boolean::{Boolean, AllocatedBit};
     use ::circuit::test::TestConstraintSystem;
-    use super::blake2s;
+    use super::{blake2s, insecure_reduced_rounds_blake2s};
     use bellman::{ConstraintSystem};
     use blake2_rfc::blake2s::Blake2s;
 
@@ -435,4 +463,34 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_reduced_rounds() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let data: Vec<u8> = (0..100).map(|_| rng.gen()).collect();
+
+        let mut num_constraints = vec![];
+        for rounds in 1..11 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input_bits: Vec<Boolean> = data.iter().enumerate().flat_map(|(byte_i, &byte)| {
+                (0..8).map(move |bit_i| (byte_i, bit_i, (byte >> bit_i) & 1 == 1))
+            }).map(|(byte_i, bit_i, b)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)), Some(b)).unwrap().into()
+            }).collect();
+
+            let r = insecure_reduced_rounds_blake2s(cs.namespace(|| "hash"), &input_bits, b"12345678", rounds).unwrap();
+            assert!(cs.is_satisfied());
+
+            let bits: Vec<bool> = ::blake2s::insecure_reduced_rounds_blake2s(&data, b"12345678", rounds).iter()
+                .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+                .collect();
+            assert_eq!(r.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), bits);
+
+            num_constraints.push(cs.num_constraints());
+        }
+
+        for pair in num_constraints.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+    }
 }
@@ -0,0 +1,178 @@
+//! Membership checks of `bloom`.
+//!
+//! The filter is allocated bit by bit and packed with
+//! `multipack::pack_into_variables`, so the caller can commit to or
+//! expose the packing. Every position of an item takes a strict
+//! decomposition of its hash and a multiplexer over the filter, i.e.
+//! about `num_bits` constraints.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::multipack::pack_into_variables;
+
+use super::poseidon2::poseidon2_compress_nums;
+
+use poseidon2::Poseidon2Params;
+
+use util::fr_from_u64;
+
+/// Allocates the bits of a filter of `num_bits` bits and returns them
+/// with their packing.
+pub fn alloc_filter<E, CS>(
+    mut cs: CS,
+    bits: Option<&[bool]>,
+    num_bits: usize
+) -> Result<(Vec<Boolean>, Vec<AllocatedNum<E>>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(num_bits.is_power_of_two());
+    if let Some(bits) = bits {
+        assert_eq!(bits.len(), num_bits);
+    }
+
+    let filter = (0..num_bits).map(|i| {
+        Ok(Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| format!("filter bit {}", i)),
+            bits.map(|b| b[i])
+        )?))
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    let packed = pack_into_variables(cs.namespace(|| "pack filter"), &filter)?;
+
+    Ok((filter, packed))
+}
+
+/// Returns the bit of `filter` at the little-endian `position`.
+fn select_bit<E, CS>(
+    mut cs: CS,
+    filter: &[Boolean],
+    position: &[Boolean]
+) -> Result<Num<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut layer: Vec<Num<E>> = filter.iter().map(|bit| {
+        Num::zero().add_bool_with_coeff(CS::one(), bit, E::Fr::one())
+    }).collect();
+
+    for (level, bit) in position.iter().enumerate() {
+        let mut next = vec![];
+        for (i, pair) in layer.chunks(2).enumerate() {
+            let (a, b) = (&pair[0], &pair[1]);
+
+            // a + bit * (b - a)
+            let selected = AllocatedNum::alloc(cs.namespace(|| format!("level {} node {}", level, i)), || {
+                if *bit.get_value().get()? {
+                    Ok(*b.get_value().get()?)
+                } else {
+                    Ok(*a.get_value().get()?)
+                }
+            })?;
+
+            cs.enforce(
+                || format!("select level {} node {}", level, i),
+                |_| bit.lc(CS::one(), E::Fr::one()),
+                |_| b.lc(E::Fr::one()) - &a.lc(E::Fr::one()),
+                |lc| lc + selected.get_variable() - &a.lc(E::Fr::one())
+            );
+
+            next.push(Num::from(selected));
+        }
+
+        layer = next;
+    }
+
+    assert_eq!(layer.len(), 1);
+
+    Ok(layer.pop().unwrap())
+}
+
+/// Enforces that the `num_hashes` positions of `item` are set in
+/// `filter`, which then contains the item or a false positive.
+pub fn enforce_membership<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    filter: &[Boolean],
+    num_hashes: usize,
+    item: &AllocatedNum<E>
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(filter.len().is_power_of_two());
+    let position_bits = filter.len().trailing_zeros() as usize;
+
+    for i in 0..num_hashes {
+        let mut cs = cs.namespace(|| format!("hash {}", i));
+
+        let index = Num::constant(CS::one(), fr_from_u64(i as u64));
+
+        let hash = poseidon2_compress_nums(cs.namespace(|| "position hash"), params, &Num::from(item.clone()), &index)?;
+        let bits = hash.into_bits_le_strict(cs.namespace(|| "position bits"))?;
+
+        let bit = select_bit(cs.namespace(|| "select bit"), filter, &bits[..position_bits])?;
+
+        cs.enforce(
+            || "bit is set",
+            |_| bit.lc(E::Fr::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + CS::one()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use ::poseidon2::generate_params;
+    use ::bloom::BloomFilter;
+    use super::*;
+
+    #[test]
+    fn test_membership() {
+        let params = generate_params::<Fr>(3);
+        let mut filter = BloomFilter::new(&params, 64, 2);
+        for i in 0..5 {
+            filter.insert(&fr(i));
+        }
+        let absent = (5..).map(fr).find(|x| !filter.contains(x)).unwrap();
+
+        for &(item, expected) in [(fr(3), true), (absent, false)].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let (bits, packed) = alloc_filter(cs.namespace(|| "filter"), Some(filter.bits()), 64).unwrap();
+            let item = AllocatedNum::alloc(cs.namespace(|| "item"), || Ok(item)).unwrap();
+
+            enforce_membership(cs.namespace(|| "membership"), &params, &bits, filter.num_hashes(), &item).unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+            assert_eq!(packed.iter().map(|p| p.get_value().unwrap()).collect::<Vec<_>>(), filter.packed());
+        }
+    }
+}
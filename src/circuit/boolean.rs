@@ -12,7 +12,8 @@ use bellman::{
     ConstraintSystem,
     SynthesisError,
     LinearCombination,
-    Variable
+    Variable,
+    Index
 };
 
 use super::{
@@ -36,6 +37,15 @@ impl AllocatedBit {
         self.variable
     }
 
+    /// Returns true if both bits are the same variable.
+    pub fn is_same_variable(&self, other: &Self) -> bool {
+        match (self.variable.get_unchecked(), other.variable.get_unchecked()) {
+            (Index::Input(a), Index::Input(b)) => a == b,
+            (Index::Aux(a), Index::Aux(b)) => a == b,
+            _ => false
+        }
+    }
+
     /// Allocate a variable in the constraint system which can only be a
     /// boolean value. Further, constrain that the boolean is false
     /// unless the condition is false.
@@ -436,6 +446,22 @@ impl Boolean {
 
                 Ok(())
             },
+            // a == a is free and a == NOT a never holds; the latter still
+            // has to be a constraint so that synthesis without a witness
+            // (e.g. during parameter generation) succeeds
+            (&Boolean::Is(ref x), &Boolean::Is(ref y)) | (&Boolean::Not(ref x), &Boolean::Not(ref y))
+                if x.is_same_variable(y) => Ok(()),
+            (&Boolean::Is(ref x), &Boolean::Not(ref y)) | (&Boolean::Not(ref x), &Boolean::Is(ref y))
+                if x.is_same_variable(y) => {
+                cs.enforce(
+                    || "enforce equal to negation",
+                    |lc| lc,
+                    |lc| lc,
+                    |lc| lc + CS::one()
+                );
+
+                Ok(())
+            },
             (a, b) => {
                 cs.enforce(
                     || "enforce equal",
@@ -506,6 +532,11 @@ impl Boolean {
         match (a, b) {
             (&Boolean::Constant(false), x) | (x, &Boolean::Constant(false)) => Ok(x.clone()),
             (&Boolean::Constant(true), x) | (x, &Boolean::Constant(true)) => Ok(x.not()),
+            // a XOR a = false and a XOR (NOT a) = true
+            (&Boolean::Is(ref x), &Boolean::Is(ref y)) | (&Boolean::Not(ref x), &Boolean::Not(ref y))
+                if x.is_same_variable(y) => Ok(Boolean::Constant(false)),
+            (&Boolean::Is(ref x), &Boolean::Not(ref y)) | (&Boolean::Not(ref x), &Boolean::Is(ref y))
+                if x.is_same_variable(y) => Ok(Boolean::Constant(true)),
             // a XOR (NOT b) = NOT(a XOR b)
             (is @ &Boolean::Is(_), not @ &Boolean::Not(_)) | (not @ &Boolean::Not(_), is @ &Boolean::Is(_)) => {
                 Ok(Boolean::xor(
@@ -535,6 +566,11 @@ impl Boolean {
             (&Boolean::Constant(false), _) | (_, &Boolean::Constant(false)) => Ok(Boolean::Constant(false)),
             // true AND x is always x
             (&Boolean::Constant(true), x) | (x, &Boolean::Constant(true)) => Ok(x.clone()),
+            // a AND a = a and a AND (NOT a) = false
+            (x @ &Boolean::Is(_), &Boolean::Is(ref y)) | (x @ &Boolean::Not(_), &Boolean::Not(ref y))
+                if x.get_variable().unwrap().is_same_variable(y) => Ok(x.clone()),
+            (&Boolean::Is(ref x), &Boolean::Not(ref y)) | (&Boolean::Not(ref x), &Boolean::Is(ref y))
+                if x.is_same_variable(y) => Ok(Boolean::Constant(false)),
             // a AND (NOT b)
             (&Boolean::Is(ref is), &Boolean::Not(ref not)) | (&Boolean::Not(ref not), &Boolean::Is(ref is)) => {
                 Ok(Boolean::Is(AllocatedBit::and_not(cs, is, not)?))
@@ -550,6 +586,66 @@ impl Boolean {
         }
     }
 
+    /// Returns `a` if `condition` is true, and `b` otherwise.
+    /// Constant operands are propagated without constraints.
+    pub fn conditionally_select<'a, E, CS>(
+        mut cs: CS,
+        condition: &'a Self,
+        a: &'a Self,
+        b: &'a Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        match (condition, a, b) {
+            (&Boolean::Constant(true), _, _) => return Ok(a.clone()),
+            (&Boolean::Constant(false), _, _) => return Ok(b.clone()),
+            (_, &Boolean::Constant(x), &Boolean::Constant(y)) if x == y => return Ok(a.clone()),
+            (_, &Boolean::Constant(true), &Boolean::Constant(false)) => return Ok(condition.clone()),
+            (_, &Boolean::Constant(false), &Boolean::Constant(true)) => return Ok(condition.not()),
+            // c ? x : false = c AND x
+            (_, x, &Boolean::Constant(false)) => return Boolean::and(cs, condition, x),
+            // c ? false : y = (NOT c) AND y
+            (_, &Boolean::Constant(false), y) => return Boolean::and(cs, &condition.not(), y),
+            _ => {}
+        }
+
+        if let (Some(x), Some(y)) = (a.get_variable(), b.get_variable()) {
+            if x.is_same_variable(y) && a.is_negated() == b.is_negated() {
+                return Ok(a.clone());
+            }
+        }
+
+        let value = match condition.get_value() {
+            Some(true) => a.get_value(),
+            Some(false) => b.get_value(),
+            None => None
+        };
+
+        let c = AllocatedBit::alloc(
+            cs.namespace(|| "conditional select result"),
+            value
+        )?;
+
+        // (a - b) * condition = c - b
+        let one = CS::one();
+        cs.enforce(
+            || "conditional select constraint",
+            |_| a.lc(one, E::Fr::one()) - &b.lc(one, E::Fr::one()),
+            |_| condition.lc(one, E::Fr::one()),
+            |lc| lc + c.get_variable() - &b.lc(one, E::Fr::one())
+        );
+
+        Ok(Boolean::from(c))
+    }
+
+    fn is_negated(&self) -> bool {
+        match *self {
+            Boolean::Not(_) => true,
+            _ => false
+        }
+    }
+
     /// Computes (a and b) xor ((not a) and c)
     pub fn sha256_ch<'a, E, CS>(
         mut cs: CS,
@@ -1605,4 +1701,70 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_same_variable_propagation() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let a = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "a"), Some(true)).unwrap());
+        let not_a = a.not();
+
+        assert_eq!(Boolean::xor(cs.namespace(|| "a xor a"), &a, &a).unwrap().get_value(), Some(false));
+        assert!(Boolean::xor(cs.namespace(|| "a xor not a"), &a, &not_a).unwrap().is_constant());
+        assert_eq!(Boolean::and(cs.namespace(|| "a and a"), &a, &a).unwrap().get_value(), Some(true));
+        assert!(Boolean::and(cs.namespace(|| "a and not a"), &a, &not_a).unwrap().is_constant());
+        Boolean::enforce_equal(cs.namespace(|| "a == a"), &a, &a).unwrap();
+
+        let c = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "c"), Some(false)).unwrap());
+        let selected = Boolean::conditionally_select(cs.namespace(|| "select same"), &c, &a, &a).unwrap();
+        assert_eq!(selected.get_value(), Some(true));
+
+        // only the booleanity of a and c
+        assert_eq!(cs.num_constraints(), 2);
+        assert!(cs.is_satisfied());
+
+        Boolean::enforce_equal(cs.namespace(|| "a == not a"), &a, &not_a).unwrap();
+        assert_eq!(cs.num_constraints(), 3);
+        assert_eq!(cs.which_is_unsatisfied(), Some("a == not a/enforce equal to negation"));
+    }
+
+    #[test]
+    fn test_conditionally_select() {
+        for &condition in [true, false].iter() {
+            for &a_val in [true, false].iter() {
+                for &b_val in [true, false].iter() {
+                    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                    let c = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "c"), Some(condition)).unwrap());
+                    let a = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "a"), Some(a_val)).unwrap());
+                    let b = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "b"), Some(b_val)).unwrap());
+
+                    let expected = if condition { a_val } else { b_val };
+
+                    let res = Boolean::conditionally_select(cs.namespace(|| "select"), &c, &a, &b).unwrap();
+                    assert_eq!(res.get_value(), Some(expected));
+                    assert_eq!(cs.num_constraints(), 3 + 2);
+
+                    // constant condition is free
+                    let res = Boolean::conditionally_select(
+                        cs.namespace(|| "constant select"), &Boolean::constant(condition), &a, &b
+                    ).unwrap();
+                    assert_eq!(res.get_value(), Some(expected));
+
+                    // constant operands reduce to the condition itself or an AND
+                    let res = Boolean::conditionally_select(
+                        cs.namespace(|| "select from constants"), &c, &Boolean::constant(true), &Boolean::constant(false)
+                    ).unwrap();
+                    assert_eq!(res.get_value(), Some(condition));
+                    let res = Boolean::conditionally_select(
+                        cs.namespace(|| "select or false"), &c, &a, &Boolean::constant(false)
+                    ).unwrap();
+                    assert_eq!(res.get_value(), Some(condition && a_val));
+
+                    assert!(cs.is_satisfied());
+                    assert_eq!(cs.num_constraints(), 3 + 2 + 1);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,207 @@
+//! Loops with a variable number of iterations, at most a constant.
+//!
+//! The body is synthesized `max` times. Iteration `i` is active iff
+//! `i < count`; the active flags are constrained to be a prefix of ones
+//! summing to `count`, so `count <= max`. The state after an inactive
+//! iteration is the state before it, whatever the body computed, so the
+//! output is the state after `count` iterations.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::num::AllocatedNum;
+
+/// One iteration of a bounded loop.
+pub trait LoopBody<E: Engine> {
+    /// Computes the next state. The witness of an inactive iteration
+    /// may be arbitrary, so constraints that only hold for meaningful
+    /// witnesses must be conditioned on `active`.
+    fn step<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: CS,
+        iteration: usize,
+        active: &Boolean,
+        state: &[AllocatedNum<E>]
+    ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>;
+}
+
+/// Returns `max` flags, the first `count` of them true, and enforces
+/// that `count` is at most `max`.
+pub fn active_flags<E, CS>(
+    mut cs: CS,
+    count: &AllocatedNum<E>,
+    max: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let count_value = match count.get_value() {
+        Some(value) => {
+            let repr = value.into_repr();
+            if repr.num_bits() > 64 {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+
+            Some(repr.as_ref()[0])
+        },
+        None => None
+    };
+
+    let mut active: Vec<AllocatedBit> = vec![];
+    for i in 0..max {
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("active {}", i)),
+            count_value.map(|c| (i as u64) < c)
+        )?;
+
+        if i > 0 {
+            // active_i implies active_{i - 1}
+            let previous = &active[i - 1];
+            cs.enforce(
+                || format!("active {} is a prefix", i),
+                |lc| lc + bit.get_variable(),
+                |lc| lc + CS::one() - previous.get_variable(),
+                |lc| lc
+            );
+        }
+
+        active.push(bit);
+    }
+
+    cs.enforce(
+        || "count is the number of active iterations",
+        |lc| active.iter().fold(lc, |lc, bit| lc + bit.get_variable()),
+        |lc| lc + CS::one(),
+        |lc| lc + count.get_variable()
+    );
+
+    Ok(active.into_iter().map(|bit| Boolean::from(bit)).collect())
+}
+
+/// Runs `body` on `initial` `count` times, `count <= max`, and returns
+/// the final state.
+pub fn bounded_loop<E, CS, B>(
+    mut cs: CS,
+    count: &AllocatedNum<E>,
+    max: usize,
+    initial: &[AllocatedNum<E>],
+    body: &mut B
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>,
+          B: LoopBody<E>
+{
+    let active = active_flags(cs.namespace(|| "active flags"), count, max)?;
+
+    let mut state = initial.to_vec();
+    for (i, active) in active.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("iteration {}", i));
+
+        let next = body.step(cs.namespace(|| "body"), i, active, &state)?;
+        assert_eq!(next.len(), state.len());
+
+        state = next.iter().zip(state.iter()).enumerate().map(|(j, (next, cur))| {
+            AllocatedNum::conditionally_select(
+                cs.namespace(|| format!("select {}", j)),
+                next,
+                cur,
+                active
+            )
+        }).collect::<Result<Vec<_>, _>>()?;
+    }
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::Field;
+    use ::circuit::test::*;
+    use ::circuit::Assignment;
+    use super::*;
+
+    /// Horner evaluation `acc = acc * x + c_i` of a polynomial given by
+    /// its coefficients from the highest degree.
+    struct Horner {
+        x: Fr,
+        coeffs: Vec<AllocatedNum<Bn256>>
+    }
+
+    impl LoopBody<Bn256> for Horner {
+        fn step<CS: ConstraintSystem<Bn256>>(
+            &mut self,
+            mut cs: CS,
+            iteration: usize,
+            _active: &Boolean,
+            state: &[AllocatedNum<Bn256>]
+        ) -> Result<Vec<AllocatedNum<Bn256>>, SynthesisError>
+        {
+            let x = self.x;
+            let c = &self.coeffs[iteration];
+            let next = AllocatedNum::alloc(cs.namespace(|| "acc"), || {
+                let mut tmp = *state[0].get_value().get()?;
+                tmp.mul_assign(&x);
+                tmp.add_assign(c.get_value().get()?);
+
+                Ok(tmp)
+            })?;
+
+            cs.enforce(
+                || "horner step",
+                |lc| lc + state[0].get_variable(),
+                |lc| lc + (x, CS::one()),
+                |lc| lc + next.get_variable() - c.get_variable()
+            );
+
+            Ok(vec![next])
+        }
+    }
+
+    #[test]
+    fn test_bounded_loop() {
+        for count in 0..5 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let coeffs = (0..4).map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("coeff {}", i)), || Ok(fr(i as u64 + 1))).unwrap()
+            }).collect();
+            let zero = AllocatedNum::alloc(cs.namespace(|| "zero"), || Ok(Fr::zero())).unwrap();
+            let count_num = AllocatedNum::alloc(cs.namespace(|| "count"), || Ok(fr(count))).unwrap();
+
+            let mut body = Horner { x: fr(10), coeffs: coeffs };
+            let result = bounded_loop(cs.namespace(|| "loop"), &count_num, 4, &[zero], &mut body).unwrap();
+
+            // 1, 12, 123, 1234
+            let expected = (0..count).fold(0, |acc, i| acc * 10 + i + 1);
+
+            if count <= 4 {
+                assert!(cs.is_satisfied());
+                assert_eq!(result[0].get_value().unwrap(), fr(expected));
+
+                // 4 flags, 3 prefix constraints, the count, and a step and a
+                // selection per iteration
+                assert_eq!(cs.num_constraints(), 4 + 3 + 1 + 4 * 2);
+            } else {
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+}
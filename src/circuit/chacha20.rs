@@ -0,0 +1,270 @@
+//! ChaCha20 stream cipher and the ChaCha20-Poly1305 AEAD (RFC 8439).
+//!
+//! Inputs are bit strings in little-endian byte order, the same as
+//! for the BLAKE2s gadget.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    Boolean
+};
+
+use super::uint32::{
+    UInt32
+};
+
+use super::multieq::MultiEq;
+
+use super::poly1305::{
+    poly1305,
+    POLY1305_TAG_SIZE
+};
+
+pub const CHACHA20_KEY_SIZE: usize = 256;
+pub const CHACHA20_NONCE_SIZE: usize = 96;
+pub const CHACHA20_BLOCK_SIZE: usize = 512;
+
+/*
+2.1.  The ChaCha Quarter Round
+       1.  a += b; d ^= a; d <<<= 16;
+       2.  c += d; b ^= c; b <<<= 12;
+       3.  a += b; d ^= a; d <<<= 8;
+       4.  c += d; b ^= c; b <<<= 7;
+*/
+
+fn quarter_round<E: Engine, CS: ConstraintSystem<E>, M>(
+    mut cs: M,
+    v: &mut [UInt32],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize
+) -> Result<(), SynthesisError>
+    where M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+{
+    v[a] = UInt32::addmany(cs.namespace(|| "step 1"), &[v[a].clone(), v[b].clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "step 2"), &v[a])?.rotr(32 - 16);
+    v[c] = UInt32::addmany(cs.namespace(|| "step 3"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "step 4"), &v[c])?.rotr(32 - 12);
+    v[a] = UInt32::addmany(cs.namespace(|| "step 5"), &[v[a].clone(), v[b].clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "step 6"), &v[a])?.rotr(32 - 8);
+    v[c] = UInt32::addmany(cs.namespace(|| "step 7"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "step 8"), &v[c])?.rotr(32 - 7);
+
+    Ok(())
+}
+
+/// Computes a 512 bit keystream block for the given counter.
+pub fn chacha20_block<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    key: &[Boolean],
+    counter: u32,
+    nonce: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    assert_eq!(key.len(), CHACHA20_KEY_SIZE);
+    assert_eq!(nonce.len(), CHACHA20_NONCE_SIZE);
+
+    // "expand 32-byte k"
+    let mut state = Vec::with_capacity(16);
+    state.push(UInt32::constant(0x61707865));
+    state.push(UInt32::constant(0x3320646e));
+    state.push(UInt32::constant(0x79622d32));
+    state.push(UInt32::constant(0x6b206574));
+    state.extend(key.chunks(32).map(|w| UInt32::from_bits(w)));
+    state.push(UInt32::constant(counter));
+    state.extend(nonce.chunks(32).map(|w| UInt32::from_bits(w)));
+
+    assert_eq!(state.len(), 16);
+
+    let mut v = state.clone();
+
+    {
+        let mut cs = MultiEq::new(&mut cs);
+
+        for i in 0..10 {
+            let mut cs = cs.namespace(|| format!("double round {}", i));
+
+            quarter_round(cs.namespace(|| "column 1"), &mut v, 0, 4,  8, 12)?;
+            quarter_round(cs.namespace(|| "column 2"), &mut v, 1, 5,  9, 13)?;
+            quarter_round(cs.namespace(|| "column 3"), &mut v, 2, 6, 10, 14)?;
+            quarter_round(cs.namespace(|| "column 4"), &mut v, 3, 7, 11, 15)?;
+
+            quarter_round(cs.namespace(|| "diagonal 1"), &mut v, 0, 5, 10, 15)?;
+            quarter_round(cs.namespace(|| "diagonal 2"), &mut v, 1, 6, 11, 12)?;
+            quarter_round(cs.namespace(|| "diagonal 3"), &mut v, 2, 7,  8, 13)?;
+            quarter_round(cs.namespace(|| "diagonal 4"), &mut v, 3, 4,  9, 14)?;
+        }
+
+        for i in 0..16 {
+            v[i] = UInt32::addmany(cs.namespace(|| format!("final addition {}", i)), &[v[i].clone(), state[i].clone()])?;
+        }
+    }
+
+    Ok(v.iter().flat_map(|w| w.into_bits()).collect())
+}
+
+/// Encrypts (or decrypts) the input with the keystream starting
+/// from the block `initial_counter`.
+pub fn chacha20<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    key: &[Boolean],
+    nonce: &[Boolean],
+    initial_counter: u32,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    assert!(input.len() % 8 == 0);
+
+    let mut output = Vec::with_capacity(input.len());
+    for (i, chunk) in input.chunks(CHACHA20_BLOCK_SIZE).enumerate() {
+        let mut cs = cs.namespace(|| format!("block {}", i));
+
+        let keystream = chacha20_block(cs.namespace(|| "keystream"), key, initial_counter.wrapping_add(i as u32), nonce)?;
+        for (j, (a, b)) in chunk.iter().zip(keystream.iter()).enumerate() {
+            output.push(Boolean::xor(cs.namespace(|| format!("xor bit {}", j)), a, b)?);
+        }
+    }
+
+    Ok(output)
+}
+
+fn aead_tag<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    key: &[Boolean],
+    nonce: &[Boolean],
+    aad: &[Boolean],
+    ciphertext: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    assert!(aad.len() % 8 == 0);
+    assert!(ciphertext.len() % 8 == 0);
+
+    let otk = chacha20_block(cs.namespace(|| "one-time key"), key, 0, nonce)?;
+
+    let pad = |data: &mut Vec<Boolean>| {
+        while data.len() % 128 != 0 {
+            data.push(Boolean::constant(false));
+        }
+    };
+
+    let mut mac_data = aad.to_vec();
+    pad(&mut mac_data);
+    mac_data.extend_from_slice(ciphertext);
+    pad(&mut mac_data);
+    for &len in [aad.len() / 8, ciphertext.len() / 8].iter() {
+        mac_data.extend((0..64).map(|i| Boolean::constant((len as u64 >> i) & 1 == 1)));
+    }
+
+    poly1305(cs.namespace(|| "poly1305"), &otk[..256], &mac_data)
+}
+
+/// ChaCha20-Poly1305 encryption, returns the ciphertext and the tag.
+pub fn chacha20_poly1305_encrypt<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    key: &[Boolean],
+    nonce: &[Boolean],
+    aad: &[Boolean],
+    plaintext: &[Boolean]
+) -> Result<(Vec<Boolean>, Vec<Boolean>), SynthesisError>
+{
+    let ciphertext = chacha20(cs.namespace(|| "encryption"), key, nonce, 1, plaintext)?;
+    let tag = aead_tag(cs.namespace(|| "authentication"), key, nonce, aad, &ciphertext)?;
+
+    Ok((ciphertext, tag))
+}
+
+/// ChaCha20-Poly1305 decryption, enforces that the tag is valid
+/// and returns the plaintext.
+pub fn chacha20_poly1305_decrypt<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    key: &[Boolean],
+    nonce: &[Boolean],
+    aad: &[Boolean],
+    ciphertext: &[Boolean],
+    tag: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    assert_eq!(tag.len(), POLY1305_TAG_SIZE);
+
+    let expected_tag = aead_tag(cs.namespace(|| "authentication"), key, nonce, aad, ciphertext)?;
+    for (i, (a, b)) in expected_tag.iter().zip(tag.iter()).enumerate() {
+        Boolean::enforce_equal(cs.namespace(|| format!("tag bit {}", i)), a, b)?;
+    }
+
+    chacha20(cs.namespace(|| "decryption"), key, nonce, 1, ciphertext)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bls12_381::{Bls12};
+    use ::circuit::test::*;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use super::*;
+
+    fn alloc_bytes<CS: ConstraintSystem<Bls12>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().enumerate().flat_map(|(i, &b)| (0..8).map(move |j| (i, j, (b >> j) & 1 == 1)))
+            .map(|(i, j, bit)| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {} {}", i, j)), Some(bit)).unwrap())
+            }).collect()
+    }
+
+    fn into_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|b| {
+            b.iter().enumerate().fold(0u8, |acc, (i, bit)| acc | ((bit.get_value().unwrap() as u8) << i))
+        }).collect()
+    }
+
+    #[test]
+    fn test_chacha20_block_vector() {
+        // RFC 8439, section 2.3.2
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let key = alloc_bytes(cs.namespace(|| "key"), &hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"));
+        let nonce = alloc_bytes(cs.namespace(|| "nonce"), &hex!("000000090000004a00000000"));
+
+        let block = chacha20_block(cs.namespace(|| "block"), &key, 1, &nonce).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&block), hex!("10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4ed2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e").to_vec());
+    }
+
+    #[test]
+    fn test_chacha20_poly1305_roundtrip() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let key = alloc_bytes(cs.namespace(|| "key"), &[7u8; 32]);
+        let nonce = alloc_bytes(cs.namespace(|| "nonce"), &[1u8; 12]);
+        let aad = alloc_bytes(cs.namespace(|| "aad"), b"header");
+        let plaintext = alloc_bytes(cs.namespace(|| "plaintext"), b"a note plaintext of some length");
+
+        let (ciphertext, tag) = chacha20_poly1305_encrypt(cs.namespace(|| "encrypt"), &key, &nonce, &aad, &plaintext).unwrap();
+        let decrypted = chacha20_poly1305_decrypt(cs.namespace(|| "decrypt"), &key, &nonce, &aad, &ciphertext, &tag).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&decrypted), b"a note plaintext of some length".to_vec());
+
+        // a modified ciphertext is rejected
+        let mut ciphertext = into_bytes(&ciphertext);
+        ciphertext[0] ^= 1;
+        let tag = into_bytes(&tag);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let key = alloc_bytes(cs.namespace(|| "key"), &[7u8; 32]);
+        let nonce = alloc_bytes(cs.namespace(|| "nonce"), &[1u8; 12]);
+        let aad = alloc_bytes(cs.namespace(|| "aad"), b"header");
+        let ciphertext = alloc_bytes(cs.namespace(|| "ciphertext"), &ciphertext);
+        let tag = alloc_bytes(cs.namespace(|| "tag"), &tag);
+
+        chacha20_poly1305_decrypt(cs.namespace(|| "decrypt"), &key, &nonce, &aad, &ciphertext, &tag).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,145 @@
+//! In-circuit side of `commit_and_prove`: recomputes the vector
+//! commitment to allocated values, so that it can be exposed and
+//! compared with the commitments of other proofs.
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    self,
+    Boolean
+};
+
+use super::num::AllocatedNum;
+
+use super::ecc::{
+    EdwardsPoint,
+    FixedBase
+};
+
+use jubjub::JubjubEngine;
+
+use commit_and_prove::{
+    VectorCommitmentKey,
+    HALF_BITS
+};
+
+/// The generators of a `VectorCommitmentKey` with their window tables.
+#[derive(Clone)]
+pub struct VectorCommitmentGadget<E: JubjubEngine> {
+    blinding: FixedBase<E>,
+    generators: Vec<FixedBase<E>>
+}
+
+impl<E: JubjubEngine> VectorCommitmentGadget<E> {
+    pub fn new(key: &VectorCommitmentKey<E>, params: &E::Params) -> Self {
+        VectorCommitmentGadget {
+            blinding: FixedBase::new(key.blinding.clone(), params),
+            generators: key.generators.iter().map(|g| FixedBase::new(g.clone(), params)).collect()
+        }
+    }
+
+    /// Commits to `values` with the little-endian bits of the
+    /// randomness.
+    pub fn commit<CS>(
+        &self,
+        mut cs: CS,
+        values: &[AllocatedNum<E>],
+        randomness: &[Boolean],
+        params: &E::Params
+    ) -> Result<EdwardsPoint<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert_eq!(values.len() * 2, self.generators.len());
+
+        let mut cm = self.blinding.mul(cs.namespace(|| "randomness"), randomness, params)?;
+        for (i, (value, g)) in values.iter().zip(self.generators.chunks(2)).enumerate() {
+            let mut cs = cs.namespace(|| format!("value {}", i));
+
+            let bits = value.into_bits_le_strict(cs.namespace(|| "into bits"))?;
+            cm = g[0].mul_and_add(cs.namespace(|| "low half"), &bits[..HALF_BITS], &cm, params)?;
+            cm = g[1].mul_and_add(cs.namespace(|| "high half"), &bits[HALF_BITS..], &cm, params)?;
+        }
+
+        Ok(cm)
+    }
+
+    /// Witnesses the randomness, commits to `values` and exposes the
+    /// commitment as a public input.
+    pub fn expose<CS>(
+        &self,
+        mut cs: CS,
+        values: &[AllocatedNum<E>],
+        randomness: Option<E::Fs>,
+        params: &E::Params
+    ) -> Result<EdwardsPoint<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let randomness = boolean::field_into_boolean_vec_le(cs.namespace(|| "randomness"), randomness)?;
+        let cm = self.commit(cs.namespace(|| "commitment"), values, &randomness, params)?;
+        cm.inputize(cs.namespace(|| "expose commitment"))?;
+
+        Ok(cm)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::Field;
+    use alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use ::circuit::test::*;
+    use super::*;
+
+    #[test]
+    fn test_shared_witness() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let key = VectorCommitmentKey::<Bn256>::new(b"shared", 2, params);
+        let gadget = VectorCommitmentGadget::new(&key, params);
+
+        let a: Fr = rng.gen();
+        let b: Fr = rng.gen();
+        let r: Fs = rng.gen();
+        let expected = key.commit(&[a, b], r, params).into_xy();
+
+        // two circuits using a and b differently expose the same
+        // commitment
+        let mut exposed = vec![];
+        for square in vec![false, true] {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a)).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap();
+            if square {
+                a.square(cs.namespace(|| "a^2")).unwrap();
+            } else {
+                a.mul(cs.namespace(|| "a * b"), &b).unwrap();
+            }
+
+            gadget.expose(cs.namespace(|| "shared"), &[a, b], Some(r), params).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_inputs(), 3);
+            exposed.push((
+                cs.get_input(1, "shared/expose commitment/x/input variable"),
+                cs.get_input(2, "shared/expose commitment/y/input variable")
+            ));
+        }
+
+        assert_eq!(exposed[0], expected);
+        assert_eq!(exposed[1], expected);
+
+        // another value gives another commitment
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut c = a;
+        c.add_assign(&Fr::one());
+        let c = AllocatedNum::alloc(cs.namespace(|| "c"), || Ok(c)).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b)).unwrap();
+        let cm = gadget.expose(cs.namespace(|| "shared"), &[c, b], Some(r), params).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cm.get_x().get_value().unwrap() != expected.0);
+    }
+}
@@ -0,0 +1,188 @@
+//! In-circuit opening checks for the schemes of `commitment`, with
+//! the same positions and values as the native `CommitmentScheme`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+
+use super::num::AllocatedNum;
+
+use super::ecc::EdwardsPoint;
+
+use super::pedersen_hash::pedersen_commitment;
+
+use super::indexed_merkle_tree::compute_root;
+
+use jubjub::JubjubEngine;
+
+use commitment::{
+    PedersenCommitmentScheme,
+    MerkleCommitmentScheme
+};
+
+pub trait CommitmentGadget<E: Engine> {
+    type Commitment;
+    type Position;
+    type Value;
+    type Proof;
+
+    /// Enforces that `value` is the opening of `commitment` at
+    /// `position`.
+    fn verify_opening<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        commitment: &Self::Commitment,
+        position: &Self::Position,
+        value: &Self::Value,
+        proof: &Self::Proof
+    ) -> Result<(), SynthesisError>;
+}
+
+fn enforce_equal<E, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    cs.enforce(
+        || "enforce equal",
+        |lc| lc + a.get_variable() - b.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+
+    Ok(())
+}
+
+/// The randomness is given by its little-endian bits.
+impl<'a, E: JubjubEngine> CommitmentGadget<E> for PedersenCommitmentScheme<'a, E> {
+    type Commitment = EdwardsPoint<E>;
+    type Position = ();
+    type Value = Vec<Boolean>;
+    type Proof = Vec<Boolean>;
+
+    fn verify_opening<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        commitment: &EdwardsPoint<E>,
+        _position: &(),
+        value: &Vec<Boolean>,
+        proof: &Vec<Boolean>
+    ) -> Result<(), SynthesisError>
+    {
+        let computed = pedersen_commitment(
+            cs.namespace(|| "commit"),
+            self.personalization,
+            value,
+            proof,
+            self.params
+        )?;
+
+        enforce_equal(cs.namespace(|| "x"), computed.get_x(), commitment.get_x())?;
+        enforce_equal(cs.namespace(|| "y"), computed.get_y(), commitment.get_y())
+    }
+}
+
+/// The leaf index is given by its little-endian bits.
+impl<'a, E: Engine> CommitmentGadget<E> for MerkleCommitmentScheme<'a, E::Fr> {
+    type Commitment = AllocatedNum<E>;
+    type Position = Vec<Boolean>;
+    type Value = AllocatedNum<E>;
+    type Proof = Vec<AllocatedNum<E>>;
+
+    fn verify_opening<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        commitment: &AllocatedNum<E>,
+        position: &Vec<Boolean>,
+        value: &AllocatedNum<E>,
+        proof: &Vec<AllocatedNum<E>>
+    ) -> Result<(), SynthesisError>
+    {
+        let root = compute_root(cs.namespace(|| "root"), self.params, value, position, proof)?;
+
+        enforce_equal(cs.namespace(|| "enforce root"), &root, commitment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bls12_381::{Bls12, Fr};
+    use bellman::pairing::ff::Field;
+    use ::circuit::test::*;
+    use ::circuit::boolean::{AllocatedBit, field_into_boolean_vec_le};
+    use ::commitment::CommitmentScheme;
+    use ::jubjub::JubjubBls12;
+    use ::pedersen_hash::Personalization;
+    use ::poseidon2::generate_params;
+    use super::*;
+
+    #[test]
+    fn test_pedersen_opening() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = JubjubBls12::new();
+        let scheme = PedersenCommitmentScheme::<Bls12> {
+            personalization: Personalization::Custom(1),
+            params: &params
+        };
+
+        let message: Vec<bool> = (0..100).map(|_| rng.gen()).collect();
+        let (commitment, data) = scheme.commit(&message, rng);
+        let (value, r) = scheme.open(&data, &()).unwrap();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let commitment = EdwardsPoint::witness(cs.namespace(|| "commitment"), Some(commitment), &params).unwrap();
+        let value: Vec<Boolean> = value.iter().enumerate().map(|(i, b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap())
+        }).collect();
+        let r = field_into_boolean_vec_le(cs.namespace(|| "r"), Some(r)).unwrap();
+
+        scheme.verify_opening(cs.namespace(|| "opening"), &commitment, &(), &value, &r).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("bit 7/boolean", if message[7] { Fr::zero() } else { Fr::one() });
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_merkle_opening() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<Fr>(3);
+        let scheme = MerkleCommitmentScheme { params: &params };
+
+        let leaves: Vec<Fr> = (0..8).map(|_| rng.gen()).collect();
+        let (root, data) = CommitmentScheme::commit(&scheme, &leaves, rng);
+        let (value, path) = scheme.open(&data, &5).unwrap();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let root = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(root)).unwrap();
+        let value = AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(value)).unwrap();
+        let index: Vec<Boolean> = (0..3).map(|i| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("index bit {}", i)), Some((5 >> i) & 1 == 1)).unwrap())
+        }).collect();
+        let path: Vec<_> = path.into_iter().enumerate().map(|(i, el)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("path {}", i)), || Ok(el)).unwrap()
+        }).collect();
+
+        scheme.verify_opening(cs.namespace(|| "opening"), &root, &index, &value, &path).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("value/num", rng.gen());
+        assert!(!cs.is_satisfied());
+    }
+}
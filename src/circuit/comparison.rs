@@ -0,0 +1,217 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+    BitIterator
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::{
+    Assignment
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+/// Allocates the `num_bits` least significant bits of the value
+/// in little-endian order and enforces that they pack into `lc`.
+/// The system is unsatisfiable if the value doesn't fit into
+/// `num_bits` bits.
+pub fn alloc_packed_bits<E, CS>(
+    mut cs: CS,
+    value: Option<E::Fr>,
+    lc: LinearCombination<E>,
+    num_bits: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(num_bits < E::Fr::CAPACITY as usize);
+
+    let values: Vec<Option<bool>> = match value {
+        Some(value) => {
+            let mut tmp: Vec<bool> = BitIterator::new(value.into_repr()).collect();
+            tmp.reverse();
+            tmp.truncate(num_bits);
+
+            tmp.into_iter().map(|b| Some(b)).collect()
+        },
+        None => vec![None; num_bits]
+    };
+
+    let mut bits = vec![];
+    let mut packed = LinearCombination::<E>::zero();
+    let mut coeff = E::Fr::one();
+
+    for (i, b) in values.into_iter().enumerate() {
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            b
+        )?;
+
+        packed = packed + (coeff, bit.get_variable());
+        coeff.double();
+
+        bits.push(Boolean::from(bit));
+    }
+
+    cs.enforce(
+        || "packing constraint",
+        |lc| lc,
+        |lc| lc,
+        |_| packed - &lc
+    );
+
+    Ok(bits)
+}
+
+/// Enforces that `a <= b`. Both numbers are expected to
+/// be already constrained to be less than `2^num_bits`,
+/// otherwise the comparison is meaningless.
+pub fn enforce_less_or_equal<E, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    // b - a is in [0, 2^num_bits) iff a <= b
+    let value = match (a.get_value(), b.get_value()) {
+        (Some(a), Some(b)) => {
+            let mut tmp = b;
+            tmp.sub_assign(&a);
+
+            Some(tmp)
+        },
+        _ => None
+    };
+
+    alloc_packed_bits(
+        cs.namespace(|| "decompose difference"),
+        value,
+        LinearCombination::zero() + b.get_variable() - a.get_variable(),
+        num_bits
+    )?;
+
+    Ok(())
+}
+
+/// Returns a boolean that is `true` if and only if `a < b`.
+/// Both numbers are expected to be already constrained to be
+/// less than `2^num_bits`, otherwise the result is meaningless.
+pub fn less_than<E, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<E>,
+    b: &AllocatedNum<E>,
+    num_bits: usize
+) -> Result<Boolean, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    // 2^num_bits + a - b has the top bit set iff a >= b
+    let mut shift = E::Fr::one();
+    for _ in 0..num_bits {
+        shift.double();
+    }
+
+    let value = match (a.get_value(), b.get_value()) {
+        (Some(a), Some(b)) => {
+            let mut tmp = shift;
+            tmp.add_assign(&a);
+            tmp.sub_assign(&b);
+
+            Some(tmp)
+        },
+        _ => None
+    };
+
+    let bits = alloc_packed_bits(
+        cs.namespace(|| "decompose shifted difference"),
+        value,
+        LinearCombination::zero() + (shift, CS::one()) + a.get_variable() - b.get_variable(),
+        num_bits + 1
+    )?;
+
+    Ok(bits[num_bits].not())
+}
+
+/// Takes a linear combination `num` and returns a boolean
+/// that is `true` if and only if `num < 2^num_bits`, assuming
+/// that `num < 2^(num_bits + 1)`.
+pub fn fits_into_bits<E, CS>(
+    mut cs: CS,
+    num: &Num<E>,
+    num_bits: usize
+) -> Result<Boolean, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let bits = alloc_packed_bits(
+        cs.namespace(|| "decompose number"),
+        num.get_value(),
+        num.lc(E::Fr::one()),
+        num_bits + 1
+    )?;
+
+    Ok(bits[num_bits].not())
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use ::circuit::test::*;
+    use super::super::num::AllocatedNum;
+    use super::{enforce_less_or_equal, less_than};
+
+    #[test]
+    fn test_less_or_equal() {
+        for &(a, b, expected) in [(3u64, 5u64, true), (5, 5, true), (6, 5, false), (0, 255, true)].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str(&a.to_string()).unwrap())).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str(&b.to_string()).unwrap())).unwrap();
+
+            enforce_less_or_equal(cs.namespace(|| "compare"), &a, &b, 8).unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+            assert_eq!(cs.num_constraints(), 8 + 1);
+        }
+    }
+
+    #[test]
+    fn test_less_than() {
+        for a in 0u64..16 {
+            for b in 0u64..16 {
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+
+                let a_num = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str(&a.to_string()).unwrap())).unwrap();
+                let b_num = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str(&b.to_string()).unwrap())).unwrap();
+
+                let lt = less_than(cs.namespace(|| "compare"), &a_num, &b_num, 4).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(lt.get_value().unwrap(), a < b);
+            }
+        }
+    }
+}
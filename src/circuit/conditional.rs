@@ -0,0 +1,228 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    Namespace
+};
+
+use super::{
+    Assignment
+};
+
+use super::num::{
+    AllocatedNum
+};
+
+use super::boolean::{
+    Boolean
+};
+
+/// Circuit values that can be selected between
+/// based on an (allocated or constant) boolean flag.
+pub trait Selectable<E: Engine>: Sized + Clone {
+    /// Returns `a` if `flag` is true, and `b` otherwise.
+    fn conditionally_select<CS>(
+        cs: CS,
+        flag: &Boolean,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>;
+}
+
+impl<E: Engine> Selectable<E> for AllocatedNum<E> {
+    fn conditionally_select<CS>(
+        cs: CS,
+        flag: &Boolean,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        AllocatedNum::conditionally_select(cs, a, b, flag)
+    }
+}
+
+impl<E: Engine> Selectable<E> for Boolean {
+    fn conditionally_select<CS>(
+        cs: CS,
+        flag: &Boolean,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        Boolean::conditionally_select(cs, flag, a, b)
+    }
+}
+
+impl<E: Engine, T: Selectable<E>> Selectable<E> for Vec<T> {
+    fn conditionally_select<CS>(
+        mut cs: CS,
+        flag: &Boolean,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert_eq!(a.len(), b.len(), "branches must produce outputs of the same length");
+
+        let mut result = Vec::with_capacity(a.len());
+        for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+            let selected = T::conditionally_select(
+                cs.namespace(|| format!("select element {}", i)),
+                flag,
+                a,
+                b
+            )?;
+
+            result.push(selected);
+        }
+
+        Ok(result)
+    }
+}
+
+impl<E: Engine, A: Selectable<E>, B: Selectable<E>> Selectable<E> for (A, B) {
+    fn conditionally_select<CS>(
+        mut cs: CS,
+        flag: &Boolean,
+        a: &Self,
+        b: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let first = A::conditionally_select(cs.namespace(|| "select first"), flag, &a.0, &b.0)?;
+        let second = B::conditionally_select(cs.namespace(|| "select second"), flag, &a.1, &b.1)?;
+
+        Ok((first, second))
+    }
+}
+
+/// Synthesizes both branches and returns the output of `branch_a`
+/// if `flag` is true, and the output of `branch_b` otherwise.
+/// If the flag is a constant only the taken branch is synthesized,
+/// so no constraints are spent on the other one or on the selection.
+///
+/// Note that constraints produced inside of the branches are always
+/// enforced, so the branches must be satisfiable for any witness
+/// that is plugged in (e.g. by using dummy values for the branch that
+/// is not taken).
+pub fn if_then_else_gadget<E, CS, T, A, B>(
+    mut cs: CS,
+    flag: &Boolean,
+    branch_a: A,
+    branch_b: B
+) -> Result<T, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>,
+          T: Selectable<E>,
+          A: for<'a> FnOnce(&mut Namespace<'a, E, CS::Root>) -> Result<T, SynthesisError>,
+          B: for<'a> FnOnce(&mut Namespace<'a, E, CS::Root>) -> Result<T, SynthesisError>
+{
+    match *flag {
+        Boolean::Constant(true) => {
+            return branch_a(&mut cs.namespace(|| "branch a"));
+        },
+        Boolean::Constant(false) => {
+            return branch_b(&mut cs.namespace(|| "branch b"));
+        },
+        _ => {}
+    }
+
+    let a = branch_a(&mut cs.namespace(|| "branch a"))?;
+    let b = branch_b(&mut cs.namespace(|| "branch b"))?;
+
+    T::conditionally_select(cs.namespace(|| "select output"), flag, &a, &b)
+}
+
+/// Synthesizes the `branch` and returns its output if `flag` is true,
+/// and the `default` otherwise. Costs nothing if the flag is constant
+/// false, in which case the branch is not synthesized at all.
+pub fn if_then_gadget<E, CS, T, A>(
+    mut cs: CS,
+    flag: &Boolean,
+    branch: A,
+    default: &T
+) -> Result<T, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>,
+          T: Selectable<E>,
+          A: for<'a> FnOnce(&mut Namespace<'a, E, CS::Root>) -> Result<T, SynthesisError>
+{
+    if let Boolean::Constant(false) = *flag {
+        return Ok(default.clone());
+    }
+
+    let output = branch(&mut cs.namespace(|| "branch"))?;
+
+    T::conditionally_select(cs.namespace(|| "select output"), flag, &output, default)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use ::circuit::test::*;
+    use super::super::num::AllocatedNum;
+    use super::super::boolean::{Boolean, AllocatedBit};
+    use super::if_then_else_gadget;
+
+    fn square_or_double<F>(make_flag: F) -> (TestConstraintSystem<Bn256>, Option<Fr>)
+        where F: FnOnce(&mut TestConstraintSystem<Bn256>) -> Boolean
+    {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let flag = make_flag(&mut cs);
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::from_str("7").unwrap())).unwrap();
+
+        let out = if_then_else_gadget(
+            cs.namespace(|| "if then else"),
+            &flag,
+            |cs| {
+                x.square(cs.namespace(|| "square"))
+            },
+            |cs| {
+                let two = AllocatedNum::alloc(cs.namespace(|| "two"), || Ok(Fr::from_str("2").unwrap()))?;
+                x.mul(cs.namespace(|| "double"), &two)
+            }
+        ).unwrap();
+
+        let value = out.get_value();
+
+        (cs, value)
+    }
+
+    #[test]
+    fn test_if_then_else_allocated_flag() {
+        for &flag in [true, false].iter() {
+            let (cs, value) = square_or_double(|cs| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| "flag"), Some(flag)).unwrap())
+            });
+
+            assert!(cs.is_satisfied());
+            // flag booleanity, square, double and the selection
+            assert_eq!(cs.num_constraints(), 1 + 1 + 1 + 1);
+
+            let expected = if flag { "49" } else { "14" };
+            assert_eq!(value.unwrap(), Fr::from_str(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_if_then_else_constant_flag() {
+        let (cs, value) = square_or_double(|_| Boolean::constant(true));
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1);
+        assert_eq!(value.unwrap(), Fr::from_str("49").unwrap());
+
+        let (cs, value) = square_or_double(|_| Boolean::constant(false));
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1);
+        assert_eq!(value.unwrap(), Fr::from_str("14").unwrap());
+    }
+}
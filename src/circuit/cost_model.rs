@@ -0,0 +1,369 @@
+//! Backend-specific cost of constraints.
+//!
+//! All gadgets emit R1CS constraints, but the backends that prove them
+//! don't pay the same for each: a PLONK-style arithmetization needs
+//! extra gates for long linear combinations and may support lookups,
+//! while in R1CS every constraint costs one row whatever its shape.
+//! `CostEstimator` wraps a constraint system, attaches a `CostModel` to
+//! it and sums the estimated cost of the forwarded constraints.
+//! Gadgets that can synthesize a check in several ways query the model
+//! through `HasCostModel` on the root of their constraint system.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::Field;
+
+use bellman::{
+    LinearCombination,
+    SynthesisError,
+    ConstraintSystem,
+    Variable,
+    Index
+};
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+pub trait CostModel {
+    /// Cost of a linear constraint over `num_terms` variables.
+    fn linear(&self, num_terms: usize) -> usize;
+
+    /// Cost of `A * B = C` with the given number of variables in every
+    /// linear combination.
+    fn multiplication(&self, a_terms: usize, b_terms: usize, c_terms: usize) -> usize;
+
+    /// Cost of constraining one variable to be boolean.
+    fn boolean(&self) -> usize;
+
+    /// Cost of one lookup into a table of `2^table_bits` entries, or
+    /// `None` if the backend has no lookups.
+    fn lookup(&self, table_bits: usize) -> Option<usize>;
+
+    /// Cost of a range check of `num_bits` bits by decomposition into
+    /// booleans.
+    fn range_check_by_decomposition(&self, num_bits: usize) -> usize {
+        num_bits * self.boolean() + self.linear(num_bits + 1)
+    }
+
+    /// Cost of a range check of `num_bits` bits by lookups of chunks of
+    /// `table_bits` bits.
+    fn range_check_by_lookup(&self, num_bits: usize, table_bits: usize) -> Option<usize> {
+        assert!(table_bits > 0);
+
+        let num_chunks = (num_bits + table_bits - 1) / table_bits;
+        self.lookup(table_bits).map(|cost| num_chunks * cost + self.linear(num_chunks + 1))
+    }
+}
+
+/// Every constraint is one row, there are no lookups.
+#[derive(Clone, Copy, Debug)]
+pub struct R1csCost;
+
+impl CostModel for R1csCost {
+    fn linear(&self, _num_terms: usize) -> usize {
+        1
+    }
+
+    fn multiplication(&self, _a_terms: usize, _b_terms: usize, _c_terms: usize) -> usize {
+        1
+    }
+
+    fn boolean(&self) -> usize {
+        1
+    }
+
+    fn lookup(&self, _table_bits: usize) -> Option<usize> {
+        None
+    }
+}
+
+/// Gates of `width` wires, one of them the output, and optionally a
+/// lookup argument with one gate per lookup. A linear combination with
+/// more terms than fit into a gate is accumulated by addition gates.
+#[derive(Clone, Copy, Debug)]
+pub struct PlonkCost {
+    pub width: usize,
+    pub lookups: bool
+}
+
+impl PlonkCost {
+    /// Addition gates needed to reduce `num_terms` variables to one,
+    /// every gate replaces `width - 1` terms by its output.
+    fn accumulation(&self, num_terms: usize) -> usize {
+        assert!(self.width >= 3);
+
+        if num_terms <= 1 {
+            0
+        } else {
+            (num_terms - 1 + self.width - 3) / (self.width - 2)
+        }
+    }
+}
+
+impl CostModel for PlonkCost {
+    fn linear(&self, num_terms: usize) -> usize {
+        assert!(self.width >= 3);
+
+        // additions down to `width` terms, which fit into the last gate
+        if num_terms <= self.width {
+            1
+        } else {
+            1 + (num_terms - 3) / (self.width - 2)
+        }
+    }
+
+    fn multiplication(&self, a_terms: usize, b_terms: usize, c_terms: usize) -> usize {
+        1 + self.accumulation(a_terms) + self.accumulation(b_terms) + self.accumulation(c_terms)
+    }
+
+    fn boolean(&self) -> usize {
+        1
+    }
+
+    fn lookup(&self, _table_bits: usize) -> Option<usize> {
+        if self.lookups {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeCheckStrategy {
+    Decomposition,
+    Lookup { table_bits: usize }
+}
+
+/// The cheapest way to check that a value has at most `num_bits` bits,
+/// with lookup tables of at most `max_table_bits` bits.
+pub fn range_check_strategy<M: CostModel>(model: &M, num_bits: usize, max_table_bits: usize) -> RangeCheckStrategy {
+    let mut best = (model.range_check_by_decomposition(num_bits), RangeCheckStrategy::Decomposition);
+
+    for table_bits in 1..(max_table_bits + 1) {
+        if let Some(cost) = model.range_check_by_lookup(num_bits, table_bits) {
+            if cost < best.0 {
+                best = (cost, RangeCheckStrategy::Lookup { table_bits: table_bits });
+            }
+        }
+    }
+
+    best.1
+}
+
+/// Constraint systems that know the cost model of their backend.
+pub trait HasCostModel {
+    type Model: CostModel;
+
+    fn cost_model(&self) -> &Self::Model;
+}
+
+/// Number of variables other than `ONE` in `lc`, and whether it is a
+/// constant.
+fn shape<E: Engine>(lc: &LinearCombination<E>) -> (usize, bool) {
+    let mut terms: BTreeMap<(bool, usize), E::Fr> = BTreeMap::new();
+    for &(var, ref coeff) in lc.as_ref() {
+        let key = match var.get_unchecked() {
+            Index::Input(i) => (true, i),
+            Index::Aux(i) => (false, i)
+        };
+
+        terms.entry(key).or_insert(E::Fr::zero()).add_assign(coeff);
+    }
+
+    let num_terms = terms.iter().filter(|&(key, coeff)| *key != (true, 0) && !coeff.is_zero()).count();
+
+    (num_terms, num_terms == 0)
+}
+
+/// Constraint system adapter that estimates the cost of the constraints
+/// it forwards under a `CostModel`.
+///
+/// A constraint is linear if `A` or `B` is a constant, and boolean if it
+/// is `a * (1 - a) = 0` for a single variable `a`.
+pub struct CostEstimator<E: Engine, CS: ConstraintSystem<E>, M: CostModel> {
+    cs: CS,
+    model: M,
+    cost: usize,
+    _marker: PhantomData<E>
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>, M: CostModel> CostEstimator<E, CS, M> {
+    pub fn new(cs: CS, model: M) -> Self {
+        CostEstimator {
+            cs: cs,
+            model: model,
+            cost: 0,
+            _marker: PhantomData
+        }
+    }
+
+    /// Estimated cost of the constraints enforced so far.
+    pub fn cost(&self) -> usize {
+        self.cost
+    }
+
+    pub fn into_inner(self) -> CS {
+        self.cs
+    }
+
+    fn is_boolean(a: &LinearCombination<E>, b: &LinearCombination<E>, c: &LinearCombination<E>) -> bool {
+        if shape(c) != (0, true) {
+            return false;
+        }
+
+        // a + b = 1 with a single variable in each
+        let sum = a.clone() + b;
+        let mut constant = E::Fr::zero();
+        for &(var, ref coeff) in sum.as_ref() {
+            if let Index::Input(0) = var.get_unchecked() {
+                constant.add_assign(coeff);
+            }
+        }
+
+        shape(a).0 == 1 && shape(b).0 == 1 && shape(&sum).1 && constant == E::Fr::one()
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>, M: CostModel> HasCostModel for CostEstimator<E, CS, M> {
+    type Model = M;
+
+    fn cost_model(&self) -> &M {
+        &self.model
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>, M: CostModel> ConstraintSystem<E> for CostEstimator<E, CS, M> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.cs.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        annotation: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        let (a_terms, a_is_constant) = shape(&a);
+        let (b_terms, b_is_constant) = shape(&b);
+        let (c_terms, _) = shape(&c);
+
+        self.cost += if a_is_constant || b_is_constant {
+            // the terms of `c` may overlap the others, which is ignored
+            self.model.linear(a_terms + b_terms + c_terms)
+        } else if Self::is_boolean(&a, &b, &c) {
+            self.model.boolean()
+        } else {
+            self.model.multiplication(a_terms, b_terms, c_terms)
+        };
+
+        self.cs.enforce(annotation, |_| a, |_| b, |_| c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.cs.get_root().push_namespace(name_fn);
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        self.cs.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bls12_381::{Bls12, Fr};
+    use bellman::ConstraintSystem;
+    use ::circuit::test::*;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use ::circuit::num::AllocatedNum;
+    use ::circuit::multipack::pack_into_inputs;
+    use super::*;
+
+    /// A product, 16 booleans and their packing into an input, which is
+    /// a linear constraint over 17 variables.
+    fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS) {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(rng.gen::<Fr>())).unwrap();
+        a.square(cs.namespace(|| "a squared")).unwrap();
+
+        let bits: Vec<Boolean> = (0..16).map(|i| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(rng.gen())).unwrap())
+        }).collect();
+
+        pack_into_inputs(cs.namespace(|| "pack"), &bits).unwrap();
+    }
+
+    #[test]
+    fn test_cost_estimator() {
+        let mut cs = CostEstimator::new(TestConstraintSystem::<Bls12>::new(), R1csCost);
+        synthesize(&mut cs);
+        let num_constraints = cs.into_inner().num_constraints();
+        assert_eq!(num_constraints, 1 + 16 + 1);
+
+        let mut cs = CostEstimator::new(TestConstraintSystem::<Bls12>::new(), R1csCost);
+        synthesize(&mut cs);
+        assert_eq!(cs.cost(), num_constraints);
+
+        let model = PlonkCost { width: 3, lookups: false };
+        let mut cs = CostEstimator::new(TestConstraintSystem::<Bls12>::new(), model);
+        synthesize(&mut cs);
+        assert!(cs.into_inner().is_satisfied());
+
+        let mut cs = CostEstimator::new(TestConstraintSystem::<Bls12>::new(), model);
+        synthesize(&mut cs);
+        // 17 terms in gates of 3 wires take 14 additions and a final gate
+        assert_eq!(cs.cost(), 1 + 16 + model.linear(17));
+        assert_eq!(model.linear(17), 15);
+        assert_eq!(cs.cost_model().width, 3);
+    }
+
+    #[test]
+    fn test_range_check_strategy() {
+        assert_eq!(range_check_strategy(&R1csCost, 64, 16), RangeCheckStrategy::Decomposition);
+
+        let model = PlonkCost { width: 4, lookups: true };
+        assert_eq!(range_check_strategy(&model, 64, 16), RangeCheckStrategy::Lookup { table_bits: 16 });
+
+        let model = PlonkCost { width: 4, lookups: false };
+        assert_eq!(range_check_strategy(&model, 64, 16), RangeCheckStrategy::Decomposition);
+    }
+}
@@ -0,0 +1,240 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::num::{
+    AllocatedNum
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+/// Zero-padded decimal ASCII representation of a number.
+pub struct DecimalAscii {
+    /// ASCII codes of the digits, most significant digit first.
+    /// Each byte is encoded as 8 bits in big-endian order, the same
+    /// way the SHA256 gadget consumes its input.
+    pub bytes: Vec<Boolean>,
+    /// `leading_zeros[i]` is true if the digit number `i` is
+    /// a padding zero. The last digit is never marked as padding,
+    /// so zero is represented by a single "0".
+    pub leading_zeros: Vec<Boolean>
+}
+
+/// Returns decimal digits of the field element, least significant first.
+fn decimal_digits<F: PrimeField>(value: F, num_digits: usize) -> Vec<u8> {
+    let mut limbs: Vec<u64> = value.into_repr().as_ref().to_vec();
+    let mut digits = Vec::with_capacity(num_digits);
+
+    for _ in 0..num_digits {
+        // long division of the little-endian limbs by 10
+        let mut remainder = 0u128;
+        for limb in limbs.iter_mut().rev() {
+            let current = (remainder << 64) | (*limb as u128);
+            *limb = (current / 10) as u64;
+            remainder = current % 10;
+        }
+
+        digits.push(remainder as u8);
+    }
+
+    digits
+}
+
+/// Decomposes `num` into `num_digits` decimal digits and returns their
+/// ASCII encoding. The system is unsatisfiable if `num` doesn't fit
+/// into `num_digits` decimal digits.
+pub fn into_decimal_ascii<E, CS>(
+    mut cs: CS,
+    num: &AllocatedNum<E>,
+    num_digits: usize
+) -> Result<DecimalAscii, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(num_digits > 0);
+    // every digit takes 4 bits, so 10^num_digits never wraps around the modulus
+    assert!(num_digits * 4 <= E::Fr::CAPACITY as usize);
+
+    let digit_values: Vec<Option<u8>> = match num.get_value() {
+        Some(value) => {
+            let mut tmp = decimal_digits(value, num_digits);
+            tmp.reverse();
+
+            tmp.into_iter().map(|d| Some(d)).collect()
+        },
+        None => vec![None; num_digits]
+    };
+
+    let mut ten = E::Fr::one();
+    ten.double();
+    ten.double();
+    ten.add_assign(&E::Fr::one());
+    ten.double();
+
+    let mut packed = LinearCombination::<E>::zero();
+    let mut bytes = vec![];
+    let mut leading_zeros = vec![];
+    let mut still_leading = Boolean::constant(true);
+
+    for (i, digit) in digit_values.into_iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("digit {}", i));
+
+        // most significant bit first
+        let mut bits = vec![];
+        for j in (0..4).rev() {
+            let bit = AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", j)),
+                digit.map(|d| (d >> j) & 1 == 1)
+            )?;
+
+            bits.push(bit);
+        }
+
+        // digit is at most 9 = 0b1001, so the most significant bit
+        // can't be set together with any of the two middle bits
+        cs.enforce(
+            || "digit range check 1",
+            |lc| lc + bits[0].get_variable(),
+            |lc| lc + bits[1].get_variable(),
+            |lc| lc
+        );
+        cs.enforce(
+            || "digit range check 2",
+            |lc| lc + bits[0].get_variable(),
+            |lc| lc + bits[2].get_variable(),
+            |lc| lc
+        );
+
+        let mut digit_lc = LinearCombination::<E>::zero();
+        let mut coeff = E::Fr::one();
+        for bit in bits.iter().rev() {
+            digit_lc = digit_lc + (coeff, bit.get_variable());
+            coeff.double();
+        }
+
+        // Horner's scheme, starting from the most significant digit
+        let mut shifted = LinearCombination::<E>::zero();
+        for &(var, ref c) in packed.as_ref() {
+            let mut c = *c;
+            c.mul_assign(&ten);
+            shifted = shifted + (c, var);
+        }
+        packed = shifted + &digit_lc;
+
+        let bits: Vec<Boolean> = bits.into_iter().map(|b| Boolean::from(b)).collect();
+
+        if i != num_digits - 1 {
+            let hi_zero = Boolean::and(
+                cs.namespace(|| "high bits are zero"),
+                &bits[0].not(),
+                &bits[1].not()
+            )?;
+            let lo_zero = Boolean::and(
+                cs.namespace(|| "low bits are zero"),
+                &bits[2].not(),
+                &bits[3].not()
+            )?;
+            let is_zero = Boolean::and(
+                cs.namespace(|| "digit is zero"),
+                &hi_zero,
+                &lo_zero
+            )?;
+
+            still_leading = Boolean::and(
+                cs.namespace(|| "is leading zero"),
+                &still_leading,
+                &is_zero
+            )?;
+
+            leading_zeros.push(still_leading.clone());
+        } else {
+            leading_zeros.push(Boolean::constant(false));
+        }
+
+        // ASCII code of the digit is 0x30 + digit
+        bytes.push(Boolean::constant(false));
+        bytes.push(Boolean::constant(false));
+        bytes.push(Boolean::constant(true));
+        bytes.push(Boolean::constant(true));
+        bytes.extend(bits);
+    }
+
+    cs.enforce(
+        || "packing constraint",
+        |lc| lc,
+        |lc| lc,
+        |lc| lc + &packed - num.get_variable()
+    );
+
+    Ok(DecimalAscii {
+        bytes: bytes,
+        leading_zeros: leading_zeros
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use ::circuit::test::*;
+    use super::super::num::AllocatedNum;
+    use super::into_decimal_ascii;
+
+    fn bits_into_string(bits: &[super::super::boolean::Boolean]) -> String {
+        let bytes: Vec<u8> = bits.chunks(8).map(|byte| {
+            byte.iter().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+        }).collect();
+
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_decimal_ascii() {
+        for &(value, expected, padding) in [
+            ("12345", "00012345", 3),
+            ("0", "00000000", 7),
+            ("99999999", "99999999", 0),
+            ("10000000", "10000000", 0),
+            ("907", "00000907", 5)
+        ].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fr::from_str(value).unwrap())).unwrap();
+            let ascii = into_decimal_ascii(cs.namespace(|| "to ascii"), &num, 8).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(bits_into_string(&ascii.bytes), expected);
+
+            let leading: Vec<bool> = ascii.leading_zeros.iter().map(|b| b.get_value().unwrap()).collect();
+            for (i, l) in leading.into_iter().enumerate() {
+                assert_eq!(l, i < padding);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_ascii_overflow() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fr::from_str("100000000").unwrap())).unwrap();
+        into_decimal_ascii(cs.namespace(|| "to ascii"), &num, 8).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,255 @@
+//! Lookup tables with allocated contents, e.g. points precomputed in
+//! the circuit for a variable-base multiplication.
+//!
+//! Table entries and reads are both kept as `(index, is_read, values)`
+//! records. `finalize` witnesses the records sorted by index with the
+//! entry of every index in front of its reads, proves this is a
+//! permutation with `as_waksman::prove_shuffle_tuples`, and enforces
+//! that every read has the index and the values of the record before
+//! it. Following the reads back, every read equals the entry of its
+//! index, and the first record must be an entry. No range checks are
+//! needed, so every record costs `width + 1` constraints plus its share
+//! of the permutation network.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::as_waksman::prove_shuffle_tuples;
+
+use super::sorting::sorting_permutation;
+
+use util::fr_from_u64;
+
+pub struct DynamicTable<E: Engine> {
+    width: usize,
+    entries: Vec<Vec<AllocatedNum<E>>>,
+    /// The index and the values of every read.
+    reads: Vec<(AllocatedNum<E>, Vec<AllocatedNum<E>>)>
+}
+
+impl<E: Engine> DynamicTable<E> {
+    /// Makes an empty table with `width` values per entry.
+    pub fn new(width: usize) -> Self {
+        assert!(width > 0);
+
+        Self {
+            width,
+            entries: vec![],
+            reads: vec![]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn num_reads(&self) -> usize {
+        self.reads.len()
+    }
+
+    /// Appends an entry, returning its index.
+    pub fn push(&mut self, values: &[AllocatedNum<E>]) -> usize {
+        assert_eq!(values.len(), self.width);
+
+        self.entries.push(values.to_vec());
+
+        self.entries.len() - 1
+    }
+
+    /// Witnesses the entry at `index`. The read is only enforced to be
+    /// correct by `finalize`.
+    pub fn read<CS>(&mut self, mut cs: CS, index: &AllocatedNum<E>) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(!self.entries.is_empty(), "can't read from an empty table");
+
+        let entry = match index.get_value() {
+            Some(index) => {
+                let position = (0..self.entries.len()).position(|i| fr_from_u64::<E::Fr>(i as u64) == index);
+                Some(position.ok_or(SynthesisError::Unsatisfiable)?)
+            },
+            None => None
+        };
+
+        let mut values = vec![];
+        for i in 0..self.width {
+            let value = AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || {
+                let entry = entry.get()?;
+
+                Ok(*self.entries[*entry][i].get_value().get()?)
+            })?;
+
+            values.push(value);
+        }
+
+        self.reads.push((index.clone(), values.clone()));
+
+        Ok(values)
+    }
+
+    /// Enforces that all the reads returned the entries at their
+    /// indexes.
+    pub fn finalize<CS>(self, mut cs: CS) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        if self.reads.is_empty() {
+            return Ok(());
+        }
+
+        // the index and is_read of the entries are constants
+        let mut records: Vec<Vec<Num<E>>> = vec![];
+        for (i, values) in self.entries.into_iter().enumerate() {
+            let mut record = vec![
+                Num::constant(CS::one(), fr_from_u64(i as u64)),
+                Num::constant(CS::one(), E::Fr::zero())
+            ];
+            record.extend(values.into_iter().map(Num::from));
+            records.push(record);
+        }
+        for (index, values) in self.reads.into_iter() {
+            let mut record = vec![
+                Num::from(index),
+                Num::constant(CS::one(), E::Fr::one())
+            ];
+            record.extend(values.into_iter().map(Num::from));
+            records.push(record);
+        }
+
+        // sort by 2 * index + is_read, entries of an index go first
+        let keys: Vec<Option<E::Fr>> = records.iter().map(|r| {
+            let mut key = r[0].get_value()?;
+            key.double();
+            key.add_assign(&r[1].get_value()?);
+
+            Some(key)
+        }).collect();
+        let permutation = sorting_permutation::<E>(&keys);
+
+        let mut sorted_values = vec![None; records.len()];
+        for (i, record) in records.iter().enumerate() {
+            sorted_values[permutation.get(i)] = Some(record);
+        }
+
+        let mut sorted = vec![];
+        for (i, record) in sorted_values.into_iter().enumerate() {
+            let record = record.unwrap();
+            let mut sorted_record = vec![];
+            for (j, el) in record.iter().enumerate() {
+                let value = el.get_value();
+                sorted_record.push(AllocatedNum::alloc(
+                    cs.namespace(|| format!("sorted record {} element {}", i, j)),
+                    || Ok(*value.get()?)
+                )?);
+            }
+
+            sorted.push(sorted_record);
+        }
+
+        prove_shuffle_tuples(cs.namespace(|| "prove permutation"), &records, &sorted, &permutation)?;
+
+        cs.enforce(
+            || "first record is an entry",
+            |lc| lc + sorted[0][1].get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc
+        );
+
+        for (i, pair) in sorted.windows(2).enumerate() {
+            let (prev, cur) = (&pair[0], &pair[1]);
+
+            // a read has the index and the values of the previous record
+            for j in 0..(self.width + 1) {
+                let column = if j == 0 { 0 } else { j + 1 };
+
+                cs.enforce(
+                    || format!("record {} column {}", i + 1, column),
+                    |lc| lc + cur[1].get_variable(),
+                    |lc| lc + cur[column].get_variable() - prev[column].get_variable(),
+                    |lc| lc
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use super::*;
+
+    fn alloc_table<CS: ConstraintSystem<Bn256>>(mut cs: CS, values: &[(Fr, Fr)]) -> DynamicTable<Bn256> {
+        let mut table = DynamicTable::new(2);
+        for (i, &(x, y)) in values.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("entry {}", i));
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+            let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(y)).unwrap();
+
+            assert_eq!(table.push(&[x, y]), i);
+        }
+
+        table
+    }
+
+    #[test]
+    fn test_dynamic_table() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let values: Vec<(Fr, Fr)> = (0..8).map(|_| (rng.gen(), rng.gen())).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut table = alloc_table(cs.namespace(|| "table"), &values);
+
+        for (i, &index) in [3u64, 0, 7, 3, 5].iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("read {}", i));
+            let index_num = AllocatedNum::alloc(cs.namespace(|| "index"), || Ok(fr(index))).unwrap();
+            let read = table.read(cs.namespace(|| "read"), &index_num).unwrap();
+
+            assert_eq!(read[0].get_value().unwrap(), values[index as usize].0);
+            assert_eq!(read[1].get_value().unwrap(), values[index as usize].1);
+        }
+
+        table.finalize(cs.namespace(|| "finalize")).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("read 2/read/value 1/num", values[6].1);
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_read_out_of_range() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let values: Vec<(Fr, Fr)> = (0..4).map(|_| (rng.gen(), rng.gen())).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut table = alloc_table(cs.namespace(|| "table"), &values);
+
+        let index = AllocatedNum::alloc(cs.namespace(|| "index"), || Ok(fr(4))).unwrap();
+        assert!(table.read(cs.namespace(|| "read"), &index).is_err());
+    }
+}
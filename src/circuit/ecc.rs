@@ -2,7 +2,7 @@ use bellman::pairing::{
     Engine,
 };
 
-use bellman::pairing::ff::{Field};
+use bellman::pairing::ff::{Field, PrimeField};
 
 use bellman::{
     SynthesisError,
@@ -15,6 +15,7 @@ use super::{
 
 use super::num::{
     AllocatedNum,
+    BitsCache,
     Num
 };
 
@@ -22,7 +23,8 @@ use ::jubjub::{
     edwards,
     JubjubEngine,
     JubjubParams,
-    FixedGenerators
+    FixedGenerators,
+    PrimeOrder
 };
 
 use super::lookup::{
@@ -31,6 +33,8 @@ use super::lookup::{
 
 use super::boolean::Boolean;
 
+use super::scalar::ScalarNum;
+
 #[derive(Clone)]
 pub struct EdwardsPoint<E: Engine> {
     x: AllocatedNum<E>,
@@ -40,19 +44,124 @@ pub struct EdwardsPoint<E: Engine> {
 /// Perform a fixed-base scalar multiplication with
 /// `by` being in little-endian bit order.
 pub fn fixed_base_multiplication<E, CS>(
-    mut cs: CS,
+    cs: CS,
     base: FixedGenerators,
     by: &[Boolean],
     params: &E::Params
 ) -> Result<EdwardsPoint<E>, SynthesisError>
     where CS: ConstraintSystem<E>,
           E: JubjubEngine
+{
+    fixed_base_multiplication_with_initial(cs, base, by, None, params)
+}
+
+/// Computes `point + by * base` for a fixed base, adding the
+/// windows of the multiplication directly into `point`.
+pub fn fixed_base_multiplication_and_add<E, CS>(
+    cs: CS,
+    base: FixedGenerators,
+    by: &[Boolean],
+    point: &EdwardsPoint<E>,
+    params: &E::Params
+) -> Result<EdwardsPoint<E>, SynthesisError>
+    where CS: ConstraintSystem<E>,
+          E: JubjubEngine
+{
+    fixed_base_multiplication_with_initial(cs, base, by, Some(point.clone()), params)
+}
+
+fn fixed_base_multiplication_with_initial<E, CS>(
+    cs: CS,
+    base: FixedGenerators,
+    by: &[Boolean],
+    initial: Option<EdwardsPoint<E>>,
+    params: &E::Params
+) -> Result<EdwardsPoint<E>, SynthesisError>
+    where CS: ConstraintSystem<E>,
+          E: JubjubEngine
+{
+    windowed_multiplication(cs, params.circuit_generators(base), by, initial, params)
+}
+
+/// A generator other than the `FixedGenerators` of the parameters,
+/// e.g. one from `group_hash::derive_generator`, registered for
+/// fixed-base multiplication in the circuit.
+#[derive(Clone)]
+pub struct FixedBase<E: JubjubEngine> {
+    point: edwards::Point<E, PrimeOrder>,
+    windows: Vec<Vec<(E::Fr, E::Fr)>>
+}
+
+impl<E: JubjubEngine> FixedBase<E> {
+    /// Precomputes the 3-bit window tables of `point`, the same as the
+    /// parameters do for the `FixedGenerators`.
+    pub fn new(point: edwards::Point<E, PrimeOrder>, params: &E::Params) -> Self {
+        let mut windows = vec![];
+        let mut gen = point.clone();
+        for _ in 0..params.fixed_base_chunks_per_generator() {
+            let mut coeffs = vec![(E::Fr::zero(), E::Fr::one())];
+            let mut g = gen.clone();
+            for _ in 0..7 {
+                coeffs.push(g.into_xy());
+                g = g.add(&gen, params);
+            }
+            windows.push(coeffs);
+
+            // gen = gen * 8
+            gen = g;
+        }
+
+        FixedBase {
+            point: point,
+            windows: windows
+        }
+    }
+
+    pub fn point(&self) -> &edwards::Point<E, PrimeOrder> {
+        &self.point
+    }
+
+    /// Same as `fixed_base_multiplication` for this base.
+    pub fn mul<CS>(
+        &self,
+        cs: CS,
+        by: &[Boolean],
+        params: &E::Params
+    ) -> Result<EdwardsPoint<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        windowed_multiplication(cs, &self.windows, by, None, params)
+    }
+
+    /// Same as `fixed_base_multiplication_and_add` for this base.
+    pub fn mul_and_add<CS>(
+        &self,
+        cs: CS,
+        by: &[Boolean],
+        point: &EdwardsPoint<E>,
+        params: &E::Params
+    ) -> Result<EdwardsPoint<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        windowed_multiplication(cs, &self.windows, by, Some(point.clone()), params)
+    }
+}
+
+fn windowed_multiplication<E, CS>(
+    mut cs: CS,
+    windows: &[Vec<(E::Fr, E::Fr)>],
+    by: &[Boolean],
+    initial: Option<EdwardsPoint<E>>,
+    params: &E::Params
+) -> Result<EdwardsPoint<E>, SynthesisError>
+    where CS: ConstraintSystem<E>,
+          E: JubjubEngine
 {
     // Represents the result of the multiplication
-    let mut result = None;
+    let mut result = initial;
 
     for (i, (chunk, window)) in by.chunks(3)
-                                  .zip(params.circuit_generators(base).iter())
+                                  .zip(windows.iter())
                                   .enumerate()
     {
         let chunk_a = chunk.get(0).map(|e| e.clone()).unwrap_or(Boolean::constant(false));
@@ -157,6 +266,31 @@ impl<E: JubjubEngine> EdwardsPoint<E> {
         Ok(tmp)
     }
 
+    /// This converts the point into the encoding of
+    /// `edwards::Point::write`: the little-endian bytes of y
+    /// with the sign of x in the most significant bit. The bits
+    /// of every byte are least significant first.
+    pub fn to_bytes_le<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(E::Fr::NUM_BITS <= 255);
+
+        let x = self.x.into_bits_le_strict(
+            cs.namespace(|| "unpack x")
+        )?;
+
+        let mut bytes = self.y.to_bytes_le(
+            cs.namespace(|| "unpack y")
+        )?;
+
+        *bytes.last_mut().unwrap() = x[0].clone();
+
+        Ok(bytes)
+    }
+
     /// This 'witnesses' a point inside the constraint system.
     /// It guarantees the point is on the curve.
     pub fn witness<Order, CS>(
@@ -247,6 +381,24 @@ impl<E: JubjubEngine> EdwardsPoint<E> {
         })
     }
 
+    /// Returns `a` if condition is true, and `b` otherwise.
+    pub fn select<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let x = AllocatedNum::conditionally_select(cs.namespace(|| "x"), &a.x, &b.x, condition)?;
+        let y = AllocatedNum::conditionally_select(cs.namespace(|| "y"), &a.y, &b.y, condition)?;
+
+        Ok(EdwardsPoint {
+            x,
+            y
+        })
+    }
+
     /// Performs a scalar multiplication of this twisted Edwards
     /// point by a scalar represented as a sequence of booleans
     /// in little-endian bit order.
@@ -302,6 +454,35 @@ impl<E: JubjubEngine> EdwardsPoint<E> {
         Ok(result.get()?.clone())
     }
 
+    /// Same as `mul` for a reduced scalar.
+    pub fn mul_scalar<CS>(
+        &self,
+        cs: CS,
+        by: &ScalarNum<E>,
+        params: &E::Params
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        self.mul(cs, by.bits(), params)
+    }
+
+    /// Same as `mul` for the bits of an allocated number, taken from
+    /// the `cache`, so a number multiplying several points is only
+    /// decomposed once.
+    pub fn mul_num<CS>(
+        &self,
+        mut cs: CS,
+        by: &AllocatedNum<E>,
+        cache: &mut BitsCache,
+        params: &E::Params
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let bits = cache.into_bits_le_strict(cs.namespace(|| "scalar bits"), by)?;
+
+        self.mul(cs.namespace(|| "multiplication"), &bits, params)
+    }
+
     pub fn interpret<CS>(
         mut cs: CS,
         x: &AllocatedNum<E>,
@@ -767,8 +948,11 @@ mod test {
         MontgomeryPoint,
         EdwardsPoint,
         AllocatedNum,
+        BitsCache,
+        FixedBase,
         fixed_base_multiplication
     };
+    use ::group_hash::derive_generator;
     use super::super::boolean::{
         Boolean,
         AllocatedBit
@@ -908,6 +1092,40 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_derived_fixed_base_multiplication() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let base = FixedBase::new(derive_generator::<Bls12>(b"test generator", params), params);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let s = Fs::rand(rng);
+        let q = base.point().mul(s, params);
+        let (x1, y1) = q.into_xy();
+
+        let mut s_bits = BitIterator::new(s.into_repr()).collect::<Vec<_>>();
+        s_bits.reverse();
+        s_bits.truncate(Fs::NUM_BITS as usize);
+
+        let s_bits = s_bits.into_iter()
+                           .enumerate()
+                           .map(|(i, b)| AllocatedBit::alloc(cs.namespace(|| format!("scalar bit {}", i)), Some(b)).unwrap())
+                           .map(|v| Boolean::from(v))
+                           .collect::<Vec<_>>();
+
+        let q = base.mul(cs.namespace(|| "multiplication"), &s_bits, params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(q.x.get_value().unwrap(), x1);
+        assert_eq!(q.y.get_value().unwrap(), y1);
+
+        // the same tables as the parameters make for their generators
+        let generator = FixedBase::new(params.generator(FixedGenerators::NoteCommitmentRandomness).clone(), params);
+        assert!(generator.windows[..] == params.circuit_generators(FixedGenerators::NoteCommitmentRandomness)[..]);
+    }
+
     #[test]
     fn test_edwards_multiplication() {
         let params = &JubjubBls12::new();
@@ -965,6 +1183,58 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_edwards_multiplication_by_cached_num() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let s = Fs::rand(rng);
+        let mut s_repr = <Fr as PrimeField>::Repr::default();
+        s_repr.as_mut().copy_from_slice(s.into_repr().as_ref());
+        let s_value = Fr::from_repr(s_repr).unwrap();
+
+        let decomposition_cost = {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let s_num = AllocatedNum::alloc(cs.namespace(|| "scalar"), || Ok(s_value)).unwrap();
+            s_num.into_bits_le_strict(cs.namespace(|| "decompose")).unwrap();
+
+            cs.num_constraints()
+        };
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut cache = BitsCache::new();
+        let s_num = AllocatedNum::alloc(cs.namespace(|| "scalar"), || Ok(s_value)).unwrap();
+
+        let mut costs = vec![];
+        for i in 0..2 {
+            let p = edwards::Point::<Bls12, _>::rand(rng, params);
+            let (x0, y0) = p.into_xy();
+            let (x1, y1) = p.mul(s, params).into_xy();
+
+            let p = EdwardsPoint {
+                x: AllocatedNum::alloc(cs.namespace(|| format!("x{}", i)), || Ok(x0)).unwrap(),
+                y: AllocatedNum::alloc(cs.namespace(|| format!("y{}", i)), || Ok(y0)).unwrap()
+            };
+
+            let before = cs.num_constraints();
+            let q = p.mul_num(
+                cs.namespace(|| format!("scalar mul {}", i)),
+                &s_num,
+                &mut cache,
+                params
+            ).unwrap();
+            costs.push(cs.num_constraints() - before);
+
+            assert_eq!(q.x.get_value().unwrap(), x1);
+            assert_eq!(q.y.get_value().unwrap(), y1);
+        }
+
+        assert!(cs.is_satisfied());
+
+        // the second multiplication reuses the bits of the scalar
+        assert_eq!(costs[1], costs[0] - decomposition_cost);
+    }
+
     #[test]
     fn test_conditionally_select() {
         let params = &JubjubBls12::new();
@@ -1099,6 +1369,30 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_to_bytes_le() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let p = edwards::Point::<Bls12, _>::rand(rng, params);
+            let mut expected = vec![];
+            p.write(&mut expected).unwrap();
+
+            let q = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p), params).unwrap();
+            let bits = q.to_bytes_le(cs.namespace(|| "to bytes")).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let bytes: Vec<u8> = bits.chunks(8).map(|byte| {
+                byte.iter().rev().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+            }).collect();
+            assert_eq!(bytes, expected);
+        }
+    }
+
     #[test]
     fn test_edwards_doubling() {
         let params = &JubjubBls12::new();
@@ -1233,6 +1527,7 @@ mod baby_test {
         MontgomeryPoint,
         EdwardsPoint,
         AllocatedNum,
+        BitsCache,
         fixed_base_multiplication
     };
     use super::super::boolean::{
@@ -1431,6 +1726,58 @@ mod baby_test {
         }
     }
 
+    #[test]
+    fn test_edwards_multiplication_by_cached_num() {
+        let params = &AltJubjubBn256::new();
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let s = Fs::rand(rng);
+        let mut s_repr = <Fr as PrimeField>::Repr::default();
+        s_repr.as_mut().copy_from_slice(s.into_repr().as_ref());
+        let s_value = Fr::from_repr(s_repr).unwrap();
+
+        let decomposition_cost = {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let s_num = AllocatedNum::alloc(cs.namespace(|| "scalar"), || Ok(s_value)).unwrap();
+            s_num.into_bits_le_strict(cs.namespace(|| "decompose")).unwrap();
+
+            cs.num_constraints()
+        };
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut cache = BitsCache::new();
+        let s_num = AllocatedNum::alloc(cs.namespace(|| "scalar"), || Ok(s_value)).unwrap();
+
+        let mut costs = vec![];
+        for i in 0..2 {
+            let p = edwards::Point::<Bn256, _>::rand(rng, params);
+            let (x0, y0) = p.into_xy();
+            let (x1, y1) = p.mul(s, params).into_xy();
+
+            let p = EdwardsPoint {
+                x: AllocatedNum::alloc(cs.namespace(|| format!("x{}", i)), || Ok(x0)).unwrap(),
+                y: AllocatedNum::alloc(cs.namespace(|| format!("y{}", i)), || Ok(y0)).unwrap()
+            };
+
+            let before = cs.num_constraints();
+            let q = p.mul_num(
+                cs.namespace(|| format!("scalar mul {}", i)),
+                &s_num,
+                &mut cache,
+                params
+            ).unwrap();
+            costs.push(cs.num_constraints() - before);
+
+            assert_eq!(q.x.get_value().unwrap(), x1);
+            assert_eq!(q.y.get_value().unwrap(), y1);
+        }
+
+        assert!(cs.is_satisfied());
+
+        // the second multiplication reuses the bits of the scalar
+        assert_eq!(costs[1], costs[0] - decomposition_cost);
+    }
+
     #[test]
     fn test_conditionally_select() {
         let params = &AltJubjubBn256::new();
@@ -1565,6 +1912,30 @@ mod baby_test {
         }
     }
 
+    #[test]
+    fn test_to_bytes_le() {
+        let params = &AltJubjubBn256::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let p = edwards::Point::<Bn256, _>::rand(rng, params);
+            let mut expected = vec![];
+            p.write(&mut expected).unwrap();
+
+            let q = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p), params).unwrap();
+            let bits = q.to_bytes_le(cs.namespace(|| "to bytes")).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let bytes: Vec<u8> = bits.chunks(8).map(|byte| {
+                byte.iter().rev().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+            }).collect();
+            assert_eq!(bytes, expected);
+        }
+    }
+
     #[test]
     fn test_edwards_doubling() {
         let params = &AltJubjubBn256::new();
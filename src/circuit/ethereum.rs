@@ -0,0 +1,732 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    Variable
+};
+
+use super::Assignment;
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::keccak::{
+    keccak256,
+    keccak256_var
+};
+
+use super::rlp::{
+    RlpHeader,
+    RlpItem,
+    decode_header,
+    decode_list,
+    shift_bytes_left,
+    payload_into_bytes
+};
+
+use util::fr_from_u64;
+
+/// Number of items of a branch node, 16 children and a value.
+const BRANCH_ITEMS: usize = 17;
+
+/// Computes the path of a storage slot in the account storage trie,
+/// that is `keccak256(slot)`. The slot is given as 32 bytes in
+/// big-endian byte order, every byte in little-endian bit order.
+pub fn storage_trie_key<E, CS>(
+    cs: CS,
+    slot: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(slot.len(), 256);
+
+    keccak256(cs, slot)
+}
+
+/// Computes the storage slot of the `mapping` element with the
+/// given `key`, declared at the storage slot `slot`, that is
+/// `keccak256(key || slot)` following the Solidity storage layout.
+/// Both are given as 32 bytes words with the same encoding
+/// as in `storage_trie_key`.
+pub fn mapping_element_slot<E, CS>(
+    cs: CS,
+    key: &[Boolean],
+    slot: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(key.len(), 256);
+    assert_eq!(slot.len(), 256);
+
+    let mut preimage = key.to_vec();
+    preimage.extend_from_slice(slot);
+
+    keccak256(cs, &preimage)
+}
+
+/// Packs the bits, in little-endian order, into a number.
+fn pack<E: Engine>(one: Variable, bits: &[Boolean]) -> Num<E> {
+    let mut coeff = E::Fr::one();
+    let mut num = Num::zero();
+    for bit in bits.iter() {
+        num = num.add_bool_with_coeff(one, bit, coeff);
+        coeff.double();
+    }
+
+    num
+}
+
+/// Nibble `i` of the trie key, the high nibble of a byte first.
+fn nibble(key: &[Boolean], i: usize) -> &[Boolean] {
+    let offset = 8 * (i / 2) + if i % 2 == 0 { 4 } else { 0 };
+
+    &key[offset..(offset + 4)]
+}
+
+/// Keccak-256 of the RLP item that starts at the beginning of `node`
+/// and has the given header.
+fn node_hash<E, CS>(
+    cs: CS,
+    node: &[Boolean],
+    header: &RlpHeader<E>
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut length = header.header_length.clone();
+    length.add_assign(&header.payload_length);
+
+    keccak256_var(cs, node, &length)
+}
+
+/// Enforces that the `hash` is the `reference` given as the packings
+/// of its lower and upper 16 bytes, if the `condition` is set.
+fn enforce_reference<E, CS>(
+    mut cs: CS,
+    condition: &Boolean,
+    hash: &[Boolean],
+    reference: &(Num<E>, Num<E>)
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let one = CS::one();
+
+    for (i, (half, expected)) in hash.chunks(128).zip([&reference.0, &reference.1].iter()).enumerate() {
+        let half = pack::<E>(one, half);
+
+        cs.enforce(
+            || format!("half {}", i),
+            |_| condition.lc(one, E::Fr::one()),
+            |lc| lc + &half.lc(E::Fr::one()) - &expected.lc(E::Fr::one()),
+            |lc| lc
+        );
+    }
+
+    Ok(())
+}
+
+/// Decodes the branch `node` with the given header and returns whether
+/// it has a child at the index with little-endian bits `index_bits`,
+/// with the reference to the child. The children must be empty or
+/// hashes, and the value must be empty, which is always the case in
+/// a trie with keys of the same length. Any other node, in particular
+/// an extension node with its two items, is rejected.
+fn branch_child<E, CS>(
+    mut cs: CS,
+    node: &[Boolean],
+    header: &RlpHeader<E>,
+    index_bits: &[Boolean]
+) -> Result<(Boolean, (Num<E>, Num<E>)), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(node.len() % 8 == 0);
+
+    let one = CS::one();
+    let length = node.len() / 8;
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    Boolean::enforce_equal(
+        cs.namespace(|| "enforce list"),
+        &header.is_list,
+        &Boolean::constant(true)
+    )?;
+
+    // a branch takes more than 55 bytes, so the header takes two or
+    // three bytes, and the first item starts at one of these offsets
+    let h = &header.header_length;
+    cs.enforce(
+        || "long list header",
+        |lc| lc + &h.lc(E::Fr::one()) - (fr_from_u64(2), one),
+        |lc| lc + &h.lc(E::Fr::one()) - (fr_from_u64(3), one),
+        |lc| lc
+    );
+
+    let end_value = match (h.get_value(), header.payload_length.get_value()) {
+        (Some(mut end), Some(payload_length)) => {
+            end.add_assign(&payload_length);
+
+            Some(end)
+        },
+        _ => None
+    };
+
+    // end[j] is set iff the node ends at j
+    let mut end = vec![];
+    for j in 0..(length + 1) {
+        end.push(Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| format!("end {}", j)),
+            end_value.map(|end| end == fr_from_u64(j as u64))
+        )?));
+    }
+
+    cs.enforce(
+        || "single end",
+        |lc| end.iter().fold(lc, |lc, bit| lc + &bit.lc(one, E::Fr::one())),
+        |lc| lc + one,
+        |lc| lc + one
+    );
+    cs.enforce(
+        || "end position",
+        |lc| end.iter().enumerate().fold(lc, |lc, (j, bit)| lc + &bit.lc(one, fr_from_u64(j as u64))),
+        |lc| lc + one,
+        |lc| lc + &h.lc(E::Fr::one()) + &header.payload_length.lc(E::Fr::one())
+    );
+
+    let mut count = AllocatedNum::alloc(cs.namespace(|| "count 0"), || Ok(E::Fr::zero()))?;
+    cs.enforce(
+        || "count starts at zero",
+        |lc| lc + count.get_variable(),
+        |lc| lc + one,
+        |lc| lc
+    );
+
+    let packed_index = pack::<E>(one, index_bits);
+    let index = AllocatedNum::alloc(cs.namespace(|| "index"), || Ok(*packed_index.get_value().get()?))?;
+    cs.enforce(
+        || "pack index",
+        |lc| lc + index.get_variable() - &packed_index.lc(E::Fr::one()),
+        |lc| lc + one,
+        |lc| lc
+    );
+
+    // Every position tracks whether an item starts there: the first
+    // item starts right after the header, and every item is followed
+    // by the next one or by the end of the node. Items are either the
+    // empty string 0x80 or a 32 byte string 0xa0 || hash.
+    let mut empty_items: Vec<Boolean> = vec![];
+    let mut hash_items: Vec<Boolean> = vec![];
+    let mut selections = vec![];
+
+    for j in 0..(length + 1) {
+        let cs = &mut cs.namespace(|| format!("position {}", j));
+
+        // the first item starts right after the header, at 2 or 3,
+        // i.e. 3 - h and h - 2
+        let mut flow = Num::zero();
+        if j == 2 || j == 3 {
+            let mut header_length = h.clone();
+            let mut offset = fr_from_u64::<E::Fr>(5 - j as u64);
+            if j == 2 {
+                header_length.scale(minus_one);
+            } else {
+                offset.negate();
+            }
+
+            flow = Num::constant(one, offset);
+            flow.add_assign(&header_length);
+        }
+        if j >= 1 {
+            flow = flow.add_bool_with_coeff(one, &empty_items[j - 1], E::Fr::one());
+        }
+        if j >= 33 {
+            flow = flow.add_bool_with_coeff(one, &hash_items[j - 33], E::Fr::one());
+
+            // the value is empty
+            cs.enforce(
+                || "empty value",
+                |_| hash_items[j - 33].lc(one, E::Fr::one()),
+                |_| end[j].lc(one, E::Fr::one()),
+                |lc| lc
+            );
+        }
+
+        if j == length {
+            cs.enforce(
+                || "end of the last item",
+                |lc| lc + &end[j].lc(one, E::Fr::one()) - &flow.lc(E::Fr::one()),
+                |lc| lc + one,
+                |lc| lc
+            );
+
+            break;
+        }
+
+        let start_value = match (flow.get_value(), end[j].get_value()) {
+            (Some(flow), Some(end)) => Some(!flow.is_zero() && !end),
+            _ => None
+        };
+        let start = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "start"), start_value)?);
+
+        cs.enforce(
+            || "start or end",
+            |lc| lc + &start.lc(one, E::Fr::one()) + &end[j].lc(one, E::Fr::one()) - &flow.lc(E::Fr::one()),
+            |lc| lc + one,
+            |lc| lc
+        );
+
+        let prefix = &node[(8 * j)..(8 * (j + 1))];
+        let prefix_num = pack::<E>(one, prefix);
+
+        let empty_item = Boolean::and(cs.namespace(|| "empty item"), &start, &prefix[5].not())?;
+        let hash_item = Boolean::and(cs.namespace(|| "hash item"), &start, &prefix[5])?;
+
+        cs.enforce(
+            || "empty item prefix",
+            |_| empty_item.lc(one, E::Fr::one()),
+            |lc| lc + &prefix_num.lc(E::Fr::one()) - (fr_from_u64(0x80), one),
+            |lc| lc
+        );
+        cs.enforce(
+            || "hash item prefix",
+            |_| hash_item.lc(one, E::Fr::one()),
+            |lc| lc + &prefix_num.lc(E::Fr::one()) - (fr_from_u64(0xa0), one),
+            |lc| lc
+        );
+
+        if j + 33 > length {
+            Boolean::enforce_equal(cs.namespace(|| "hash fits"), &hash_item, &Boolean::constant(false))?;
+        }
+
+        let is_index = AllocatedNum::equals(cs.namespace(|| "is index"), &count, &index)?;
+        let selected = Boolean::and(cs.namespace(|| "selected"), &start, &is_index)?;
+
+        let next = AllocatedNum::alloc(cs.namespace(|| "count"), || {
+            let mut tmp = *count.get_value().get()?;
+            if *start.get_value().get()? {
+                tmp.add_assign(&E::Fr::one());
+            }
+
+            Ok(tmp)
+        })?;
+        cs.enforce(
+            || "count items",
+            |lc| lc + next.get_variable() - count.get_variable() - &start.lc(one, E::Fr::one()),
+            |lc| lc + one,
+            |lc| lc
+        );
+
+        let data: Vec<Boolean> = (8 * (j + 1)..8 * (j + 33)).map(|i| {
+            node.get(i).cloned().unwrap_or(Boolean::constant(false))
+        }).collect();
+
+        empty_items.push(empty_item);
+        hash_items.push(hash_item);
+        selections.push((selected, prefix[5].clone(), pack::<E>(one, &data[..128]), pack::<E>(one, &data[128..])));
+        count = next;
+    }
+
+    cs.enforce(
+        || "number of items",
+        |lc| lc + count.get_variable() - (fr_from_u64(BRANCH_ITEMS as u64), one),
+        |lc| lc + one,
+        |lc| lc
+    );
+
+    // the item at the index, if the witness is a well-formed branch
+    let selected = selections.iter().map(|s| s.0.get_value()).collect::<Option<Vec<_>>>().map(|selected| {
+        selected.iter().position(|&s| s).map(|j| &selections[j])
+    });
+
+    let present = Boolean::from(AllocatedBit::alloc(
+        cs.namespace(|| "present"),
+        selected.and_then(|s| s.map_or(Some(false), |s| s.1.get_value()))
+    )?);
+    let lo = AllocatedNum::alloc(cs.namespace(|| "child lower half"), || {
+        match *selected.get()? {
+            Some(s) => Ok(*s.2.get_value().get()?),
+            None => Ok(E::Fr::zero())
+        }
+    })?;
+    let hi = AllocatedNum::alloc(cs.namespace(|| "child upper half"), || {
+        match *selected.get()? {
+            Some(s) => Ok(*s.3.get_value().get()?),
+            None => Ok(E::Fr::zero())
+        }
+    })?;
+
+    for (j, &(ref selected, ref is_hash, ref data_lo, ref data_hi)) in selections.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("select position {}", j));
+
+        cs.enforce(
+            || "present",
+            |_| selected.lc(one, E::Fr::one()),
+            |lc| lc + &is_hash.lc(one, E::Fr::one()) - &present.lc(one, E::Fr::one()),
+            |lc| lc
+        );
+        cs.enforce(
+            || "lower half",
+            |_| selected.lc(one, E::Fr::one()),
+            |lc| lc + &data_lo.lc(E::Fr::one()) - lo.get_variable(),
+            |lc| lc
+        );
+        cs.enforce(
+            || "upper half",
+            |_| selected.lc(one, E::Fr::one()),
+            |lc| lc + &data_hi.lc(E::Fr::one()) - hi.get_variable(),
+            |lc| lc
+        );
+    }
+
+    Ok((present, (Num::from(lo), Num::from(hi))))
+}
+
+/// Decodes the `leaf` at the `depth` and returns whether its path is
+/// the rest of the `key`, and its value as 32 bytes in big-endian
+/// byte order.
+fn leaf_contents<E, CS>(
+    mut cs: CS,
+    items: &[RlpItem<E>],
+    key: &[Boolean],
+    depth: usize
+) -> Result<(Boolean, Vec<Boolean>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let one = CS::one();
+
+    let constant_byte = |byte: u8| -> Vec<Boolean> {
+        (0..8).map(|i| Boolean::constant((byte >> i) & 1 == 1)).collect()
+    };
+
+    // hex prefix encoding of the rest of the key with the leaf flag,
+    // an odd number of nibbles puts the first one into the flag byte
+    let mut path = vec![];
+    if depth % 2 == 1 {
+        path.extend_from_slice(nibble(key, depth));
+        path.extend(constant_byte(0x30)[4..].iter().cloned());
+        path.extend_from_slice(&key[(8 * (depth / 2 + 1))..]);
+    } else {
+        path.extend(constant_byte(0x20));
+        path.extend_from_slice(&key[(8 * (depth / 2))..]);
+    }
+
+    let path_item = &items[0];
+    Boolean::enforce_equal(
+        cs.namespace(|| "path is a string"),
+        &path_item.header.is_list,
+        &Boolean::constant(false)
+    )?;
+    cs.enforce(
+        || "path length",
+        |lc| lc + &path_item.header.payload_length.lc(E::Fr::one()) - (fr_from_u64((path.len() / 8) as u64), one),
+        |lc| lc + one,
+        |lc| lc
+    );
+
+    let mut path_matches = Boolean::constant(true);
+    for (i, (a, b)) in path_item.payload.iter().zip(path.iter()).enumerate() {
+        let differs = Boolean::xor(cs.namespace(|| format!("path bit {} differs", i)), a, b)?;
+        path_matches = Boolean::and(cs.namespace(|| format!("path matches to bit {}", i)), &path_matches, &differs.not())?;
+    }
+
+    // the value is stored RLP encoded
+    let value_item = &items[1];
+    Boolean::enforce_equal(
+        cs.namespace(|| "value is a string"),
+        &value_item.header.is_list,
+        &Boolean::constant(false)
+    )?;
+
+    let header = decode_header(cs.namespace(|| "value header"), &value_item.payload)?;
+    Boolean::enforce_equal(
+        cs.namespace(|| "value is an encoded string"),
+        &header.is_list,
+        &Boolean::constant(false)
+    )?;
+    cs.enforce(
+        || "value length",
+        |lc| lc + &value_item.header.payload_length.lc(E::Fr::one())
+                - &header.header_length.lc(E::Fr::one())
+                - &header.payload_length.lc(E::Fr::one()),
+        |lc| lc + one,
+        |lc| lc
+    );
+
+    let payload = shift_bytes_left(
+        cs.namespace(|| "shift to value"),
+        &value_item.payload,
+        &header.header_length,
+        2
+    )?;
+    let value = payload_into_bytes(
+        cs.namespace(|| "align value"),
+        &RlpItem { header: header, payload: payload },
+        32
+    )?;
+
+    Ok((path_matches, value))
+}
+
+/// Verifies a proof of the storage slot `slot` against the
+/// `storage_root` of an account, both given as 32 bytes with the same
+/// encoding as in `storage_trie_key`, as returned by `eth_getProof`.
+/// Returns whether the slot is set and its value as 32 bytes in
+/// big-endian byte order, which is zero if it isn't.
+///
+/// The proof is walked through the `branches` down to the `leaf`, every
+/// node padded with zero bytes to the same length for any proof of the
+/// circuit, so the depth of the trie is fixed at the synthesis time.
+/// When the last branch has no child at the key, the slot is not set
+/// and the `leaf` isn't bound by a hash, but it still has to be a
+/// well-formed leaf at the depth, e.g. of the slot with any value.
+///
+/// Extension nodes are not supported: every node above the leaf must
+/// be a branch, and a proof through an extension node doesn't satisfy
+/// the circuit. A trie has one wherever several keys share a prefix
+/// that no other key has, e.g. at the root of a storage whose slots
+/// all have keys starting with the same nibble, so the slots below
+/// one can't be proven. Nodes embedded into their parents aren't
+/// supported either: only a leaf nine or more nibbles deep with a
+/// short value is small enough to be embedded, which takes another
+/// key sharing its first eight nibbles, likely in storages of tens
+/// of thousands of slots.
+pub fn verify_storage_proof<E, CS>(
+    mut cs: CS,
+    storage_root: &[Boolean],
+    slot: &[Boolean],
+    branches: &[Vec<Boolean>],
+    leaf: &[Boolean]
+) -> Result<(Boolean, Vec<Boolean>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(storage_root.len(), 256);
+    assert!(!branches.is_empty() && branches.len() < 64);
+
+    let one = CS::one();
+
+    let key = storage_trie_key(cs.namespace(|| "trie key"), slot)?;
+
+    let mut reference = (pack::<E>(one, &storage_root[..128]), pack::<E>(one, &storage_root[128..]));
+    let mut present = Boolean::constant(true);
+
+    for (depth, node) in branches.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("branch {}", depth));
+
+        // only the last branch may have no child at the key
+        Boolean::enforce_equal(cs.namespace(|| "parent has the node"), &present, &Boolean::constant(true))?;
+
+        let header = decode_header(cs.namespace(|| "header"), node)?;
+        let hash = node_hash(cs.namespace(|| "hash"), node, &header)?;
+        enforce_reference(cs.namespace(|| "reference"), &present, &hash, &reference)?;
+
+        let (child_present, child) = branch_child(
+            cs.namespace(|| "child"),
+            node,
+            &header,
+            nibble(&key, depth)
+        )?;
+
+        present = child_present;
+        reference = child;
+    }
+
+    let cs = &mut cs.namespace(|| "leaf");
+
+    let (header, items) = decode_list(cs.namespace(|| "decode"), leaf, 2, 33)?;
+    let hash = node_hash(cs.namespace(|| "hash"), leaf, &header)?;
+    enforce_reference(cs.namespace(|| "reference"), &present, &hash, &reference)?;
+
+    let (path_matches, value) = leaf_contents(cs.namespace(|| "contents"), &items, &key, branches.len())?;
+
+    let exists = Boolean::and(cs.namespace(|| "exists"), &present, &path_matches)?;
+
+    let value = value.iter().enumerate().map(|(i, bit)| {
+        Boolean::and(cs.namespace(|| format!("value bit {}", i)), bit, &exists)
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    Ok((exists, value))
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::ConstraintSystem;
+    use bellman::pairing::bn256::Bn256;
+    use ::circuit::boolean::{AllocatedBit, Boolean};
+    use ::circuit::test::TestConstraintSystem;
+    use tiny_keccak::{Keccak, Hasher};
+    use super::verify_storage_proof;
+
+    const BRANCH_LENGTH: usize = 100;
+    const LEAF_LENGTH: usize = 70;
+
+    fn keccak(data: &[u8]) -> [u8; 32] {
+        let mut h = Keccak::v256();
+        h.update(data);
+        let mut result = [0u8; 32];
+        h.finalize(&mut result);
+
+        result
+    }
+
+    fn native_string(payload: &[u8]) -> Vec<u8> {
+        if payload.len() == 1 && payload[0] < 0x80 {
+            return payload.to_vec();
+        }
+
+        assert!(payload.len() < 56);
+        let mut result = vec![0x80 + payload.len() as u8];
+        result.extend_from_slice(payload);
+
+        result
+    }
+
+    fn native_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload: Vec<u8> = items.iter().flat_map(|i| i.iter().cloned()).collect();
+        let mut result = if payload.len() < 56 {
+            vec![0xc0 + payload.len() as u8]
+        } else {
+            vec![0xf8, payload.len() as u8]
+        };
+        result.extend(payload);
+
+        result
+    }
+
+    fn slot(value: u8) -> [u8; 32] {
+        let mut slot = [0u8; 32];
+        slot[31] = value;
+
+        slot
+    }
+
+    /// Leaf of the slot right below the branches at `depth`, with the
+    /// value given in big-endian without leading zeroes.
+    fn leaf(slot: &[u8; 32], depth: usize, value: &[u8]) -> Vec<u8> {
+        let key = keccak(slot);
+
+        let mut path = if depth % 2 == 1 {
+            vec![0x30 | (key[depth / 2] & 0x0f)]
+        } else {
+            vec![0x20]
+        };
+        path.extend_from_slice(&key[((depth + 1) / 2)..]);
+
+        native_list(&[native_string(&path), native_string(&native_string(value))])
+    }
+
+    fn branch(children: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut items = vec![vec![0x80]; 17];
+        for &(nibble, child) in children.iter() {
+            items[nibble as usize] = native_string(&keccak(child));
+        }
+
+        native_list(&items)
+    }
+
+    fn alloc_bytes<CS: ConstraintSystem<Bn256>>(mut cs: CS, bytes: &[u8], length: usize) -> Vec<Boolean> {
+        (0..(8 * length)).map(|i| {
+            let bit = (bytes.get(i / 8).cloned().unwrap_or(0) >> (i % 8)) & 1 == 1;
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(bit)).unwrap())
+        }).collect()
+    }
+
+    fn bits_into_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|byte| {
+            byte.iter().rev().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+        }).collect()
+    }
+
+    fn verify(root: &[u8], slot: &[u8; 32], branches: &[Vec<u8>], leaf: &[u8]) -> (bool, bool, Vec<u8>) {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let root = alloc_bytes(cs.namespace(|| "root"), root, 32);
+        let slot = alloc_bytes(cs.namespace(|| "slot"), slot, 32);
+        let branches: Vec<Vec<Boolean>> = branches.iter().enumerate().map(|(i, node)| {
+            alloc_bytes(cs.namespace(|| format!("branch {}", i)), node, BRANCH_LENGTH)
+        }).collect();
+        let leaf = alloc_bytes(cs.namespace(|| "leaf"), leaf, LEAF_LENGTH);
+
+        let (exists, value) = verify_storage_proof(cs.namespace(|| "proof"), &root, &slot, &branches, &leaf).unwrap();
+
+        (cs.is_satisfied(), exists.get_value().unwrap(), bits_into_bytes(&value))
+    }
+
+    #[test]
+    fn test_verify_storage_proof() {
+        // the keys of the slots 0 and 1 start with the nibbles 2 and b
+        let (slot_0, slot_1) = (slot(0), slot(1));
+        assert_eq!((keccak(&slot_0)[0] >> 4, keccak(&slot_1)[0] >> 4), (0x2, 0xb));
+
+        let leaf_0 = leaf(&slot_0, 1, &[0x2a]);
+        let leaf_1 = leaf(&slot_1, 1, &[0x12, 0x34]);
+        let root_node = branch(&[(0x2, &leaf_0[..]), (0xb, &leaf_1[..])]);
+        let root = keccak(&root_node);
+        let branches = vec![root_node];
+
+        let mut value_0 = vec![0u8; 32];
+        value_0[31] = 0x2a;
+        assert_eq!(verify(&root, &slot_0, &branches, &leaf_0), (true, true, value_0));
+
+        let mut value_1 = vec![0u8; 32];
+        value_1[30..].copy_from_slice(&[0x12, 0x34]);
+        assert_eq!(verify(&root, &slot_1, &branches, &leaf_1), (true, true, value_1));
+
+        // another key under the nibble 2, excluded by the leaf there
+        let other = (2..).map(slot).find(|s| keccak(s)[0] >> 4 == 0x2).unwrap();
+        assert_eq!(verify(&root, &other, &branches, &leaf_0), (true, false, vec![0u8; 32]));
+
+        // a key under an empty child, with an unbound leaf
+        let empty = (2..).map(slot).find(|s| keccak(s)[0] >> 4 == 0x5).unwrap();
+        assert_eq!(verify(&root, &empty, &branches, &leaf(&empty, 1, &[0x01])), (true, false, vec![0u8; 32]));
+
+        // a leaf with another value
+        let forged = leaf(&slot_0, 1, &[0x2b]);
+        assert!(!verify(&root, &slot_0, &branches, &forged).0);
+    }
+
+    #[test]
+    fn test_extension_nodes_are_rejected() {
+        // the keys of the slot 0 and of another slot share the first
+        // nibble only, so the root is an extension node with this nibble
+        let slot_0 = slot(0);
+        let key_0 = keccak(&slot_0);
+        let other = (2..).map(slot).find(|s| {
+            let key = keccak(s);
+            key[0] >> 4 == key_0[0] >> 4 && key[0] & 0x0f != key_0[0] & 0x0f
+        }).unwrap();
+
+        let leaf_0 = leaf(&slot_0, 2, &[0x2a]);
+        let leaf_other = leaf(&other, 2, &[0x01]);
+        let branch_node = branch(&[(key_0[0] & 0x0f, &leaf_0[..]), (keccak(&other)[0] & 0x0f, &leaf_other[..])]);
+
+        // odd length extension path, the nibble goes into the flag byte
+        let extension_node = native_list(&[
+            native_string(&[0x10 | (key_0[0] >> 4)]),
+            native_string(&keccak(&branch_node))
+        ]);
+        let root = keccak(&extension_node);
+
+        let (satisfied, _, _) = verify(&root, &slot_0, &[extension_node, branch_node], &leaf_0);
+        assert!(!satisfied);
+    }
+}
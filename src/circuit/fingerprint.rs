@@ -0,0 +1,195 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    Circuit,
+    LinearCombination,
+    SynthesisError,
+    ConstraintSystem,
+    Variable,
+    Index
+};
+
+use byteorder::{BigEndian, ByteOrder};
+use blake2_rfc::blake2s::Blake2s;
+
+use std::marker::PhantomData;
+
+use super::test::hash_lc;
+
+/// Constraint system that hashes the structure of the circuit
+/// as it's being synthesized: the number of variables and the
+/// normalized linear combinations of every constraint. Witness
+/// values are never computed, and annotations and namespaces
+/// don't affect the digest, so it's stable across prover and
+/// verifier builds of the same circuit.
+pub struct CircuitFingerprint<E: Engine> {
+    hasher: Blake2s,
+    num_inputs: usize,
+    num_aux: usize,
+    num_constraints: usize,
+    _marker: PhantomData<E>
+}
+
+impl<E: Engine> CircuitFingerprint<E> {
+    pub fn new() -> Self {
+        CircuitFingerprint {
+            hasher: Blake2s::new(32),
+            // the constant one is the first input
+            num_inputs: 1,
+            num_aux: 0,
+            num_constraints: 0,
+            _marker: PhantomData
+        }
+    }
+
+    /// Synthesizes the circuit and returns its digest.
+    pub fn of<C: Circuit<E>>(circuit: C) -> Result<[u8; 32], SynthesisError> {
+        let mut cs = Self::new();
+        circuit.synthesize(&mut cs)?;
+
+        Ok(cs.finalize())
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_aux(&self) -> usize {
+        self.num_aux
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        let mut h = self.hasher;
+
+        let mut buf = [0u8; 24];
+        BigEndian::write_u64(&mut buf[0..8], self.num_inputs as u64);
+        BigEndian::write_u64(&mut buf[8..16], self.num_aux as u64);
+        BigEndian::write_u64(&mut buf[16..24], self.num_constraints as u64);
+        h.update(&buf);
+
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(h.finalize().as_ref());
+
+        digest
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for CircuitFingerprint<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        _: A,
+        _: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        Ok(Variable::new_unchecked(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        _: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        hash_lc::<E>(a.as_ref(), &mut self.hasher);
+        hash_lc::<E>(b.as_ref(), &mut self.hasher);
+        hash_lc::<E>(c.as_ref(), &mut self.hasher);
+
+        self.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bls12_381::{Bls12, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::{Circuit, ConstraintSystem, SynthesisError};
+    use ::circuit::num::AllocatedNum;
+    use super::CircuitFingerprint;
+
+    struct PowerCircuit {
+        x: Option<Fr>,
+        num_squarings: usize
+    }
+
+    impl Circuit<Bls12> for PowerCircuit {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut x = AllocatedNum::alloc(cs.namespace(|| "x"), || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            for i in 0..self.num_squarings {
+                x = x.square(cs.namespace(|| format!("square {}", i)))?;
+            }
+            x.inputize(cs.namespace(|| "result"))?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let blank = CircuitFingerprint::of(PowerCircuit { x: None, num_squarings: 3 }).unwrap();
+        let with_witness = CircuitFingerprint::of(PowerCircuit {
+            x: Some(Fr::from_str("7").unwrap()),
+            num_squarings: 3
+        }).unwrap();
+        let other = CircuitFingerprint::of(PowerCircuit { x: None, num_squarings: 4 }).unwrap();
+
+        assert_eq!(blank, with_witness);
+        assert!(blank != other);
+
+        let mut cs = CircuitFingerprint::<Bls12>::new();
+        PowerCircuit { x: Some(Fr::one()), num_squarings: 3 }.synthesize(&mut cs).unwrap();
+        assert_eq!(cs.num_inputs(), 2);
+        assert_eq!(cs.num_aux(), 4);
+        assert_eq!(cs.num_constraints(), 4);
+        assert_eq!(cs.finalize(), blank);
+    }
+}
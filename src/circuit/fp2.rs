@@ -0,0 +1,507 @@
+//! Arithmetic in a quadratic extension `E::Fr[u] / (u^2 - non_residue)`.
+//!
+//! Elements are pairs of linear combinations, so additions, negations
+//! and multiplications by constants are free. They are used to verify
+//! FRI layers of proofs over a field whose challenges are drawn from
+//! its quadratic extension, see `fold`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+/// `c0 + c1 * u` with `u^2 = non_residue`, where `non_residue` is not
+/// a square in `E::Fr`.
+#[derive(Clone)]
+pub struct Fp2Num<E: Engine> {
+    pub c0: Num<E>,
+    pub c1: Num<E>,
+    pub non_residue: E::Fr
+}
+
+fn mul_values<F: Field>(a: (F, F), b: (F, F), non_residue: &F) -> (F, F) {
+    let mut v0 = a.0;
+    v0.mul_assign(&b.0);
+    let mut v1 = a.1;
+    v1.mul_assign(&b.1);
+
+    let mut c0 = v1;
+    c0.mul_assign(non_residue);
+    c0.add_assign(&v0);
+
+    let mut c1 = a.0;
+    c1.mul_assign(&b.1);
+    let mut tmp = a.1;
+    tmp.mul_assign(&b.0);
+    c1.add_assign(&tmp);
+
+    (c0, c1)
+}
+
+fn inverse_value<F: Field>(a: (F, F), non_residue: &F) -> Result<(F, F), SynthesisError> {
+    // (c0 - c1 u) / (c0^2 - non_residue c1^2)
+    let mut norm = a.1;
+    norm.square();
+    norm.mul_assign(non_residue);
+    norm.negate();
+    let mut tmp = a.0;
+    tmp.square();
+    norm.add_assign(&tmp);
+
+    let norm_inv = norm.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+    let mut c0 = a.0;
+    c0.mul_assign(&norm_inv);
+    let mut c1 = a.1;
+    c1.mul_assign(&norm_inv);
+    c1.negate();
+
+    Ok((c0, c1))
+}
+
+fn constant_num<E: Engine, CS: ConstraintSystem<E>>(value: E::Fr) -> Num<E> {
+    Num::zero().add_bool_with_coeff(CS::one(), &Boolean::constant(true), value)
+}
+
+fn sub_num<E: Engine>(a: &Num<E>, b: &Num<E>) -> Num<E> {
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    let mut neg = b.clone();
+    neg.scale(minus_one);
+
+    let mut res = a.clone();
+    res.add_assign(&neg);
+
+    res
+}
+
+impl<E: Engine> Fp2Num<E> {
+    pub fn alloc<CS>(
+        mut cs: CS,
+        non_residue: E::Fr,
+        value: Option<(E::Fr, E::Fr)>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let c0 = AllocatedNum::alloc(cs.namespace(|| "c0"), || Ok(value.get()?.0))?;
+        let c1 = AllocatedNum::alloc(cs.namespace(|| "c1"), || Ok(value.get()?.1))?;
+
+        Ok(Fp2Num {
+            c0: c0.into(),
+            c1: c1.into(),
+            non_residue
+        })
+    }
+
+    pub fn constant<CS>(non_residue: E::Fr, value: (E::Fr, E::Fr)) -> Self
+        where CS: ConstraintSystem<E>
+    {
+        Fp2Num {
+            c0: constant_num::<E, CS>(value.0),
+            c1: constant_num::<E, CS>(value.1),
+            non_residue
+        }
+    }
+
+    /// Embeds an element of the base field.
+    pub fn from_base(c0: Num<E>, non_residue: E::Fr) -> Self {
+        Fp2Num {
+            c0,
+            c1: Num::zero(),
+            non_residue
+        }
+    }
+
+    pub fn get_value(&self) -> Option<(E::Fr, E::Fr)> {
+        match (self.c0.get_value(), self.c1.get_value()) {
+            (Some(c0), Some(c1)) => Some((c0, c1)),
+            _ => None
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        assert!(self.non_residue == other.non_residue);
+
+        let mut c0 = self.c0.clone();
+        c0.add_assign(&other.c0);
+        let mut c1 = self.c1.clone();
+        c1.add_assign(&other.c1);
+
+        Fp2Num {
+            c0,
+            c1,
+            non_residue: self.non_residue
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        assert!(self.non_residue == other.non_residue);
+
+        Fp2Num {
+            c0: sub_num(&self.c0, &other.c0),
+            c1: sub_num(&self.c1, &other.c1),
+            non_residue: self.non_residue
+        }
+    }
+
+    pub fn scale(&self, coeff: E::Fr) -> Self {
+        let mut res = self.clone();
+        res.c0.scale(coeff);
+        res.c1.scale(coeff);
+
+        res
+    }
+
+    /// `c0 - c1 * u`, the image under the Frobenius map.
+    pub fn conjugate(&self) -> Self {
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+
+        let mut res = self.clone();
+        res.c1.scale(minus_one);
+
+        res
+    }
+
+    /// Enforces `a * b == c` with three constraints.
+    fn enforce_product<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        c: &Self
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        assert!(a.non_residue == b.non_residue && a.non_residue == c.non_residue);
+
+        let v1 = AllocatedNum::alloc(cs.namespace(|| "a1 * b1"), || {
+            let mut tmp = *a.c1.get_value().get()?;
+            tmp.mul_assign(b.c1.get_value().get()?);
+
+            Ok(tmp)
+        })?;
+
+        let one = E::Fr::one();
+        let mut minus_non_residue = a.non_residue;
+        minus_non_residue.negate();
+        let mut one_minus_non_residue = minus_non_residue;
+        one_minus_non_residue.add_assign(&one);
+
+        cs.enforce(
+            || "a1 * b1",
+            |lc| lc + &a.c1.lc(one),
+            |lc| lc + &b.c1.lc(one),
+            |lc| lc + v1.get_variable()
+        );
+
+        // a0 b0 = c0 - non_residue a1 b1
+        cs.enforce(
+            || "c0",
+            |lc| lc + &a.c0.lc(one),
+            |lc| lc + &b.c0.lc(one),
+            |lc| lc + &c.c0.lc(one) + (minus_non_residue, v1.get_variable())
+        );
+
+        // (a0 + a1)(b0 + b1) = a0 b0 + a1 b1 + c1
+        cs.enforce(
+            || "c1",
+            |lc| lc + &a.c0.lc(one) + &a.c1.lc(one),
+            |lc| lc + &b.c0.lc(one) + &b.c1.lc(one),
+            |lc| lc + &c.c0.lc(one) + &c.c1.lc(one) + (one_minus_non_residue, v1.get_variable())
+        );
+
+        Ok(())
+    }
+
+    /// Costs three constraints.
+    pub fn mul<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let value = match (self.get_value(), other.get_value()) {
+            (Some(a), Some(b)) => Some(mul_values(a, b, &self.non_residue)),
+            _ => None
+        };
+
+        let res = Self::alloc(cs.namespace(|| "product"), self.non_residue, value)?;
+        Self::enforce_product(cs.namespace(|| "multiplication"), self, other, &res)?;
+
+        Ok(res)
+    }
+
+    pub fn square<CS>(
+        &self,
+        cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        self.mul(cs, self)
+    }
+
+    /// Multiplies both coefficients by an element of the base field,
+    /// costs two constraints.
+    pub fn mul_by_base<CS>(
+        &self,
+        mut cs: CS,
+        other: &Num<E>
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let one = E::Fr::one();
+        let mut coeffs = vec![];
+
+        for (i, c) in [&self.c0, &self.c1].iter().enumerate() {
+            let product = AllocatedNum::alloc(cs.namespace(|| format!("c{}", i)), || {
+                let mut tmp = *c.get_value().get()?;
+                tmp.mul_assign(other.get_value().get()?);
+
+                Ok(tmp)
+            })?;
+
+            cs.enforce(
+                || format!("c{} multiplication", i),
+                |lc| lc + &c.lc(one),
+                |lc| lc + &other.lc(one),
+                |lc| lc + product.get_variable()
+            );
+
+            coeffs.push(Num::from(product));
+        }
+
+        let c1 = coeffs.pop().unwrap();
+        let c0 = coeffs.pop().unwrap();
+
+        Ok(Fp2Num {
+            c0,
+            c1,
+            non_residue: self.non_residue
+        })
+    }
+
+    /// Costs three constraints, unsatisfiable for zero.
+    pub fn inverse<CS>(
+        &self,
+        mut cs: CS
+    ) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let value = match self.get_value() {
+            Some(a) => Some(inverse_value(a, &self.non_residue)?),
+            None => None
+        };
+
+        let inv = Self::alloc(cs.namespace(|| "inverse"), self.non_residue, value)?;
+        let one = Self::constant::<CS>(self.non_residue, (E::Fr::one(), E::Fr::zero()));
+        Self::enforce_product(cs.namespace(|| "multiplication"), self, &inv, &one)?;
+
+        Ok(inv)
+    }
+
+    pub fn enforce_equal<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let one = E::Fr::one();
+
+        cs.enforce(
+            || "c0 equality",
+            |lc| lc + &self.c0.lc(one),
+            |lc| lc + CS::one(),
+            |lc| lc + &other.c0.lc(one)
+        );
+
+        cs.enforce(
+            || "c1 equality",
+            |lc| lc + &self.c1.lc(one),
+            |lc| lc + CS::one(),
+            |lc| lc + &other.c1.lc(one)
+        );
+
+        Ok(())
+    }
+}
+
+/// One FRI folding step with an extension field challenge:
+/// `(a + b) / 2 + alpha * (a - b) / (2x)` for the values `a` at `x` and
+/// `b` at `-x`, as in `fri`. `x` is in the base field and must not be
+/// zero. Costs five constraints.
+pub fn fold<E, CS>(
+    mut cs: CS,
+    values: &[Fp2Num<E>; 2],
+    x: &Num<E>,
+    alpha: &Fp2Num<E>
+) -> Result<Fp2Num<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let two = E::Fr::from_str("2").unwrap();
+    let two_inv = two.inverse().unwrap();
+
+    let even = values[0].add(&values[1]).scale(two_inv);
+    let difference = values[0].sub(&values[1]);
+
+    // odd * 2x = a - b
+    let odd_value = match (difference.get_value(), x.get_value()) {
+        (Some(d), Some(x)) => {
+            let mut denominator = x;
+            denominator.double();
+            let denominator_inv = denominator.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            let mut c0 = d.0;
+            c0.mul_assign(&denominator_inv);
+            let mut c1 = d.1;
+            c1.mul_assign(&denominator_inv);
+
+            Some((c0, c1))
+        },
+        _ => None
+    };
+
+    let odd = Fp2Num::alloc(cs.namespace(|| "odd"), alpha.non_residue, odd_value)?;
+
+    let one = E::Fr::one();
+    for (i, (o, d)) in [&odd.c0, &odd.c1].iter().zip([&difference.c0, &difference.c1].iter()).enumerate() {
+        cs.enforce(
+            || format!("odd c{}", i),
+            |lc| lc + &o.lc(one),
+            |lc| lc + &x.lc(two),
+            |lc| lc + &d.lc(one)
+        );
+    }
+
+    let odd = odd.mul(cs.namespace(|| "alpha * odd"), alpha)?;
+
+    Ok(even.add(&odd))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use super::*;
+
+    fn fold_values(values: [(Fr, Fr); 2], x: Fr, alpha: (Fr, Fr), non_residue: &Fr) -> (Fr, Fr) {
+        let two_inv = Fr::from_str("2").unwrap().inverse().unwrap();
+        let mut two_x_inv = x;
+        two_x_inv.double();
+        let two_x_inv = two_x_inv.inverse().unwrap();
+
+        let mut even = values[0];
+        even.0.add_assign(&values[1].0);
+        even.1.add_assign(&values[1].1);
+        even.0.mul_assign(&two_inv);
+        even.1.mul_assign(&two_inv);
+
+        let mut odd = values[0];
+        odd.0.sub_assign(&values[1].0);
+        odd.1.sub_assign(&values[1].1);
+        let odd = mul_values(odd, (two_x_inv, Fr::zero()), non_residue);
+        let odd = mul_values(odd, alpha, non_residue);
+
+        even.0.add_assign(&odd.0);
+        even.1.add_assign(&odd.1);
+
+        even
+    }
+
+    #[test]
+    fn test_fp2_arithmetic() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        // the generator of the multiplicative group is not a square
+        let non_residue = Fr::multiplicative_generator();
+
+        let a_value: (Fr, Fr) = (rng.gen(), rng.gen());
+        let b_value: (Fr, Fr) = (rng.gen(), rng.gen());
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = Fp2Num::alloc(cs.namespace(|| "a"), non_residue, Some(a_value)).unwrap();
+        let b = Fp2Num::alloc(cs.namespace(|| "b"), non_residue, Some(b_value)).unwrap();
+
+        let product = a.mul(cs.namespace(|| "a * b"), &b).unwrap();
+        assert_eq!(product.get_value().unwrap(), mul_values(a_value, b_value, &non_residue));
+
+        let commuted = b.mul(cs.namespace(|| "b * a"), &a).unwrap();
+        product.enforce_equal(cs.namespace(|| "commutativity"), &commuted).unwrap();
+
+        // a * a^-1 = 1
+        let inv = a.inverse(cs.namespace(|| "a^-1")).unwrap();
+        let one = inv.mul(cs.namespace(|| "a * a^-1"), &a).unwrap();
+        assert_eq!(one.get_value().unwrap(), (Fr::one(), Fr::zero()));
+
+        // a * conj(a) is in the base field
+        let norm = a.mul(cs.namespace(|| "norm"), &a.conjugate()).unwrap();
+        assert!(norm.get_value().unwrap().1.is_zero());
+
+        // (a + b)^2 - (a - b)^2 = 4ab
+        let lhs = a.add(&b).square(cs.namespace(|| "(a + b)^2")).unwrap()
+            .sub(&a.sub(&b).square(cs.namespace(|| "(a - b)^2")).unwrap());
+        let rhs = product.scale(Fr::from_str("4").unwrap());
+        lhs.enforce_equal(cs.namespace(|| "identity"), &rhs).unwrap();
+
+        let x: Fr = rng.gen();
+        let x_num = Num::from(AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap());
+        let scaled = a.mul_by_base(cs.namespace(|| "a * x"), &x_num).unwrap();
+        let expected = Fp2Num::constant::<TestConstraintSystem<Bn256>>(non_residue, (x, Fr::zero()));
+        let expected = a.mul(cs.namespace(|| "a * (x + 0u)"), &expected).unwrap();
+        scaled.enforce_equal(cs.namespace(|| "base multiplication"), &expected).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("a^-1/inverse/c1/num", rng.gen());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_fold() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let non_residue = Fr::multiplicative_generator();
+
+        let values: [(Fr, Fr); 2] = [(rng.gen(), rng.gen()), (rng.gen(), rng.gen())];
+        let alpha_value: (Fr, Fr) = (rng.gen(), rng.gen());
+        let x: Fr = rng.gen();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = Fp2Num::alloc(cs.namespace(|| "a"), non_residue, Some(values[0])).unwrap();
+        let b = Fp2Num::alloc(cs.namespace(|| "b"), non_residue, Some(values[1])).unwrap();
+        let alpha = Fp2Num::alloc(cs.namespace(|| "alpha"), non_residue, Some(alpha_value)).unwrap();
+        let x_num = Num::from(AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap());
+
+        let folded = fold(cs.namespace(|| "fold"), &[a, b], &x_num, &alpha).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 5);
+        assert_eq!(folded.get_value().unwrap(), fold_values(values, x, alpha_value, &non_residue));
+
+        cs.set("fold/odd/c0/num", rng.gen());
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,150 @@
+//! GMiMC-erf permutation and sponge hash for the parameters of
+//! `gmimc::generate_params`. Every round constrains one S-box, e.g. 3
+//! constraints for `alpha = 5`, independently of the state width.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::sbox::SBox;
+
+use gmimc::GmimcParams;
+
+pub fn gmimc_permutation<E, CS>(
+    mut cs: CS,
+    params: &GmimcParams<E::Fr>,
+    state: &[Num<E>]
+) -> Result<Vec<Num<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(state.len(), params.state_width);
+
+    let sbox = SBox::Power(params.alpha);
+
+    let mut state = state.to_vec();
+    for (round, c) in params.round_constants.iter().enumerate() {
+        let mut x = state[0].clone();
+        x.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), *c);
+        let f = Num::from(sbox.apply(cs.namespace(|| format!("round {}", round)), &x)?);
+
+        for s in state[1..].iter_mut() {
+            s.add_assign(&f);
+        }
+        state.rotate_left(1);
+    }
+
+    Ok(state)
+}
+
+/// Sponge hash matching `gmimc::gmimc_hash`.
+pub fn gmimc_hash<E, CS>(
+    mut cs: CS,
+    params: &GmimcParams<E::Fr>,
+    input: &[AllocatedNum<E>]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let rate = params.rate();
+
+    let mut input: Vec<Num<E>> = input.iter().map(|el| Num::from(el.clone())).collect();
+    let mut one = Num::<E>::zero();
+    one.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), E::Fr::one());
+    input.push(one);
+    while input.len() % rate != 0 {
+        input.push(Num::zero());
+    }
+
+    let mut state = vec![Num::<E>::zero(); params.state_width];
+    for (i, chunk) in input.chunks(rate).enumerate() {
+        for (s, el) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(el);
+        }
+        state = gmimc_permutation(cs.namespace(|| format!("permutation {}", i)), params, &state)?;
+    }
+
+    let output = AllocatedNum::alloc(cs.namespace(|| "output"), || {
+        Ok(*state[0].get_value().get()?)
+    })?;
+
+    cs.enforce(
+        || "output constraint",
+        |_| state[0].lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + output.get_variable()
+    );
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::bls12_381::{self, Bls12};
+    use ::circuit::test::*;
+    use ::gmimc::generate_params;
+    use super::*;
+
+    #[test]
+    fn test_gmimc_hash_wide() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<Fr>(12, 128);
+
+        // a whole leaf of 10 elements is absorbed by a single permutation
+        let input: Vec<Fr> = (0..10).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input_vars: Vec<_> = input.iter().enumerate().map(|(i, el)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap()
+        }).collect();
+
+        let output = gmimc_hash(cs.namespace(|| "hash"), &params, &input_vars).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(output.get_value().unwrap(), ::gmimc::gmimc_hash(&params, &input));
+        assert_eq!(cs.num_constraints(), params.num_rounds * 3 + 1);
+    }
+
+    #[test]
+    fn test_gmimc_permutation() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<bls12_381::Fr>(3, 128);
+
+        let mut input: Vec<bls12_381::Fr> = (0..3).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let state: Vec<Num<Bls12>> = input.iter().enumerate().map(|(i, el)| {
+            Num::from(AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap())
+        }).collect();
+
+        let output = gmimc_permutation(cs.namespace(|| "permutation"), &params, &state).unwrap();
+
+        ::gmimc::gmimc_permutation(&params, &mut input);
+
+        assert!(cs.is_satisfied());
+        for (o, expected) in output.iter().zip(input.iter()) {
+            assert_eq!(o.get_value().unwrap(), *expected);
+        }
+    }
+}
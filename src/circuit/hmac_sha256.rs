@@ -0,0 +1,222 @@
+//! HMAC-SHA-256 (RFC 2104) and HKDF (RFC 5869) over the SHA-256
+//! gadget, with bits in the same big-endian order as for `sha256`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    Boolean
+};
+
+use super::uint32::{
+    UInt32
+};
+
+use super::sha256::{
+    sha256,
+    sha256_compression_function,
+    sha256_from_midstate,
+    get_sha256_iv
+};
+
+const BLOCK_SIZE: usize = 512;
+const HASH_SIZE: usize = 256;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+fn xor_pad<E, CS>(
+    mut cs: CS,
+    key_block: &[Boolean],
+    pad: u8
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    key_block.iter().enumerate().map(|(i, bit)| {
+        let pad_bit = Boolean::constant((pad >> (7 - i % 8)) & 1 == 1);
+
+        Boolean::xor(cs.namespace(|| format!("bit {}", i)), bit, &pad_bit)
+    }).collect()
+}
+
+/// Computes the inner and outer midstates of HMAC for the key,
+/// i.e. the compressions of the padded key XORed with `ipad` and
+/// `opad`. They only depend on the key, so several messages can
+/// be authenticated with one key at the cost of two compressions.
+pub fn hmac_sha256_midstates<E, CS>(
+    mut cs: CS,
+    key: &[Boolean]
+) -> Result<(Vec<UInt32>, Vec<UInt32>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(key.len() % 8 == 0);
+
+    // keys longer than the block size are hashed first
+    let mut key_block = if key.len() > BLOCK_SIZE {
+        sha256(cs.namespace(|| "key hash"), key)?
+    } else {
+        key.to_vec()
+    };
+    key_block.resize(BLOCK_SIZE, Boolean::constant(false));
+
+    let inner_block = xor_pad(cs.namespace(|| "ipad"), &key_block, IPAD)?;
+    let outer_block = xor_pad(cs.namespace(|| "opad"), &key_block, OPAD)?;
+
+    let inner = sha256_compression_function(cs.namespace(|| "inner midstate"), &inner_block, &get_sha256_iv())?;
+    let outer = sha256_compression_function(cs.namespace(|| "outer midstate"), &outer_block, &get_sha256_iv())?;
+
+    Ok((inner, outer))
+}
+
+/// Computes HMAC from the midstates returned by `hmac_sha256_midstates`.
+pub fn hmac_sha256_from_midstates<E, CS>(
+    mut cs: CS,
+    inner: &[UInt32],
+    outer: &[UInt32],
+    message: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let inner_hash = sha256_from_midstate(cs.namespace(|| "inner hash"), inner, BLOCK_SIZE as u64, message)?;
+
+    sha256_from_midstate(cs.namespace(|| "outer hash"), outer, BLOCK_SIZE as u64, &inner_hash)
+}
+
+pub fn hmac_sha256<E, CS>(
+    mut cs: CS,
+    key: &[Boolean],
+    message: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let (inner, outer) = hmac_sha256_midstates(cs.namespace(|| "key"), key)?;
+
+    hmac_sha256_from_midstates(cs.namespace(|| "hmac"), &inner, &outer, message)
+}
+
+/// HKDF-Extract, an empty salt is replaced with a zero block
+/// of the hash size.
+pub fn hkdf_sha256_extract<E, CS>(
+    cs: CS,
+    salt: &[Boolean],
+    ikm: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    if salt.is_empty() {
+        hmac_sha256(cs, &vec![Boolean::constant(false); HASH_SIZE], ikm)
+    } else {
+        hmac_sha256(cs, salt, ikm)
+    }
+}
+
+/// HKDF-Expand, returns `length` bytes of output keying material.
+pub fn hkdf_sha256_expand<E, CS>(
+    mut cs: CS,
+    prk: &[Boolean],
+    info: &[Boolean],
+    length: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(prk.len() >= HASH_SIZE);
+    assert!(info.len() % 8 == 0);
+    assert!(length <= 255 * HASH_SIZE / 8);
+
+    let (inner, outer) = hmac_sha256_midstates(cs.namespace(|| "prk"), prk)?;
+
+    // T(i) = HMAC(PRK, T(i - 1) || info || i)
+    let mut okm = Vec::with_capacity(length * 8);
+    let mut t = vec![];
+    let mut i = 1u8;
+    while okm.len() < length * 8 {
+        let mut message = t;
+        message.extend_from_slice(info);
+        message.extend((0..8).rev().map(|j| Boolean::constant((i >> j) & 1 == 1)));
+
+        t = hmac_sha256_from_midstates(cs.namespace(|| format!("block {}", i)), &inner, &outer, &message)?;
+        okm.extend_from_slice(&t);
+        i += 1;
+    }
+    okm.truncate(length * 8);
+
+    Ok(okm)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bls12_381::{Bls12};
+    use ::circuit::test::*;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use super::*;
+
+    fn constant_bytes(bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().flat_map(|&b| (0..8).rev().map(move |i| Boolean::constant((b >> i) & 1 == 1))).collect()
+    }
+
+    fn alloc_bytes<CS: ConstraintSystem<Bls12>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().enumerate().flat_map(|(i, &b)| (0..8).rev().map(move |j| (i, j, (b >> j) & 1 == 1)))
+            .map(|(i, j, bit)| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {} {}", i, j)), Some(bit)).unwrap())
+            }).collect()
+    }
+
+    fn into_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|b| {
+            b.iter().fold(0u8, |acc, bit| (acc << 1) | (bit.get_value().unwrap() as u8))
+        }).collect()
+    }
+
+    #[test]
+    fn test_hmac_vectors() {
+        // RFC 4231, test case 2
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let key = alloc_bytes(cs.namespace(|| "key"), b"Jefe");
+        let message = alloc_bytes(cs.namespace(|| "message"), b"what do ya want for nothing?");
+        let mac = hmac_sha256(cs.namespace(|| "hmac"), &key, &message).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&mac), hex!("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843").to_vec());
+
+        // RFC 4231, test case 6: a key longer than the block size
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let key = constant_bytes(&[0xaa; 131]);
+        let message = alloc_bytes(cs.namespace(|| "message"), b"Test Using Larger Than Block-Size Key - Hash Key First");
+        let mac = hmac_sha256(cs.namespace(|| "hmac"), &key, &message).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&mac), hex!("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54").to_vec());
+    }
+
+    #[test]
+    fn test_hkdf_vector() {
+        // RFC 5869, test case 1
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let ikm = alloc_bytes(cs.namespace(|| "ikm"), &[0x0b; 22]);
+        let salt = constant_bytes(&hex!("000102030405060708090a0b0c"));
+        let info = constant_bytes(&hex!("f0f1f2f3f4f5f6f7f8f9"));
+
+        let prk = hkdf_sha256_extract(cs.namespace(|| "extract"), &salt, &ikm).unwrap();
+        assert_eq!(into_bytes(&prk), hex!("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5").to_vec());
+
+        let okm = hkdf_sha256_expand(cs.namespace(|| "expand"), &prk, &info, 42).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            into_bytes(&okm),
+            hex!("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865").to_vec()
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Non-membership and insertion proofs for `indexed_merkle_tree`.
+//!
+//! A value is proven absent by the inclusion of the low leaf with
+//! `low.value < value < low.next_value`, or `low.next_value = 0` when
+//! the low leaf is the last one. All the compared values are range
+//! checked to the `value_bits` of the tree.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::comparison::{
+    alloc_packed_bits,
+    less_than
+};
+
+use super::poseidon2::poseidon2_compress;
+
+use poseidon2::Poseidon2Params;
+
+#[derive(Clone)]
+pub struct AllocatedIndexedLeaf<E: Engine> {
+    pub value: AllocatedNum<E>,
+    pub next_index: AllocatedNum<E>,
+    pub next_value: AllocatedNum<E>
+}
+
+impl<E: Engine> AllocatedIndexedLeaf<E> {
+    pub fn hash<CS>(
+        &self,
+        mut cs: CS,
+        params: &Poseidon2Params<E::Fr>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let tmp = poseidon2_compress(cs.namespace(|| "hash value and next index"), params, &self.value, &self.next_index)?;
+
+        poseidon2_compress(cs.namespace(|| "hash next value"), params, &tmp, &self.next_value)
+    }
+}
+
+fn pack_index<E: Engine, CS: ConstraintSystem<E>>(bits: &[Boolean]) -> Num<E> {
+    let mut num = Num::<E>::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter() {
+        num = num.add_bool_with_coeff(CS::one(), bit, coeff);
+        coeff.double();
+    }
+
+    num
+}
+
+fn alloc_num<E, CS>(mut cs: CS, num: &Num<E>) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let allocated = AllocatedNum::alloc(cs.namespace(|| "allocate"), || {
+        Ok(*num.get_value().get()?)
+    })?;
+
+    cs.enforce(
+        || "enforce allocation",
+        |_| num.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + allocated.get_variable()
+    );
+
+    Ok(allocated)
+}
+
+/// Computes the root from a leaf hash, the little-endian bits of its
+/// index and its authentication path from the leaf level up.
+pub fn compute_root<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    leaf_hash: &AllocatedNum<E>,
+    index_bits: &[Boolean],
+    path: &[AllocatedNum<E>]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(index_bits.len(), path.len());
+
+    let mut cur = leaf_hash.clone();
+    for (i, (bit, sibling)) in index_bits.iter().zip(path.iter()).enumerate() {
+        let cs = &mut cs.namespace(|| format!("merkle tree hash {}", i));
+
+        let (left, right) = AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "conditional reversal of preimage"),
+            &cur,
+            sibling,
+            bit
+        )?;
+
+        cur = poseidon2_compress(cs.namespace(|| "node hash"), params, &left, &right)?;
+    }
+
+    Ok(cur)
+}
+
+fn enforce_equal<E, CS>(mut cs: CS, a: &AllocatedNum<E>, b: &AllocatedNum<E>)
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    cs.enforce(
+        || "enforce equal",
+        |lc| lc + a.get_variable() - b.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+}
+
+fn enforce_range<E, CS>(cs: CS, num: &AllocatedNum<E>, value_bits: usize) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    alloc_packed_bits(cs, num.get_value(), LinearCombination::zero() + num.get_variable(), value_bits)?;
+
+    Ok(())
+}
+
+/// Enforces that `value` is not in the set with the given `root`.
+pub fn enforce_non_membership<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    root: &AllocatedNum<E>,
+    value: &AllocatedNum<E>,
+    low_leaf: &AllocatedIndexedLeaf<E>,
+    low_index_bits: &[Boolean],
+    low_path: &[AllocatedNum<E>],
+    value_bits: usize
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    enforce_range(cs.namespace(|| "value range"), value, value_bits)?;
+    enforce_range(cs.namespace(|| "low value range"), &low_leaf.value, value_bits)?;
+    enforce_range(cs.namespace(|| "next value range"), &low_leaf.next_value, value_bits)?;
+
+    let above_low = less_than(cs.namespace(|| "low value < value"), &low_leaf.value, value, value_bits)?;
+    Boolean::enforce_equal(cs.namespace(|| "enforce value is above the low leaf"), &above_low, &Boolean::constant(true))?;
+
+    let below_next = less_than(cs.namespace(|| "value < next value"), value, &low_leaf.next_value, value_bits)?;
+
+    let zero = AllocatedNum::alloc(cs.namespace(|| "zero"), || Ok(E::Fr::zero()))?;
+    zero.assert_zero(cs.namespace(|| "enforce zero"))?;
+    let is_last = AllocatedNum::equals(cs.namespace(|| "next value is zero"), &low_leaf.next_value, &zero)?;
+
+    // below_next OR is_last
+    let neither = Boolean::and(cs.namespace(|| "neither below next nor last"), &below_next.not(), &is_last.not())?;
+    Boolean::enforce_equal(cs.namespace(|| "enforce value is below the next one"), &neither, &Boolean::constant(false))?;
+
+    let low_hash = low_leaf.hash(cs.namespace(|| "low leaf hash"), params)?;
+    let computed_root = compute_root(cs.namespace(|| "low leaf inclusion"), params, &low_hash, low_index_bits, low_path)?;
+    enforce_equal(cs.namespace(|| "enforce low leaf root"), &computed_root, root);
+
+    Ok(())
+}
+
+/// Inserts `value` into the set with the given `root` and returns the
+/// new root. The low leaf is relinked to the new leaf, which takes
+/// over its successor, and the new slot must be empty after that.
+pub fn insert<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    root: &AllocatedNum<E>,
+    value: &AllocatedNum<E>,
+    low_leaf: &AllocatedIndexedLeaf<E>,
+    low_index_bits: &[Boolean],
+    low_path: &[AllocatedNum<E>],
+    new_index_bits: &[Boolean],
+    new_path: &[AllocatedNum<E>],
+    value_bits: usize
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    enforce_non_membership(
+        cs.namespace(|| "non-membership"),
+        params,
+        root,
+        value,
+        low_leaf,
+        low_index_bits,
+        low_path,
+        value_bits
+    )?;
+
+    let new_index = alloc_num(cs.namespace(|| "new index"), &pack_index::<E, CS>(new_index_bits))?;
+
+    let updated_low = AllocatedIndexedLeaf {
+        value: low_leaf.value.clone(),
+        next_index: new_index,
+        next_value: value.clone()
+    };
+    let updated_low_hash = updated_low.hash(cs.namespace(|| "updated low leaf hash"), params)?;
+    let intermediate_root = compute_root(cs.namespace(|| "updated low leaf root"), params, &updated_low_hash, low_index_bits, low_path)?;
+
+    let empty = AllocatedNum::alloc(cs.namespace(|| "empty slot"), || Ok(E::Fr::zero()))?;
+    empty.assert_zero(cs.namespace(|| "enforce empty slot"))?;
+    let empty_root = compute_root(cs.namespace(|| "empty slot inclusion"), params, &empty, new_index_bits, new_path)?;
+    enforce_equal(cs.namespace(|| "enforce empty slot root"), &empty_root, &intermediate_root);
+
+    let new_leaf = AllocatedIndexedLeaf {
+        value: value.clone(),
+        next_index: low_leaf.next_index.clone(),
+        next_value: low_leaf.next_value.clone()
+    };
+    let new_hash = new_leaf.hash(cs.namespace(|| "new leaf hash"), params)?;
+
+    compute_root(cs.namespace(|| "new leaf root"), params, &new_hash, new_index_bits, new_path)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::test::*;
+    use ::indexed_merkle_tree::{IndexedMerkleTree, IndexedLeaf};
+    use ::poseidon2::generate_params;
+    use super::*;
+
+    const DEPTH: usize = 4;
+    const VALUE_BITS: usize = 64;
+
+    fn alloc<CS: ConstraintSystem<Bn256>>(mut cs: CS, value: Fr) -> AllocatedNum<Bn256> {
+        AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(value)).unwrap()
+    }
+
+    fn alloc_leaf<CS: ConstraintSystem<Bn256>>(mut cs: CS, leaf: &IndexedLeaf<Fr>) -> AllocatedIndexedLeaf<Bn256> {
+        AllocatedIndexedLeaf {
+            value: alloc(cs.namespace(|| "value"), leaf.value),
+            next_index: alloc(cs.namespace(|| "next index"), fr(leaf.next_index)),
+            next_value: alloc(cs.namespace(|| "next value"), leaf.next_value)
+        }
+    }
+
+    fn alloc_index<CS: ConstraintSystem<Bn256>>(mut cs: CS, index: u64) -> Vec<Boolean> {
+        (0..DEPTH).map(|i| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some((index >> i) & 1 == 1)).unwrap())
+        }).collect()
+    }
+
+    fn alloc_path<CS: ConstraintSystem<Bn256>>(mut cs: CS, path: &[Fr]) -> Vec<AllocatedNum<Bn256>> {
+        path.iter().enumerate().map(|(i, el)| alloc(cs.namespace(|| format!("path {}", i)), *el)).collect()
+    }
+
+    #[test]
+    fn test_insert() {
+        let params = generate_params::<Fr>(3);
+        let mut tree = IndexedMerkleTree::new(&params, DEPTH, VALUE_BITS);
+
+        for &x in [30u64, 10, 20, 40].iter() {
+            let old_root = tree.root();
+            let witness = tree.insert(fr(x)).unwrap();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let root = alloc(cs.namespace(|| "root"), old_root);
+            let value = alloc(cs.namespace(|| "value"), fr(x));
+            let low_leaf = alloc_leaf(cs.namespace(|| "low leaf"), &witness.low.low_leaf);
+            let low_index = alloc_index(cs.namespace(|| "low index"), witness.low.low_index);
+            let low_path = alloc_path(cs.namespace(|| "low path"), &witness.low.low_path);
+            let new_index = alloc_index(cs.namespace(|| "new index"), witness.new_index);
+            let new_path = alloc_path(cs.namespace(|| "new path"), &witness.new_path);
+
+            let new_root = insert(
+                cs.namespace(|| "insert"),
+                &params,
+                &root,
+                &value,
+                &low_leaf,
+                &low_index,
+                &low_path,
+                &new_index,
+                &new_path,
+                VALUE_BITS
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(new_root.get_value().unwrap(), tree.root());
+        }
+    }
+
+    #[test]
+    fn test_non_membership() {
+        let params = generate_params::<Fr>(3);
+        let mut tree = IndexedMerkleTree::new(&params, DEPTH, VALUE_BITS);
+        for &x in [10u64, 20, 30].iter() {
+            tree.insert(fr(x)).unwrap();
+        }
+
+        // absent values, and a member with the low leaf of its neighbour
+        let low_of_25 = tree.non_membership_witness(&fr(25)).unwrap();
+        for &(x, expected) in [(25u64, true), (35, true), (20, false), (30, false)].iter() {
+            let witness = tree.non_membership_witness(&fr(x)).unwrap_or(low_of_25.clone());
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let root = alloc(cs.namespace(|| "root"), tree.root());
+            let value = alloc(cs.namespace(|| "value"), fr(x));
+            let low_leaf = alloc_leaf(cs.namespace(|| "low leaf"), &witness.low_leaf);
+            let low_index = alloc_index(cs.namespace(|| "low index"), witness.low_index);
+            let low_path = alloc_path(cs.namespace(|| "low path"), &witness.low_path);
+
+            enforce_non_membership(
+                cs.namespace(|| "non-membership"),
+                &params,
+                &root,
+                &value,
+                &low_leaf,
+                &low_index,
+                &low_path,
+                VALUE_BITS
+            ).unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Compression of the public inputs into their hash.
+//!
+//! The cost of verification grows with the number of public inputs.
+//! `HashedInputs` synthesizes a circuit with its inputs allocated as
+//! auxiliary variables instead and exposes only the first element of
+//! their `rescue_hash` as the single public input. The verifier
+//! computes it from the would-be inputs with `hash_inputs`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::PrimeField;
+
+use bellman::{
+    Circuit,
+    LinearCombination,
+    SynthesisError,
+    ConstraintSystem,
+    Variable
+};
+
+use std::marker::PhantomData;
+
+use super::num::AllocatedNum;
+
+use super::rescue::rescue_hash;
+
+use rescue::{
+    RescueParams,
+    rescue_hash as native_rescue_hash
+};
+
+/// The public input of a circuit wrapped in `HashedInputs` with the
+/// given inputs.
+pub fn hash_inputs<F: PrimeField>(params: &RescueParams<F>, inputs: &[F]) -> F {
+    native_rescue_hash(params, inputs)[0]
+}
+
+/// Constraint system adapter that allocates the public inputs as
+/// auxiliary variables and records them.
+pub struct InputHasher<E: Engine, CS: ConstraintSystem<E>> {
+    cs: CS,
+    inputs: Vec<AllocatedNum<E>>,
+    _marker: PhantomData<E>
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> InputHasher<E, CS> {
+    pub fn new(cs: CS) -> Self {
+        InputHasher {
+            cs: cs,
+            inputs: vec![],
+            _marker: PhantomData
+        }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Exposes the hash of the recorded inputs as the only public input
+    /// and returns the underlying constraint system.
+    pub fn finalize(mut self, params: &RescueParams<E::Fr>) -> Result<CS, SynthesisError> {
+        let hash = rescue_hash(self.cs.namespace(|| "input hash"), params, &self.inputs)?;
+        hash[0].inputize(self.cs.namespace(|| "input hash input"))?;
+
+        Ok(self.cs)
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> ConstraintSystem<E> for InputHasher<E, CS> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.cs.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        let input = AllocatedNum::alloc(self.cs.namespace(annotation), f)?;
+        let variable = input.get_variable();
+        self.inputs.push(input);
+
+        Ok(variable)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        annotation: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        self.cs.enforce(annotation, a, b, c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.cs.get_root().push_namespace(name_fn);
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        self.cs.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// `circuit` with its public inputs replaced by their hash.
+pub struct HashedInputs<'a, E: Engine, C: Circuit<E>> {
+    pub params: &'a RescueParams<E::Fr>,
+    pub circuit: C
+}
+
+impl<'a, E: Engine, C: Circuit<E>> Circuit<E> for HashedInputs<'a, E, C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>
+    {
+        let mut hasher = InputHasher::new(cs);
+        self.circuit.synthesize(&mut hasher)?;
+        hasher.finalize(self.params)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use ::rescue::generate_params;
+    use super::*;
+
+    /// Exposes its values and their product.
+    struct Product {
+        values: Vec<Fr>
+    }
+
+    impl Circuit<Bn256> for Product {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(self, cs: &mut CS) -> Result<(), SynthesisError>
+        {
+            let mut product: Option<AllocatedNum<Bn256>> = None;
+            for (i, value) in self.values.iter().enumerate() {
+                let num = AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(*value))?;
+                num.inputize(cs.namespace(|| format!("value {} input", i)))?;
+
+                product = Some(match product {
+                    Some(p) => p.mul(cs.namespace(|| format!("product {}", i)), &num)?,
+                    None => num
+                });
+            }
+
+            product.unwrap().inputize(cs.namespace(|| "product input"))
+        }
+    }
+
+    #[test]
+    fn test_hashed_inputs() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<Fr>(3, 128);
+        let values: Vec<Fr> = (0..6).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        Product { values: values.clone() }.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 1 + 7);
+
+        let inputs: Vec<Fr> = (1..cs.num_inputs()).map(|i| {
+            let path = if i < 7 { format!("value {} input/input variable", i - 1) } else { "product input/input variable".into() };
+            cs.get_input(i, &path)
+        }).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        HashedInputs {
+            params: &params,
+            circuit: Product { values: values }
+        }.synthesize(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 2);
+        assert!(cs.verify(&[hash_inputs(&params, &inputs)]));
+
+        cs.set("value 2 input/input variable/num", rng.gen());
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,595 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::num::Num;
+
+use util::fr_from_u64;
+
+const KECCAK_ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008
+];
+
+// Rotation offsets, indexed as [x + 5*y]
+const KECCAK_ROTATION_OFFSETS: [usize; 25] = [
+     0,  1, 62, 28, 27,
+    36, 44,  6, 55, 20,
+     3, 10, 43, 25, 39,
+    41, 45, 15, 21,  8,
+    18,  2, 61, 56, 14
+];
+
+pub const KECCAK_STATE_WIDTH: usize = 1600;
+pub const KECCAK_LANE_WIDTH: usize = 64;
+
+/// Rate of the Keccak-256 sponge in bits.
+pub const KECCAK_256_RATE: usize = 1088;
+
+/// Rates of the SHA-3 and SHAKE sponges in bits, i.e. 1600 bits
+/// minus a capacity of twice the security level.
+pub const SHA3_256_RATE: usize = 1088;
+pub const SHA3_512_RATE: usize = 576;
+pub const SHAKE128_RATE: usize = 1344;
+pub const SHAKE256_RATE: usize = 1088;
+
+/// Domain separation bytes of FIPS 202 (`01` and `1111` suffixes
+/// followed by the first bit of the padding).
+const SHA3_DELIMITER: u8 = 0x06;
+const SHAKE_DELIMITER: u8 = 0x1f;
+
+/// Lanes are represented in little-endian bit order.
+type Lane = Vec<Boolean>;
+
+fn lane_xor<E, CS>(
+    mut cs: CS,
+    a: &Lane,
+    b: &Lane
+) -> Result<Lane, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    a.iter().zip(b.iter()).enumerate().map(|(i, (a, b))| {
+        Boolean::xor(cs.namespace(|| format!("xor bit {}", i)), a, b)
+    }).collect()
+}
+
+fn lane_rotl(a: &Lane, by: usize) -> Lane {
+    (0..KECCAK_LANE_WIDTH).map(|i| {
+        a[(i + KECCAK_LANE_WIDTH - by) % KECCAK_LANE_WIDTH].clone()
+    }).collect()
+}
+
+/// Keccak-f[1600] permutation over the state of 25 lanes
+/// given as 1600 bits, lane `x + 5*y` occupying bits
+/// `64*(x + 5*y)..64*(x + 5*y + 1)` in little-endian order.
+pub fn keccak_f1600<E, CS>(
+    mut cs: CS,
+    state: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(state.len(), KECCAK_STATE_WIDTH);
+
+    let mut a: Vec<Lane> = state.chunks(KECCAK_LANE_WIDTH).map(|l| l.to_vec()).collect();
+
+    for (round, rc) in KECCAK_ROUND_CONSTANTS.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("round {}", round));
+
+        // theta
+        let mut c = vec![];
+        for x in 0..5 {
+            let cs = &mut cs.namespace(|| format!("theta column {}", x));
+            let mut tmp = a[x].clone();
+            for y in 1..5 {
+                tmp = lane_xor(cs.namespace(|| format!("xor row {}", y)), &tmp, &a[x + 5*y])?;
+            }
+            c.push(tmp);
+        }
+
+        let mut d = vec![];
+        for x in 0..5 {
+            d.push(lane_xor(
+                cs.namespace(|| format!("theta d {}", x)),
+                &c[(x + 4) % 5],
+                &lane_rotl(&c[(x + 1) % 5], 1)
+            )?);
+        }
+
+        for y in 0..5 {
+            for x in 0..5 {
+                a[x + 5*y] = lane_xor(
+                    cs.namespace(|| format!("theta apply {} {}", x, y)),
+                    &a[x + 5*y],
+                    &d[x]
+                )?;
+            }
+        }
+
+        // rho and pi
+        let mut b = vec![vec![]; 25];
+        for y in 0..5 {
+            for x in 0..5 {
+                b[y + 5*((2*x + 3*y) % 5)] = lane_rotl(&a[x + 5*y], KECCAK_ROTATION_OFFSETS[x + 5*y]);
+            }
+        }
+
+        // chi
+        for y in 0..5 {
+            for x in 0..5 {
+                let cs = &mut cs.namespace(|| format!("chi {} {}", x, y));
+                let mut lane = vec![];
+                for i in 0..KECCAK_LANE_WIDTH {
+                    let tmp = Boolean::and(
+                        cs.namespace(|| format!("and bit {}", i)),
+                        &b[(x + 1) % 5 + 5*y][i].not(),
+                        &b[(x + 2) % 5 + 5*y][i]
+                    )?;
+
+                    lane.push(Boolean::xor(
+                        cs.namespace(|| format!("xor bit {}", i)),
+                        &b[x + 5*y][i],
+                        &tmp
+                    )?);
+                }
+                a[x + 5*y] = lane;
+            }
+        }
+
+        // iota, xor with a constant is free
+        let rc: Lane = (0..KECCAK_LANE_WIDTH).map(|i| Boolean::constant((rc >> i) & 1 == 1)).collect();
+        a[0] = lane_xor(cs.namespace(|| "iota"), &a[0], &rc)?;
+    }
+
+    Ok(a.into_iter().flat_map(|l| l.into_iter()).collect())
+}
+
+/// Keccak sponge with the given `rate` (in bits) and
+/// domain separation byte, that is put right after the
+/// message as a part of the multi-rate padding. Input
+/// must be byte-aligned, every byte in little-endian bit order.
+/// Outputs longer than the rate are squeezed block by block.
+pub fn keccak_sponge<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    rate: usize,
+    delimiter: u8,
+    output_len: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+    assert!(rate % KECCAK_LANE_WIDTH == 0 && rate < KECCAK_STATE_WIDTH);
+
+    let mut padded = input.to_vec();
+    for i in 0..8 {
+        padded.push(Boolean::constant((delimiter >> i) & 1 == 1));
+    }
+    while padded.len() % rate != 0 {
+        padded.push(Boolean::constant(false));
+    }
+    // final bit of the padding
+    let last = padded.len() - 1;
+    padded[last] = Boolean::constant(true);
+
+    let mut state = vec![Boolean::constant(false); KECCAK_STATE_WIDTH];
+
+    for (i, block) in padded.chunks(rate).enumerate() {
+        let cs = &mut cs.namespace(|| format!("block {}", i));
+
+        for (j, bit) in block.iter().enumerate() {
+            state[j] = Boolean::xor(
+                cs.namespace(|| format!("absorb bit {}", j)),
+                &state[j],
+                bit
+            )?;
+        }
+
+        state = keccak_f1600(cs.namespace(|| "permutation"), &state)?;
+    }
+
+    let mut output = state[..rate].to_vec();
+    let mut i = 0;
+    while output.len() < output_len {
+        state = keccak_f1600(cs.namespace(|| format!("squeeze {}", i)), &state)?;
+        output.extend_from_slice(&state[..rate]);
+        i += 1;
+    }
+
+    output.truncate(output_len);
+
+    Ok(output)
+}
+
+/// Keccak-256 hash as used by Ethereum (original Keccak padding).
+/// Input must be byte-aligned, every byte in little-endian bit order.
+/// Output is 256 bits with the same byte layout.
+pub fn keccak256<E, CS>(
+    cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    keccak_sponge(cs, input, KECCAK_256_RATE, 0x01, 256)
+}
+
+/// Keccak-256 hash of the first `length` bytes of the `input`, with
+/// the length known only to the prover. The bytes past the length
+/// are enforced to be zero. Every block the input can span is
+/// permuted, and the state after the block holding the end of the
+/// padding is selected, so the cost is that of the longest input.
+pub fn keccak256_var<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    length: &Num<E>
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+
+    let one = CS::one();
+    let rate = KECCAK_256_RATE / 8;
+    let max_length = input.len() / 8;
+    let num_blocks = max_length / rate + 1;
+
+    let length_value = length.get_value().map(|length| {
+        (0..(max_length + 1)).find(|&j| fr_from_u64::<E::Fr>(j as u64) == length)
+    });
+
+    // past[j] is set iff j >= length
+    let mut past = vec![];
+    for j in 0..max_length {
+        past.push(Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| format!("past length {}", j)),
+            length_value.map(|l| j >= l.unwrap_or(max_length + 1))
+        )?));
+    }
+
+    for j in 1..max_length {
+        cs.enforce(
+            || format!("past length is monotone {}", j),
+            |_| past[j - 1].lc(one, E::Fr::one()),
+            |lc| lc + one - &past[j].lc(one, E::Fr::one()),
+            |lc| lc
+        );
+    }
+
+    let mut message_length = LinearCombination::<E>::zero();
+    for p in past.iter() {
+        message_length = message_length + one - &p.lc(one, E::Fr::one());
+    }
+    cs.enforce(
+        || "message length",
+        |lc| lc + &message_length - &length.lc(E::Fr::one()),
+        |lc| lc + one,
+        |lc| lc
+    );
+
+    for (j, p) in past.iter().enumerate() {
+        let byte = &input[(8 * j)..(8 * (j + 1))];
+        let mut coeff = E::Fr::one();
+        let mut byte_lc = LinearCombination::<E>::zero();
+        for bit in byte.iter() {
+            byte_lc = byte_lc + &bit.lc(one, coeff);
+            coeff.double();
+        }
+
+        cs.enforce(
+            || format!("zero past length {}", j),
+            |_| p.lc(one, E::Fr::one()),
+            |_| byte_lc,
+            |lc| lc
+        );
+    }
+
+    let past_at = |j: isize| -> Boolean {
+        if j < 0 {
+            Boolean::constant(false)
+        } else if j as usize >= max_length {
+            Boolean::constant(true)
+        } else {
+            past[j as usize].clone()
+        }
+    };
+
+    // the padding starts at the first byte past the length and
+    // ends in the last byte of the block holding that byte
+    let mut padded = Vec::with_capacity(num_blocks * KECCAK_256_RATE);
+    let mut last_blocks = vec![];
+    for j in 0..(num_blocks * rate) {
+        let cs = &mut cs.namespace(|| format!("pad byte {}", j));
+
+        let mut byte: Vec<Boolean> = if j < max_length {
+            input[(8 * j)..(8 * (j + 1))].to_vec()
+        } else {
+            vec![Boolean::constant(false); 8]
+        };
+
+        let first = Boolean::and(
+            cs.namespace(|| "first padding byte"),
+            &past_at(j as isize),
+            &past_at(j as isize - 1).not()
+        )?;
+        byte[0] = Boolean::xor(cs.namespace(|| "delimiter"), &byte[0], &first)?;
+
+        if j % rate == rate - 1 {
+            let last = Boolean::and(
+                cs.namespace(|| "last block"),
+                &past_at(j as isize),
+                &past_at((j + 1 - rate) as isize - 1).not()
+            )?;
+            byte[7] = Boolean::xor(cs.namespace(|| "final bit"), &byte[7], &last)?;
+
+            last_blocks.push(last);
+        }
+
+        padded.extend(byte);
+    }
+
+    let mut state = vec![Boolean::constant(false); KECCAK_STATE_WIDTH];
+    let mut output = vec![Boolean::constant(false); 256];
+
+    for (i, (block, last)) in padded.chunks(KECCAK_256_RATE).zip(last_blocks.iter()).enumerate() {
+        let cs = &mut cs.namespace(|| format!("block {}", i));
+
+        for (j, bit) in block.iter().enumerate() {
+            state[j] = Boolean::xor(
+                cs.namespace(|| format!("absorb bit {}", j)),
+                &state[j],
+                bit
+            )?;
+        }
+
+        state = keccak_f1600(cs.namespace(|| "permutation"), &state)?;
+
+        // exactly one block is the last one
+        for j in 0..256 {
+            let selected = Boolean::and(
+                cs.namespace(|| format!("select bit {}", j)),
+                last,
+                &state[j]
+            )?;
+
+            output[j] = Boolean::xor(
+                cs.namespace(|| format!("output bit {}", j)),
+                &output[j],
+                &selected
+            )?;
+        }
+    }
+
+    Ok(output)
+}
+
+/// SHA3-256 hash of FIPS 202, with the same bit layout as `keccak256`.
+pub fn sha3_256<E, CS>(
+    cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    keccak_sponge(cs, input, SHA3_256_RATE, SHA3_DELIMITER, 256)
+}
+
+/// SHA3-512 hash of FIPS 202, with the same bit layout as `keccak256`.
+pub fn sha3_512<E, CS>(
+    cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    keccak_sponge(cs, input, SHA3_512_RATE, SHA3_DELIMITER, 512)
+}
+
+/// SHAKE128 extendable output function, returning `output_len` bits.
+/// Expanding a seed with it gives arbitrarily many pseudorandom bits
+/// inside the circuit.
+pub fn shake128<E, CS>(
+    cs: CS,
+    input: &[Boolean],
+    output_len: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    keccak_sponge(cs, input, SHAKE128_RATE, SHAKE_DELIMITER, output_len)
+}
+
+/// SHAKE256 extendable output function, returning `output_len` bits.
+pub fn shake256<E, CS>(
+    cs: CS,
+    input: &[Boolean],
+    output_len: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    keccak_sponge(cs, input, SHAKE256_RATE, SHAKE_DELIMITER, output_len)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::Field;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use ::circuit::test::TestConstraintSystem;
+    use ::circuit::num::Num;
+    use ::util::fr_from_u64;
+    use super::{keccak256, keccak256_var, sha3_256, sha3_512, shake128, shake256};
+    use bellman::{ConstraintSystem};
+    use tiny_keccak::{Keccak, Shake, Hasher, Xof};
+
+    fn bytes_to_bits(data: &[u8]) -> Vec<Boolean> {
+        data.iter().flat_map(|&byte| (0..8).map(move |i| Boolean::constant((byte >> i) & 1 == 1))).collect()
+    }
+
+    fn bits_to_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|chunk| {
+            chunk.iter().enumerate().fold(0u8, |acc, (i, b)| acc | ((b.get_value().unwrap() as u8) << i))
+        }).collect()
+    }
+
+    #[test]
+    fn test_keccak256_constant_constraints() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let input_bits: Vec<_> = (0..512).map(|_| Boolean::constant(rng.gen())).collect();
+        keccak256(&mut cs, &input_bits).unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_keccak256() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &input_len in [0, 1, 32, 64, 135, 136, 137, 200].iter() {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+
+            let mut h = Keccak::v256();
+            h.update(&data);
+            let mut expected = [0u8; 32];
+            h.finalize(&mut expected);
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let mut input_bits = vec![];
+
+            for (byte_i, input_byte) in data.into_iter().enumerate() {
+                for bit_i in 0..8 {
+                    let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+
+                    input_bits.push(AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+                }
+            }
+
+            let r = keccak256(&mut cs, &input_bits).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let mut s = r.into_iter();
+            for b in expected.iter() {
+                for i in 0..8 {
+                    let c = s.next().unwrap().get_value().unwrap();
+
+                    assert_eq!(c, (b >> i) & 1u8 == 1u8);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_keccak256_var() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // a window of two blocks, the padding ending in either
+        for &input_len in [0, 1, 135, 136, 150].iter() {
+            let mut data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+
+            let mut h = Keccak::v256();
+            h.update(&data);
+            let mut expected = [0u8; 32];
+            h.finalize(&mut expected);
+
+            data.resize(150, 0);
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let input_bits: Vec<Boolean> = data.iter().enumerate().flat_map(|(byte_i, &input_byte)| {
+                (0..8).map(|bit_i| {
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)),
+                        Some((input_byte >> bit_i) & 1u8 == 1u8)
+                    ).unwrap().into()
+                }).collect::<Vec<_>>()
+            }).collect();
+            let length = Num::constant(TestConstraintSystem::<Bn256>::one(), fr_from_u64(input_len as u64));
+
+            let r = keccak256_var(cs.namespace(|| "keccak"), &input_bits, &length).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(bits_to_bytes(&r), expected.to_vec());
+
+            // the length must cover all the non-zero bytes
+            if input_len < 150 {
+                cs.set(&format!("input bit {} 0/boolean", input_len), Fr::one());
+                assert_eq!(cs.which_is_unsatisfied().unwrap(), format!("keccak/zero past length {}", input_len));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sha3() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let empty_256 = sha3_256(cs.namespace(|| "sha3-256 empty"), &[]).unwrap();
+        assert_eq!(bits_to_bytes(&empty_256), hex!("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a").to_vec());
+
+        let abc_256 = sha3_256(cs.namespace(|| "sha3-256 abc"), &bytes_to_bits(b"abc")).unwrap();
+        assert_eq!(bits_to_bytes(&abc_256), hex!("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532").to_vec());
+
+        let empty_512 = sha3_512(cs.namespace(|| "sha3-512 empty"), &[]).unwrap();
+        assert_eq!(bits_to_bytes(&empty_512), hex!("a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26").to_vec());
+    }
+
+    #[test]
+    fn test_shake() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // outputs shorter than a block and spanning several blocks
+        for &(input_len, output_len) in [(0, 32), (17, 168), (168, 200), (100, 500)].iter() {
+            let data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let input_bits: Vec<Boolean> = data.iter().enumerate().flat_map(|(byte_i, &input_byte)| {
+                (0..8).map(|bit_i| {
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)),
+                        Some((input_byte >> bit_i) & 1u8 == 1u8)
+                    ).unwrap().into()
+                }).collect::<Vec<_>>()
+            }).collect();
+
+            let r_128 = shake128(cs.namespace(|| "shake128"), &input_bits, output_len * 8).unwrap();
+            let r_256 = shake256(cs.namespace(|| "shake256"), &input_bits, output_len * 8).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let mut expected = vec![0u8; output_len];
+            let mut h = Shake::v128();
+            h.update(&data);
+            h.squeeze(&mut expected);
+            assert_eq!(bits_to_bytes(&r_128), expected);
+
+            let mut h = Shake::v256();
+            h.update(&data);
+            h.squeeze(&mut expected);
+            assert_eq!(bits_to_bytes(&r_256), expected);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Verification of a `key_rotation` chain of at most `k` rotations,
+//! where the number of rotations is a variable.
+//!
+//! Rotation `i` is active iff `i < length`. The active flags are
+//! constrained to be a prefix of ones summing to `length`, so
+//! `length <= k`. Inactive rotations are witnessed with any point of
+//! large order as `r` and don't change the current key.
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::bounded_loop::active_flags;
+
+use super::ecc::EdwardsPoint;
+
+use super::baby_eddsa::EddsaSignature;
+
+use jubjub::JubjubEngine;
+
+use key_rotation::KEY_ROTATION_TAG;
+
+use rescue::RescueParams;
+
+use util::fr_from_u64;
+
+#[derive(Clone)]
+pub struct AllocatedRotation<E: JubjubEngine> {
+    pub new_key: EdwardsPoint<E>,
+    /// The signature of the previous key, `R` and `S`.
+    pub r: EdwardsPoint<E>,
+    pub s: AllocatedNum<E>
+}
+
+/// Enforces that the first `length` of `rotations` form a valid chain
+/// from `initial`, starting at `first_epoch`, and returns the last key.
+pub fn verify_rotation_chain<E, CS>(
+    mut cs: CS,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    generator: &EdwardsPoint<E>,
+    initial: &EdwardsPoint<E>,
+    first_epoch: &AllocatedNum<E>,
+    length: &AllocatedNum<E>,
+    rotations: &[AllocatedRotation<E>]
+) -> Result<EdwardsPoint<E>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let active = active_flags(cs.namespace(|| "active flags"), length, rotations.len())?;
+
+    let tag = Num::constant(CS::one(), fr_from_u64(KEY_ROTATION_TAG));
+
+    let mut key = initial.clone();
+    for (i, (rotation, active)) in rotations.iter().zip(active.iter()).enumerate() {
+        let mut cs = cs.namespace(|| format!("rotation {}", i));
+
+        let epoch = Num::from(first_epoch.clone()).add_bool_with_coeff(
+            CS::one(),
+            &Boolean::constant(true),
+            fr_from_u64(i as u64)
+        );
+
+        rotation.new_key.assert_not_small_order(cs.namespace(|| "new key order"), params)?;
+
+        let message = [
+            tag.clone(),
+            epoch,
+            Num::from(rotation.new_key.get_x().clone()),
+            Num::from(rotation.new_key.get_y().clone())
+        ];
+
+        let signature = EddsaSignature {
+            r: rotation.r.clone(),
+            s: rotation.s.clone(),
+            pk: key.clone()
+        };
+
+        signature.verify_raw_scalar_if(
+            cs.namespace(|| "signature"),
+            active,
+            params,
+            rescue_params,
+            &message,
+            generator.clone()
+        )?;
+
+        key = EdwardsPoint::select(cs.namespace(|| "next key"), &rotation.new_key, &key, active)?;
+    }
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+    use ::circuit::test::*;
+    use alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use jubjub::{FixedGenerators, JubjubParams, Unknown, edwards};
+    use eddsa::{PrivateKey, PublicKey};
+    use key_rotation::{Rotation, rotate};
+    use rescue::generate_params;
+    use super::*;
+
+    fn fs_to_fr(s: Fs) -> Fr {
+        let mut bytes = vec![];
+        s.into_repr().write_le(&mut bytes).unwrap();
+
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.read_le(&bytes[..]).unwrap();
+
+        Fr::from_repr(repr).unwrap()
+    }
+
+    #[test]
+    fn test_rotation_chain() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let keys: Vec<PrivateKey<Bn256>> = (0..4).map(|_| PrivateKey(rng.gen())).collect();
+        let public: Vec<PublicKey<Bn256>> = keys.iter().map(|k| PublicKey::from_private(k, p_g, params)).collect();
+        let rotations: Vec<Rotation<Bn256>> = (0..3).map(|i| {
+            rotate(&keys[i], 7 + i as u64, &public[i + 1], p_g, params, &rescue_params)
+        }).collect();
+
+        let g: edwards::Point<Bn256, Unknown> = params.generator(p_g).clone().into();
+
+        for &(length, satisfied) in [(3u64, true), (1, true), (0, true), (5, false)].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let generator = EdwardsPoint::witness(cs.namespace(|| "generator"), Some(g.clone()), params).unwrap();
+            let initial = EdwardsPoint::witness(cs.namespace(|| "initial"), Some(public[0].0.clone()), params).unwrap();
+            let first_epoch = AllocatedNum::alloc(cs.namespace(|| "first epoch"), || Ok(fr(7))).unwrap();
+            let length_num = AllocatedNum::alloc(cs.namespace(|| "length"), || Ok(fr(length))).unwrap();
+
+            // the last rotation is a dummy
+            let allocated: Vec<AllocatedRotation<Bn256>> = (0..4).map(|i| {
+                let (new_key, r, s) = if i < 3 {
+                    (rotations[i].new_key.0.clone(), rotations[i].signature.r.clone(), rotations[i].signature.s)
+                } else {
+                    (g.clone(), g.clone(), Fs::zero())
+                };
+
+                AllocatedRotation {
+                    new_key: EdwardsPoint::witness(cs.namespace(|| format!("new key {}", i)), Some(new_key), params).unwrap(),
+                    r: EdwardsPoint::witness(cs.namespace(|| format!("r {}", i)), Some(r), params).unwrap(),
+                    s: AllocatedNum::alloc(cs.namespace(|| format!("s {}", i)), || Ok(fs_to_fr(s))).unwrap()
+                }
+            }).collect();
+
+            let last = verify_rotation_chain(
+                cs.namespace(|| "chain"),
+                params,
+                &rescue_params,
+                &generator,
+                &initial,
+                &first_epoch,
+                &length_num,
+                &allocated
+            ).unwrap();
+
+            assert_eq!(cs.is_satisfied(), satisfied);
+            if satisfied {
+                let expected = public[length as usize].0.into_xy();
+                assert_eq!(last.get_x().get_value().unwrap(), expected.0);
+                assert_eq!(last.get_y().get_value().unwrap(), expected.1);
+
+                // a rotation at another epoch is rejected
+                if length > 0 {
+                    cs.set("first epoch/num", fr(8));
+                    assert!(!cs.is_satisfied());
+                }
+            }
+        }
+    }
+}
@@ -193,6 +193,177 @@ pub fn lookup3_xy_with_conditional_negation<E: Engine, CS>(
     Ok((x, y.into()))
 }
 
+/// Products of all the subsets of `bits`, indexed by the bitmask
+/// of the subset, `2^n - n - 1` constraints for `n` bits.
+fn monomials<E: Engine, CS>(
+    mut cs: CS,
+    bits: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let mut monomials = vec![Boolean::constant(true)];
+
+    for (i, bit) in bits.iter().enumerate() {
+        for j in 0..(1 << i) {
+            let m = if j == 0 {
+                bit.clone()
+            } else {
+                Boolean::and(
+                    cs.namespace(|| format!("monomial {}", j | (1 << i))),
+                    &monomials[j],
+                    bit
+                )?
+            };
+
+            monomials.push(m);
+        }
+    }
+
+    Ok(monomials)
+}
+
+fn table_index(bits: &[Boolean]) -> Option<usize> {
+    let mut index = 0;
+    for (i, bit) in bits.iter().enumerate() {
+        if bit.get_value()? {
+            index |= 1 << i;
+        }
+    }
+
+    Some(index)
+}
+
+/// Looks up every column of constants at the index given by `bits`
+/// in little-endian order. The table is interpolated as a multilinear
+/// polynomial of the bits, the products of all but the first bit are
+/// shared by the columns and the first bit is multiplied in by the
+/// output constraint, so the cost is `2^(n-1) - n` constraints plus one
+/// per column, as for `lookup3_xy` with `n = 3`.
+fn lookup_n_columns<E: Engine, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    columns: &[Vec<E::Fr>]
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let n = bits.len();
+    assert!(n >= 1);
+
+    let index = table_index(bits);
+    let monomials = monomials(cs.namespace(|| "monomials"), &bits[1..])?;
+
+    let one = CS::one();
+
+    columns.iter().enumerate().map(|(c, column)| {
+        assert_eq!(column.len(), 1 << n);
+
+        let res = AllocatedNum::alloc(
+            cs.namespace(|| format!("column {}", c)),
+            || {
+                Ok(column[*index.get()?])
+            }
+        )?;
+
+        let mut coeffs = vec![E::Fr::zero(); 1 << n];
+        synth::<E, _>(n, column.iter(), &mut coeffs);
+
+        cs.enforce(
+            || format!("column {} lookup", c),
+            |lc| monomials.iter().enumerate().fold(lc, |lc, (mask, m)| {
+                lc + &m.lc::<E>(one, coeffs[(mask << 1) | 1])
+            }),
+            |lc| lc + &bits[0].lc::<E>(one, E::Fr::one()),
+            |lc| monomials.iter().enumerate().fold(lc + res.get_variable(), |lc, (mask, m)| {
+                lc - &m.lc::<E>(one, coeffs[mask << 1])
+            })
+        );
+
+        Ok(res)
+    }).collect()
+}
+
+/// Performs a table lookup of a constant at the index given by
+/// `bits` in little-endian order, e.g. 5 or 8 bits for wider windows
+/// and table-driven S-boxes.
+pub fn lookup_n<E: Engine, CS>(
+    cs: CS,
+    bits: &[Boolean],
+    table: &[E::Fr]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let mut res = lookup_n_columns(cs, bits, &[table.to_vec()])?;
+
+    Ok(res.pop().unwrap())
+}
+
+/// Same as `lookup_n` for a table of coordinate pairs, which share
+/// the products of the bits.
+pub fn lookup_n_xy<E: Engine, CS>(
+    cs: CS,
+    bits: &[Boolean],
+    coords: &[(E::Fr, E::Fr)]
+) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let columns = vec![
+        coords.iter().map(|c| c.0).collect(),
+        coords.iter().map(|c| c.1).collect()
+    ];
+
+    let mut res = lookup_n_columns(cs, bits, &columns)?;
+    let y = res.pop().unwrap();
+    let x = res.pop().unwrap();
+
+    Ok((x, y))
+}
+
+/// Performs a lookup in a table of allocated numbers with a balanced
+/// selection tree, halving the table with every bit starting from
+/// the least significant one, `2^n - 1` constraints for `n` bits.
+pub fn lookup_n_variable<E: Engine, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    table: &[AllocatedNum<E>]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    assert_eq!(table.len(), 1 << bits.len());
+
+    let mut cur = table.to_vec();
+    for (i, bit) in bits.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("level {}", i));
+
+        cur = cur.chunks(2).enumerate().map(|(j, pair)| {
+            AllocatedNum::conditionally_select(
+                cs.namespace(|| format!("select {}", j)),
+                &pair[1],
+                &pair[0],
+                bit
+            )
+        }).collect::<Result<Vec<_>, _>>()?;
+    }
+
+    Ok(cur.pop().unwrap())
+}
+
+/// Same as `lookup_n_variable` for a table of coordinate pairs.
+pub fn lookup_n_variable_xy<E: Engine, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    coords: &[(AllocatedNum<E>, AllocatedNum<E>)]
+) -> Result<(AllocatedNum<E>, AllocatedNum<E>), SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let xs: Vec<_> = coords.iter().map(|c| c.0.clone()).collect();
+    let ys: Vec<_> = coords.iter().map(|c| c.1.clone()).collect();
+
+    let x = lookup_n_variable(cs.namespace(|| "x"), bits, &xs)?;
+    let y = lookup_n_variable(cs.namespace(|| "y"), bits, &ys)?;
+
+    Ok((x, y))
+}
+
 #[cfg(test)]
 mod test {
     use rand::{SeedableRng, Rand, Rng, XorShiftRng};
@@ -282,6 +453,67 @@ mod test {
         }
     }
 
+    fn alloc_bits(cs: &mut TestConstraintSystem<Bls12>, index: usize, num_bits: usize) -> Vec<Boolean> {
+        (0..num_bits).map(|i| {
+            Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some((index >> i) & 1 == 1)).unwrap()
+            )
+        }).collect()
+    }
+
+    #[test]
+    fn test_lookup_n() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &num_bits in [1, 3, 5, 8].iter() {
+            let points: Vec<(Fr, Fr)> = (0..(1 << num_bits)).map(|_| (rng.gen(), rng.gen())).collect();
+
+            for _ in 0..10 {
+                let index = rng.gen::<usize>() % (1 << num_bits);
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let bits = alloc_bits(&mut cs, index, num_bits);
+                let num_constraints = cs.num_constraints();
+
+                let res = lookup_n_xy(cs.namespace(|| "lookup"), &bits, &points).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(res.0.get_value().unwrap(), points[index].0);
+                assert_eq!(res.1.get_value().unwrap(), points[index].1);
+                assert_eq!(cs.num_constraints() - num_constraints, (1 << (num_bits - 1)) - num_bits + 2);
+
+                let table: Vec<Fr> = points.iter().map(|p| p.0).collect();
+                let res = lookup_n(cs.namespace(|| "single lookup"), &bits, &table).unwrap();
+                assert!(cs.is_satisfied());
+                assert_eq!(res.get_value().unwrap(), points[index].0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_n_variable() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let index = rng.gen::<usize>() % 32;
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let bits = alloc_bits(&mut cs, index, 5);
+
+            let values: Vec<Fr> = (0..32).map(|_| rng.gen()).collect();
+            let table: Vec<_> = values.iter().enumerate().map(|(i, v)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("entry {}", i)), || Ok(*v)).unwrap()
+            }).collect();
+            let num_constraints = cs.num_constraints();
+
+            let res = lookup_n_variable(cs.namespace(|| "lookup"), &bits, &table).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(res.get_value().unwrap(), values[index]);
+            assert_eq!(cs.num_constraints() - num_constraints, 31);
+        }
+    }
+
     #[test]
     fn test_synth() {
         let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
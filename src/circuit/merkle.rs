@@ -45,6 +45,67 @@ pub trait MerkleTree<E: Engine> {
 }
 
 
+/// Largest number of leaves accepted by `compute_root`.
+pub const MAX_FULL_TREE_LEAVES: usize = 1 << 10;
+
+/// Computes the root of a complete tree from all of its leaf hashes,
+/// hashing the nodes with `hash_node` of the given tree, so the result
+/// matches a tree built off-circuit with the same hasher. The number of
+/// leaves must be a power of the branching factor.
+pub fn compute_root_from_hashes<E, T, CS>(
+    mut cs: CS,
+    tree: &T,
+    leaves: &[T::Hash]
+) -> Result<T::Hash, SynthesisError>
+    where E: Engine,
+          T: MerkleTree<E>,
+          T::Hash: Clone,
+          CS: ConstraintSystem<E>
+{
+    let branching = tree.branching_factor();
+
+    assert!(!leaves.is_empty());
+    assert!(leaves.len() <= MAX_FULL_TREE_LEAVES);
+
+    let mut width = leaves.len();
+    while width % branching == 0 {
+        width /= branching;
+    }
+    assert_eq!(width, 1, "number of leaves must be a power of the branching factor");
+
+    let mut cur = leaves.to_vec();
+    let mut level = 0;
+    while cur.len() > 1 {
+        let mut cs = cs.namespace(|| format!("level {}", level));
+
+        cur = cur.chunks(branching).enumerate().map(|(i, children)| {
+            tree.hash_node(cs.namespace(|| format!("node {}", i)), children, level)
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        level += 1;
+    }
+
+    Ok(cur.pop().unwrap())
+}
+
+/// Same as `compute_root_from_hashes`, but hashes the leaves first.
+pub fn compute_root<E, T, CS>(
+    mut cs: CS,
+    tree: &T,
+    leaves: &[Vec<Boolean>]
+) -> Result<T::Hash, SynthesisError>
+    where E: Engine,
+          T: MerkleTree<E>,
+          T::Hash: Clone,
+          CS: ConstraintSystem<E>
+{
+    let hashes = leaves.iter().enumerate().map(|(i, leaf)| {
+        tree.hash_leaf(cs.namespace(|| format!("leaf {}", i)), leaf)
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    compute_root_from_hashes(cs.namespace(|| "tree"), tree, &hashes)
+}
+
 pub struct PedersenHashTree<'a, E: JubjubEngine> {
     root: AllocatedNum<E>,
     height: usize,
@@ -943,4 +1004,48 @@ fn test_poseidon_quartic_tree() {
     // let inputs: Vec<AllocatedNum<Bn256>> = leaf_hashes.iter().enumerate().map(|(i, b)| {
     //         AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), Some(*b)).unwrap()
     // }).collect();
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_compute_root_of_quartic_tree() {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::PrimeField;
+    use crate::poseidon::{self};
+    use crate::poseidon::bn256::Bn256PoseidonParams;
+    use crate::group_hash::BlakeHasher;
+    use crate::circuit::test::*;
+    use crate::circuit::num::AllocatedNum;
+
+    let leaf_hashes = (0..16).map(|el| Fr::from_str(&el.to_string()).unwrap()).collect::<Vec<_>>();
+    let params = Bn256PoseidonParams::new_for_quartic_tree::<BlakeHasher>();
+
+    let node_hashes: Vec<Fr> = leaf_hashes.chunks(4).map(|chunk| {
+        poseidon::poseidon_hash::<Bn256>(&params, &chunk[..])[0]
+    }).collect();
+    let root_hash = poseidon::poseidon_hash::<Bn256>(&params, &node_hashes[..])[0];
+
+    let mut cs = TestConstraintSystem::<Bn256>::new();
+
+    let leaves: Vec<AllocatedNum<Bn256>> = leaf_hashes.iter().enumerate().map(|(i, el)| {
+        AllocatedNum::alloc(cs.namespace(|| format!("leaf {}", i)), || Ok(*el)).unwrap()
+    }).collect();
+
+    let zero = AllocatedNum::alloc(cs.namespace(|| "zero"), || Ok(Fr::zero())).unwrap();
+
+    let tree = PoseidonHashTree::new(
+        zero.clone(),
+        2,
+        2,
+        &params,
+        zero
+    );
+
+    let root = compute_root_from_hashes(
+        cs.namespace(|| "compute root"),
+        &tree,
+        &leaves
+    ).unwrap();
+
+    assert!(cs.is_satisfied());
+    assert_eq!(root.get_value().unwrap(), root_hash);
+}
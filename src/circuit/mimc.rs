@@ -0,0 +1,203 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::{
+    Assignment
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::boolean::{
+    Boolean
+};
+
+use ::mimc::MimcSpongeParams;
+
+/// Computes `t^5` for the linear combination `t`
+/// using three constraints.
+fn fifth_power<E, CS>(
+    mut cs: CS,
+    t: &Num<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let t2 = AllocatedNum::alloc(cs.namespace(|| "t^2"), || {
+        let mut tmp = *t.get_value().get()?;
+        tmp.square();
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "t^2 constraint",
+        |_| t.lc(E::Fr::one()),
+        |_| t.lc(E::Fr::one()),
+        |lc| lc + t2.get_variable()
+    );
+
+    let t4 = t2.square(cs.namespace(|| "t^4"))?;
+
+    let t5 = AllocatedNum::alloc(cs.namespace(|| "t^5"), || {
+        let mut tmp = *t.get_value().get()?;
+        tmp.mul_assign(t4.get_value().get()?);
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "t^5 constraint",
+        |_| t.lc(E::Fr::one()),
+        |lc| lc + t4.get_variable(),
+        |lc| lc + t5.get_variable()
+    );
+
+    Ok(t5)
+}
+
+/// Gadget for `mimc::mimc_feistel`.
+pub fn mimc_feistel<E, CS>(
+    mut cs: CS,
+    params: &MimcSpongeParams<E>,
+    x_l: &Num<E>,
+    x_r: &Num<E>,
+    k: &Num<E>
+) -> Result<(Num<E>, Num<E>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut x_l = x_l.clone();
+    let mut x_r = x_r.clone();
+    let num_rounds = params.num_rounds();
+
+    for round in 0..num_rounds {
+        let mut t = x_l.clone();
+        t.add_assign(k);
+        t = t.add_bool_with_coeff(CS::one(), &Boolean::constant(true), *params.round_constant(round));
+
+        let t5 = fifth_power(cs.namespace(|| format!("round {}", round)), &t)?;
+
+        let mut tmp = x_r.clone();
+        tmp.add_assign(&Num::from(t5));
+
+        if round < num_rounds - 1 {
+            x_r = x_l;
+            x_l = tmp;
+        } else {
+            x_r = tmp;
+        }
+    }
+
+    Ok((x_l, x_r))
+}
+
+/// Gadget for `mimc::mimc_sponge_hash`, compatible
+/// with circomlib's `MiMCSponge` template.
+pub fn mimc_sponge_hash<E, CS>(
+    mut cs: CS,
+    params: &MimcSpongeParams<E>,
+    input: &[AllocatedNum<E>],
+    k: &Num<E>,
+    num_outputs: usize
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(num_outputs > 0);
+
+    let mut r = Num::zero();
+    let mut c = Num::zero();
+
+    let mut outputs = vec![];
+
+    for (i, el) in input.iter().enumerate() {
+        r.add_assign(&Num::from(el.clone()));
+        let (new_r, new_c) = mimc_feistel(
+            cs.namespace(|| format!("absorb element {}", i)),
+            params,
+            &r,
+            &c,
+            k
+        )?;
+        r = new_r;
+        c = new_c;
+    }
+
+    for i in 0..num_outputs {
+        if i > 0 {
+            let (new_r, new_c) = mimc_feistel(
+                cs.namespace(|| format!("squeeze element {}", i)),
+                params,
+                &r,
+                &c,
+                k
+            )?;
+            r = new_r;
+            c = new_c;
+        }
+
+        let output = AllocatedNum::alloc(cs.namespace(|| format!("output {}", i)), || {
+            Ok(*r.get_value().get()?)
+        })?;
+
+        cs.enforce(
+            || format!("output {} allocation", i),
+            |_| r.lc(E::Fr::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + output.get_variable()
+        );
+
+        outputs.push(output);
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use ::circuit::test::*;
+    use ::circuit::num::{AllocatedNum, Num};
+    use ::mimc::{self, MimcSpongeParams};
+    use super::mimc_sponge_hash;
+
+    #[test]
+    fn test_mimc_sponge_gadget() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = MimcSpongeParams::<Bn256>::new_circom();
+
+        let input: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+        let expected = mimc::mimc_sponge_hash(&params, &input, Fr::zero(), 2);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let input_words: Vec<AllocatedNum<Bn256>> = input.iter().enumerate().map(|(i, el)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap()
+        }).collect();
+
+        let res = mimc_sponge_hash(cs.namespace(|| "mimc"), &params, &input_words, &Num::zero(), 2).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].get_value().unwrap(), expected[0]);
+        assert_eq!(res[1].get_value().unwrap(), expected[1]);
+        // 3 constraints per round for 4 permutations and 2 output allocations
+        assert_eq!(cs.num_constraints(), 3 * 220 * 4 + 2);
+    }
+}
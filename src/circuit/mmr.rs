@@ -0,0 +1,187 @@
+//! Inclusion proofs of `mmr`.
+//!
+//! The number of leaves is a constant of the circuit, so the heights
+//! of the mountains are too, while the index of the leaf is witnessed.
+//! The path is hashed up to the highest mountain and the peak of the
+//! leaf's mountain is compared to the node at its height. A leaf is in
+//! the mountain of height `h` iff the bits of its index above `h`
+//! match the number of leaves and the bit `h` is zero.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::poseidon2::{
+    poseidon2_compress,
+    poseidon2_compress_nums
+};
+
+use mmr::{
+    mountain_heights,
+    max_height
+};
+
+use poseidon2::Poseidon2Params;
+
+use util::fr_from_u64;
+
+/// Number of bits of the index of a leaf.
+pub fn index_bits_len(num_leaves: u64) -> usize {
+    64 - num_leaves.leading_zeros() as usize
+}
+
+/// Computes the root of the range of `num_leaves` leaves from the
+/// `leaf` at the index with little-endian bits `index_bits` and the
+/// `path` and `peaks` of its `MmrProof`.
+pub fn compute_root<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    num_leaves: u64,
+    leaf: &AllocatedNum<E>,
+    index_bits: &[Boolean],
+    path: &[AllocatedNum<E>],
+    peaks: &[AllocatedNum<E>]
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let heights = mountain_heights(num_leaves);
+    assert!(!heights.is_empty());
+    assert_eq!(index_bits.len(), index_bits_len(num_leaves));
+    assert_eq!(path.len(), max_height(num_leaves));
+    assert_eq!(peaks.len(), heights.len());
+
+    // nodes[h] is the node at height h above the leaf
+    let mut nodes = vec![leaf.clone()];
+    for (i, (bit, sibling)) in index_bits.iter().zip(path.iter()).enumerate() {
+        let cs = &mut cs.namespace(|| format!("merkle tree hash {}", i));
+
+        let (left, right) = AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "conditional reversal of preimage"),
+            &nodes[i],
+            sibling,
+            bit
+        )?;
+
+        let node = poseidon2_compress(cs.namespace(|| "node hash"), params, &left, &right)?;
+        nodes.push(node);
+    }
+
+    // matches[i] is true iff the index bits from i up match num_leaves
+    let mut matches = vec![Boolean::constant(true); index_bits.len() + 1];
+    for i in (0..index_bits.len()).rev() {
+        let bit = if (num_leaves >> i) & 1 == 1 { index_bits[i].clone() } else { index_bits[i].not() };
+        matches[i] = Boolean::and(cs.namespace(|| format!("index matches from bit {}", i)), &matches[i + 1], &bit)?;
+    }
+
+    let mut selectors = LinearCombination::<E>::zero();
+    for (mountain, (&height, peak)) in heights.iter().zip(peaks.iter()).enumerate() {
+        let selected = Boolean::and(
+            cs.namespace(|| format!("leaf is in mountain {}", mountain)),
+            &matches[height + 1],
+            &index_bits[height].not()
+        )?;
+
+        cs.enforce(
+            || format!("peak of mountain {}", mountain),
+            |lc| lc + &selected.lc(CS::one(), E::Fr::one()),
+            |lc| lc + peak.get_variable() - nodes[height].get_variable(),
+            |lc| lc
+        );
+
+        selectors = selectors + &selected.lc(CS::one(), E::Fr::one());
+    }
+
+    // i.e. the index is below num_leaves
+    cs.enforce(
+        || "leaf is in a mountain",
+        |lc| lc + &selectors,
+        |lc| lc + CS::one(),
+        |lc| lc + CS::one()
+    );
+
+    let mut bag = peaks[peaks.len() - 1].clone();
+    for (i, peak) in peaks[..(peaks.len() - 1)].iter().enumerate().rev() {
+        bag = poseidon2_compress(cs.namespace(|| format!("bag peak {}", i)), params, peak, &bag)?;
+    }
+
+    let size = Num::constant(CS::one(), fr_from_u64(num_leaves));
+
+    poseidon2_compress_nums(cs.namespace(|| "root"), params, &size, &Num::from(bag))
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::test::*;
+    use ::poseidon2::generate_params;
+    use ::mmr::MerkleMountainRange;
+    use super::*;
+
+    #[test]
+    fn test_compute_root() {
+        let params = generate_params::<Fr>(3);
+        let mut mmr = MerkleMountainRange::new(&params);
+        for i in 0..11 {
+            mmr.append(fr(i + 1));
+        }
+        let num_leaves = mmr.num_leaves();
+
+        for index in 0..num_leaves {
+            let proof = mmr.prove(index);
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(fr(index + 1))).unwrap();
+            let index_bits: Vec<Boolean> = (0..index_bits_len(num_leaves)).map(|i| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("index bit {}", i)), Some((index >> i) & 1 == 1)).unwrap())
+            }).collect();
+            let path: Vec<_> = proof.path.iter().enumerate().map(|(i, node)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("path {}", i)), || Ok(*node)).unwrap()
+            }).collect();
+            let peaks: Vec<_> = proof.peaks.iter().enumerate().map(|(i, peak)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("peak {}", i)), || Ok(*peak)).unwrap()
+            }).collect();
+
+            let root = compute_root(cs.namespace(|| "root"), &params, num_leaves, &leaf, &index_bits, &path, &peaks).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(root.get_value().unwrap(), mmr.root());
+
+            cs.set("leaf/num", fr(index + 2));
+            assert!(!cs.is_satisfied());
+        }
+
+        // an index past the leaves
+        let proof = mmr.prove(10);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(fr(11))).unwrap();
+        let index_bits: Vec<Boolean> = (0..4).map(|i| Boolean::constant((11 >> i) & 1 == 1)).collect();
+        let path: Vec<_> = proof.path.iter().enumerate().map(|(i, node)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("path {}", i)), || Ok(*node)).unwrap()
+        }).collect();
+        let peaks: Vec<_> = proof.peaks.iter().enumerate().map(|(i, peak)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("peak {}", i)), || Ok(*peak)).unwrap()
+        }).collect();
+
+        compute_root(cs.namespace(|| "root"), &params, num_leaves, &leaf, &index_bits, &path, &peaks).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
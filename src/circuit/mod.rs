@@ -4,6 +4,7 @@ pub mod test;
 pub mod boolean;
 pub mod multieq;
 pub mod uint32;
+pub mod uint64;
 pub mod blake2s;
 pub mod num;
 pub mod lookup;
@@ -14,11 +15,57 @@ pub mod baby_pedersen_hash;
 pub mod multipack;
 pub mod sha256;
 pub mod baby_eddsa;
+pub mod sigma;
+pub mod key_rotation;
 pub mod float_point;
 pub mod polynomial_lookup;
 pub mod as_waksman;
 pub mod poseidon_hash;
 pub mod merkle;
+pub mod comparison;
+pub mod range_proof;
+pub mod set_membership;
+pub mod sorting;
+pub mod dynamic_table;
+pub mod conditional;
+pub mod bounded_loop;
+pub mod decimal;
+pub mod keccak;
+pub mod ethereum;
+pub mod u256;
+pub mod bitcoin;
+pub mod rlp;
+pub mod mimc;
+pub mod fingerprint;
+pub mod optimizer;
+pub mod cost_model;
+pub mod aes;
+pub mod chacha20;
+pub mod poly1305;
+pub mod hmac_sha256;
+pub mod polynomial;
+pub mod air;
+pub mod fp2;
+pub mod sbox;
+pub mod rescue;
+pub mod input_hashing;
+pub mod prf;
+pub mod scalar_reduction;
+pub mod scalar;
+pub mod anemoi;
+pub mod poseidon2;
+pub mod gmimc;
+pub mod indexed_merkle_tree;
+pub mod root_history;
+pub mod append_only_tree;
+pub mod mmr;
+pub mod bloom;
+pub mod commitment;
+pub mod commit_and_prove;
+pub mod pcd;
+pub mod state_transition;
+pub mod transcript;
+pub mod witness;
 
 pub mod sapling;
 pub mod sprout;
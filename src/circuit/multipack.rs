@@ -81,6 +81,44 @@ pub fn pack_into_variables<E, CS>(
     Ok(result)
 }
 
+/// Enforces the result bit of a verifier gadget to be true.
+///
+/// Verifier gadgets return their result as a `Boolean` so that it
+/// can be composed with other conditions ("soft" use); a circuit that
+/// only accepts valid proofs must enforce the final bit ("hard" use).
+pub fn enforce_result<E, CS>(
+    mut cs: CS,
+    flag: &Boolean
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    Boolean::enforce_equal(cs.namespace(|| "result is true"), flag, &Boolean::constant(true))
+}
+
+/// Exposes the result bit of a verifier gadget as a public input
+/// packed with `pack_into_inputs`, so that the verifier of the outer
+/// proof checks it instead.
+pub fn expose_result<E, CS>(
+    mut cs: CS,
+    flag: &Boolean
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    pack_into_inputs(cs.namespace(|| "result input"), &[flag.clone()])
+}
+
+/// Enforces the result bit of a verifier gadget to be true and
+/// exposes it as a public input.
+pub fn enforce_and_expose_result<E, CS>(
+    mut cs: CS,
+    flag: &Boolean
+) -> Result<(), SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    enforce_result(cs.namespace(|| "enforce"), flag)?;
+    expose_result(cs.namespace(|| "expose"), flag)
+}
+
 pub fn bytes_to_bits(bytes: &[u8]) -> Vec<bool>
 {
     bytes.iter()
@@ -120,6 +158,96 @@ pub fn compute_multipacking<E: Engine>(
     result
 }
 
+/// How a public input was allocated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputKind {
+    /// A field element exposed with `AllocatedNum::inputize`.
+    Field,
+    /// Booleans exposed with `pack_into_inputs`, taking
+    /// `ceil(num_bits / CAPACITY)` inputs.
+    Packed { num_bits: usize }
+}
+
+pub enum InputValue<E: Engine> {
+    Field(E::Fr),
+    Bits(Vec<bool>)
+}
+
+/// The public inputs of a circuit in allocation order, every one
+/// declared by the namespace it is allocated in. The verifier computes
+/// its inputs with `pack`, and `TestConstraintSystem::check_input_layout`
+/// checks that the circuit allocates them in the same order.
+#[derive(Clone, Debug)]
+pub struct InputLayout {
+    entries: Vec<(String, InputKind)>
+}
+
+impl InputLayout {
+    pub fn new() -> Self {
+        InputLayout {
+            entries: vec![]
+        }
+    }
+
+    pub fn field(&mut self, namespace: &str) {
+        self.entries.push((namespace.into(), InputKind::Field));
+    }
+
+    pub fn packed(&mut self, namespace: &str, num_bits: usize) {
+        self.entries.push((namespace.into(), InputKind::Packed { num_bits: num_bits }));
+    }
+
+    pub fn entries(&self) -> &[(String, InputKind)] {
+        &self.entries
+    }
+
+    /// Paths of the input variables, as named by `inputize` and
+    /// `pack_into_inputs`, with their kinds.
+    pub fn paths<E: Engine>(&self) -> Vec<(String, InputKind)> {
+        let mut result = vec![];
+        for &(ref namespace, kind) in self.entries.iter() {
+            match kind {
+                InputKind::Field => {
+                    result.push((format!("{}/input variable", namespace), kind));
+                },
+                InputKind::Packed { num_bits } => {
+                    let capacity = E::Fr::CAPACITY as usize;
+                    for i in 0..((num_bits + capacity - 1) / capacity) {
+                        result.push((format!("{}/input {}", namespace, i), kind));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn num_inputs<E: Engine>(&self) -> usize {
+        self.paths::<E>().len()
+    }
+
+    /// Computes the public inputs from one value per declaration.
+    pub fn pack<E: Engine>(&self, values: &[InputValue<E>]) -> Vec<E::Fr> {
+        assert_eq!(values.len(), self.entries.len(), "one value per declared input");
+
+        let mut result = vec![];
+        for (&(ref namespace, kind), value) in self.entries.iter().zip(values.iter()) {
+            match (kind, value) {
+                (InputKind::Field, &InputValue::Field(ref value)) => {
+                    result.push(*value);
+                },
+                (InputKind::Packed { num_bits }, &InputValue::Bits(ref bits)) => {
+                    assert_eq!(bits.len(), num_bits, "wrong number of bits for `{}`", namespace);
+                    result.extend(compute_multipacking::<E>(bits));
+                },
+                _ => panic!("wrong kind of value for `{}`", namespace)
+            }
+        }
+
+        result
+    }
+}
+
 #[test]
 fn test_multipacking() {
     use rand::{SeedableRng, Rng, XorShiftRng};
@@ -154,3 +282,27 @@ fn test_multipacking() {
         assert!(cs.verify(&expected_inputs));
     }
 }
+
+#[test]
+fn test_enforce_and_expose_result() {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bls12_381::{Bls12};
+    use ::circuit::test::*;
+    use super::boolean::{AllocatedBit, Boolean};
+
+    for &value in [false, true].iter() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let flag = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "flag"), Some(value)).unwrap());
+
+        expose_result(cs.namespace(|| "soft"), &flag).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&compute_multipacking::<Bls12>(&[value])));
+
+        enforce_and_expose_result(cs.namespace(|| "hard"), &flag).unwrap();
+        assert_eq!(cs.is_satisfied(), value);
+    }
+
+    // a constant false result can never be enforced
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    assert!(enforce_result(cs.namespace(|| "enforce"), &Boolean::constant(false)).is_err());
+}
@@ -26,6 +26,8 @@ use super::boolean::{
     AllocatedBit
 };
 
+use std::collections::HashMap;
+
 pub struct AllocatedNum<E: Engine> {
     value: Option<E::Fr>,
     variable: Variable
@@ -255,6 +257,25 @@ impl<E: Engine> AllocatedNum<E> {
         Ok(bits.into_iter().map(|b| Boolean::from(b)).collect())
     }
 
+    /// Little-endian bytes of the number in the encoding of
+    /// `PrimeFieldRepr::write_le`, with the bits of every byte
+    /// least significant first as taken by BLAKE2s. Hashes taking
+    /// the most significant bit first, like SHA-256, need them
+    /// reordered with `boolean::le_bits_into_le_bytes`.
+    pub fn to_bytes_le<CS>(
+        &self,
+        cs: CS
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let num_bits = <E::Fr as PrimeField>::Repr::default().as_ref().len() * 64;
+
+        let mut bits = self.into_bits_le_strict(cs)?;
+        bits.resize(num_bits, Boolean::constant(false));
+
+        Ok(bits)
+    }
+
     pub fn mul<CS>(
         &self,
         mut cs: CS,
@@ -374,6 +395,12 @@ impl<E: Engine> AllocatedNum<E> {
     ) -> Result<(Self, Self), SynthesisError>
         where CS: ConstraintSystem<E>
     {
+        match *condition {
+            Boolean::Constant(true) => return Ok((b.clone(), a.clone())),
+            Boolean::Constant(false) => return Ok((a.clone(), b.clone())),
+            _ => {}
+        }
+
         let c = Self::alloc(
             cs.namespace(|| "conditional reversal result 1"),
             || {
@@ -425,6 +452,12 @@ impl<E: Engine> AllocatedNum<E> {
     ) -> Result<Self, SynthesisError>
         where CS: ConstraintSystem<E>
     {
+        match *condition {
+            Boolean::Constant(true) => return Ok(a.clone()),
+            Boolean::Constant(false) => return Ok(b.clone()),
+            _ => {}
+        }
+
         let c = Self::alloc(
             cs.namespace(|| "conditional select result"),
             || {
@@ -612,6 +645,80 @@ impl<E: Engine> AllocatedNum<E> {
     }
 }
 
+/// Bit decompositions of allocated numbers made during one synthesis,
+/// keyed by their variable. Gadgets that need the bits of the same
+/// number share a cache, so the decomposition is only constrained
+/// once. A strict decomposition also serves non-strict requests.
+pub struct BitsCache {
+    bits: HashMap<Variable, (Vec<Boolean>, bool)>
+}
+
+impl BitsCache {
+    pub fn new() -> Self {
+        BitsCache {
+            bits: HashMap::new()
+        }
+    }
+
+    /// Same as `AllocatedNum::into_bits_le`, memoized.
+    pub fn into_bits_le<E, CS>(
+        &mut self,
+        cs: CS,
+        num: &AllocatedNum<E>
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        if let Some(&(ref bits, _)) = self.bits.get(&num.get_variable()) {
+            return Ok(bits.clone());
+        }
+
+        let bits = num.into_bits_le(cs)?;
+        self.bits.insert(num.get_variable(), (bits.clone(), false));
+
+        Ok(bits)
+    }
+
+    /// Same as `AllocatedNum::into_bits_le_strict`, memoized.
+    pub fn into_bits_le_strict<E, CS>(
+        &mut self,
+        cs: CS,
+        num: &AllocatedNum<E>
+    ) -> Result<Vec<Boolean>, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        if let Some(&(ref bits, true)) = self.bits.get(&num.get_variable()) {
+            return Ok(bits.clone());
+        }
+
+        let bits = num.into_bits_le_strict(cs)?;
+        self.bits.insert(num.get_variable(), (bits.clone(), true));
+
+        Ok(bits)
+    }
+
+    /// Records `bits`, already enforced to pack into `num`, as its
+    /// decomposition. There are fewer of them than the capacity, so
+    /// zero-padded they are the strict decomposition.
+    pub fn insert<E: Engine>(
+        &mut self,
+        num: &AllocatedNum<E>,
+        bits: &[Boolean]
+    )
+    {
+        assert!(bits.len() < E::Fr::CAPACITY as usize);
+
+        if let Some(&(_, true)) = self.bits.get(&num.get_variable()) {
+            return;
+        }
+
+        let mut bits = bits.to_vec();
+        bits.resize(E::Fr::NUM_BITS as usize, Boolean::constant(false));
+        self.bits.insert(num.get_variable(), (bits, true));
+    }
+}
+
 pub struct Num<E: Engine> {
     value: Option<E::Fr>,
     lc: LinearCombination<E>
@@ -643,6 +750,14 @@ impl<E: Engine> Num<E> {
         }
     }
 
+    /// The constant `value`, which takes no variable or constraint.
+    pub fn constant(one: Variable, value: E::Fr) -> Self {
+        Num {
+            value: Some(value),
+            lc: LinearCombination::zero() + (value, one)
+        }
+    }
+
     pub fn get_value(&self) -> Option<E::Fr> {
         self.value
     }
@@ -814,7 +929,7 @@ mod test {
     use bellman::pairing::bls12_381::{Bls12, Fr};
     use bellman::pairing::ff::{Field, PrimeField, BitIterator};
     use ::circuit::test::*;
-    use super::{AllocatedNum, Boolean};
+    use super::{AllocatedNum, BitsCache, Boolean};
 
     #[test]
     fn test_allocated_num() {
@@ -1030,4 +1145,60 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_bits_cache() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let r = Fr::rand(&mut rng);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut cache = BitsCache::new();
+
+        let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(r)).unwrap();
+        let bits = cache.into_bits_le(cs.namespace(|| "bits"), &n).unwrap();
+        let num_constraints = cs.num_constraints();
+
+        // the cache is keyed by the variable, so clones share the decomposition
+        let copy = n.clone();
+        let bits_again = cache.into_bits_le(cs.namespace(|| "bits again"), &copy).unwrap();
+        assert_eq!(cs.num_constraints(), num_constraints);
+        for (a, b) in bits.iter().zip(bits_again.iter()) {
+            assert!(a.get_variable().unwrap().is_same_variable(b.get_variable().unwrap()));
+        }
+
+        // the non-strict decomposition can't be reused for the strict one
+        let strict = cache.into_bits_le_strict(cs.namespace(|| "strict bits"), &n).unwrap();
+        assert!(cs.num_constraints() > num_constraints);
+        let num_constraints = cs.num_constraints();
+
+        let strict_again = cache.into_bits_le(cs.namespace(|| "strict bits again"), &n).unwrap();
+        assert_eq!(cs.num_constraints(), num_constraints);
+        for (a, b) in strict.iter().zip(strict_again.iter()) {
+            assert!(a.get_variable().unwrap().is_same_variable(b.get_variable().unwrap()));
+        }
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_to_bytes_le() {
+        use bellman::pairing::ff::PrimeFieldRepr;
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let value: Fr = rng.gen();
+        let mut expected = vec![];
+        value.into_repr().write_le(&mut expected).unwrap();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let n = AllocatedNum::alloc(&mut cs, || Ok(value)).unwrap();
+        let bits = n.to_bytes_le(cs.namespace(|| "to bytes")).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        let bytes: Vec<u8> = bits.chunks(8).map(|byte| {
+            byte.iter().rev().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+        }).collect();
+        assert_eq!(bytes, expected);
+    }
 }
@@ -0,0 +1,409 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::{
+    LinearCombination,
+    SynthesisError,
+    ConstraintSystem,
+    Variable,
+    Index
+};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::marker::PhantomData;
+
+use blake2_rfc::blake2s::Blake2s;
+use byteorder::{BigEndian, ByteOrder};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum VarKey {
+    Input(usize),
+    Aux(usize)
+}
+
+impl VarKey {
+    fn from_variable(var: Variable) -> Self {
+        match var.get_unchecked() {
+            Index::Input(i) => VarKey::Input(i),
+            Index::Aux(i) => VarKey::Aux(i)
+        }
+    }
+
+    fn into_variable(self) -> Variable {
+        match self {
+            VarKey::Input(i) => Variable::new_unchecked(Index::Input(i)),
+            VarKey::Aux(i) => Variable::new_unchecked(Index::Aux(i))
+        }
+    }
+}
+
+const ONE: VarKey = VarKey::Input(0);
+
+type Terms<E> = BTreeMap<VarKey, <E as Engine>::Fr>;
+
+fn add_term<E: Engine>(terms: &mut Terms<E>, var: VarKey, coeff: &E::Fr) {
+    let remove = {
+        let el = terms.entry(var).or_insert(E::Fr::zero());
+        el.add_assign(coeff);
+        el.is_zero()
+    };
+
+    if remove {
+        terms.remove(&var);
+    }
+}
+
+fn into_lc<E: Engine>(terms: &Terms<E>) -> LinearCombination<E> {
+    terms.iter().fold(LinearCombination::zero(), |lc, (var, coeff)| lc + (*coeff, var.into_variable()))
+}
+
+/// Returns `k` if the terms are `k * ONE`.
+fn as_constant<E: Engine>(terms: &Terms<E>) -> Option<E::Fr> {
+    match terms.len() {
+        0 => Some(E::Fr::zero()),
+        1 => terms.get(&ONE).cloned(),
+        _ => None
+    }
+}
+
+/// Constraint system adapter that eliminates linear constraints
+/// before they reach the underlying constraint system.
+///
+/// A constraint is linear if either `A` or `B` is a constant, which
+/// covers copies `a * 1 = b`, constants multiplied into linear
+/// combinations and the range of `Num` based compositions. For a linear
+/// constraint `L = 0` some auxiliary variable `v` of `L` that wasn't
+/// used by any forwarded constraint yet is expressed through the rest
+/// of `L` and substituted into all later constraints. Constraints that
+/// became duplicates after the substitution are dropped too.
+///
+/// Eliminated variables stay allocated in the underlying system
+/// but are no longer constrained, so the adapter must only be used
+/// for the whole circuit and never for variables that are referenced
+/// by the caller directly, e.g. public inputs are never eliminated.
+pub struct LinearOptimizer<E: Engine, CS: ConstraintSystem<E>> {
+    cs: CS,
+    substitutions: HashMap<VarKey, Terms<E>>,
+    used: HashSet<VarKey>,
+    seen: HashSet<[u8; 32]>,
+    num_eliminated: usize,
+    _marker: PhantomData<E>
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> LinearOptimizer<E, CS> {
+    pub fn new(cs: CS) -> Self {
+        LinearOptimizer {
+            cs: cs,
+            substitutions: HashMap::new(),
+            used: HashSet::new(),
+            seen: HashSet::new(),
+            num_eliminated: 0,
+            _marker: PhantomData
+        }
+    }
+
+    /// Number of constraints that were not forwarded.
+    pub fn num_eliminated(&self) -> usize {
+        self.num_eliminated
+    }
+
+    pub fn into_inner(self) -> CS {
+        self.cs
+    }
+
+    fn substitute(&self, lc: &LinearCombination<E>) -> Terms<E> {
+        let mut terms = Terms::<E>::new();
+        for &(var, ref coeff) in lc.as_ref() {
+            let key = VarKey::from_variable(var);
+            match self.substitutions.get(&key) {
+                Some(replacement) => {
+                    for (var, c) in replacement.iter() {
+                        let mut c = *c;
+                        c.mul_assign(coeff);
+                        add_term::<E>(&mut terms, *var, &c);
+                    }
+                },
+                None => add_term::<E>(&mut terms, key, coeff)
+            }
+        }
+
+        terms
+    }
+
+    /// Tries to eliminate a variable using `terms = 0`.
+    fn eliminate(&mut self, terms: &Terms<E>) -> bool {
+        if terms.is_empty() {
+            // trivially satisfied
+            return true;
+        }
+
+        // the most recently allocated variable is the most likely to be a copy
+        let pivot = terms.keys().rev().find(|var| match **var {
+            VarKey::Aux(_) => !self.used.contains(var),
+            VarKey::Input(_) => false
+        }).cloned();
+
+        let pivot = match pivot {
+            Some(pivot) => pivot,
+            None => return false
+        };
+
+        // v = -(terms - c * v) / c
+        let mut factor = terms[&pivot].inverse().expect("coefficients are non-zero");
+        factor.negate();
+
+        let mut replacement = Terms::<E>::new();
+        for (var, coeff) in terms.iter() {
+            if *var != pivot {
+                let mut c = *coeff;
+                c.mul_assign(&factor);
+                replacement.insert(*var, c);
+                self.used.insert(*var);
+            }
+        }
+
+        self.used.insert(pivot);
+        self.substitutions.insert(pivot, replacement);
+
+        true
+    }
+
+    fn digest(a: &Terms<E>, b: &Terms<E>, c: &Terms<E>) -> [u8; 32] {
+        let mut h = Blake2s::new(32);
+        let mut buf = [0u8; 9];
+        for terms in [a, b, c].iter() {
+            BigEndian::write_u64(&mut buf[0..8], terms.len() as u64);
+            h.update(&buf[0..8]);
+            for (var, coeff) in terms.iter() {
+                match *var {
+                    VarKey::Input(i) => {
+                        buf[0] = b'I';
+                        BigEndian::write_u64(&mut buf[1..9], i as u64);
+                    },
+                    VarKey::Aux(i) => {
+                        buf[0] = b'A';
+                        BigEndian::write_u64(&mut buf[1..9], i as u64);
+                    }
+                }
+                h.update(&buf);
+
+                let mut repr = vec![];
+                coeff.into_repr().write_be(&mut repr).unwrap();
+                h.update(&repr);
+            }
+        }
+
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(h.finalize().as_ref());
+
+        digest
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> ConstraintSystem<E> for LinearOptimizer<E, CS> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.cs.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        annotation: A,
+        a: LA,
+        b: LB,
+        c: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let a = self.substitute(&a(LinearCombination::zero()));
+        let b = self.substitute(&b(LinearCombination::zero()));
+        let c = self.substitute(&c(LinearCombination::zero()));
+
+        let linear = match (as_constant::<E>(&a), as_constant::<E>(&b)) {
+            (Some(k), _) => Some((k, &b)),
+            (_, Some(k)) => Some((k, &a)),
+            _ => None
+        };
+
+        if let Some((k, other)) = linear {
+            // k * other - c = 0
+            let mut terms = Terms::<E>::new();
+            for (var, coeff) in other.iter() {
+                let mut tmp = *coeff;
+                tmp.mul_assign(&k);
+                add_term::<E>(&mut terms, *var, &tmp);
+            }
+            for (var, coeff) in c.iter() {
+                let mut tmp = *coeff;
+                tmp.negate();
+                add_term::<E>(&mut terms, *var, &tmp);
+            }
+
+            if self.eliminate(&terms) {
+                self.num_eliminated += 1;
+                return;
+            }
+        }
+
+        if !self.seen.insert(Self::digest(&a, &b, &c)) {
+            self.num_eliminated += 1;
+            return;
+        }
+
+        for terms in [&a, &b, &c].iter() {
+            for var in terms.keys() {
+                self.used.insert(*var);
+            }
+        }
+
+        self.cs.enforce(annotation, |_| into_lc(&a), |_| into_lc(&b), |_| into_lc(&c));
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        self.cs.get_root().push_namespace(name_fn);
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        self.cs.get_root().pop_namespace();
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bls12_381::{Bls12, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::ConstraintSystem;
+    use ::circuit::test::*;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use ::circuit::num::{AllocatedNum, Num};
+    use super::LinearOptimizer;
+
+    #[test]
+    fn test_copies_are_eliminated() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let one = TestConstraintSystem::<Bls12>::one();
+        let mut cs = LinearOptimizer::new(TestConstraintSystem::<Bls12>::new());
+
+        let x_value: Fr = rng.gen();
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x_value)).unwrap();
+
+        // y = 3 * x + 5 through a copy
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+            let mut tmp = x_value;
+            tmp.mul_assign(&Fr::from_str("3").unwrap());
+            tmp.add_assign(&Fr::from_str("5").unwrap());
+            Ok(tmp)
+        }).unwrap();
+        cs.enforce(
+            || "y = 3x + 5",
+            |lc| lc + (Fr::from_str("3").unwrap(), x.get_variable()) + (Fr::from_str("5").unwrap(), one),
+            |lc| lc + one,
+            |lc| lc + y.get_variable()
+        );
+
+        let y2 = y.square(cs.namespace(|| "y^2")).unwrap();
+        // the same multiplication again
+        y.square(cs.namespace(|| "y^2 again")).unwrap();
+        y2.inputize(cs.namespace(|| "input")).unwrap();
+
+        assert_eq!(cs.num_eliminated(), 1);
+        let cs = cs.into_inner();
+        assert!(cs.is_satisfied());
+        // squaring, the repeated squaring (differs in the output) and the input
+        assert_eq!(cs.num_constraints(), 3);
+    }
+
+    #[test]
+    fn test_soundness_preserved() {
+        // a variable used in a multiplication before its linear definition
+        // must not be eliminated, otherwise the multiplication would be free
+        let one = TestConstraintSystem::<Bls12>::one();
+        let mut cs = LinearOptimizer::new(TestConstraintSystem::<Bls12>::new());
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("3").unwrap())).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("9").unwrap())).unwrap();
+        cs.enforce(|| "a * a = b", |lc| lc + a.get_variable(), |lc| lc + a.get_variable(), |lc| lc + b.get_variable());
+        cs.enforce(|| "b = 9", |lc| lc + b.get_variable(), |lc| lc + one, |lc| lc + (Fr::from_str("9").unwrap(), one));
+
+        let mut cs = cs.into_inner();
+        assert_eq!(cs.num_constraints(), 2);
+        assert!(cs.is_satisfied());
+
+        cs.set("b/num", Fr::from_str("10").unwrap());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_boolean_composition() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let mut plain = TestConstraintSystem::<Bls12>::new();
+            let mut optimized = LinearOptimizer::new(TestConstraintSystem::<Bls12>::new());
+
+            let values: Vec<bool> = (0..8).map(|_| rng.gen()).collect();
+
+            fn synthesize<CS: ConstraintSystem<Bls12>>(cs: &mut CS, values: &[bool]) {
+                let bits: Vec<Boolean> = values.iter().enumerate().map(|(i, b)| {
+                    Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*b)).unwrap())
+                }).collect();
+
+                let mut packed = Num::<Bls12>::zero();
+                let mut coeff = Fr::one();
+                for bit in bits.iter() {
+                    packed = packed.add_bool_with_coeff(CS::one(), bit, coeff);
+                    coeff.double();
+                }
+
+                let num = AllocatedNum::alloc(cs.namespace(|| "packed"), || Ok(packed.get_value().unwrap())).unwrap();
+                cs.enforce(|| "pack", |_| packed.lc(Fr::one()), |lc| lc + CS::one(), |lc| lc + num.get_variable());
+                let copy = AllocatedNum::alloc(cs.namespace(|| "copy"), || Ok(num.get_value().unwrap())).unwrap();
+                cs.enforce(|| "copy", |lc| lc + num.get_variable(), |lc| lc + CS::one(), |lc| lc + copy.get_variable());
+                copy.inputize(cs.namespace(|| "input")).unwrap();
+            }
+
+            synthesize(&mut plain, &values);
+            synthesize(&mut optimized, &values);
+
+            assert_eq!(optimized.num_eliminated(), 2);
+            let optimized = optimized.into_inner();
+            assert!(optimized.is_satisfied());
+            assert_eq!(optimized.num_constraints() + 2, plain.num_constraints());
+        }
+    }
+}
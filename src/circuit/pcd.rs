@@ -0,0 +1,212 @@
+//! Template of a proof-carrying data node.
+//!
+//! A node receives the messages of its predecessors together with
+//! proofs that they were computed correctly, checks the proofs, runs
+//! its own predicate on the messages and outputs a new message. The
+//! message is a fixed number of bits, exposed as the public inputs of
+//! the node with `multipack::pack_into_inputs`, so a proof of a node is
+//! checked against `multipack::compute_multipacking` of its message.
+//!
+//! The proof system is left to `RecursiveVerifierGadget`, and the order
+//! in which the nodes of a graph are proven to `pcd::PcdGraph`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    Circuit,
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::num::AllocatedNum;
+
+use super::multipack::{
+    pack_into_inputs,
+    pack_into_variables
+};
+
+/// Verifies a proof of a predecessor node in the circuit, e.g. a FRI
+/// or a pairing-based verifier.
+pub trait RecursiveVerifierGadget<E: Engine> {
+    type Proof;
+
+    /// Enforces that `proof` is valid for the public inputs `inputs`,
+    /// the packed message of the predecessor.
+    fn verify<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        inputs: &[AllocatedNum<E>],
+        proof: Option<&Self::Proof>
+    ) -> Result<(), SynthesisError>;
+}
+
+/// The application logic of a node.
+pub trait PcdPredicate<E: Engine> {
+    /// Computes the message of the node from the messages of its
+    /// predecessors, in the order of `PcdNode::incoming`.
+    fn synthesize<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        incoming: &[Vec<Boolean>]
+    ) -> Result<Vec<Boolean>, SynthesisError>;
+}
+
+pub struct Incoming<'a, E: Engine, V: RecursiveVerifierGadget<E> + 'a> {
+    /// The verifier for the circuit of the predecessor.
+    pub verifier: &'a V,
+    pub message: Option<Vec<bool>>,
+    pub proof: Option<V::Proof>
+}
+
+pub struct PcdNode<'a, E: Engine, V: RecursiveVerifierGadget<E> + 'a, P: PcdPredicate<E> + 'a> {
+    /// Number of bits of every message.
+    pub message_len: usize,
+    pub incoming: Vec<Incoming<'a, E, V>>,
+    pub predicate: &'a P
+}
+
+impl<'a, E, V, P> Circuit<E> for PcdNode<'a, E, V, P>
+    where E: Engine,
+          V: RecursiveVerifierGadget<E> + 'a,
+          P: PcdPredicate<E> + 'a
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>
+    {
+        let mut messages = vec![];
+
+        for (i, incoming) in self.incoming.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("incoming {}", i));
+
+            if let Some(ref message) = incoming.message {
+                assert_eq!(message.len(), self.message_len);
+            }
+
+            let mut bits = vec![];
+            for j in 0..self.message_len {
+                let bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("message bit {}", j)),
+                    incoming.message.as_ref().map(|m| m[j])
+                )?;
+
+                bits.push(Boolean::from(bit));
+            }
+
+            let inputs = pack_into_variables(cs.namespace(|| "pack message"), &bits)?;
+
+            incoming.verifier.verify(
+                cs.namespace(|| "verify proof"),
+                &inputs,
+                incoming.proof.as_ref()
+            )?;
+
+            messages.push(bits);
+        }
+
+        let output = self.predicate.synthesize(cs.namespace(|| "predicate"), &messages)?;
+        assert_eq!(output.len(), self.message_len);
+
+        pack_into_inputs(cs.namespace(|| "output"), &output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::Field;
+    use ::circuit::test::*;
+    use ::circuit::multieq::MultiEq;
+    use ::circuit::uint32::UInt32;
+    use ::circuit::multipack::compute_multipacking;
+    use super::*;
+
+    /// Accepts the message itself as its proof.
+    struct MessageVerifier;
+
+    impl RecursiveVerifierGadget<Bn256> for MessageVerifier {
+        type Proof = Fr;
+
+        fn verify<CS: ConstraintSystem<Bn256>>(
+            &self,
+            mut cs: CS,
+            inputs: &[AllocatedNum<Bn256>],
+            proof: Option<&Fr>
+        ) -> Result<(), SynthesisError>
+        {
+            let proof = AllocatedNum::alloc(cs.namespace(|| "proof"), || Ok(*proof.ok_or(SynthesisError::AssignmentMissing)?))?;
+
+            cs.enforce(
+                || "proof is the message",
+                |lc| lc + proof.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + inputs[0].get_variable()
+            );
+
+            Ok(())
+        }
+    }
+
+    /// Adds up the incoming counters and one.
+    struct Counter;
+
+    impl PcdPredicate<Bn256> for Counter {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(
+            &self,
+            cs: CS,
+            incoming: &[Vec<Boolean>]
+        ) -> Result<Vec<Boolean>, SynthesisError>
+        {
+            let mut cs = MultiEq::new(cs);
+
+            let mut operands: Vec<UInt32> = incoming.iter().map(|m| UInt32::from_bits(m)).collect();
+            operands.push(UInt32::constant(1));
+            if operands.len() == 1 {
+                operands.push(UInt32::constant(0));
+            }
+
+            Ok(UInt32::addmany(cs.namespace(|| "sum"), &operands)?.into_bits())
+        }
+    }
+
+    fn u32_to_bits(value: u32) -> Vec<bool> {
+        (0..32).map(|i| (value >> i) & 1 == 1).collect()
+    }
+
+    #[test]
+    fn test_pcd_node() {
+        let verifier = MessageVerifier;
+        let incoming: Vec<Incoming<Bn256, MessageVerifier>> = [3u32, 5].iter().map(|&v| {
+            let message = u32_to_bits(v);
+            let proof = compute_multipacking::<Bn256>(&message)[0];
+
+            Incoming {
+                verifier: &verifier,
+                message: Some(message),
+                proof: Some(proof)
+            }
+        }).collect();
+
+        let node = PcdNode {
+            message_len: 32,
+            incoming: incoming,
+            predicate: &Counter
+        };
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        node.synthesize(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.find_unconstrained(), "");
+        assert!(cs.verify(&compute_multipacking::<Bn256>(&u32_to_bits(9))));
+
+        cs.set("incoming 1/message bit 0/boolean", Fr::zero());
+        assert!(!cs.is_satisfied());
+    }
+}
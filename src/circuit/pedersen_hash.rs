@@ -1,7 +1,8 @@
 use super::*;
 use super::ecc::{
     MontgomeryPoint,
-    EdwardsPoint
+    EdwardsPoint,
+    fixed_base_multiplication_and_add
 };
 use super::boolean::Boolean;
 use ::jubjub::*;
@@ -29,7 +30,6 @@ pub fn pedersen_hash<E: JubjubEngine, CS>(
     where CS: ConstraintSystem<E>
 {
     let personalization = personalization.get_constant_bools();
-    assert_eq!(personalization.len(), 6);
 
     let mut edwards_result = None;
     let mut bits = personalization.iter().chain(bits.iter());
@@ -110,6 +110,36 @@ pub fn pedersen_hash<E: JubjubEngine, CS>(
     Ok(edwards_result.unwrap())
 }
 
+/// Computes the windowed Pedersen commitment
+/// `PedersenHash(personalization, bits) + r * R`, with the bits of
+/// the randomness `r` in little-endian order. The windows of the
+/// fixed-base multiplication by the note commitment randomness
+/// generator are added directly into the hash.
+pub fn pedersen_commitment<E: JubjubEngine, CS>(
+    mut cs: CS,
+    personalization: Personalization,
+    bits: &[Boolean],
+    randomness: &[Boolean],
+    params: &E::Params
+) -> Result<EdwardsPoint<E>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    let hash = pedersen_hash(
+        cs.namespace(|| "hash"),
+        personalization,
+        bits,
+        params
+    )?;
+
+    fixed_base_multiplication_and_add(
+        cs.namespace(|| "randomization"),
+        FixedGenerators::NoteCommitmentRandomness,
+        randomness,
+        &hash,
+        params
+    )
+}
+
 #[cfg(test)]
 mod test {
     use rand::{SeedableRng, Rng, XorShiftRng};
@@ -191,6 +221,100 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_custom_personalization() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &JubjubBls12::new();
+
+        let input: Vec<bool> = (0..300).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let input_bools: Vec<Boolean> = input.iter().enumerate().map(|(i, b)| {
+            Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| format!("input {}", i)), Some(*b)).unwrap()
+            )
+        }).collect();
+
+        let res = pedersen_hash(
+            cs.namespace(|| "pedersen hash"),
+            Personalization::Custom(7),
+            &input_bools,
+            params
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        let expected = ::pedersen_hash::pedersen_hash::<Bls12, _>(
+            Personalization::Custom(7),
+            input.clone().into_iter(),
+            params
+        ).into_xy();
+
+        assert_eq!(res.get_x().get_value().unwrap(), expected.0);
+        assert_eq!(res.get_y().get_value().unwrap(), expected.1);
+
+        // other tags and the built-in personalizations give different results
+        for personalization in [
+            Personalization::Custom(6),
+            Personalization::NoteCommitment,
+            Personalization::MerkleTree(0)
+        ].iter() {
+            let unexpected = ::pedersen_hash::pedersen_hash::<Bls12, _>(
+                *personalization,
+                input.clone().into_iter(),
+                params
+            ).into_xy();
+
+            assert!(expected != unexpected);
+        }
+    }
+
+    #[test]
+    fn test_pedersen_commitment() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &JubjubBls12::new();
+
+        let input: Vec<bool> = (0..200).map(|_| rng.gen()).collect();
+        let r: fs::Fs = rng.gen();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let input_bools: Vec<Boolean> = input.iter().enumerate().map(|(i, b)| {
+            Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| format!("input {}", i)), Some(*b)).unwrap()
+            )
+        }).collect();
+
+        let r_bools = ::circuit::boolean::field_into_boolean_vec_le(cs.namespace(|| "r"), Some(r)).unwrap();
+
+        let res = pedersen_commitment(
+            cs.namespace(|| "pedersen commitment"),
+            Personalization::Custom(1),
+            &input_bools,
+            &r_bools,
+            params
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        let expected = ::pedersen_hash::pedersen_commitment::<Bls12, _>(
+            Personalization::Custom(1),
+            input.into_iter(),
+            r,
+            params
+        ).into_xy();
+
+        assert_eq!(res.get_x().get_value().unwrap(), expected.0);
+        assert_eq!(res.get_y().get_value().unwrap(), expected.1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reserved_merkle_level() {
+        Personalization::MerkleTree(::pedersen_hash::CUSTOM_PERSONALIZATION_PREFIX).get_bits();
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,441 @@
+//! Poly1305 one-time authenticator (RFC 8439).
+//!
+//! Inputs are bit strings in little-endian byte order, the same as
+//! for the BLAKE2s gadget, so the message is a little-endian integer.
+//!
+//! The accumulator is kept as 130 bits. A block step checks
+//! `(h + m) * r + 5 * q = h' + 2^130 * q` over two 65 bit limbs
+//! with explicit carries, which implies `h' = (h + m) * r mod p` for
+//! `p = 2^130 - 5`. The accumulator isn't required to be fully
+//! reduced until the final step.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::{
+    Assignment
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+pub const POLY1305_KEY_SIZE: usize = 256;
+pub const POLY1305_TAG_SIZE: usize = 128;
+
+const BLOCK_SIZE: usize = 128;
+const ACCUMULATOR_SIZE: usize = 130;
+const LIMB_SIZE: usize = 65;
+const QUOTIENT_SIZE: usize = 125;
+const CARRY_SIZE: usize = 67;
+
+// Native arithmetic on little-endian bit vectors, used for the witness.
+
+fn add_bits(a: &[bool], b: &[bool]) -> Vec<bool> {
+    let len = ::std::cmp::max(a.len(), b.len()) + 1;
+    let mut res = Vec::with_capacity(len);
+    let mut carry = false;
+    for i in 0..len {
+        let x = a.get(i).cloned().unwrap_or(false);
+        let y = b.get(i).cloned().unwrap_or(false);
+        res.push(x ^ y ^ carry);
+        carry = (x & y) | (carry & (x ^ y));
+    }
+
+    res
+}
+
+fn sub_bits(a: &[bool], b: &[bool]) -> Vec<bool> {
+    let mut res = Vec::with_capacity(a.len());
+    let mut borrow = false;
+    for i in 0..a.len() {
+        let x = a[i];
+        let y = b.get(i).cloned().unwrap_or(false);
+        res.push(x ^ y ^ borrow);
+        borrow = (!x & y) | (borrow & !(x ^ y));
+    }
+    assert!(!borrow);
+
+    res
+}
+
+fn mul_bits(a: &[bool], b: &[bool]) -> Vec<bool> {
+    let mut res = vec![false; a.len() + b.len()];
+    for (i, _) in b.iter().enumerate().filter(|&(_, b)| *b) {
+        let mut shifted = vec![false; i];
+        shifted.extend_from_slice(a);
+        res = add_bits(&res, &shifted);
+    }
+
+    res
+}
+
+fn ge_bits(a: &[bool], b: &[bool]) -> bool {
+    let len = ::std::cmp::max(a.len(), b.len());
+    for i in (0..len).rev() {
+        let x = a.get(i).cloned().unwrap_or(false);
+        let y = b.get(i).cloned().unwrap_or(false);
+        if x != y {
+            return x;
+        }
+    }
+
+    true
+}
+
+fn resize(mut a: Vec<bool>, len: usize) -> Vec<bool> {
+    assert!(a.iter().skip(len).all(|b| !*b));
+    a.resize(len, false);
+
+    a
+}
+
+fn constant_bits(value: u64, len: usize) -> Vec<bool> {
+    (0..len).map(|i| i < 64 && (value >> i) & 1 == 1).collect()
+}
+
+fn modulus() -> Vec<bool> {
+    // 2^130 - 5
+    sub_bits(&constant_bits(0, ACCUMULATOR_SIZE).into_iter().chain(Some(true)).collect::<Vec<_>>(), &constant_bits(5, 3))
+}
+
+fn bit_values(bits: &[Boolean]) -> Option<Vec<bool>> {
+    bits.iter().map(|b| b.get_value()).collect()
+}
+
+fn alloc_bits<E, CS>(
+    mut cs: CS,
+    values: Option<&[bool]>,
+    len: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    (0..len).map(|i| {
+        Ok(Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            values.map(|v| v[i])
+        )?))
+    }).collect()
+}
+
+fn pack<E: Engine>(one: ::bellman::Variable, bits: &[Boolean]) -> Num<E> {
+    let mut num = Num::<E>::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter() {
+        num = num.add_bool_with_coeff(one, bit, coeff);
+        coeff.double();
+    }
+
+    num
+}
+
+fn combine<E: Engine>(terms: &[(&Num<E>, E::Fr)]) -> Num<E> {
+    let mut res = Num::<E>::zero();
+    for &(num, coeff) in terms.iter() {
+        let mut term = num.clone();
+        term.scale(coeff);
+        res.add_assign(&term);
+    }
+
+    res
+}
+
+/// Computes `h' = (h + m) * r mod 2^130 - 5` for a 130 bit
+/// accumulator `h`, a padded block `m` and the clamped `r`.
+fn poly1305_block<E, CS>(
+    mut cs: CS,
+    h: &[Boolean],
+    m: &[Boolean],
+    r: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(h.len(), ACCUMULATOR_SIZE);
+    assert_eq!(m.len(), ACCUMULATOR_SIZE);
+    assert_eq!(r.len(), BLOCK_SIZE);
+
+    let h_values = bit_values(h);
+    let m_values = bit_values(m);
+    let r_values = bit_values(r);
+
+    // witness for h' and q such that (h + m) * r = q * p + h'
+    let mut witness = None;
+    if let (Some(h_val), Some(m_val), Some(r_val)) = (h_values, m_values, r_values) {
+        let a = add_bits(&h_val, &m_val);
+        let x = mul_bits(&a, &r_val);
+
+        let mut q = x[ACCUMULATOR_SIZE..].to_vec();
+        let mut h_new = add_bits(&x[..ACCUMULATOR_SIZE], &mul_bits(&q, &constant_bits(5, 3)));
+        let p = modulus();
+        while ge_bits(&h_new, &p) {
+            h_new = sub_bits(&h_new, &p);
+            q = add_bits(&q, &constant_bits(1, 1));
+        }
+
+        let h_new = resize(h_new, ACCUMULATOR_SIZE);
+        let q = resize(q, QUOTIENT_SIZE);
+
+        let (a0, a1) = (&a[..LIMB_SIZE], &a[LIMB_SIZE..]);
+        let (r0, r1) = (&r_val[..LIMB_SIZE], &r_val[LIMB_SIZE..]);
+        let (q0, q1) = (&q[..LIMB_SIZE], &q[LIMB_SIZE..]);
+        let (h0, h1) = (&h_new[..LIMB_SIZE], &h_new[LIMB_SIZE..]);
+        let five = constant_bits(5, 3);
+
+        // c0 = (a0 * r0 + 5 * q0 - h0) / 2^65
+        let l0 = add_bits(&mul_bits(a0, r0), &mul_bits(q0, &five));
+        let c0 = sub_bits(&l0, h0)[LIMB_SIZE..].to_vec();
+
+        // c1 = (a0 * r1 + a1 * r0 + 5 * q1 + c0 - h1) / 2^65
+        let l1 = add_bits(&add_bits(&mul_bits(a0, r1), &mul_bits(a1, r0)), &mul_bits(q1, &five));
+        let c1 = sub_bits(&add_bits(&l1, &c0), h1)[LIMB_SIZE..].to_vec();
+
+        witness = Some((h_new, q, resize(c0, CARRY_SIZE), resize(c1, CARRY_SIZE)));
+    }
+
+    let h_new = alloc_bits(cs.namespace(|| "accumulator"), witness.as_ref().map(|w| &w.0[..]), ACCUMULATOR_SIZE)?;
+    let q = alloc_bits(cs.namespace(|| "quotient"), witness.as_ref().map(|w| &w.1[..]), QUOTIENT_SIZE)?;
+    let c0 = alloc_bits(cs.namespace(|| "first carry"), witness.as_ref().map(|w| &w.2[..]), CARRY_SIZE)?;
+    let c1 = alloc_bits(cs.namespace(|| "second carry"), witness.as_ref().map(|w| &w.3[..]), CARRY_SIZE)?;
+
+    let one = CS::one();
+
+    let mut a0 = pack::<E>(one, &h[..LIMB_SIZE]);
+    a0.add_assign(&pack(one, &m[..LIMB_SIZE]));
+    let mut a1 = pack::<E>(one, &h[LIMB_SIZE..]);
+    a1.add_assign(&pack(one, &m[LIMB_SIZE..]));
+    let r0 = pack::<E>(one, &r[..LIMB_SIZE]);
+    let r1 = pack::<E>(one, &r[LIMB_SIZE..]);
+    let q0 = pack::<E>(one, &q[..LIMB_SIZE]);
+    let q1 = pack::<E>(one, &q[LIMB_SIZE..]);
+    let h0 = pack::<E>(one, &h_new[..LIMB_SIZE]);
+    let h1 = pack::<E>(one, &h_new[LIMB_SIZE..]);
+    let c0 = pack::<E>(one, &c0);
+    let c1 = pack::<E>(one, &c1);
+
+    let mut base = E::Fr::one();
+    for _ in 0..LIMB_SIZE {
+        base.double();
+    }
+    let five = E::Fr::from_str("5").unwrap();
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let mut minus_five = five;
+    minus_five.negate();
+
+    // a0 * r0 = h0 + 2^65 * c0 - 5 * q0
+    cs.enforce(
+        || "first limb",
+        |_| a0.lc(E::Fr::one()),
+        |_| r0.lc(E::Fr::one()),
+        |_| combine(&[(&h0, E::Fr::one()), (&c0, base), (&q0, minus_five)]).lc(E::Fr::one())
+    );
+
+    let t = AllocatedNum::alloc(cs.namespace(|| "cross product"), || {
+        let mut t = *a0.get_value().get()?;
+        t.mul_assign(r1.get_value().get()?);
+
+        Ok(t)
+    })?;
+
+    cs.enforce(
+        || "cross product constraint",
+        |_| a0.lc(E::Fr::one()),
+        |_| r1.lc(E::Fr::one()),
+        |lc| lc + t.get_variable()
+    );
+
+    // a1 * r0 = h1 + 2^65 * c1 - c0 - 5 * q1 - a0 * r1
+    let t = Num::from(t);
+    cs.enforce(
+        || "second limb",
+        |_| a1.lc(E::Fr::one()),
+        |_| r0.lc(E::Fr::one()),
+        |_| combine(&[(&h1, E::Fr::one()), (&c1, base), (&c0, minus_one), (&q1, minus_five), (&t, minus_one)]).lc(E::Fr::one())
+    );
+
+    // a1 * r1 = q0 + 2^65 * q1 - c1
+    cs.enforce(
+        || "third limb",
+        |_| a1.lc(E::Fr::one()),
+        |_| r1.lc(E::Fr::one()),
+        |_| combine(&[(&q0, E::Fr::one()), (&q1, base), (&c1, minus_one)]).lc(E::Fr::one())
+    );
+
+    Ok(h_new)
+}
+
+/// Computes the Poly1305 tag of the message with the 256 bit
+/// one-time key `r || s`.
+pub fn poly1305<E, CS>(
+    mut cs: CS,
+    key: &[Boolean],
+    message: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(key.len(), POLY1305_KEY_SIZE);
+    assert!(message.len() % 8 == 0);
+
+    // clamp r: the top four bits of bytes 3, 7, 11 and 15
+    // and the bottom two bits of bytes 4, 8 and 12 are cleared
+    let r: Vec<Boolean> = key[..BLOCK_SIZE].iter().enumerate().map(|(i, bit)| {
+        let (byte, bit_index) = (i / 8, i % 8);
+        let cleared = (byte % 4 == 3 && bit_index >= 4) || (byte % 4 == 0 && byte != 0 && bit_index < 2);
+        if cleared {
+            Boolean::constant(false)
+        } else {
+            bit.clone()
+        }
+    }).collect();
+    let s = &key[BLOCK_SIZE..];
+
+    let mut h = vec![Boolean::constant(false); ACCUMULATOR_SIZE];
+    for (i, block) in message.chunks(BLOCK_SIZE).enumerate() {
+        let mut m = block.to_vec();
+        m.push(Boolean::constant(true));
+        m.resize(ACCUMULATOR_SIZE, Boolean::constant(false));
+
+        h = poly1305_block(cs.namespace(|| format!("block {}", i)), &h, &m, &r)?;
+    }
+
+    // g = h + 5 overflows 130 bits iff h >= p, in which case
+    // the low bits of g are the reduced accumulator
+    let g_values = bit_values(&h).map(|h| add_bits(&h, &constant_bits(5, 3)));
+    let g = alloc_bits(cs.namespace(|| "reduction"), g_values.as_ref().map(|g| &g[..]), ACCUMULATOR_SIZE + 1)?;
+
+    let one = CS::one();
+    let five = E::Fr::from_str("5").unwrap();
+
+    cs.enforce(
+        || "reduction constraint",
+        |_| pack::<E>(one, &g).lc(E::Fr::one()),
+        |lc| lc + one,
+        |_| pack::<E>(one, &h).lc(E::Fr::one()) + (five, one)
+    );
+
+    let overflow = &g[ACCUMULATOR_SIZE];
+    let g_low = pack::<E>(one, &g[..POLY1305_TAG_SIZE]);
+    let h_low = pack::<E>(one, &h[..POLY1305_TAG_SIZE]);
+
+    let reduced = AllocatedNum::alloc(cs.namespace(|| "reduced accumulator"), || {
+        if *overflow.get_value().get()? {
+            Ok(*g_low.get_value().get()?)
+        } else {
+            Ok(*h_low.get_value().get()?)
+        }
+    })?;
+
+    // overflow * (g_low - h_low) = reduced - h_low
+    cs.enforce(
+        || "reduced accumulator constraint",
+        |_| overflow.lc(one, E::Fr::one()),
+        |_| g_low.lc(E::Fr::one()) - &h_low.lc(E::Fr::one()),
+        |lc| lc + reduced.get_variable() - &h_low.lc(E::Fr::one())
+    );
+
+    // tag = (reduced + s) mod 2^128
+    let reduced_values = g_values.and_then(|g| {
+        let h = bit_values(&h)?;
+        Some(if g[ACCUMULATOR_SIZE] { g } else { h })
+    });
+    let tag_values = match (reduced_values, bit_values(s)) {
+        (Some(reduced), Some(s)) => Some(add_bits(&reduced[..POLY1305_TAG_SIZE], &s)),
+        _ => None
+    };
+    let tag = alloc_bits(cs.namespace(|| "tag"), tag_values.as_ref().map(|t| &t[..]), POLY1305_TAG_SIZE + 1)?;
+
+    cs.enforce(
+        || "tag constraint",
+        |_| pack::<E>(one, &tag).lc(E::Fr::one()),
+        |lc| lc + one,
+        |_| pack::<E>(one, s).lc(E::Fr::one()) + reduced.get_variable()
+    );
+
+    Ok(tag[..POLY1305_TAG_SIZE].to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bls12_381::{Bls12};
+    use ::circuit::test::*;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use super::*;
+
+    fn alloc_bytes<CS: ConstraintSystem<Bls12>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().enumerate().flat_map(|(i, &b)| (0..8).map(move |j| (i, j, (b >> j) & 1 == 1)))
+            .map(|(i, j, bit)| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {} {}", i, j)), Some(bit)).unwrap())
+            }).collect()
+    }
+
+    fn into_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|b| {
+            b.iter().enumerate().fold(0u8, |acc, (i, bit)| acc | ((bit.get_value().unwrap() as u8) << i))
+        }).collect()
+    }
+
+    #[test]
+    fn test_poly1305_vector() {
+        // RFC 8439, section 2.5.2
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let key = alloc_bytes(cs.namespace(|| "key"), &hex!("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b"));
+        let message = alloc_bytes(cs.namespace(|| "message"), b"Cryptographic Forum Research Group");
+
+        let tag = poly1305(cs.namespace(|| "poly1305"), &key, &message).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&tag), hex!("a8061dc1305136c6c22b8baf0c0127a9").to_vec());
+    }
+
+    #[test]
+    fn test_poly1305_wraparound() {
+        // (2^129 - 1) * 2 = 2^130 - 2 = 3 mod p
+        let mut key = [0u8; 32];
+        key[0] = 2;
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let key_bits = alloc_bytes(cs.namespace(|| "key"), &key);
+        let message = alloc_bytes(cs.namespace(|| "message"), &[0xff; 16]);
+        let tag = poly1305(cs.namespace(|| "poly1305"), &key_bits, &message).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&tag), hex!("03000000000000000000000000000000").to_vec());
+
+        // (2 + 2^128) * 2 + 2^128 - 1 = 3 mod 2^128
+        for b in key[16..].iter_mut() {
+            *b = 0xff;
+        }
+        let mut message = [0u8; 16];
+        message[0] = 2;
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let key_bits = alloc_bytes(cs.namespace(|| "key"), &key);
+        let message = alloc_bytes(cs.namespace(|| "message"), &message);
+        let tag = poly1305(cs.namespace(|| "poly1305"), &key_bits, &message).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(into_bytes(&tag), hex!("03000000000000000000000000000000").to_vec());
+    }
+}
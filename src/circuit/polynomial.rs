@@ -0,0 +1,434 @@
+//! Evaluation of polynomials in the circuit: Horner's rule for
+//! coefficient vectors, barycentric evaluation for values over
+//! radix-2 domains, the vanishing polynomial of such domains and the
+//! powers of a challenge.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+/// Multiplicative subgroup of `E::Fr` of size `2^log_size` with
+/// the constants used by the gadgets below.
+#[derive(Clone, Debug)]
+pub struct Radix2Domain<E: Engine> {
+    pub size: usize,
+    pub log_size: u32,
+    pub generator: E::Fr,
+    pub size_inv: E::Fr
+}
+
+impl<E: Engine> Radix2Domain<E> {
+    pub fn new(size: usize) -> Result<Self, SynthesisError> {
+        if !size.is_power_of_two() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let log_size = size.trailing_zeros();
+        if log_size > E::Fr::S {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        let mut generator = E::Fr::root_of_unity();
+        for _ in log_size..E::Fr::S {
+            generator.square();
+        }
+
+        let size_inv = E::Fr::from_str(&size.to_string()).unwrap().inverse().unwrap();
+
+        Ok(Self {
+            size,
+            log_size,
+            generator,
+            size_inv
+        })
+    }
+
+    /// Returns `generator^index`.
+    pub fn element(&self, index: usize) -> E::Fr {
+        self.generator.pow(&[(index % self.size) as u64])
+    }
+
+    pub fn elements(&self) -> Vec<E::Fr> {
+        let mut res = Vec::with_capacity(self.size);
+        let mut cur = E::Fr::one();
+        for _ in 0..self.size {
+            res.push(cur);
+            cur.mul_assign(&self.generator);
+        }
+
+        res
+    }
+}
+
+/// Evaluates the polynomial with the given coefficients (lowest
+/// degree first) at `x`, one constraint per coefficient.
+pub fn evaluate_horner<E, CS>(
+    mut cs: CS,
+    coeffs: &[AllocatedNum<E>],
+    x: &AllocatedNum<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(!coeffs.is_empty());
+
+    let mut acc = coeffs.last().unwrap().clone();
+    for (i, coeff) in coeffs.iter().rev().skip(1).enumerate() {
+        let next = AllocatedNum::alloc(cs.namespace(|| format!("step {}", i)), || {
+            let mut tmp = *acc.get_value().get()?;
+            tmp.mul_assign(x.get_value().get()?);
+            tmp.add_assign(coeff.get_value().get()?);
+
+            Ok(tmp)
+        })?;
+
+        // acc * x = next - coeff
+        cs.enforce(
+            || format!("step {} constraint", i),
+            |lc| lc + acc.get_variable(),
+            |lc| lc + x.get_variable(),
+            |lc| lc + next.get_variable() - coeff.get_variable()
+        );
+
+        acc = next;
+    }
+
+    Ok(acc)
+}
+
+/// Computes `x^(2^log)` by repeated squaring.
+pub fn pow_power_of_two<E, CS>(
+    mut cs: CS,
+    x: &AllocatedNum<E>,
+    log: u32
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut cur = x.clone();
+    for i in 0..log {
+        cur = cur.square(cs.namespace(|| format!("square {}", i)))?;
+    }
+
+    Ok(cur)
+}
+
+/// Computes `1, c, c^2, ..., c^(n-1)`, matching `transcript::powers`.
+/// The constant one costs a constraint and every further power a
+/// multiplication, `n - 1` constraints for `n >= 2`.
+pub fn challenge_powers<E, CS>(
+    mut cs: CS,
+    c: &AllocatedNum<E>,
+    n: usize
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    if n == 0 {
+        return Ok(vec![]);
+    }
+
+    let one = AllocatedNum::alloc(cs.namespace(|| "one"), || Ok(E::Fr::one()))?;
+    cs.enforce(
+        || "one constraint",
+        |lc| lc + one.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + CS::one()
+    );
+
+    let mut result = vec![one];
+    if n > 1 {
+        result.push(c.clone());
+    }
+
+    for i in 2..n {
+        let power = result[i - 1].mul(cs.namespace(|| format!("power {}", i)), c)?;
+        result.push(power);
+    }
+
+    Ok(result)
+}
+
+/// Evaluates `Z_H(x) = x^n - 1` for the domain `H` of size `n`.
+pub fn evaluate_vanishing_polynomial<E, CS>(
+    mut cs: CS,
+    domain: &Radix2Domain<E>,
+    x: &AllocatedNum<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let x_n = pow_power_of_two(cs.namespace(|| "x^n"), x, domain.log_size)?;
+
+    let z = AllocatedNum::alloc(cs.namespace(|| "Z_H(x)"), || {
+        let mut tmp = *x_n.get_value().get()?;
+        tmp.sub_assign(&E::Fr::one());
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "Z_H(x) constraint",
+        |lc| lc + x_n.get_variable() - CS::one(),
+        |lc| lc + CS::one(),
+        |lc| lc + z.get_variable()
+    );
+
+    Ok(z)
+}
+
+/// Evaluates at `x` the polynomial of degree less than `n` that
+/// takes the given values over the domain, using
+/// `f(x) = Z_H(x) / n * sum_i f_i * w^i / (x - w^i)`.
+/// `x` must not be in the domain.
+pub fn evaluate_barycentric<E, CS>(
+    mut cs: CS,
+    domain: &Radix2Domain<E>,
+    values: &[AllocatedNum<E>],
+    x: &AllocatedNum<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(values.len(), domain.size);
+
+    let z = evaluate_vanishing_polynomial(cs.namespace(|| "vanishing polynomial"), domain, x)?;
+
+    let mut sum = Num::<E>::zero();
+    for (i, (value, omega)) in values.iter().zip(domain.elements().into_iter()).enumerate() {
+        // term = f_i * w^i / (x - w^i)
+        let term = AllocatedNum::alloc(cs.namespace(|| format!("term {}", i)), || {
+            let mut denominator = *x.get_value().get()?;
+            denominator.sub_assign(&omega);
+            let denominator = denominator.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            let mut tmp = *value.get_value().get()?;
+            tmp.mul_assign(&omega);
+            tmp.mul_assign(&denominator);
+
+            Ok(tmp)
+        })?;
+
+        cs.enforce(
+            || format!("term {} constraint", i),
+            |lc| lc + x.get_variable() - (omega, CS::one()),
+            |lc| lc + term.get_variable(),
+            |lc| lc + (omega, value.get_variable())
+        );
+
+        sum = sum.add_number_with_coeff(&term, E::Fr::one());
+    }
+
+    let result = AllocatedNum::alloc(cs.namespace(|| "result"), || {
+        let mut tmp = *z.get_value().get()?;
+        tmp.mul_assign(sum.get_value().get()?);
+        tmp.mul_assign(&domain.size_inv);
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "result constraint",
+        |lc| lc + z.get_variable(),
+        |_| sum.lc(domain.size_inv),
+        |lc| lc + result.get_variable()
+    );
+
+    Ok(result)
+}
+
+/// Evaluates the Lagrange basis polynomials `L_i(x)` of the domain
+/// for the given indices, i.e. the polynomials that are one at `w^i`
+/// and zero on the rest of the domain, using
+/// `L_i(x) = w^i * Z_H(x) / (n * (x - w^i))`. The vanishing polynomial
+/// is evaluated once and each selector costs one constraint. `x` must
+/// not be in the domain.
+pub fn evaluate_lagrange_polynomials<E, CS>(
+    mut cs: CS,
+    domain: &Radix2Domain<E>,
+    indices: &[usize],
+    x: &AllocatedNum<E>
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let z = evaluate_vanishing_polynomial(cs.namespace(|| "vanishing polynomial"), domain, x)?;
+
+    indices.iter().map(|&index| {
+        let omega = domain.element(index);
+
+        let mut coeff = omega;
+        coeff.mul_assign(&domain.size_inv);
+
+        let l = AllocatedNum::alloc(cs.namespace(|| format!("L_{}(x)", index)), || {
+            let mut denominator = *x.get_value().get()?;
+            denominator.sub_assign(&omega);
+            let denominator = denominator.inverse().ok_or(SynthesisError::DivisionByZero)?;
+
+            let mut tmp = *z.get_value().get()?;
+            tmp.mul_assign(&coeff);
+            tmp.mul_assign(&denominator);
+
+            Ok(tmp)
+        })?;
+
+        // (x - w^i) * L_i(x) = w^i / n * Z_H(x)
+        cs.enforce(
+            || format!("L_{}(x) constraint", index),
+            |lc| lc + x.get_variable() - (omega, CS::one()),
+            |lc| lc + l.get_variable(),
+            |lc| lc + (coeff, z.get_variable())
+        );
+
+        Ok(l)
+    }).collect()
+}
+
+/// Evaluates a single Lagrange basis polynomial `L_index(x)`.
+pub fn evaluate_lagrange_polynomial<E, CS>(
+    cs: CS,
+    domain: &Radix2Domain<E>,
+    index: usize,
+    x: &AllocatedNum<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    Ok(evaluate_lagrange_polynomials(cs, domain, &[index], x)?.pop().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field};
+    use ::circuit::test::*;
+    use ::circuit::num::AllocatedNum;
+    use ::transcript::powers;
+    use super::*;
+
+    fn evaluate(coeffs: &[Fr], x: &Fr) -> Fr {
+        let mut acc = Fr::zero();
+        for c in coeffs.iter().rev() {
+            acc.mul_assign(x);
+            acc.add_assign(c);
+        }
+
+        acc
+    }
+
+    fn alloc_all<CS: ConstraintSystem<Bn256>>(mut cs: CS, values: &[Fr]) -> Vec<AllocatedNum<Bn256>> {
+        values.iter().enumerate().map(|(i, v)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(*v)).unwrap()
+        }).collect()
+    }
+
+    #[test]
+    fn test_horner_and_barycentric() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let domain = Radix2Domain::<Bn256>::new(16).unwrap();
+
+        let coeffs: Vec<Fr> = (0..16).map(|_| rng.gen()).collect();
+        let values: Vec<Fr> = domain.elements().iter().map(|w| evaluate(&coeffs, w)).collect();
+        let x: Fr = rng.gen();
+        let expected = evaluate(&coeffs, &x);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let x_var = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+        let coeffs = alloc_all(cs.namespace(|| "coeffs"), &coeffs);
+        let values = alloc_all(cs.namespace(|| "values"), &values);
+
+        let horner = evaluate_horner(cs.namespace(|| "horner"), &coeffs, &x_var).unwrap();
+        let barycentric = evaluate_barycentric(cs.namespace(|| "barycentric"), &domain, &values, &x_var).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(horner.get_value().unwrap(), expected);
+        assert_eq!(barycentric.get_value().unwrap(), expected);
+
+        // 15 Horner steps, 4 squarings, Z_H(x), 16 terms and the result
+        assert_eq!(cs.num_constraints(), 15 + 4 + 1 + 16 + 1);
+    }
+
+    #[test]
+    fn test_vanishing_polynomial() {
+        let domain = Radix2Domain::<Bn256>::new(8).unwrap();
+
+        for (i, w) in domain.elements().into_iter().enumerate() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(w)).unwrap();
+            let z = evaluate_vanishing_polynomial(cs.namespace(|| "Z_H"), &domain, &x).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(z.get_value().unwrap().is_zero(), "Z_H doesn't vanish at element {}", i);
+        }
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::from_str("3").unwrap())).unwrap();
+        let z = evaluate_vanishing_polynomial(cs.namespace(|| "Z_H"), &domain, &x).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(z.get_value().unwrap(), Fr::from_str("6560").unwrap());
+    }
+
+    #[test]
+    fn test_lagrange_polynomials() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let domain = Radix2Domain::<Bn256>::new(8).unwrap();
+        let x: Fr = rng.gen();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let x_var = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+
+        // first and last rows
+        let selectors = evaluate_lagrange_polynomials(cs.namespace(|| "selectors"), &domain, &[0, 7], &x_var).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        // L_i is the barycentric evaluation of the i-th unit vector
+        for (&index, selector) in [0, 7].iter().zip(selectors.iter()) {
+            let values: Vec<Fr> = (0..8).map(|j| if j == index { Fr::one() } else { Fr::zero() }).collect();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let x_var = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+            let values = alloc_all(cs.namespace(|| "values"), &values);
+            let expected = evaluate_barycentric(cs.namespace(|| "barycentric"), &domain, &values, &x_var).unwrap();
+
+            assert_eq!(selector.get_value().unwrap(), expected.get_value().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_challenge_powers() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let c: Fr = rng.gen();
+
+        for n in 0..6 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let c_var = AllocatedNum::alloc(cs.namespace(|| "c"), || Ok(c)).unwrap();
+
+            let result = challenge_powers(cs.namespace(|| "powers"), &c_var, n).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(result.iter().map(|p| p.get_value().unwrap()).collect::<Vec<_>>(), powers(&c, n));
+            assert_eq!(cs.num_constraints(), if n < 2 { n } else { n - 1 });
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Poseidon2 permutation and Merkle node compression for the
+//! parameters of `poseidon2::generate_params`.
+//!
+//! Both linear layers are free, so only the S-boxes are constrained:
+//! `R_F * t + R_P` of them, 3 constraints each for `alpha = 5`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::sbox::SBox;
+
+use poseidon2::Poseidon2Params;
+
+fn sum<E: Engine>(state: &[Num<E>]) -> Num<E> {
+    let mut acc = Num::<E>::zero();
+    for s in state.iter() {
+        acc.add_assign(s);
+    }
+
+    acc
+}
+
+fn external_linear_layer<E: Engine>(state: &mut [Num<E>]) {
+    let sum = sum(state);
+    for s in state.iter_mut() {
+        s.add_assign(&sum);
+    }
+}
+
+fn internal_linear_layer<E: Engine>(params: &Poseidon2Params<E::Fr>, state: &mut [Num<E>]) {
+    let sum = sum(state);
+    for (s, d) in state.iter_mut().zip(params.internal_diagonal.iter()) {
+        s.scale(*d);
+        s.add_assign(&sum);
+    }
+}
+
+fn external_round<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    round: usize,
+    state: &mut [Num<E>]
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let sbox = SBox::Power(params.alpha);
+
+    for (i, (s, c)) in state.iter_mut().zip(params.external_round_constants[round].iter()).enumerate() {
+        s.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), *c);
+        *s = Num::from(sbox.apply(cs.namespace(|| format!("sbox {}", i)), s)?);
+    }
+    external_linear_layer(state);
+
+    Ok(())
+}
+
+pub fn poseidon2_permutation<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    state: &[Num<E>]
+) -> Result<Vec<Num<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(state.len(), params.state_width);
+
+    let half = params.full_rounds / 2;
+    let sbox = SBox::Power(params.alpha);
+
+    let mut state = state.to_vec();
+    external_linear_layer(&mut state);
+
+    for round in 0..half {
+        external_round(cs.namespace(|| format!("external round {}", round)), params, round, &mut state)?;
+    }
+
+    for (round, c) in params.internal_round_constants.iter().enumerate() {
+        state[0].mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), *c);
+        state[0] = Num::from(sbox.apply(cs.namespace(|| format!("internal round {}", round)), &state[0])?);
+        internal_linear_layer(params, &mut state);
+    }
+
+    for round in half..params.full_rounds {
+        external_round(cs.namespace(|| format!("external round {}", round)), params, round, &mut state)?;
+    }
+
+    Ok(state)
+}
+
+/// Merkle node compression matching `poseidon2::poseidon2_compress`.
+pub fn poseidon2_compress<E, CS>(
+    cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    left: &AllocatedNum<E>,
+    right: &AllocatedNum<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    poseidon2_compress_nums(cs, params, &Num::from(left.clone()), &Num::from(right.clone()))
+}
+
+/// Same as `poseidon2_compress` for linear combinations, e.g. to hash
+/// a constant without allocating it.
+pub fn poseidon2_compress_nums<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    left: &Num<E>,
+    right: &Num<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut state = vec![Num::<E>::zero(); params.state_width];
+    state[0] = left.clone();
+    state[1] = right.clone();
+
+    let state = poseidon2_permutation(cs.namespace(|| "permutation"), params, &state)?;
+
+    let mut result = state[0].clone();
+    result.add_assign(left);
+
+    let allocated = AllocatedNum::alloc(cs.namespace(|| "output"), || {
+        Ok(*result.get_value().get()?)
+    })?;
+
+    cs.enforce(
+        || "output constraint",
+        |_| result.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + allocated.get_variable()
+    );
+
+    Ok(allocated)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::bls12_381::{self, Bls12};
+    use ::circuit::test::*;
+    use ::poseidon2::generate_params;
+    use super::*;
+
+    #[test]
+    fn test_poseidon2_compress() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<Fr>(3);
+
+        let (a, b): (Fr, Fr) = (rng.gen(), rng.gen());
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let a_var = AllocatedNum::alloc(cs.namespace(|| "left"), || Ok(a)).unwrap();
+        let b_var = AllocatedNum::alloc(cs.namespace(|| "right"), || Ok(b)).unwrap();
+
+        let output = poseidon2_compress(cs.namespace(|| "compress"), &params, &a_var, &b_var).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(output.get_value().unwrap(), ::poseidon2::poseidon2_compress(&params, &a, &b));
+
+        // x^5 on every element of the external rounds and on the
+        // first one of the internal rounds, and the output
+        assert_eq!(cs.num_constraints(), (8 * 3 + 56) * 3 + 1);
+    }
+
+    #[test]
+    fn test_poseidon2_permutation() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<bls12_381::Fr>(2);
+
+        let mut input: Vec<bls12_381::Fr> = (0..2).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let state: Vec<Num<Bls12>> = input.iter().enumerate().map(|(i, el)| {
+            Num::from(AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap())
+        }).collect();
+
+        let output = poseidon2_permutation(cs.namespace(|| "permutation"), &params, &state).unwrap();
+
+        ::poseidon2::poseidon2_permutation(&params, &mut input);
+
+        assert!(cs.is_satisfied());
+        for (o, expected) in output.iter().zip(input.iter()) {
+            assert_eq!(o.get_value().unwrap(), *expected);
+        }
+    }
+}
@@ -241,6 +241,81 @@ pub fn poseidon_mimc<E: PoseidonEngine<SBox = QuinticSBox<E> >, CS>(
     Ok(result)
 }
 
+/// Gadget for `poseidon::circom_poseidon_hash`, compatible
+/// with circomlib's `Poseidon(n)` template.
+pub fn circom_poseidon_hash<E: PoseidonEngine<SBox = QuinticSBox<E> >, CS>(
+    mut cs: CS,
+    input: &[AllocatedNum<E>],
+    params: &E::Params
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where CS: ConstraintSystem<E>
+{
+    assert_eq!(input.len() + 1, params.t() as usize);
+
+    let r_f = params.r_f();
+    let r_p = params.r_p();
+    let t = params.t();
+
+    let mut state: Vec<Num<E>> = vec![Num::zero()];
+    state.extend(input.iter().map(|el| Num::from(el.clone())));
+
+    for round in 0..(2*r_f + r_p) {
+        let full_round = round < r_f || round >= r_f + r_p;
+
+        let round_constants = if full_round {
+            params.full_round_key(if round < r_f { round } else { round - r_p })
+        } else {
+            params.partial_round_key(round - r_f)
+        };
+        for (el, c) in state.iter_mut().zip(round_constants.iter()) {
+            el.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), *c);
+        }
+
+        if full_round {
+            let s_box_applied = E::SBox::apply_sbox(
+                cs.namespace(|| format!("apply s-box for round {}", round)),
+                &state[..]
+            )?;
+
+            state = s_box_applied.into_iter().map(|el| Num::from(el)).collect();
+        } else {
+            let s_box_applied = E::SBox::apply_sbox(
+                cs.namespace(|| format!("apply s-box for round {}", round)),
+                &state[0..1]
+            )?;
+
+            state[0] = Num::from(s_box_applied[0].clone());
+        }
+
+        let mut linear_transformation_results = vec![];
+        for row in 0..t {
+            let row = params.mds_matrix_row(row);
+            let linear_applied = scalar_product_over_lc(&state[..], row);
+            linear_transformation_results.push(linear_applied);
+        }
+
+        state = linear_transformation_results;
+    }
+
+    let allocated: AllocatedNum<E> = AllocatedNum::alloc(
+        cs.namespace(|| "allocate output"), 
+        || {
+            let val = *state[0].get_value().get()?;
+
+            Ok(val)
+        }
+    )?;
+
+    cs.enforce(
+        || "enforce allocation of output",
+        |_| state[0].lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + allocated.get_variable()
+    );
+
+    Ok(allocated)
+}
+
 fn poseidon_mimc_round<E: PoseidonEngine<SBox = QuinticSBox<E> >, CS>(
     mut cs: CS,
     input: &[Num<E>],
@@ -523,4 +598,33 @@ mod test {
             assert_eq!(res[0].get_value().unwrap(), expected[0]);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_circom_poseidon_hash_gadget() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        for num_inputs in 1..6 {
+            let params = Bn256PoseidonParams::new_circom(num_inputs);
+            let input: Vec<Fr> = (0..num_inputs).map(|_| rng.gen()).collect();
+            let expected = poseidon::circom_poseidon_hash::<Bn256>(&params, &input[..]);
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let input_words: Vec<AllocatedNum<Bn256>> = input.iter().enumerate().map(|(i, b)| {
+                AllocatedNum::alloc(
+                    cs.namespace(|| format!("input {}", i)),
+                    || {
+                        Ok(*b)
+                    }).unwrap()
+            }).collect();
+
+            let res = circom_poseidon_hash(
+                cs.namespace(|| "poseidon hash"),
+                &input_words,
+                &params
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(res.get_value().unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Keyed pseudorandom functions.
+//!
+//! Protocol gadgets derive values like nullifiers with a PRF. Taking a
+//! `PrfGadget` instead of calling BLAKE2s directly lets a circuit use a
+//! cheaper algebraic instantiation. Every PRF can be evaluated on field
+//! elements or on bits; the conversion between the two is done by the
+//! implementation, so that BLAKE2s on bits and the sponges on field
+//! elements don't pay for it.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+
+use super::num::AllocatedNum;
+
+use super::multipack::pack_into_variables;
+
+use super::blake2s::blake2s;
+
+use super::rescue::rescue_hash;
+
+use super::poseidon2::poseidon2_compress;
+
+use rescue::RescueParams;
+
+use poseidon2::Poseidon2Params;
+
+pub trait PrfGadget<E: Engine> {
+    /// Evaluates the PRF keyed by `key` on `input`.
+    fn evaluate<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        key: &[AllocatedNum<E>],
+        input: &[AllocatedNum<E>]
+    ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>;
+
+    /// Evaluates the PRF keyed by `key` on `input`, given and returned
+    /// as little-endian bits.
+    fn evaluate_bits<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        key: &[Boolean],
+        input: &[Boolean]
+    ) -> Result<Vec<Boolean>, SynthesisError>;
+}
+
+/// Evaluates an algebraic PRF on bits, packed into field elements with
+/// `pack_into_variables`. Every output element is unpacked strictly.
+fn evaluate_bits_packed<E, CS, P>(
+    prf: &P,
+    mut cs: CS,
+    key: &[Boolean],
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>,
+          P: PrfGadget<E>
+{
+    let key = pack_into_variables(cs.namespace(|| "pack key"), key)?;
+    let input = pack_into_variables(cs.namespace(|| "pack input"), input)?;
+
+    let output = prf.evaluate(cs.namespace(|| "prf"), &key, &input)?;
+
+    let mut bits = vec![];
+    for (i, el) in output.iter().enumerate() {
+        bits.extend(el.into_bits_le_strict(cs.namespace(|| format!("unpack output {}", i)))?);
+    }
+
+    Ok(bits)
+}
+
+/// `BLAKE2s(key || input)` with the given personalization, e.g.
+/// `PRF^nf` of Sapling.
+#[derive(Clone, Copy, Debug)]
+pub struct Blake2sPrf {
+    pub personalization: &'static [u8]
+}
+
+impl<E: Engine> PrfGadget<E> for Blake2sPrf {
+    /// Field elements are unpacked strictly and the digest is packed
+    /// with `pack_into_variables`.
+    fn evaluate<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        key: &[AllocatedNum<E>],
+        input: &[AllocatedNum<E>]
+    ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    {
+        let unpack = |cs: &mut CS, name: &str, elements: &[AllocatedNum<E>]| -> Result<Vec<Boolean>, SynthesisError> {
+            let mut bits = vec![];
+            for (i, el) in elements.iter().enumerate() {
+                bits.extend(el.into_bits_le_strict(cs.namespace(|| format!("unpack {} {}", name, i)))?);
+            }
+
+            Ok(bits)
+        };
+
+        let key = unpack(&mut cs, "key", key)?;
+        let input = unpack(&mut cs, "input", input)?;
+
+        let digest = <Self as PrfGadget<E>>::evaluate_bits(self, cs.namespace(|| "prf"), &key, &input)?;
+
+        pack_into_variables(cs.namespace(|| "pack digest"), &digest)
+    }
+
+    fn evaluate_bits<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        key: &[Boolean],
+        input: &[Boolean]
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    {
+        let mut preimage = key.to_vec();
+        preimage.extend(input.iter().cloned());
+
+        blake2s(cs, &preimage, self.personalization)
+    }
+}
+
+/// The `rescue_hash` of `key || input`, `rate` elements.
+pub struct RescuePrf<'a, E: Engine> {
+    pub params: &'a RescueParams<E::Fr>
+}
+
+impl<'a, E: Engine> PrfGadget<E> for RescuePrf<'a, E> {
+    fn evaluate<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        key: &[AllocatedNum<E>],
+        input: &[AllocatedNum<E>]
+    ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    {
+        let mut preimage = key.to_vec();
+        preimage.extend(input.iter().cloned());
+
+        rescue_hash(cs, self.params, &preimage)
+    }
+
+    fn evaluate_bits<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        key: &[Boolean],
+        input: &[Boolean]
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    {
+        evaluate_bits_packed(self, cs, key, input)
+    }
+}
+
+/// `poseidon2_compress` folded over `key || input`, starting from the
+/// first key element, one element. The key must not be empty.
+pub struct Poseidon2Prf<'a, E: Engine> {
+    pub params: &'a Poseidon2Params<E::Fr>
+}
+
+impl<'a, E: Engine> PrfGadget<E> for Poseidon2Prf<'a, E> {
+    fn evaluate<CS: ConstraintSystem<E>>(
+        &self,
+        mut cs: CS,
+        key: &[AllocatedNum<E>],
+        input: &[AllocatedNum<E>]
+    ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    {
+        assert!(!key.is_empty());
+
+        let mut acc = key[0].clone();
+        for (i, el) in key[1..].iter().chain(input.iter()).enumerate() {
+            acc = poseidon2_compress(cs.namespace(|| format!("compress {}", i)), self.params, &acc, el)?;
+        }
+
+        Ok(vec![acc])
+    }
+
+    fn evaluate_bits<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        key: &[Boolean],
+        input: &[Boolean]
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    {
+        evaluate_bits_packed(self, cs, key, input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{BitIterator, PrimeField};
+    use blake2_rfc::blake2s::Blake2s;
+    use ::circuit::test::*;
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::multipack::compute_multipacking;
+    use ::rescue::{generate_params, rescue_hash as native_rescue_hash};
+    use ::poseidon2::{generate_params as generate_poseidon2_params, poseidon2_compress as native_compress};
+    use super::*;
+
+    fn alloc_elements(cs: &mut TestConstraintSystem<Bn256>, name: &str, values: &[Fr]) -> Vec<AllocatedNum<Bn256>> {
+        values.iter().enumerate().map(|(i, v)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("{} {}", name, i)), || Ok(*v)).unwrap()
+        }).collect()
+    }
+
+    fn values(elements: &[AllocatedNum<Bn256>]) -> Vec<Fr> {
+        elements.iter().map(|el| el.get_value().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_blake2s_prf() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let prf = Blake2sPrf { personalization: b"12345678" };
+
+        let key: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+        let input: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+
+        let mut h = Blake2s::with_params(32, &[], &[], prf.personalization);
+        h.update(&key);
+        h.update(&input);
+        let expected: Vec<bool> = h.finalize().as_ref().iter()
+            .flat_map(|&b| (0..8).map(move |i| (b >> i) & 1 == 1))
+            .collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let alloc_bits = |cs: &mut TestConstraintSystem<Bn256>, name: &str, bytes: &[u8]| -> Vec<Boolean> {
+            bytes.iter().flat_map(|&b| (0..8).map(move |i| (b >> i) & 1 == 1)).enumerate().map(|(i, b)| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), Some(b)).unwrap())
+            }).collect()
+        };
+        let key_bits = alloc_bits(&mut cs, "key", &key);
+        let input_bits = alloc_bits(&mut cs, "input", &input);
+
+        let output = PrfGadget::<Bn256>::evaluate_bits(&prf, cs.namespace(|| "prf"), &key_bits, &input_bits).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(output.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), expected);
+
+        // on field elements the digest comes packed
+        let key = alloc_elements(&mut cs, "key element", &[rng.gen()]);
+        let input = alloc_elements(&mut cs, "input element", &[rng.gen(), rng.gen()]);
+        let output = PrfGadget::<Bn256>::evaluate(&prf, cs.namespace(|| "prf elements"), &key, &input).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(output.len(), 2);
+    }
+
+    #[test]
+    fn test_algebraic_prfs() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let rescue_params = generate_params::<Fr>(3, 128);
+        let poseidon2_params = generate_poseidon2_params::<Fr>(3);
+
+        let key_values: Vec<Fr> = (0..2).map(|_| rng.gen()).collect();
+        let input_values: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+        let preimage: Vec<Fr> = key_values.iter().chain(input_values.iter()).cloned().collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let key = alloc_elements(&mut cs, "key", &key_values);
+        let input = alloc_elements(&mut cs, "input", &input_values);
+
+        let output = RescuePrf { params: &rescue_params }.evaluate(cs.namespace(|| "rescue"), &key, &input).unwrap();
+        assert_eq!(values(&output), native_rescue_hash(&rescue_params, &preimage));
+
+        let output = Poseidon2Prf { params: &poseidon2_params }.evaluate(cs.namespace(|| "poseidon2"), &key, &input).unwrap();
+        let expected = preimage[1..].iter().fold(preimage[0], |acc, x| native_compress(&poseidon2_params, &acc, x));
+        assert_eq!(values(&output), vec![expected]);
+
+        // on bits the key and the input are packed separately
+        let key_bits: Vec<bool> = (0..300).map(|_| rng.gen()).collect();
+        let input_bits: Vec<bool> = (0..100).map(|_| rng.gen()).collect();
+        let mut preimage = compute_multipacking::<Bn256>(&key_bits);
+        preimage.extend(compute_multipacking::<Bn256>(&input_bits));
+
+        let key: Vec<Boolean> = key_bits.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("key bit {}", i)), Some(b)).unwrap())
+        }).collect();
+        let input: Vec<Boolean> = input_bits.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("input bit {}", i)), Some(b)).unwrap())
+        }).collect();
+
+        let output = RescuePrf { params: &rescue_params }.evaluate_bits(cs.namespace(|| "rescue bits"), &key, &input).unwrap();
+        let expected = native_rescue_hash(&rescue_params, &preimage);
+        assert_eq!(output.len(), expected.len() * Fr::NUM_BITS as usize);
+
+        let expected_bits: Vec<bool> = expected.iter().flat_map(|el| {
+            let mut bits: Vec<bool> = BitIterator::new(el.into_repr()).collect();
+            bits.reverse();
+            bits.truncate(Fr::NUM_BITS as usize);
+
+            bits
+        }).collect();
+        assert_eq!(output.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), expected_bits);
+
+        assert!(cs.is_satisfied());
+    }
+}
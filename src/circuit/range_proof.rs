@@ -0,0 +1,206 @@
+//! Range claims collected during synthesis and discharged together.
+//!
+//! Gadgets register `(value, num_bits)` claims on a shared
+//! `RangeProofGadget` instead of decomposing the value on the spot,
+//! and `finalize` enforces them at the end of the circuit. Claims on
+//! the same variable are merged, only the tightest one is decomposed,
+//! so a value checked by several gadgets (e.g. a note value checked
+//! for 64 bits by the output and for 128 bits by a balance sum) pays
+//! for one decomposition of `num_bits + 1` constraints. The bits are
+//! recorded in a `BitsCache`, so a gadget decomposing a claimed value
+//! afterwards doesn't constrain it again.
+//!
+//! Packing the claims of several values into one constraint isn't
+//! sound in R1CS, as their differences to the packed bits can cancel
+//! out, so every distinct value keeps its own packing constraint.
+//!
+//! Dropping the gadget with claims that were never finalized would
+//! silently leave the values unconstrained, so this is checked in
+//! debug builds.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    BitsCache
+};
+
+use super::comparison::alloc_packed_bits;
+
+#[must_use = "the claims are only enforced by `finalize`"]
+pub struct RangeProofGadget<E: Engine> {
+    claims: Vec<(AllocatedNum<E>, usize)>
+}
+
+impl<E: Engine> RangeProofGadget<E> {
+    pub fn new() -> Self {
+        Self {
+            claims: vec![]
+        }
+    }
+
+    /// Claims that `value < 2^num_bits`.
+    pub fn claim(&mut self, value: &AllocatedNum<E>, num_bits: usize) {
+        assert!(num_bits < E::Fr::CAPACITY as usize);
+
+        let existing = self.claims.iter_mut().find(|c| c.0.get_variable() == value.get_variable());
+        match existing {
+            Some(claim) => {
+                if num_bits < claim.1 {
+                    claim.1 = num_bits;
+                }
+            },
+            None => self.claims.push((value.clone(), num_bits))
+        }
+    }
+
+    pub fn claim_u64(&mut self, value: &AllocatedNum<E>) {
+        self.claim(value, 64);
+    }
+
+    pub fn claim_u128(&mut self, value: &AllocatedNum<E>) {
+        self.claim(value, 128);
+    }
+
+    /// Number of distinct values claimed so far.
+    pub fn num_claims(&self) -> usize {
+        self.claims.len()
+    }
+
+    /// Number of constraints `finalize` will enforce.
+    pub fn num_constraints(&self) -> usize {
+        self.claims.iter().map(|c| c.1 + 1).sum()
+    }
+
+    /// Enforces all the claims, returning every claimed value with its
+    /// little-endian bits, which are also recorded in `cache`.
+    pub fn finalize<CS>(
+        mut self,
+        mut cs: CS,
+        cache: &mut BitsCache
+    ) -> Result<Vec<(AllocatedNum<E>, Vec<Boolean>)>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let claims = ::std::mem::replace(&mut self.claims, vec![]);
+
+        claims.into_iter().enumerate().map(|(i, (value, num_bits))| {
+            let bits = alloc_packed_bits(
+                cs.namespace(|| format!("range claim {}", i)),
+                value.get_value(),
+                LinearCombination::zero() + value.get_variable(),
+                num_bits
+            )?;
+            cache.insert(&value, &bits);
+
+            Ok((value, bits))
+        }).collect()
+    }
+}
+
+impl<E: Engine> Drop for RangeProofGadget<E> {
+    fn drop(&mut self) {
+        if !::std::thread::panicking() {
+            debug_assert!(self.claims.is_empty(), "range claims dropped without being finalized");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use super::*;
+
+    #[test]
+    fn test_claims_are_merged() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("12345").unwrap())).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from_str("67890").unwrap())).unwrap();
+
+        let mut range = RangeProofGadget::new();
+        range.claim_u128(&a);
+        range.claim_u64(&a);
+        range.claim_u128(&a);
+        range.claim(&b, 32);
+
+        assert_eq!(range.num_claims(), 2);
+        assert_eq!(range.num_constraints(), 65 + 33);
+
+        let bits = range.finalize(cs.namespace(|| "range"), &mut BitsCache::new()).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 65 + 33);
+        assert_eq!(bits[0].1.len(), 64);
+        assert_eq!(bits[1].1.len(), 32);
+    }
+
+    #[test]
+    fn test_claimed_bits_are_cached() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("12345").unwrap())).unwrap();
+
+        let mut range = RangeProofGadget::new();
+        range.claim_u64(&a);
+
+        let mut cache = BitsCache::new();
+        range.finalize(cs.namespace(|| "range"), &mut cache).unwrap();
+        assert_eq!(cs.num_constraints(), 65);
+
+        let bits = cache.into_bits_le_strict(cs.namespace(|| "strict bits"), &a).unwrap();
+        assert_eq!(cs.num_constraints(), 65);
+        assert_eq!(bits.len(), Fr::NUM_BITS as usize);
+        assert!(bits[64..].iter().all(|b| b.get_value() == Some(false)));
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let mut value = Fr::one();
+        for _ in 0..64 {
+            value.double();
+        }
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(value)).unwrap();
+
+        let mut range = RangeProofGadget::new();
+        range.claim_u128(&a);
+        range.claim_u64(&a);
+        range.finalize(cs.namespace(|| "range"), &mut BitsCache::new()).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "range claims dropped without being finalized")]
+    fn test_unfinalized_claims() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::one())).unwrap();
+
+        let mut range = RangeProofGadget::new();
+        range.claim_u64(&a);
+        drop(range);
+    }
+}
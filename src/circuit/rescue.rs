@@ -0,0 +1,212 @@
+//! Rescue-Prime permutation and sponge hash for the parameters
+//! of `rescue::generate_params`, for any engine and any valid
+//! S-box exponent.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::sbox::SBox;
+
+use rescue::RescueParams;
+
+fn mds_mul<E: Engine>(matrix: &[Vec<E::Fr>], state: &[Num<E>]) -> Vec<Num<E>> {
+    matrix.iter().map(|row| {
+        let mut acc = Num::<E>::zero();
+        for (coeff, s) in row.iter().zip(state.iter()) {
+            let mut term = s.clone();
+            term.scale(*coeff);
+            acc.add_assign(&term);
+        }
+
+        acc
+    }).collect()
+}
+
+fn add_constants<CS: ConstraintSystem<E>, E: Engine>(state: &mut [Num<E>], constants: &[E::Fr]) {
+    for (s, c) in state.iter_mut().zip(constants.iter()) {
+        s.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), *c);
+    }
+}
+
+pub fn rescue_permutation<E, CS>(
+    mut cs: CS,
+    params: &RescueParams<E::Fr>,
+    state: &[Num<E>]
+) -> Result<Vec<Num<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let m = params.state_width;
+    assert_eq!(state.len(), m);
+
+    let sbox = SBox::Power(params.alpha);
+    sbox.validate::<E::Fr>()?;
+
+    let mut state = state.to_vec();
+    for round in 0..params.num_rounds {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+        let constants = &params.round_constants[(2 * m * round)..(2 * m * (round + 1))];
+
+        let forward = state.iter().enumerate().map(|(i, s)| {
+            sbox.apply(cs.namespace(|| format!("forward s-box {}", i)), s).map(Num::from)
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        state = mds_mul(&params.mds_matrix, &forward);
+        add_constants::<CS, E>(&mut state, &constants[..m]);
+
+        let backward = state.iter().enumerate().map(|(i, s)| {
+            sbox.apply_inverse(cs.namespace(|| format!("inverse s-box {}", i)), s).map(Num::from)
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        state = mds_mul(&params.mds_matrix, &backward);
+        add_constants::<CS, E>(&mut state, &constants[m..]);
+    }
+
+    Ok(state)
+}
+
+/// Sponge hash matching `rescue::rescue_hash`.
+pub fn rescue_hash<E, CS>(
+    cs: CS,
+    params: &RescueParams<E::Fr>,
+    input: &[AllocatedNum<E>]
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let input: Vec<Num<E>> = input.iter().map(|el| Num::from(el.clone())).collect();
+
+    rescue_hash_nums(cs, params, &input)
+}
+
+/// Same as `rescue_hash` for linear combinations, e.g. to hash a
+/// constant tag without allocating it.
+pub fn rescue_hash_nums<E, CS>(
+    mut cs: CS,
+    params: &RescueParams<E::Fr>,
+    input: &[Num<E>]
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let rate = params.rate();
+
+    let mut input = input.to_vec();
+    let mut one = Num::<E>::zero();
+    one.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), E::Fr::one());
+    input.push(one);
+    while input.len() % rate != 0 {
+        input.push(Num::zero());
+    }
+
+    let mut state = vec![Num::<E>::zero(); params.state_width];
+    for (i, chunk) in input.chunks(rate).enumerate() {
+        for (s, el) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(el);
+        }
+        state = rescue_permutation(cs.namespace(|| format!("permutation {}", i)), params, &state)?;
+    }
+
+    state[..rate].iter().enumerate().map(|(i, num)| {
+        let allocated = AllocatedNum::alloc(cs.namespace(|| format!("output {}", i)), || {
+            Ok(*num.get_value().get()?)
+        })?;
+
+        cs.enforce(
+            || format!("output {} constraint", i),
+            |_| num.lc(E::Fr::one()),
+            |lc| lc + CS::one(),
+            |lc| lc + allocated.get_variable()
+        );
+
+        Ok(allocated)
+    }).collect()
+}
+
+/// Low `num_bits` bits of the first element of the hash, as used by
+/// `util::rescue_hash_to_scalar`.
+pub fn rescue_hash_to_bits<E, CS>(
+    mut cs: CS,
+    params: &RescueParams<E::Fr>,
+    input: &[Num<E>],
+    num_bits: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let h = rescue_hash_nums(cs.namespace(|| "hash"), params, input)?;
+
+    let mut bits = h[0].into_bits_le_strict(cs.namespace(|| "into bits"))?;
+    bits.truncate(num_bits);
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::bls12_381::{self, Bls12};
+    use ::circuit::test::*;
+    use ::rescue::{generate_params, generate_params_with_exponent};
+    use super::*;
+
+    #[test]
+    fn test_rescue_hash() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // x^5 and x^7 over BN256 go through the same code path
+        for params in [
+            generate_params::<Fr>(3, 128),
+            generate_params_with_exponent::<Fr>(3, 128, 7).unwrap()
+        ].iter() {
+            let input: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let input_vars: Vec<_> = input.iter().enumerate().map(|(i, el)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap()
+            }).collect();
+
+            let output = rescue_hash(cs.namespace(|| "hash"), params, &input_vars).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(
+                output.iter().map(|el| el.get_value().unwrap()).collect::<Vec<_>>(),
+                ::rescue::rescue_hash(params, &input)
+            );
+        }
+
+        let params = generate_params::<bls12_381::Fr>(3, 128);
+        let input: Vec<bls12_381::Fr> = (0..2).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_vars: Vec<_> = input.iter().enumerate().map(|(i, el)| {
+            AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*el)).unwrap()
+        }).collect();
+
+        let output = rescue_hash(cs.namespace(|| "hash"), &params, &input_vars).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(output[0].get_value().unwrap(), ::rescue::rescue_hash(&params, &input)[0]);
+    }
+}
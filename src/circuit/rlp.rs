@@ -0,0 +1,621 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use super::{
+    Assignment
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::boolean::{
+    Boolean
+};
+
+use super::comparison::alloc_packed_bits;
+
+use super::conditional::Selectable;
+
+use util::fr_from_u64;
+
+// Every byte is represented as 8 booleans in little-endian bit order,
+// the same layout as consumed by the Keccak gadget, so encoded data
+// can be hashed directly.
+
+/// Header of an RLP item. The header is allowed to use at most
+/// two bytes to encode the payload length, so items are limited
+/// to 65535 bytes. Non-canonical encodings (e.g. a long form
+/// header for a short payload) are not rejected, as the encoding
+/// is usually bound by a hash anyway.
+pub struct RlpHeader<E: Engine> {
+    pub is_list: Boolean,
+    pub header_length: Num<E>,
+    pub payload_length: Num<E>
+}
+
+/// A decoded item of an RLP list.
+pub struct RlpItem<E: Engine> {
+    pub header: RlpHeader<E>,
+    /// Window of the input starting at the first byte of the
+    /// payload. Only the first `payload_length` bytes belong
+    /// to the item.
+    pub payload: Vec<Boolean>
+}
+
+/// Number of bits required to represent all values in `0..=value`.
+fn bits_for(value: usize) -> usize {
+    let mut bits = 0;
+    while (1usize << bits) <= value {
+        bits += 1;
+    }
+
+    bits
+}
+
+fn byte_into_num<E: Engine>(
+    one: Variable,
+    bits: &[Boolean],
+    shift: usize
+) -> Num<E>
+{
+    let mut coeff = E::Fr::one();
+    for _ in 0..shift {
+        coeff.double();
+    }
+
+    let mut num = Num::zero();
+    for bit in bits.iter() {
+        num = num.add_bool_with_coeff(one, bit, coeff);
+        coeff.double();
+    }
+
+    num
+}
+
+fn mul_by_boolean<E, CS>(
+    mut cs: CS,
+    num: &Num<E>,
+    bit: &Boolean
+) -> Result<Num<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let result = AllocatedNum::alloc(
+        cs.namespace(|| "product"),
+        || {
+            if *bit.get_value().get()? {
+                Ok(*num.get_value().get()?)
+            } else {
+                Ok(E::Fr::zero())
+            }
+        }
+    )?;
+
+    cs.enforce(
+        || "product constraint",
+        |_| num.lc(E::Fr::one()),
+        |_| bit.lc(CS::one(), E::Fr::one()),
+        |lc| lc + result.get_variable()
+    );
+
+    Ok(Num::from(result))
+}
+
+/// Enforces that `num <= bound`.
+fn enforce_at_most<E, CS>(
+    cs: CS,
+    num: &Num<E>,
+    bound: usize
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let bound_fr = fr_from_u64::<E::Fr>(bound as u64);
+    let value = num.get_value().map(|v| {
+        let mut tmp = bound_fr;
+        tmp.sub_assign(&v);
+
+        tmp
+    });
+
+    alloc_packed_bits(
+        cs,
+        value,
+        LinearCombination::<E>::zero() + (bound_fr, CS::one()) - &num.lc(E::Fr::one()),
+        bits_for(bound)
+    )?;
+
+    Ok(())
+}
+
+/// Shifts the byte array to the left by `by` bytes, filling
+/// the vacant positions with zeroes. The shift amount is
+/// enforced to be less than `2^num_bits`.
+pub fn shift_bytes_left<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    by: &Num<E>,
+    num_bits: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+
+    let bits = alloc_packed_bits(
+        cs.namespace(|| "decompose shift"),
+        by.get_value(),
+        by.lc(E::Fr::one()),
+        num_bits
+    )?;
+
+    let mut current = input.to_vec();
+    for (i, bit) in bits.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("shift layer {}", i));
+        let shift = 8 << i;
+
+        let mut next = Vec::with_capacity(current.len());
+        for j in 0..current.len() {
+            let shifted = current.get(j + shift).cloned().unwrap_or(Boolean::constant(false));
+
+            next.push(<Boolean as Selectable<E>>::conditionally_select(
+                cs.namespace(|| format!("select bit {}", j)),
+                bit,
+                &shifted,
+                &current[j]
+            )?);
+        }
+
+        current = next;
+    }
+
+    Ok(current)
+}
+
+/// Decodes the header of the RLP item that starts
+/// at the beginning of the `input`.
+pub fn decode_header<E, CS>(
+    mut cs: CS,
+    input: &[Boolean]
+) -> Result<RlpHeader<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let one = CS::one();
+    let byte = |i: usize| -> Vec<Boolean> {
+        (0..8).map(|j| input.get(8*i + j).cloned().unwrap_or(Boolean::constant(false))).collect()
+    };
+
+    let prefix = byte(0);
+
+    // 0x00..0x7f: a single byte, that is its own payload
+    // 0x80..0xb7: a string of up to 55 bytes
+    // 0xb8..0xbf: a string with the length encoded in the following 1..8 bytes
+    // 0xc0..0xf7 and 0xf8..0xff: the same for lists
+    let is_single = prefix[7].not();
+
+    let is_list = Boolean::and(
+        cs.namespace(|| "is list"),
+        &prefix[7],
+        &prefix[6]
+    )?;
+
+    // long forms have the bits 3..5 set
+    let tmp = Boolean::and(
+        cs.namespace(|| "long form marker 1"),
+        &prefix[5],
+        &prefix[4]
+    )?;
+    let long_marker = Boolean::and(
+        cs.namespace(|| "long form marker 2"),
+        &tmp,
+        &prefix[3]
+    )?;
+
+    let is_long = Boolean::and(
+        cs.namespace(|| "is long"),
+        &prefix[7],
+        &long_marker
+    )?;
+    let is_short = Boolean::and(
+        cs.namespace(|| "is short"),
+        &prefix[7],
+        &long_marker.not()
+    )?;
+
+    // at most two bytes of the length are supported
+    for &i in [1, 2].iter() {
+        cs.enforce(
+            || format!("length of length limit {}", i),
+            |_| is_long.lc(one, E::Fr::one()),
+            |_| prefix[i].lc(one, E::Fr::one()),
+            |lc| lc
+        );
+    }
+
+    let two_length_bytes = Boolean::and(
+        cs.namespace(|| "two bytes of length"),
+        &is_long,
+        &prefix[0]
+    )?;
+    let one_length_byte = Boolean::and(
+        cs.namespace(|| "one byte of length"),
+        &is_long,
+        &prefix[0].not()
+    )?;
+
+    let mut two = E::Fr::one();
+    two.double();
+
+    let header_length = Num::zero()
+        .add_bool_with_coeff(one, &is_short, E::Fr::one())
+        .add_bool_with_coeff(one, &is_long, two)
+        .add_bool_with_coeff(one, &two_length_bytes, E::Fr::one());
+
+    let short_length = byte_into_num::<E>(one, &prefix[0..6], 0);
+    let short_length = mul_by_boolean(
+        cs.namespace(|| "short length"),
+        &short_length,
+        &is_short
+    )?;
+
+    let first_length_byte = byte_into_num::<E>(one, &byte(1), 0);
+    let long_length_1 = mul_by_boolean(
+        cs.namespace(|| "long length 1"),
+        &first_length_byte,
+        &one_length_byte
+    )?;
+
+    let mut long_length_2 = byte_into_num::<E>(one, &byte(1), 8);
+    long_length_2.add_assign(&byte_into_num::<E>(one, &byte(2), 0));
+    let long_length_2 = mul_by_boolean(
+        cs.namespace(|| "long length 2"),
+        &long_length_2,
+        &two_length_bytes
+    )?;
+
+    let mut payload_length = Num::zero().add_bool_with_coeff(one, &is_single, E::Fr::one());
+    payload_length.add_assign(&short_length);
+    payload_length.add_assign(&long_length_1);
+    payload_length.add_assign(&long_length_2);
+
+    Ok(RlpHeader {
+        is_list: is_list,
+        header_length: header_length,
+        payload_length: payload_length
+    })
+}
+
+/// Decodes an RLP list of exactly `num_items` items, every one of them
+/// having at most `max_item_length` bytes of payload. The list must
+/// fit into the `input`, bytes after the end of the list are ignored.
+pub fn decode_list<E, CS>(
+    mut cs: CS,
+    input: &[Boolean],
+    num_items: usize,
+    max_item_length: usize
+) -> Result<(RlpHeader<E>, Vec<RlpItem<E>>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(input.len() % 8 == 0);
+
+    let input_length = input.len() / 8;
+    let shift_bits = bits_for(input_length);
+
+    let list_header = decode_header(cs.namespace(|| "list header"), input)?;
+    Boolean::enforce_equal(
+        cs.namespace(|| "enforce list"),
+        &list_header.is_list,
+        &Boolean::constant(true)
+    )?;
+
+    let mut end = list_header.header_length.clone();
+    end.add_assign(&list_header.payload_length);
+    enforce_at_most(cs.namespace(|| "list fits into input"), &end, input_length)?;
+
+    let mut offset = list_header.header_length.clone();
+    let mut items = vec![];
+
+    for i in 0..num_items {
+        let cs = &mut cs.namespace(|| format!("item {}", i));
+
+        let window = shift_bytes_left(
+            cs.namespace(|| "shift to item"),
+            input,
+            &offset,
+            shift_bits
+        )?;
+
+        let header = decode_header(cs.namespace(|| "header"), &window)?;
+        enforce_at_most(
+            cs.namespace(|| "payload length limit"),
+            &header.payload_length,
+            max_item_length
+        )?;
+
+        // header takes at most 3 bytes
+        let window_length = ::std::cmp::min(window.len(), 8 * (max_item_length + 3));
+        let mut payload = shift_bytes_left(
+            cs.namespace(|| "shift to payload"),
+            &window[0..window_length],
+            &header.header_length,
+            2
+        )?;
+        payload.truncate(8 * max_item_length);
+
+        offset.add_assign(&header.header_length);
+        offset.add_assign(&header.payload_length);
+
+        items.push(RlpItem {
+            header: header,
+            payload: payload
+        });
+    }
+
+    // the list must be consumed exactly
+    cs.enforce(
+        || "enforce number of items",
+        |lc| lc + &offset.lc(E::Fr::one()) - &end.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+
+    Ok((list_header, items))
+}
+
+/// Right-aligns the payload of the item in a window of `max_length`
+/// bytes, which gives the big-endian bytes of the integer it encodes.
+pub fn payload_into_bytes<E, CS>(
+    mut cs: CS,
+    item: &RlpItem<E>,
+    max_length: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(item.payload.len() >= max_length * 8);
+
+    enforce_at_most(
+        cs.namespace(|| "payload length limit"),
+        &item.header.payload_length,
+        max_length
+    )?;
+
+    // prepend zeroes and shift by the length, so the payload
+    // ends up aligned to the end of the window
+    let mut padded = vec![Boolean::constant(false); max_length * 8];
+    padded.extend_from_slice(&item.payload[0..(max_length * 8)]);
+
+    let mut aligned = shift_bytes_left(
+        cs.namespace(|| "align payload"),
+        &padded,
+        &item.header.payload_length,
+        bits_for(max_length)
+    )?;
+    aligned.truncate(max_length * 8);
+
+    Ok(aligned)
+}
+
+/// Interprets the payload of the item as a big-endian integer
+/// of at most `max_length` bytes, as RLP encodes scalars.
+pub fn payload_into_num<E, CS>(
+    mut cs: CS,
+    item: &RlpItem<E>,
+    max_length: usize
+) -> Result<Num<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(max_length * 8 <= E::Fr::CAPACITY as usize);
+
+    let aligned = payload_into_bytes(&mut cs, item, max_length)?;
+
+    let mut num = Num::zero();
+    for (i, byte) in aligned.chunks(8).rev().enumerate() {
+        num.add_assign(&byte_into_num::<E>(CS::one(), byte, 8 * i));
+    }
+
+    Ok(num)
+}
+
+fn encode_header(offset: u8, length: usize) -> Vec<Boolean> {
+    let header = if length < 56 {
+        vec![offset + length as u8]
+    } else {
+        let mut length_bytes = vec![];
+        let mut tmp = length;
+        while tmp > 0 {
+            length_bytes.push(tmp as u8);
+            tmp >>= 8;
+        }
+        length_bytes.reverse();
+
+        let mut header = vec![offset + 55 + length_bytes.len() as u8];
+        header.extend(length_bytes);
+
+        header
+    };
+
+    header.into_iter().flat_map(|byte| {
+        (0..8).map(move |i| Boolean::constant((byte >> i) & 1 == 1))
+    }).collect()
+}
+
+/// Encodes a string with the length known at the synthesis time,
+/// which doesn't require any constraints. Single byte strings are
+/// not supported, as their encoding depends on the value.
+pub fn encode_string(payload: &[Boolean]) -> Vec<Boolean> {
+    assert!(payload.len() % 8 == 0);
+    assert!(payload.len() != 8, "single byte strings have a value dependent encoding");
+
+    let mut result = encode_header(0x80, payload.len() / 8);
+    result.extend_from_slice(payload);
+
+    result
+}
+
+/// Encodes a list of already encoded items.
+pub fn encode_list(items: &[Vec<Boolean>]) -> Vec<Boolean> {
+    let payload: Vec<Boolean> = items.iter().flat_map(|i| i.iter().cloned()).collect();
+    assert!(payload.len() % 8 == 0);
+
+    let mut result = encode_header(0xc0, payload.len() / 8);
+    result.extend(payload);
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use ::circuit::test::*;
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use super::{decode_list, payload_into_num, encode_string, encode_list};
+
+    fn native_header(offset: u8, length: usize) -> Vec<u8> {
+        if length < 56 {
+            vec![offset + length as u8]
+        } else {
+            assert!(length < 256);
+            vec![offset + 56, length as u8]
+        }
+    }
+
+    fn native_string(payload: &[u8]) -> Vec<u8> {
+        if payload.len() == 1 && payload[0] < 0x80 {
+            return payload.to_vec();
+        }
+
+        let mut result = native_header(0x80, payload.len());
+        result.extend_from_slice(payload);
+
+        result
+    }
+
+    fn native_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload: Vec<u8> = items.iter().flat_map(|i| i.iter().cloned()).collect();
+        let mut result = native_header(0xc0, payload.len());
+        result.extend(payload);
+
+        result
+    }
+
+    fn alloc_bytes<CS: ConstraintSystem<Bn256>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        let mut bits = vec![];
+        for (byte_i, byte) in bytes.iter().enumerate() {
+            for bit_i in 0..8 {
+                let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+                bits.push(AllocatedBit::alloc(cs, Some((byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+            }
+        }
+
+        bits
+    }
+
+    fn bits_into_bytes(bits: &[Boolean]) -> Vec<u8> {
+        bits.chunks(8).map(|byte| {
+            byte.iter().rev().fold(0u8, |acc, b| (acc << 1) | (b.get_value().unwrap() as u8))
+        }).collect()
+    }
+
+    fn num_value(value: usize) -> Fr {
+        Fr::from_str(&value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_decode_list() {
+        let payloads = vec![
+            vec![0xab; 32],
+            b"dog".to_vec(),
+            vec![0x05],
+            vec![],
+            vec![0x11; 60]
+        ];
+        let items: Vec<Vec<u8>> = payloads.iter().map(|p| native_string(p)).collect();
+        let encoding = native_list(&items);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input = alloc_bytes(cs.namespace(|| "input"), &encoding);
+
+        let (header, decoded) = decode_list(cs.namespace(|| "decode"), &input, payloads.len(), 64).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(header.payload_length.get_value().unwrap(), num_value(encoding.len() - 2));
+
+        for (item, payload) in decoded.iter().zip(payloads.iter()) {
+            assert_eq!(item.header.is_list.get_value().unwrap(), false);
+            assert_eq!(item.header.payload_length.get_value().unwrap(), num_value(payload.len()));
+            assert_eq!(&bits_into_bytes(&item.payload)[0..payload.len()], &payload[..]);
+        }
+    }
+
+    #[test]
+    fn test_decode_list_wrong_number_of_items() {
+        let items = vec![native_string(b"cat"), native_string(b"dog")];
+        let encoding = native_list(&items);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input = alloc_bytes(cs.namespace(|| "input"), &encoding);
+
+        decode_list(cs.namespace(|| "decode"), &input, 1, 8).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_payload_into_num() {
+        let payloads = vec![vec![0x01, 0x00], vec![0x7f], vec![], vec![0x12, 0x34, 0x56]];
+        let expected = [256, 127, 0, 0x123456];
+
+        let items: Vec<Vec<u8>> = payloads.iter().map(|p| native_string(p)).collect();
+        let encoding = native_list(&items);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let input = alloc_bytes(cs.namespace(|| "input"), &encoding);
+
+        let (_, decoded) = decode_list(cs.namespace(|| "decode"), &input, payloads.len(), 4).unwrap();
+
+        for (i, (item, expected)) in decoded.iter().zip(expected.iter()).enumerate() {
+            let num = payload_into_num(cs.namespace(|| format!("into num {}", i)), item, 4).unwrap();
+            assert_eq!(num.get_value().unwrap(), num_value(*expected));
+        }
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_encode() {
+        let a = vec![0xcd; 32];
+        let b = vec![0x42; 70];
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let a_bits = alloc_bytes(cs.namespace(|| "a"), &a);
+        let b_bits = alloc_bytes(cs.namespace(|| "b"), &b);
+
+        let encoded = encode_list(&[encode_string(&a_bits), encode_string(&b_bits), encode_string(&[])]);
+
+        let expected = native_list(&[native_string(&a), native_string(&b), native_string(&[])]);
+
+        assert_eq!(bits_into_bytes(&encoded), expected);
+        assert_eq!(cs.num_constraints(), 0);
+    }
+}
@@ -0,0 +1,80 @@
+//! Anchors from the recent roots of `root_history`.
+//!
+//! The anchor is proven to be a slot of the committed ring buffer,
+//! so the statement doesn't reveal how old it is. Short histories
+//! exposed as public inputs can use `set_membership::enforce_membership`
+//! instead.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::Boolean;
+
+use super::num::AllocatedNum;
+
+use super::set_membership::enforce_membership_in_tree;
+
+use poseidon2::Poseidon2Params;
+
+/// Enforces that `anchor` is in the root history with the given
+/// `commitment`, at the slot with little-endian bits `slot_bits`
+/// and the authentication path `path` from `RootHistory::witness`.
+pub fn enforce_recent_root<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    commitment: &AllocatedNum<E>,
+    anchor: &AllocatedNum<E>,
+    slot_bits: &[Boolean],
+    path: &[AllocatedNum<E>]
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    enforce_membership_in_tree(cs.namespace(|| "anchor in history"), params, commitment, anchor, slot_bits, path)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::test::*;
+    use ::poseidon2::generate_params;
+    use ::root_history::RootHistory;
+    use super::*;
+
+    #[test]
+    fn test_recent_root() {
+        let params = generate_params::<Fr>(3);
+        let mut history = RootHistory::new(&params, 3, fr(1000));
+        for i in 0..10 {
+            history.push(fr(i));
+        }
+
+        for &(anchor, stale, recent) in [(9u64, 9u64, true), (4, 4, true), (1, 9, false)].iter() {
+            // stale anchors use the witness of a root in the history
+            let (slot, path) = history.witness(&fr(stale)).unwrap();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let commitment = AllocatedNum::alloc(cs.namespace(|| "commitment"), || Ok(history.commitment())).unwrap();
+            let anchor = AllocatedNum::alloc(cs.namespace(|| "anchor"), || Ok(fr(anchor))).unwrap();
+            let slot_bits: Vec<_> = (0..3).map(|i| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("slot bit {}", i)), Some((slot >> i) & 1 == 1)).unwrap())
+            }).collect();
+            let path: Vec<_> = path.into_iter().enumerate().map(|(i, el)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("path {}", i)), || Ok(el)).unwrap()
+            }).collect();
+
+            enforce_recent_root(cs.namespace(|| "recent root"), &params, &commitment, &anchor, &slot_bits, &path).unwrap();
+
+            assert_eq!(cs.is_satisfied(), recent);
+        }
+    }
+}
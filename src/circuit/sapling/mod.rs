@@ -11,9 +11,21 @@ use bellman::pairing::ff::{
 use bellman::{
     SynthesisError,
     ConstraintSystem,
-    Circuit
+    Circuit,
+    LinearCombination
 };
 
+use bellman::groth16::{
+    Parameters,
+    PreparedVerifyingKey,
+    prepare_verifying_key
+};
+
+use bellman::pairing::bls12_381::Bls12;
+use bellman::pairing::bn256::Bn256;
+
+use std::io::{self, Read};
+
 use jubjub::{
     JubjubEngine,
     FixedGenerators
@@ -24,7 +36,8 @@ use constants;
 use primitives::{
     ValueCommitment,
     ProofGenerationKey,
-    PaymentAddress
+    PaymentAddress,
+    TimeLock
 };
 
 use super::Assignment;
@@ -34,6 +47,32 @@ use super::pedersen_hash;
 use super::blake2s;
 use super::num;
 use super::multipack;
+use super::comparison;
+use super::prf::{Blake2sPrf, PrfGadget};
+use super::witness::WitnessSources;
+
+/// The circuits are generic over the engine, the curve is chosen by
+/// the `JubjubEngine` implementation: Jubjub (`jubjub::JubjubBls12`
+/// parameters) for BLS12-381 and Baby Jubjub (`alt_babyjubjub::AltJubjubBn256`
+/// parameters) for BN256.
+pub type SpendBls12<'a> = Spend<'a, Bls12>;
+pub type OutputBls12<'a> = Output<'a, Bls12>;
+pub type SpendBn256<'a> = Spend<'a, Bn256>;
+pub type OutputBn256<'a> = Output<'a, Bn256>;
+
+/// Reads the Groth16 parameters of a circuit and prepares
+/// the verifying key. The points are checked to be in the
+/// correct subgroup if `checked` is set.
+pub fn load_parameters<E: JubjubEngine, R: Read>(
+    reader: R,
+    checked: bool
+) -> io::Result<(Parameters<E>, PreparedVerifyingKey<E>)>
+{
+    let params = Parameters::read(reader, checked)?;
+    let pvk = prepare_verifying_key(&params.vk);
+
+    Ok((params, pvk))
+}
 
 /// This is an instance of the `Spend` circuit.
 pub struct Spend<'a, E: JubjubEngine> {
@@ -76,10 +115,72 @@ pub struct Output<'a, E: JubjubEngine> {
     /// The randomness used to hide the note commitment data
     pub commitment_randomness: Option<E::Fs>,
 
-    /// The ephemeral secret key for DH with recipient
+    /// The ephemeral secret key for DH with recipient. The
+    /// circuit exposes epk = esk . g_d for the g_d placed in the
+    /// note, so the key used for the note encryption is bound to
+    /// the committed recipient.
     pub esk: Option<E::Fs>
 }
 
+impl<'a, E: JubjubEngine> Spend<'a, E> {
+    /// Instance without a witness for a tree of the given depth,
+    /// for parameter generation and constraint counting.
+    pub fn blank(params: &'a E::Params, depth: usize) -> Self {
+        Spend {
+            params: params,
+            value_commitment: None,
+            proof_generation_key: None,
+            payment_address: None,
+            commitment_randomness: None,
+            ar: None,
+            auth_path: vec![None; depth],
+            anchor: None
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine> Output<'a, E> {
+    /// Instance without a witness, for parameter generation and
+    /// constraint counting.
+    pub fn blank(params: &'a E::Params) -> Self {
+        Output {
+            params: params,
+            value_commitment: None,
+            payment_address: None,
+            commitment_randomness: None,
+            esk: None
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine> WitnessSources for Spend<'a, E> {
+    fn witness_sources(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("ak", "proof_generation_key"),
+            ("ar", "ar"),
+            ("nsk", "proof_generation_key"),
+            ("witness g_d", "payment_address"),
+            ("value commitment", "value_commitment"),
+            ("rcm", "commitment_randomness"),
+            ("merkle tree hash", "auth_path"),
+            ("conditional anchor", "anchor")
+        ]
+    }
+}
+
+impl<'a, E: JubjubEngine> WitnessSources for Output<'a, E> {
+    fn witness_sources(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("value commitment", "value_commitment"),
+            ("witness g_d", "payment_address"),
+            ("pk_d bits of y", "payment_address"),
+            ("pk_d bit of x", "payment_address"),
+            ("rcm", "commitment_randomness"),
+            ("esk", "esk")
+        ]
+    }
+}
+
 /// Exposes a Pedersen commitment to the value as an
 /// input to the circuit
 fn expose_value_commitment<E, CS>(
@@ -135,6 +236,24 @@ fn expose_value_commitment<E, CS>(
 
 impl<'a, E: JubjubEngine> Circuit<E> for Spend<'a, E> {
     fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>
+    {
+        self.synthesize_with_extension(
+            cs,
+            pedersen_hash::Personalization::NoteCommitment,
+            vec![]
+        )
+    }
+}
+
+impl<'a, E: JubjubEngine> Spend<'a, E> {
+    /// Synthesizes the spend of a note whose contents are followed by
+    /// `extension` and committed to under `personalization`.
+    fn synthesize_with_extension<CS: ConstraintSystem<E>>(
+        self,
+        cs: &mut CS,
+        personalization: pedersen_hash::Personalization,
+        extension: Vec<boolean::Boolean>
+    ) -> Result<(), SynthesisError>
     {
         // Prover witnesses ak (ensures that it's on the curve)
         let ak = ecc::EdwardsPoint::witness(
@@ -206,22 +325,16 @@ impl<'a, E: JubjubEngine> Circuit<E> for Spend<'a, E> {
             ak.repr(cs.namespace(|| "representation of ak"))?
         );
 
-        // This is the nullifier preimage for PRF^nf
-        let mut nf_preimage = vec![];
-
-        // Extend ivk and nf preimages with the representation of
-        // nk.
-        {
-            let repr_nk = nk.repr(
-                cs.namespace(|| "representation of nk")
-            )?;
+        // The representation of nk is the key of PRF^nf
+        let repr_nk = nk.repr(
+            cs.namespace(|| "representation of nk")
+        )?;
 
-            ivk_preimage.extend(repr_nk.iter().cloned());
-            nf_preimage.extend(repr_nk);
-        }
+        // Extend the ivk preimage with the representation of nk.
+        ivk_preimage.extend(repr_nk.iter().cloned());
 
         assert_eq!(ivk_preimage.len(), 512);
-        assert_eq!(nf_preimage.len(), 256);
+        assert_eq!(repr_nk.len(), 256);
 
         // Compute the incoming viewing key ivk
         let mut ivk = blake2s::blake2s(
@@ -306,17 +419,22 @@ impl<'a, E: JubjubEngine> Circuit<E> for Spend<'a, E> {
             pk_d.repr(cs.namespace(|| "representation of pk_d"))?
         );
 
+        // Place the extension, if any, after the standard contents
+        let extension_len = extension.len();
+        note_contents.extend(extension);
+
         assert_eq!(
             note_contents.len(),
             64 + // value
             256 + // g_d
-            256 // p_d
+            256 + // p_d
+            extension_len
         );
 
         // Compute the hash of the note contents
         let mut cm = pedersen_hash::pedersen_hash(
             cs.namespace(|| "note content hash"),
-            pedersen_hash::Personalization::NoteCommitment,
+            personalization,
             &note_contents,
             self.params
         )?;
@@ -448,23 +566,154 @@ impl<'a, E: JubjubEngine> Circuit<E> for Spend<'a, E> {
         }
         
         // Let's compute nf = BLAKE2s(nk || rho)
-        nf_preimage.extend(
-            rho.repr(cs.namespace(|| "representation of rho"))?
-        );
+        let repr_rho = rho.repr(cs.namespace(|| "representation of rho"))?;
 
-        assert_eq!(nf_preimage.len(), 512);
+        assert_eq!(repr_rho.len(), 256);
         
         // Compute nf
-        let nf = blake2s::blake2s(
+        let prf_nf = Blake2sPrf {
+            personalization: constants::PRF_NF_PERSONALIZATION
+        };
+        let nf = PrfGadget::<E>::evaluate_bits(
+            &prf_nf,
             cs.namespace(|| "nf computation"),
-            &nf_preimage,
-            constants::PRF_NF_PERSONALIZATION
+            &repr_nk,
+            &repr_rho
         )?;
 
         multipack::pack_into_inputs(cs.namespace(|| "pack nullifier"), &nf)
     }
 }
 
+/// The spend of a `TimedNote`. The current height is the first
+/// public input, followed by the inputs of `Spend`.
+pub struct TimedSpend<'a, E: JubjubEngine> {
+    pub spend: Spend<'a, E>,
+
+    /// The time lock committed to in the note
+    pub time_lock: Option<TimeLock>,
+
+    /// The height the note is spent at
+    pub current_height: Option<u32>
+}
+
+impl<'a, E: JubjubEngine> TimedSpend<'a, E> {
+    pub fn blank(params: &'a E::Params, depth: usize) -> Self {
+        TimedSpend {
+            spend: Spend::blank(params, depth),
+            time_lock: None,
+            current_height: None
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine> WitnessSources for TimedSpend<'a, E> {
+    fn witness_sources(&self) -> Vec<(&'static str, &'static str)> {
+        let mut sources = vec![
+            ("current height", "current_height"),
+            ("time lock", "time_lock")
+        ];
+        sources.extend(self.spend.witness_sources());
+
+        sources
+    }
+}
+
+impl<'a, E: JubjubEngine> Circuit<E> for TimedSpend<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>
+    {
+        let current_height = alloc_height(
+            cs.namespace(|| "current height"),
+            self.current_height
+        )?.0;
+        current_height.inputize(cs.namespace(|| "expose current height"))?;
+
+        let lock_bits = enforce_time_lock(
+            cs.namespace(|| "time lock"),
+            self.time_lock,
+            &current_height
+        )?;
+
+        self.spend.synthesize_with_extension(
+            cs,
+            pedersen_hash::Personalization::Custom(constants::TIMED_NOTE_COMMITMENT_TAG),
+            lock_bits
+        )
+    }
+}
+
+/// Allocates a height with its 32 little-endian bits.
+fn alloc_height<E, CS>(
+    mut cs: CS,
+    value: Option<u32>
+) -> Result<(num::AllocatedNum<E>, Vec<boolean::Boolean>), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let height = num::AllocatedNum::alloc(
+        cs.namespace(|| "height"),
+        || {
+            let value = *value.get()? as u64;
+
+            Ok(E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(value)).unwrap())
+        }
+    )?;
+
+    let bits = comparison::alloc_packed_bits(
+        cs.namespace(|| "bits"),
+        height.get_value(),
+        LinearCombination::zero() + height.get_variable(),
+        32
+    )?;
+
+    Ok((height, bits))
+}
+
+/// Witnesses a time lock and enforces that it has been unlocked and
+/// hasn't expired at `current_height`, which must fit into 32 bits.
+/// Returns the bits of the lock, to be appended to the note contents.
+pub fn enforce_time_lock<E, CS>(
+    mut cs: CS,
+    lock: Option<TimeLock>,
+    current_height: &num::AllocatedNum<E>
+) -> Result<Vec<boolean::Boolean>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let (unlock_height, mut bits) = alloc_height(
+        cs.namespace(|| "unlock height"),
+        lock.map(|l| l.unlock_height)
+    )?;
+    let (expiry_height, expiry_bits) = alloc_height(
+        cs.namespace(|| "expiry height"),
+        lock.map(|l| l.expiry_height)
+    )?;
+
+    // unlock_height <= current_height < expiry_height
+    comparison::enforce_less_or_equal(
+        cs.namespace(|| "unlocked"),
+        &unlock_height,
+        current_height,
+        32
+    )?;
+
+    let not_expired = comparison::less_than(
+        cs.namespace(|| "compare with expiry"),
+        current_height,
+        &expiry_height,
+        32
+    )?;
+    boolean::Boolean::enforce_equal(
+        cs.namespace(|| "not expired"),
+        &not_expired,
+        &boolean::Boolean::constant(true)
+    )?;
+
+    bits.extend(expiry_bits);
+
+    Ok(bits)
+}
+
 impl<'a, E: JubjubEngine> Circuit<E> for Output<'a, E> {
     fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>
     {
@@ -602,6 +851,229 @@ impl<'a, E: JubjubEngine> Circuit<E> for Output<'a, E> {
     }
 }
 
+/// Discloses a note to an auditor holding an incoming viewing
+/// key: proves that the note commitment opens to the claimed
+/// value and diversified base, and that its pk_d is derived
+/// from them with the viewing key, i.e. that the note was sent
+/// to a payment address of that key. The public inputs are
+/// the value, ivk, g_d and the note commitment.
+pub struct PaymentDisclosure<'a, E: JubjubEngine> {
+    pub params: &'a E::Params,
+
+    /// The incoming viewing key of the recipient
+    pub ivk: Option<E::Fs>,
+
+    /// The value of the note
+    pub value: Option<u64>,
+
+    /// The payment address the note was sent to
+    pub payment_address: Option<PaymentAddress<E>>,
+
+    /// The randomness of the note commitment
+    pub commitment_randomness: Option<E::Fs>
+}
+
+impl<'a, E: JubjubEngine> PaymentDisclosure<'a, E> {
+    /// Instance without a witness, for parameter generation and
+    /// constraint counting.
+    pub fn blank(params: &'a E::Params) -> Self {
+        PaymentDisclosure {
+            params: params,
+            ivk: None,
+            value: None,
+            payment_address: None,
+            commitment_randomness: None
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine> WitnessSources for PaymentDisclosure<'a, E> {
+    fn witness_sources(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("value", "value"),
+            ("ivk", "ivk"),
+            ("witness g_d", "payment_address"),
+            ("rcm", "commitment_randomness")
+        ]
+    }
+}
+
+impl<'a, E: JubjubEngine> Circuit<E> for PaymentDisclosure<'a, E> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>
+    {
+        // Booleanize the value into little-endian bit order
+        // and expose it.
+        let value_bits = boolean::u64_into_boolean_vec_le(
+            cs.namespace(|| "value"),
+            self.value
+        )?;
+
+        multipack::pack_into_inputs(cs.namespace(|| "pack value"), &value_bits)?;
+
+        // Booleanize ivk and expose it. It's the output of CRH^ivk,
+        // so it's truncated to the capacity of the scalar field.
+        let mut ivk = boolean::field_into_boolean_vec_le(
+            cs.namespace(|| "ivk"),
+            self.ivk
+        )?;
+
+        for (i, bit) in ivk.drain((E::Fs::CAPACITY as usize)..).enumerate() {
+            boolean::Boolean::enforce_equal(
+                cs.namespace(|| format!("ivk is truncated {}", i)),
+                &bit,
+                &boolean::Boolean::constant(false)
+            )?;
+        }
+
+        multipack::pack_into_inputs(cs.namespace(|| "pack ivk"), &ivk)?;
+
+        let g_d = {
+            let params = self.params;
+
+            ecc::EdwardsPoint::witness(
+                cs.namespace(|| "witness g_d"),
+                self.payment_address.as_ref().and_then(|a| a.g_d(params)),
+                self.params
+            )?
+        };
+
+        // As in the Output circuit, g_d must be large order for
+        // pk_d to bind ivk.
+        g_d.assert_not_small_order(
+            cs.namespace(|| "g_d not small order"),
+            self.params
+        )?;
+
+        g_d.inputize(cs.namespace(|| "g_d"))?;
+
+        // Compute pk_d = g_d^ivk
+        let pk_d = g_d.mul(
+            cs.namespace(|| "compute pk_d"),
+            &ivk,
+            self.params
+        )?;
+
+        let mut note_contents = value_bits;
+
+        note_contents.extend(
+            g_d.repr(cs.namespace(|| "representation of g_d"))?
+        );
+
+        note_contents.extend(
+            pk_d.repr(cs.namespace(|| "representation of pk_d"))?
+        );
+
+        assert_eq!(
+            note_contents.len(),
+            64 + // value
+            256 + // g_d
+            256 // pk_d
+        );
+
+        // Compute the hash of the note contents
+        let mut cm = pedersen_hash::pedersen_hash(
+            cs.namespace(|| "note content hash"),
+            pedersen_hash::Personalization::NoteCommitment,
+            &note_contents,
+            self.params
+        )?;
+
+        {
+            let rcm = boolean::field_into_boolean_vec_le(
+                cs.namespace(|| "rcm"),
+                self.commitment_randomness
+            )?;
+
+            let rcm = ecc::fixed_base_multiplication(
+                cs.namespace(|| "computation of commitment randomness"),
+                FixedGenerators::NoteCommitmentRandomness,
+                &rcm,
+                self.params
+            )?;
+
+            cm = cm.add(
+                cs.namespace(|| "randomization of note commitment"),
+                &rcm,
+                self.params
+            )?;
+        }
+
+        cm.get_x().inputize(cs.namespace(|| "commitment"))?;
+
+        Ok(())
+    }
+}
+
+/// Enforces the homomorphic balance of value commitments:
+/// sum(spends) - sum(outputs) = value_balance . V + randomness . R
+/// for the value and randomness generators V and R. The balance
+/// is given by its 64 bit magnitude (little-endian) and sign, the
+/// randomness is the difference of the randomness of the spends
+/// and of the outputs, e.g. the binding signature key.
+pub fn enforce_value_balance<E, CS>(
+    mut cs: CS,
+    spends: &[ecc::EdwardsPoint<E>],
+    outputs: &[ecc::EdwardsPoint<E>],
+    value_balance: &[boolean::Boolean],
+    value_balance_is_negative: &boolean::Boolean,
+    randomness: &[boolean::Boolean],
+    params: &E::Params
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    assert_eq!(value_balance.len(), 64);
+
+    let balance = ecc::fixed_base_multiplication(
+        cs.namespace(|| "computation of the value balance"),
+        FixedGenerators::ValueCommitmentValue,
+        value_balance,
+        params
+    )?;
+
+    // A positive balance is on the side of the outputs, a
+    // negative one on the side of the spends.
+    let mut lhs = balance.conditionally_select(
+        cs.namespace(|| "negative balance"),
+        value_balance_is_negative
+    )?;
+
+    let mut rhs = ecc::fixed_base_multiplication_and_add(
+        cs.namespace(|| "computation of the randomness"),
+        FixedGenerators::ValueCommitmentRandomness,
+        randomness,
+        &balance.conditionally_select(
+            cs.namespace(|| "positive balance"),
+            &value_balance_is_negative.not()
+        )?,
+        params
+    )?;
+
+    for (i, cv) in spends.iter().enumerate() {
+        lhs = lhs.add(cs.namespace(|| format!("spend {}", i)), cv, params)?;
+    }
+
+    for (i, cv) in outputs.iter().enumerate() {
+        rhs = rhs.add(cs.namespace(|| format!("output {}", i)), cv, params)?;
+    }
+
+    cs.enforce(
+        || "x coordinates are equal",
+        |lc| lc + lhs.get_x().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + rhs.get_x().get_variable()
+    );
+
+    cs.enforce(
+        || "y coordinates are equal",
+        |lc| lc + lhs.get_y().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + rhs.get_y().get_variable()
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_input_circuit_with_bls12_381() {
     use bellman::pairing::ff::{Field, BitIterator};
@@ -734,6 +1206,120 @@ fn test_input_circuit_with_bls12_381() {
     }
 }
 
+#[test]
+fn test_timed_spend_circuit() {
+    use bellman::pairing::ff::{Field, BitIterator};
+    use bellman::pairing::bls12_381::*;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use ::circuit::test::*;
+    use jubjub::{JubjubBls12, fs, edwards};
+    use primitives::{Note, TimedNote};
+
+    let params = &JubjubBls12::new();
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let tree_depth = 4;
+
+    let value_commitment = ValueCommitment {
+        value: rng.gen(),
+        randomness: rng.gen()
+    };
+
+    let proof_generation_key = ProofGenerationKey {
+        ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+        nsk: rng.gen()
+    };
+    let viewing_key = proof_generation_key.into_viewing_key(params);
+
+    let payment_address = loop {
+        let diversifier = ::primitives::Diversifier(rng.gen());
+
+        if let Some(p) = viewing_key.into_payment_address(diversifier, params) {
+            break p;
+        }
+    };
+
+    let commitment_randomness: fs::Fs = rng.gen();
+    let auth_path = vec![Some((rng.gen(), rng.gen())); tree_depth];
+    let ar: fs::Fs = rng.gen();
+
+    let time_lock = TimeLock {
+        unlock_height: 1000,
+        expiry_height: 2000
+    };
+    let note = TimedNote {
+        note: Note {
+            value: value_commitment.value,
+            g_d: payment_address.diversifier.g_d(params).unwrap(),
+            pk_d: payment_address.pk_d.clone(),
+            r: commitment_randomness
+        },
+        lock: time_lock
+    };
+
+    let cm: Fr = note.cm(params);
+    assert!(cm != note.note.cm(params));
+
+    let mut position = 0u64;
+    let mut cur = cm;
+    for (i, val) in auth_path.iter().enumerate() {
+        let (uncle, b) = val.unwrap();
+
+        let (lhs, rhs) = if b { (uncle, cur) } else { (cur, uncle) };
+        let mut lhs: Vec<bool> = BitIterator::new(lhs.into_repr()).collect();
+        let mut rhs: Vec<bool> = BitIterator::new(rhs.into_repr()).collect();
+        lhs.reverse();
+        rhs.reverse();
+
+        cur = ::pedersen_hash::pedersen_hash::<Bls12, _>(
+            ::pedersen_hash::Personalization::MerkleTree(i),
+            lhs.into_iter()
+               .take(Fr::NUM_BITS as usize)
+               .chain(rhs.into_iter().take(Fr::NUM_BITS as usize)),
+            params
+        ).into_xy().0;
+
+        if b {
+            position |= 1 << i;
+        }
+    }
+
+    let expected_nf = note.nf(&viewing_key, position, params);
+    let expected_nf = multipack::compute_multipacking::<Bls12>(&multipack::bytes_to_bits_le(&expected_nf));
+
+    for &(height, spendable) in [(999u32, false), (1000, true), (1999, true), (2000, false)].iter() {
+        assert_eq!(time_lock.is_spendable_at(height), spendable);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let instance = TimedSpend {
+            spend: Spend {
+                params: params,
+                value_commitment: Some(value_commitment.clone()),
+                proof_generation_key: Some(proof_generation_key.clone()),
+                payment_address: Some(payment_address.clone()),
+                commitment_randomness: Some(commitment_randomness),
+                ar: Some(ar),
+                auth_path: auth_path.clone(),
+                anchor: Some(cur)
+            },
+            time_lock: Some(time_lock),
+            current_height: Some(height)
+        };
+
+        instance.synthesize(&mut cs).unwrap();
+
+        assert_eq!(cs.is_satisfied(), spendable);
+        assert_eq!(cs.get("randomization of note commitment/x3/num"), cm);
+
+        assert_eq!(cs.num_inputs(), 9);
+        assert_eq!(cs.get_input(1, "expose current height/input variable"), Fr::from_str(&height.to_string()).unwrap());
+        assert_eq!(cs.get_input(6, "anchor/input variable"), cur);
+        assert_eq!(cs.get_input(7, "pack nullifier/input 0"), expected_nf[0]);
+        assert_eq!(cs.get_input(8, "pack nullifier/input 1"), expected_nf[1]);
+    }
+}
+
 #[test]
 fn test_output_circuit_with_bls12_381() {
     use bellman::pairing::ff::{Field};
@@ -817,3 +1403,318 @@ fn test_output_circuit_with_bls12_381() {
         }
     }
 }
+
+#[test]
+fn test_output_circuit_epk_binding() {
+    use bellman::pairing::bls12_381::*;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use ::circuit::test::*;
+    use jubjub::{JubjubBls12, fs, edwards};
+
+    let params = &JubjubBls12::new();
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let value_commitment = ValueCommitment {
+        value: rng.gen(),
+        randomness: rng.gen()
+    };
+
+    let proof_generation_key = ::primitives::ProofGenerationKey {
+        ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+        nsk: rng.gen()
+    };
+
+    let viewing_key = proof_generation_key.into_viewing_key(params);
+
+    let payment_address;
+
+    loop {
+        let diversifier = ::primitives::Diversifier(rng.gen());
+
+        if let Some(p) = viewing_key.into_payment_address(
+            diversifier,
+            params
+        )
+        {
+            payment_address = p;
+            break;
+        }
+    }
+
+    let esk: fs::Fs = rng.gen();
+
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+    let instance = Output {
+        params: params,
+        value_commitment: Some(value_commitment),
+        payment_address: Some(payment_address),
+        commitment_randomness: Some(rng.gen()),
+        esk: Some(esk)
+    };
+
+    instance.synthesize(&mut cs).unwrap();
+    assert!(cs.is_satisfied());
+
+    // an epk for another diversified base is rejected
+    let other_epk = edwards::Point::rand(rng, params).mul_by_cofactor(params).mul(esk, params).into_xy();
+    cs.set("epk/x/input variable", other_epk.0);
+    cs.set("epk/y/input variable", other_epk.1);
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_blank_circuits() {
+    use bellman::pairing::bls12_381::*;
+    use ::circuit::fingerprint::CircuitFingerprint;
+    use jubjub::JubjubBls12;
+
+    let params = &JubjubBls12::new();
+
+    let mut cs = CircuitFingerprint::<Bls12>::new();
+    Spend::blank(params, 32).synthesize(&mut cs).unwrap();
+    assert_eq!(cs.num_constraints(), 98777);
+    assert_eq!(cs.num_inputs(), 8);
+
+    let mut cs = CircuitFingerprint::<Bls12>::new();
+    Output::blank(params).synthesize(&mut cs).unwrap();
+    assert_eq!(cs.num_constraints(), 7827);
+    assert_eq!(cs.num_inputs(), 6);
+}
+
+#[test]
+fn test_output_circuit_with_bn256() {
+    use bellman::pairing::ff::{Field};
+    use bellman::pairing::bn256::*;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use ::circuit::test::*;
+    use alt_babyjubjub::{AltJubjubBn256, fs, edwards};
+
+    let params = &AltJubjubBn256::new();
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    for _ in 0..10 {
+        let value_commitment = ValueCommitment {
+            value: rng.gen(),
+            randomness: rng.gen()
+        };
+
+        let nsk: fs::Fs = rng.gen();
+        let ak = edwards::Point::rand(rng, params).mul_by_cofactor(params);
+
+        let proof_generation_key = ::primitives::ProofGenerationKey {
+            ak: ak.clone(),
+            nsk: nsk.clone()
+        };
+
+        let viewing_key = proof_generation_key.into_viewing_key(params);
+
+        let payment_address;
+
+        loop {
+            let diversifier = ::primitives::Diversifier(rng.gen());
+
+            if let Some(p) = viewing_key.into_payment_address(
+                diversifier,
+                params
+            )
+            {
+                payment_address = p;
+                break;
+            }
+        }
+
+        let commitment_randomness: fs::Fs = rng.gen();
+        let esk: fs::Fs = rng.gen();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let instance: OutputBn256 = Output {
+            params: params,
+            value_commitment: Some(value_commitment.clone()),
+            payment_address: Some(payment_address.clone()),
+            commitment_randomness: Some(commitment_randomness),
+            esk: Some(esk.clone())
+        };
+
+        instance.synthesize(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        let expected_cm = payment_address.create_note(
+            value_commitment.value,
+            commitment_randomness,
+            params
+        ).expect("should be valid").cm(params);
+
+        let expected_epk = payment_address.g_d(params).expect("should be valid").mul(esk, params);
+        let expected_epk_xy = expected_epk.into_xy();
+
+        assert_eq!(cs.num_inputs(), 6);
+        assert_eq!(cs.get_input(0, "ONE"), Fr::one());
+        assert_eq!(cs.get_input(3, "epk/x/input variable"), expected_epk_xy.0);
+        assert_eq!(cs.get_input(4, "epk/y/input variable"), expected_epk_xy.1);
+        assert_eq!(cs.get_input(5, "commitment/input variable"), expected_cm);
+    }
+}
+
+#[test]
+fn test_payment_disclosure_circuit() {
+    use bellman::pairing::ff::{Field};
+    use bellman::pairing::bls12_381::*;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use ::circuit::test::*;
+    use jubjub::{JubjubBls12, fs, edwards};
+
+    let params = &JubjubBls12::new();
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let proof_generation_key = ::primitives::ProofGenerationKey {
+        ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+        nsk: rng.gen()
+    };
+
+    let viewing_key = proof_generation_key.into_viewing_key(params);
+
+    let payment_address;
+
+    loop {
+        let diversifier = ::primitives::Diversifier(rng.gen());
+
+        if let Some(p) = viewing_key.into_payment_address(
+            diversifier,
+            params
+        )
+        {
+            payment_address = p;
+            break;
+        }
+    }
+
+    let value: u64 = rng.gen();
+    let commitment_randomness: fs::Fs = rng.gen();
+
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+
+    let instance = PaymentDisclosure {
+        params: params,
+        ivk: Some(viewing_key.ivk()),
+        value: Some(value),
+        payment_address: Some(payment_address.clone()),
+        commitment_randomness: Some(commitment_randomness)
+    };
+
+    instance.synthesize(&mut cs).unwrap();
+
+    assert!(cs.is_satisfied());
+    assert_eq!(cs.find_unconstrained(), "");
+
+    let expected_cm = payment_address.create_note(
+        value,
+        commitment_randomness,
+        params
+    ).expect("should be valid").cm(params);
+
+    let g_d = payment_address.g_d(params).expect("should be valid").into_xy();
+
+    let mut ivk_bits = multipack::bytes_to_bits_le(&{
+        let mut bytes = vec![];
+        viewing_key.ivk().into_repr().write_le(&mut bytes).unwrap();
+        bytes
+    });
+    ivk_bits.truncate(fs::Fs::CAPACITY as usize);
+
+    let mut value_bits = multipack::bytes_to_bits_le(&{
+        let mut bytes = vec![];
+        bytes.extend((0..8).map(|i| (value >> (8 * i)) as u8));
+        bytes
+    });
+    value_bits.truncate(64);
+
+    let mut expected = multipack::compute_multipacking::<Bls12>(&value_bits);
+    expected.extend(multipack::compute_multipacking::<Bls12>(&ivk_bits));
+    expected.push(g_d.0);
+    expected.push(g_d.1);
+    expected.push(expected_cm);
+
+    assert!(cs.verify(&expected));
+
+    // the note doesn't open to another value
+    let mut other = expected.clone();
+    other[0].add_assign(&Fr::one());
+    assert!(!cs.verify(&other));
+
+    // and another viewing key doesn't derive its pk_d
+    cs.set("ivk/bit 0/boolean", if ivk_bits[0] { Fr::zero() } else { Fr::one() });
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_value_balance() {
+    use bellman::pairing::bls12_381::*;
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use ::circuit::test::*;
+    use jubjub::{JubjubBls12, fs};
+
+    let params = &JubjubBls12::new();
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    for &(num_spends, num_outputs) in [(2, 3), (3, 1), (0, 1)].iter() {
+        let spends: Vec<ValueCommitment<Bls12>> = (0..num_spends).map(|_| ValueCommitment {
+            value: rng.gen::<u32>() as u64,
+            randomness: rng.gen()
+        }).collect();
+        let outputs: Vec<ValueCommitment<Bls12>> = (0..num_outputs).map(|_| ValueCommitment {
+            value: rng.gen::<u32>() as u64,
+            randomness: rng.gen()
+        }).collect();
+
+        let spent: u64 = spends.iter().map(|c| c.value).sum();
+        let received: u64 = outputs.iter().map(|c| c.value).sum();
+        let (magnitude, is_negative) = if spent >= received {
+            (spent - received, false)
+        } else {
+            (received - spent, true)
+        };
+
+        let mut randomness = fs::Fs::zero();
+        for c in spends.iter() {
+            randomness.add_assign(&c.randomness);
+        }
+        for c in outputs.iter() {
+            randomness.sub_assign(&c.randomness);
+        }
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let witness = |cs: &mut TestConstraintSystem<Bls12>, name: &str, cvs: &[ValueCommitment<Bls12>]| {
+            cvs.iter().enumerate().map(|(i, c)| {
+                ecc::EdwardsPoint::witness(cs.namespace(|| format!("{} {}", name, i)), Some(c.cm(params)), params).unwrap()
+            }).collect::<Vec<_>>()
+        };
+        let spend_points = witness(&mut cs, "spend", &spends);
+        let output_points = witness(&mut cs, "output", &outputs);
+
+        let value_balance = boolean::u64_into_boolean_vec_le(cs.namespace(|| "value balance"), Some(magnitude)).unwrap();
+        let is_negative = boolean::Boolean::from(
+            boolean::AllocatedBit::alloc(cs.namespace(|| "is negative"), Some(is_negative)).unwrap()
+        );
+        let randomness = boolean::field_into_boolean_vec_le(cs.namespace(|| "randomness"), Some(randomness)).unwrap();
+
+        enforce_value_balance(
+            cs.namespace(|| "balance"),
+            &spend_points,
+            &output_points,
+            &value_balance,
+            &is_negative,
+            &randomness,
+            params
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        // the balance with the other sign doesn't hold
+        cs.set("is negative/boolean", if is_negative.get_value().unwrap() { Fr::zero() } else { Fr::one() });
+        assert!(!cs.is_satisfied());
+    }
+}
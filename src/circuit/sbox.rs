@@ -0,0 +1,286 @@
+//! S-boxes of the algebraic hash gadgets, selected at runtime.
+//!
+//! `x^alpha` is a permutation of the field only if `gcd(alpha, p - 1) = 1`,
+//! e.g. `x^5` and `x^7` for both BN256 and BLS12-381, while `x^3`
+//! is not. The inverse of `x^alpha` is computed by allocating the
+//! preimage and constraining the forward power, so it has the same
+//! cost as the forward direction.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use rescue::exponent_inverse;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SBox {
+    /// `x^alpha`
+    Power(u64),
+    /// `x^-1`, with zero mapped to zero
+    Inverse
+}
+
+impl SBox {
+    /// Checks that the S-box is a permutation of the field.
+    pub fn validate<F: PrimeField>(&self) -> Result<(), SynthesisError> {
+        match *self {
+            SBox::Power(alpha) => {
+                if exponent_inverse::<F>(alpha).is_none() {
+                    return Err(SynthesisError::Unsatisfiable);
+                }
+            },
+            SBox::Inverse => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_native<F: PrimeField>(&self, el: &mut F) {
+        match *self {
+            SBox::Power(alpha) => {
+                *el = el.pow(&[alpha]);
+            },
+            SBox::Inverse => {
+                *el = el.inverse().unwrap_or(F::zero());
+            }
+        }
+    }
+
+    pub fn apply_inverse_native<F: PrimeField>(&self, el: &mut F) -> Result<(), SynthesisError> {
+        match *self {
+            SBox::Power(alpha) => {
+                let alpha_inv = exponent_inverse::<F>(alpha).ok_or(SynthesisError::Unsatisfiable)?;
+                *el = el.pow(&alpha_inv);
+            },
+            SBox::Inverse => {
+                *el = el.inverse().unwrap_or(F::zero());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Allocates the image of `x`.
+    pub fn apply<E, CS>(
+        &self,
+        mut cs: CS,
+        x: &Num<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let result = AllocatedNum::alloc(cs.namespace(|| "image"), || {
+            let mut tmp = *x.get_value().get()?;
+            self.apply_native(&mut tmp);
+
+            Ok(tmp)
+        })?;
+
+        match *self {
+            SBox::Power(alpha) => {
+                enforce_power(cs.namespace(|| "power"), x, alpha, &Num::from(result.clone()))?;
+            },
+            SBox::Inverse => {
+                enforce_inverse(cs.namespace(|| "inverse"), x, &Num::from(result.clone()))?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Allocates the preimage of `x`.
+    pub fn apply_inverse<E, CS>(
+        &self,
+        mut cs: CS,
+        x: &Num<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        self.validate::<E::Fr>()?;
+
+        let preimage = AllocatedNum::alloc(cs.namespace(|| "preimage"), || {
+            let mut tmp = *x.get_value().get()?;
+            self.apply_inverse_native(&mut tmp)?;
+
+            Ok(tmp)
+        })?;
+
+        match *self {
+            SBox::Power(alpha) => {
+                enforce_power(cs.namespace(|| "power"), &Num::from(preimage.clone()), alpha, x)?;
+            },
+            SBox::Inverse => {
+                // the inversion is an involution
+                enforce_inverse(cs.namespace(|| "inverse"), &Num::from(preimage.clone()), x)?;
+            }
+        }
+
+        Ok(preimage)
+    }
+}
+
+/// Enforces `base^alpha = result` by square-and-multiply, with the
+/// last multiplication constrained directly into `result`.
+fn enforce_power<E, CS>(
+    mut cs: CS,
+    base: &Num<E>,
+    alpha: u64,
+    result: &Num<E>
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(alpha >= 2);
+
+    // (multiply by the base instead of squaring) for every step
+    let mut steps = vec![];
+    let num_bits = 64 - alpha.leading_zeros();
+    for i in (0..(num_bits - 1)).rev() {
+        steps.push(false);
+        if (alpha >> i) & 1 == 1 {
+            steps.push(true);
+        }
+    }
+
+    let mut cur = base.clone();
+    for (i, &by_base) in steps.iter().enumerate() {
+        let other = if by_base { base.clone() } else { cur.clone() };
+
+        let out = if i + 1 == steps.len() {
+            result.clone()
+        } else {
+            Num::from(AllocatedNum::alloc(cs.namespace(|| format!("step {}", i)), || {
+                let mut tmp = *cur.get_value().get()?;
+                tmp.mul_assign(other.get_value().get()?);
+
+                Ok(tmp)
+            })?)
+        };
+
+        cs.enforce(
+            || format!("step {} constraint", i),
+            |_| cur.lc(E::Fr::one()),
+            |_| other.lc(E::Fr::one()),
+            |_| out.lc(E::Fr::one())
+        );
+
+        cur = out;
+    }
+
+    Ok(())
+}
+
+/// Enforces `y = x^-1` for non-zero `x` and `y = 0` otherwise.
+fn enforce_inverse<E, CS>(
+    mut cs: CS,
+    x: &Num<E>,
+    y: &Num<E>
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let product = AllocatedNum::alloc(cs.namespace(|| "product"), || {
+        let mut tmp = *x.get_value().get()?;
+        tmp.mul_assign(y.get_value().get()?);
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "product constraint",
+        |_| x.lc(E::Fr::one()),
+        |_| y.lc(E::Fr::one()),
+        |lc| lc + product.get_variable()
+    );
+
+    // x * (xy - 1) = 0 and y * (xy - 1) = 0
+    cs.enforce(
+        || "x is zero or inverted",
+        |_| x.lc(E::Fr::one()),
+        |lc| lc + product.get_variable() - CS::one(),
+        |lc| lc
+    );
+
+    cs.enforce(
+        || "y is zero or inverted",
+        |_| y.lc(E::Fr::one()),
+        |lc| lc + product.get_variable() - CS::one(),
+        |lc| lc
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::bls12_381::{self};
+    use ::circuit::test::*;
+    use super::*;
+
+    #[test]
+    fn test_sbox_constraints() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &(sbox, expected_constraints) in [(SBox::Power(5), 3), (SBox::Power(7), 4), (SBox::Inverse, 3)].iter() {
+            let x: Fr = rng.gen();
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let x_var = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+
+            let y = sbox.apply(cs.namespace(|| "forward"), &Num::from(x_var.clone())).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), expected_constraints);
+
+            let mut expected = x;
+            sbox.apply_native(&mut expected);
+            assert_eq!(y.get_value().unwrap(), expected);
+
+            // the preimage of the image is the input
+            let preimage = sbox.apply_inverse(cs.namespace(|| "backward"), &Num::from(y)).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(preimage.get_value().unwrap(), x);
+        }
+    }
+
+    #[test]
+    fn test_inverse_of_zero() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::zero())).unwrap();
+
+        let y = SBox::Inverse.apply(cs.namespace(|| "inverse"), &Num::from(x)).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(y.get_value().unwrap().is_zero());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(SBox::Power(3).validate::<Fr>().is_err());
+        assert!(SBox::Power(5).validate::<Fr>().is_ok());
+        assert!(SBox::Power(7).validate::<Fr>().is_ok());
+        assert!(SBox::Power(3).validate::<bls12_381::Fr>().is_err());
+        assert!(SBox::Power(5).validate::<bls12_381::Fr>().is_ok());
+        assert!(SBox::Inverse.validate::<bls12_381::Fr>().is_ok());
+    }
+}
@@ -0,0 +1,188 @@
+//! Typed elements of the two fields of a circuit over an embedded
+//! curve: `BaseNum` for the circuit field `E::Fr`, e.g. coordinates and
+//! hash outputs, and `ScalarNum` for the scalar field `E::Fs` of the
+//! prime order subgroup, as used by scalar multiplications.
+//!
+//! A `ScalarNum` is always reduced modulo `r`. Turning a `BaseNum` or
+//! hash bits into one goes through an explicit reduction, so a value of
+//! one field can't be used as the other by accident.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+    BitIterator
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::num::AllocatedNum;
+
+use super::scalar_reduction::{
+    enforce_reduced,
+    reduce_mod_subgroup_order
+};
+
+use jubjub::JubjubEngine;
+
+#[derive(Clone)]
+pub struct BaseNum<E: JubjubEngine> {
+    num: AllocatedNum<E>
+}
+
+impl<E: JubjubEngine> BaseNum<E> {
+    pub fn new(num: AllocatedNum<E>) -> Self {
+        BaseNum {
+            num: num
+        }
+    }
+
+    pub fn get_num(&self) -> &AllocatedNum<E> {
+        &self.num
+    }
+
+    pub fn into_num(self) -> AllocatedNum<E> {
+        self.num
+    }
+
+    pub fn get_value(&self) -> Option<E::Fr> {
+        self.num.get_value()
+    }
+
+    /// The value modulo `r`, from its strict bit decomposition.
+    pub fn reduce_to_scalar<CS>(&self, mut cs: CS) -> Result<ScalarNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let bits = self.num.into_bits_le_strict(cs.namespace(|| "into bits"))?;
+
+        ScalarNum::from_hash(cs.namespace(|| "reduce"), &bits)
+    }
+}
+
+/// An element of `E::Fs` as its little-endian bits, `bits(r)` of them,
+/// encoding a number less than `r`.
+#[derive(Clone)]
+pub struct ScalarNum<E: JubjubEngine> {
+    bits: Vec<Boolean>
+}
+
+impl<E: JubjubEngine> ScalarNum<E> {
+    /// Witnesses a scalar, enforcing that it is reduced.
+    pub fn witness<CS>(mut cs: CS, value: Option<E::Fs>) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let num_bits = E::Fs::NUM_BITS as usize;
+        let values: Option<Vec<bool>> = value.map(|value| {
+            let mut bits: Vec<bool> = BitIterator::new(value.into_repr()).collect();
+            bits.reverse();
+            bits.truncate(num_bits);
+
+            bits
+        });
+
+        let bits = (0..num_bits).map(|i| {
+            Ok(Boolean::from(AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", i)),
+                values.as_ref().map(|v| v[i])
+            )?))
+        }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        enforce_reduced(cs.namespace(|| "is reduced"), &bits)?;
+
+        Ok(ScalarNum {
+            bits: bits
+        })
+    }
+
+    /// `h mod r` for the little-endian bits of a hash output of at most
+    /// 256 bits.
+    pub fn from_hash<CS>(cs: CS, h: &[Boolean]) -> Result<Self, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        Ok(ScalarNum {
+            bits: reduce_mod_subgroup_order(cs, h)?
+        })
+    }
+
+    /// Wraps bits that the caller knows to be a valid scalar, e.g. the
+    /// output of a gadget that reduces them itself. Nothing is enforced.
+    pub fn from_bits_unchecked(bits: Vec<Boolean>) -> Self {
+        ScalarNum {
+            bits: bits
+        }
+    }
+
+    pub fn bits(&self) -> &[Boolean] {
+        &self.bits
+    }
+
+    pub fn get_value(&self) -> Option<E::Fs> {
+        let mut acc = E::Fs::zero();
+        for bit in self.bits.iter().rev() {
+            acc.double();
+            if bit.get_value()? {
+                acc.add_assign(&E::Fs::one());
+            }
+        }
+
+        Some(acc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use ::circuit::ecc::EdwardsPoint;
+    use alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use jubjub::{edwards, Unknown};
+    use super::*;
+
+    #[test]
+    fn test_scalar_num() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+
+        let s: Fs = rng.gen();
+        let p = edwards::Point::<Bn256, Unknown>::rand(rng, params).mul_by_cofactor(params);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let scalar = ScalarNum::witness(cs.namespace(|| "s"), Some(s)).unwrap();
+        assert_eq!(scalar.get_value(), Some(s));
+
+        let point = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p.clone()), params).unwrap();
+        let result = point.mul_scalar(cs.namespace(|| "s * p"), &scalar, params).unwrap();
+
+        assert!(cs.is_satisfied());
+        let expected = p.mul(s, params).into_xy();
+        assert_eq!(result.get_x().get_value().unwrap(), expected.0);
+        assert_eq!(result.get_y().get_value().unwrap(), expected.1);
+
+        // a base field element is reduced explicitly
+        let h: Fr = rng.gen();
+        let num = AllocatedNum::alloc(cs.namespace(|| "h"), || Ok(h)).unwrap();
+        let reduced = BaseNum::new(num).reduce_to_scalar(cs.namespace(|| "h mod r")).unwrap();
+
+        let mut h_repr = <Fs as PrimeField>::Repr::default();
+        h_repr.as_mut().copy_from_slice(h.into_repr().as_ref());
+        let mut expected = Fs::zero();
+        for b in BitIterator::new(h_repr) {
+            expected.double();
+            if b {
+                expected.add_assign(&Fs::one());
+            }
+        }
+
+        assert!(cs.is_satisfied());
+        assert_eq!(reduced.get_value(), Some(expected));
+    }
+}
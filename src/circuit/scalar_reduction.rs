@@ -0,0 +1,304 @@
+//! Reduction of a hash output modulo the order `r` of the prime order
+//! subgroup of the embedded curve, i.e. the modulus of `E::Fs`.
+//!
+//! A hash output `h` of up to 256 bits doesn't fit into the circuit
+//! field, so `h = q * r + rem` is enforced on 128 bit limbs:
+//!
+//! `q * r_0 + rem_0 = h_0 + carry * 2^128`
+//! `q * r_1 + rem_1 + carry = h_1`
+//!
+//! `q` has `len(h) - bits(r) + 1` bits and `carry` one bit more, so
+//! both sides stay below `2^(129 + bits(q))`, far from the modulus of
+//! the circuit field, and the equalities hold over the integers.
+//! `rem < r` is enforced separately.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr,
+    BitIterator
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+
+use super::comparison::alloc_packed_bits;
+
+use jubjub::JubjubEngine;
+
+const LIMB_BITS: usize = 128;
+
+fn fr_from_limbs<F: PrimeField>(limbs: &[u64]) -> F {
+    let mut repr = F::Repr::default();
+    for (r, l) in repr.as_mut().iter_mut().zip(limbs.iter()) {
+        *r = *l;
+    }
+
+    F::from_repr(repr).expect("is in the field")
+}
+
+fn bits_of<R: PrimeFieldRepr>(repr: R, num_bits: usize) -> Vec<bool> {
+    let mut bits: Vec<bool> = BitIterator::new(repr).collect();
+    bits.reverse();
+    bits.truncate(num_bits);
+
+    bits
+}
+
+fn alloc_bits<E, CS>(mut cs: CS, values: Option<Vec<bool>>, num_bits: usize) -> Result<Vec<Boolean>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    (0..num_bits).map(|i| {
+        Ok(Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            values.as_ref().map(|v| v[i])
+        )?))
+    }).collect()
+}
+
+/// Packs `bits`, the `i`-th with the coefficient `coeff * 2^i`.
+fn pack<E, CS>(bits: &[Boolean], coeff: E::Fr) -> LinearCombination<E>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let mut coeff = coeff;
+    let mut lc = LinearCombination::zero();
+    for bit in bits.iter() {
+        lc = lc + &bit.lc(CS::one(), coeff);
+        coeff.double();
+    }
+
+    lc
+}
+
+/// Returns the little-endian bits of `h mod r` for the little-endian
+/// bits `h` of a hash output of at most 256 bits, `bits(r)` of them.
+pub fn reduce_mod_subgroup_order<E, CS>(
+    mut cs: CS,
+    h: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    assert!(h.len() <= 2 * LIMB_BITS);
+
+    let r = E::Fs::char();
+    let r_bits = r.num_bits() as usize;
+    assert!(r_bits > LIMB_BITS && r_bits < E::Fr::CAPACITY as usize);
+
+    let q_bits = if h.len() >= r_bits { h.len() - r_bits + 1 } else { 0 };
+
+    // long division by r
+    let h_value: Option<Vec<bool>> = h.iter().map(|b| b.get_value()).collect();
+    let division = h_value.map(|h_value| {
+        let mut rem = <E::Fs as PrimeField>::Repr::default();
+        for (i, &b) in h_value.iter().enumerate() {
+            if b {
+                rem.as_mut()[i / 64] |= 1 << (i % 64);
+            }
+        }
+
+        let mut q = vec![false; q_bits];
+        for i in (0..q_bits).rev() {
+            let mut shifted = r;
+            shifted.shl(i as u32);
+            if rem >= shifted {
+                rem.sub_noborrow(&shifted);
+                q[i] = true;
+            }
+        }
+
+        (q, bits_of(rem, r_bits))
+    });
+
+    let q = alloc_bits(cs.namespace(|| "quotient"), division.as_ref().map(|d| d.0.clone()), q_bits)?;
+    let rem = alloc_bits(cs.namespace(|| "remainder"), division.as_ref().map(|d| d.1.clone()), r_bits)?;
+
+    let r_0 = fr_from_limbs::<E::Fr>(&r.as_ref()[0..2]);
+    let r_1 = fr_from_limbs::<E::Fr>(&r.as_ref()[2..]);
+    let limb_shift = fr_from_limbs::<E::Fr>(&[0, 0, 1]);
+
+    let low = |bits: &[Boolean]| bits[..LIMB_BITS.min(bits.len())].to_vec();
+    let high = |bits: &[Boolean]| bits[LIMB_BITS.min(bits.len())..].to_vec();
+
+    let one = E::Fr::one();
+
+    // q * r_0 + rem_0 - h_0
+    let low_lc = pack::<E, CS>(&q, r_0) + &pack::<E, CS>(&low(&rem), one) - &pack::<E, CS>(&low(h), one);
+
+    let eval = |bits: &[Boolean], coeff: &E::Fr| -> Option<E::Fr> {
+        let mut acc = E::Fr::zero();
+        let mut c = *coeff;
+        for bit in bits.iter() {
+            if bit.get_value()? {
+                acc.add_assign(&c);
+            }
+            c.double();
+        }
+
+        Some(acc)
+    };
+
+    let carry_value = match (eval(&q, &r_0), eval(&low(&rem), &one), eval(&low(h), &one)) {
+        (Some(q_r_0), Some(rem_0), Some(h_0)) => {
+            let mut tmp = q_r_0;
+            tmp.add_assign(&rem_0);
+            tmp.sub_assign(&h_0);
+            tmp.mul_assign(&limb_shift.inverse().unwrap());
+
+            Some(tmp)
+        },
+        _ => None
+    };
+
+    let carry = alloc_bits(
+        cs.namespace(|| "carry"),
+        carry_value.map(|c| bits_of(c.into_repr(), q_bits + 1)),
+        q_bits + 1
+    )?;
+
+    cs.enforce(
+        || "low limb",
+        |_| low_lc - &pack::<E, CS>(&carry, limb_shift),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+
+    cs.enforce(
+        || "high limb",
+        |_| pack::<E, CS>(&q, r_1) + &pack::<E, CS>(&high(&rem), one) + &pack::<E, CS>(&carry, one) - &pack::<E, CS>(&high(h), one),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+
+    enforce_reduced(cs.namespace(|| "remainder is reduced"), &rem)?;
+
+    Ok(rem)
+}
+
+/// Enforces that the little-endian `bits`, `bits(r)` of them, encode a
+/// number less than `r`.
+pub fn enforce_reduced<E, CS>(
+    cs: CS,
+    bits: &[Boolean]
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let r = E::Fs::char();
+    let r_bits = r.num_bits() as usize;
+    assert_eq!(bits.len(), r_bits);
+
+    // r - 1 - value has bits(r) bits iff value < r
+    let mut r_minus_one = fr_from_limbs::<E::Fr>(r.as_ref());
+    r_minus_one.sub_assign(&E::Fr::one());
+
+    let mut value = Some(E::Fr::zero());
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter() {
+        value = match (value, bit.get_value()) {
+            (Some(mut v), Some(b)) => {
+                if b {
+                    v.add_assign(&coeff);
+                }
+
+                Some(v)
+            },
+            _ => None
+        };
+        coeff.double();
+    }
+
+    alloc_packed_bits(
+        cs,
+        value.map(|value| {
+            let mut tmp = r_minus_one;
+            tmp.sub_assign(&value);
+
+            tmp
+        }),
+        LinearCombination::zero() + (r_minus_one, CS::one()) - &pack::<E, CS>(bits, E::Fr::one()),
+        r_bits
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::bls12_381::Bls12;
+    use ::circuit::test::*;
+    use super::*;
+
+    fn reduce_natively<E: JubjubEngine>(h: &[bool]) -> Vec<bool> {
+        let mut acc = E::Fs::zero();
+        for &b in h.iter().rev() {
+            acc.double();
+            if b {
+                acc.add_assign(&E::Fs::one());
+            }
+        }
+
+        bits_of(acc.into_repr(), E::Fs::char().num_bits() as usize)
+    }
+
+    fn check<E: JubjubEngine>(h: &[bool]) {
+        let mut cs = TestConstraintSystem::<E>::new();
+        let h_bits: Vec<Boolean> = h.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("h {}", i)), Some(b)).unwrap())
+        }).collect();
+
+        let rem = reduce_mod_subgroup_order(cs.namespace(|| "reduce"), &h_bits).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(rem.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), reduce_natively::<E>(h));
+    }
+
+    #[test]
+    fn test_reduce_mod_subgroup_order() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let h: Vec<bool> = (0..256).map(|_| rng.gen()).collect();
+            check::<Bn256>(&h);
+            check::<Bls12>(&h);
+        }
+
+        check::<Bn256>(&vec![true; 256]);
+        check::<Bn256>(&vec![false; 256]);
+        check::<Bn256>(&(0..200).map(|_| rng.gen()).collect::<Vec<bool>>());
+    }
+
+    #[test]
+    fn test_unreduced_remainder() {
+        // h = r: the remainder r with a zero quotient satisfies the
+        // limb equations but is not reduced
+        let r = <<Bn256 as JubjubEngine>::Fs as PrimeField>::char();
+        let h = bits_of(r, 256);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let h_bits: Vec<Boolean> = h.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("h {}", i)), Some(b)).unwrap())
+        }).collect();
+
+        reduce_mod_subgroup_order(cs.namespace(|| "reduce"), &h_bits).unwrap();
+        assert!(cs.is_satisfied());
+
+        cs.set("reduce/quotient/bit 0/boolean", Fr::zero());
+        for (i, &b) in h.iter().take(r.num_bits() as usize).enumerate() {
+            cs.set(&format!("reduce/remainder/bit {}/boolean", i), if b { Fr::one() } else { Fr::zero() });
+        }
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,328 @@
+//! One-of-N checks for allow and deny lists.
+//!
+//! For small sets `x` is a member iff `prod (x - s_i) = 0`, which is
+//! enforced with a chain of `N - 1` multiplications. The same product
+//! over all pairs checks that the elements of a list are distinct. Large sets are
+//! committed to as a Poseidon2 Merkle tree (see
+//! `poseidon2::merkle_root`) and membership is an inclusion proof.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::indexed_merkle_tree::compute_root;
+
+use poseidon2::Poseidon2Params;
+
+fn difference<E: Engine>(x: &Num<E>, s: &Num<E>) -> Num<E> {
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    let mut tmp = s.clone();
+    tmp.scale(minus_one);
+    tmp.add_assign(x);
+
+    tmp
+}
+
+/// Allocates the product of all but the last of `factors`, returning
+/// it as a number together with the last factor.
+fn chain_product<E, CS>(
+    mut cs: CS,
+    mut factors: Vec<Num<E>>
+) -> Result<(Num<E>, Num<E>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let last = factors.pop().expect("set must not be empty");
+
+    let mut product = Num::<E>::zero();
+    product.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), E::Fr::one());
+
+    for (i, factor) in factors.into_iter().enumerate() {
+        if i == 0 {
+            product = factor;
+            continue;
+        }
+
+        let next = AllocatedNum::alloc(cs.namespace(|| format!("product {}", i)), || {
+            let mut tmp = *product.get_value().get()?;
+            tmp.mul_assign(factor.get_value().get()?);
+
+            Ok(tmp)
+        })?;
+
+        cs.enforce(
+            || format!("product constraint {}", i),
+            |_| product.lc(E::Fr::one()),
+            |_| factor.lc(E::Fr::one()),
+            |lc| lc + next.get_variable()
+        );
+
+        product = Num::from(next);
+    }
+
+    Ok((product, last))
+}
+
+/// Allocates `prod (x - s_i)` for all but the last element of `set`,
+/// returning it as a number together with the last difference.
+fn partial_product<E, CS>(
+    cs: CS,
+    x: &Num<E>,
+    set: &[Num<E>]
+) -> Result<(Num<E>, Num<E>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    chain_product(cs, set.iter().map(|s| difference(x, s)).collect())
+}
+
+/// Enforces `product * last != 0` with two constraints.
+fn enforce_nonzero_product<E, CS>(
+    mut cs: CS,
+    product: &Num<E>,
+    last: &Num<E>
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let inv = AllocatedNum::alloc(cs.namespace(|| "inverse of the product"), || {
+        let mut tmp = *product.get_value().get()?;
+        tmp.mul_assign(last.get_value().get()?);
+
+        match tmp.inverse() {
+            Some(inv) => Ok(inv),
+            None => Err(SynthesisError::Unsatisfiable)
+        }
+    })?;
+
+    // (prod * inv) * last = 1
+    let product_inv = AllocatedNum::alloc(cs.namespace(|| "product times inverse"), || {
+        let mut tmp = *product.get_value().get()?;
+        tmp.mul_assign(inv.get_value().get()?);
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "product times inverse constraint",
+        |_| product.lc(E::Fr::one()),
+        |lc| lc + inv.get_variable(),
+        |lc| lc + product_inv.get_variable()
+    );
+
+    cs.enforce(
+        || "product is non-zero",
+        |lc| lc + product_inv.get_variable(),
+        |_| last.lc(E::Fr::one()),
+        |lc| lc + CS::one()
+    );
+
+    Ok(())
+}
+
+/// Enforces that `x` equals one of the elements of `set`, which can
+/// be constants or variables. Costs `N - 1` constraints, or one for a
+/// single element.
+pub fn enforce_membership<E, CS>(
+    mut cs: CS,
+    x: &Num<E>,
+    set: &[Num<E>]
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let (product, last) = partial_product(cs.namespace(|| "product"), x, set)?;
+
+    cs.enforce(
+        || "product is zero",
+        |_| product.lc(E::Fr::one()),
+        |_| last.lc(E::Fr::one()),
+        |lc| lc
+    );
+
+    Ok(())
+}
+
+/// Enforces that `x` differs from all the elements of `set`. Costs
+/// `N` constraints, or two for a single element.
+pub fn enforce_non_membership<E, CS>(
+    mut cs: CS,
+    x: &Num<E>,
+    set: &[Num<E>]
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let (product, last) = partial_product(cs.namespace(|| "product"), x, set)?;
+
+    enforce_nonzero_product(cs.namespace(|| "non-zero"), &product, &last)
+}
+
+/// Enforces that the elements of `values` are pairwise distinct, e.g.
+/// the nullifiers spent in one transaction, by checking that the
+/// product of all the differences is non-zero. Costs `N (N - 1) / 2`
+/// constraints, or two for two elements.
+pub fn enforce_distinct<E, CS>(
+    mut cs: CS,
+    values: &[Num<E>]
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut differences = vec![];
+    for (i, a) in values.iter().enumerate() {
+        for b in values[(i + 1)..].iter() {
+            differences.push(difference(a, b));
+        }
+    }
+
+    if differences.is_empty() {
+        return Ok(());
+    }
+
+    let (product, last) = chain_product(cs.namespace(|| "product"), differences)?;
+
+    enforce_nonzero_product(cs.namespace(|| "non-zero"), &product, &last)
+}
+
+/// Enforces that `x` is the leaf at `index_bits` of the committed set
+/// with the given `root`, for sets too large for the product check.
+pub fn enforce_membership_in_tree<E, CS>(
+    mut cs: CS,
+    params: &Poseidon2Params<E::Fr>,
+    root: &AllocatedNum<E>,
+    x: &AllocatedNum<E>,
+    index_bits: &[Boolean],
+    path: &[AllocatedNum<E>]
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let computed_root = compute_root(cs.namespace(|| "inclusion"), params, x, index_bits, path)?;
+
+    cs.enforce(
+        || "enforce root",
+        |lc| lc + computed_root.get_variable() - root.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::test::*;
+    use ::poseidon2::{generate_params, merkle_root, merkle_path};
+    use super::*;
+
+    fn constant(x: u64) -> Num<Bn256> {
+        Num::zero().add_bool_with_coeff(TestConstraintSystem::<Bn256>::one(), &Boolean::constant(true), fr(x))
+    }
+
+    fn alloc_set<CS: ConstraintSystem<Bn256>>(mut cs: CS, x: u64) -> (Num<Bn256>, Vec<Num<Bn256>>) {
+        let x = Num::from(AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(fr(x))).unwrap());
+        let variable = Num::from(AllocatedNum::alloc(cs.namespace(|| "variable"), || Ok(fr(7))).unwrap());
+
+        (x, vec![constant(1), constant(3), variable, constant(10)])
+    }
+
+    #[test]
+    fn test_membership() {
+        for &(x, member) in [(7u64, true), (3, true), (8, false)].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let (x_num, set) = alloc_set(cs.namespace(|| "set"), x);
+
+            enforce_membership(cs.namespace(|| "membership"), &x_num, &set).unwrap();
+
+            assert_eq!(cs.is_satisfied(), member);
+            assert_eq!(cs.num_constraints(), 3);
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let (x_num, set) = alloc_set(cs.namespace(|| "set"), x);
+
+            let result = enforce_non_membership(cs.namespace(|| "non-membership"), &x_num, &set);
+            if member {
+                assert!(result.is_err());
+            } else {
+                assert!(cs.is_satisfied());
+                assert_eq!(cs.num_constraints(), 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_distinct() {
+        for &(values, distinct) in [(&[1u64, 3, 7, 10][..], true), (&[1, 3, 7, 3][..], false), (&[5, 6][..], true), (&[5, 5][..], false)].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let nums: Vec<Num<Bn256>> = values.iter().enumerate().map(|(i, &v)| {
+                Num::from(AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(fr(v))).unwrap())
+            }).collect();
+
+            let result = enforce_distinct(cs.namespace(|| "distinct"), &nums);
+            if distinct {
+                assert!(cs.is_satisfied());
+            } else {
+                assert!(result.is_err());
+            }
+        }
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let nums: Vec<Num<Bn256>> = (0..4).map(|i| {
+            Num::from(AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(fr(i))).unwrap())
+        }).collect();
+        enforce_distinct(cs.namespace(|| "distinct"), &nums).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 4 * 3 / 2);
+
+        cs.set("value 3/num", fr(1));
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_membership_in_tree() {
+        let params = generate_params::<Fr>(3);
+        let leaves: Vec<Fr> = (0..8).map(|i| fr(100 + i)).collect();
+        let root = merkle_root(&params, &leaves);
+
+        for &(index, value, member) in [(5usize, 105u64, true), (5, 106, false)].iter() {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let root = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(root)).unwrap();
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(fr(value))).unwrap();
+            let index_bits: Vec<_> = (0..3).map(|i| {
+                Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("index bit {}", i)), Some((index >> i) & 1 == 1)).unwrap())
+            }).collect();
+            let path: Vec<_> = merkle_path(&params, &leaves, index).into_iter().enumerate().map(|(i, el)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("path {}", i)), || Ok(el)).unwrap()
+            }).collect();
+
+            enforce_membership_in_tree(cs.namespace(|| "membership"), &params, &root, &x, &index_bits, &path).unwrap();
+
+            assert_eq!(cs.is_satisfied(), member);
+        }
+    }
+}
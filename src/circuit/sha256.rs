@@ -3,6 +3,7 @@ use super::multieq::MultiEq;
 use super::boolean::Boolean;
 use bellman::{ConstraintSystem, SynthesisError};
 use bellman::pairing::Engine;
+use sha2::{Sha256, Digest};
 
 const ROUND_CONSTANTS: [u32; 64] = [
     0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
@@ -39,15 +40,34 @@ pub fn sha256_block_no_padding<E, CS>(
 }
 
 pub fn sha256<E, CS>(
+    cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    sha256_from_midstate(cs, &get_sha256_iv(), 0, input)
+}
+
+/// Continues hashing from the `midstate` that was reached after
+/// compressing a prefix of `prefix_len` bits, that must be a multiple
+/// of the block size. The padding accounts for the whole message, so
+/// the result is the hash of the prefix followed by the `input`.
+/// The midstate can be e.g. allocated with `UInt32::alloc` or obtained
+/// from `sha256_compression_function`.
+pub fn sha256_from_midstate<E, CS>(
     mut cs: CS,
+    midstate: &[UInt32],
+    prefix_len: u64,
     input: &[Boolean]
 ) -> Result<Vec<Boolean>, SynthesisError>
     where E: Engine, CS: ConstraintSystem<E>
 {
     assert!(input.len() % 8 == 0);
+    assert!(prefix_len % 512 == 0);
+    assert_eq!(midstate.len(), 8);
 
     let mut padded = input.to_vec();
-    let plen = padded.len() as u64;
+    let plen = prefix_len + padded.len() as u64;
     // append a single '1' bit
     padded.push(Boolean::constant(true));
     // append K '0' bits, where K is the minimum number >= 0 such that L + 1 + K + 64 is a multiple of 512
@@ -60,7 +80,7 @@ pub fn sha256<E, CS>(
     }
     assert!(padded.len() % 512 == 0);
 
-    let mut cur = get_sha256_iv();
+    let mut cur = midstate.to_vec();
     for (i, block) in padded.chunks(512).enumerate() {
         cur = sha256_compression_function(
             cs.namespace(|| format!("block {}", i)),
@@ -74,10 +94,46 @@ pub fn sha256<E, CS>(
     .collect())
 }
 
+/// `SHA256(SHA256(input))`, as used for Bitcoin block headers and
+/// transaction ids. The inner digest is hashed as it is, its bits are
+/// not decomposed again.
+pub fn sha256d<E, CS>(
+    mut cs: CS,
+    input: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let inner = sha256(cs.namespace(|| "inner hash"), input)?;
+
+    sha256(cs.namespace(|| "outer hash"), &inner)
+}
+
+/// BIP-340 tagged hash `SHA256(SHA256(tag) || SHA256(tag) || msg)`.
+/// The prefix is a single constant block, so hashing starts from its
+/// midstate at no cost.
+pub fn tagged_hash<E, CS>(
+    mut cs: CS,
+    tag: &[u8],
+    msg: &[Boolean]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: Engine, CS: ConstraintSystem<E>
+{
+    let tag_hash = Sha256::digest(tag);
+    let prefix: Vec<Boolean> = tag_hash.iter().chain(tag_hash.iter()).flat_map(|&byte| {
+        (0..8).rev().map(move |i| Boolean::constant((byte >> i) & 1u8 == 1u8))
+    }).collect();
+
+    let midstate = sha256_compression_function(cs.namespace(|| "tag prefix"), &prefix, &get_sha256_iv())?;
+
+    sha256_from_midstate(cs.namespace(|| "message"), &midstate, 512, msg)
+}
+
 pub fn get_sha256_iv() -> Vec<UInt32> {
     IV.iter().map(|&v| UInt32::constant(v)).collect()
 }
 
+/// The SHA-256 compression function: updates the chaining value
+/// `current_hash_value` with a single 512 bit block.
 pub fn sha256_compression_function<E, CS>(
     cs: CS,
     input: &[Boolean],
@@ -413,4 +469,112 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_midstate_continuation() {
+        use sha2::{Sha256, Digest};
+
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &suffix_len in [0, 1, 55, 56, 64, 100].iter() {
+            let prefix: Vec<u8> = (0..128).map(|_| rng.gen()).collect();
+            let suffix: Vec<u8> = (0..suffix_len).map(|_| rng.gen()).collect();
+
+            let mut h = Sha256::new();
+            h.input(&prefix);
+            h.input(&suffix);
+            let expected = h.result();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let to_bits = |bytes: &[u8]| -> Vec<Boolean> {
+                bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| Boolean::constant((byte >> i) & 1u8 == 1u8))).collect()
+            };
+
+            // the prefix is hashed "off-circuit" and only the midstate is witnessed
+            let mut midstate = get_sha256_iv();
+            for block in to_bits(&prefix).chunks(512) {
+                midstate = sha256_compression_function(&mut cs, block, &midstate).unwrap();
+            }
+            let midstate: Vec<UInt32> = midstate.iter().enumerate().map(|(i, word)| {
+                UInt32::alloc(cs.namespace(|| format!("midstate word {}", i)), word.get_value()).unwrap()
+            }).collect();
+
+            let suffix_bits: Vec<Boolean> = suffix.iter().enumerate().flat_map(|(byte_i, &byte)| {
+                (0..8).rev().map(move |bit_i| (byte_i, bit_i, (byte >> bit_i) & 1u8 == 1u8))
+            }).map(|(byte_i, bit_i, bit)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("suffix bit {} {}", byte_i, bit_i)), Some(bit)).unwrap().into()
+            }).collect();
+
+            let r = sha256_from_midstate(cs.namespace(|| "continuation"), &midstate, 1024, &suffix_bits).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let expected_bits: Vec<bool> = expected.as_slice().iter()
+                .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8))
+                .collect();
+            let r: Vec<bool> = r.iter().map(|b| b.get_value().unwrap()).collect();
+
+            assert_eq!(r, expected_bits);
+        }
+    }
+
+    fn alloc_bytes<CS: ConstraintSystem<Bls12>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        bytes.iter().enumerate().flat_map(|(byte_i, &byte)| {
+            (0..8).rev().map(move |bit_i| (byte_i, bit_i, (byte >> bit_i) & 1u8 == 1u8))
+        }).map(|(byte_i, bit_i, bit)| {
+            AllocatedBit::alloc(cs.namespace(|| format!("input bit {} {}", byte_i, bit_i)), Some(bit)).unwrap().into()
+        }).collect()
+    }
+
+    fn bits_of(bytes: &[u8]) -> Vec<bool> {
+        bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8)).collect()
+    }
+
+    #[test]
+    fn test_sha256d() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &len in [0, 32, 80, 100].iter() {
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let expected = Sha256::digest(&Sha256::digest(&data));
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input = alloc_bytes(cs.namespace(|| "input"), &data);
+            let r = sha256d(cs.namespace(|| "sha256d"), &input).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(r.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), bits_of(&expected));
+        }
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let tag = b"BIP0340/challenge";
+
+        for &len in [0, 32, 96].iter() {
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let tag_hash = Sha256::digest(tag);
+            let mut h = Sha256::new();
+            h.input(&tag_hash);
+            h.input(&tag_hash);
+            h.input(&data);
+            let expected = h.result();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let input = alloc_bytes(cs.namespace(|| "input"), &data);
+            let r = tagged_hash(cs.namespace(|| "tagged hash"), tag, &input).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(r.iter().map(|b| b.get_value().unwrap()).collect::<Vec<_>>(), bits_of(&expected));
+
+            // the constant prefix block costs nothing
+            let mut plain = TestConstraintSystem::<Bls12>::new();
+            let input = alloc_bytes(plain.namespace(|| "input"), &data);
+            sha256(plain.namespace(|| "sha256"), &input).unwrap();
+            assert_eq!(cs.num_constraints(), plain.num_constraints());
+        }
+    }
 }
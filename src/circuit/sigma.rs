@@ -0,0 +1,396 @@
+//! Verification of the proofs of `sigma` in the circuit.
+
+use bellman::pairing::ff::{
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::boolean::Boolean;
+
+use super::ecc::EdwardsPoint;
+
+use super::rescue::rescue_hash_to_bits;
+
+use jubjub::JubjubEngine;
+
+use rescue::RescueParams;
+
+use util::fr_from_u64;
+
+use sigma::{
+    DLOG_TAG,
+    DH_TUPLE_TAG,
+    EQUAL_VALUE_TAG
+};
+
+#[derive(Clone)]
+pub struct AllocatedDlogProof<E: JubjubEngine> {
+    pub commitment: EdwardsPoint<E>,
+    pub response: AllocatedNum<E>
+}
+
+#[derive(Clone)]
+pub struct AllocatedDhTupleProof<E: JubjubEngine> {
+    pub commitment_g: EdwardsPoint<E>,
+    pub commitment_h: EdwardsPoint<E>,
+    pub response: AllocatedNum<E>
+}
+
+#[derive(Clone)]
+pub struct AllocatedEqualValueProof<E: JubjubEngine> {
+    pub commitment_1: EdwardsPoint<E>,
+    pub commitment_2: EdwardsPoint<E>,
+    pub response_value: AllocatedNum<E>,
+    pub response_1: AllocatedNum<E>,
+    pub response_2: AllocatedNum<E>
+}
+
+/// Bits of `sigma::challenge`.
+fn challenge<E, CS>(
+    mut cs: CS,
+    rescue_params: &RescueParams<E::Fr>,
+    tag: u64,
+    points: &[&EdwardsPoint<E>]
+) -> Result<Vec<Boolean>, SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let mut input = vec![Num::constant(CS::one(), fr_from_u64(tag))];
+    for p in points.iter() {
+        input.push(Num::from(p.get_x().clone()));
+        input.push(Num::from(p.get_y().clone()));
+    }
+
+    rescue_hash_to_bits(cs.namespace(|| "hash"), rescue_params, &input, E::Fs::CAPACITY as usize)
+}
+
+/// Enforces s . base == commitment + c . point.
+fn enforce_response<E, CS>(
+    mut cs: CS,
+    params: &E::Params,
+    base: &EdwardsPoint<E>,
+    point: &EdwardsPoint<E>,
+    commitment: &EdwardsPoint<E>,
+    c: &[Boolean],
+    s: &[Boolean]
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let lhs = base.mul(cs.namespace(|| "s*base"), s, params)?;
+    let rhs = point.mul(cs.namespace(|| "c*point"), c, params)?;
+    let rhs = rhs.add(cs.namespace(|| "add commitment"), commitment, params)?;
+
+    enforce_equal_points(cs, &lhs, &rhs)
+}
+
+/// Enforces s . g + s_r . h == commitment + c . point.
+fn enforce_commitment_response<E, CS>(
+    mut cs: CS,
+    params: &E::Params,
+    g: &EdwardsPoint<E>,
+    h: &EdwardsPoint<E>,
+    point: &EdwardsPoint<E>,
+    commitment: &EdwardsPoint<E>,
+    c: &[Boolean],
+    s: &[Boolean],
+    s_r: &[Boolean]
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    let lhs = g.mul(cs.namespace(|| "s*g"), s, params)?;
+    let s_r_h = h.mul(cs.namespace(|| "s_r*h"), s_r, params)?;
+    let lhs = lhs.add(cs.namespace(|| "add s_r*h"), &s_r_h, params)?;
+    let rhs = point.mul(cs.namespace(|| "c*point"), c, params)?;
+    let rhs = rhs.add(cs.namespace(|| "add commitment"), commitment, params)?;
+
+    enforce_equal_points(cs, &lhs, &rhs)
+}
+
+fn enforce_equal_points<E, CS>(
+    mut cs: CS,
+    lhs: &EdwardsPoint<E>,
+    rhs: &EdwardsPoint<E>
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    cs.enforce(
+        || "check x coordinate",
+        |lc| lc + lhs.get_x().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + rhs.get_x().get_variable()
+    );
+
+    cs.enforce(
+        || "check y coordinate",
+        |lc| lc + lhs.get_y().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + rhs.get_y().get_variable()
+    );
+
+    Ok(())
+}
+
+/// Enforces that `proof` shows knowledge of the discrete log of `p`
+/// to the base `g`. As with the native check, both are expected to be
+/// in the prime order subgroup.
+pub fn verify_dlog<E, CS>(
+    mut cs: CS,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    g: &EdwardsPoint<E>,
+    p: &EdwardsPoint<E>,
+    proof: &AllocatedDlogProof<E>
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    proof.commitment.assert_not_small_order(cs.namespace(|| "commitment order"), params)?;
+
+    let c = challenge(cs.namespace(|| "challenge"), rescue_params, DLOG_TAG, &[g, p, &proof.commitment])?;
+    let s = proof.response.into_bits_le_strict(cs.namespace(|| "response bits"))?;
+
+    enforce_response(cs.namespace(|| "check"), params, g, p, &proof.commitment, &c, &s)
+}
+
+/// Enforces that `p` and `q` have the same discrete log to the bases
+/// `g` and `h`.
+pub fn verify_dh_tuple<E, CS>(
+    mut cs: CS,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    g: &EdwardsPoint<E>,
+    h: &EdwardsPoint<E>,
+    p: &EdwardsPoint<E>,
+    q: &EdwardsPoint<E>,
+    proof: &AllocatedDhTupleProof<E>
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    proof.commitment_g.assert_not_small_order(cs.namespace(|| "commitment g order"), params)?;
+    proof.commitment_h.assert_not_small_order(cs.namespace(|| "commitment h order"), params)?;
+
+    let c = challenge(
+        cs.namespace(|| "challenge"),
+        rescue_params,
+        DH_TUPLE_TAG,
+        &[g, h, p, q, &proof.commitment_g, &proof.commitment_h]
+    )?;
+    let s = proof.response.into_bits_le_strict(cs.namespace(|| "response bits"))?;
+
+    enforce_response(cs.namespace(|| "check g"), params, g, p, &proof.commitment_g, &c, &s)?;
+    enforce_response(cs.namespace(|| "check h"), params, h, q, &proof.commitment_h, &c, &s)
+}
+
+/// Enforces that `c_1` and `c_2` commit to the same value with the
+/// bases `g_1`, `h_1` and `g_2`, `h_2`, e.g. an in-circuit value
+/// commitment and a commitment published elsewhere. As with the
+/// native check, the commitments are expected to be in the prime
+/// order subgroup.
+pub fn verify_equal_value<E, CS>(
+    mut cs: CS,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    g_1: &EdwardsPoint<E>,
+    h_1: &EdwardsPoint<E>,
+    g_2: &EdwardsPoint<E>,
+    h_2: &EdwardsPoint<E>,
+    c_1: &EdwardsPoint<E>,
+    c_2: &EdwardsPoint<E>,
+    proof: &AllocatedEqualValueProof<E>
+) -> Result<(), SynthesisError>
+    where E: JubjubEngine,
+          CS: ConstraintSystem<E>
+{
+    proof.commitment_1.assert_not_small_order(cs.namespace(|| "commitment 1 order"), params)?;
+    proof.commitment_2.assert_not_small_order(cs.namespace(|| "commitment 2 order"), params)?;
+
+    let c = challenge(
+        cs.namespace(|| "challenge"),
+        rescue_params,
+        EQUAL_VALUE_TAG,
+        &[g_1, h_1, g_2, h_2, c_1, c_2, &proof.commitment_1, &proof.commitment_2]
+    )?;
+    let s = proof.response_value.into_bits_le_strict(cs.namespace(|| "value response bits"))?;
+    let s_1 = proof.response_1.into_bits_le_strict(cs.namespace(|| "response 1 bits"))?;
+    let s_2 = proof.response_2.into_bits_le_strict(cs.namespace(|| "response 2 bits"))?;
+
+    enforce_commitment_response(cs.namespace(|| "check 1"), params, g_1, h_1, c_1, &proof.commitment_1, &c, &s, &s_1)?;
+    enforce_commitment_response(cs.namespace(|| "check 2"), params, g_2, h_2, c_2, &proof.commitment_2, &c, &s, &s_2)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::PrimeFieldRepr;
+    use ::circuit::test::*;
+    use alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use jubjub::{FixedGenerators, JubjubParams, Unknown, edwards};
+    use rescue::generate_params;
+    use sigma;
+    use super::*;
+
+    fn fs_to_fr(s: Fs) -> Fr {
+        let mut bytes = vec![];
+        s.into_repr().write_le(&mut bytes).unwrap();
+
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.read_le(&bytes[..]).unwrap();
+
+        Fr::from_repr(repr).unwrap()
+    }
+
+    #[test]
+    fn test_verify_dh_tuple() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let g: edwards::Point<Bn256, Unknown> = params.generator(FixedGenerators::SpendingKeyGenerator).clone().into();
+        let h: edwards::Point<Bn256, Unknown> = params.generator(FixedGenerators::NullifierPosition).clone().into();
+
+        let x: Fs = rng.gen();
+        let p = g.mul(x, params);
+        let q = h.mul(x, params);
+
+        let proof = sigma::prove_dh_tuple(x, &g, &h, params, &rescue_params, rng);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let points: Vec<EdwardsPoint<Bn256>> = [&g, &h, &p, &q].iter().enumerate().map(|(i, point)| {
+            EdwardsPoint::witness(cs.namespace(|| format!("point {}", i)), Some((*point).clone()), params).unwrap()
+        }).collect();
+
+        let allocated = AllocatedDhTupleProof {
+            commitment_g: EdwardsPoint::witness(cs.namespace(|| "commitment g"), Some(proof.commitment_g.clone()), params).unwrap(),
+            commitment_h: EdwardsPoint::witness(cs.namespace(|| "commitment h"), Some(proof.commitment_h.clone()), params).unwrap(),
+            response: AllocatedNum::alloc(cs.namespace(|| "response"), || Ok(fs_to_fr(proof.response))).unwrap()
+        };
+
+        verify_dh_tuple(
+            cs.namespace(|| "verify"),
+            params,
+            &rescue_params,
+            &points[0],
+            &points[1],
+            &points[2],
+            &points[3],
+            &allocated
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("response/num", fs_to_fr(rng.gen()));
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_verify_dlog() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let g: edwards::Point<Bn256, Unknown> = params.generator(FixedGenerators::SpendingKeyGenerator).clone().into();
+
+        let x: Fs = rng.gen();
+        let p = g.mul(x, params);
+
+        let proof = sigma::prove_dlog(x, &g, params, &rescue_params, rng);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let g = EdwardsPoint::witness(cs.namespace(|| "g"), Some(g), params).unwrap();
+        let p = EdwardsPoint::witness(cs.namespace(|| "p"), Some(p), params).unwrap();
+        let allocated = AllocatedDlogProof {
+            commitment: EdwardsPoint::witness(cs.namespace(|| "commitment"), Some(proof.commitment.clone()), params).unwrap(),
+            response: AllocatedNum::alloc(cs.namespace(|| "response"), || Ok(fs_to_fr(proof.response))).unwrap()
+        };
+
+        verify_dlog(cs.namespace(|| "verify"), params, &rescue_params, &g, &p, &allocated).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("response/num", fs_to_fr(rng.gen()));
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_verify_equal_value() {
+        use ::circuit::boolean::field_into_boolean_vec_le;
+        use ::circuit::ecc::fixed_base_multiplication;
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let bases_1 = sigma::PedersenBases::<Bn256> {
+            value: params.generator(FixedGenerators::ValueCommitmentValue).clone(),
+            randomness: params.generator(FixedGenerators::ValueCommitmentRandomness).clone()
+        };
+        let bases_2 = sigma::PedersenBases::<Bn256> {
+            value: params.generator(FixedGenerators::SpendingKeyGenerator).clone(),
+            randomness: params.generator(FixedGenerators::NullifierPosition).clone()
+        };
+
+        let v: Fs = rng.gen();
+        let r_1: Fs = rng.gen();
+        let r_2: Fs = rng.gen();
+        let c_2 = bases_2.commit(v, r_2, params);
+
+        let proof = sigma::prove_equal_value(v, r_1, r_2, &bases_1, &bases_2, params, &rescue_params, rng);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        // the value commitment computed in the circuit
+        let v_bits = field_into_boolean_vec_le(cs.namespace(|| "v"), Some(v)).unwrap();
+        let r_1_bits = field_into_boolean_vec_le(cs.namespace(|| "r_1"), Some(r_1)).unwrap();
+        let c_1 = fixed_base_multiplication(cs.namespace(|| "v*g_1"), FixedGenerators::ValueCommitmentValue, &v_bits, params).unwrap();
+        let r_1_h_1 = fixed_base_multiplication(cs.namespace(|| "r_1*h_1"), FixedGenerators::ValueCommitmentRandomness, &r_1_bits, params).unwrap();
+        let c_1 = c_1.add(cs.namespace(|| "c_1"), &r_1_h_1, params).unwrap();
+
+        let points: Vec<EdwardsPoint<Bn256>> = [&bases_1.value, &bases_1.randomness, &bases_2.value, &bases_2.randomness].iter().enumerate().map(|(i, point)| {
+            EdwardsPoint::witness(cs.namespace(|| format!("base {}", i)), Some((*point).clone()), params).unwrap()
+        }).collect();
+        let c_2 = EdwardsPoint::witness(cs.namespace(|| "c_2"), Some(c_2), params).unwrap();
+
+        let allocated = AllocatedEqualValueProof {
+            commitment_1: EdwardsPoint::witness(cs.namespace(|| "commitment 1"), Some(proof.commitment_1.clone()), params).unwrap(),
+            commitment_2: EdwardsPoint::witness(cs.namespace(|| "commitment 2"), Some(proof.commitment_2.clone()), params).unwrap(),
+            response_value: AllocatedNum::alloc(cs.namespace(|| "response value"), || Ok(fs_to_fr(proof.response_value))).unwrap(),
+            response_1: AllocatedNum::alloc(cs.namespace(|| "response 1"), || Ok(fs_to_fr(proof.response_1))).unwrap(),
+            response_2: AllocatedNum::alloc(cs.namespace(|| "response 2"), || Ok(fs_to_fr(proof.response_2))).unwrap()
+        };
+
+        verify_equal_value(
+            cs.namespace(|| "verify"),
+            params,
+            &rescue_params,
+            &points[0],
+            &points[1],
+            &points[2],
+            &points[3],
+            &c_1,
+            &c_2,
+            &allocated
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        cs.set("response value/num", fs_to_fr(rng.gen()));
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,214 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::{
+    Assignment
+};
+
+use super::num::{
+    AllocatedNum
+};
+
+use super::as_waksman::prove_shuffle;
+
+use super::comparison::enforce_less_or_equal;
+
+use crate::as_waksman::IntegerPermutation;
+
+/// Computes a permutation that sorts the values in ascending
+/// order. Element number `i` is routed to the place `elements[i]`,
+/// the same convention as used by the permutation network.
+/// If some of the values are unknown an identity permutation
+/// is returned, as only the topology matters in this case.
+pub fn sorting_permutation<E: Engine>(
+    values: &[Option<E::Fr>]
+) -> IntegerPermutation
+{
+    let size = values.len();
+    let mut permutation = IntegerPermutation::new(size);
+
+    if values.iter().any(|v| v.is_none()) {
+        return permutation;
+    }
+
+    let mut indexes: Vec<usize> = (0..size).collect();
+    // stable sort, so equal elements keep their relative order
+    indexes.sort_by_key(|&i| values[i].unwrap().into_repr());
+
+    for (place, &i) in indexes.iter().enumerate() {
+        permutation.elements[i] = place;
+    }
+
+    permutation
+}
+
+/// Witnesses a sorted version of the `original` list and proves that
+/// it's both a permutation of the `original` and is sorted in
+/// ascending order. All elements are expected to be already constrained
+/// to be less than `2^num_bits`, as sortedness is proven by comparing
+/// neighbours over `num_bits` bits.
+pub fn prove_sorted<E, CS>(
+    cs: CS,
+    original: &[AllocatedNum<E>],
+    num_bits: usize
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where CS: ConstraintSystem<E>,
+          E: Engine
+{
+    let values: Vec<Option<E::Fr>> = original.iter().map(|e| e.get_value()).collect();
+    let permutation = sorting_permutation::<E>(&values);
+
+    prove_sorted_with_permutation(cs, original, &permutation, num_bits)
+}
+
+/// Same as `prove_sorted`, but uses the caller-provided permutation
+/// to witness the sorted list, so it can be reused if the sorting
+/// was already done outside of the circuit.
+pub fn prove_sorted_with_permutation<E, CS>(
+    mut cs: CS,
+    original: &[AllocatedNum<E>],
+    permutation: &IntegerPermutation,
+    num_bits: usize
+) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    where CS: ConstraintSystem<E>,
+          E: Engine
+{
+    assert_eq!(original.len(), permutation.size());
+
+    if original.len() <= 1 {
+        return Ok(original.to_vec());
+    }
+
+    let mut sorted_values: Vec<Option<E::Fr>> = vec![None; original.len()];
+    for (i, el) in original.iter().enumerate() {
+        sorted_values[permutation.get(i)] = el.get_value();
+    }
+
+    let mut sorted = vec![];
+    for (i, value) in sorted_values.into_iter().enumerate() {
+        let el = AllocatedNum::alloc(
+            cs.namespace(|| format!("sorted element {}", i)),
+            || {
+                Ok(*value.get()?)
+            }
+        )?;
+
+        sorted.push(el);
+    }
+
+    prove_shuffle(
+        cs.namespace(|| "prove permutation"),
+        original,
+        &sorted,
+        permutation
+    )?;
+
+    for (i, pair) in sorted.windows(2).enumerate() {
+        enforce_less_or_equal(
+            cs.namespace(|| format!("enforce ordering of elements {} and {}", i, i + 1)),
+            &pair[0],
+            &pair[1],
+            num_bits
+        )?;
+    }
+
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use ::circuit::test::*;
+    use super::super::num::AllocatedNum;
+    use super::{prove_sorted, prove_sorted_with_permutation};
+    use crate::as_waksman::IntegerPermutation;
+
+    #[test]
+    fn test_sorting_gadget() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        for size in 2..32 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let values: Vec<u32> = (0..size).map(|_| rng.gen::<u32>() % 64).collect();
+
+            let original: Vec<AllocatedNum<Bn256>> = values.iter().enumerate().map(|(i, v)| {
+                AllocatedNum::alloc(
+                    cs.namespace(|| format!("original {}", i)),
+                    || Ok(Fr::from_str(&v.to_string()).unwrap())
+                ).unwrap()
+            }).collect();
+
+            let sorted = prove_sorted(cs.namespace(|| "sort"), &original, 32).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let mut expected = values.clone();
+            expected.sort();
+
+            for (s, e) in sorted.iter().zip(expected.iter()) {
+                assert_eq!(s.get_value().unwrap(), Fr::from_str(&e.to_string()).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sorting_gadget_bound_to_original() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        for size in 2..16 {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+
+            let values: Vec<u32> = (0..size).map(|_| rng.gen::<u32>() % 64).collect();
+
+            let original: Vec<AllocatedNum<Bn256>> = values.iter().enumerate().map(|(i, v)| {
+                AllocatedNum::alloc(
+                    cs.namespace(|| format!("original {}", i)),
+                    || Ok(Fr::from_str(&v.to_string()).unwrap())
+                ).unwrap()
+            }).collect();
+
+            prove_sorted(cs.namespace(|| "sort"), &original, 32).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            // the sorted list no longer is a permutation of the original one
+            let i = rng.gen::<usize>() % size;
+            cs.set(&format!("original {}/num", i), Fr::from_str(&(values[i] + 64).to_string()).unwrap());
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_sorting_gadget_wrong_order() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let values = [3u32, 1, 2];
+
+        let original: Vec<AllocatedNum<Bn256>> = values.iter().enumerate().map(|(i, v)| {
+            AllocatedNum::alloc(
+                cs.namespace(|| format!("original {}", i)),
+                || Ok(Fr::from_str(&v.to_string()).unwrap())
+            ).unwrap()
+        }).collect();
+
+        // identity permutation is a valid shuffle, but not a sorted one
+        let permutation = IntegerPermutation::new(values.len());
+
+        prove_sorted_with_permutation(cs.namespace(|| "sort"), &original, &permutation, 32).unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+}
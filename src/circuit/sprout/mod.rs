@@ -46,6 +46,36 @@ pub struct JSOutput {
     pub r: Option<CommitmentRandomness>
 }
 
+impl JoinSplit {
+    /// Instance without a witness, for parameter generation and
+    /// constraint counting.
+    pub fn blank() -> Self {
+        let input = || JSInput {
+            value: None,
+            a_sk: None,
+            rho: None,
+            r: None,
+            auth_path: [None; TREE_DEPTH]
+        };
+
+        let output = || JSOutput {
+            value: None,
+            a_pk: None,
+            r: None
+        };
+
+        JoinSplit {
+            vpub_old: None,
+            vpub_new: None,
+            h_sig: None,
+            phi: None,
+            inputs: vec![input(), input()],
+            outputs: vec![output(), output()],
+            rt: None
+        }
+    }
+}
+
 impl<E: Engine> Circuit<E> for JoinSplit {
     fn synthesize<CS: ConstraintSystem<E>>(
         self,
@@ -487,3 +517,14 @@ fn test_sprout_constraints() {
         assert!(cs.verify(&expected_inputs));
     }
 }
+
+#[test]
+fn test_blank_joinsplit() {
+    use bellman::pairing::bls12_381::{Bls12};
+    use ::circuit::fingerprint::CircuitFingerprint;
+
+    let mut cs = CircuitFingerprint::<Bls12>::new();
+    JoinSplit::blank().synthesize(&mut cs).unwrap();
+
+    assert_eq!(cs.num_constraints(), 1989085);
+}
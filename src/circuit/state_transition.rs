@@ -0,0 +1,262 @@
+//! Harness for rollup circuits: a fixed number of operations applied in
+//! order to a Merkle state, proving `old_root -> new_root`.
+//!
+//! The state is a Poseidon2 tree (see `poseidon2::merkle_root`) whose
+//! leaves are field elements. Every operation reads and writes leaves
+//! through `StateAccess` and returns the public data it commits to,
+//! which is chained into `ops_commitment`. The public inputs are
+//! `old_root`, `new_root` and `ops_commitment`, in this order.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    Circuit,
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::AllocatedNum;
+
+use super::poseidon2::poseidon2_compress;
+
+use super::indexed_merkle_tree::compute_root;
+
+use poseidon2::{
+    Poseidon2Params,
+    poseidon2_compress as native_compress
+};
+
+/// The current root of the state, updated by the operations.
+pub struct StateAccess<'a, E: Engine> {
+    params: &'a Poseidon2Params<E::Fr>,
+    root: AllocatedNum<E>
+}
+
+impl<'a, E: Engine> StateAccess<'a, E> {
+    pub fn root(&self) -> &AllocatedNum<E> {
+        &self.root
+    }
+
+    /// Enforces that the leaf at `index_bits` (little-endian) is
+    /// `value`.
+    pub fn read<CS>(
+        &self,
+        mut cs: CS,
+        index_bits: &[Boolean],
+        path: &[AllocatedNum<E>],
+        value: &AllocatedNum<E>
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        let root = compute_root(cs.namespace(|| "root"), self.params, value, index_bits, path)?;
+
+        cs.enforce(
+            || "enforce root",
+            |lc| lc + root.get_variable() - self.root.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc
+        );
+
+        Ok(())
+    }
+
+    /// Enforces that the leaf at `index_bits` is `old_value` and
+    /// replaces it with `new_value`.
+    pub fn read_write<CS>(
+        &mut self,
+        mut cs: CS,
+        index_bits: &[Boolean],
+        path: &[AllocatedNum<E>],
+        old_value: &AllocatedNum<E>,
+        new_value: &AllocatedNum<E>
+    ) -> Result<(), SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        self.read(cs.namespace(|| "read"), index_bits, path, old_value)?;
+        self.root = compute_root(cs.namespace(|| "write"), self.params, new_value, index_bits, path)?;
+
+        Ok(())
+    }
+}
+
+/// One kind of operation of the state transition, e.g. a transfer.
+pub trait OpGadget<E: Engine> {
+    /// Everything the prover needs for one operation, e.g. the
+    /// authentication paths of the leaves it touches.
+    type Witness;
+
+    /// Applies the operation to the state and returns its public
+    /// data. The number of elements must not depend on the witness.
+    fn synthesize<CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        state: &mut StateAccess<E>,
+        witness: Option<&Self::Witness>
+    ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>;
+}
+
+pub struct StateTransitionCircuit<'a, E: Engine, Op: OpGadget<E> + 'a> {
+    pub params: &'a Poseidon2Params<E::Fr>,
+    pub op: &'a Op,
+    pub old_root: Option<E::Fr>,
+    /// One entry per operation slot, `None` when synthesizing
+    /// without a witness.
+    pub ops: Vec<Option<Op::Witness>>
+}
+
+/// Chains the public data of the operations as
+/// `c = compress(c, x)` starting from zero.
+pub fn ops_commitment<F: PrimeField>(params: &Poseidon2Params<F>, data: &[F]) -> F {
+    data.iter().fold(F::zero(), |acc, x| native_compress(params, &acc, x))
+}
+
+impl<'a, E, Op> Circuit<E> for StateTransitionCircuit<'a, E, Op>
+    where E: Engine,
+          Op: OpGadget<E> + 'a
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError>
+    {
+        let old_root = AllocatedNum::alloc(cs.namespace(|| "old root"), || Ok(*self.old_root.get()?))?;
+        old_root.inputize(cs.namespace(|| "old root input"))?;
+
+        let mut state = StateAccess {
+            params: self.params,
+            root: old_root
+        };
+
+        let mut commitment = AllocatedNum::alloc(cs.namespace(|| "initial commitment"), || Ok(E::Fr::zero()))?;
+        cs.enforce(
+            || "initial commitment is zero",
+            |lc| lc + commitment.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc
+        );
+
+        for (i, witness) in self.ops.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("op {}", i));
+
+            let data = self.op.synthesize(cs.namespace(|| "op"), &mut state, witness.as_ref())?;
+
+            for (j, x) in data.iter().enumerate() {
+                commitment = poseidon2_compress(cs.namespace(|| format!("commit {}", j)), self.params, &commitment, x)?;
+            }
+        }
+
+        state.root.inputize(cs.namespace(|| "new root input"))?;
+        commitment.inputize(cs.namespace(|| "ops commitment input"))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use ::poseidon2::{generate_params, merkle_root, merkle_path};
+    use super::*;
+
+    const DEPTH: usize = 3;
+
+    struct DepositWitness {
+        index: usize,
+        old_value: Fr,
+        amount: Fr,
+        path: Vec<Fr>
+    }
+
+    /// Adds `amount` to a leaf, publishing the index and the amount.
+    struct Deposit;
+
+    impl OpGadget<Bn256> for Deposit {
+        type Witness = DepositWitness;
+
+        fn synthesize<CS: ConstraintSystem<Bn256>>(
+            &self,
+            mut cs: CS,
+            state: &mut StateAccess<Bn256>,
+            witness: Option<&DepositWitness>
+        ) -> Result<Vec<AllocatedNum<Bn256>>, SynthesisError>
+        {
+            let index = AllocatedNum::alloc(cs.namespace(|| "index"), || Ok(fr(witness.get()?.index as u64)))?;
+            let mut index_bits = index.into_bits_le(cs.namespace(|| "index bits"))?;
+            for (i, bit) in index_bits.drain(DEPTH..).enumerate() {
+                Boolean::enforce_equal(cs.namespace(|| format!("index bit {} is zero", i)), &bit, &Boolean::constant(false))?;
+            }
+
+            let old_value = AllocatedNum::alloc(cs.namespace(|| "old value"), || Ok(witness.get()?.old_value))?;
+            let amount = AllocatedNum::alloc(cs.namespace(|| "amount"), || Ok(witness.get()?.amount))?;
+            let new_value = AllocatedNum::alloc(cs.namespace(|| "new value"), || {
+                let mut tmp = witness.get()?.old_value;
+                tmp.add_assign(&witness.get()?.amount);
+
+                Ok(tmp)
+            })?;
+
+            cs.enforce(
+                || "new value",
+                |lc| lc + old_value.get_variable() + amount.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + new_value.get_variable()
+            );
+
+            let path = (0..DEPTH).map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("path {}", i)), || Ok(witness.get()?.path[i]))
+            }).collect::<Result<Vec<_>, _>>()?;
+
+            state.read_write(cs.namespace(|| "update"), &index_bits, &path, &old_value, &new_value)?;
+
+            Ok(vec![index, amount])
+        }
+    }
+
+    #[test]
+    fn test_state_transition() {
+        let params = generate_params::<Fr>(3);
+        let mut leaves: Vec<Fr> = (0..8).map(|i| fr(i * 10)).collect();
+        let old_root = merkle_root(&params, &leaves);
+
+        let mut ops = vec![];
+        let mut data = vec![];
+        for &(index, amount) in [(3usize, 5u64), (6, 1), (3, 2)].iter() {
+            ops.push(Some(DepositWitness {
+                index,
+                old_value: leaves[index],
+                amount: fr(amount),
+                path: merkle_path(&params, &leaves, index)
+            }));
+            data.push(fr(index as u64));
+            data.push(fr(amount));
+
+            leaves[index].add_assign(&fr(amount));
+        }
+
+        let circuit = StateTransitionCircuit {
+            params: &params,
+            op: &Deposit,
+            old_root: Some(old_root),
+            ops
+        };
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&[old_root, merkle_root(&params, &leaves), ops_commitment(&params, &data)]));
+
+        cs.set("op 2/op/amount/num", fr(3));
+        assert!(!cs.is_satisfied());
+    }
+}
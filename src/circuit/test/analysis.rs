@@ -0,0 +1,135 @@
+//! Detection of under-constrained variables.
+//!
+//! A variable that appears in no constraint can take any value. A
+//! variable whose constraints involve no other variable, such as a bit
+//! that is only constrained to be boolean, is bound to a few values
+//! but not to the rest of the circuit. Both are usually missing
+//! constraints, unless the variable is a witness checked elsewhere,
+//! e.g. a private input that the caller feeds to another gadget.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    Variable,
+    Index
+};
+
+use std::collections::BTreeMap;
+
+use super::{
+    OrderedVariable,
+    TestConstraintSystem,
+    proc_lc
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Usage {
+    /// The variable appears in no constraint.
+    Unconstrained,
+    /// Every constraint of the variable involves no other variable
+    /// besides `ONE`.
+    Isolated
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnderconstrainedVariable {
+    /// Full path of the variable, which names the namespaces it was
+    /// allocated in.
+    pub path: String,
+    pub is_input: bool,
+    pub usage: Usage
+}
+
+impl<E: Engine> TestConstraintSystem<E> {
+    /// Reports the under-constrained variables, in allocation order,
+    /// inputs first. `ONE` is never reported.
+    pub fn find_underconstrained(&self) -> Vec<UnderconstrainedVariable> {
+        // for every variable, whether it appears in some constraint and
+        // whether one of them involves another variable
+        let mut usage: BTreeMap<OrderedVariable, bool> = BTreeMap::new();
+
+        for &(ref a, ref b, ref c, _) in &self.constraints {
+            let mut vars = proc_lc::<E>(a.as_ref());
+            vars.extend(proc_lc::<E>(b.as_ref()));
+            vars.extend(proc_lc::<E>(c.as_ref()));
+
+            let vars: Vec<OrderedVariable> = vars.into_iter()
+                .map(|(var, _)| var)
+                .filter(|var| !is_one(var))
+                .collect();
+
+            let connected = vars.len() > 1;
+            for var in vars {
+                *usage.entry(var).or_insert(false) |= connected;
+            }
+        }
+
+        let mut result = vec![];
+        let all = self.inputs.iter().enumerate().skip(1).map(|(i, &(_, ref path))| (Index::Input(i), path))
+            .chain(self.aux.iter().enumerate().map(|(i, &(_, ref path))| (Index::Aux(i), path)));
+
+        for (index, path) in all {
+            let usage = match usage.get(&OrderedVariable(Variable::new_unchecked(index))) {
+                None => Usage::Unconstrained,
+                Some(&false) => Usage::Isolated,
+                Some(&true) => continue
+            };
+
+            result.push(UnderconstrainedVariable {
+                path: path.clone(),
+                is_input: match index {
+                    Index::Input(_) => true,
+                    Index::Aux(_) => false
+                },
+                usage
+            });
+        }
+
+        result
+    }
+}
+
+fn is_one(var: &OrderedVariable) -> bool {
+    match var.0.get_unchecked() {
+        Index::Input(0) => true,
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::num::AllocatedNum;
+    use super::*;
+
+    #[test]
+    fn test_find_underconstrained() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("3").unwrap())).unwrap();
+        let b = a.square(cs.namespace(|| "a squared")).unwrap();
+        b.inputize(cs.namespace(|| "b")).unwrap();
+
+        AllocatedNum::alloc(cs.namespace(|| "forgotten"), || Ok(Fr::one())).unwrap();
+        AllocatedBit::alloc(cs.namespace(|| "flag"), Some(true)).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.find_underconstrained(), vec![
+            UnderconstrainedVariable {
+                path: "forgotten/num".into(),
+                is_input: false,
+                usage: Usage::Unconstrained
+            },
+            UnderconstrainedVariable {
+                path: "flag/boolean".into(),
+                is_input: false,
+                usage: Usage::Isolated
+            }
+        ]);
+    }
+}
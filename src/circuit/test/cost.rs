@@ -0,0 +1,158 @@
+//! Constraint-cost regression checks for the gadgets.
+//!
+//! Every gadget configuration is synthesized in its own namespace and
+//! its count is compared against the recorded one. A count that grows
+//! beyond the tolerance fails the test; a count that shrinks is only
+//! returned to the caller, so that the table can be tightened.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use super::TestConstraintSystem;
+
+/// Recorded cost of one gadget configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct ExpectedCost {
+    pub name: &'static str,
+    pub constraints: usize,
+    /// Allowed growth in percent of `constraints`.
+    pub tolerance_percent: usize
+}
+
+impl ExpectedCost {
+    pub fn exact(name: &'static str, constraints: usize) -> Self {
+        Self {
+            name,
+            constraints,
+            tolerance_percent: 0
+        }
+    }
+
+    /// Returns an error describing the regression, if any, or else
+    /// a message describing the improvement, if any.
+    pub fn check(&self, actual: usize) -> Result<Option<String>, String> {
+        let allowed = self.constraints + self.constraints * self.tolerance_percent / 100;
+        if actual > allowed {
+            return Err(format!(
+                "{}: {} constraints, expected {} (+{}%)",
+                self.name, actual, self.constraints, self.tolerance_percent
+            ));
+        }
+
+        if actual < self.constraints {
+            return Ok(Some(format!("{}: {} constraints, down from {}", self.name, actual, self.constraints)));
+        }
+
+        Ok(None)
+    }
+
+    /// Checks the constraints enforced in the namespace named after
+    /// this configuration.
+    pub fn check_namespace<E: Engine>(&self, cs: &TestConstraintSystem<E>) -> Result<Option<String>, String> {
+        self.check(cs.num_constraints_in(self.name))
+    }
+}
+
+/// Checks all `expected` costs against `cs`, reporting every
+/// regression at once. Returns the improvements.
+pub fn assert_costs<E: Engine>(cs: &TestConstraintSystem<E>, expected: &[ExpectedCost]) -> Vec<String> {
+    let mut errors = vec![];
+    let mut improvements = vec![];
+    for e in expected.iter() {
+        match e.check_namespace(cs) {
+            Ok(improvement) => improvements.extend(improvement),
+            Err(error) => errors.push(error)
+        }
+    }
+
+    assert!(errors.is_empty(), "constraint count regressions:\n{}", errors.join("\n"));
+
+    improvements
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::ff::PrimeField;
+    use bellman::pairing::bls12_381::{self, Bls12};
+    use bellman::pairing::bn256::{self, Bn256};
+    use ::circuit::boolean::{AllocatedBit, Boolean};
+    use ::circuit::{blake2s, sha256, pedersen_hash};
+    use ::jubjub::JubjubBls12;
+    use ::alt_babyjubjub::AltJubjubBn256;
+    use super::*;
+
+    fn alloc_bits<E: Engine, CS: ConstraintSystem<E>, R: Rng>(
+        mut cs: CS,
+        rng: &mut R,
+        num_bits: usize
+    ) -> Vec<Boolean> {
+        (0..num_bits).map(|i| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(rng.gen())).unwrap())
+        }).collect()
+    }
+
+    #[test]
+    fn test_bls12_gadget_costs() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &JubjubBls12::new();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let bits = alloc_bits(cs.namespace(|| "block"), rng, 512);
+        sha256::sha256_compression_function(cs.namespace(|| "sha256 block"), &bits, &sha256::get_sha256_iv()).unwrap();
+        blake2s::blake2s(cs.namespace(|| "blake2s block"), &bits, b"12345678").unwrap();
+
+        let bits = alloc_bits(cs.namespace(|| "pedersen input"), rng, bls12_381::Fr::NUM_BITS as usize * 2);
+        pedersen_hash::pedersen_hash(
+            cs.namespace(|| "pedersen note commitment 510 bits"),
+            pedersen_hash::Personalization::NoteCommitment,
+            &bits,
+            params
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_costs(&cs, &[
+            ExpectedCost::exact("sha256 block", 25840),
+            ExpectedCost::exact("blake2s block", 21518 - 512),
+            ExpectedCost::exact("pedersen note commitment 510 bits", 1377 - 510)
+        ]);
+    }
+
+    #[test]
+    fn test_bn256_gadget_costs() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let bits = alloc_bits(cs.namespace(|| "pedersen input"), rng, bn256::Fr::NUM_BITS as usize * 2);
+        pedersen_hash::pedersen_hash(
+            cs.namespace(|| "baby pedersen note commitment 508 bits"),
+            pedersen_hash::Personalization::NoteCommitment,
+            &bits,
+            params
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_costs(&cs, &[
+            ExpectedCost::exact("baby pedersen note commitment 508 bits", 1374 - 508)
+        ]);
+    }
+
+    #[test]
+    fn test_regression_is_reported() {
+        let cost = ExpectedCost {
+            name: "gadget",
+            constraints: 100,
+            tolerance_percent: 5
+        };
+
+        assert_eq!(cost.check(90), Ok(Some("gadget: 90 constraints, down from 100".to_string())));
+        assert_eq!(cost.check(100), Ok(None));
+        assert_eq!(cost.check(105), Ok(None));
+        assert!(cost.check(106).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Audit of the public inputs after synthesis.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use circuit::multipack::{
+    InputKind,
+    InputLayout
+};
+
+use super::TestConstraintSystem;
+
+/// How an input was allocated, recognized by the name given to it by
+/// `inputize` or `pack_into_inputs`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    Inputize,
+    Packing,
+    Other
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicInput {
+    pub path: String,
+    pub source: InputSource
+}
+
+impl<E: Engine> TestConstraintSystem<E> {
+    /// The public inputs except `ONE`, in allocation order.
+    pub fn public_inputs(&self) -> Vec<PublicInput> {
+        self.inputs.iter().skip(1).map(|&(_, ref path)| {
+            let name = path.rsplit('/').next().unwrap();
+
+            let source = if name == "input variable" {
+                InputSource::Inputize
+            } else if name.starts_with("input ") && name[6..].parse::<usize>().is_ok() {
+                InputSource::Packing
+            } else {
+                InputSource::Other
+            };
+
+            PublicInput {
+                path: path.clone(),
+                source: source
+            }
+        }).collect()
+    }
+
+    /// Checks that the inputs were allocated as declared by `layout`,
+    /// in the same order, describing the first difference.
+    pub fn check_input_layout(&self, layout: &InputLayout) -> Result<(), String> {
+        let actual = self.public_inputs();
+        let expected = layout.paths::<E>();
+
+        for (i, (actual, &(ref path, kind))) in actual.iter().zip(expected.iter()).enumerate() {
+            let source = match kind {
+                InputKind::Field => InputSource::Inputize,
+                InputKind::Packed { .. } => InputSource::Packing
+            };
+
+            if actual.path != *path || actual.source != source {
+                return Err(format!("input {}: allocated `{}`, declared `{}` ({:?})", i, actual.path, path, kind));
+            }
+        }
+
+        if actual.len() != expected.len() {
+            return Err(format!("{} inputs allocated, {} declared", actual.len(), expected.len()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::PrimeField;
+    use ::circuit::boolean::{AllocatedBit, Boolean};
+    use ::circuit::num::AllocatedNum;
+    use ::circuit::multipack::{InputValue, pack_into_inputs};
+    use super::*;
+
+    #[test]
+    fn test_input_layout() {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let root = Fr::from_str("42").unwrap();
+        let message: Vec<bool> = (0..300).map(|i| i % 3 == 0).collect();
+
+        let num = AllocatedNum::alloc(cs.namespace(|| "root value"), || Ok(root)).unwrap();
+        num.inputize(cs.namespace(|| "root")).unwrap();
+
+        let bits: Vec<Boolean> = message.iter().enumerate().map(|(i, &b)| {
+            Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(b)).unwrap())
+        }).collect();
+        pack_into_inputs(cs.namespace(|| "message"), &bits).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.public_inputs()[1], PublicInput {
+            path: "message/input 0".into(),
+            source: InputSource::Packing
+        });
+
+        let mut layout = InputLayout::new();
+        layout.field("root");
+        layout.packed("message", 300);
+
+        assert_eq!(layout.num_inputs::<Bn256>(), 3);
+        assert_eq!(cs.check_input_layout(&layout), Ok(()));
+        assert!(cs.verify(&layout.pack::<Bn256>(&[InputValue::Field(root), InputValue::Bits(message)])));
+
+        let mut swapped = InputLayout::new();
+        swapped.packed("message", 300);
+        swapped.field("root");
+        assert!(cs.check_input_layout(&swapped).is_err());
+
+        let mut short = InputLayout::new();
+        short.field("root");
+        short.packed("message", 200);
+        assert!(cs.check_input_layout(&short).is_err());
+    }
+}
@@ -26,6 +26,11 @@ use std::collections::HashSet;
 
 use blake2_rfc::blake2s::Blake2s;
 
+pub mod cost;
+pub mod analysis;
+pub mod perturbation;
+pub mod inputs;
+
 #[derive(Debug)]
 enum NamedObject {
     Constraint(usize),
@@ -102,7 +107,7 @@ fn proc_lc<E: Engine>(
     map
 }
 
-fn hash_lc<E: Engine>(
+pub(crate) fn hash_lc<E: Engine>(
     terms: &[(Variable, E::Fr)],
     h: &mut Blake2s
 )
@@ -330,6 +335,34 @@ impl<E: Engine> TestConstraintSystem<E> {
         self.constraints.len()
     }
 
+    pub fn num_aux(&self) -> usize
+    {
+        self.aux.len()
+    }
+
+    /// Number of constraints enforced inside the namespace `path`.
+    pub fn num_constraints_in(&self, path: &str) -> usize
+    {
+        let prefix = format!("{}/", path);
+
+        self.constraints.iter().filter(|c| c.3.starts_with(&prefix)).count()
+    }
+
+    /// Number of constraints per namespace, with paths truncated to
+    /// `depth` levels. Constraints outside of any namespace at this
+    /// depth are counted under their full path.
+    pub fn constraints_per_namespace(&self, depth: usize) -> BTreeMap<String, usize>
+    {
+        let mut result = BTreeMap::new();
+        for &(_, _, _, ref path) in &self.constraints {
+            let parts: Vec<&str> = path.split('/').collect();
+            let namespace = parts[..depth.min(parts.len())].join("/");
+            *result.entry(namespace).or_insert(0) += 1;
+        }
+
+        result
+    }
+
     pub fn set(&mut self, path: &str, to: E::Fr)
     {
         match self.named_objects.get(path) {
@@ -394,6 +427,12 @@ impl<E: Engine> TestConstraintSystem<E> {
     }
 }
 
+/// `x` as an element of the BN256 scalar field.
+#[cfg(test)]
+pub fn fr(x: u64) -> ::bellman::pairing::bn256::Fr {
+    ::util::fr_from_u64(x)
+}
+
 fn compute_path(ns: &[String], this: String) -> String {
     if this.chars().any(|a| a == '/') {
         panic!("'/' is not allowed in names");
@@ -0,0 +1,132 @@
+//! Detection of malleable witnesses by perturbation.
+//!
+//! Every auxiliary variable of a satisfied constraint system is set in
+//! turn to other values, keeping the inputs and the rest of the witness.
+//! If the constraints still hold, the variable is not determined by the
+//! others and a proof can be produced for another witness, which is a
+//! missing constraint unless the gadget intends it.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::Field;
+
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use super::TestConstraintSystem;
+
+#[derive(Clone)]
+pub struct MalleableWitness<E: Engine> {
+    pub path: String,
+    /// A value other than the assigned one that satisfies the system.
+    pub value: E::Fr
+}
+
+impl<E: Engine> TestConstraintSystem<E> {
+    /// Tries `trials` random values for every auxiliary variable, and
+    /// the flipped value for the variables assigned zero or one, and
+    /// reports the variables for which one of them is accepted.
+    pub fn find_malleable_witnesses<R: Rng>(&mut self, rng: &mut R, trials: usize) -> Vec<MalleableWitness<E>> {
+        assert!(self.is_satisfied(), "the perturbed system must be satisfied");
+
+        let mut result = vec![];
+        for i in 0..self.aux.len() {
+            let original = self.aux[i].0;
+
+            let mut candidates = vec![];
+            if original.is_zero() {
+                candidates.push(E::Fr::one());
+            } else if original == E::Fr::one() {
+                candidates.push(E::Fr::zero());
+            }
+            candidates.extend((0..trials).map(|_| rng.gen::<E::Fr>()).filter(|v| *v != original));
+
+            for value in candidates {
+                self.aux[i].0 = value;
+                let accepted = self.is_satisfied();
+                self.aux[i].0 = original;
+
+                if accepted {
+                    result.push(MalleableWitness {
+                        path: self.aux[i].1.clone(),
+                        value
+                    });
+
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Number of malleable witnesses per namespace, with paths truncated to
+/// `depth` levels as in `TestConstraintSystem::constraints_per_namespace`.
+pub fn malleable_per_namespace<E: Engine>(witnesses: &[MalleableWitness<E>], depth: usize) -> BTreeMap<String, usize> {
+    let mut result = BTreeMap::new();
+    for w in witnesses.iter() {
+        let parts: Vec<&str> = w.path.split('/').collect();
+        let namespace = parts[..depth.min(parts.len())].join("/");
+        *result.entry(namespace).or_insert(0) += 1;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::PrimeField;
+    use ::circuit::boolean::AllocatedBit;
+    use ::circuit::num::AllocatedNum;
+    use super::*;
+
+    #[test]
+    fn test_find_malleable_witnesses() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        {
+            let mut cs = cs.namespace(|| "sound");
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from_str("3").unwrap())).unwrap();
+            a.inputize(cs.namespace(|| "a input")).unwrap();
+            a.square(cs.namespace(|| "a squared")).unwrap();
+        }
+
+        {
+            let mut cs = cs.namespace(|| "unsound");
+            let zero = AllocatedNum::alloc(cs.namespace(|| "zero"), || Ok(Fr::zero())).unwrap();
+            zero.inputize(cs.namespace(|| "zero input")).unwrap();
+
+            // only multiplied by zero
+            let free = AllocatedNum::alloc(cs.namespace(|| "free"), || Ok(Fr::from_str("7").unwrap())).unwrap();
+            cs.enforce(
+                || "vanishing",
+                |lc| lc + zero.get_variable(),
+                |lc| lc + free.get_variable(),
+                |lc| lc
+            );
+
+            AllocatedBit::alloc(cs.namespace(|| "flag"), Some(false)).unwrap();
+        }
+
+        let malleable = cs.find_malleable_witnesses(rng, 4);
+        let paths: Vec<&str> = malleable.iter().map(|w| &w.path[..]).collect();
+        assert_eq!(paths, vec!["unsound/free/num", "unsound/flag/boolean"]);
+        assert_eq!(malleable[1].value, Fr::one());
+
+        let per_namespace = malleable_per_namespace(&malleable, 1);
+        assert_eq!(per_namespace.get("unsound"), Some(&2));
+        assert_eq!(per_namespace.get("sound"), None);
+
+        // the system is left unchanged
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.get("unsound/free/num"), Fr::from_str("7").unwrap());
+    }
+}
@@ -0,0 +1,421 @@
+//! Fiat-Shamir transcripts recomputed in the circuit, matching
+//! `transcript::Blake2sTranscript` and `transcript::RescueTranscript`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    BitsCache,
+    Num
+};
+
+use super::blake2s::blake2s;
+
+use super::rescue::rescue_permutation;
+
+use super::polynomial::challenge_powers;
+
+use rescue::RescueParams;
+
+use params_digest::ParamsDigest;
+
+use transcript::{
+    Blake2sTranscript,
+    BLAKE2S_TRANSCRIPT_PERSONALIZATION,
+    ABSORB_TAG,
+    SQUEEZE_TAG
+};
+
+pub trait TranscriptGadget<E: Engine> {
+    fn commit_num<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: CS,
+        el: &AllocatedNum<E>
+    ) -> Result<(), SynthesisError>;
+
+    fn get_challenge<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: CS
+    ) -> Result<AllocatedNum<E>, SynthesisError>;
+
+    /// Samples a challenge and returns its first `n` powers, matching
+    /// `Transcript::get_challenge_powers`.
+    fn get_challenge_powers<CS: ConstraintSystem<E>>(
+        &mut self,
+        mut cs: CS,
+        n: usize
+    ) -> Result<Vec<AllocatedNum<E>>, SynthesisError>
+    {
+        let challenge = self.get_challenge(cs.namespace(|| "challenge"))?;
+
+        challenge_powers(cs.namespace(|| "powers"), &challenge, n)
+    }
+
+    /// Absorbs the digest of the parameters as a constant, matching
+    /// `Transcript::commit_params`.
+    fn commit_params<CS: ConstraintSystem<E>, P: ParamsDigest>(
+        &mut self,
+        cs: CS,
+        params: &P
+    ) -> Result<(), SynthesisError>;
+}
+
+fn enforce_allocated<E, CS>(
+    mut cs: CS,
+    num: &Num<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let allocated = AllocatedNum::alloc(cs.namespace(|| "allocate"), || {
+        Ok(*num.get_value().get()?)
+    })?;
+
+    cs.enforce(
+        || "allocation constraint",
+        |_| num.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + allocated.get_variable()
+    );
+
+    Ok(allocated)
+}
+
+fn byte_into_bits(byte: u8) -> Vec<Boolean> {
+    (0..8).map(|i| Boolean::constant((byte >> i) & 1 == 1)).collect()
+}
+
+/// The state is kept as the bits of the BLAKE2s digest, in the
+/// order of the `blake2s` gadget.
+#[derive(Clone)]
+pub struct Blake2sTranscriptGadget<E: Engine> {
+    state: Vec<Boolean>,
+    num_updates: usize,
+    _marker: ::std::marker::PhantomData<E>
+}
+
+impl<E: Engine> Blake2sTranscriptGadget<E> {
+    /// The label is absorbed natively, so it costs no constraints.
+    pub fn new(label: &[u8]) -> Self {
+        let initial = Blake2sTranscript::<E::Fr>::new(label);
+
+        Self {
+            state: initial.state().iter().flat_map(|&b| byte_into_bits(b)).collect(),
+            num_updates: 0,
+            _marker: ::std::marker::PhantomData
+        }
+    }
+
+    fn update<CS: ConstraintSystem<E>>(
+        &mut self,
+        mut cs: CS,
+        tag: u8,
+        data: &[Boolean]
+    ) -> Result<(), SynthesisError>
+    {
+        let mut input = self.state.clone();
+        input.extend(byte_into_bits(tag));
+        input.extend_from_slice(data);
+
+        let cs = cs.namespace(|| format!("update {}", self.num_updates));
+        self.state = blake2s(cs, &input, BLAKE2S_TRANSCRIPT_PERSONALIZATION)?;
+        self.num_updates += 1;
+
+        Ok(())
+    }
+
+    /// Absorbs bits in the order of the `blake2s` gadget, i.e. the
+    /// little-endian bits of every byte.
+    pub fn commit_bits<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: CS,
+        bits: &[Boolean]
+    ) -> Result<(), SynthesisError>
+    {
+        assert!(bits.len() % 8 == 0);
+
+        self.update(cs, ABSORB_TAG, bits)
+    }
+
+    /// Same as `commit_num`, with the decomposition taken from the
+    /// `cache`, so a number absorbed more than once or already
+    /// decomposed by another gadget is only decomposed once.
+    pub fn commit_num_cached<CS: ConstraintSystem<E>>(
+        &mut self,
+        mut cs: CS,
+        el: &AllocatedNum<E>,
+        cache: &mut BitsCache
+    ) -> Result<(), SynthesisError>
+    {
+        let bits = cache.into_bits_le_strict(cs.namespace(|| "decompose"), el)?;
+
+        self.commit_decomposed(cs.namespace(|| "commit"), bits)
+    }
+
+    fn commit_decomposed<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: CS,
+        mut bits: Vec<Boolean>
+    ) -> Result<(), SynthesisError>
+    {
+        bits.resize(<E::Fr as PrimeField>::Repr::default().as_ref().len() * 64, Boolean::constant(false));
+
+        self.commit_bits(cs, &bits)
+    }
+}
+
+impl<E: Engine> TranscriptGadget<E> for Blake2sTranscriptGadget<E> {
+    fn commit_num<CS: ConstraintSystem<E>>(
+        &mut self,
+        mut cs: CS,
+        el: &AllocatedNum<E>
+    ) -> Result<(), SynthesisError>
+    {
+        let bits = el.into_bits_le_strict(cs.namespace(|| "decompose"))?;
+
+        self.commit_decomposed(cs.namespace(|| "commit"), bits)
+    }
+
+    fn commit_params<CS: ConstraintSystem<E>, P: ParamsDigest>(
+        &mut self,
+        cs: CS,
+        params: &P
+    ) -> Result<(), SynthesisError>
+    {
+        let mut bytes = vec![];
+        params.digest_element::<E::Fr>().into_repr().write_le(&mut bytes).unwrap();
+        let bits: Vec<Boolean> = bytes.iter().flat_map(|&b| byte_into_bits(b)).collect();
+
+        self.commit_bits(cs, &bits)
+    }
+
+    fn get_challenge<CS: ConstraintSystem<E>>(
+        &mut self,
+        mut cs: CS
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+    {
+        self.update(cs.namespace(|| "squeeze"), SQUEEZE_TAG, &[])?;
+
+        let capacity = E::Fr::CAPACITY as usize;
+        assert!(capacity <= self.state.len());
+
+        let mut num = Num::<E>::zero();
+        let mut coeff = E::Fr::one();
+        for bit in self.state[..capacity].iter() {
+            num = num.add_bool_with_coeff(CS::one(), bit, coeff);
+            coeff.double();
+        }
+
+        enforce_allocated(cs.namespace(|| "challenge"), &num)
+    }
+}
+
+#[derive(Clone)]
+pub struct RescueTranscriptGadget<'a, E: Engine> {
+    params: &'a RescueParams<E::Fr>,
+    state: Vec<Num<E>>,
+    buffer: Vec<Num<E>>,
+    num_squeezes: usize
+}
+
+impl<'a, E: Engine> RescueTranscriptGadget<'a, E> {
+    pub fn new(params: &'a RescueParams<E::Fr>) -> Self {
+        Self {
+            params: params,
+            state: vec![Num::zero(); params.state_width],
+            buffer: vec![],
+            num_squeezes: 0
+        }
+    }
+
+    /// Absorbs a linear combination without allocating it.
+    pub fn commit_lc(&mut self, el: &Num<E>) {
+        self.buffer.push(el.clone());
+    }
+}
+
+impl<'a, E: Engine> TranscriptGadget<E> for RescueTranscriptGadget<'a, E> {
+    fn commit_num<CS: ConstraintSystem<E>>(
+        &mut self,
+        _cs: CS,
+        el: &AllocatedNum<E>
+    ) -> Result<(), SynthesisError>
+    {
+        self.buffer.push(Num::from(el.clone()));
+
+        Ok(())
+    }
+
+    fn commit_params<CS: ConstraintSystem<E>, P: ParamsDigest>(
+        &mut self,
+        _cs: CS,
+        params: &P
+    ) -> Result<(), SynthesisError>
+    {
+        let digest = Num::<E>::zero().add_bool_with_coeff(CS::one(), &Boolean::constant(true), params.digest_element());
+        self.buffer.push(digest);
+
+        Ok(())
+    }
+
+    fn get_challenge<CS: ConstraintSystem<E>>(
+        &mut self,
+        mut cs: CS
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+    {
+        let mut cs = cs.namespace(|| format!("squeeze {}", self.num_squeezes));
+        self.num_squeezes += 1;
+
+        let rate = self.params.rate();
+
+        let mut one = Num::<E>::zero();
+        one.mut_add_bool_with_coeff(CS::one(), &Boolean::constant(true), E::Fr::one());
+        self.buffer.push(one);
+        while self.buffer.len() % rate != 0 {
+            self.buffer.push(Num::zero());
+        }
+
+        let buffer = ::std::mem::replace(&mut self.buffer, vec![]);
+        for (i, chunk) in buffer.chunks(rate).enumerate() {
+            for (s, el) in self.state.iter_mut().zip(chunk.iter()) {
+                s.add_assign(el);
+            }
+            self.state = rescue_permutation(cs.namespace(|| format!("permutation {}", i)), self.params, &self.state)?;
+        }
+
+        // keep the state small by continuing from the allocated challenge
+        let challenge = enforce_allocated(cs.namespace(|| "challenge"), &self.state[0])?;
+        self.state[0] = Num::from(challenge.clone());
+
+        Ok(challenge)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::{ConstraintSystem};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use ::rescue::generate_params;
+    use ::transcript::{Transcript, RescueTranscript};
+    use super::*;
+
+    fn run_transcript<E, CS, T, G>(
+        mut cs: CS,
+        native: &mut T,
+        gadget: &mut G,
+        elements: &[E::Fr]
+    ) where E: Engine, CS: ConstraintSystem<E>, T: Transcript<E::Fr>, G: TranscriptGadget<E>
+    {
+        // commit an increasing number of elements between the squeezes
+        let mut elements = elements.iter().enumerate();
+        for round in 0..4 {
+            for _ in 0..round {
+                let (i, el) = elements.next().unwrap();
+                let var = AllocatedNum::alloc(cs.namespace(|| format!("element {}", i)), || Ok(*el)).unwrap();
+
+                native.commit_field_element(el);
+                gadget.commit_num(cs.namespace(|| format!("commit {}", i)), &var).unwrap();
+            }
+
+            let challenge = gadget.get_challenge(cs.namespace(|| format!("challenge {}", round))).unwrap();
+            assert_eq!(challenge.get_value().unwrap(), native.get_challenge());
+        }
+    }
+
+    #[test]
+    fn test_blake2s_transcript_gadget() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let elements: Vec<Fr> = (0..6).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut native = Blake2sTranscript::<Fr>::new(b"test");
+        let mut gadget = Blake2sTranscriptGadget::<Bn256>::new(b"test");
+        run_transcript(cs.namespace(|| "transcript"), &mut native, &mut gadget, &elements);
+
+        assert!(cs.is_satisfied());
+
+        // raw bytes are absorbed in the same order
+        let bytes: Vec<u8> = (0..40).map(|_| rng.gen()).collect();
+        let bits: Vec<Boolean> = bytes.iter().flat_map(|&b| byte_into_bits(b)).collect();
+        native.commit_bytes(&bytes);
+        gadget.commit_bits(cs.namespace(|| "bytes"), &bits).unwrap();
+
+        let params = ::rescue::generate_params::<Fr>(3, 128);
+        native.commit_params(&params);
+        gadget.commit_params(cs.namespace(|| "params"), &params).unwrap();
+        assert_eq!(gadget.get_challenge(cs.namespace(|| "last challenge")).unwrap().get_value().unwrap(), native.get_challenge());
+    }
+
+    #[test]
+    fn test_blake2s_transcript_cached_commit() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let el: Fr = rng.gen();
+
+        let decomposition_cost = {
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let var = AllocatedNum::alloc(cs.namespace(|| "element"), || Ok(el)).unwrap();
+            var.into_bits_le_strict(cs.namespace(|| "decompose")).unwrap();
+
+            cs.num_constraints()
+        };
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut native = Blake2sTranscript::<Fr>::new(b"test");
+        let mut gadget = Blake2sTranscriptGadget::<Bn256>::new(b"test");
+        let mut cache = BitsCache::new();
+
+        let var = AllocatedNum::alloc(cs.namespace(|| "element"), || Ok(el)).unwrap();
+
+        native.commit_field_element(&el);
+        gadget.commit_num_cached(cs.namespace(|| "commit 0"), &var, &mut cache).unwrap();
+        let first_cost = cs.num_constraints();
+
+        native.commit_field_element(&el);
+        gadget.commit_num_cached(cs.namespace(|| "commit 1"), &var, &mut cache).unwrap();
+        let second_cost = cs.num_constraints() - first_cost;
+
+        assert_eq!(second_cost, first_cost - decomposition_cost);
+
+        assert_eq!(gadget.get_challenge(cs.namespace(|| "challenge")).unwrap().get_value().unwrap(), native.get_challenge());
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_rescue_transcript_gadget() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let elements: Vec<Fr> = (0..6).map(|_| rng.gen()).collect();
+        let params = generate_params::<Fr>(3, 128);
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let mut native = RescueTranscript::new(&params);
+        let mut gadget = RescueTranscriptGadget::<Bn256>::new(&params);
+        run_transcript(cs.namespace(|| "transcript"), &mut native, &mut gadget, &elements);
+
+        assert!(cs.is_satisfied());
+
+        // the parameters are absorbed as a constant
+        native.commit_params(&params);
+        gadget.commit_params(cs.namespace(|| "params"), &params).unwrap();
+        assert_eq!(gadget.get_challenge(cs.namespace(|| "last challenge")).unwrap().get_value().unwrap(), native.get_challenge());
+        assert!(cs.is_satisfied());
+    }
+}
@@ -0,0 +1,552 @@
+//! 256-bit unsigned integers of four `UInt64` limbs, for balances and
+//! storage values with Ethereum semantics.
+//!
+//! Every operation sums the limbs column by column and splits each
+//! column into a 64-bit limb and a carry into the next one with
+//! `comparison::alloc_packed_bits`. Columns stay below `2^131`, far
+//! from the field modulus, so the arithmetic is exact.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::pairing::ff::{
+    Field
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    Variable
+};
+
+use super::Assignment;
+
+use super::boolean::Boolean;
+
+use super::num::{
+    AllocatedNum,
+    Num
+};
+
+use super::uint64::UInt64;
+
+use super::comparison::alloc_packed_bits;
+
+use util::fr_from_u128;
+
+/// Value of a `U256Gadget`, least significant limb first.
+pub type U256 = [u64; 4];
+
+/// Bits of the carries of multiplication and reduction. A column is
+/// at most four products of limbs and a carry, below `2^131`.
+const CARRY_BITS: usize = 67;
+
+/// Represents an interpretation of four `UInt64` limbs as an unsigned
+/// integer.
+#[derive(Clone)]
+pub struct U256Gadget {
+    // Least significant limb first
+    limbs: Vec<UInt64>
+}
+
+/// Adds the little-endian number of `bits` times `coeff` to `num`.
+fn add_bits<E: Engine>(mut num: Num<E>, one: Variable, bits: &[Boolean], mut coeff: E::Fr) -> Num<E> {
+    for bit in bits {
+        num = num.add_bool_with_coeff(one, bit, coeff);
+        coeff.double();
+    }
+
+    num
+}
+
+/// Splits `column` into its low limb and the `carry_bits` bits above.
+fn split_column<E, CS>(
+    cs: CS,
+    column: &Num<E>,
+    carry_bits: usize
+) -> Result<(UInt64, Vec<Boolean>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut bits = alloc_packed_bits(cs, column.get_value(), column.lc(E::Fr::one()), 64 + carry_bits)?;
+    let carry = bits.split_off(64);
+
+    Ok((UInt64::from_bits(&bits), carry))
+}
+
+/// `a - b` modulo `2^256` and whether it borrowed, i.e. `a < b`.
+fn sub_with_borrow(a: &U256, b: &U256) -> (U256, bool) {
+    let mut result = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (tmp, b_0) = a[i].overflowing_sub(b[i]);
+        let (tmp, b_1) = tmp.overflowing_sub(borrow as u64);
+        result[i] = tmp;
+        borrow = b_0 || b_1;
+    }
+
+    (result, borrow)
+}
+
+/// Quotient and remainder of the little-endian limbs `x` by `modulus`.
+fn div_rem(x: &[u64], modulus: &U256) -> (Vec<u64>, U256) {
+    let mut quotient = vec![0u64; x.len()];
+    let mut remainder = [0u64; 4];
+
+    for i in (0..(x.len() * 64)).rev() {
+        // the remainder is below the modulus, so twice it fits 257 bits
+        let overflow = remainder[3] >> 63 == 1;
+        for k in (1..4).rev() {
+            remainder[k] = (remainder[k] << 1) | (remainder[k - 1] >> 63);
+        }
+        remainder[0] = (remainder[0] << 1) | ((x[i / 64] >> (i % 64)) & 1);
+
+        let (reduced, borrow) = sub_with_borrow(&remainder, modulus);
+        if overflow || !borrow {
+            remainder = reduced;
+            quotient[i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    (quotient, remainder)
+}
+
+/// Reduces the number of little-endian limbs `x` modulo `modulus` by
+/// enforcing `x = quotient * modulus + remainder` and
+/// `remainder < modulus`.
+fn reduce_limbs<E, CS>(
+    mut cs: CS,
+    x: &[UInt64],
+    modulus: &U256
+) -> Result<U256Gadget, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    assert!(*modulus != [0; 4]);
+
+    let division = x.iter().map(|limb| limb.get_value()).collect::<Option<Vec<_>>>().map(|x| div_rem(&x, modulus));
+
+    let quotient = (0..x.len()).map(|i| {
+        UInt64::alloc(
+            cs.namespace(|| format!("quotient limb {}", i)),
+            division.as_ref().map(|d| d.0[i])
+        )
+    }).collect::<Result<Vec<_>, SynthesisError>>()?;
+    let remainder = U256Gadget::alloc(cs.namespace(|| "remainder"), division.map(|d| d.1))?;
+
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    let shift_inverse = fr_from_u128::<E::Fr>(1 << 64).inverse().unwrap();
+
+    // Every column of quotient * modulus + remainder - x plus the carry
+    // is a multiple of 2^64, whose quotient is the next carry. The last
+    // carry is zero.
+    let num_columns = x.len() + 4;
+    let mut carry = vec![];
+    for k in 0..num_columns {
+        let mut column = add_bits(Num::zero(), CS::one(), &carry, E::Fr::one());
+        for (i, q) in quotient.iter().enumerate() {
+            if i <= k && k - i < 4 && modulus[k - i] != 0 {
+                column = add_bits(column, CS::one(), &q.into_bits(), fr_from_u128(modulus[k - i] as u128));
+            }
+        }
+        if k < 4 {
+            column = add_bits(column, CS::one(), &remainder.limbs[k].into_bits(), E::Fr::one());
+        }
+        if k < x.len() {
+            column = add_bits(column, CS::one(), &x[k].into_bits(), minus_one);
+        }
+        column.scale(shift_inverse);
+
+        let carry_bits = if k + 1 == num_columns { 0 } else { CARRY_BITS };
+        carry = alloc_packed_bits(
+            cs.namespace(|| format!("column {}", k)),
+            column.get_value(),
+            column.lc(E::Fr::one()),
+            carry_bits
+        )?;
+    }
+
+    let is_reduced = remainder.less_than(cs.namespace(|| "remainder is reduced"), &U256Gadget::constant(*modulus))?;
+    Boolean::enforce_equal(
+        cs.namespace(|| "remainder is below the modulus"),
+        &is_reduced,
+        &Boolean::constant(true)
+    )?;
+
+    Ok(remainder)
+}
+
+impl U256Gadget {
+    /// Construct a constant `U256Gadget` from its limbs
+    pub fn constant(value: U256) -> Self {
+        U256Gadget {
+            limbs: value.iter().map(|&limb| UInt64::constant(limb)).collect()
+        }
+    }
+
+    /// Allocate a `U256Gadget` in the constraint system
+    pub fn alloc<E, CS>(
+        mut cs: CS,
+        value: Option<U256>
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let limbs = (0..4).map(|i| {
+            UInt64::alloc(cs.namespace(|| format!("limb {}", i)), value.map(|v| v[i]))
+        }).collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(U256Gadget {
+            limbs: limbs
+        })
+    }
+
+    /// Converts four little-endian limbs into a `U256Gadget`.
+    pub fn from_limbs(limbs: &[UInt64]) -> Self {
+        assert_eq!(limbs.len(), 4);
+
+        U256Gadget {
+            limbs: limbs.to_vec()
+        }
+    }
+
+    pub fn limbs(&self) -> &[UInt64] {
+        &self.limbs
+    }
+
+    pub fn get_value(&self) -> Option<U256> {
+        let mut value = [0u64; 4];
+        for (v, limb) in value.iter_mut().zip(self.limbs.iter()) {
+            *v = limb.get_value()?;
+        }
+
+        Some(value)
+    }
+
+    /// Turns this `U256Gadget` into its little-endian bits.
+    pub fn into_bits(&self) -> Vec<Boolean> {
+        self.limbs.iter().flat_map(|limb| limb.into_bits()).collect()
+    }
+
+    /// Converts 256 little-endian bits into a `U256Gadget`.
+    pub fn from_bits(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 256);
+
+        U256Gadget {
+            limbs: bits.chunks(64).map(|chunk| UInt64::from_bits(chunk)).collect()
+        }
+    }
+
+    /// Adds `other` modulo `2^256`, returning the sum and the carry.
+    pub fn add<E, CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<(Self, Boolean), SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let mut limbs = vec![];
+        let mut carry = Boolean::constant(false);
+        for (i, (a, b)) in self.limbs.iter().zip(other.limbs.iter()).enumerate() {
+            let column = add_bits(Num::zero(), CS::one(), &a.into_bits(), E::Fr::one());
+            let column = add_bits(column, CS::one(), &b.into_bits(), E::Fr::one());
+            let column = column.add_bool_with_coeff(CS::one(), &carry, E::Fr::one());
+
+            let (limb, mut carry_bits) = split_column(cs.namespace(|| format!("limb {}", i)), &column, 1)?;
+            limbs.push(limb);
+            carry = carry_bits.pop().unwrap();
+        }
+
+        Ok((U256Gadget { limbs: limbs }, carry))
+    }
+
+    /// Subtracts `other` modulo `2^256`, returning the difference and
+    /// the borrow, which is set iff `self < other`.
+    pub fn sub<E, CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<(Self, Boolean), SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let mut minus_one = E::Fr::one();
+        minus_one.negate();
+
+        let mut limbs = vec![];
+        let mut borrow = Boolean::constant(false);
+        for (i, (a, b)) in self.limbs.iter().zip(other.limbs.iter()).enumerate() {
+            // a - b - borrow + 2^64 has its bit 64 set iff it doesn't borrow
+            let column = add_bits(Num::zero(), CS::one(), &a.into_bits(), E::Fr::one());
+            let column = add_bits(column, CS::one(), &b.into_bits(), minus_one);
+            let column = column.add_bool_with_coeff(CS::one(), &borrow, minus_one);
+            let column = column.add_bool_with_coeff(CS::one(), &Boolean::constant(true), fr_from_u128(1 << 64));
+
+            let (limb, mut carry_bits) = split_column(cs.namespace(|| format!("limb {}", i)), &column, 1)?;
+            limbs.push(limb);
+            borrow = carry_bits.pop().unwrap().not();
+        }
+
+        Ok((U256Gadget { limbs: limbs }, borrow))
+    }
+
+    /// Schoolbook multiplication, returning the low and the high halves
+    /// of the 512-bit product.
+    pub fn mul<E, CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<(Self, Self), SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let mut columns: Vec<Num<E>> = (0..8).map(|_| Num::zero()).collect();
+        for (i, a) in self.limbs.iter().enumerate() {
+            for (j, b) in other.limbs.iter().enumerate() {
+                let product = AllocatedNum::alloc(cs.namespace(|| format!("product {} {}", i, j)), || {
+                    let a = *a.get_value().get()? as u128;
+                    let b = *b.get_value().get()? as u128;
+
+                    Ok(fr_from_u128(a * b))
+                })?;
+
+                cs.enforce(
+                    || format!("product {} {} constraint", i, j),
+                    |_| add_bits(Num::zero(), CS::one(), &a.into_bits(), E::Fr::one()).lc(E::Fr::one()),
+                    |_| add_bits(Num::zero(), CS::one(), &b.into_bits(), E::Fr::one()).lc(E::Fr::one()),
+                    |lc| lc + product.get_variable()
+                );
+
+                columns[i + j].mut_add_number_with_coeff(&product, E::Fr::one());
+            }
+        }
+
+        let mut limbs = vec![];
+        let mut carry = vec![];
+        for (k, column) in columns.into_iter().enumerate() {
+            let column = add_bits(column, CS::one(), &carry, E::Fr::one());
+
+            // the product fits 512 bits, so nothing carries out of the last column
+            let carry_bits = if k == 7 { 0 } else { CARRY_BITS };
+            let (limb, carry_out) = split_column(cs.namespace(|| format!("column {}", k)), &column, carry_bits)?;
+            limbs.push(limb);
+            carry = carry_out;
+        }
+
+        let high = limbs.split_off(4);
+
+        Ok((U256Gadget { limbs: limbs }, U256Gadget { limbs: high }))
+    }
+
+    /// Reduces modulo the constant `modulus`.
+    pub fn reduce<E, CS>(
+        &self,
+        cs: CS,
+        modulus: &U256
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        reduce_limbs(cs, &self.limbs, modulus)
+    }
+
+    /// Multiplies by `other` modulo the constant `modulus`.
+    pub fn mul_mod<E, CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        modulus: &U256
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let (low, high) = self.mul(cs.namespace(|| "multiplication"), other)?;
+        let product: Vec<UInt64> = low.limbs.into_iter().chain(high.limbs.into_iter()).collect();
+
+        reduce_limbs(cs.namespace(|| "reduction"), &product, modulus)
+    }
+
+    /// Returns whether `self < other`.
+    pub fn less_than<E, CS>(
+        &self,
+        cs: CS,
+        other: &Self
+    ) -> Result<Boolean, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let (_, borrow) = self.sub(cs, other)?;
+
+        Ok(borrow)
+    }
+
+    /// Returns whether `self <= other`.
+    pub fn less_or_equal<E, CS>(
+        &self,
+        cs: CS,
+        other: &Self
+    ) -> Result<Boolean, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        Ok(other.less_than(cs, self)?.not())
+    }
+
+    pub fn enforce_equal<E, CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<(), SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        for (i, (a, b)) in self.into_bits().iter().zip(other.into_bits().iter()).enumerate() {
+            Boolean::enforce_equal(cs.namespace(|| format!("bit {} is equal", i)), a, b)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use ::circuit::test::*;
+    use super::*;
+
+    const SECP256K1_P: U256 = [0xfffffffefffffc2f, 0xffffffffffffffff, 0xffffffffffffffff, 0xffffffffffffffff];
+
+    fn mul_natively(a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut result = vec![0u64; a.len() + b.len()];
+        for i in 0..a.len() {
+            let mut carry = 0u128;
+            for j in 0..b.len() {
+                let tmp = (a[i] as u128) * (b[j] as u128) + (result[i + j] as u128) + carry;
+                result[i + j] = tmp as u64;
+                carry = tmp >> 64;
+            }
+            result[i + b.len()] = carry as u64;
+        }
+
+        result
+    }
+
+    fn add_natively(a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut carry = 0u128;
+        a.iter().enumerate().map(|(i, &a)| {
+            let tmp = (a as u128) + (b.get(i).cloned().unwrap_or(0) as u128) + carry;
+            carry = tmp >> 64;
+
+            tmp as u64
+        }).collect()
+    }
+
+    fn to_u256(limbs: &[u64]) -> U256 {
+        [limbs[0], limbs[1], limbs[2], limbs[3]]
+    }
+
+    #[test]
+    fn test_add_sub() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for i in 0..20 {
+            let a: U256 = rng.gen();
+            let b: U256 = if i == 0 { [u64::max_value(); 4] } else { rng.gen() };
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let a_var = U256Gadget::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_var = U256Gadget::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+
+            let (sum, carry) = a_var.add(cs.namespace(|| "add"), &b_var).unwrap();
+            let (difference, borrow) = a_var.sub(cs.namespace(|| "sub"), &b_var).unwrap();
+            let less_than = a_var.less_than(cs.namespace(|| "less than"), &b_var).unwrap();
+            let less_or_equal = a_var.less_or_equal(cs.namespace(|| "less or equal"), &a_var).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let (expected_difference, expected_borrow) = sub_with_borrow(&a, &b);
+            assert_eq!(difference.get_value().unwrap(), expected_difference);
+            assert_eq!(borrow.get_value().unwrap(), expected_borrow);
+            assert_eq!(less_than.get_value().unwrap(), expected_borrow);
+            assert!(less_or_equal.get_value().unwrap());
+
+            // the sum minus b wraps back to a
+            let (expected_a, expected_carry) = sub_with_borrow(&sum.get_value().unwrap(), &b);
+            assert_eq!(expected_a, a);
+            assert_eq!(carry.get_value().unwrap(), expected_carry);
+
+            cs.set("add/limb 0/bit 0/boolean", if (a[0] ^ b[0]) & 1 == 1 { Fr::zero() } else { Fr::one() });
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_mul() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for i in 0..10 {
+            let a: U256 = if i == 0 { [u64::max_value(); 4] } else { rng.gen() };
+            let b: U256 = if i == 0 { [u64::max_value(); 4] } else { rng.gen() };
+
+            let mut cs = TestConstraintSystem::<Bn256>::new();
+            let a_var = U256Gadget::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+            let b_var = U256Gadget::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+
+            let (low, high) = a_var.mul(cs.namespace(|| "mul"), &b_var).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            let expected = mul_natively(&a, &b);
+            assert_eq!(low.get_value().unwrap(), to_u256(&expected[..4]));
+            assert_eq!(high.get_value().unwrap(), to_u256(&expected[4..]));
+        }
+    }
+
+    #[test]
+    fn test_mul_mod() {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &modulus in [SECP256K1_P, [1000000007, 0, 0, 0]].iter() {
+            for _ in 0..5 {
+                let a: U256 = rng.gen();
+                let b: U256 = rng.gen();
+
+                let mut cs = TestConstraintSystem::<Bn256>::new();
+                let a_var = U256Gadget::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+                let b_var = U256Gadget::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+
+                let reduced = a_var.reduce(cs.namespace(|| "reduce"), &modulus).unwrap();
+                let result = a_var.mul_mod(cs.namespace(|| "mul mod"), &b_var, &modulus).unwrap();
+
+                assert!(cs.is_satisfied());
+
+                let product = mul_natively(&a, &b);
+                let (quotient, remainder) = div_rem(&product, &modulus);
+                assert_eq!(result.get_value().unwrap(), remainder);
+                assert!(sub_with_borrow(&remainder, &modulus).1);
+
+                // quotient * modulus + remainder is the product
+                let expected = add_natively(&mul_natively(&quotient, &modulus), &remainder);
+                assert_eq!(&expected[..8], &product[..]);
+                assert!(expected[8..].iter().all(|&l| l == 0));
+
+                let (_, a_reduced) = div_rem(&a, &modulus);
+                assert_eq!(reduced.get_value().unwrap(), a_reduced);
+                if modulus[1..] == [0, 0, 0] {
+                    let m = modulus[0] as u128;
+                    assert_eq!(remainder, [(((a_reduced[0] as u128) * ((div_rem(&b, &modulus).1)[0] as u128)) % m) as u64, 0, 0, 0]);
+                }
+
+                // a remainder off by the modulus
+                let limb = "mul mod/reduction/remainder/limb 3/allocated bit 63/boolean";
+                let flipped = if cs.get(limb) == Fr::zero() { Fr::one() } else { Fr::zero() };
+                cs.set(limb, flipped);
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+}
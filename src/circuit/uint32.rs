@@ -90,6 +90,10 @@ impl UInt32 {
         })
     }
 
+    pub fn get_value(&self) -> Option<u32> {
+        self.value
+    }
+
     pub fn into_bits_be(&self) -> Vec<Boolean> {
         self.bits.iter().rev().cloned().collect()
     }
@@ -0,0 +1,193 @@
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+/// Represents an interpretation of 64 `Boolean` objects as an
+/// unsigned integer.
+#[derive(Clone)]
+pub struct UInt64 {
+    // Least significant bit first
+    bits: Vec<Boolean>,
+    value: Option<u64>
+}
+
+impl UInt64 {
+    /// Construct a constant `UInt64` from a `u64`
+    pub fn constant(value: u64) -> Self
+    {
+        let mut bits = Vec::with_capacity(64);
+
+        let mut tmp = value;
+        for _ in 0..64 {
+            if tmp & 1 == 1 {
+                bits.push(Boolean::constant(true))
+            } else {
+                bits.push(Boolean::constant(false))
+            }
+
+            tmp >>= 1;
+        }
+
+        UInt64 {
+            bits: bits,
+            value: Some(value)
+        }
+    }
+
+    /// Allocate a `UInt64` in the constraint system
+    pub fn alloc<E, CS>(
+        mut cs: CS,
+        value: Option<u64>
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let values = match value {
+            Some(mut val) => {
+                let mut v = Vec::with_capacity(64);
+
+                for _ in 0..64 {
+                    v.push(Some(val & 1 == 1));
+                    val >>= 1;
+                }
+
+                v
+            },
+            None => vec![None; 64]
+        };
+
+        let bits = values.into_iter()
+                         .enumerate()
+                         .map(|(i, v)| {
+                            Ok(Boolean::from(AllocatedBit::alloc(
+                                cs.namespace(|| format!("allocated bit {}", i)),
+                                v
+                            )?))
+                         })
+                         .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt64 {
+            bits: bits,
+            value: value
+        })
+    }
+
+    pub fn get_value(&self) -> Option<u64> {
+        self.value
+    }
+
+    pub fn into_bits_be(&self) -> Vec<Boolean> {
+        self.bits.iter().rev().cloned().collect()
+    }
+
+    pub fn from_bits_be(bits: &[Boolean]) -> Self {
+        assert_eq!(bits.len(), 64);
+
+        let mut value = Some(0u64);
+        for b in bits {
+            value.as_mut().map(|v| *v <<= 1);
+
+            match b.get_value() {
+                Some(true) => { value.as_mut().map(|v| *v |= 1); },
+                Some(false) => {},
+                None => { value = None; }
+            }
+        }
+
+        UInt64 {
+            value: value,
+            bits: bits.iter().rev().cloned().collect()
+        }
+    }
+
+
+    /// Turns this `UInt64` into its little-endian byte order representation.
+    pub fn into_bits(&self) -> Vec<Boolean> {
+        self.bits.clone()
+    }
+
+    /// Converts a little-endian byte order representation of bits into a
+    /// `UInt64`.
+    pub fn from_bits(bits: &[Boolean]) -> Self
+    {
+        assert_eq!(bits.len(), 64);
+
+        let new_bits = bits.to_vec();
+
+        let mut value = Some(0u64);
+        for b in new_bits.iter().rev() {
+            value.as_mut().map(|v| *v <<= 1);
+
+            match b {
+                &Boolean::Constant(b) => {
+                    if b {
+                        value.as_mut().map(|v| *v |= 1);
+                    }
+                },
+                &Boolean::Is(ref b) => {
+                    match b.get_value() {
+                        Some(true) => { value.as_mut().map(|v| *v |= 1); },
+                        Some(false) => {},
+                        None => { value = None }
+                    }
+                },
+                &Boolean::Not(ref b) => {
+                    match b.get_value() {
+                        Some(false) => { value.as_mut().map(|v| *v |= 1); },
+                        Some(true) => {},
+                        None => { value = None }
+                    }
+                }
+            }
+        }
+
+        UInt64 {
+            value: value,
+            bits: new_bits
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use ::circuit::boolean::{Boolean};
+    use super::{UInt64};
+    use bellman::pairing::bls12_381::{Bls12};
+    use ::circuit::test::*;
+    use bellman::{ConstraintSystem};
+
+    #[test]
+    fn test_uint64_alloc() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..100 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let value: u64 = rng.gen();
+            let a = UInt64::alloc(cs.namespace(|| "a"), Some(value)).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(a.get_value(), Some(value));
+            assert_eq!(UInt64::from_bits(&a.into_bits()).get_value(), Some(value));
+            assert_eq!(UInt64::from_bits_be(&a.into_bits_be()).get_value(), Some(value));
+
+            for (i, bit) in UInt64::constant(value).into_bits().iter().enumerate() {
+                match bit {
+                    &Boolean::Constant(bit) => assert_eq!(bit, (value >> i) & 1 == 1),
+                    _ => unreachable!()
+                }
+            }
+        }
+    }
+}
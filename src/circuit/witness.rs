@@ -0,0 +1,229 @@
+//! Diagnostics for incomplete witnesses.
+//!
+//! Synthesizing a circuit with a missing witness value fails with a
+//! bare `SynthesisError::AssignmentMissing`. `check_witness` runs the
+//! synthesis without building any constraints, records the path of
+//! the allocation that failed and maps it back to the `Option` fields
+//! of the circuit registered with `WitnessSources`.
+
+use bellman::pairing::{
+    Engine,
+};
+
+use bellman::{
+    Circuit,
+    LinearCombination,
+    SynthesisError,
+    ConstraintSystem,
+    Variable,
+    Index
+};
+
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Registers which fields of a circuit the witness values of its
+/// namespaces come from.
+pub trait WitnessSources {
+    /// Pairs of a top level namespace and the name of the field it's
+    /// witnessed from. A namespace also matches the namespaces named
+    /// after it with an index, e.g. `"merkle tree hash"` matches
+    /// `"merkle tree hash 3"`.
+    fn witness_sources(&self) -> Vec<(&'static str, &'static str)>;
+}
+
+#[derive(Debug)]
+pub struct WitnessError {
+    pub error: SynthesisError,
+    /// Path of the failed allocation, or of the last allocation or
+    /// namespace before the failure if it didn't come from one.
+    pub path: String,
+    /// Fields of the circuit the path traces back to.
+    pub sources: Vec<&'static str>
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at `{}`", self.error, self.path)?;
+        if !self.sources.is_empty() {
+            write!(f, " (witnessed from {})", self.sources.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn source_matches(path: &str, namespace: &str) -> bool {
+    path.starts_with(namespace) && match path[namespace.len()..].chars().next() {
+        None | Some('/') | Some(' ') => true,
+        _ => false
+    }
+}
+
+struct WitnessCheckingCS<E: Engine> {
+    current_namespace: Vec<String>,
+    last_path: String,
+    failed_at: Option<String>,
+    num_inputs: usize,
+    num_aux: usize,
+    _marker: PhantomData<E>
+}
+
+impl<E: Engine> WitnessCheckingCS<E> {
+    fn path(&self, name: String) -> String {
+        let mut path = self.current_namespace.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(&name);
+
+        path
+    }
+
+    fn try_alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<(), SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.last_path = self.path(annotation().into());
+
+        match f() {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                if self.failed_at.is_none() {
+                    self.failed_at = Some(self.last_path.clone());
+                }
+
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for WitnessCheckingCS<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.try_alloc(annotation, f)?;
+        self.num_aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        annotation: A,
+        f: F
+    ) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.try_alloc(annotation, f)?;
+        self.num_inputs += 1;
+
+        Ok(Variable::new_unchecked(Index::Input(self.num_inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(
+        &mut self,
+        _: A,
+        _: LA,
+        _: LB,
+        _: LC
+    )
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where NR: Into<String>, N: FnOnce() -> NR
+    {
+        let name = name_fn().into();
+        self.last_path = self.path(name.clone());
+        self.current_namespace.push(name);
+    }
+
+    fn pop_namespace(&mut self)
+    {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root
+    {
+        self
+    }
+}
+
+/// Synthesizes `circuit` only to check that its witness is complete.
+pub fn check_witness<E, C>(circuit: C) -> Result<(), WitnessError>
+    where E: Engine, C: Circuit<E> + WitnessSources
+{
+    let sources = circuit.witness_sources();
+
+    let mut cs = WitnessCheckingCS::<E> {
+        current_namespace: vec![],
+        last_path: String::new(),
+        failed_at: None,
+        // the constant one is the first input
+        num_inputs: 1,
+        num_aux: 0,
+        _marker: PhantomData
+    };
+
+    match circuit.synthesize(&mut cs) {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let path = cs.failed_at.unwrap_or(cs.last_path);
+            let sources = sources.iter()
+                .filter(|&&(namespace, _)| source_matches(&path, namespace))
+                .map(|&(_, field)| field)
+                .collect();
+
+            Err(WitnessError {
+                error,
+                path,
+                sources
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bls12_381::Bls12;
+    use bellman::SynthesisError;
+    use jubjub::JubjubBls12;
+    use ::circuit::sapling::{Spend, Output};
+    use super::*;
+
+    #[test]
+    fn test_missing_witness_is_traced() {
+        let params = &JubjubBls12::new();
+
+        let err = check_witness::<Bls12, _>(Spend::blank(params, 32)).unwrap_err();
+        assert!(err.path.starts_with("ak/"));
+        assert_eq!(err.sources, vec!["proof_generation_key"]);
+
+        let err = check_witness::<Bls12, _>(Output::blank(params)).unwrap_err();
+        assert!(err.path.starts_with("value commitment/"));
+        assert_eq!(err.sources, vec!["value_commitment"]);
+        match err.error {
+            SynthesisError::AssignmentMissing => {},
+            _ => panic!("unexpected error")
+        }
+    }
+
+    #[test]
+    fn test_source_matching() {
+        assert!(source_matches("merkle tree hash 3/path element", "merkle tree hash"));
+        assert!(source_matches("ar", "ar"));
+        assert!(!source_matches("anchor/input variable", "ar"));
+        assert!(!source_matches("arx/bit 0", "ar"));
+    }
+}
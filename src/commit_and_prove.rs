@@ -0,0 +1,129 @@
+//! Pedersen vector commitments to witness values, for commit-and-prove
+//! composition: circuits that expose a commitment to the values they
+//! share, made with the same key and randomness, can be proven
+//! separately and linked by comparing the commitments. The in-circuit
+//! side is `circuit::commit_and_prove`.
+//!
+//! C = r . H + sum_i (lo_i . G_2i + hi_i . G_2i+1), where lo_i and hi_i
+//! are the low and high 128 bits of the i-th value, so every scalar
+//! is below the subgroup order. All generators are derived from a
+//! domain tag with `derive_generator`, so no relation between them is
+//! known.
+
+use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use jubjub::{
+    JubjubEngine,
+    PrimeOrder,
+    edwards
+};
+
+use group_hash::derive_generator;
+
+/// Bits in each half of a committed value.
+pub const HALF_BITS: usize = 128;
+
+/// The generators of a commitment to `num_values` values.
+#[derive(Clone)]
+pub struct VectorCommitmentKey<E: JubjubEngine> {
+    /// H, the generator of the randomness
+    pub blinding: edwards::Point<E, PrimeOrder>,
+    /// G_0, G_1, ..., two per value
+    pub generators: Vec<edwards::Point<E, PrimeOrder>>
+}
+
+fn generator_tag(domain_tag: &[u8], index: u32) -> Vec<u8> {
+    let mut tag = domain_tag.to_vec();
+    tag.write_u32::<BigEndian>(index).expect("writing to a vector doesn't fail");
+
+    tag
+}
+
+/// The low and high halves of `value` as scalars.
+pub fn split_value<E: JubjubEngine>(value: &E::Fr) -> (E::Fs, E::Fs) {
+    let repr = value.into_repr();
+    let limbs = repr.as_ref();
+    assert!(limbs.len() <= 4);
+
+    let half = |from: usize| {
+        let mut tmp = <E::Fs as PrimeField>::Repr::default();
+        for (i, l) in limbs.iter().skip(from).take(HALF_BITS / 64).enumerate() {
+            tmp.as_mut()[i] = *l;
+        }
+
+        E::Fs::from_repr(tmp).expect("128 bits are below the subgroup order")
+    };
+
+    (half(0), half(HALF_BITS / 64))
+}
+
+impl<E: JubjubEngine> VectorCommitmentKey<E> {
+    pub fn new(domain_tag: &[u8], num_values: usize, params: &E::Params) -> Self {
+        VectorCommitmentKey {
+            blinding: derive_generator(&generator_tag(domain_tag, 0), params),
+            generators: (0..(2 * num_values)).map(|i| {
+                derive_generator(&generator_tag(domain_tag, i as u32 + 1), params)
+            }).collect()
+        }
+    }
+
+    pub fn num_values(&self) -> usize {
+        self.generators.len() / 2
+    }
+
+    pub fn commit(
+        &self,
+        values: &[E::Fr],
+        randomness: E::Fs,
+        params: &E::Params
+    ) -> edwards::Point<E, PrimeOrder>
+    {
+        assert_eq!(values.len(), self.num_values());
+
+        let mut cm = self.blinding.mul(randomness, params);
+        for (value, g) in values.iter().zip(self.generators.chunks(2)) {
+            let (lo, hi) = split_value::<E>(value);
+            cm = cm.add(&g[0].mul(lo, params), params)
+                   .add(&g[1].mul(hi, params), params);
+        }
+
+        cm
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use super::*;
+
+    #[test]
+    fn test_vector_commitment() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let key = VectorCommitmentKey::<Bn256>::new(b"test", 3, params);
+        assert_eq!(key.num_values(), 3);
+
+        let values: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+        let r: Fs = rng.gen();
+        let cm = key.commit(&values, r, params);
+
+        assert!(key.commit(&values, r, params) == cm);
+        assert!(key.commit(&values, rng.gen(), params) != cm);
+        assert!(VectorCommitmentKey::<Bn256>::new(b"other", 3, params).commit(&values, r, params) != cm);
+
+        let mut swapped = values.clone();
+        swapped.swap(0, 1);
+        assert!(key.commit(&swapped, r, params) != cm);
+
+        // the halves recombine into the value
+        let (lo, hi) = split_value::<Bn256>(&values[0]);
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.as_mut()[..2].copy_from_slice(&lo.into_repr().as_ref()[..2]);
+        repr.as_mut()[2..].copy_from_slice(&hi.into_repr().as_ref()[..2]);
+        assert_eq!(Fr::from_repr(repr).unwrap(), values[0]);
+    }
+}
@@ -0,0 +1,202 @@
+//! Commitment schemes behind a common interface, so protocols can be
+//! written once for any backend. A commitment is made to a message
+//! and opened at a position: the whole message for Pedersen
+//! commitments, a leaf for Merkle trees and an evaluation point for
+//! FRI. The in-circuit side is `circuit::commitment::CommitmentGadget`.
+
+use bellman::pairing::ff::PrimeField;
+
+use bellman::multicore::Worker;
+
+use bellman::SynthesisError;
+
+use rand::Rng;
+
+use jubjub::{
+    JubjubEngine,
+    PrimeOrder,
+    edwards
+};
+
+use pedersen_hash::{
+    Personalization,
+    pedersen_commitment
+};
+
+use poseidon2::{
+    Poseidon2Params,
+    merkle_root,
+    merkle_path
+};
+
+use indexed_merkle_tree::root_from_path;
+
+use fri::{
+    Commitment,
+    CommittedPolynomial,
+    FriPcs,
+    Opening
+};
+
+use fri::layout::OracleLayout;
+
+pub trait CommitmentScheme {
+    type Message;
+    /// What a commitment is opened at.
+    type Position;
+    /// The opened value.
+    type Value;
+    type Commitment: Clone + PartialEq;
+    /// Data the committer keeps to make openings.
+    type ProverData;
+    type Proof;
+
+    fn commit<R: Rng>(&self, message: &Self::Message, rng: &mut R) -> (Self::Commitment, Self::ProverData);
+
+    fn open(
+        &self,
+        data: &Self::ProverData,
+        position: &Self::Position
+    ) -> Result<(Self::Value, Self::Proof), SynthesisError>;
+
+    fn verify(
+        &self,
+        commitment: &Self::Commitment,
+        position: &Self::Position,
+        value: &Self::Value,
+        proof: &Self::Proof
+    ) -> bool;
+}
+
+/// Windowed Pedersen commitment to a bit string, opened as a whole
+/// with its randomness.
+pub struct PedersenCommitmentScheme<'a, E: JubjubEngine> {
+    pub personalization: Personalization,
+    pub params: &'a E::Params
+}
+
+impl<'a, E: JubjubEngine> CommitmentScheme for PedersenCommitmentScheme<'a, E> {
+    type Message = Vec<bool>;
+    type Position = ();
+    type Value = Vec<bool>;
+    type Commitment = edwards::Point<E, PrimeOrder>;
+    type ProverData = (Vec<bool>, E::Fs);
+    type Proof = E::Fs;
+
+    fn commit<R: Rng>(&self, message: &Vec<bool>, rng: &mut R) -> (Self::Commitment, Self::ProverData) {
+        let r: E::Fs = rng.gen();
+        let cm = pedersen_commitment::<E, _>(self.personalization, message.iter().cloned(), r, self.params);
+
+        (cm, (message.clone(), r))
+    }
+
+    fn open(&self, data: &Self::ProverData, _position: &()) -> Result<(Vec<bool>, E::Fs), SynthesisError> {
+        Ok(data.clone())
+    }
+
+    fn verify(&self, commitment: &Self::Commitment, _position: &(), value: &Vec<bool>, proof: &E::Fs) -> bool {
+        pedersen_commitment::<E, _>(self.personalization, value.iter().cloned(), *proof, self.params) == *commitment
+    }
+}
+
+/// Poseidon2 Merkle tree over a power of two number of leaves, opened
+/// at a leaf index.
+pub struct MerkleCommitmentScheme<'a, F: PrimeField> {
+    pub params: &'a Poseidon2Params<F>
+}
+
+impl<'a, F: PrimeField> CommitmentScheme for MerkleCommitmentScheme<'a, F> {
+    type Message = Vec<F>;
+    type Position = usize;
+    type Value = F;
+    type Commitment = F;
+    type ProverData = Vec<F>;
+    type Proof = Vec<F>;
+
+    fn commit<R: Rng>(&self, message: &Vec<F>, _rng: &mut R) -> (F, Vec<F>) {
+        (merkle_root(self.params, message), message.clone())
+    }
+
+    fn open(&self, data: &Vec<F>, position: &usize) -> Result<(F, Vec<F>), SynthesisError> {
+        let value = *data.get(*position).ok_or(SynthesisError::Unsatisfiable)?;
+
+        Ok((value, merkle_path(self.params, data, *position)))
+    }
+
+    fn verify(&self, commitment: &F, position: &usize, value: &F, proof: &Vec<F>) -> bool {
+        (*position >> proof.len()) == 0 && root_from_path(self.params, value, *position as u64, proof) == *commitment
+    }
+}
+
+/// Coefficients of a polynomial, opened at an evaluation point.
+impl<F: PrimeField, L: OracleLayout> CommitmentScheme for FriPcs<F, L> {
+    type Message = Vec<F>;
+    type Position = F;
+    type Value = F;
+    type Commitment = Commitment;
+    type ProverData = (Commitment, CommittedPolynomial<F>);
+    type Proof = Opening<F>;
+
+    fn commit<R: Rng>(&self, message: &Vec<F>, _rng: &mut R) -> (Commitment, Self::ProverData) {
+        let (commitment, poly) = FriPcs::commit(self, &Worker::new(), message);
+
+        (commitment, (commitment, poly))
+    }
+
+    fn open(&self, data: &Self::ProverData, position: &F) -> Result<(F, Opening<F>), SynthesisError> {
+        FriPcs::open(self, &Worker::new(), &data.0, &data.1, *position)
+    }
+
+    fn verify(&self, commitment: &Commitment, position: &F, value: &F, proof: &Opening<F>) -> bool {
+        FriPcs::verify(self, commitment, *position, *value, proof)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bls12_381::{Bls12, Fr};
+    use bellman::pairing::ff::Field;
+    use jubjub::JubjubBls12;
+    use fri::FriParams;
+    use poseidon2::generate_params;
+    use super::*;
+
+    /// Commits, opens and checks that a wrong value is rejected.
+    fn check_scheme<S, R>(scheme: &S, message: &S::Message, position: S::Position, wrong: S::Value, rng: &mut R)
+        where S: CommitmentScheme, R: Rng
+    {
+        let (commitment, data) = scheme.commit(message, rng);
+        let (value, proof) = scheme.open(&data, &position).unwrap();
+
+        assert!(scheme.verify(&commitment, &position, &value, &proof));
+        assert!(!scheme.verify(&commitment, &position, &wrong, &proof));
+    }
+
+    #[test]
+    fn test_schemes() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let jubjub_params = JubjubBls12::new();
+        let pedersen = PedersenCommitmentScheme::<Bls12> {
+            personalization: Personalization::Custom(1),
+            params: &jubjub_params
+        };
+        let message: Vec<bool> = (0..100).map(|_| rng.gen()).collect();
+        let mut wrong = message.clone();
+        wrong[7] = !wrong[7];
+        check_scheme(&pedersen, &message, (), wrong, rng);
+
+        let poseidon2_params = generate_params::<Fr>(3);
+        let merkle = MerkleCommitmentScheme { params: &poseidon2_params };
+        let leaves: Vec<Fr> = (0..8).map(|_| rng.gen()).collect();
+        let mut wrong = leaves[3];
+        wrong.add_assign(&Fr::one());
+        check_scheme(&merkle, &leaves, 3, wrong, rng);
+
+        let fri = FriPcs::<Fr>::new(FriParams { lde_factor: 4, num_queries: 16, final_degree_plus_one: 2 }, 16).unwrap();
+        let coeffs: Vec<Fr> = (0..16).map(|_| rng.gen()).collect();
+        let z: Fr = rng.gen();
+        check_scheme(&fri, &coeffs, z, Fr::one(), rng);
+    }
+}
@@ -14,6 +14,19 @@ pub const CRH_IVK_PERSONALIZATION: &'static [u8; 8]
 pub const PRF_NF_PERSONALIZATION: &'static [u8; 8]
           = b"Zcash_nf";
 
+// BLAKE2b invocation personalizations
+/// BLAKE2b Personalization for PRF^expand(sk, t) = BLAKE2b-512(sk | t)
+pub const PRF_EXPAND_PERSONALIZATION: &'static [u8; 16]
+          = b"Zcash_ExpandSeed";
+
+/// BLAKE2b Personalization for PRF^ock = BLAKE2b-256(ovk | cv | cmu | epk)
+pub const PRF_OCK_PERSONALIZATION: &'static [u8; 16]
+          = b"Zcash_Derive_ock";
+
+/// BLAKE2b Personalization for KDF^Sapling = BLAKE2b-256(dhsecret | epk)
+pub const KDF_SAPLING_PERSONALIZATION: &'static [u8; 16]
+          = b"Zcash_SaplingKDF";
+
 // Group hash personalizations
 /// BLAKE2s Personalization for Pedersen hash generators.
 pub const PEDERSEN_HASH_GENERATORS_PERSONALIZATION: &'static [u8; 8]
@@ -39,6 +52,17 @@ pub const VALUE_COMMITMENT_GENERATOR_PERSONALIZATION: &'static [u8; 8]
 pub const NULLIFIER_POSITION_IN_TREE_GENERATOR_PERSONALIZATION: &'static [u8; 8]
           = b"Zcash_J_";
 
+/// BLAKE2s Personalization for the generators of `group_hash::derive_generator`
+pub const DERIVED_GENERATOR_PERSONALIZATION: &'static [u8; 8]
+          = b"SC_DGen_";
+
 /// BLAKE2s Personalization hash of (R_x || message) in EdDSA variant with 256 bit hash
 pub const MATTER_EDDSA_BLAKE2S_PERSONALIZATION: &'static [u8; 8] 
-            = b"Matter_H";
\ No newline at end of file
+            = b"Matter_H";
+
+/// BLAKE2b Personalization of the deterministic EdDSA nonce H*(sk || mode || P_G, M)
+pub const MATTER_EDDSA_NONCE_PERSONALIZATION: &'static [u8; 16]
+            = b"MatterEdDSANonce";
+
+/// Tag of the `Custom` Pedersen hash personalization of timed note commitments
+pub const TIMED_NOTE_COMMITMENT_TAG: u8 = 2;
@@ -0,0 +1,326 @@
+//! Generation of Solidity verifiers for Groth16 proofs and
+//! encoding of proofs as calldata. Only BN256 is supported,
+//! as it's the only curve with EVM precompiles.
+
+use bellman::pairing::{
+    CurveAffine
+};
+
+use bellman::pairing::bn256::{
+    Bn256,
+    Fq,
+    Fr,
+    G1Affine,
+    G2Affine
+};
+
+use bellman::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::groth16::{
+    VerifyingKey,
+    Proof
+};
+
+use tiny_keccak::{Keccak, Hasher};
+
+fn fq_into_hex(el: &Fq) -> String {
+    format!("{}", el.into_repr())
+}
+
+fn g1_into_hex(point: &G1Affine) -> [String; 2] {
+    let (x, y) = point.into_xy_unchecked();
+
+    [fq_into_hex(&x), fq_into_hex(&y)]
+}
+
+// EVM expects the imaginary part of the Fq2 coordinates first
+fn g2_into_hex(point: &G2Affine) -> [String; 4] {
+    let (x, y) = point.into_xy_unchecked();
+
+    [fq_into_hex(&x.c1), fq_into_hex(&x.c0), fq_into_hex(&y.c1), fq_into_hex(&y.c0)]
+}
+
+fn write_be<F: PrimeField>(el: &F, buf: &mut Vec<u8>) {
+    el.into_repr().write_be(buf).expect("must write into a vector");
+}
+
+fn verify_proof_signature(num_inputs: usize) -> String {
+    format!("verifyProof(uint256[2],uint256[2][2],uint256[2],uint256[{}])", num_inputs)
+}
+
+/// Returns the selector of the `verifyProof` function
+/// of the verifier with `num_inputs` public inputs.
+pub fn verify_proof_selector(num_inputs: usize) -> [u8; 4] {
+    let mut hasher = Keccak::v256();
+    hasher.update(verify_proof_signature(num_inputs).as_bytes());
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&hash[0..4]);
+
+    selector
+}
+
+/// Encodes the proof as `(uint256[2] a, uint256[2][2] b, uint256[2] c)`.
+pub fn encode_proof(proof: &Proof<Bn256>) -> Vec<u8> {
+    let mut buf = vec![];
+
+    let (a_x, a_y) = proof.a.into_xy_unchecked();
+    write_be(&a_x, &mut buf);
+    write_be(&a_y, &mut buf);
+
+    let (b_x, b_y) = proof.b.into_xy_unchecked();
+    write_be(&b_x.c1, &mut buf);
+    write_be(&b_x.c0, &mut buf);
+    write_be(&b_y.c1, &mut buf);
+    write_be(&b_y.c0, &mut buf);
+
+    let (c_x, c_y) = proof.c.into_xy_unchecked();
+    write_be(&c_x, &mut buf);
+    write_be(&c_y, &mut buf);
+
+    buf
+}
+
+/// Encodes the call of `verifyProof` of the generated verifier.
+pub fn encode_calldata(proof: &Proof<Bn256>, public_inputs: &[Fr]) -> Vec<u8> {
+    let mut buf = verify_proof_selector(public_inputs.len()).to_vec();
+    buf.extend(encode_proof(proof));
+    for input in public_inputs.iter() {
+        write_be(input, &mut buf);
+    }
+
+    buf
+}
+
+// prices of the precompiles since EIP-1108 and of non-zero calldata
+// bytes since EIP-2028
+const EC_ADD_GAS: usize = 150;
+const EC_MUL_GAS: usize = 6000;
+const PAIRING_BASE_GAS: usize = 45000;
+const PAIRING_PER_PAIR_GAS: usize = 34000;
+const CALLDATA_BYTE_GAS: usize = 16;
+
+/// Size of a proof encoded by `encode_proof`.
+pub const PROOF_SIZE: usize = 8 * 32;
+
+/// Expected cost of verifying a proof with the generated verifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerificationCost {
+    pub proof_size: usize,
+    pub calldata_size: usize,
+    /// Gas of the ecAdd, ecMul and pairing precompiles.
+    pub precompile_gas: usize,
+    /// Gas of the calldata, counting every byte as non-zero.
+    pub calldata_gas: usize
+}
+
+impl VerificationCost {
+    /// Upper bound of the gas, without the execution of the contract
+    /// code around the precompiles and the base cost of a transaction.
+    pub fn gas(&self) -> usize {
+        self.precompile_gas + self.calldata_gas
+    }
+}
+
+/// Estimates the cost of verifying a proof with `num_inputs` public
+/// inputs. A circuit wrapped in `circuit::input_hashing::HashedInputs`
+/// has a single one, at the price of hashing them in the circuit.
+pub fn estimate_verification_cost(num_inputs: usize) -> VerificationCost {
+    let calldata_size = 4 + PROOF_SIZE + 32 * num_inputs;
+
+    VerificationCost {
+        proof_size: PROOF_SIZE,
+        calldata_size,
+        precompile_gas: num_inputs * (EC_ADD_GAS + EC_MUL_GAS) + PAIRING_BASE_GAS + 4 * PAIRING_PER_PAIR_GAS,
+        calldata_gas: calldata_size * CALLDATA_BYTE_GAS
+    }
+}
+
+/// Emits a Solidity contract that verifies Groth16 proofs
+/// for the given verifying key.
+pub fn generate_solidity_verifier(vk: &VerifyingKey<Bn256>) -> String {
+    assert!(vk.ic.len() > 0);
+    let num_inputs = vk.ic.len() - 1;
+
+    let alpha = g1_into_hex(&vk.alpha_g1);
+    let beta = g2_into_hex(&vk.beta_g2);
+    let gamma = g2_into_hex(&vk.gamma_g2);
+    let delta = g2_into_hex(&vk.delta_g2);
+    let ic: Vec<[String; 2]> = vk.ic.iter().map(|p| g1_into_hex(p)).collect();
+
+    let mut out = String::new();
+
+    out.push_str("// SPDX-License-Identifier: MIT OR Apache-2.0\n");
+    out.push_str("pragma solidity >=0.6.0 <0.9.0;\n\n");
+    out.push_str("contract Verifier {\n");
+    out.push_str("    uint256 constant SCALAR_FIELD = 21888242871839275222246405745257275088548364400416034343698204186575808495617;\n");
+    out.push_str("    uint256 constant BASE_FIELD = 21888242871839275222246405745257275088696311157297823662689037894645226208583;\n\n");
+
+    out.push_str("    function ecAdd(uint256[2] memory p1, uint256[2] memory p2) internal view returns (uint256[2] memory r) {\n");
+    out.push_str("        uint256[4] memory input;\n");
+    out.push_str("        input[0] = p1[0];\n");
+    out.push_str("        input[1] = p1[1];\n");
+    out.push_str("        input[2] = p2[0];\n");
+    out.push_str("        input[3] = p2[1];\n");
+    out.push_str("        bool success;\n");
+    out.push_str("        assembly {\n");
+    out.push_str("            success := staticcall(sub(gas(), 2000), 6, input, 0x80, r, 0x40)\n");
+    out.push_str("        }\n");
+    out.push_str("        require(success, \"ecAdd failed\");\n");
+    out.push_str("    }\n\n");
+
+    out.push_str("    function ecMul(uint256[2] memory p, uint256 s) internal view returns (uint256[2] memory r) {\n");
+    out.push_str("        uint256[3] memory input;\n");
+    out.push_str("        input[0] = p[0];\n");
+    out.push_str("        input[1] = p[1];\n");
+    out.push_str("        input[2] = s;\n");
+    out.push_str("        bool success;\n");
+    out.push_str("        assembly {\n");
+    out.push_str("            success := staticcall(sub(gas(), 2000), 7, input, 0x60, r, 0x40)\n");
+    out.push_str("        }\n");
+    out.push_str("        require(success, \"ecMul failed\");\n");
+    out.push_str("    }\n\n");
+
+    out.push_str(&format!(
+        "    function verifyProof(uint256[2] memory a, uint256[2][2] memory b, uint256[2] memory c, uint256[{}] memory input) public view returns (bool) {{\n",
+        num_inputs
+    ));
+    out.push_str("        uint256[2] memory vk_x;\n");
+    out.push_str(&format!("        vk_x[0] = {};\n", ic[0][0]));
+    out.push_str(&format!("        vk_x[1] = {};\n", ic[0][1]));
+    out.push_str("        uint256[2] memory term;\n");
+    for (i, point) in ic[1..].iter().enumerate() {
+        out.push_str(&format!("        require(input[{}] < SCALAR_FIELD, \"input is not in the field\");\n", i));
+        out.push_str(&format!("        term[0] = {};\n", point[0]));
+        out.push_str(&format!("        term[1] = {};\n", point[1]));
+        out.push_str(&format!("        vk_x = ecAdd(vk_x, ecMul(term, input[{}]));\n", i));
+    }
+    out.push_str("\n");
+
+    // e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+    let mut pairing_input: Vec<String> = vec![];
+    pairing_input.push("a[0]".to_owned());
+    pairing_input.push("(BASE_FIELD - (a[1] % BASE_FIELD)) % BASE_FIELD".to_owned());
+    pairing_input.extend(["b[0][0]", "b[0][1]", "b[1][0]", "b[1][1]"].iter().map(|s| s.to_string()));
+    pairing_input.extend(alpha.iter().cloned());
+    pairing_input.extend(beta.iter().cloned());
+    pairing_input.extend(["vk_x[0]", "vk_x[1]"].iter().map(|s| s.to_string()));
+    pairing_input.extend(gamma.iter().cloned());
+    pairing_input.extend(["c[0]", "c[1]"].iter().map(|s| s.to_string()));
+    pairing_input.extend(delta.iter().cloned());
+
+    out.push_str(&format!("        uint256[{}] memory p;\n", pairing_input.len()));
+    for (i, el) in pairing_input.iter().enumerate() {
+        out.push_str(&format!("        p[{}] = {};\n", i, el));
+    }
+    out.push_str("        uint256[1] memory result;\n");
+    out.push_str("        bool success;\n");
+    out.push_str("        assembly {\n");
+    out.push_str(&format!(
+        "            success := staticcall(sub(gas(), 2000), 8, p, {}, result, 0x20)\n",
+        pairing_input.len() * 32
+    ));
+    out.push_str("        }\n");
+    out.push_str("        require(success, \"pairing check failed\");\n\n");
+    out.push_str("        return result[0] == 1;\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+    use bellman::{Circuit, ConstraintSystem, SynthesisError};
+    use bellman::groth16::{generate_random_parameters, create_random_proof, prepare_verifying_key, verify_proof};
+    use circuit::test::TestConstraintSystem;
+    use super::{generate_solidity_verifier, encode_calldata, encode_proof, verify_proof_selector, estimate_verification_cost};
+
+    struct SquareCircuit {
+        x: Option<Fr>
+    }
+
+    impl Circuit<Bn256> for SquareCircuit {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(|| "y", || {
+                let mut tmp = x_value.ok_or(SynthesisError::AssignmentMissing)?;
+                tmp.square();
+
+                Ok(tmp)
+            })?;
+
+            cs.enforce(|| "square", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_verifier_generation() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let params = generate_random_parameters::<Bn256, _, _>(SquareCircuit { x: None }, rng).unwrap();
+
+        let x: Fr = rng.gen();
+        let mut y = x;
+        y.square();
+
+        let proof = create_random_proof(SquareCircuit { x: Some(x) }, &params, rng).unwrap();
+
+        let contract = generate_solidity_verifier(&params.vk);
+        assert!(contract.contains("uint256[1] memory input"));
+        assert!(contract.contains("uint256[24] memory p;"));
+
+        let calldata = encode_calldata(&proof, &[y]);
+        assert_eq!(calldata.len(), 4 + 32 * (8 + 1));
+        assert_eq!(&calldata[0..4], &verify_proof_selector(1)[..]);
+
+        let cost = estimate_verification_cost(1);
+        assert_eq!(cost.proof_size, encode_proof(&proof).len());
+        assert_eq!(cost.calldata_size, calldata.len());
+        assert_eq!(cost.precompile_gas, 6150 + 45000 + 4 * 34000);
+        assert!(estimate_verification_cost(10).gas() - cost.gas() == 9 * (6150 + 32 * 16));
+    }
+
+    #[test]
+    fn test_verified_circuit_is_satisfied() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let x: Fr = rng.gen();
+        let mut y = x;
+        y.square();
+
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        SquareCircuit { x: Some(x) }.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&[y]));
+
+        let mut wrong_y = y;
+        wrong_y.add_assign(&Fr::one());
+        cs.set("y", wrong_y);
+        assert!(!cs.is_satisfied());
+        assert_eq!(cs.which_is_unsatisfied(), Some("square"));
+
+        // the statement the contract checks is the same one the circuit enforces
+        let params = generate_random_parameters::<Bn256, _, _>(SquareCircuit { x: None }, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        let proof = create_random_proof(SquareCircuit { x: Some(x) }, &params, rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[wrong_y]).unwrap());
+
+        let calldata = encode_calldata(&proof, &[y]);
+        let mut input = Fr::zero().into_repr();
+        input.read_be(&calldata[4 + 32 * 8..]).unwrap();
+        assert_eq!(Fr::from_repr(input).unwrap(), y);
+    }
+}
@@ -0,0 +1,449 @@
+//! Multiplicative subgroups of prime fields and FFTs over them,
+//! for provers preparing inputs of the polynomial IOP verifiers.
+//!
+//! Power of two domains use the radix-2 FFT, which is parallelized
+//! with the `Worker` of bellman. Other sizes dividing `p - 1` use a
+//! mixed-radix FFT, where the sub-transforms of the first level are
+//! computed in parallel.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::multicore::Worker;
+
+use bellman::SynthesisError;
+
+#[derive(Clone, Debug)]
+pub struct Domain<F: PrimeField> {
+    pub size: u64,
+    pub power_of_two: u64,
+    pub generator: F
+}
+
+impl<F: PrimeField> Domain<F> {
+    /// Creates the smallest power of two domain that has at least
+    /// `expected_size` elements.
+    pub fn new_for_size(expected_size: u64) -> Result<Self, SynthesisError> {
+        let size = expected_size.next_power_of_two();
+        let power_of_two = size.trailing_zeros() as u64;
+
+        if power_of_two > F::S as u64 {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        let mut generator = F::root_of_unity();
+        for _ in power_of_two..(F::S as u64) {
+            generator.square();
+        }
+
+        Ok(Self {
+            size,
+            power_of_two,
+            generator
+        })
+    }
+
+    /// Creates the domain of exactly `size` elements, which must
+    /// divide `p - 1`.
+    pub fn new_mixed_radix(size: u64) -> Result<Self, SynthesisError> {
+        if size == 0 {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        if size.is_power_of_two() {
+            return Self::new_for_size(size);
+        }
+
+        let mut exp = F::char();
+        exp.sub_noborrow(&F::Repr::from(1));
+        if div_rem_small(&mut exp, size) != 0 {
+            return Err(SynthesisError::PolynomialDegreeTooLarge);
+        }
+
+        Ok(Self {
+            size,
+            power_of_two: size.trailing_zeros() as u64,
+            generator: F::multiplicative_generator().pow(exp)
+        })
+    }
+
+    pub fn is_radix_2(&self) -> bool {
+        self.size.is_power_of_two()
+    }
+
+    /// Returns `generator^index`.
+    pub fn element(&self, index: u64) -> F {
+        self.generator.pow(&[index % self.size])
+    }
+
+    pub fn elements(&self) -> Vec<F> {
+        let mut res = Vec::with_capacity(self.size as usize);
+        let mut cur = F::one();
+        for _ in 0..self.size {
+            res.push(cur);
+            cur.mul_assign(&self.generator);
+        }
+
+        res
+    }
+
+    /// Evaluates the polynomial with coefficients `values` (lowest
+    /// degree first) over the domain, in place. The coefficients
+    /// are padded with zeroes up to the domain size.
+    pub fn fft(&self, worker: &Worker, values: &mut Vec<F>) {
+        self.pad(values);
+        transform(worker, values, &self.generator);
+    }
+
+    /// Interpolates the values over the domain into coefficients,
+    /// in place.
+    pub fn ifft(&self, worker: &Worker, values: &mut Vec<F>) {
+        self.pad(values);
+        transform(worker, values, &self.generator.inverse().unwrap());
+
+        let size_inv = F::from_str(&self.size.to_string()).unwrap().inverse().unwrap();
+        distribute_powers(worker, values, F::one(), size_inv);
+    }
+
+    /// Evaluates the polynomial over the coset `offset * H`.
+    pub fn coset_fft(&self, worker: &Worker, values: &mut Vec<F>, offset: &F) {
+        self.pad(values);
+        distribute_powers(worker, values, *offset, F::one());
+        transform(worker, values, &self.generator);
+    }
+
+    /// Interpolates the values over the coset `offset * H`.
+    pub fn coset_ifft(&self, worker: &Worker, values: &mut Vec<F>, offset: &F) {
+        self.ifft(worker, values);
+        distribute_powers(worker, values, offset.inverse().unwrap(), F::one());
+    }
+
+    fn pad(&self, values: &mut Vec<F>) {
+        assert!(values.len() as u64 <= self.size);
+        values.resize(self.size as usize, F::zero());
+    }
+}
+
+/// Divides `repr` by `divisor` in place and returns the remainder.
+fn div_rem_small<R: PrimeFieldRepr>(repr: &mut R, divisor: u64) -> u64 {
+    let mut rem = 0u128;
+    for limb in repr.as_mut().iter_mut().rev() {
+        let cur = (rem << 64) | (*limb as u128);
+        *limb = (cur / divisor as u128) as u64;
+        rem = cur % divisor as u128;
+    }
+
+    rem as u64
+}
+
+/// Multiplies `values[i]` by `scale * base^i`.
+fn distribute_powers<F: PrimeField>(worker: &Worker, values: &mut [F], base: F, scale: F) {
+    worker.scope(values.len(), |scope, chunk| {
+        for (i, values) in values.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                let mut cur = base.pow(&[(i * chunk) as u64]);
+                cur.mul_assign(&scale);
+                for v in values.iter_mut() {
+                    v.mul_assign(&cur);
+                    cur.mul_assign(&base);
+                }
+            });
+        }
+    });
+}
+
+fn transform<F: PrimeField>(worker: &Worker, a: &mut [F], omega: &F) {
+    let n = a.len();
+    if n.is_power_of_two() {
+        let log_n = n.trailing_zeros();
+        let log_cpus = worker.log_num_cpus();
+
+        if log_n <= log_cpus {
+            serial_fft(a, omega, log_n);
+        } else {
+            parallel_fft(a, worker, omega, log_n, log_cpus);
+        }
+    } else {
+        mixed_radix_fft(a, worker, omega);
+    }
+}
+
+fn bitreverse(mut n: u32, l: u32) -> u32 {
+    let mut r = 0;
+    for _ in 0..l {
+        r = (r << 1) | (n & 1);
+        n >>= 1;
+    }
+    r
+}
+
+fn serial_fft<F: PrimeField>(a: &mut [F], omega: &F, log_n: u32) {
+    let n = a.len() as u32;
+    assert_eq!(n, 1 << log_n);
+
+    for k in 0..n {
+        let rk = bitreverse(k, log_n);
+        if k < rk {
+            a.swap(rk as usize, k as usize);
+        }
+    }
+
+    let mut m = 1;
+    for _ in 0..log_n {
+        let w_m = omega.pow(&[(n / (2 * m)) as u64]);
+
+        let mut k = 0;
+        while k < n {
+            let mut w = F::one();
+            for j in 0..m {
+                let mut t = a[(k + j + m) as usize];
+                t.mul_assign(&w);
+                let mut tmp = a[(k + j) as usize];
+                tmp.sub_assign(&t);
+                a[(k + j + m) as usize] = tmp;
+                a[(k + j) as usize].add_assign(&t);
+                w.mul_assign(&w_m);
+            }
+
+            k += 2 * m;
+        }
+
+        m *= 2;
+    }
+}
+
+fn parallel_fft<F: PrimeField>(
+    a: &mut [F],
+    worker: &Worker,
+    omega: &F,
+    log_n: u32,
+    log_cpus: u32
+)
+{
+    assert!(log_n >= log_cpus);
+
+    let num_cpus = 1 << log_cpus;
+    let log_new_n = log_n - log_cpus;
+    let mut tmp = vec![vec![F::zero(); 1 << log_new_n]; num_cpus];
+    let new_omega = omega.pow(&[num_cpus as u64]);
+
+    worker.scope(0, |scope, _| {
+        let a = &*a;
+
+        for (j, tmp) in tmp.iter_mut().enumerate() {
+            scope.spawn(move |_| {
+                // Shuffle into a sub-FFT
+                let omega_j = omega.pow(&[j as u64]);
+                let omega_step = omega.pow(&[(j as u64) << log_new_n]);
+
+                let mut elt = F::one();
+                for i in 0..(1 << log_new_n) {
+                    for s in 0..num_cpus {
+                        let idx = (i + (s << log_new_n)) % (1 << log_n);
+                        let mut t = a[idx];
+                        t.mul_assign(&elt);
+                        tmp[i].add_assign(&t);
+                        elt.mul_assign(&omega_step);
+                    }
+                    elt.mul_assign(&omega_j);
+                }
+
+                // Perform sub-FFT
+                serial_fft(tmp, &new_omega, log_new_n);
+            });
+        }
+    });
+
+    worker.scope(a.len(), |scope, chunk| {
+        let tmp = &tmp;
+
+        for (idx, a) in a.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                let mut idx = idx * chunk;
+                let mask = (1 << log_cpus) - 1;
+                for a in a {
+                    *a = tmp[idx & mask][idx >> log_cpus];
+                    idx += 1;
+                }
+            });
+        }
+    });
+}
+
+fn smallest_factor(n: usize) -> usize {
+    let mut p = 2;
+    while p * p <= n {
+        if n % p == 0 {
+            return p;
+        }
+        p += 1;
+    }
+
+    n
+}
+
+/// Decimation in time over the smallest prime factor `p` of the
+/// size: the `p` interleaved subsequences are transformed first and
+/// then combined with the twiddle factors.
+fn mixed_radix_fft<F: PrimeField>(a: &mut [F], worker: &Worker, omega: &F) {
+    let n = a.len();
+    let p = smallest_factor(n);
+    let m = n / p;
+
+    let mut subs: Vec<Vec<F>> = (0..p).map(|j| {
+        a.iter().skip(j).step_by(p).cloned().collect()
+    }).collect();
+
+    let sub_omega = omega.pow(&[p as u64]);
+    worker.scope(0, |scope, _| {
+        for sub in subs.iter_mut() {
+            scope.spawn(move |_| {
+                serial_transform(sub, &sub_omega);
+            });
+        }
+    });
+
+    // A[k + m * t] = sum_j omega^(j * (k + m * t)) * F_j[k]
+    worker.scope(n, |scope, chunk| {
+        let subs = &subs;
+
+        for (i, a) in a.chunks_mut(chunk).enumerate() {
+            scope.spawn(move |_| {
+                for (offset, a) in a.iter_mut().enumerate() {
+                    let idx = i * chunk + offset;
+                    let k = idx % m;
+                    let w = omega.pow(&[idx as u64]);
+
+                    let mut acc = F::zero();
+                    let mut cur = F::one();
+                    for sub in subs.iter() {
+                        let mut t = sub[k];
+                        t.mul_assign(&cur);
+                        acc.add_assign(&t);
+                        cur.mul_assign(&w);
+                    }
+
+                    *a = acc;
+                }
+            });
+        }
+    });
+}
+
+fn serial_transform<F: PrimeField>(a: &mut [F], omega: &F) {
+    let n = a.len();
+    if n == 1 {
+        return;
+    }
+
+    if n.is_power_of_two() {
+        serial_fft(a, omega, n.trailing_zeros());
+        return;
+    }
+
+    let p = smallest_factor(n);
+    let m = n / p;
+
+    let mut subs: Vec<Vec<F>> = (0..p).map(|j| {
+        a.iter().skip(j).step_by(p).cloned().collect()
+    }).collect();
+
+    let sub_omega = omega.pow(&[p as u64]);
+    for sub in subs.iter_mut() {
+        serial_transform(sub, &sub_omega);
+    }
+
+    let mut w = F::one();
+    for idx in 0..n {
+        let k = idx % m;
+
+        let mut acc = F::zero();
+        let mut cur = F::one();
+        for sub in subs.iter() {
+            let mut t = sub[k];
+            t.mul_assign(&cur);
+            acc.add_assign(&t);
+            cur.mul_assign(&w);
+        }
+
+        a[idx] = acc;
+        w.mul_assign(omega);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::Fr;
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::multicore::Worker;
+    use super::Domain;
+
+    fn evaluate(coeffs: &[Fr], x: &Fr) -> Fr {
+        let mut acc = Fr::zero();
+        for c in coeffs.iter().rev() {
+            acc.mul_assign(x);
+            acc.add_assign(c);
+        }
+
+        acc
+    }
+
+    #[test]
+    fn test_fft_against_naive_evaluation() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        for &size in [1, 2, 16, 1024].iter() {
+            let domain = Domain::<Fr>::new_for_size(size).unwrap();
+            check_domain(rng, &worker, &domain);
+        }
+
+        // 2^28 * 3^2 * 13 * ... divides p - 1 for BN256
+        for &size in [3, 12, 18, 39, 72].iter() {
+            let domain = Domain::<Fr>::new_mixed_radix(size).unwrap();
+            check_domain(rng, &worker, &domain);
+        }
+
+        assert!(Domain::<Fr>::new_mixed_radix(5).is_err());
+    }
+
+    fn check_domain<R: Rng>(rng: &mut R, worker: &Worker, domain: &Domain<Fr>) {
+        let size = domain.size;
+
+        assert_eq!(domain.generator.pow(&[size]), Fr::one());
+        for p in 2..(size + 1) {
+            if size % p == 0 {
+                assert!(domain.generator.pow(&[size / p]) != Fr::one());
+            }
+        }
+
+        let coeffs: Vec<Fr> = (0..size).map(|_| rng.gen()).collect();
+
+        let mut values = coeffs.clone();
+        domain.fft(worker, &mut values);
+
+        for (w, v) in domain.elements().iter().zip(values.iter()) {
+            assert_eq!(evaluate(&coeffs, w), *v);
+        }
+
+        domain.ifft(worker, &mut values);
+        assert_eq!(values, coeffs);
+
+        let offset = Fr::multiplicative_generator();
+        let mut values = coeffs.clone();
+        domain.coset_fft(worker, &mut values, &offset);
+
+        for (w, v) in domain.elements().iter().zip(values.iter()) {
+            let mut x = *w;
+            x.mul_assign(&offset);
+            assert_eq!(evaluate(&coeffs, &x), *v);
+        }
+
+        domain.coset_ifft(worker, &mut values, &offset);
+        assert_eq!(values, coeffs);
+    }
+}
@@ -5,6 +5,13 @@ use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 use rand::{Rng};
 use std::io::{self, Read, Write};
 
+use fields::{from_bytes_le_ct, read_le_ct};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+use secret::{scrub_bytes, scrub_field};
+
 use jubjub::{
     FixedGenerators, 
     JubjubEngine, 
@@ -13,21 +20,24 @@ use jubjub::{
     edwards::Point,
     ToUniform};
 
-use util::{hash_to_scalar, hash_to_scalar_s, sha256_hash_to_scalar};
+use util::{hash_to_scalar, hash_to_scalar_s, sha256_hash_to_scalar, rescue_hash_to_scalar};
 
-use ::constants::{MATTER_EDDSA_BLAKE2S_PERSONALIZATION};
+use ::constants::{MATTER_EDDSA_BLAKE2S_PERSONALIZATION, MATTER_EDDSA_NONCE_PERSONALIZATION};
 
-fn read_scalar<E: JubjubEngine, R: Read>(reader: R) -> io::Result<E::Fs> {
-    let mut s_repr = <E::Fs as PrimeField>::Repr::default();
-    s_repr.read_le(reader)?;
+use rescue::{RescueParams, rescue_hash};
 
-    match E::Fs::from_repr(s_repr) {
-        Ok(s) => Ok(s),
-        Err(_) => Err(io::Error::new(
+use params_digest::ParamsDigest;
+
+fn read_scalar<E: JubjubEngine, R: Read>(reader: R) -> io::Result<E::Fs> {
+    let (s, is_canonical) = read_le_ct::<E::Fs, R>(reader)?;
+    if !is_canonical {
+        return Err(io::Error::new(
             io::ErrorKind::InvalidInput,
             "scalar is not in field",
-        )),
+        ));
     }
+
+    Ok(s)
 }
 
 fn write_scalar<E: JubjubEngine, W: Write>(s: &E::Fs, writer: W) -> io::Result<()> {
@@ -46,6 +56,65 @@ fn sha256_h_star<E: JubjubEngine>(a: &[u8], b: &[u8]) -> E::Fs {
     sha256_hash_to_scalar::<E>(&[], a, b)
 }
 
+/// Rescue hash of R_X || PK_X || M as a scalar.
+fn rescue_h_star<E: JubjubEngine>(
+    params: &RescueParams<E::Fr>,
+    r_x: E::Fr,
+    pk_x: E::Fr,
+    msg: &[E::Fr]
+) -> E::Fs {
+    let mut input = vec![r_x, pk_x];
+    input.extend_from_slice(msg);
+
+    rescue_hash_to_scalar::<E>(params, &input)
+}
+
+/// Coefficients a_i = H(L || X_i || Y_i) of the aggregated key
+/// sum(a_i . PK_i), where L = H(X_1 || Y_1 || ... || X_n || Y_n). Hashing
+/// every key together with all of them stops a rogue key from
+/// cancelling the others.
+pub fn aggregation_coefficients<E: JubjubEngine>(
+    keys: &[PublicKey<E>],
+    rescue_params: &RescueParams<E::Fr>
+) -> Vec<E::Fs> {
+    let coordinates: Vec<(E::Fr, E::Fr)> = keys.iter().map(|k| k.0.into_xy()).collect();
+
+    let mut all = vec![];
+    for &(x, y) in coordinates.iter() {
+        all.push(x);
+        all.push(y);
+    }
+    let l = rescue_hash(rescue_params, &all)[0];
+
+    coordinates.into_iter().map(|(x, y)| {
+        rescue_hash_to_scalar::<E>(rescue_params, &[l, x, y])
+    }).collect()
+}
+
+fn field_message_bytes<E: JubjubEngine>(msg: &[E::Fr]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for el in msg.iter() {
+        el.into_repr().write_le(&mut bytes).expect("has serialized message");
+    }
+
+    bytes
+}
+
+fn random_nonce<E: JubjubEngine, R: Rng>(msg: &[u8], rng: &mut R) -> E::Fs {
+    // T = (l_H + 128) bits of randomness
+    // For H*, l_H = 512 bits
+    let mut t = [0u8; 80];
+    rng.fill_bytes(&mut t[..]);
+
+    // Generate randomness using hash function based on some entropy and the message
+    // Generation of randommess is completely off-chain, so we use BLAKE2b!
+    // r = H*(T || M)
+    let r = h_star::<E>(&t[..], msg);
+    scrub_bytes(&mut t[..]);
+
+    r
+}
+
 #[derive(Copy, Clone)]
 pub struct SerializedSignature {
     rbar: [u8; 32],
@@ -60,6 +129,20 @@ pub struct Signature<E: JubjubEngine> {
 
 pub struct PrivateKey<E: JubjubEngine>(pub E::Fs);
 
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Zeroize for PrivateKey<E> {
+    fn zeroize(&mut self) {
+        scrub_field(&mut self.0);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Drop for PrivateKey<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 #[derive(Clone)]
 pub struct PublicKey<E: JubjubEngine>(pub Point<E, Unknown>);
 
@@ -94,6 +177,37 @@ impl<E: JubjubEngine> PrivateKey<E> {
         write_scalar::<E, W>(&self.0, writer)
     }
 
+    /// Nonce for the signing mode `scheme` (one tag per `sign_*`
+    /// method), RFC 6979 style: r = H*(sk || scheme || P_G, M). The mode
+    /// and the generator are hashed in since reusing a nonce with a
+    /// different challenge reveals the key.
+    fn deterministic_nonce(&self, scheme: u8, msg: &[u8], p_g: FixedGenerators) -> E::Fs {
+        let mut key = [0u8; 34];
+        write_scalar::<E, &mut [u8]>(&self.0, &mut key[..32]).expect("has serialized sk");
+        key[32] = scheme;
+        key[33] = p_g as u8;
+
+        let r = hash_to_scalar::<E>(MATTER_EDDSA_NONCE_PERSONALIZATION, &key[..], msg);
+        scrub_bytes(&mut key[..]);
+
+        r
+    }
+
+    /// This key's share a_i . sk_i of the key aggregated from `keys`,
+    /// with this key at `index`. The shares of all keys add up to the
+    /// private key of `PublicKey::aggregate`.
+    pub fn aggregation_share(
+        &self,
+        keys: &[PublicKey<E>],
+        index: usize,
+        rescue_params: &RescueParams<E::Fr>
+    ) -> Self {
+        let mut share = aggregation_coefficients(keys, rescue_params)[index];
+        share.mul_assign(&self.0);
+
+        PrivateKey(share)
+    }
+
     pub fn sign_raw_message<R: Rng>(
         &self,
         msg: &[u8],
@@ -102,16 +216,33 @@ impl<E: JubjubEngine> PrivateKey<E> {
         params: &E::Params,
         max_message_size: usize,
     ) -> Signature<E> {
-        // T = (l_H + 128) bits of randomness
-        // For H*, l_H = 512 bits
-        let mut t = [0u8; 80];
-        rng.fill_bytes(&mut t[..]);
+        let r = random_nonce::<E, R>(msg, rng);
+
+        self.sign_raw_message_with_nonce(r, msg, p_g, params, max_message_size)
+    }
 
-        // Generate randomness using hash function based on some entropy and the message
-        // Generation of randommess is completely off-chain, so we use BLAKE2b!
-        // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
+    /// Same as `sign_raw_message`, but with the nonce derived from the key and
+    /// the message, so it doesn't need an RNG.
+    pub fn sign_raw_message_deterministic(
+        &self,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+        max_message_size: usize,
+    ) -> Signature<E> {
+        let r = self.deterministic_nonce(0, msg, p_g);
 
+        self.sign_raw_message_with_nonce(r, msg, p_g, params, max_message_size)
+    }
+
+    fn sign_raw_message_with_nonce(
+        &self,
+        r: E::Fs,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+        max_message_size: usize,
+    ) -> Signature<E> {
         let pk = PublicKey::from_private(&self, p_g, params);
         let order_check = pk.0.mul(E::Fs::char(), params);
         assert!(order_check.eq(&Point::zero()));
@@ -152,16 +283,31 @@ impl<E: JubjubEngine> PrivateKey<E> {
         p_g: FixedGenerators,
         params: &E::Params,
     ) -> Signature<E> {
-        // T = (l_H + 128) bits of randomness
-        // For H*, l_H = 512 bits
-        let mut t = [0u8; 80];
-        rng.fill_bytes(&mut t[..]);
+        let r = random_nonce::<E, R>(msg, rng);
+
+        self.sign_schnorr_blake2s_with_nonce(r, msg, p_g, params)
+    }
+
+    /// Same as `sign_schnorr_blake2s`, but with the nonce derived from the key and
+    /// the message, so it doesn't need an RNG.
+    pub fn sign_schnorr_blake2s_deterministic(
+        &self,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
+        let r = self.deterministic_nonce(1, msg, p_g);
 
-        // Generate randomness using hash function based on some entropy and the message
-        // Generation of randommess is completely off-chain, so we use BLAKE2b!
-        // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
+        self.sign_schnorr_blake2s_with_nonce(r, msg, p_g, params)
+    }
 
+    fn sign_schnorr_blake2s_with_nonce(
+        &self,
+        r: E::Fs,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
         let pk = PublicKey::from_private(&self, p_g, params);
         let order_check = pk.0.mul(E::Fs::char(), params);
         assert!(order_check.eq(&Point::zero()));
@@ -203,16 +349,31 @@ impl<E: JubjubEngine> PrivateKey<E> {
         p_g: FixedGenerators,
         params: &E::Params,
     ) -> Signature<E> {
-        // T = (l_H + 128) bits of randomness
-        // For H*, l_H = 512 bits
-        let mut t = [0u8; 80];
-        rng.fill_bytes(&mut t[..]);
+        let r = random_nonce::<E, R>(msg, rng);
+
+        self.musig_sha256_sign_with_nonce(r, msg, p_g, params)
+    }
+
+    /// Same as `musig_sha256_sign`, but with the nonce derived from the key and
+    /// the message, so it doesn't need an RNG.
+    pub fn musig_sha256_sign_deterministic(
+        &self,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
+        let r = self.deterministic_nonce(2, msg, p_g);
 
-        // Generate randomness using hash function based on some entropy and the message
-        // Generation of randommess is completely off-chain, so we use BLAKE2b!
-        // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
+        self.musig_sha256_sign_with_nonce(r, msg, p_g, params)
+    }
 
+    fn musig_sha256_sign_with_nonce(
+        &self,
+        r: E::Fs,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
         let pk = PublicKey::from_private(&self, p_g, params);
         let order_check = pk.0.mul(E::Fs::char(), params);
         assert!(order_check.eq(&Point::zero()));
@@ -257,16 +418,31 @@ impl<E: JubjubEngine> PrivateKey<E> {
         p_g: FixedGenerators,
         params: &E::Params,
     ) -> Signature<E> {
-        // T = (l_H + 128) bits of randomness
-        // For H*, l_H = 512 bits
-        let mut t = [0u8; 80];
-        rng.fill_bytes(&mut t[..]);
+        let r = random_nonce::<E, R>(msg, rng);
 
-        // Generate randomness using hash function based on some entropy and the message
-        // Generation of randommess is completely off-chain, so we use BLAKE2b!
-        // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
+        self.sign_with_nonce(r, msg, p_g, params)
+    }
 
+    /// Same as `sign`, but with the nonce derived from the key and
+    /// the message, so it doesn't need an RNG.
+    pub fn sign_deterministic(
+        &self,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
+        let r = self.deterministic_nonce(3, msg, p_g);
+
+        self.sign_with_nonce(r, msg, p_g, params)
+    }
+
+    fn sign_with_nonce(
+        &self,
+        r: E::Fs,
+        msg: &[u8],
+        p_g: FixedGenerators,
+        params: &E::Params,
+    ) -> Signature<E> {
         let pk = PublicKey::from_private(&self, p_g, params);
         let order_check = pk.0.mul(E::Fs::char(), params);
         assert!(order_check.eq(&Point::zero()));
@@ -302,6 +478,67 @@ impl<E: JubjubEngine> PrivateKey<E> {
         let as_unknown = Point::from(r_g);
         Signature { r: as_unknown, s: s }
     }
+
+    /// Signs a message of field elements of the circuit, which is hashed
+    /// with Rescue instead of being serialized to bytes.
+    pub fn sign_raw_scalar<R: Rng>(
+        &self,
+        msg: &[E::Fr],
+        rng: &mut R,
+        p_g: FixedGenerators,
+        params: &E::Params,
+        rescue_params: &RescueParams<E::Fr>,
+    ) -> Signature<E> {
+        let r = random_nonce::<E, R>(&field_message_bytes::<E>(msg), rng);
+
+        self.sign_raw_scalar_with_nonce(r, msg, p_g, params, rescue_params)
+    }
+
+    /// Same as `sign_raw_scalar`, but with the nonce derived from the key and
+    /// the message, so it doesn't need an RNG. The digest of the Rescue
+    /// parameters is hashed into the nonce as well, as they change the
+    /// challenge of the same message.
+    pub fn sign_raw_scalar_deterministic(
+        &self,
+        msg: &[E::Fr],
+        p_g: FixedGenerators,
+        params: &E::Params,
+        rescue_params: &RescueParams<E::Fr>,
+    ) -> Signature<E> {
+        let mut nonce_input = rescue_params.digest().to_vec();
+        nonce_input.extend(field_message_bytes::<E>(msg));
+
+        let r = self.deterministic_nonce(4, &nonce_input, p_g);
+
+        self.sign_raw_scalar_with_nonce(r, msg, p_g, params, rescue_params)
+    }
+
+    fn sign_raw_scalar_with_nonce(
+        &self,
+        r: E::Fs,
+        msg: &[E::Fr],
+        p_g: FixedGenerators,
+        params: &E::Params,
+        rescue_params: &RescueParams<E::Fr>,
+    ) -> Signature<E> {
+        let pk = PublicKey::from_private(&self, p_g, params);
+        let order_check = pk.0.mul(E::Fs::char(), params);
+        assert!(order_check.eq(&Point::zero()));
+
+        // R = r . P_G
+        let r_g = params.generator(p_g).mul(r, params);
+
+        let (r_g_x, _) = r_g.into_xy();
+        let (pk_x, _) = pk.0.into_xy();
+
+        // S = r + H(R_X || PK_X || M) . sk
+        let mut s = rescue_h_star::<E>(rescue_params, r_g_x, pk_x, msg);
+        s.mul_assign(&self.0);
+        s.add_assign(&r);
+
+        let as_unknown = Point::from(r_g);
+        Signature { r: as_unknown, s: s }
+    }
 }
 
 impl<E: JubjubEngine> PublicKey<E> {
@@ -310,6 +547,19 @@ impl<E: JubjubEngine> PublicKey<E> {
         PublicKey(res)
     }
 
+    /// n-of-n aggregation sum(a_i . PK_i) with the coefficients of
+    /// `aggregation_coefficients`.
+    pub fn aggregate(keys: &[Self], params: &E::Params, rescue_params: &RescueParams<E::Fr>) -> Self {
+        let coefficients = aggregation_coefficients(keys, rescue_params);
+
+        let mut res = Point::zero();
+        for (key, a) in keys.iter().zip(coefficients.into_iter()) {
+            res = res.add(&key.0.mul(a, params), params);
+        }
+
+        PublicKey(res)
+    }
+
     pub fn randomize(&self, alpha: E::Fs, p_g: FixedGenerators, params: &E::Params) -> Self {
         let res: Point<E, Unknown> = params.generator(p_g).mul(alpha, params).into();
         let res = res.add(&self.0, params);
@@ -521,6 +771,38 @@ impl<E: JubjubEngine> PublicKey<E> {
         ).eq(&Point::zero())
     }
 
+    pub fn verify_raw_scalar(
+        &self,
+        msg: &[E::Fr],
+        sig: &Signature<E>,
+        p_g: FixedGenerators,
+        params: &E::Params,
+        rescue_params: &RescueParams<E::Fr>,
+    ) -> bool {
+        // c = H(R_X || PK_X || M)
+        let (r_g_x, _) = sig.r.into_xy();
+        let (pk_x, _) = self.0.into_xy();
+        let c = rescue_h_star::<E>(rescue_params, r_g_x, pk_x, msg);
+
+        // this one is for a simple sanity check. In application purposes the pk will always be in a right group 
+        let order_check_pk = self.0.mul(E::Fs::char(), params);
+        if !order_check_pk.eq(&Point::zero()) {
+            return false;
+        }
+
+        // r is input from user, so always check it!
+        let order_check_r = sig.r.mul(E::Fs::char(), params);
+        if !order_check_r.eq(&Point::zero()) {
+            return false;
+        }
+
+        // 0 = -S . P_G + R + c . vk that requires all points to be in the same group
+        self.0.mul(c, params).add(&sig.r, params).add(
+            &params.generator(p_g).mul(sig.s, params).negate().into(),
+            params
+        ).eq(&Point::zero())
+    }
+
     pub fn verify_serialized(
         &self,
         msg: &[u8],
@@ -537,24 +819,23 @@ impl<E: JubjubEngine> PublicKey<E> {
             Ok(r) => r,
             Err(_) => return false,
         };
-        // S < order(G)
-        // (E::Fs guarantees its representation is in the field)
-        let s = match read_scalar::<E, &[u8]>(&sig.sbar[..]) {
-            Ok(s) => s,
-            Err(_) => return false,
-        };
+        // S < order(G), checked together with the equation
+        let (s, s_is_canonical) = from_bytes_le_ct::<E::Fs>(&sig.sbar[..]);
         // 0 = h_G(-S . P_G + R + c . vk)
-        self.0.mul(c, params).add(&r, params).add(
+        let is_valid = self.0.mul(c, params).add(&r, params).add(
             &params.generator(p_g).mul(s, params).negate().into(),
             params
-        ).mul_by_cofactor(params).eq(&Point::zero())
+        ).mul_by_cofactor(params).eq(&Point::zero());
+
+        is_valid & s_is_canonical
     }
 }
 
 #[cfg(test)]
 mod baby_tests {
-    use bellman::pairing::bn256::Bn256;
+    use bellman::pairing::bn256::{Bn256, Fr};
     use rand::thread_rng;
+    use rescue::generate_params;
 
     use alt_babyjubjub::{AltJubjubBn256, fs::Fs, edwards, FixedGenerators};
 
@@ -769,6 +1050,118 @@ mod baby_tests {
         }
     }
 
+    #[test]
+    fn deterministic_signatures() {
+        let rng = &mut thread_rng();
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+
+        let sk = PrivateKey::<Bn256>(rng.gen());
+        let vk = PublicKey::from_private(&sk, p_g, params);
+
+        let msg1 = b"Foo bar";
+        let msg2 = b"Spam eggs";
+
+        let sig1 = sk.sign_deterministic(msg1, p_g, params);
+        let sig2 = sk.sign_deterministic(msg2, p_g, params);
+        assert!(vk.verify(msg1, &sig1, p_g, params));
+        assert!(vk.verify(msg2, &sig2, p_g, params));
+        assert!(sig1.r != sig2.r);
+
+        let again = sk.sign_deterministic(msg1, p_g, params);
+        assert!(sig1.r == again.r && sig1.s == again.s);
+
+        let sig = sk.sign_schnorr_blake2s_deterministic(msg1, p_g, params);
+        assert!(vk.verify_schnorr_blake2s(msg1, &sig, p_g, params));
+        assert!(sig.r != sig1.r);
+
+        let sig = sk.musig_sha256_sign_deterministic(msg1, p_g, params);
+        assert!(vk.verify_musig_sha256(msg1, &sig, p_g, params));
+        assert!(sig.r != sig1.r);
+
+        let sig = sk.sign_raw_message_deterministic(msg1, p_g, params, 16);
+        assert!(vk.verify_for_raw_message(msg1, &sig, p_g, params, 16));
+        assert!(sig.r != sig1.r);
+    }
+
+    #[test]
+    fn random_signatures_for_raw_scalar() {
+        let rng = &mut thread_rng();
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        for _ in 0..100 {
+            let sk = PrivateKey::<Bn256>(rng.gen());
+            let vk = PublicKey::from_private(&sk, p_g, params);
+
+            let msg1: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+            let msg2: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+
+            let sig1 = sk.sign_raw_scalar(&msg1, rng, p_g, params, &rescue_params);
+            let sig2 = sk.sign_raw_scalar_deterministic(&msg2, p_g, params, &rescue_params);
+
+            assert!(vk.verify_raw_scalar(&msg1, &sig1, p_g, params, &rescue_params));
+            assert!(vk.verify_raw_scalar(&msg2, &sig2, p_g, params, &rescue_params));
+            assert!(!vk.verify_raw_scalar(&msg1, &sig2, p_g, params, &rescue_params));
+            assert!(!vk.verify_raw_scalar(&msg2, &sig1, p_g, params, &rescue_params));
+            assert!(!vk.verify_raw_scalar(&msg1[..2], &sig1, p_g, params, &rescue_params));
+        }
+    }
+
+    #[test]
+    fn deterministic_raw_scalar_nonce_depends_on_rescue_params() {
+        let rng = &mut thread_rng();
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+        let other_rescue_params = generate_params::<Fr>(4, 128);
+
+        let sk = PrivateKey::<Bn256>(rng.gen());
+        let vk = PublicKey::from_private(&sk, p_g, params);
+        let msg: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+
+        let sig1 = sk.sign_raw_scalar_deterministic(&msg, p_g, params, &rescue_params);
+        let sig2 = sk.sign_raw_scalar_deterministic(&msg, p_g, params, &other_rescue_params);
+
+        assert!(vk.verify_raw_scalar(&msg, &sig1, p_g, params, &rescue_params));
+        assert!(vk.verify_raw_scalar(&msg, &sig2, p_g, params, &other_rescue_params));
+        assert!(sig1.r != sig2.r);
+
+        let sig = sk.sign_raw_scalar_deterministic(&msg, p_g, params, &rescue_params);
+        assert!(sig.r == sig1.r);
+    }
+
+    #[test]
+    fn aggregated_signatures() {
+        let rng = &mut thread_rng();
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let sks: Vec<PrivateKey<Bn256>> = (0..3).map(|_| PrivateKey(rng.gen())).collect();
+        let vks: Vec<PublicKey<Bn256>> = sks.iter().map(|sk| PublicKey::from_private(sk, p_g, params)).collect();
+        let vk = PublicKey::aggregate(&vks, params, &rescue_params);
+
+        let mut sk = Fs::zero();
+        for (i, share) in sks.iter().enumerate() {
+            sk.add_assign(&share.aggregation_share(&vks, i, &rescue_params).0);
+        }
+        let sk = PrivateKey::<Bn256>(sk);
+        assert!(PublicKey::from_private(&sk, p_g, params).0 == vk.0);
+
+        let msg = b"Foo bar";
+        let sig = sk.sign(msg, rng, p_g, params);
+        assert!(vk.verify(msg, &sig, p_g, params));
+
+        // the plain sum of the keys is not the aggregated key
+        let mut sum = vks[0].0.clone();
+        for key in vks[1..].iter() {
+            sum = sum.add(&key.0, params);
+        }
+        assert!(sum != vk.0);
+    }
+
     #[test]
     fn get_generator_for_signatures() {
         let rng = &mut thread_rng();
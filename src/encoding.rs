@@ -0,0 +1,353 @@
+//! Canonical JSON and hex encodings of BN256 Groth16 proofs,
+//! verifying keys and public inputs for web clients.
+//!
+//! Field elements are encoded as `0x` prefixed strings of exactly
+//! 64 lowercase hex digits holding the big-endian canonical
+//! representation, non-canonical values are rejected.
+//!
+//! In JSON the points are uncompressed affine coordinates: G1 points
+//! are `[x, y]` and G2 points are `[[x.c0, x.c1], [y.c0, y.c1]]`. The
+//! point at infinity is encoded with all coordinates set to zero. Note
+//! that the EVM precompiles expect `c1` first, see `contract::encode_proof`.
+//!
+//! Hex encoding is the `0x` prefixed binary encoding of `bellman`:
+//! compressed points for proofs and uncompressed points for
+//! verifying keys.
+
+use std::io;
+
+use bellman::pairing::{
+    CurveAffine
+};
+
+use bellman::pairing::bn256::{
+    Bn256,
+    Fq,
+    Fq2,
+    Fr,
+    G1Affine,
+    G2Affine
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::groth16::{
+    Proof,
+    VerifyingKey
+};
+
+use serde_json;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProofJson {
+    pub a: [String; 2],
+    pub b: [[String; 2]; 2],
+    pub c: [String; 2]
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifyingKeyJson {
+    pub alpha_g1: [String; 2],
+    pub beta_g1: [String; 2],
+    pub beta_g2: [[String; 2]; 2],
+    pub gamma_g2: [[String; 2]; 2],
+    pub delta_g1: [String; 2],
+    pub delta_g2: [[String; 2]; 2],
+    pub ic: Vec<[String; 2]>
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Encodes bytes as a `0x` prefixed lowercase hex string.
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(2 + bytes.len() * 2);
+    out.push_str("0x");
+    for b in bytes.iter() {
+        out.push_str(&format!("{:02x}", b));
+    }
+
+    out
+}
+
+/// Decodes a `0x` prefixed hex string, both letter cases are accepted.
+pub fn hex_to_bytes(hex: &str) -> io::Result<Vec<u8>> {
+    if !hex.starts_with("0x") {
+        return Err(invalid_data("hex string must start with 0x"));
+    }
+    let digits = hex[2..].as_bytes();
+    if digits.len() % 2 != 0 {
+        return Err(invalid_data("hex string has odd length"));
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() / 2);
+    for pair in digits.chunks(2) {
+        let pair = ::std::str::from_utf8(pair).map_err(|_| invalid_data("invalid hex digit"))?;
+        let byte = u8::from_str_radix(pair, 16).map_err(|_| invalid_data("invalid hex digit"))?;
+        bytes.push(byte);
+    }
+
+    Ok(bytes)
+}
+
+pub fn field_to_hex<F: PrimeField>(el: &F) -> String {
+    let mut bytes = vec![];
+    el.into_repr().write_be(&mut bytes).expect("must write into a vector");
+
+    bytes_to_hex(&bytes)
+}
+
+pub fn field_from_hex<F: PrimeField>(hex: &str) -> io::Result<F> {
+    if hex.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(invalid_data("field element must be lowercase hex"));
+    }
+    let bytes = hex_to_bytes(hex)?;
+
+    let mut repr = F::Repr::default();
+    if bytes.len() != repr.as_ref().len() * 8 {
+        return Err(invalid_data("field element must have exactly 64 hex digits"));
+    }
+    repr.read_be(&bytes[..])?;
+
+    F::from_repr(repr).map_err(|_| invalid_data("field element is not canonical"))
+}
+
+fn g1_to_json(point: &G1Affine) -> [String; 2] {
+    let (x, y) = if point.is_zero() {
+        (Fq::zero(), Fq::zero())
+    } else {
+        point.into_xy_unchecked()
+    };
+
+    [field_to_hex(&x), field_to_hex(&y)]
+}
+
+fn g1_from_json(coords: &[String; 2]) -> io::Result<G1Affine> {
+    let x: Fq = field_from_hex(&coords[0])?;
+    let y: Fq = field_from_hex(&coords[1])?;
+
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+
+    G1Affine::from_xy_checked(x, y).map_err(|_| invalid_data("point is not on the curve"))
+}
+
+fn fq2_to_json(el: &Fq2) -> [String; 2] {
+    [field_to_hex(&el.c0), field_to_hex(&el.c1)]
+}
+
+fn fq2_from_json(coords: &[String; 2]) -> io::Result<Fq2> {
+    Ok(Fq2 {
+        c0: field_from_hex(&coords[0])?,
+        c1: field_from_hex(&coords[1])?
+    })
+}
+
+fn g2_to_json(point: &G2Affine) -> [[String; 2]; 2] {
+    let (x, y) = if point.is_zero() {
+        (Fq2::zero(), Fq2::zero())
+    } else {
+        point.into_xy_unchecked()
+    };
+
+    [fq2_to_json(&x), fq2_to_json(&y)]
+}
+
+fn g2_from_json(coords: &[[String; 2]; 2]) -> io::Result<G2Affine> {
+    let x = fq2_from_json(&coords[0])?;
+    let y = fq2_from_json(&coords[1])?;
+
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+
+    G2Affine::from_xy_checked(x, y).map_err(|_| invalid_data("point is not on the curve"))
+}
+
+impl ProofJson {
+    pub fn from_proof(proof: &Proof<Bn256>) -> Self {
+        ProofJson {
+            a: g1_to_json(&proof.a),
+            b: g2_to_json(&proof.b),
+            c: g1_to_json(&proof.c)
+        }
+    }
+
+    pub fn into_proof(&self) -> io::Result<Proof<Bn256>> {
+        Ok(Proof {
+            a: g1_from_json(&self.a)?,
+            b: g2_from_json(&self.b)?,
+            c: g1_from_json(&self.c)?
+        })
+    }
+}
+
+impl VerifyingKeyJson {
+    pub fn from_vk(vk: &VerifyingKey<Bn256>) -> Self {
+        VerifyingKeyJson {
+            alpha_g1: g1_to_json(&vk.alpha_g1),
+            beta_g1: g1_to_json(&vk.beta_g1),
+            beta_g2: g2_to_json(&vk.beta_g2),
+            gamma_g2: g2_to_json(&vk.gamma_g2),
+            delta_g1: g1_to_json(&vk.delta_g1),
+            delta_g2: g2_to_json(&vk.delta_g2),
+            ic: vk.ic.iter().map(|p| g1_to_json(p)).collect()
+        }
+    }
+
+    pub fn into_vk(&self) -> io::Result<VerifyingKey<Bn256>> {
+        let mut ic = Vec::with_capacity(self.ic.len());
+        for p in self.ic.iter() {
+            ic.push(g1_from_json(p)?);
+        }
+
+        Ok(VerifyingKey {
+            alpha_g1: g1_from_json(&self.alpha_g1)?,
+            beta_g1: g1_from_json(&self.beta_g1)?,
+            beta_g2: g2_from_json(&self.beta_g2)?,
+            gamma_g2: g2_from_json(&self.gamma_g2)?,
+            delta_g1: g1_from_json(&self.delta_g1)?,
+            delta_g2: g2_from_json(&self.delta_g2)?,
+            ic: ic
+        })
+    }
+}
+
+pub fn proof_to_json(proof: &Proof<Bn256>) -> String {
+    serde_json::to_string(&ProofJson::from_proof(proof)).expect("must serialize")
+}
+
+pub fn proof_from_json(json: &str) -> io::Result<Proof<Bn256>> {
+    let proof: ProofJson = serde_json::from_str(json)?;
+
+    proof.into_proof()
+}
+
+pub fn vk_to_json(vk: &VerifyingKey<Bn256>) -> String {
+    serde_json::to_string(&VerifyingKeyJson::from_vk(vk)).expect("must serialize")
+}
+
+pub fn vk_from_json(json: &str) -> io::Result<VerifyingKey<Bn256>> {
+    let vk: VerifyingKeyJson = serde_json::from_str(json)?;
+
+    vk.into_vk()
+}
+
+/// Encodes public inputs as a JSON array of field elements.
+pub fn inputs_to_json(inputs: &[Fr]) -> String {
+    let inputs: Vec<String> = inputs.iter().map(|el| field_to_hex(el)).collect();
+
+    serde_json::to_string(&inputs).expect("must serialize")
+}
+
+pub fn inputs_from_json(json: &str) -> io::Result<Vec<Fr>> {
+    let inputs: Vec<String> = serde_json::from_str(json)?;
+
+    inputs.iter().map(|el| field_from_hex(el)).collect()
+}
+
+pub fn proof_to_hex(proof: &Proof<Bn256>) -> String {
+    let mut bytes = vec![];
+    proof.write(&mut bytes).expect("must write into a vector");
+
+    bytes_to_hex(&bytes)
+}
+
+pub fn proof_from_hex(hex: &str) -> io::Result<Proof<Bn256>> {
+    let bytes = hex_to_bytes(hex)?;
+
+    Proof::read(&bytes[..])
+}
+
+pub fn vk_to_hex(vk: &VerifyingKey<Bn256>) -> String {
+    let mut bytes = vec![];
+    vk.write(&mut bytes).expect("must write into a vector");
+
+    bytes_to_hex(&bytes)
+}
+
+pub fn vk_from_hex(hex: &str) -> io::Result<VerifyingKey<Bn256>> {
+    let bytes = hex_to_bytes(hex)?;
+
+    VerifyingKey::read(&bytes[..])
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::{Circuit, ConstraintSystem, SynthesisError};
+    use bellman::groth16::{generate_random_parameters, create_random_proof};
+    use super::*;
+
+    struct SquareCircuit {
+        x: Option<Fr>
+    }
+
+    impl Circuit<Bn256> for SquareCircuit {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(|| "y", || {
+                let mut tmp = x_value.ok_or(SynthesisError::AssignmentMissing)?;
+                tmp.square();
+
+                Ok(tmp)
+            })?;
+
+            cs.enforce(|| "square", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let params = generate_random_parameters::<Bn256, _, _>(SquareCircuit { x: None }, rng).unwrap();
+
+        let x: Fr = rng.gen();
+        let mut y = x;
+        y.square();
+
+        let proof = create_random_proof(SquareCircuit { x: Some(x) }, &params, rng).unwrap();
+
+        assert!(proof_from_json(&proof_to_json(&proof)).unwrap() == proof);
+        assert!(proof_from_hex(&proof_to_hex(&proof)).unwrap() == proof);
+        assert!(vk_from_json(&vk_to_json(&params.vk)).unwrap() == params.vk);
+        assert!(vk_from_hex(&vk_to_hex(&params.vk)).unwrap() == params.vk);
+        assert_eq!(inputs_from_json(&inputs_to_json(&[y])).unwrap(), vec![y]);
+
+        // compressed G1, G2 and G1 points
+        assert_eq!(proof_to_hex(&proof).len(), 2 + 2 * (32 + 64 + 32));
+    }
+
+    #[test]
+    fn test_field_encoding() {
+        assert_eq!(
+            field_to_hex(&Fr::one()),
+            "0x0000000000000000000000000000000000000000000000000000000000000001"
+        );
+        assert_eq!(inputs_to_json(&[Fr::from_str("255").unwrap()]),
+            "[\"0x00000000000000000000000000000000000000000000000000000000000000ff\"]"
+        );
+
+        // modulus is not a canonical encoding
+        assert!(field_from_hex::<Fr>("0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001").is_err());
+        // neither is a short one
+        assert!(field_from_hex::<Fr>("0x01").is_err());
+        assert!(field_from_hex::<Fr>("0000000000000000000000000000000000000000000000000000000000000001").is_err());
+        // nor an uppercase one
+        assert!(field_from_hex::<Fr>("0x00000000000000000000000000000000000000000000000000000000000000ff").is_ok());
+        assert!(field_from_hex::<Fr>("0x00000000000000000000000000000000000000000000000000000000000000FF").is_err());
+        assert!(field_from_hex::<Fr>("0X00000000000000000000000000000000000000000000000000000000000000ff").is_err());
+    }
+}
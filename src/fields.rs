@@ -0,0 +1,96 @@
+//! Decoding of field elements without branching on their canonicality.
+//!
+//! `PrimeField::from_repr` errors out on a representation that isn't
+//! below the modulus, so a verifier that rejects such input returns
+//! earlier for it. The functions here always decode an element, zero
+//! for a non-canonical representation, and return a flag next to it,
+//! so that the flags of all the decoded elements can be checked once
+//! together with the rest of the verification.
+
+use bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use std::io::{self, Read};
+
+/// 1 if `a < b` and 0 otherwise, for little-endian limbs of the same
+/// length, in constant time.
+fn lt_ct(a: &[u64], b: &[u64]) -> u64 {
+    assert_eq!(a.len(), b.len());
+
+    let mut borrow = 0u64;
+    for (a, b) in a.iter().zip(b.iter()) {
+        let tmp = (*a as u128).wrapping_sub(*b as u128).wrapping_sub(borrow as u128);
+        borrow = (tmp >> 127) as u64;
+    }
+
+    borrow
+}
+
+/// Decodes `repr`, returning zero and `false` if it isn't canonical.
+pub fn from_repr_ct<F: PrimeField>(repr: F::Repr) -> (F, bool) {
+    let is_canonical = lt_ct(repr.as_ref(), F::char().as_ref());
+    let mask = 0u64.wrapping_sub(is_canonical);
+
+    let mut masked = F::Repr::default();
+    for (m, r) in masked.as_mut().iter_mut().zip(repr.as_ref().iter()) {
+        *m = *r & mask;
+    }
+
+    (F::from_repr(masked).expect("is canonical or zero"), is_canonical == 1)
+}
+
+/// Reads a little-endian representation and decodes it with
+/// `from_repr_ct`. Only an error of the reader is returned as such.
+pub fn read_le_ct<F: PrimeField, R: Read>(reader: R) -> io::Result<(F, bool)> {
+    let mut repr = F::Repr::default();
+    repr.read_le(reader)?;
+
+    Ok(from_repr_ct(repr))
+}
+
+/// Same as `read_le_ct` for a byte slice of exactly the size of a
+/// representation.
+pub fn from_bytes_le_ct<F: PrimeField>(bytes: &[u8]) -> (F, bool) {
+    assert_eq!(bytes.len(), F::Repr::default().as_ref().len() * 8);
+
+    read_le_ct(bytes).expect("has the size of a representation")
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+    use bellman::pairing::bn256::{Fr, FrRepr};
+    use super::*;
+
+    #[test]
+    fn test_from_repr_ct() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..100 {
+            let a: Fr = rng.gen();
+            assert_eq!(from_repr_ct::<Fr>(a.into_repr()), (a, true));
+        }
+
+        let mut r_minus_one = Fr::char();
+        r_minus_one.sub_noborrow(&FrRepr::from(1));
+        assert!(from_repr_ct::<Fr>(r_minus_one).1);
+
+        let mut r_plus_one = Fr::char();
+        r_plus_one.add_nocarry(&FrRepr::from(1));
+        for repr in vec![Fr::char(), r_plus_one, FrRepr([!0u64; 4])] {
+            assert!(Fr::from_repr(repr).is_err());
+            assert_eq!(from_repr_ct::<Fr>(repr), (Fr::zero(), false));
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_le_ct() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let a: Fr = rng.gen();
+
+        let mut bytes = vec![];
+        a.into_repr().write_le(&mut bytes).unwrap();
+        assert_eq!(from_bytes_le_ct::<Fr>(&bytes), (a, true));
+
+        assert_eq!(from_bytes_le_ct::<Fr>(&[0xff; 32]), (Fr::zero(), false));
+    }
+}
@@ -0,0 +1,64 @@
+//! Order of the leaves of the committed oracles.
+//!
+//! A leaf holds the values at `x_i` and `-x_i` for `x_i = offset * w^i`,
+//! where `i` is the index of the pair, below the number of leaves. The
+//! layout decides at which leaf of the Merkle tree every pair goes, so
+//! that proofs of provers that store the evaluations in another order
+//! can be verified.
+
+/// Bijection between the indexes of the pairs and the leaves of an
+/// oracle with `num_leaves` leaves, a power of two.
+pub trait OracleLayout {
+    /// Identifies the layout in the parameter digest.
+    const ID: u8;
+
+    fn leaf_index(pair: usize, num_leaves: usize) -> usize;
+
+    fn pair_index(leaf: usize, num_leaves: usize) -> usize;
+}
+
+/// Leaf `i` holds the values at `x_i` and `-x_i`, i.e. the evaluations
+/// in natural order split in half.
+#[derive(Clone, Copy, Debug)]
+pub struct NaturalOrder;
+
+impl OracleLayout for NaturalOrder {
+    const ID: u8 = 0;
+
+    fn leaf_index(pair: usize, _num_leaves: usize) -> usize {
+        pair
+    }
+
+    fn pair_index(leaf: usize, _num_leaves: usize) -> usize {
+        leaf
+    }
+}
+
+/// The evaluations are stored in bit-reversed order and leaf `i` holds
+/// two consecutive ones, so it holds the pair with the bit-reversed
+/// index of `i`.
+#[derive(Clone, Copy, Debug)]
+pub struct BitReversedOrder;
+
+fn bitreverse(mut n: usize, num_leaves: usize) -> usize {
+    assert!(num_leaves.is_power_of_two());
+
+    let mut r = 0;
+    for _ in 0..num_leaves.trailing_zeros() {
+        r = (r << 1) | (n & 1);
+        n >>= 1;
+    }
+    r
+}
+
+impl OracleLayout for BitReversedOrder {
+    const ID: u8 = 1;
+
+    fn leaf_index(pair: usize, num_leaves: usize) -> usize {
+        bitreverse(pair, num_leaves)
+    }
+
+    fn pair_index(leaf: usize, num_leaves: usize) -> usize {
+        bitreverse(leaf, num_leaves)
+    }
+}
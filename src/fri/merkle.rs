@@ -0,0 +1,109 @@
+//! BLAKE2s Merkle trees over rows of field elements, used for the
+//! FRI oracles.
+
+use bellman::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use blake2_rfc::blake2s::Blake2s;
+
+pub const HASH_SIZE: usize = 32;
+
+pub type Hash = [u8; HASH_SIZE];
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+fn finalize(h: Blake2s) -> Hash {
+    let mut res = [0u8; HASH_SIZE];
+    res.copy_from_slice(h.finalize().as_ref());
+
+    res
+}
+
+pub fn hash_leaf<F: PrimeField>(values: &[F]) -> Hash {
+    let mut h = Blake2s::new(HASH_SIZE);
+    h.update(&[LEAF_PREFIX]);
+
+    let mut buf = vec![];
+    for v in values.iter() {
+        v.into_repr().write_be(&mut buf).unwrap();
+    }
+    h.update(&buf);
+
+    finalize(h)
+}
+
+pub fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut h = Blake2s::new(HASH_SIZE);
+    h.update(&[NODE_PREFIX]);
+    h.update(left);
+    h.update(right);
+
+    finalize(h)
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    // nodes[1] is the root, the leaves are nodes[n..2n]
+    nodes: Vec<Hash>
+}
+
+impl MerkleTree {
+    /// Builds the tree over the leaf hashes, the number of leaves
+    /// must be a power of two.
+    pub fn new(leaves: Vec<Hash>) -> Self {
+        let n = leaves.len();
+        assert!(n.is_power_of_two());
+
+        let mut nodes = vec![[0u8; HASH_SIZE]; n];
+        nodes.extend(leaves);
+        for i in (1..n).rev() {
+            nodes[i] = hash_node(&nodes[2 * i], &nodes[2 * i + 1]);
+        }
+
+        Self {
+            nodes
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.nodes.len() / 2
+    }
+
+    pub fn root(&self) -> Hash {
+        self.nodes[1]
+    }
+
+    /// Returns the siblings of the leaf from the bottom up.
+    pub fn path(&self, index: usize) -> Vec<Hash> {
+        assert!(index < self.size());
+
+        let mut res = vec![];
+        let mut i = index + self.size();
+        while i > 1 {
+            res.push(self.nodes[i ^ 1]);
+            i >>= 1;
+        }
+
+        res
+    }
+}
+
+pub fn verify_path(root: &Hash, leaf: &Hash, index: usize, path: &[Hash]) -> bool {
+    if path.len() >= 64 || index >> path.len() != 0 {
+        return false;
+    }
+
+    let mut cur = *leaf;
+    for (i, sibling) in path.iter().enumerate() {
+        cur = if (index >> i) & 1 == 0 {
+            hash_node(&cur, sibling)
+        } else {
+            hash_node(sibling, &cur)
+        };
+    }
+
+    &cur == root
+}
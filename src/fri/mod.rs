@@ -0,0 +1,1036 @@
+//! Transparent polynomial commitment scheme based on FRI.
+//!
+//! A polynomial of degree less than `degree` is committed to by the
+//! Merkle root of its evaluations over a coset of a power of two
+//! domain of size `degree * lde_factor` (its low degree extension).
+//! Every leaf holds the values at `x` and `-x`, so one leaf is opened
+//! per folding round.
+//!
+//! The value `y = f(z)` is proven by running FRI on the quotient
+//! `(f(x) - y) / (x - z)`, whose values are derived by the verifier
+//! from the queried values of `f`, so the quotient isn't committed
+//! separately. The folding stops at a polynomial of degree less than
+//! `final_degree_plus_one`, which is sent in the clear.
+//!
+//! Several claims `f_i(z_i) = y_i` are opened at once by running FRI on
+//! `sum_i gamma^i (f_i(x) - y_i) / (x - z_i)`, so a chain of steps can
+//! defer its claims to an `Accumulator` and check them with a single
+//! low degree test at the end.
+//!
+//! The order of the leaves of the oracles is a `layout::OracleLayout`,
+//! natural by default.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use bellman::multicore::Worker;
+
+use bellman::SynthesisError;
+
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use std::fmt;
+
+use domain::Domain;
+
+use jubjub::serialization::{
+    read_fr,
+    read_vec,
+    write_fr,
+    write_vec
+};
+
+pub mod merkle;
+
+pub mod layout;
+
+pub mod rounds;
+
+use self::merkle::{
+    Hash,
+    HASH_SIZE,
+    MerkleTree,
+    hash_leaf,
+    verify_path
+};
+
+use self::layout::{
+    OracleLayout,
+    NaturalOrder
+};
+
+use self::rounds::FoldingRounds;
+
+use std::marker::PhantomData;
+
+use transcript::{
+    Transcript,
+    Blake2sTranscript,
+    powers
+};
+
+use params_digest::{
+    ParamsDigest,
+    write_usize
+};
+
+const TRANSCRIPT_LABEL: &[u8] = b"sapling-crypto fri pcs";
+
+#[derive(Clone, Copy, Debug)]
+pub struct FriParams {
+    pub lde_factor: usize,
+    pub num_queries: usize,
+    pub final_degree_plus_one: usize
+}
+
+/// Largest log2 of the evaluation domain size that `FriParams::for_security`
+/// accounts for in the field size check.
+pub const MAX_LOG_DOMAIN_SIZE: usize = 32;
+
+impl FriParams {
+    /// Parameters for `security_bits` bits of soundness with the given
+    /// blowup. The bound used is the provable one for list decoding up
+    /// to the Johnson bound, where a query is passed with probability
+    /// at most sqrt(1 / lde_factor), i.e. every query gives
+    /// log2(lde_factor) / 2 bits. The field must also be large enough
+    /// for the folding challenges, which is checked for domains of up to
+    /// 2^MAX_LOG_DOMAIN_SIZE elements.
+    pub fn for_security<F: PrimeField>(
+        security_bits: usize,
+        lde_factor: usize,
+        final_degree_plus_one: usize
+    ) -> Result<Self, SynthesisError> {
+        if !lde_factor.is_power_of_two()
+            || lde_factor < 2
+            || security_bits == 0
+            || (F::CAPACITY as usize) < security_bits + MAX_LOG_DOMAIN_SIZE
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let log_lde_factor = lde_factor.trailing_zeros() as usize;
+        let num_queries = (2 * security_bits + log_lde_factor - 1) / log_lde_factor;
+
+        Ok(FriParams {
+            lde_factor,
+            num_queries,
+            final_degree_plus_one
+        })
+    }
+
+    /// Bits of soundness of the queries, by the bound of `for_security`.
+    pub fn security_bits(&self) -> usize {
+        self.num_queries * (self.lde_factor.trailing_zeros() as usize) / 2
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Commitment {
+    pub root: Hash
+}
+
+/// Prover side data of a committed polynomial.
+#[derive(Clone, Debug)]
+pub struct CommittedPolynomial<F: PrimeField> {
+    pub coeffs: Vec<F>,
+    values: Vec<F>,
+    tree: MerkleTree
+}
+
+/// Opened leaf of one oracle: the values at `x` and `-x`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayerQuery<F: PrimeField> {
+    pub values: [F; 2],
+    pub path: Vec<Hash>
+}
+
+/// One query round, with the leaves of the committed polynomials
+/// and of every folded oracle except the last one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query<F: PrimeField> {
+    pub layers: Vec<LayerQuery<F>>
+}
+
+/// Claim that the committed polynomial takes `value` at `z`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Claim<F: PrimeField> {
+    pub commitment: Commitment,
+    pub z: F,
+    pub value: F
+}
+
+/// Claims deferred by the steps of a chain, checked together by one
+/// batched opening.
+#[derive(Clone, Debug)]
+pub struct Accumulator<F: PrimeField> {
+    claims: Vec<Claim<F>>
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening<F: PrimeField> {
+    pub layer_roots: Vec<Hash>,
+    pub final_coeffs: Vec<F>,
+    pub queries: Vec<Query<F>>
+}
+
+/// Mismatch between the shape of an opening and the parameters it's
+/// verified with, found by `FriPcs::check_opening`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpeningShapeError {
+    NoClaims,
+    LayerRoots { expected: usize, got: usize },
+    FinalCoeffs { expected: usize, got: usize },
+    Queries { expected: usize, got: usize },
+    /// Number of opened oracles of a query, one per claim and one per
+    /// layer root.
+    QueryLayers { query: usize, expected: usize, got: usize },
+    PathLength { query: usize, layer: usize, expected: usize, got: usize }
+}
+
+impl fmt::Display for OpeningShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OpeningShapeError::NoClaims => write!(f, "no claims to open"),
+            OpeningShapeError::LayerRoots { expected, got } => {
+                write!(f, "expected {} layer roots, got {}", expected, got)
+            },
+            OpeningShapeError::FinalCoeffs { expected, got } => {
+                write!(f, "expected {} final coefficients, got {}", expected, got)
+            },
+            OpeningShapeError::Queries { expected, got } => {
+                write!(f, "expected {} queries, got {}", expected, got)
+            },
+            OpeningShapeError::QueryLayers { query, expected, got } => {
+                write!(f, "query {}: expected {} opened oracles, got {}", query, expected, got)
+            },
+            OpeningShapeError::PathLength { query, layer, expected, got } => {
+                write!(f, "query {}, oracle {}: expected a path of {} hashes, got {}", query, layer, expected, got)
+            }
+        }
+    }
+}
+
+/// The leaves of the oracles are ordered by the layout `L`.
+#[derive(Clone, Debug)]
+pub struct FriPcs<F: PrimeField, L: OracleLayout = NaturalOrder> {
+    params: FriParams,
+    degree: usize,
+    domain: Domain<F>,
+    offset: F,
+    num_rounds: usize,
+    _layout: PhantomData<L>
+}
+
+fn fold<F: PrimeField>(values: &[F; 2], x: &F, alpha: &F, two_inv: &F) -> Option<F> {
+    // (a + b) / 2 + alpha * (a - b) / (2x)
+    let mut even = values[0];
+    even.add_assign(&values[1]);
+    even.mul_assign(two_inv);
+
+    let mut odd = values[0];
+    odd.sub_assign(&values[1]);
+    odd.mul_assign(two_inv);
+    odd.mul_assign(&x.inverse()?);
+    odd.mul_assign(alpha);
+
+    even.add_assign(&odd);
+
+    Some(even)
+}
+
+fn quotient<F: PrimeField>(value: &F, x: &F, z: &F, y: &F) -> Option<F> {
+    let mut denominator = *x;
+    denominator.sub_assign(z);
+
+    let mut res = *value;
+    res.sub_assign(y);
+    res.mul_assign(&denominator.inverse()?);
+
+    Some(res)
+}
+
+fn evaluate<F: PrimeField>(coeffs: &[F], x: &F) -> F {
+    let mut acc = F::zero();
+    for c in coeffs.iter().rev() {
+        acc.mul_assign(x);
+        acc.add_assign(c);
+    }
+
+    acc
+}
+
+fn pairs_tree<F: PrimeField, L: OracleLayout>(values: &[F]) -> MerkleTree {
+    let half = values.len() / 2;
+
+    MerkleTree::new((0..half).map(|leaf| {
+        let i = L::pair_index(leaf, half);
+
+        hash_leaf(&[values[i], values[i + half]])
+    }).collect())
+}
+
+fn open_leaf<F: PrimeField, L: OracleLayout>(values: &[F], tree: &MerkleTree, index: usize) -> LayerQuery<F> {
+    let half = values.len() / 2;
+
+    LayerQuery {
+        values: [values[index], values[index + half]],
+        path: tree.path(L::leaf_index(index, half))
+    }
+}
+
+/// Checks the opened leaves of the claims at `leaf` and combines
+/// their quotients at `x` and `-x`.
+fn combine_quotients<F: PrimeField>(
+    claims: &[Claim<F>],
+    layers: &[LayerQuery<F>],
+    leaf: usize,
+    x: &F,
+    gamma: &F
+) -> Option<[F; 2]>
+{
+    let mut minus_x = *x;
+    minus_x.negate();
+
+    let mut result = [F::zero(); 2];
+    for ((claim, layer), power) in claims.iter().zip(layers.iter()).zip(powers(gamma, claims.len())) {
+        if !verify_path(&claim.commitment.root, &hash_leaf(&layer.values), leaf, &layer.path) {
+            return None;
+        }
+
+        for (r, (v, point)) in result.iter_mut().zip(layer.values.iter().zip([*x, minus_x].iter())) {
+            let mut tmp = quotient(v, point, &claim.z, &claim.value)?;
+            tmp.mul_assign(&power);
+            r.add_assign(&tmp);
+        }
+    }
+
+    Some(result)
+}
+
+impl<F: PrimeField, L: OracleLayout> FriPcs<F, L> {
+    /// Creates the scheme for polynomials of degree less than `degree`.
+    /// `degree`, `lde_factor` and `final_degree_plus_one` must be powers
+    /// of two.
+    pub fn new(params: FriParams, degree: usize) -> Result<Self, SynthesisError> {
+        if !degree.is_power_of_two()
+            || !params.lde_factor.is_power_of_two()
+            || params.lde_factor < 2
+            || !params.final_degree_plus_one.is_power_of_two()
+            || params.final_degree_plus_one > degree
+            || params.num_queries == 0
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let domain = Domain::new_for_size((degree * params.lde_factor) as u64)?;
+        let num_rounds = (degree / params.final_degree_plus_one).trailing_zeros() as usize;
+
+        Ok(Self {
+            params,
+            degree,
+            domain,
+            offset: F::multiplicative_generator(),
+            num_rounds,
+            _layout: PhantomData
+        })
+    }
+
+    pub fn params(&self) -> &FriParams {
+        &self.params
+    }
+
+    pub fn commit(&self, worker: &Worker, coeffs: &[F]) -> (Commitment, CommittedPolynomial<F>) {
+        assert!(coeffs.len() <= self.degree);
+
+        let mut values = coeffs.to_vec();
+        self.domain.coset_fft(worker, &mut values, &self.offset);
+
+        let tree = pairs_tree::<F, L>(&values);
+        let commitment = Commitment {
+            root: tree.root()
+        };
+
+        (commitment, CommittedPolynomial {
+            coeffs: coeffs.to_vec(),
+            values,
+            tree
+        })
+    }
+
+    /// Starts the transcript with the claims and draws the batching
+    /// challenge, which is one for a single claim.
+    fn start_transcript(&self, claims: &[Claim<F>]) -> (Blake2sTranscript<F>, F) {
+        let mut transcript = Blake2sTranscript::new(TRANSCRIPT_LABEL);
+        transcript.commit_params(self);
+        for claim in claims.iter() {
+            transcript.commit_bytes(&claim.commitment.root);
+            transcript.commit_field_element(&claim.z);
+            transcript.commit_field_element(&claim.value);
+        }
+
+        let gamma = if claims.len() > 1 {
+            transcript.get_challenge()
+        } else {
+            F::one()
+        };
+
+        (transcript, gamma)
+    }
+
+    /// Returns `f(z)` with the proof of it. `z` must not be in the
+    /// evaluation coset.
+    pub fn open(
+        &self,
+        worker: &Worker,
+        commitment: &Commitment,
+        poly: &CommittedPolynomial<F>,
+        z: F
+    ) -> Result<(F, Opening<F>), SynthesisError>
+    {
+        let claim = Claim {
+            commitment: *commitment,
+            z,
+            value: evaluate(&poly.coeffs, &z)
+        };
+        let opening = self.open_claims(worker, &[claim], &[poly])?;
+
+        Ok((claim.value, opening))
+    }
+
+    /// Opens every polynomial at its point with a single proof,
+    /// returning the claims to verify it against.
+    pub fn open_batch(
+        &self,
+        worker: &Worker,
+        polys: &[(&CommittedPolynomial<F>, F)]
+    ) -> Result<(Vec<Claim<F>>, Opening<F>), SynthesisError>
+    {
+        let claims: Vec<_> = polys.iter().map(|&(poly, z)| {
+            Claim {
+                commitment: Commitment {
+                    root: poly.tree.root()
+                },
+                z,
+                value: evaluate(&poly.coeffs, &z)
+            }
+        }).collect();
+        let polys: Vec<_> = polys.iter().map(|&(poly, _)| poly).collect();
+
+        let opening = self.open_claims(worker, &claims, &polys)?;
+
+        Ok((claims, opening))
+    }
+
+    fn open_claims(
+        &self,
+        worker: &Worker,
+        claims: &[Claim<F>],
+        polys: &[&CommittedPolynomial<F>]
+    ) -> Result<Opening<F>, SynthesisError>
+    {
+        assert!(!claims.is_empty());
+        assert_eq!(claims.len(), polys.len());
+
+        let (transcript, gamma) = self.start_transcript(claims);
+
+        let two_inv = F::from_str("2").unwrap().inverse().unwrap();
+
+        let points = self.points(0);
+        let mut cur = vec![F::zero(); points.len()];
+        for ((claim, poly), power) in claims.iter().zip(polys.iter()).zip(powers(&gamma, claims.len())) {
+            for ((c, v), x) in cur.iter_mut().zip(poly.values.iter()).zip(points.iter()) {
+                let mut tmp = quotient(v, x, &claim.z, &claim.value).ok_or(SynthesisError::DivisionByZero)?;
+                tmp.mul_assign(&power);
+                c.add_assign(&tmp);
+            }
+        }
+
+        let fold_layer = |cur: &[F], round: usize, alpha: &F| -> Vec<F> {
+            let half = cur.len() / 2;
+            let points = self.points(round);
+            (0..half).map(|i| {
+                fold(&[cur[i], cur[i + half]], &points[i], alpha, &two_inv).unwrap()
+            }).collect()
+        };
+
+        let mut oracles: Vec<(Vec<F>, MerkleTree)> = vec![];
+        let mut layer_roots = vec![];
+        let mut rounds = FoldingRounds::new(transcript, self.num_rounds);
+        for round in 0..self.num_rounds.saturating_sub(1) {
+            let (alpha, folded) = rounds.challenge();
+            cur = fold_layer(&cur, round, &alpha);
+
+            let tree = pairs_tree::<F, L>(&cur);
+            rounds = folded.absorb_root(&tree.root());
+            layer_roots.push(tree.root());
+            oracles.push((cur.clone(), tree));
+        }
+
+        let mut transcript = if self.num_rounds > 0 {
+            let (alpha, folded) = rounds.challenge();
+            cur = fold_layer(&cur, self.num_rounds - 1, &alpha);
+            folded.finish()
+        } else {
+            rounds.finish()
+        };
+
+        let mut final_offset = self.offset;
+        for _ in 0..self.num_rounds {
+            final_offset.square();
+        }
+        let final_domain = Domain::new_for_size(cur.len() as u64)?;
+        final_domain.coset_ifft(worker, &mut cur, &final_offset);
+        cur.truncate(self.params.final_degree_plus_one);
+
+        for c in cur.iter() {
+            transcript.commit_field_element(c);
+        }
+
+        let queries = (0..self.params.num_queries).map(|_| {
+            let mut index = L::pair_index(transcript.get_index(points.len() / 2), points.len() / 2);
+
+            let mut layers: Vec<_> = polys.iter().map(|poly| open_leaf::<F, L>(&poly.values, &poly.tree, index)).collect();
+            for &(ref values, ref tree) in oracles.iter() {
+                index %= values.len() / 2;
+                layers.push(open_leaf::<F, L>(values, tree, index));
+            }
+
+            Query {
+                layers
+            }
+        }).collect();
+
+        Ok(Opening {
+            layer_roots,
+            final_coeffs: cur,
+            queries
+        })
+    }
+
+    /// Checks that `value` is the value at `z` of the polynomial
+    /// committed to by `commitment`.
+    pub fn verify(
+        &self,
+        commitment: &Commitment,
+        z: F,
+        value: F,
+        opening: &Opening<F>
+    ) -> bool
+    {
+        let claim = Claim {
+            commitment: *commitment,
+            z,
+            value
+        };
+
+        self.verify_batch(&[claim], opening)
+    }
+
+    /// Checks that the lengths in an opening of `num_claims` claims
+    /// match the parameters, before any hashing.
+    pub fn check_opening(&self, num_claims: usize, opening: &Opening<F>) -> Result<(), OpeningShapeError> {
+        if num_claims == 0 {
+            return Err(OpeningShapeError::NoClaims);
+        }
+
+        let num_layer_roots = self.num_rounds.max(1) - 1;
+        if opening.layer_roots.len() != num_layer_roots {
+            return Err(OpeningShapeError::LayerRoots {
+                expected: num_layer_roots,
+                got: opening.layer_roots.len()
+            });
+        }
+
+        if opening.final_coeffs.len() != self.params.final_degree_plus_one {
+            return Err(OpeningShapeError::FinalCoeffs {
+                expected: self.params.final_degree_plus_one,
+                got: opening.final_coeffs.len()
+            });
+        }
+
+        if opening.queries.len() != self.params.num_queries {
+            return Err(OpeningShapeError::Queries {
+                expected: self.params.num_queries,
+                got: opening.queries.len()
+            });
+        }
+
+        // the trees of the committed polynomials have size / 2 leaves,
+        // every folded oracle half as many as the previous one
+        let log_leaves = self.domain.size.trailing_zeros() as usize - 1;
+        for (i, query) in opening.queries.iter().enumerate() {
+            if query.layers.len() != num_claims + num_layer_roots {
+                return Err(OpeningShapeError::QueryLayers {
+                    query: i,
+                    expected: num_claims + num_layer_roots,
+                    got: query.layers.len()
+                });
+            }
+
+            for (j, layer) in query.layers.iter().enumerate() {
+                let expected = log_leaves - j.saturating_sub(num_claims - 1);
+                if layer.path.len() != expected {
+                    return Err(OpeningShapeError::PathLength {
+                        query: i,
+                        layer: j,
+                        expected,
+                        got: layer.path.len()
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a batched opening of the claims.
+    pub fn verify_batch(&self, claims: &[Claim<F>], opening: &Opening<F>) -> bool {
+        if self.check_opening(claims.len(), opening).is_err() {
+            return false;
+        }
+
+        let (transcript, gamma) = self.start_transcript(claims);
+
+        let two_inv = F::from_str("2").unwrap().inverse().unwrap();
+
+        let mut alphas = vec![];
+        let mut rounds = FoldingRounds::new(transcript, self.num_rounds);
+        for root in opening.layer_roots.iter() {
+            let (alpha, folded) = rounds.challenge();
+            alphas.push(alpha);
+            rounds = folded.absorb_root(root);
+        }
+
+        let mut transcript = if self.num_rounds > 0 {
+            let (alpha, folded) = rounds.challenge();
+            alphas.push(alpha);
+            folded.finish()
+        } else {
+            rounds.finish()
+        };
+
+        for c in opening.final_coeffs.iter() {
+            transcript.commit_field_element(c);
+        }
+
+        let size = self.domain.size as usize;
+        for query in opening.queries.iter() {
+            let mut index = L::pair_index(transcript.get_index(size / 2), size / 2);
+
+            let (claim_layers, folded_layers) = query.layers.split_at(claims.len());
+
+            let mut layer_size = size;
+            let mut expected: Option<F> = None;
+            for round in 0..(folded_layers.len() + 1) {
+                let half = layer_size / 2;
+
+                // position of the value folded in the previous round
+                let position = index;
+                index %= half;
+
+                let x = self.point(round, index);
+                let leaf = L::leaf_index(index, half);
+
+                let values = if round == 0 {
+                    match combine_quotients(claims, claim_layers, leaf, &x, &gamma) {
+                        Some(values) => values,
+                        None => return false
+                    }
+                } else {
+                    let layer = &folded_layers[round - 1];
+                    if !verify_path(&opening.layer_roots[round - 1], &hash_leaf(&layer.values), leaf, &layer.path) {
+                        return false;
+                    }
+
+                    if Some(layer.values[position / half]) != expected {
+                        return false;
+                    }
+
+                    layer.values
+                };
+
+                if round < self.num_rounds {
+                    expected = Some(fold(&values, &x, &alphas[round], &two_inv).unwrap());
+                } else {
+                    // no folding, the committed polynomial has at most
+                    // the final degree
+                    let mut minus_x = x;
+                    minus_x.negate();
+                    let final_ok = evaluate(&opening.final_coeffs, &x) == values[0]
+                        && evaluate(&opening.final_coeffs, &minus_x) == values[1];
+                    if !final_ok {
+                        return false;
+                    }
+                }
+
+                layer_size = half;
+            }
+
+            if self.num_rounds > 0 {
+                let x = self.point(self.num_rounds, index);
+                if Some(evaluate(&opening.final_coeffs, &x)) != expected {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Points `offset^(2^round) * w^(2^round * i)` of the oracle of
+    /// the given round.
+    fn points(&self, round: usize) -> Vec<F> {
+        let size = (self.domain.size as usize) >> round;
+
+        (0..size).map(|i| self.point(round, i)).collect()
+    }
+
+    fn point(&self, round: usize, index: usize) -> F {
+        let mut x = self.offset;
+        let mut generator = self.domain.generator;
+        for _ in 0..round {
+            x.square();
+            generator.square();
+        }
+        x.mul_assign(&generator.pow(&[index as u64]));
+
+        x
+    }
+}
+
+/// The parameters, the degree bound, the field modulus and the
+/// layout. The Merkle trees are binary BLAKE2s trees.
+impl<F: PrimeField, L: OracleLayout> ParamsDigest for FriPcs<F, L> {
+    const TAG: &'static [u8; 8] = b"FriPcs\0\0";
+
+    fn write_body<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        F::char().write_be(&mut *writer)?;
+        write_usize(writer, self.degree)?;
+        write_usize(writer, self.params.lde_factor)?;
+        write_usize(writer, self.params.num_queries)?;
+        write_usize(writer, self.params.final_degree_plus_one)?;
+        write_usize(writer, L::ID as usize)
+    }
+}
+
+impl Commitment {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.root)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut root = [0u8; HASH_SIZE];
+        reader.read_exact(&mut root)?;
+
+        Ok(Self {
+            root
+        })
+    }
+}
+
+impl<F: PrimeField> Accumulator<F> {
+    pub fn new() -> Self {
+        Self {
+            claims: vec![]
+        }
+    }
+
+    /// Defers a claim instead of verifying its opening.
+    pub fn add(&mut self, claim: Claim<F>) {
+        self.claims.push(claim);
+    }
+
+    pub fn claims(&self) -> &[Claim<F>] {
+        &self.claims
+    }
+
+    pub fn len(&self) -> usize {
+        self.claims.len()
+    }
+
+    /// Checks all the deferred claims with an opening made by
+    /// `FriPcs::open_batch` over the same claims in the same order.
+    pub fn verify<L: OracleLayout>(&self, pcs: &FriPcs<F, L>, opening: &Opening<F>) -> bool {
+        pcs.verify_batch(&self.claims, opening)
+    }
+}
+
+fn write_hashes<W: Write>(writer: &mut W, hashes: &[Hash]) -> io::Result<()> {
+    write_vec(writer, hashes, |w, h| w.write_all(h))
+}
+
+fn read_hashes<R: Read>(reader: &mut R) -> io::Result<Vec<Hash>> {
+    read_vec(reader, |r| {
+        let mut h = [0u8; HASH_SIZE];
+        r.read_exact(&mut h)?;
+
+        Ok(h)
+    })
+}
+
+impl<F: PrimeField> Opening<F> {
+    /// Writes the opening with lengths as big-endian `u32` and field
+    /// elements in big-endian canonical form.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_hashes(&mut writer, &self.layer_roots)?;
+        write_vec(&mut writer, &self.final_coeffs, |w, c| write_fr(w, c))?;
+        write_vec(&mut writer, &self.queries, |w, query| {
+            write_vec(w, &query.layers, |w, layer| {
+                write_fr(w, &layer.values[0])?;
+                write_fr(w, &layer.values[1])?;
+                write_hashes(w, &layer.path)
+            })
+        })
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let layer_roots = read_hashes(&mut reader)?;
+        let final_coeffs = read_vec(&mut reader, |r| read_fr(r))?;
+        let queries = read_vec(&mut reader, |r| {
+            let layers = read_vec(r, |r| {
+                let a = read_fr(r)?;
+                let b = read_fr(r)?;
+
+                Ok(LayerQuery {
+                    values: [a, b],
+                    path: read_hashes(r)?
+                })
+            })?;
+
+            Ok(Query {
+                layers
+            })
+        })?;
+
+        Ok(Self {
+            layer_roots,
+            final_coeffs,
+            queries
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::Fr;
+    use bellman::multicore::Worker;
+    use super::*;
+
+    #[test]
+    fn test_commit_open_verify() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let params = FriParams {
+            lde_factor: 4,
+            num_queries: 16,
+            final_degree_plus_one: 2
+        };
+        let pcs = FriPcs::<Fr>::new(params, 64).unwrap();
+
+        let coeffs: Vec<Fr> = (0..64).map(|_| rng.gen()).collect();
+        let (commitment, poly) = pcs.commit(&worker, &coeffs);
+
+        let z: Fr = rng.gen();
+        let (value, opening) = pcs.open(&worker, &commitment, &poly, z).unwrap();
+
+        assert_eq!(value, evaluate(&coeffs, &z));
+        assert!(pcs.verify(&commitment, z, value, &opening));
+
+        // serialization roundtrip
+        let mut buf = vec![];
+        commitment.write(&mut buf).unwrap();
+        opening.write(&mut buf).unwrap();
+
+        let mut reader = &buf[..];
+        assert_eq!(Commitment::read(&mut reader).unwrap(), commitment);
+        assert_eq!(Opening::<Fr>::read(&mut reader).unwrap(), opening);
+
+        // a wrong value is rejected
+        let mut wrong = value;
+        wrong.add_assign(&Fr::one());
+        assert!(!pcs.verify(&commitment, z, wrong, &opening));
+
+        // as well as a modified opening
+        let mut tampered = opening.clone();
+        tampered.final_coeffs[0].add_assign(&Fr::one());
+        assert!(!pcs.verify(&commitment, z, value, &tampered));
+    }
+
+    #[test]
+    fn test_accumulated_claims() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let params = FriParams {
+            lde_factor: 4,
+            num_queries: 16,
+            final_degree_plus_one: 2
+        };
+        let pcs = FriPcs::<Fr>::new(params, 32).unwrap();
+
+        let polys: Vec<_> = (0..3).map(|_| {
+            let coeffs: Vec<Fr> = (0..32).map(|_| rng.gen()).collect();
+            pcs.commit(&worker, &coeffs).1
+        }).collect();
+        let points: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+
+        let to_open: Vec<_> = polys.iter().zip(points.iter()).map(|(p, z)| (p, *z)).collect();
+        let (claims, opening) = pcs.open_batch(&worker, &to_open).unwrap();
+
+        // every step of the chain defers its claim
+        let mut accumulator = Accumulator::new();
+        for (claim, poly) in claims.iter().zip(polys.iter()) {
+            assert_eq!(claim.value, evaluate(&poly.coeffs, &claim.z));
+            accumulator.add(*claim);
+        }
+
+        assert_eq!(accumulator.len(), 3);
+        assert!(accumulator.verify(&pcs, &opening));
+
+        // a single wrong claim invalidates the batch
+        let mut wrong = claims.clone();
+        wrong[1].value.add_assign(&Fr::one());
+        assert!(!pcs.verify_batch(&wrong, &opening));
+
+        // as well as a missing one
+        assert!(!pcs.verify_batch(&claims[..2], &opening));
+    }
+
+    #[test]
+    fn test_high_degree_is_rejected() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        // both schemes use the same evaluation coset of size 256
+        let large = FriPcs::<Fr>::new(FriParams { lde_factor: 4, num_queries: 16, final_degree_plus_one: 1 }, 64).unwrap();
+        let small = FriPcs::<Fr>::new(FriParams { lde_factor: 8, num_queries: 16, final_degree_plus_one: 1 }, 32).unwrap();
+
+        let coeffs: Vec<Fr> = (0..40).map(|_| rng.gen()).collect();
+        let (commitment, poly) = large.commit(&worker, &coeffs);
+
+        let z: Fr = rng.gen();
+        let (value, opening) = large.open(&worker, &commitment, &poly, z).unwrap();
+        assert!(large.verify(&commitment, z, value, &opening));
+
+        // the polynomial can't be opened as one of degree less than 32
+        let (value, opening) = small.open(&worker, &commitment, &poly, z).unwrap();
+        assert!(!small.verify(&commitment, z, value, &opening));
+    }
+
+    #[test]
+    fn test_params_are_bound() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        // openings of both schemes have the same shape
+        let pcs = FriPcs::<Fr>::new(FriParams { lde_factor: 4, num_queries: 16, final_degree_plus_one: 2 }, 32).unwrap();
+        let other = FriPcs::<Fr>::new(FriParams { lde_factor: 8, num_queries: 16, final_degree_plus_one: 2 }, 32).unwrap();
+        assert!(pcs.digest() != other.digest());
+        assert_eq!(pcs.digest(), FriPcs::<Fr>::new(*pcs.params(), 32).unwrap().digest());
+
+        let coeffs: Vec<Fr> = (0..16).map(|_| rng.gen()).collect();
+        let (commitment, poly) = pcs.commit(&worker, &coeffs);
+
+        let z: Fr = rng.gen();
+        let (value, opening) = pcs.open(&worker, &commitment, &poly, z).unwrap();
+        assert!(pcs.verify(&commitment, z, value, &opening));
+        assert!(!other.verify(&commitment, z, value, &opening));
+    }
+
+    #[test]
+    fn test_for_security() {
+        let params = FriParams::for_security::<Fr>(128, 4, 2).unwrap();
+        assert_eq!(params.num_queries, 128);
+        assert_eq!(params.security_bits(), 128);
+
+        let params = FriParams::for_security::<Fr>(100, 8, 2).unwrap();
+        assert_eq!(params.num_queries, 67);
+        assert!(params.security_bits() >= 100);
+
+        assert!(FriParams::for_security::<Fr>(128, 3, 2).is_err());
+        assert!(FriParams::for_security::<Fr>(240, 4, 2).is_err());
+
+        let pcs = FriPcs::<Fr>::new(FriParams::for_security::<Fr>(80, 16, 2).unwrap(), 64).unwrap();
+        assert_eq!(pcs.params().num_queries, 40);
+    }
+
+    #[test]
+    fn test_check_opening() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        let params = FriParams { lde_factor: 4, num_queries: 16, final_degree_plus_one: 2 };
+        let pcs = FriPcs::<Fr>::new(params, 32).unwrap();
+
+        let coeffs: Vec<Fr> = (0..32).map(|_| rng.gen()).collect();
+        let (commitment, poly) = pcs.commit(&worker, &coeffs);
+        let z: Fr = rng.gen();
+        let (value, opening) = pcs.open(&worker, &commitment, &poly, z).unwrap();
+        assert_eq!(pcs.check_opening(1, &opening), Ok(()));
+
+        assert_eq!(pcs.check_opening(0, &opening), Err(OpeningShapeError::NoClaims));
+        assert_eq!(
+            pcs.check_opening(2, &opening),
+            Err(OpeningShapeError::QueryLayers { query: 0, expected: 5, got: 4 })
+        );
+
+        let mut tampered = opening.clone();
+        tampered.layer_roots.pop();
+        assert_eq!(
+            pcs.check_opening(1, &tampered),
+            Err(OpeningShapeError::LayerRoots { expected: 3, got: 2 })
+        );
+
+        let mut tampered = opening.clone();
+        tampered.queries.truncate(3);
+        assert_eq!(
+            pcs.check_opening(1, &tampered),
+            Err(OpeningShapeError::Queries { expected: 16, got: 3 })
+        );
+
+        let mut tampered = opening.clone();
+        tampered.queries[2].layers[3].path.pop();
+        assert_eq!(
+            pcs.check_opening(1, &tampered),
+            Err(OpeningShapeError::PathLength { query: 2, layer: 3, expected: 3, got: 2 })
+        );
+        assert!(!pcs.verify(&commitment, z, value, &tampered));
+    }
+
+    #[test]
+    fn test_bit_reversed_layout() {
+        use super::layout::BitReversedOrder;
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let worker = Worker::new();
+
+        for leaf in 0..16 {
+            let pair = BitReversedOrder::pair_index(leaf, 16);
+            assert_eq!(BitReversedOrder::leaf_index(pair, 16), leaf);
+        }
+        assert_eq!(BitReversedOrder::pair_index(1, 16), 8);
+
+        let params = FriParams { lde_factor: 4, num_queries: 16, final_degree_plus_one: 2 };
+        let pcs = FriPcs::<Fr, BitReversedOrder>::new(params, 32).unwrap();
+        let natural = FriPcs::<Fr>::new(params, 32).unwrap();
+        assert!(pcs.digest() != natural.digest());
+
+        let coeffs: Vec<Fr> = (0..32).map(|_| rng.gen()).collect();
+        let (commitment, poly) = pcs.commit(&worker, &coeffs);
+        assert!(commitment != natural.commit(&worker, &coeffs).0);
+
+        let z: Fr = rng.gen();
+        let (value, opening) = pcs.open(&worker, &commitment, &poly, z).unwrap();
+        assert!(pcs.verify(&commitment, z, value, &opening));
+        assert!(!natural.verify(&commitment, z, value, &opening));
+    }
+}
@@ -0,0 +1,135 @@
+//! Absorption order of the folding rounds.
+//!
+//! The challenge of a round must depend only on the roots of the layers
+//! of the previous rounds: it is drawn after the root of the previous
+//! layer is absorbed and before the root of the layer it folds into.
+//! `FoldingRounds` tracks this as a typestate, so drawing two challenges
+//! in a row or absorbing a root before its challenge doesn't compile.
+//! The last layer is not committed, it is sent as the final polynomial.
+
+use bellman::pairing::ff::PrimeField;
+
+use std::marker::PhantomData;
+
+use transcript::{
+    Transcript,
+    Blake2sTranscript
+};
+
+use super::merkle::Hash;
+
+/// The next call draws the challenge of the current round.
+pub struct AwaitingChallenge;
+
+/// The next call absorbs the root of the layer folded with the last
+/// challenge, or ends the folding after the last round.
+pub struct AwaitingRoot;
+
+pub struct FoldingRounds<F: PrimeField, S> {
+    transcript: Blake2sTranscript<F>,
+    round: usize,
+    num_rounds: usize,
+    _state: PhantomData<S>
+}
+
+impl<F: PrimeField, S> FoldingRounds<F, S> {
+    fn into_state<T>(self, round: usize) -> FoldingRounds<F, T> {
+        FoldingRounds {
+            transcript: self.transcript,
+            round,
+            num_rounds: self.num_rounds,
+            _state: PhantomData
+        }
+    }
+}
+
+impl<F: PrimeField> FoldingRounds<F, AwaitingChallenge> {
+    pub fn new(transcript: Blake2sTranscript<F>, num_rounds: usize) -> Self {
+        FoldingRounds {
+            transcript,
+            round: 0,
+            num_rounds,
+            _state: PhantomData
+        }
+    }
+
+    /// Draws the challenge of the current round.
+    pub fn challenge(mut self) -> (F, FoldingRounds<F, AwaitingRoot>) {
+        assert!(self.round < self.num_rounds, "no folding rounds left");
+
+        let alpha = self.transcript.get_challenge();
+        let round = self.round;
+
+        (alpha, self.into_state(round))
+    }
+
+    /// Ends a folding without rounds.
+    pub fn finish(self) -> Blake2sTranscript<F> {
+        assert_eq!(self.num_rounds, 0, "folding rounds left");
+
+        self.transcript
+    }
+}
+
+impl<F: PrimeField> FoldingRounds<F, AwaitingRoot> {
+    /// Absorbs the root of the layer folded in the current round and
+    /// moves on to the next one.
+    pub fn absorb_root(mut self, root: &Hash) -> FoldingRounds<F, AwaitingChallenge> {
+        assert!(self.round + 1 < self.num_rounds, "the last layer is not committed");
+
+        self.transcript.commit_bytes(root);
+        let round = self.round + 1;
+
+        self.into_state(round)
+    }
+
+    /// Ends the folding after the challenge of the last round.
+    pub fn finish(self) -> Blake2sTranscript<F> {
+        assert_eq!(self.round + 1, self.num_rounds, "folding rounds left");
+
+        self.transcript
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::Fr;
+    use transcript::{Transcript, Blake2sTranscript};
+    use super::*;
+
+    #[test]
+    fn test_matches_interleaved_transcript() {
+        let roots: Vec<Hash> = (0..3u8).map(|i| [i; 32]).collect();
+
+        let mut expected = Blake2sTranscript::<Fr>::new(b"test");
+        let mut expected_alphas = vec![];
+        for round in 0..4 {
+            expected_alphas.push(expected.get_challenge());
+            if round < 3 {
+                expected.commit_bytes(&roots[round]);
+            }
+        }
+
+        let mut rounds = FoldingRounds::new(Blake2sTranscript::<Fr>::new(b"test"), 4);
+        let mut alphas = vec![];
+        for root in roots.iter() {
+            let (alpha, folded) = rounds.challenge();
+            alphas.push(alpha);
+            rounds = folded.absorb_root(root);
+        }
+        let (alpha, folded) = rounds.challenge();
+        alphas.push(alpha);
+        let mut transcript = folded.finish();
+
+        assert_eq!(alphas, expected_alphas);
+        assert_eq!(transcript.get_challenge(), expected.get_challenge());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_root_after_last_round() {
+        let rounds = FoldingRounds::new(Blake2sTranscript::<Fr>::new(b"test"), 1);
+        let (_, folded) = rounds.challenge();
+        folded.absorb_root(&[0; 32]);
+    }
+}
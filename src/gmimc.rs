@@ -0,0 +1,172 @@
+//! GMiMC-erf (https://eprint.iacr.org/2019/397), the generalized MiMC
+//! with an expanding round function, for state widths up to 12.
+//!
+//! A round of the unbalanced Feistel network computes
+//! `f = (x_0 + c)^alpha`, adds it to every other element and rotates
+//! the state left by one:
+//!
+//! ```text
+//! (x_0, x_1, ..., x_{t-1}) -> (x_1 + f, ..., x_{t-1} + f, x_0)
+//! ```
+//!
+//! so a round costs a single S-box whatever the width is, which makes
+//! wide states cheap for absorbing many elements at once.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use rescue::{
+    smallest_exponent,
+    expand_constants,
+    to_decimal
+};
+
+pub const GMIMC_MAX_STATE_WIDTH: usize = 12;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GmimcParams<F: PrimeField> {
+    pub state_width: usize,
+    pub security_level: usize,
+    pub num_rounds: usize,
+    pub alpha: u64,
+    /// One constant per round.
+    pub round_constants: Vec<F>
+}
+
+impl<F: PrimeField> GmimcParams<F> {
+    /// The sponge keeps one element of capacity.
+    pub fn rate(&self) -> usize {
+        self.state_width - 1
+    }
+}
+
+/// The interpolation bound, i.e. the rounds for the degree `alpha^r` to
+/// exceed `2^min(security_level, log p)` times the `t` branches, plus
+/// `2t` rounds for the Feistel network to be fully diffused in both
+/// directions.
+fn get_number_of_rounds<F: PrimeField>(state_width: usize, security_level: usize, alpha: u64) -> usize {
+    let bits = std::cmp::min(security_level, F::NUM_BITS as usize) as f64;
+    let log2_alpha = (alpha as f64).log2();
+    let log2_width = (state_width as f64).log2();
+
+    let interpolation = ((bits + log2_width) / log2_alpha).ceil() as usize;
+
+    interpolation + 2 * state_width
+}
+
+/// Derives the parameters for `state_width` in `2..=12` and the
+/// security level in bits with the smallest valid S-box exponent.
+pub fn generate_params<F: PrimeField>(state_width: usize, security_level: usize) -> GmimcParams<F> {
+    assert!(state_width >= 2 && state_width <= GMIMC_MAX_STATE_WIDTH, "unsupported state width");
+
+    let alpha = smallest_exponent::<F>();
+    let num_rounds = get_number_of_rounds::<F>(state_width, security_level, alpha);
+
+    let seed = format!(
+        "GMiMC-erf({},{},{})",
+        to_decimal(F::char().as_ref()),
+        state_width,
+        security_level
+    );
+
+    GmimcParams {
+        state_width,
+        security_level,
+        num_rounds,
+        alpha,
+        round_constants: expand_constants(&seed, num_rounds)
+    }
+}
+
+pub fn gmimc_permutation<F: PrimeField>(params: &GmimcParams<F>, state: &mut [F]) {
+    assert_eq!(state.len(), params.state_width);
+
+    for c in params.round_constants.iter() {
+        let mut f = state[0];
+        f.add_assign(c);
+        let f = f.pow(&[params.alpha]);
+
+        for s in state[1..].iter_mut() {
+            s.add_assign(&f);
+        }
+        state.rotate_left(1);
+    }
+}
+
+/// Sponge hash with a capacity of one element, padded like
+/// `rescue::rescue_hash`.
+pub fn gmimc_hash<F: PrimeField>(params: &GmimcParams<F>, input: &[F]) -> F {
+    let rate = params.rate();
+
+    let mut input = input.to_vec();
+    input.push(F::one());
+    while input.len() % rate != 0 {
+        input.push(F::zero());
+    }
+
+    let mut state = vec![F::zero(); params.state_width];
+    for chunk in input.chunks(rate) {
+        for (s, el) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(el);
+        }
+        gmimc_permutation(params, &mut state);
+    }
+
+    state[0]
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::pairing::{bn256, bls12_381};
+    use super::*;
+
+    #[test]
+    fn test_round_numbers() {
+        let params = generate_params::<bn256::Fr>(3, 128);
+        assert_eq!(params.alpha, 5);
+        assert_eq!(params.num_rounds, 56 + 6);
+        assert_eq!(params.round_constants.len(), params.num_rounds);
+
+        let params = generate_params::<bls12_381::Fr>(12, 128);
+        assert_eq!(params.num_rounds, 57 + 24);
+    }
+
+    #[test]
+    fn test_round_is_invertible() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut params = generate_params::<bn256::Fr>(4, 128);
+        params.num_rounds = 1;
+        params.round_constants.truncate(1);
+
+        let input: Vec<bn256::Fr> = (0..4).map(|_| rng.gen()).collect();
+        let mut state = input.clone();
+        gmimc_permutation(&params, &mut state);
+
+        // x_0 comes out last and determines f
+        let mut f = state[3];
+        f.add_assign(&params.round_constants[0]);
+        let f = f.pow(&[params.alpha]);
+
+        for i in 0..3 {
+            let mut x = state[i];
+            x.sub_assign(&f);
+            assert_eq!(x, input[i + 1]);
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        let params = generate_params::<bn256::Fr>(12, 128);
+
+        let input: Vec<bn256::Fr> = (0..11).map(|i| bn256::Fr::from_str(&i.to_string()).unwrap()).collect();
+        let h = gmimc_hash(&params, &input);
+
+        assert!(h != gmimc_hash(&params, &input[..10]));
+        assert!(h != gmimc_hash(&params, &[input.clone(), vec![bn256::Fr::zero()]].concat()));
+        assert_eq!(h, gmimc_hash(&params, &input));
+    }
+}
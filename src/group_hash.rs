@@ -10,6 +10,7 @@ use bellman::pairing::ff::{
 
 use tiny_keccak::{Keccak, Hasher};
 use blake2_rfc::blake2s::Blake2s;
+use byteorder::{BigEndian, WriteBytesExt};
 use constants;
 
 pub trait GroupHasher {
@@ -180,6 +181,46 @@ pub fn generic_group_hash<E: JubjubEngine, H: GroupHasher>(
     }
 }
 
+/// Nothing-up-my-sleeve generator for an application domain, so that
+/// applications can make their own independent Pedersen generators.
+///
+/// The generator is the first point found by `generic_group_hash` with
+/// `BlakeHasher` and `DERIVED_GENERATOR_PERSONALIZATION` on the input
+/// len(tag) || tag || i for i = 0, 1, ..., where the length is a
+/// big-endian u32. The length prefix makes the input unambiguous, so
+/// different tags always give independent generators. It is also a
+/// different personalization than the one of the generators in the
+/// Jubjub parameters.
+pub fn derive_generator<E: JubjubEngine>(
+    domain_tag: &[u8],
+    params: &E::Params
+) -> edwards::Point<E, PrimeOrder>
+{
+    assert!(domain_tag.len() <= u32::max_value() as usize);
+
+    let mut tag = vec![];
+    tag.write_u32::<BigEndian>(domain_tag.len() as u32).expect("writing to a vector doesn't fail");
+    tag.extend_from_slice(domain_tag);
+    let i = tag.len();
+    tag.push(0u8);
+
+    loop {
+        let gh = generic_group_hash::<E, BlakeHasher>(
+            &tag,
+            constants::DERIVED_GENERATOR_PERSONALIZATION,
+            params
+        );
+
+        // We don't want to overflow and start reusing generators
+        assert!(tag[i] != u8::max_value());
+        tag[i] += 1;
+
+        if let Some(gh) = gh {
+            break gh;
+        }
+    }
+}
+
 #[test]
 fn test_generic_hash() {
     use bellman::pairing::bn256::Bn256;
@@ -223,4 +264,28 @@ fn blake2s_consistency_test() {
     let reference = hex!("989e1d96f8d977db95b7fcb59d26fe7f66b4e21e84cdb9387b67aa78ebd07ecf");
 
     assert_eq!(reference[..], h[..]);
+}
+
+#[test]
+fn test_derive_generator() {
+    use bellman::pairing::bn256::Bn256;
+    use bellman::pairing::bls12_381::Bls12;
+    use alt_babyjubjub::AltJubjubBn256;
+    use jubjub::{JubjubBls12, FixedGenerators, JubjubParams};
+
+    let params = AltJubjubBn256::new();
+
+    let g = derive_generator::<Bn256>(b"my app", &params);
+    assert!(g == derive_generator::<Bn256>(b"my app", &params));
+    assert!(g != derive_generator::<Bn256>(b"my app 2", &params));
+    assert!(derive_generator::<Bn256>(b"", &params) != derive_generator::<Bn256>(&[0], &params));
+    assert!(g != edwards::Point::zero());
+
+    for &base in [FixedGenerators::SpendingKeyGenerator, FixedGenerators::ValueCommitmentValue].iter() {
+        assert!(g != *params.generator(base));
+    }
+
+    let params = JubjubBls12::new();
+    let g = derive_generator::<Bls12>(b"my app", &params);
+    assert!(g != edwards::Point::zero());
 }
\ No newline at end of file
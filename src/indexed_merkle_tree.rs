@@ -0,0 +1,290 @@
+//! Indexed Merkle tree for sets with cheap non-membership proofs,
+//! e.g. of nullifiers.
+//!
+//! Every leaf holds a value together with the index and value of the
+//! next larger one, so the leaves form a sorted linked list through
+//! the tree. A value is absent iff there is a leaf with a smaller value
+//! whose successor is larger (or the end of the list, marked by a next
+//! value of zero). The tree starts with the zero leaf `(0, 0, 0)`, and
+//! leaves are appended left to right.
+//!
+//! Nodes and leaves are hashed with `poseidon2::poseidon2_compress`,
+//! a leaf as `H(H(value, next_index), next_value)`. Empty slots are
+//! zero.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use poseidon2::{
+    Poseidon2Params,
+    poseidon2_compress
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexedLeaf<F: PrimeField> {
+    pub value: F,
+    pub next_index: u64,
+    pub next_value: F
+}
+
+impl<F: PrimeField> IndexedLeaf<F> {
+    pub fn hash(&self, params: &Poseidon2Params<F>) -> F {
+        let next_index = F::from_repr(F::Repr::from(self.next_index)).unwrap();
+        let tmp = poseidon2_compress(params, &self.value, &next_index);
+
+        poseidon2_compress(params, &tmp, &self.next_value)
+    }
+}
+
+/// Low leaf proving that a value is absent.
+#[derive(Clone, Debug)]
+pub struct NonMembershipWitness<F: PrimeField> {
+    pub low_index: u64,
+    pub low_leaf: IndexedLeaf<F>,
+    pub low_path: Vec<F>
+}
+
+/// Everything the insertion gadget needs: the low leaf before the
+/// update and the path of the new slot after it.
+#[derive(Clone, Debug)]
+pub struct InsertionWitness<F: PrimeField> {
+    pub low: NonMembershipWitness<F>,
+    pub new_index: u64,
+    pub new_path: Vec<F>
+}
+
+pub struct IndexedMerkleTree<'a, F: PrimeField> {
+    params: &'a Poseidon2Params<F>,
+    depth: usize,
+    value_bits: usize,
+    leaves: Vec<IndexedLeaf<F>>,
+    /// Filled nodes of every level starting from the leaf hashes.
+    layers: Vec<Vec<F>>,
+    /// Roots of the empty subtrees of every height.
+    empty: Vec<F>
+}
+
+fn less_than<F: PrimeField>(a: &F, b: &F) -> bool {
+    a.into_repr() < b.into_repr()
+}
+
+impl<'a, F: PrimeField> IndexedMerkleTree<'a, F> {
+    /// Values of the set must be non-zero and fit into `value_bits`,
+    /// the bit length the gadget compares them with.
+    pub fn new(params: &'a Poseidon2Params<F>, depth: usize, value_bits: usize) -> Self {
+        assert!(depth < 64);
+        assert!(value_bits + 1 < F::CAPACITY as usize);
+
+        let mut empty = vec![F::zero()];
+        for i in 0..depth {
+            let node = poseidon2_compress(params, &empty[i], &empty[i]);
+            empty.push(node);
+        }
+
+        let mut tree = Self {
+            params,
+            depth,
+            value_bits,
+            leaves: vec![],
+            layers: vec![vec![]; depth + 1],
+            empty
+        };
+
+        tree.push(IndexedLeaf {
+            value: F::zero(),
+            next_index: 0,
+            next_value: F::zero()
+        });
+
+        tree
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn value_bits(&self) -> usize {
+        self.value_bits
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn leaf(&self, index: u64) -> &IndexedLeaf<F> {
+        &self.leaves[index as usize]
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.depth, 0)
+    }
+
+    fn node(&self, level: usize, index: usize) -> F {
+        self.layers[level].get(index).cloned().unwrap_or(self.empty[level])
+    }
+
+    /// Authentication path of a slot, from the leaf level up.
+    pub fn auth_path(&self, index: u64) -> Vec<F> {
+        let mut index = index as usize;
+
+        (0..self.depth).map(|level| {
+            let sibling = self.node(level, index ^ 1);
+            index >>= 1;
+
+            sibling
+        }).collect()
+    }
+
+    fn set_leaf_hash(&mut self, index: usize, hash: F) {
+        let mut index = index;
+        let mut cur = hash;
+        for level in 0..=self.depth {
+            if self.layers[level].len() == index {
+                self.layers[level].push(cur);
+            } else {
+                self.layers[level][index] = cur;
+            }
+
+            if level < self.depth {
+                let (left, right) = if index & 1 == 0 {
+                    (cur, self.node(level, index + 1))
+                } else {
+                    (self.node(level, index - 1), cur)
+                };
+                cur = poseidon2_compress(self.params, &left, &right);
+                index >>= 1;
+            }
+        }
+    }
+
+    fn push(&mut self, leaf: IndexedLeaf<F>) {
+        assert!(self.leaves.len() < 1 << self.depth, "tree is full");
+
+        let index = self.leaves.len();
+        self.leaves.push(leaf);
+        self.set_leaf_hash(index, leaf.hash(self.params));
+    }
+
+    fn update(&mut self, index: usize, leaf: IndexedLeaf<F>) {
+        self.leaves[index] = leaf;
+        self.set_leaf_hash(index, leaf.hash(self.params));
+    }
+
+    pub fn contains(&self, value: &F) -> bool {
+        self.leaves.iter().any(|leaf| leaf.value == *value)
+    }
+
+    fn fits(&self, value: &F) -> bool {
+        value.into_repr().num_bits() as usize <= self.value_bits
+    }
+
+    /// Finds the leaf to prove the absence of `value` with, or `None`
+    /// if the value is present or out of range.
+    pub fn non_membership_witness(&self, value: &F) -> Option<NonMembershipWitness<F>> {
+        if !self.fits(value) || self.contains(value) {
+            return None;
+        }
+
+        let low_index = self.leaves.iter().position(|leaf| {
+            less_than(&leaf.value, value) && (leaf.next_value.is_zero() || less_than(value, &leaf.next_value))
+        }).expect("the zero leaf is always present");
+
+        Some(NonMembershipWitness {
+            low_index: low_index as u64,
+            low_leaf: self.leaves[low_index],
+            low_path: self.auth_path(low_index as u64)
+        })
+    }
+
+    /// Inserts `value` into the set, returning `None` if it's already
+    /// present or out of range.
+    pub fn insert(&mut self, value: F) -> Option<InsertionWitness<F>> {
+        let low = self.non_membership_witness(&value)?;
+        let new_index = self.leaves.len() as u64;
+
+        let mut updated_low = low.low_leaf;
+        updated_low.next_index = new_index;
+        updated_low.next_value = value;
+        self.update(low.low_index as usize, updated_low);
+
+        let new_path = self.auth_path(new_index);
+        self.push(IndexedLeaf {
+            value,
+            next_index: low.low_leaf.next_index,
+            next_value: low.low_leaf.next_value
+        });
+
+        Some(InsertionWitness {
+            low,
+            new_index,
+            new_path
+        })
+    }
+}
+
+/// Recomputes the root from a leaf hash and its authentication path.
+pub fn root_from_path<F: PrimeField>(params: &Poseidon2Params<F>, leaf_hash: &F, index: u64, path: &[F]) -> F {
+    let mut cur = *leaf_hash;
+    for (level, sibling) in path.iter().enumerate() {
+        cur = if (index >> level) & 1 == 0 {
+            poseidon2_compress(params, &cur, sibling)
+        } else {
+            poseidon2_compress(params, sibling, &cur)
+        };
+    }
+
+    cur
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::ff::Field;
+    use bellman::pairing::bn256::Fr;
+    use poseidon2::generate_params;
+    use circuit::test::fr;
+    use super::*;
+
+    #[test]
+    fn test_sorted_insertion() {
+        let params = generate_params::<Fr>(3);
+        let mut tree = IndexedMerkleTree::new(&params, 4, 64);
+
+        for &x in [30u64, 10, 20, 40].iter() {
+            let root = tree.root();
+            let witness = tree.insert(fr(x)).unwrap();
+
+            // the low leaf was included in the previous root
+            let low = &witness.low;
+            assert_eq!(root_from_path(&params, &low.low_leaf.hash(&params), low.low_index, &low.low_path), root);
+            assert_eq!(root_from_path(&params, &tree.leaf(witness.new_index).hash(&params), witness.new_index, &witness.new_path), tree.root());
+        }
+
+        // the list is sorted: 0 -> 10 -> 20 -> 30 -> 40 -> end
+        let mut index = 0;
+        let mut values = vec![];
+        loop {
+            let leaf = tree.leaf(index);
+            values.push(leaf.value);
+            if leaf.next_value.is_zero() {
+                break;
+            }
+            index = leaf.next_index;
+        }
+        assert_eq!(values, vec![fr(0), fr(10), fr(20), fr(30), fr(40)]);
+
+        assert!(tree.insert(fr(20)).is_none());
+        assert!(tree.non_membership_witness(&fr(0)).is_none());
+        assert_eq!(tree.non_membership_witness(&fr(25)).unwrap().low_leaf.value, fr(20));
+        assert_eq!(tree.non_membership_witness(&fr(50)).unwrap().low_leaf.value, fr(40));
+
+        // out of range
+        let mut large = Fr::one();
+        for _ in 0..64 {
+            large.double();
+        }
+        assert!(tree.non_membership_witness(&large).is_none());
+    }
+}
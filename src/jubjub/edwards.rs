@@ -11,7 +11,8 @@ use super::{
     JubjubParams,
     Unknown,
     PrimeOrder,
-    montgomery
+    montgomery,
+    serialization
 };
 
 use rand::{
@@ -20,6 +21,8 @@ use rand::{
 
 use std::marker::PhantomData;
 
+use fields::from_repr_ct;
+
 use std::io::{
     self,
     Write,
@@ -105,17 +108,19 @@ impl<E: JubjubEngine> Point<E, Unknown> {
         let x_sign = (y_repr.as_ref()[3] >> 63) == 1;
         y_repr.as_mut()[3] &= 0x7fffffffffffffff;
 
-        match E::Fr::from_repr(y_repr) {
-            Ok(y) => {
-                match Self::get_for_y(y, x_sign, params) {
-                    Some(p) => Ok(p),
-                    None => {
-                        Err(io::Error::new(io::ErrorKind::InvalidInput, "not on curve"))
-                    }
-                }
-            },
-            Err(_) => {
-                Err(io::Error::new(io::ErrorKind::InvalidInput, "y is not in field"))
+        // a non-canonical y decodes to zero and is only rejected after
+        // the point is recovered, not before
+        let (y, y_is_canonical) = from_repr_ct::<E::Fr>(y_repr);
+        let p = Self::get_for_y(y, x_sign, params);
+
+        if !y_is_canonical {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "y is not in field"));
+        }
+
+        match p {
+            Some(p) => Ok(p),
+            None => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "not on curve"))
             }
         }
     }
@@ -259,6 +264,34 @@ impl<E: JubjubEngine, Subgroup> Point<E, Subgroup> {
         y_repr.write_le(writer)
     }
 
+    /// Writes the extended coordinates as they are, used to cache
+    /// the precomputed tables of the parameters.
+    pub(crate) fn write_raw<W: Write>(
+        &self,
+        writer: &mut W
+    ) -> io::Result<()>
+    {
+        serialization::write_fr(writer, &self.x)?;
+        serialization::write_fr(writer, &self.y)?;
+        serialization::write_fr(writer, &self.t)?;
+        serialization::write_fr(writer, &self.z)
+    }
+
+    /// Reads the coordinates written by `write_raw`. The point is
+    /// not checked to be on the curve or in the subgroup.
+    pub(crate) fn read_raw<R: Read>(
+        reader: &mut R
+    ) -> io::Result<Self>
+    {
+        Ok(Point {
+            x: serialization::read_fr(reader)?,
+            y: serialization::read_fr(reader)?,
+            t: serialization::read_fr(reader)?,
+            z: serialization::read_fr(reader)?,
+            _marker: PhantomData
+        })
+    }
+
     /// Convert from a Montgomery point
     pub fn from_montgomery(
         m: &montgomery::Point<E, Subgroup>,
@@ -36,6 +36,14 @@ use bellman::pairing::bls12_381::{
     Fr
 };
 
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use std::sync::{Arc, Once};
+
 /// This is an implementation of the twisted Edwards Jubjub curve.
 pub mod edwards;
 
@@ -46,6 +54,8 @@ pub mod montgomery;
 /// This is an implementation of the scalar field for Jubjub.
 pub mod fs;
 
+pub(crate) mod serialization;
+
 #[cfg(test)]
 pub mod tests;
 
@@ -137,6 +147,30 @@ pub trait JubjubParams<E: JubjubEngine>: Sized + Send + Sync + Clone {
     fn pedersen_hash_exp_window_size(&self) -> u32;
 }
 
+/// Returns the parameters for BLS12-381, shared by all callers
+/// and computed on the first call. With the `embedded_params`
+/// feature they are loaded from the embedded tables instead.
+pub fn params() -> Arc<JubjubBls12> {
+    static INIT: Once = Once::new();
+    // only written by `call_once`, which completes before any read
+    static mut INSTANCE: Option<Arc<JubjubBls12>> = None;
+
+    INIT.call_once(|| {
+        #[cfg(feature = "embedded_params")]
+        let params = JubjubBls12::embedded().expect("embedded parameters are corrupted");
+        #[cfg(not(feature = "embedded_params"))]
+        let params = JubjubBls12::new();
+
+        unsafe {
+            INSTANCE = Some(Arc::new(params));
+        }
+    });
+
+    unsafe {
+        (*::std::ptr::addr_of!(INSTANCE)).clone().unwrap()
+    }
+}
+
 impl JubjubEngine for Bls12 {
     type Fs = self::fs::Fs;
     type Params = JubjubBls12;
@@ -408,6 +442,68 @@ impl JubjubBls12 {
 
         tmp_params
     }
+
+    /// Serializes the parameters together with the precomputed tables,
+    /// so that they can be loaded with `read` instead of recomputed.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut body = vec![];
+        serialization::write_fr(&mut body, &self.edwards_d)?;
+        serialization::write_fr(&mut body, &self.montgomery_a)?;
+        serialization::write_fr(&mut body, &self.montgomery_2a)?;
+        serialization::write_fr(&mut body, &self.scale)?;
+        serialization::write_points(&mut body, &self.pedersen_hash_generators)?;
+        serialization::write_vec(&mut body, &self.pedersen_hash_exp, |w, tables| {
+            serialization::write_vec(w, tables, |w, table| serialization::write_points(w, table))
+        })?;
+        serialization::write_vec(&mut body, &self.pedersen_circuit_generators, |w, windows| {
+            serialization::write_vec(w, windows, |w, coeffs| serialization::write_coordinates(w, coeffs))
+        })?;
+        serialization::write_points(&mut body, &self.fixed_base_generators)?;
+        serialization::write_vec(&mut body, &self.fixed_base_circuit_generators, |w, windows| {
+            serialization::write_vec(w, windows, |w, coeffs| serialization::write_coordinates(w, coeffs))
+        })?;
+
+        serialization::write_checked(writer, b"jubjubbl", &body)
+    }
+
+    /// Reads the parameters written by `write`. The checksum only
+    /// detects corruption: the points aren't validated, so the
+    /// input must come from a trusted source.
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let body = serialization::read_checked(reader, b"jubjubbl")?;
+        let mut reader = &body[..];
+
+        let params = JubjubBls12 {
+            edwards_d: serialization::read_fr(&mut reader)?,
+            montgomery_a: serialization::read_fr(&mut reader)?,
+            montgomery_2a: serialization::read_fr(&mut reader)?,
+            scale: serialization::read_fr(&mut reader)?,
+            pedersen_hash_generators: serialization::read_points(&mut reader)?,
+            pedersen_hash_exp: serialization::read_vec(&mut reader, |r| {
+                serialization::read_vec(r, |r| serialization::read_points(r))
+            })?,
+            pedersen_circuit_generators: serialization::read_vec(&mut reader, |r| {
+                serialization::read_vec(r, |r| serialization::read_coordinates(r))
+            })?,
+            fixed_base_generators: serialization::read_points(&mut reader)?,
+            fixed_base_circuit_generators: serialization::read_vec(&mut reader, |r| {
+                serialization::read_vec(r, |r| serialization::read_coordinates(r))
+            })?,
+        };
+
+        if !reader.is_empty() || params.fixed_base_generators.len() != FixedGenerators::Max as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed parameters"));
+        }
+
+        Ok(params)
+    }
+
+    /// Loads the parameters embedded at build time with the
+    /// `embedded_params` feature, see `build.rs`.
+    #[cfg(feature = "embedded_params")]
+    pub fn embedded() -> io::Result<Self> {
+        Self::read(&include_bytes!(concat!(env!("OUT_DIR"), "/jubjub_bls12.params"))[..])
+    }
 }
 
 #[test]
@@ -444,3 +540,39 @@ fn test_jubjub_bls12_num_generators() {
 
     assert_eq!(params.pedersen_circuit_generators.len(), 5);
 }
+
+#[test]
+fn test_jubjub_bls12_serialization() {
+    let params = JubjubBls12::new();
+
+    let mut serialized = vec![];
+    params.write(&mut serialized).unwrap();
+
+    let deserialized = JubjubBls12::read(&serialized[..]).unwrap();
+
+    assert!(params.edwards_d == deserialized.edwards_d);
+    assert!(params.scale == deserialized.scale);
+    assert!(params.pedersen_hash_generators == deserialized.pedersen_hash_generators);
+    assert!(params.pedersen_hash_exp == deserialized.pedersen_hash_exp);
+    assert!(params.fixed_base_generators == deserialized.fixed_base_generators);
+    assert_eq!(params.pedersen_circuit_generators, deserialized.pedersen_circuit_generators);
+    assert_eq!(params.fixed_base_circuit_generators, deserialized.fixed_base_circuit_generators);
+
+    // corruption is detected
+    let mid = serialized.len() / 2;
+    serialized[mid] ^= 1;
+    assert!(JubjubBls12::read(&serialized[..]).is_err());
+    serialized[mid] ^= 1;
+
+    // and so is a truncated input
+    assert!(JubjubBls12::read(&serialized[..(serialized.len() - 1)]).is_err());
+}
+
+#[test]
+fn test_shared_params() {
+    let a = params();
+    let b = params();
+
+    assert!(Arc::ptr_eq(&a, &b));
+    assert!(a.fixed_base_generators == JubjubBls12::new().fixed_base_generators);
+}
@@ -0,0 +1,178 @@
+//! Binary format of the precomputed parameters, shared by the
+//! parameter sets of the different curves.
+//!
+//! The body is prefixed with a magic identifying the parameter set
+//! and a format version, and followed by a BLAKE2s checksum of
+//! everything before it.
+
+use bellman::pairing::ff::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use byteorder::{
+    BigEndian,
+    ReadBytesExt,
+    WriteBytesExt
+};
+
+use blake2_rfc::blake2s::Blake2s;
+
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use super::{
+    JubjubEngine,
+    edwards
+};
+
+const VERSION: u32 = 1;
+const CHECKSUM_SIZE: usize = 32;
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn checksum(data: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let mut h = Blake2s::new(CHECKSUM_SIZE);
+    h.update(data);
+
+    let mut res = [0u8; CHECKSUM_SIZE];
+    res.copy_from_slice(h.finalize().as_ref());
+
+    res
+}
+
+pub(crate) fn write_checked<W: Write>(
+    mut writer: W,
+    magic: &[u8; 8],
+    body: &[u8]
+) -> io::Result<()>
+{
+    let mut data = Vec::with_capacity(magic.len() + 4 + body.len());
+    data.extend_from_slice(magic);
+    data.write_u32::<BigEndian>(VERSION)?;
+    data.extend_from_slice(body);
+
+    writer.write_all(&data)?;
+    writer.write_all(&checksum(&data))
+}
+
+/// Reads the whole input, checks the envelope and returns the body.
+pub(crate) fn read_checked<R: Read>(
+    mut reader: R,
+    magic: &[u8; 8]
+) -> io::Result<Vec<u8>>
+{
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    if data.len() < magic.len() + 4 + CHECKSUM_SIZE {
+        return Err(invalid_data("parameters are truncated"));
+    }
+
+    let (data, expected) = data.split_at(data.len() - CHECKSUM_SIZE);
+    if &checksum(data)[..] != expected {
+        return Err(invalid_data("checksum mismatch"));
+    }
+
+    if &data[..magic.len()] != &magic[..] {
+        return Err(invalid_data("unexpected parameter set"));
+    }
+
+    let mut version = &data[magic.len()..(magic.len() + 4)];
+    if version.read_u32::<BigEndian>()? != VERSION {
+        return Err(invalid_data("unsupported format version"));
+    }
+
+    Ok(data[(magic.len() + 4)..].to_vec())
+}
+
+pub(crate) fn write_fr<F: PrimeField, W: Write>(
+    writer: &mut W,
+    el: &F
+) -> io::Result<()>
+{
+    el.into_repr().write_be(writer)
+}
+
+pub(crate) fn read_fr<F: PrimeField, R: Read>(
+    reader: &mut R
+) -> io::Result<F>
+{
+    let mut repr = F::Repr::default();
+    repr.read_be(reader)?;
+
+    F::from_repr(repr).map_err(|_| invalid_data("field element is not canonical"))
+}
+
+pub(crate) fn write_vec<T, W: Write, F>(
+    writer: &mut W,
+    items: &[T],
+    mut f: F
+) -> io::Result<()>
+    where F: FnMut(&mut W, &T) -> io::Result<()>
+{
+    writer.write_u32::<BigEndian>(items.len() as u32)?;
+    for item in items.iter() {
+        f(writer, item)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_vec<T, R: Read, F>(
+    reader: &mut R,
+    mut f: F
+) -> io::Result<Vec<T>>
+    where F: FnMut(&mut R) -> io::Result<T>
+{
+    let len = reader.read_u32::<BigEndian>()? as usize;
+    let mut items = Vec::with_capacity(len);
+    for _ in 0..len {
+        items.push(f(reader)?);
+    }
+
+    Ok(items)
+}
+
+pub(crate) fn write_points<E: JubjubEngine, S, W: Write>(
+    writer: &mut W,
+    points: &[edwards::Point<E, S>]
+) -> io::Result<()>
+{
+    write_vec(writer, points, |w, p| p.write_raw(w))
+}
+
+pub(crate) fn read_points<E: JubjubEngine, S, R: Read>(
+    reader: &mut R
+) -> io::Result<Vec<edwards::Point<E, S>>>
+{
+    read_vec(reader, |r| edwards::Point::read_raw(r))
+}
+
+pub(crate) fn write_coordinates<F: PrimeField, W: Write>(
+    writer: &mut W,
+    coords: &[(F, F)]
+) -> io::Result<()>
+{
+    write_vec(writer, coords, |w, &(ref x, ref y)| {
+        write_fr(w, x)?;
+        write_fr(w, y)
+    })
+}
+
+pub(crate) fn read_coordinates<F: PrimeField, R: Read>(
+    reader: &mut R
+) -> io::Result<Vec<(F, F)>>
+{
+    read_vec(reader, |r| {
+        let x = read_fr(r)?;
+        let y = read_fr(r)?;
+
+        Ok((x, y))
+    })
+}
@@ -0,0 +1,117 @@
+//! Chains of key rotations: every key signs the next one together
+//! with the epoch of the rotation, with `eddsa` signatures over field
+//! elements, so that `circuit::key_rotation` can check a chain from a
+//! known key to the current one.
+
+use bellman::pairing::ff::PrimeField;
+
+use jubjub::{
+    FixedGenerators,
+    JubjubEngine
+};
+
+use eddsa::{
+    PrivateKey,
+    PublicKey,
+    Signature
+};
+
+use rescue::RescueParams;
+
+pub const KEY_ROTATION_TAG: u64 = 1;
+
+#[derive(Clone)]
+pub struct Rotation<E: JubjubEngine> {
+    pub new_key: PublicKey<E>,
+    /// Signature of the previous key over `rotation_message`.
+    pub signature: Signature<E>
+}
+
+/// (tag, epoch, x, y) of the new key.
+pub fn rotation_message<E: JubjubEngine>(epoch: u64, new_key: &PublicKey<E>) -> Vec<E::Fr> {
+    let (x, y) = new_key.0.into_xy();
+
+    vec![
+        E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(KEY_ROTATION_TAG)).unwrap(),
+        E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(epoch)).unwrap(),
+        x,
+        y
+    ]
+}
+
+pub fn rotate<E: JubjubEngine>(
+    old_key: &PrivateKey<E>,
+    epoch: u64,
+    new_key: &PublicKey<E>,
+    p_g: FixedGenerators,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>
+) -> Rotation<E> {
+    let signature = old_key.sign_raw_scalar_deterministic(
+        &rotation_message(epoch, new_key),
+        p_g,
+        params,
+        rescue_params
+    );
+
+    Rotation {
+        new_key: new_key.clone(),
+        signature
+    }
+}
+
+/// Checks the rotations starting from `initial` at `first_epoch`,
+/// returning the last key.
+pub fn verify_chain<E: JubjubEngine>(
+    initial: &PublicKey<E>,
+    first_epoch: u64,
+    rotations: &[Rotation<E>],
+    p_g: FixedGenerators,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>
+) -> Option<PublicKey<E>> {
+    let mut key = initial.clone();
+    for (i, rotation) in rotations.iter().enumerate() {
+        let message = rotation_message(first_epoch + i as u64, &rotation.new_key);
+        if !key.verify_raw_scalar(&message, &rotation.signature, p_g, params, rescue_params) {
+            return None;
+        }
+
+        key = rotation.new_key.clone();
+    }
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use alt_babyjubjub::AltJubjubBn256;
+    use rescue::generate_params;
+    use super::*;
+
+    #[test]
+    fn test_rotation_chain() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::SpendingKeyGenerator;
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let keys: Vec<PrivateKey<Bn256>> = (0..4).map(|_| PrivateKey(rng.gen())).collect();
+        let public: Vec<PublicKey<Bn256>> = keys.iter().map(|k| PublicKey::from_private(k, p_g, params)).collect();
+
+        let rotations: Vec<Rotation<Bn256>> = (0..3).map(|i| {
+            rotate(&keys[i], 10 + i as u64, &public[i + 1], p_g, params, &rescue_params)
+        }).collect();
+
+        let last = verify_chain(&public[0], 10, &rotations, p_g, params, &rescue_params).unwrap();
+        assert!(last.0 == public[3].0);
+
+        // epochs are bound
+        assert!(verify_chain(&public[0], 11, &rotations, p_g, params, &rescue_params).is_none());
+        // as well as the order
+        assert!(verify_chain(&public[1], 11, &rotations[1..], p_g, params, &rescue_params).is_some());
+        assert!(verify_chain(&public[0], 10, &rotations[1..], p_g, params, &rescue_params).is_none());
+    }
+}
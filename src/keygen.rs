@@ -0,0 +1,187 @@
+//! Groth16 parameter generation for the built-in circuits.
+//!
+//! The circuits are synthesized without a witness, and the parameters
+//! are bundled with the circuit they were generated for, so that a
+//! bundle for one circuit (or tree depth) can't be loaded for another.
+//!
+//! Parameters generated here have known toxic waste and are only good
+//! for testing, see `setup` for a ceremony.
+
+use std::io::{self, Read, Write};
+
+use bellman::pairing::Engine;
+
+use bellman::pairing::bls12_381::Bls12;
+
+use bellman::groth16::{
+    Parameters,
+    PreparedVerifyingKey,
+    generate_random_parameters,
+    prepare_verifying_key
+};
+
+use bellman::SynthesisError;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use rand::Rng;
+
+use jubjub::JubjubEngine;
+
+use circuit::sapling::{Spend, Output};
+use circuit::sprout::{self, JoinSplit};
+
+const MAGIC: &[u8; 4] = b"SCKP";
+
+/// Version of the serialized bundle.
+pub const PARAMETERS_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitKind {
+    SaplingSpend {
+        depth: u32
+    },
+    SaplingOutput,
+    SproutJoinSplit
+}
+
+impl CircuitKind {
+    fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (tag, depth) = match *self {
+            CircuitKind::SaplingSpend { depth } => (0, depth),
+            CircuitKind::SaplingOutput => (1, 0),
+            CircuitKind::SproutJoinSplit => (2, sprout::TREE_DEPTH as u32)
+        };
+
+        writer.write_u8(tag)?;
+        writer.write_u32::<BigEndian>(depth)
+    }
+
+    fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let tag = reader.read_u8()?;
+        let depth = reader.read_u32::<BigEndian>()?;
+
+        match (tag, depth) {
+            (0, depth) => Ok(CircuitKind::SaplingSpend { depth }),
+            (1, 0) => Ok(CircuitKind::SaplingOutput),
+            (2, depth) if depth as usize == sprout::TREE_DEPTH => Ok(CircuitKind::SproutJoinSplit),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unknown circuit"))
+        }
+    }
+}
+
+/// Parameters together with the circuit they were generated for.
+#[derive(Clone)]
+pub struct ParameterBundle<E: Engine> {
+    pub kind: CircuitKind,
+    pub params: Parameters<E>
+}
+
+impl<E: Engine> ParameterBundle<E> {
+    pub fn prepared_verifying_key(&self) -> PreparedVerifyingKey<E> {
+        prepare_verifying_key(&self.params.vk)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<BigEndian>(PARAMETERS_VERSION)?;
+        self.kind.write(&mut writer)?;
+
+        self.params.write(&mut writer)
+    }
+
+    /// Reads a bundle, failing if it was generated for a different
+    /// circuit than `expected`.
+    pub fn read<R: Read>(mut reader: R, expected: CircuitKind, checked: bool) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a parameter bundle"));
+        }
+
+        let version = reader.read_u32::<BigEndian>()?;
+        if version != PARAMETERS_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported parameters version"));
+        }
+
+        let kind = CircuitKind::read(&mut reader)?;
+        if kind != expected {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "parameters are for a different circuit"));
+        }
+
+        Ok(Self {
+            kind,
+            params: Parameters::read(&mut reader, checked)?
+        })
+    }
+}
+
+pub fn generate_parameters_for_spend<E, R>(
+    params: &E::Params,
+    depth: usize,
+    rng: &mut R
+) -> Result<ParameterBundle<E>, SynthesisError>
+    where E: JubjubEngine, R: Rng
+{
+    let circuit = Spend::<E>::blank(params, depth);
+
+    Ok(ParameterBundle {
+        kind: CircuitKind::SaplingSpend { depth: depth as u32 },
+        params: generate_random_parameters(circuit, rng)?
+    })
+}
+
+pub fn generate_parameters_for_output<E, R>(
+    params: &E::Params,
+    rng: &mut R
+) -> Result<ParameterBundle<E>, SynthesisError>
+    where E: JubjubEngine, R: Rng
+{
+    let circuit = Output::<E>::blank(params);
+
+    Ok(ParameterBundle {
+        kind: CircuitKind::SaplingOutput,
+        params: generate_random_parameters(circuit, rng)?
+    })
+}
+
+pub fn generate_parameters_for_joinsplit<R: Rng>(
+    rng: &mut R
+) -> Result<ParameterBundle<Bls12>, SynthesisError>
+{
+    let circuit = JoinSplit::blank();
+
+    Ok(ParameterBundle {
+        kind: CircuitKind::SproutJoinSplit,
+        params: generate_random_parameters(circuit, rng)?
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, XorShiftRng};
+    use bellman::pairing::bls12_381::Bls12;
+    use jubjub::JubjubBls12;
+    use super::*;
+
+    #[test]
+    fn test_output_parameters_roundtrip() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &JubjubBls12::new();
+
+        let bundle = generate_parameters_for_output::<Bls12, _>(params, rng).unwrap();
+
+        let mut buf = vec![];
+        bundle.write(&mut buf).unwrap();
+
+        let read = ParameterBundle::<Bls12>::read(&buf[..], CircuitKind::SaplingOutput, true).unwrap();
+        assert_eq!(read.kind, CircuitKind::SaplingOutput);
+        assert!(read.params == bundle.params);
+
+        // parameters can't be loaded for another circuit
+        assert!(ParameterBundle::<Bls12>::read(&buf[..], CircuitKind::SaplingSpend { depth: 32 }, true).is_err());
+
+        // or from a future version
+        buf[7] += 1;
+        assert!(ParameterBundle::<Bls12>::read(&buf[..], CircuitKind::SaplingOutput, true).is_err());
+    }
+}
@@ -2,6 +2,9 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+#[cfg(all(target_arch = "wasm32", feature = "multicore"))]
+compile_error!("`multicore` uses threads, build for wasm32 with `default-features = false, features = [\"wasm\"]`");
+
 pub extern crate bellman;
 extern crate blake2_rfc_bellman_edition as blake2_rfc;
 extern crate digest;
@@ -10,6 +13,12 @@ extern crate byteorder;
 extern crate tiny_keccak;
 extern crate sha2;
 
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+
+#[cfg(any(test, feature = "json"))]
+extern crate serde_json;
+
 #[cfg(test)]
 #[macro_use]
 extern crate hex_literal;
@@ -19,6 +28,7 @@ pub mod jubjub;
 pub mod alt_babyjubjub;
 pub mod baby_group_hash;
 pub mod group_hash;
+pub mod blake2s;
 pub mod circuit;
 pub mod baby_pedersen_hash;
 pub mod pedersen_hash;
@@ -28,10 +38,43 @@ pub mod redbabyjubjub;
 pub mod redjubjub;
 pub mod baby_util;
 pub mod util;
+pub mod fields;
+pub mod secret;
 pub mod eddsa;
+pub mod sigma;
+pub mod key_rotation;
 pub mod interpolation;
+pub mod domain;
+pub mod fri;
 pub mod as_waksman;
 pub mod poseidon;
+pub mod rescue;
+pub mod anemoi;
+pub mod poseidon2;
+pub mod gmimc;
+pub mod indexed_merkle_tree;
+pub mod root_history;
+pub mod append_only_tree;
+pub mod mmr;
+pub mod bloom;
+pub mod params_digest;
+pub mod commitment;
+pub mod commit_and_prove;
+pub mod pcd;
+pub mod transcript;
+pub mod contract;
+pub mod mimc;
+pub mod setup;
+pub mod keygen;
+
+#[cfg(feature = "json")]
+pub mod encoding;
+
+#[cfg(feature = "zcash")]
+pub mod zcash;
+
+#[cfg(test)]
+mod test_vectors;
 
 extern crate serde;
 #[macro_use]
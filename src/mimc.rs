@@ -0,0 +1,157 @@
+/// MiMC in the Feistel sponge mode (MiMC-2n/n) with x^5 as the round
+/// function, compatible with circomlib's `MiMCSponge` template.
+use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use bellman::pairing::{Engine};
+use tiny_keccak::{Keccak, Hasher};
+
+/// Number of rounds of circomlib's `MiMCSponge`.
+pub const CIRCOM_MIMC_SPONGE_ROUNDS: usize = 220;
+
+pub struct MimcSpongeParams<E: Engine> {
+    round_constants: Vec<E::Fr>
+}
+
+fn keccak256(input: &[u8]) -> [u8; 32] {
+    let mut h = Keccak::v256();
+    h.update(input);
+    let mut output = [0u8; 32];
+    h.finalize(&mut output);
+
+    output
+}
+
+impl<E: Engine> MimcSpongeParams<E> {
+    /// Constants are produced by iterated Keccak-256 starting from
+    /// the `seed` and reduced modulo the field characteristic,
+    /// the first and the last constants are zero.
+    pub fn new(seed: &[u8], num_rounds: usize) -> Self {
+        assert!(num_rounds >= 2);
+
+        let mut round_constants = vec![E::Fr::zero()];
+        let mut digest = keccak256(seed);
+        for _ in 1..(num_rounds - 1) {
+            digest = keccak256(&digest[..]);
+
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.read_be(&digest[..]).expect("digest is 32 bytes");
+            while repr >= E::Fr::char() {
+                repr.sub_noborrow(&E::Fr::char());
+            }
+
+            round_constants.push(E::Fr::from_repr(repr).unwrap());
+        }
+        round_constants.push(E::Fr::zero());
+
+        Self {
+            round_constants: round_constants
+        }
+    }
+
+    /// Parameters of circomlib's `MiMCSponge`.
+    pub fn new_circom() -> Self {
+        Self::new(b"mimcsponge", CIRCOM_MIMC_SPONGE_ROUNDS)
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.round_constants.len()
+    }
+
+    pub fn round_constant(&self, round: usize) -> &E::Fr {
+        &self.round_constants[round]
+    }
+}
+
+/// Feistel permutation of `(x_l, x_r)` keyed by `k`.
+pub fn mimc_feistel<E: Engine>(
+    params: &MimcSpongeParams<E>,
+    x_l: E::Fr,
+    x_r: E::Fr,
+    k: E::Fr
+) -> (E::Fr, E::Fr)
+{
+    let mut x_l = x_l;
+    let mut x_r = x_r;
+    let num_rounds = params.num_rounds();
+
+    for round in 0..num_rounds {
+        let mut t = x_l;
+        t.add_assign(&k);
+        t.add_assign(params.round_constant(round));
+
+        let mut t5 = t;
+        t5.square();
+        t5.square();
+        t5.mul_assign(&t);
+
+        let mut tmp = x_r;
+        tmp.add_assign(&t5);
+
+        if round < num_rounds - 1 {
+            x_r = x_l;
+            x_l = tmp;
+        } else {
+            x_r = tmp;
+        }
+    }
+
+    (x_l, x_r)
+}
+
+/// Sponge over the Feistel permutation, absorbing one element
+/// per permutation and squeezing `num_outputs` elements.
+pub fn mimc_sponge_hash<E: Engine>(
+    params: &MimcSpongeParams<E>,
+    input: &[E::Fr],
+    k: E::Fr,
+    num_outputs: usize
+) -> Vec<E::Fr>
+{
+    assert!(num_outputs > 0);
+
+    let mut r = E::Fr::zero();
+    let mut c = E::Fr::zero();
+
+    for el in input.iter() {
+        r.add_assign(el);
+        let (new_r, new_c) = mimc_feistel(params, r, c, k);
+        r = new_r;
+        c = new_c;
+    }
+
+    let mut outputs = vec![r];
+    for _ in 1..num_outputs {
+        let (new_r, new_c) = mimc_feistel(params, r, c, k);
+        r = new_r;
+        c = new_c;
+        outputs.push(r);
+    }
+
+    outputs
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+    use super::*;
+
+    #[test]
+    fn test_circom_mimc_sponge_vectors() {
+        // zero values of the Tornado Cash merkle tree, that
+        // hashes pairs of nodes with MiMCSponge(2, 220, 1)
+        let params = MimcSpongeParams::<Bn256>::new_circom();
+
+        let mut zero_repr = <Fr as PrimeField>::Repr::default();
+        zero_repr.read_be(&keccak256(b"tornado")[..]).unwrap();
+        while zero_repr >= Fr::char() {
+            zero_repr.sub_noborrow(&Fr::char());
+        }
+        let zero = Fr::from_repr(zero_repr).unwrap();
+
+        let level_1 = mimc_sponge_hash(&params, &[zero, zero], Fr::zero(), 1)[0];
+        let level_2 = mimc_sponge_hash(&params, &[level_1, level_1], Fr::zero(), 1)[0];
+
+        assert_eq!(format!("{}", level_1.into_repr()), "0x256a6135777eee2fd26f54b8b7037a25439d5235caee224154186d2b8a52e31d");
+        assert_eq!(format!("{}", level_2.into_repr()), "0x1151949895e82ab19924de92c40a3d6f7bcb60d92b00504b8199613683f0c200");
+    }
+}
@@ -0,0 +1,196 @@
+//! Merkle mountain range of field elements, for logs that grow without
+//! a fixed depth, e.g. block headers, with inclusion proofs for
+//! `circuit::mmr`.
+//!
+//! The leaves are split into the perfect binary trees ("mountains") of
+//! the binary decomposition of their number, the highest first. Nodes
+//! are hashed with `poseidon2::poseidon2_compress`. The root of `n`
+//! leaves is `compress(n, bag)`, where `bag` folds the peaks from the
+//! right, `compress(peak_0, compress(peak_1, ...))`, and is zero
+//! without leaves.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use poseidon2::{
+    Poseidon2Params,
+    poseidon2_compress
+};
+
+use util::fr_from_u64;
+
+/// Inclusion proof of the leaf at `index`. The path is the one in its
+/// mountain, padded with zeros to the height of the highest mountain.
+#[derive(Clone, Debug)]
+pub struct MmrProof<F: PrimeField> {
+    pub index: u64,
+    pub path: Vec<F>,
+    pub peaks: Vec<F>
+}
+
+pub struct MerkleMountainRange<'a, F: PrimeField> {
+    params: &'a Poseidon2Params<F>,
+    /// Nodes of every height starting from the leaves.
+    layers: Vec<Vec<F>>
+}
+
+/// Heights of the mountains of `num_leaves` leaves, the highest first.
+pub fn mountain_heights(num_leaves: u64) -> Vec<usize> {
+    (0..64).rev().filter(|&h| (num_leaves >> h) & 1 == 1).collect()
+}
+
+/// Height of the highest mountain, the length of the padded paths.
+pub fn max_height(num_leaves: u64) -> usize {
+    mountain_heights(num_leaves).first().cloned().unwrap_or(0)
+}
+
+/// Position and height of the mountain of the leaf at `index`, the
+/// first one whose leaves reach past it.
+pub fn mountain_of(num_leaves: u64, index: u64) -> (usize, usize) {
+    assert!(index < num_leaves);
+
+    let mut end = 0;
+    mountain_heights(num_leaves).into_iter().enumerate().find(|&(_, h)| {
+        end += 1 << h;
+
+        index < end
+    }).expect("index is below the number of leaves")
+}
+
+/// Root of `num_leaves` leaves with the given peaks.
+pub fn bag_peaks<F: PrimeField>(params: &Poseidon2Params<F>, num_leaves: u64, peaks: &[F]) -> F {
+    assert_eq!(peaks.len(), mountain_heights(num_leaves).len());
+
+    let bag = match peaks.split_last() {
+        Some((last, rest)) => rest.iter().rev().fold(*last, |acc, peak| poseidon2_compress(params, peak, &acc)),
+        None => F::zero()
+    };
+
+    poseidon2_compress(params, &fr_from_u64(num_leaves), &bag)
+}
+
+/// Checks that `leaf` is included in the range of `num_leaves` leaves
+/// with `root`.
+pub fn verify_proof<F: PrimeField>(
+    params: &Poseidon2Params<F>,
+    root: &F,
+    num_leaves: u64,
+    leaf: &F,
+    proof: &MmrProof<F>
+) -> bool
+{
+    let heights = mountain_heights(num_leaves);
+    if proof.index >= num_leaves || proof.peaks.len() != heights.len() || proof.path.len() != max_height(num_leaves) {
+        return false;
+    }
+
+    let (mountain, height) = mountain_of(num_leaves, proof.index);
+
+    let mut cur = *leaf;
+    for (i, sibling) in proof.path[..height].iter().enumerate() {
+        cur = if (proof.index >> i) & 1 == 0 {
+            poseidon2_compress(params, &cur, sibling)
+        } else {
+            poseidon2_compress(params, sibling, &cur)
+        };
+    }
+
+    cur == proof.peaks[mountain] && bag_peaks(params, num_leaves, &proof.peaks) == *root
+}
+
+impl<'a, F: PrimeField> MerkleMountainRange<'a, F> {
+    pub fn new(params: &'a Poseidon2Params<F>) -> Self {
+        MerkleMountainRange {
+            params,
+            layers: vec![vec![]]
+        }
+    }
+
+    pub fn num_leaves(&self) -> u64 {
+        self.layers[0].len() as u64
+    }
+
+    /// Appends a leaf, merging the mountains of equal height.
+    pub fn append(&mut self, leaf: F) {
+        self.layers[0].push(leaf);
+
+        let mut height = 0;
+        while self.layers[height].len() % 2 == 0 {
+            let n = self.layers[height].len();
+            let node = poseidon2_compress(self.params, &self.layers[height][n - 2], &self.layers[height][n - 1]);
+
+            if self.layers.len() == height + 1 {
+                self.layers.push(vec![]);
+            }
+            self.layers[height + 1].push(node);
+            height += 1;
+        }
+    }
+
+    /// Peaks of the mountains, the highest first.
+    pub fn peaks(&self) -> Vec<F> {
+        let n = self.num_leaves();
+
+        mountain_heights(n).into_iter().map(|h| self.layers[h][(n >> h) as usize - 1]).collect()
+    }
+
+    pub fn root(&self) -> F {
+        bag_peaks(self.params, self.num_leaves(), &self.peaks())
+    }
+
+    pub fn prove(&self, index: u64) -> MmrProof<F> {
+        let n = self.num_leaves();
+        assert!(index < n);
+
+        let (_, height) = mountain_of(n, index);
+        let mut path: Vec<F> = (0..height).map(|h| self.layers[h][((index >> h) ^ 1) as usize]).collect();
+        path.resize(max_height(n), F::zero());
+
+        MmrProof {
+            index,
+            path,
+            peaks: self.peaks()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::Fr;
+    use poseidon2::generate_params;
+    use circuit::test::fr;
+    use super::*;
+
+    #[test]
+    fn test_mmr() {
+        let params = generate_params::<Fr>(3);
+        let mut mmr = MerkleMountainRange::new(&params);
+        assert_eq!(mmr.root(), poseidon2_compress(&params, &Fr::zero(), &Fr::zero()));
+
+        for n in 1..12u64 {
+            mmr.append(fr(n));
+            assert_eq!(mmr.peaks().len(), n.count_ones() as usize);
+
+            let root = mmr.root();
+            for index in 0..n {
+                let proof = mmr.prove(index);
+                assert!(verify_proof(&params, &root, n, &fr(index + 1), &proof));
+                assert!(!verify_proof(&params, &root, n, &fr(index + 2), &proof));
+                assert!(!verify_proof(&params, &root, n + 1, &fr(index + 1), &proof));
+            }
+        }
+
+        // 4 + 2 leaves
+        let mut mmr = MerkleMountainRange::new(&params);
+        for i in 1..7 {
+            mmr.append(fr(i));
+        }
+        let h = |a: &Fr, b: &Fr| poseidon2_compress(&params, a, b);
+        let left = h(&h(&fr(1), &fr(2)), &h(&fr(3), &fr(4)));
+        let right = h(&fr(5), &fr(6));
+        assert_eq!(mmr.peaks(), vec![left, right]);
+        assert_eq!(mmr.root(), h(&fr(6), &h(&left, &right)));
+    }
+}
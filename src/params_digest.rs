@@ -0,0 +1,101 @@
+//! Canonical encoding and digest of the parameters a proof depends on,
+//! e.g. the FRI configuration or the constants of an algebraic hash.
+//!
+//! The encoding is a tag identifying the parameter type, a format
+//! version and the fields in a fixed order, with integers and lengths
+//! as big-endian `u32`/`u64` and field elements in big-endian canonical
+//! form. The digest is its BLAKE2s hash, which transcripts absorb with
+//! `Transcript::commit_params` so that challenges, and thus proofs,
+//! are bound to the exact parameters.
+
+use bellman::pairing::ff::PrimeField;
+
+use blake2_rfc::blake2s::Blake2s;
+
+use byteorder::{
+    BigEndian,
+    WriteBytesExt
+};
+
+use std::io::{
+    self,
+    Write
+};
+
+use transcript::field_from_le_bytes;
+
+pub const PARAMS_DIGEST_VERSION: u32 = 1;
+
+pub const PARAMS_DIGEST_PERSONALIZATION: &'static [u8; 8] = b"SCParams";
+
+pub const PARAMS_DIGEST_SIZE: usize = 32;
+
+pub trait ParamsDigest {
+    /// Identifies the parameter type, so that different types with
+    /// the same fields have different digests.
+    const TAG: &'static [u8; 8];
+
+    /// Writes the fields in a fixed order.
+    fn write_body<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    fn write_canonical<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(Self::TAG)?;
+        writer.write_u32::<BigEndian>(PARAMS_DIGEST_VERSION)?;
+
+        self.write_body(&mut writer)
+    }
+
+    fn digest(&self) -> [u8; PARAMS_DIGEST_SIZE] {
+        let mut buf = vec![];
+        self.write_canonical(&mut buf).expect("writing to a vector doesn't fail");
+
+        let mut h = Blake2s::with_params(PARAMS_DIGEST_SIZE, &[], &[], PARAMS_DIGEST_PERSONALIZATION);
+        h.update(&buf);
+
+        let mut res = [0u8; PARAMS_DIGEST_SIZE];
+        res.copy_from_slice(h.finalize().as_ref());
+
+        res
+    }
+
+    /// The digest read as a little-endian integer truncated to
+    /// `F::CAPACITY` bits, the form absorbed by transcripts.
+    fn digest_element<F: PrimeField>(&self) -> F {
+        field_from_le_bytes(&self.digest())
+    }
+}
+
+pub(crate) fn write_usize<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    assert!(value <= u32::max_value() as usize);
+
+    writer.write_u32::<BigEndian>(value as u32)
+}
+
+pub(crate) fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_u64::<BigEndian>(value)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::Fr;
+    use poseidon2;
+    use rescue;
+    use super::*;
+
+    #[test]
+    fn test_digests_differ() {
+        let rescue_params = rescue::generate_params::<Fr>(3, 128);
+        let poseidon2_params = poseidon2::generate_params::<Fr>(3);
+
+        assert_eq!(rescue_params.digest(), rescue::generate_params::<Fr>(3, 128).digest());
+        assert!(rescue_params.digest() != rescue::generate_params::<Fr>(4, 128).digest());
+        assert!(rescue_params.digest() != poseidon2_params.digest());
+
+        let reduced = poseidon2::generate_params_with_rounds::<Fr>(3, 8, 55);
+        assert!(poseidon2_params.digest() != reduced.digest());
+
+        let mut buf = vec![];
+        poseidon2_params.write_canonical(&mut buf).unwrap();
+        assert_eq!(&buf[..8], b"Poseidn2");
+    }
+}
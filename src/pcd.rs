@@ -0,0 +1,210 @@
+//! Scheduling of the proofs of a proof-carrying data graph, whose nodes
+//! are instances of `circuit::pcd::PcdNode`.
+//!
+//! Nodes are added after their predecessors, so the graph is acyclic
+//! and the insertion order is a valid proving order. `levels` groups
+//! the nodes that can be proven in parallel, which `prove_parallel`
+//! does on the threads of a `Worker`.
+
+use bellman::SynthesisError;
+use bellman::multicore::Worker;
+
+pub struct PcdGraph {
+    predecessors: Vec<Vec<usize>>
+}
+
+impl PcdGraph {
+    pub fn new() -> Self {
+        PcdGraph {
+            predecessors: vec![]
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.predecessors.len()
+    }
+
+    /// Adds a node whose incoming messages are the messages of
+    /// `predecessors`, in this order, returning its index.
+    pub fn add_node(&mut self, predecessors: &[usize]) -> usize {
+        let index = self.predecessors.len();
+        for &p in predecessors.iter() {
+            assert!(p < index, "predecessors must be added first");
+        }
+
+        self.predecessors.push(predecessors.to_vec());
+
+        index
+    }
+
+    pub fn predecessors(&self, node: usize) -> &[usize] {
+        &self.predecessors[node]
+    }
+
+    /// Nodes that are not a predecessor of any node, i.e. whose proofs
+    /// are the result of the computation.
+    pub fn sinks(&self) -> Vec<usize> {
+        let mut is_sink = vec![true; self.len()];
+        for predecessors in self.predecessors.iter() {
+            for &p in predecessors.iter() {
+                is_sink[p] = false;
+            }
+        }
+
+        (0..self.len()).filter(|&i| is_sink[i]).collect()
+    }
+
+    /// Groups the nodes by the length of the longest path to them from
+    /// a node without predecessors. All the predecessors of a node are
+    /// in earlier levels.
+    pub fn levels(&self) -> Vec<Vec<usize>> {
+        let mut depths: Vec<usize> = vec![];
+        let mut levels: Vec<Vec<usize>> = vec![];
+
+        for predecessors in self.predecessors.iter() {
+            let depth = predecessors.iter().map(|&p| depths[p] + 1).max().unwrap_or(0);
+            if depth == levels.len() {
+                levels.push(vec![]);
+            }
+
+            levels[depth].push(depths.len());
+            depths.push(depth);
+        }
+
+        levels
+    }
+
+    /// Proves all the nodes level by level. `prove_node` is called with
+    /// the index of the node and the messages and proofs of its
+    /// predecessors, and returns the message and the proof of the node.
+    pub fn prove<P, F>(&self, mut prove_node: F) -> Result<Vec<(Vec<bool>, P)>, SynthesisError>
+        where F: FnMut(usize, &[(&[bool], &P)]) -> Result<(Vec<bool>, P), SynthesisError>
+    {
+        let mut results: Vec<Option<(Vec<bool>, P)>> = (0..self.len()).map(|_| None).collect();
+
+        for level in self.levels() {
+            for node in level {
+                let result = prove_node(node, &self.incoming(node, &results))?;
+                results[node] = Some(result);
+            }
+        }
+
+        Ok(results.into_iter().map(|r| r.unwrap()).collect())
+    }
+
+    /// Same as `prove`, but the nodes of a level are proven in parallel.
+    /// The first error in a level is returned once the whole level is
+    /// done.
+    pub fn prove_parallel<P, F>(&self, worker: &Worker, prove_node: F) -> Result<Vec<(Vec<bool>, P)>, SynthesisError>
+        where P: Send + Sync,
+              F: Fn(usize, &[(&[bool], &P)]) -> Result<(Vec<bool>, P), SynthesisError> + Sync
+    {
+        let mut results: Vec<Option<(Vec<bool>, P)>> = (0..self.len()).map(|_| None).collect();
+
+        for level in self.levels() {
+            let mut proven: Vec<Option<Result<(Vec<bool>, P), SynthesisError>>> = (0..level.len()).map(|_| None).collect();
+
+            {
+                let results = &results;
+                let prove_node = &prove_node;
+
+                worker.scope(level.len(), |scope, chunk| {
+                    for (nodes, proven) in level.chunks(chunk).zip(proven.chunks_mut(chunk)) {
+                        scope.spawn(move |_| {
+                            for (&node, proven) in nodes.iter().zip(proven.iter_mut()) {
+                                *proven = Some(prove_node(node, &self.incoming(node, results)));
+                            }
+                        });
+                    }
+                });
+            }
+
+            for (node, result) in level.into_iter().zip(proven.into_iter()) {
+                results[node] = Some(result.expect("all nodes of the level are proven")?);
+            }
+        }
+
+        Ok(results.into_iter().map(|r| r.unwrap()).collect())
+    }
+
+    fn incoming<'a, P>(&self, node: usize, results: &'a [Option<(Vec<bool>, P)>]) -> Vec<(&'a [bool], &'a P)> {
+        self.predecessors[node].iter().map(|&p| {
+            let &(ref message, ref proof) = results[p].as_ref().expect("is proven in an earlier level");
+
+            (&message[..], proof)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pcd_graph() {
+        // 0   1
+        //  \ / \
+        //   2   3
+        //    \ /
+        //     4
+        let mut graph = PcdGraph::new();
+        let a = graph.add_node(&[]);
+        let b = graph.add_node(&[]);
+        let c = graph.add_node(&[a, b]);
+        let d = graph.add_node(&[b]);
+        let e = graph.add_node(&[c, d]);
+
+        assert_eq!(graph.levels(), vec![vec![a, b], vec![c, d], vec![e]]);
+        assert_eq!(graph.sinks(), vec![e]);
+
+        // messages count the nodes on the paths to the node, proofs are
+        // the order of proving
+        let mut order = 0;
+        let results = graph.prove(|node, incoming: &[(&[bool], &usize)]| {
+            for &(_, &proof) in incoming.iter() {
+                assert!(proof < order);
+            }
+
+            let count = incoming.iter().map(|&(m, _)| m.len()).sum::<usize>() + 1;
+            order += 1;
+
+            assert_eq!(incoming.len(), graph.predecessors(node).len());
+
+            Ok((vec![true; count], order - 1))
+        }).unwrap();
+
+        assert_eq!(results[e].0.len(), 1 + (1 + 1 + 1) + (1 + 1));
+    }
+
+    #[test]
+    fn test_prove_parallel() {
+        let mut graph = PcdGraph::new();
+        let leaves: Vec<usize> = (0..8).map(|_| graph.add_node(&[])).collect();
+        let inner: Vec<usize> = leaves.chunks(2).map(|pair| graph.add_node(pair)).collect();
+        let root = graph.add_node(&inner);
+
+        // proofs are the index of the node
+        let prove_node = |node: usize, incoming: &[(&[bool], &usize)]| -> Result<(Vec<bool>, usize), SynthesisError> {
+            let predecessors: Vec<usize> = incoming.iter().map(|&(_, &proof)| proof).collect();
+            assert_eq!(&predecessors[..], graph.predecessors(node));
+
+            let count = incoming.iter().map(|&(m, _)| m.len()).sum::<usize>() + 1;
+
+            Ok((vec![true; count], node))
+        };
+
+        let worker = Worker::new();
+        let results = graph.prove_parallel(&worker, &prove_node).unwrap();
+        assert_eq!(results, graph.prove(&prove_node).unwrap());
+        assert_eq!(results[root].0.len(), 1 + 4 * 3);
+
+        let failing = graph.prove_parallel(&worker, |node, incoming: &[(&[bool], &usize)]| {
+            if node == inner[1] {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+
+            prove_node(node, incoming)
+        });
+        assert!(failing.is_err());
+    }
+}
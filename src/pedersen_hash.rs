@@ -1,10 +1,18 @@
 use jubjub::*;
 use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 
+/// Level of the Merkle tree personalization that is reserved as
+/// the prefix of the `Custom` personalizations.
+pub const CUSTOM_PERSONALIZATION_PREFIX: usize = 62;
+
 #[derive(Copy, Clone)]
 pub enum Personalization {
     NoteCommitment,
-    MerkleTree(usize)
+    MerkleTree(usize),
+    /// Application defined domain. It's encoded as the reserved
+    /// 6 bit prefix followed by the 8 bits of the tag, so it can't
+    /// collide with the other personalizations or other tags.
+    Custom(u8)
 }
 
 impl Personalization {
@@ -13,9 +21,14 @@ impl Personalization {
             Personalization::NoteCommitment =>
                 vec![true, true, true, true, true, true],
             Personalization::MerkleTree(num) => {
-                assert!(num < 63);
+                assert!(num < CUSTOM_PERSONALIZATION_PREFIX);
 
                 (0..6).map(|i| (num >> i) & 1 == 1).collect()
+            },
+            Personalization::Custom(tag) => {
+                (0..6).map(|i| (CUSTOM_PERSONALIZATION_PREFIX >> i) & 1 == 1)
+                    .chain((0..8).map(|i| (tag >> i) & 1 == 1))
+                    .collect()
             }
         }
     }
@@ -102,6 +115,24 @@ pub fn pedersen_hash<E, I>(
     result
 }
 
+/// Computes the windowed Pedersen commitment
+/// `PedersenHash(personalization, bits) + r * R`, where `R` is the
+/// note commitment randomness generator, which is independent of
+/// the Pedersen hash generators.
+pub fn pedersen_commitment<E, I>(
+    personalization: Personalization,
+    bits: I,
+    r: E::Fs,
+    params: &E::Params
+) -> edwards::Point<E, PrimeOrder>
+    where I: IntoIterator<Item=bool>,
+          E: JubjubEngine
+{
+    params.generator(FixedGenerators::NoteCommitmentRandomness)
+          .mul(r, params)
+          .add(&pedersen_hash(personalization, bits, params), params)
+}
+
 use alt_babyjubjub::{AltJubjubBn256};
 
 pub fn baby_pedersen_hash<E, I>(
@@ -141,6 +141,41 @@ impl Bn256PoseidonParams {
     }
 }
 
+impl Bn256PoseidonParams {
+    /// Parameters matching circomlib's `Poseidon(num_inputs)` template,
+    /// to be used with `circom_poseidon_hash`. Constants are produced
+    /// by the Grain LFSR in the same way as circomlib's ones.
+    pub fn new_circom(num_inputs: usize) -> Self {
+        assert!(num_inputs >= 1 && num_inputs <= CIRCOM_PARTIAL_ROUNDS.len());
+
+        let t = (num_inputs + 1) as u32;
+        let r_f = 4u32;
+        let r_p = CIRCOM_PARTIAL_ROUNDS[num_inputs - 1];
+
+        let (round_constants, mds_matrix) = grain::generate_parameters::<bn256::Fr>(t, 2*r_f, r_p);
+
+        let first_full = (r_f * t) as usize;
+        let partial = (r_p * t) as usize;
+
+        let mut full_round_keys = round_constants[..first_full].to_vec();
+        full_round_keys.extend_from_slice(&round_constants[(first_full + partial)..]);
+        let partial_round_keys = round_constants[first_full..(first_full + partial)].to_vec();
+
+        Self {
+            t: t,
+            r_f: r_f,
+            r_p: r_p,
+            full_round_keys: full_round_keys,
+            partial_round_keys: partial_round_keys,
+            mds_matrix: mds_matrix,
+            security_level: 128
+        }
+    }
+}
+
+/// Number of partial rounds used by circomlib for 1 to 16 inputs.
+const CIRCOM_PARTIAL_ROUNDS: [u32; 16] = [56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64, 68];
+
 impl PoseidonHashParams<bn256::Bn256> for Bn256PoseidonParams {
     fn t(&self) -> u32 {
         self.t
@@ -185,9 +220,23 @@ mod test {
     use bellman::pairing::ff::PrimeField;
     use bellman::pairing::ff::Field;
     use super::Bn256PoseidonParams;
-    use crate::poseidon::{poseidon_hash, PoseidonHashParams, PoseidonEngine};
+    use crate::poseidon::{poseidon_hash, circom_poseidon_hash, PoseidonHashParams, PoseidonEngine};
     use crate::group_hash::BlakeHasher;
 
+    #[test]
+    fn test_circom_poseidon_vectors() {
+        // published circomlib test vectors
+        let params = Bn256PoseidonParams::new_circom(2);
+        let input = vec![Fr::from_str("1").unwrap(), Fr::from_str("2").unwrap()];
+        let output = circom_poseidon_hash::<Bn256>(&params, &input);
+        assert_eq!(output, Fr::from_str("7853200120776062878684798364095072458815029376092732009249414926327459813530").unwrap());
+
+        let params = Bn256PoseidonParams::new_circom(4);
+        let input: Vec<Fr> = (1..5).map(|i| Fr::from_str(&i.to_string()).unwrap()).collect();
+        let output = circom_poseidon_hash::<Bn256>(&params, &input);
+        assert_eq!(output, Fr::from_str("18821383157269793795438455681495246036402687001665670618754263018637548127333").unwrap());
+    }
+
     #[test]
     fn test_generate_bn256_poseidon_params() {
         let params = Bn256PoseidonParams::new::<BlakeHasher>();
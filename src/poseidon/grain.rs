@@ -0,0 +1,147 @@
+/// Grain LFSR based generation of Poseidon parameters, following
+/// the reference `generate_parameters_grain.sage` script, that was
+/// also used to produce the constants of circomlib.
+use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+
+const GRAIN_STATE_SIZE: usize = 80;
+
+pub struct GrainLfsr {
+    state: [bool; GRAIN_STATE_SIZE],
+    head: usize
+}
+
+impl GrainLfsr {
+    /// Initializes the generator for the prime field with x^alpha S-box
+    /// and the given state width and numbers of full and partial rounds.
+    pub fn new(field_size: u32, t: u32, r_f: u32, r_p: u32) -> Self {
+        let mut bits = vec![];
+        // field type: prime field
+        append_bits(&mut bits, 1, 2);
+        // S-box type: x^alpha
+        append_bits(&mut bits, 0, 4);
+        append_bits(&mut bits, field_size as u64, 12);
+        append_bits(&mut bits, t as u64, 12);
+        append_bits(&mut bits, r_f as u64, 10);
+        append_bits(&mut bits, r_p as u64, 10);
+        bits.extend(vec![true; 30]);
+
+        assert_eq!(bits.len(), GRAIN_STATE_SIZE);
+
+        let mut state = [false; GRAIN_STATE_SIZE];
+        state.copy_from_slice(&bits);
+
+        let mut lfsr = Self {
+            state: state,
+            head: 0
+        };
+
+        // discard the first 160 bits
+        for _ in 0..160 {
+            lfsr.next_bit();
+        }
+
+        lfsr
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let bit = [62, 51, 38, 23, 13, 0].iter().fold(false, |acc, &i| {
+            acc ^ self.state[(self.head + i) % GRAIN_STATE_SIZE]
+        });
+
+        self.state[self.head] = bit;
+        self.head = (self.head + 1) % GRAIN_STATE_SIZE;
+
+        bit
+    }
+
+    /// Bits are taken in pairs and the second bit is
+    /// output only if the first one is set.
+    fn next_filtered_bit(&mut self) -> bool {
+        loop {
+            let first = self.next_bit();
+            let second = self.next_bit();
+
+            if first {
+                return second;
+            }
+        }
+    }
+
+    /// Samples `F::NUM_BITS` bits as a big-endian integer.
+    fn next_repr<F: PrimeField>(&mut self) -> F::Repr {
+        let mut repr = F::Repr::default();
+        for _ in 0..F::NUM_BITS {
+            repr.shl(1);
+            if self.next_filtered_bit() {
+                repr.as_mut()[0] |= 1;
+            }
+        }
+
+        repr
+    }
+
+    /// Samples a field element by rejection sampling.
+    pub fn next_field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            if let Ok(el) = F::from_repr(self.next_repr::<F>()) {
+                return el;
+            }
+        }
+    }
+
+    /// Samples a field element by reducing the sampled
+    /// integer, as done for the MDS matrix generation.
+    pub fn next_field_element_without_rejection<F: PrimeField>(&mut self) -> F {
+        let mut repr = self.next_repr::<F>();
+        while repr >= F::char() {
+            repr.sub_noborrow(&F::char());
+        }
+
+        F::from_repr(repr).unwrap()
+    }
+}
+
+fn append_bits(bits: &mut Vec<bool>, value: u64, num_bits: usize) {
+    for i in (0..num_bits).rev() {
+        bits.push((value >> i) & 1 == 1);
+    }
+}
+
+/// Generates round constants for all the `r_f + r_p` rounds
+/// (`r_f` is the total number of full rounds) in the order
+/// of application, together with the Cauchy MDS matrix in the
+/// row-major order.
+pub fn generate_parameters<F: PrimeField>(t: u32, r_f: u32, r_p: u32) -> (Vec<F>, Vec<F>) {
+    let mut lfsr = GrainLfsr::new(F::NUM_BITS, t, r_f, r_p);
+
+    let round_constants: Vec<F> = (0..((r_f + r_p) * t)).map(|_| lfsr.next_field_element::<F>()).collect();
+
+    let t = t as usize;
+    let (xs, ys) = loop {
+        let values: Vec<F> = (0..(2*t)).map(|_| lfsr.next_field_element_without_rejection::<F>()).collect();
+
+        let mut unique = true;
+        for i in 0..values.len() {
+            for j in (i+1)..values.len() {
+                if values[i] == values[j] {
+                    unique = false;
+                }
+            }
+        }
+
+        if unique {
+            break (values[..t].to_vec(), values[t..].to_vec());
+        }
+    };
+
+    let mut mds_matrix = Vec::with_capacity(t * t);
+    for x in xs.iter() {
+        for y in ys.iter() {
+            let mut el = *x;
+            el.add_assign(y);
+            mds_matrix.push(el.inverse().expect("x + y must be non-zero"));
+        }
+    }
+
+    (round_constants, mds_matrix)
+}
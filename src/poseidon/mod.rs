@@ -7,6 +7,7 @@ use super::group_hash::GroupHasher;
 use rand::{Rand, Rng};
 
 pub mod bn256;
+pub mod grain;
 
 pub trait SBox<E: Engine>: Sized {
     fn apply(elements: &mut [E::Fr]);
@@ -243,6 +244,61 @@ pub fn poseidon_mimc<E: PoseidonEngine>(
     state
 }
 
+/// Poseidon permutation as implemented in circomlib. Unlike `poseidon_mimc`
+/// the linear layer is applied in the last round too.
+pub fn circom_poseidon_permutation<E: PoseidonEngine>(
+    params: &E::Params,
+    input: &[E::Fr]
+) -> Vec<E::Fr> {
+    assert_eq!(input.len(), params.t() as usize);
+    let mut state = input.to_vec();
+    let state_len = params.t() as usize;
+
+    let r_f = params.r_f();
+    let r_p = params.r_p();
+
+    for round in 0..(2*r_f + r_p) {
+        let full_round = round < r_f || round >= r_f + r_p;
+
+        let round_constants = if full_round {
+            params.full_round_key(if round < r_f { round } else { round - r_p })
+        } else {
+            params.partial_round_key(round - r_f)
+        };
+        for (el, c) in state.iter_mut().zip(round_constants.iter()) {
+            el.add_assign(c);
+        }
+
+        if full_round {
+            E::SBox::apply(&mut state[..]);
+        } else {
+            E::SBox::apply(&mut state[0..1]);
+        }
+
+        let mut new_state = vec![E::Fr::zero(); state_len];
+        for (row, el) in new_state.iter_mut().enumerate() {
+            *el = scalar_product::<E>(&state[..], params.mds_matrix_row(row as u32));
+        }
+        state = new_state;
+    }
+
+    state
+}
+
+/// Hash compatible with circomlib's `Poseidon(n)` template, expects
+/// parameters with `t = n + 1`, e.g. `Bn256PoseidonParams::new_circom(n)`.
+pub fn circom_poseidon_hash<E: PoseidonEngine>(
+    params: &E::Params,
+    input: &[E::Fr]
+) -> E::Fr {
+    assert_eq!(input.len() + 1, params.t() as usize);
+
+    let mut state = vec![E::Fr::zero()];
+    state.extend_from_slice(input);
+
+    circom_poseidon_permutation::<E>(params, &state)[0]
+}
+
 fn scalar_product<E: Engine> (input: &[E::Fr], by: &[E::Fr]) -> E::Fr {
     assert!(input.len() == by.len());
     let mut result = E::Fr::zero();
@@ -0,0 +1,281 @@
+//! Poseidon2 (https://eprint.iacr.org/2023/323) for state widths 2
+//! and 3, the widths used for 2-to-1 compression of Merkle nodes.
+//!
+//! The permutation applies the external matrix `M_E` once, then
+//! `R_F / 2` external rounds, `R_P` internal rounds and another
+//! `R_F / 2` external rounds. External rounds add a constant to every
+//! element, apply the S-box to all of them and multiply by
+//! `M_E = circ(2, 1, ..., 1)`. Internal rounds only touch the first
+//! element and multiply by `M_I = J + diag(d)`, with `d` being
+//! `[1, 2]` or `[1, 1, 2]`. Both matrices are applied with a single
+//! sum of the state.
+//!
+//! Round constants are drawn from the Grain LFSR of `poseidon::grain`,
+//! `t` per external round followed by one per internal round.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use std::io::{
+    self,
+    Write
+};
+
+use poseidon::grain::GrainLfsr;
+use rescue::smallest_exponent;
+use jubjub::serialization::{write_fr, write_vec};
+use params_digest::{ParamsDigest, write_u64, write_usize};
+
+/// Number of external rounds for 128 bits of security with `alpha = 5`.
+pub const POSEIDON2_FULL_ROUNDS: usize = 8;
+/// Number of internal rounds for 128 bits of security with `alpha = 5`
+/// over the 254 and 255 bit fields.
+pub const POSEIDON2_PARTIAL_ROUNDS: usize = 56;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Poseidon2Params<F: PrimeField> {
+    pub state_width: usize,
+    pub alpha: u64,
+    /// Total number of external rounds `R_F`.
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    /// `state_width` constants per external round.
+    pub external_round_constants: Vec<Vec<F>>,
+    pub internal_round_constants: Vec<F>,
+    /// `d` of `M_I = J + diag(d)`.
+    pub internal_diagonal: Vec<F>
+}
+
+impl<F: PrimeField> ParamsDigest for Poseidon2Params<F> {
+    const TAG: &'static [u8; 8] = b"Poseidn2";
+
+    fn write_body<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.state_width)?;
+        write_u64(writer, self.alpha)?;
+        write_usize(writer, self.full_rounds)?;
+        write_usize(writer, self.partial_rounds)?;
+        write_vec(writer, &self.external_round_constants, |w, round| write_vec(w, round, |w, el| write_fr(w, el)))?;
+        write_vec(writer, &self.internal_round_constants, |w, el| write_fr(w, el))?;
+        write_vec(writer, &self.internal_diagonal, |w, el| write_fr(w, el))
+    }
+}
+
+/// Parameters for `state_width` of 2 or 3 with the round numbers for
+/// 128 bits of security.
+pub fn generate_params<F: PrimeField>(state_width: usize) -> Poseidon2Params<F> {
+    generate_params_with_rounds(state_width, POSEIDON2_FULL_ROUNDS, POSEIDON2_PARTIAL_ROUNDS)
+}
+
+pub fn generate_params_with_rounds<F: PrimeField>(
+    state_width: usize,
+    full_rounds: usize,
+    partial_rounds: usize
+) -> Poseidon2Params<F>
+{
+    assert!(full_rounds % 2 == 0, "external rounds are split in two halves");
+
+    let one = F::one();
+    let mut two = one;
+    two.double();
+
+    let internal_diagonal = match state_width {
+        2 => vec![one, two],
+        3 => vec![one, one, two],
+        _ => panic!("only widths 2 and 3 are supported")
+    };
+
+    let mut lfsr = GrainLfsr::new(F::NUM_BITS, state_width as u32, full_rounds as u32, partial_rounds as u32);
+
+    let external_round_constants = (0..full_rounds).map(|_| {
+        (0..state_width).map(|_| lfsr.next_field_element::<F>()).collect()
+    }).collect();
+    let internal_round_constants = (0..partial_rounds).map(|_| lfsr.next_field_element::<F>()).collect();
+
+    Poseidon2Params {
+        state_width,
+        alpha: smallest_exponent::<F>(),
+        full_rounds,
+        partial_rounds,
+        external_round_constants,
+        internal_round_constants,
+        internal_diagonal
+    }
+}
+
+fn sum<F: PrimeField>(state: &[F]) -> F {
+    let mut acc = F::zero();
+    for s in state.iter() {
+        acc.add_assign(s);
+    }
+
+    acc
+}
+
+fn external_linear_layer<F: PrimeField>(state: &mut [F]) {
+    let sum = sum(state);
+    for s in state.iter_mut() {
+        s.add_assign(&sum);
+    }
+}
+
+fn internal_linear_layer<F: PrimeField>(params: &Poseidon2Params<F>, state: &mut [F]) {
+    let sum = sum(state);
+    for (s, d) in state.iter_mut().zip(params.internal_diagonal.iter()) {
+        s.mul_assign(d);
+        s.add_assign(&sum);
+    }
+}
+
+fn external_round<F: PrimeField>(params: &Poseidon2Params<F>, round: usize, state: &mut [F]) {
+    for (s, c) in state.iter_mut().zip(params.external_round_constants[round].iter()) {
+        s.add_assign(c);
+        *s = s.pow(&[params.alpha]);
+    }
+    external_linear_layer(state);
+}
+
+pub fn poseidon2_permutation<F: PrimeField>(params: &Poseidon2Params<F>, state: &mut [F]) {
+    assert_eq!(state.len(), params.state_width);
+
+    let half = params.full_rounds / 2;
+
+    external_linear_layer(state);
+
+    for round in 0..half {
+        external_round(params, round, state);
+    }
+
+    for c in params.internal_round_constants.iter() {
+        state[0].add_assign(c);
+        state[0] = state[0].pow(&[params.alpha]);
+        internal_linear_layer(params, state);
+    }
+
+    for round in half..params.full_rounds {
+        external_round(params, round, state);
+    }
+}
+
+/// 2-to-1 compression for Merkle nodes: `P(left, right, 0)_0 + left`
+/// for width 3 (no zero for width 2).
+pub fn poseidon2_compress<F: PrimeField>(params: &Poseidon2Params<F>, left: &F, right: &F) -> F {
+    let mut state = vec![F::zero(); params.state_width];
+    state[0] = *left;
+    state[1] = *right;
+
+    poseidon2_permutation(params, &mut state);
+
+    let mut result = state[0];
+    result.add_assign(left);
+
+    result
+}
+
+fn merkle_layers<F: PrimeField>(params: &Poseidon2Params<F>, leaves: &[F]) -> Vec<Vec<F>> {
+    assert!(leaves.len().is_power_of_two(), "number of leaves must be a power of two");
+
+    let mut layers = vec![leaves.to_vec()];
+    while layers.last().unwrap().len() > 1 {
+        let next = layers.last().unwrap().chunks(2).map(|pair| {
+            poseidon2_compress(params, &pair[0], &pair[1])
+        }).collect();
+        layers.push(next);
+    }
+
+    layers
+}
+
+/// Root of the complete tree over `leaves` hashed with
+/// `poseidon2_compress`.
+pub fn merkle_root<F: PrimeField>(params: &Poseidon2Params<F>, leaves: &[F]) -> F {
+    merkle_layers(params, leaves).pop().unwrap()[0]
+}
+
+/// Authentication path of the leaf at `index`, from the leaf level up.
+pub fn merkle_path<F: PrimeField>(params: &Poseidon2Params<F>, leaves: &[F], index: usize) -> Vec<F> {
+    let layers = merkle_layers(params, leaves);
+
+    layers[..(layers.len() - 1)].iter().enumerate().map(|(level, layer)| {
+        layer[(index >> level) ^ 1]
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::pairing::{bn256, bls12_381};
+    use super::*;
+
+    fn mul_by_matrix<F: PrimeField>(matrix: &[Vec<F>], state: &[F]) -> Vec<F> {
+        matrix.iter().map(|row| {
+            let mut acc = F::zero();
+            for (a, b) in row.iter().zip(state.iter()) {
+                let mut tmp = *a;
+                tmp.mul_assign(b);
+                acc.add_assign(&tmp);
+            }
+
+            acc
+        }).collect()
+    }
+
+    #[test]
+    fn test_linear_layers() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<bn256::Fr>(3);
+
+        let f = |x: &str| bn256::Fr::from_str(x).unwrap();
+        let external = vec![
+            vec![f("2"), f("1"), f("1")],
+            vec![f("1"), f("2"), f("1")],
+            vec![f("1"), f("1"), f("2")]
+        ];
+        let internal = vec![
+            vec![f("2"), f("1"), f("1")],
+            vec![f("1"), f("2"), f("1")],
+            vec![f("1"), f("1"), f("3")]
+        ];
+
+        let state: Vec<bn256::Fr> = (0..3).map(|_| rng.gen()).collect();
+
+        let mut result = state.clone();
+        external_linear_layer(&mut result);
+        assert_eq!(result, mul_by_matrix(&external, &state));
+
+        let mut result = state.clone();
+        internal_linear_layer(&params, &mut result);
+        assert_eq!(result, mul_by_matrix(&internal, &state));
+    }
+
+    #[test]
+    fn test_params() {
+        let params = generate_params::<bls12_381::Fr>(3);
+        assert_eq!(params.alpha, 5);
+        assert_eq!(params.external_round_constants.len(), 8);
+        assert_eq!(params.external_round_constants[0].len(), 3);
+        assert_eq!(params.internal_round_constants.len(), 56);
+
+        // constants are deterministic
+        assert!(params == generate_params::<bls12_381::Fr>(3));
+    }
+
+    #[test]
+    fn test_compress() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for width in 2..4 {
+            let params = generate_params::<bn256::Fr>(width);
+
+            let a: bn256::Fr = rng.gen();
+            let b: bn256::Fr = rng.gen();
+
+            let ab = poseidon2_compress(&params, &a, &b);
+            assert!(ab != poseidon2_compress(&params, &b, &a));
+            assert!(ab != poseidon2_compress(&params, &a, &a));
+            assert_eq!(ab, poseidon2_compress(&params, &a, &b));
+        }
+    }
+}
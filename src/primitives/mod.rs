@@ -21,13 +21,23 @@ use byteorder::{
 use jubjub::{
     JubjubEngine,
     JubjubParams,
+    ToUniform,
     edwards,
     PrimeOrder,
     FixedGenerators
 };
 
+use blake2_rfc::blake2b::Blake2b;
 use blake2_rfc::blake2s::Blake2s;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+use secret::{
+    scrub_bytes,
+    scrub_field
+};
+
 #[derive(Clone)]
 pub struct ValueCommitment<E: JubjubEngine> {
     pub value: u64,
@@ -56,6 +66,20 @@ pub struct ProofGenerationKey<E: JubjubEngine> {
     pub nsk: E::Fs
 }
 
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Zeroize for ProofGenerationKey<E> {
+    fn zeroize(&mut self) {
+        scrub_field(&mut self.nsk);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Drop for ProofGenerationKey<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 impl<E: JubjubEngine> ProofGenerationKey<E> {
     pub fn into_viewing_key(&self, params: &E::Params) -> ViewingKey<E> {
         ViewingKey {
@@ -177,6 +201,20 @@ pub struct Note<E: JubjubEngine> {
     pub r: E::Fs
 }
 
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Zeroize for Note<E> {
+    fn zeroize(&mut self) {
+        scrub_field(&mut self.r);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Drop for Note<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 impl<E: JubjubEngine> Note<E> {
     pub fn uncommitted() -> E::Fr {
         // The smallest u-coordinate that is not on the curve
@@ -189,6 +227,17 @@ impl<E: JubjubEngine> Note<E> {
 
     /// Computes the note commitment, returning the full point.
     fn cm_full_point(&self, params: &E::Params) -> edwards::Point<E, PrimeOrder>
+    {
+        self.commit(Personalization::NoteCommitment, vec![], params)
+    }
+
+    /// Commits to the note contents followed by `extension`.
+    fn commit(
+        &self,
+        personalization: Personalization,
+        extension: Vec<bool>,
+        params: &E::Params
+    ) -> edwards::Point<E, PrimeOrder>
     {
         // Calculate the note contents, as bytes
         let mut note_contents = vec![];
@@ -206,11 +255,12 @@ impl<E: JubjubEngine> Note<E> {
 
         // Compute the Pedersen hash of the note contents
         let hash_of_contents = pedersen_hash(
-            Personalization::NoteCommitment,
+            personalization,
             note_contents.into_iter()
                          .flat_map(|byte| {
                             (0..8).map(move |i| ((byte >> i) & 1) == 1)
-                         }),
+                         })
+                         .chain(extension.into_iter()),
             params
         );
 
@@ -229,23 +279,7 @@ impl<E: JubjubEngine> Note<E> {
         params: &E::Params
     ) -> Vec<u8>
     {
-        // Compute rho = cm + position.G
-        let rho = self
-            .cm_full_point(params)
-            .add(
-                &params.generator(FixedGenerators::NullifierPosition)
-                       .mul(position, params),
-                params
-            );
-
-        // Compute nf = BLAKE2s(nk | rho)
-        let mut nf_preimage = [0u8; 64];
-        viewing_key.nk.write(&mut nf_preimage[0..32]).unwrap();
-        rho.write(&mut nf_preimage[32..64]).unwrap();
-        let mut h = Blake2s::with_params(32, &[], &[], constants::PRF_NF_PERSONALIZATION);
-        h.update(&nf_preimage);
-        
-        h.finalize().as_ref().to_vec()
+        nf_for_commitment(&self.cm_full_point(params), viewing_key, position, params)
     }
 
     /// Computes the note commitment
@@ -256,3 +290,250 @@ impl<E: JubjubEngine> Note<E> {
         self.cm_full_point(params).into_xy().0
     }
 }
+
+/// nf = PRF^nf_nk(cm + position.G)
+fn nf_for_commitment<E: JubjubEngine>(
+    cm: &edwards::Point<E, PrimeOrder>,
+    viewing_key: &ViewingKey<E>,
+    position: u64,
+    params: &E::Params
+) -> Vec<u8>
+{
+    // Compute rho = cm + position.G
+    let rho = cm
+        .add(
+            &params.generator(FixedGenerators::NullifierPosition)
+                   .mul(position, params),
+            params
+        );
+
+    // Compute nf = BLAKE2s(nk | rho)
+    let mut nf_preimage = [0u8; 64];
+    viewing_key.nk.write(&mut nf_preimage[0..32]).unwrap();
+    rho.write(&mut nf_preimage[32..64]).unwrap();
+    let mut h = Blake2s::with_params(32, &[], &[], constants::PRF_NF_PERSONALIZATION);
+    h.update(&nf_preimage);
+    
+    h.finalize().as_ref().to_vec()
+}
+
+/// Heights between which a timed note can be spent: from
+/// `unlock_height` on and before `expiry_height`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeLock {
+    pub unlock_height: u32,
+    pub expiry_height: u32
+}
+
+impl TimeLock {
+    pub fn is_spendable_at(&self, height: u32) -> bool {
+        self.unlock_height <= height && height < self.expiry_height
+    }
+
+    /// The heights in little-endian bit order, as appended to the
+    /// contents of a timed note.
+    pub fn to_bits(&self) -> Vec<bool> {
+        (0..32).map(|i| (self.unlock_height >> i) & 1 == 1)
+            .chain((0..32).map(|i| (self.expiry_height >> i) & 1 == 1))
+            .collect()
+    }
+}
+
+/// A note that can only be spent within its time lock. The heights
+/// are committed to after the note contents, under a personalization
+/// of their own, so a timed note can't be opened as a plain one.
+pub struct TimedNote<E: JubjubEngine> {
+    pub note: Note<E>,
+    pub lock: TimeLock
+}
+
+impl<E: JubjubEngine> TimedNote<E> {
+    fn cm_full_point(&self, params: &E::Params) -> edwards::Point<E, PrimeOrder>
+    {
+        self.note.commit(
+            Personalization::Custom(constants::TIMED_NOTE_COMMITMENT_TAG),
+            self.lock.to_bits(),
+            params
+        )
+    }
+
+    pub fn nf(
+        &self,
+        viewing_key: &ViewingKey<E>,
+        position: u64,
+        params: &E::Params
+    ) -> Vec<u8>
+    {
+        nf_for_commitment(&self.cm_full_point(params), viewing_key, position, params)
+    }
+
+    pub fn cm(&self, params: &E::Params) -> E::Fr
+    {
+        self.cm_full_point(params).into_xy().0
+    }
+}
+
+/// PRF^expand(sk, t) = BLAKE2b-512(sk | t), with the domain
+/// separation byte(s) `t` choosing what is derived.
+pub fn prf_expand(sk: &[u8], t: &[u8]) -> [u8; 64] {
+    let mut h = Blake2b::with_params(64, &[], &[], constants::PRF_EXPAND_PERSONALIZATION);
+    h.update(sk);
+    h.update(t);
+
+    let mut result = [0u8; 64];
+    result.copy_from_slice(h.finalize().as_ref());
+
+    result
+}
+
+/// Keys derived from a 32 byte spending key.
+#[derive(Clone)]
+pub struct ExpandedSpendingKey<E: JubjubEngine> {
+    pub ask: E::Fs,
+    pub nsk: E::Fs,
+    pub ovk: [u8; 32]
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Zeroize for ExpandedSpendingKey<E> {
+    fn zeroize(&mut self) {
+        scrub_field(&mut self.ask);
+        scrub_field(&mut self.nsk);
+        scrub_bytes(&mut self.ovk[..]);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Drop for ExpandedSpendingKey<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<E: JubjubEngine> ExpandedSpendingKey<E> {
+    pub fn from_spending_key(sk: &[u8]) -> Self {
+        let mut ovk = [0u8; 32];
+        let mut expanded = prf_expand(sk, &[0x02]);
+        ovk.copy_from_slice(&expanded[..32]);
+        scrub_bytes(&mut expanded[..]);
+
+        ExpandedSpendingKey {
+            ask: derive_scalar::<E>(sk, 0x00),
+            nsk: derive_scalar::<E>(sk, 0x01),
+            ovk: ovk
+        }
+    }
+
+    pub fn proof_generation_key(&self, params: &E::Params) -> ProofGenerationKey<E> {
+        ProofGenerationKey {
+            ak: params.generator(FixedGenerators::SpendingKeyGenerator)
+                      .mul(self.ask, params),
+            nsk: self.nsk
+        }
+    }
+}
+
+/// ToScalar(PRF^expand(sk, [t])), scrubbing the expanded bytes.
+fn derive_scalar<E: JubjubEngine>(sk: &[u8], t: u8) -> E::Fs {
+    let mut expanded = prf_expand(sk, &[t]);
+    let scalar = E::Fs::to_uniform(&expanded);
+    scrub_bytes(&mut expanded[..]);
+
+    scalar
+}
+
+/// Derives the note commitment randomness from the note seed.
+pub fn derive_rcm<E: JubjubEngine>(rseed: &[u8; 32]) -> E::Fs {
+    derive_scalar::<E>(rseed, 0x04)
+}
+
+/// Derives the ephemeral secret key from the note seed. It isn't held
+/// by any type here, so the caller scrubs it with `scrub_field`.
+pub fn derive_esk<E: JubjubEngine>(rseed: &[u8; 32]) -> E::Fs {
+    derive_scalar::<E>(rseed, 0x05)
+}
+
+/// PRF^ock, the key encrypting the outgoing ciphertext of a note.
+pub fn prf_ock<E: JubjubEngine>(
+    ovk: &[u8; 32],
+    cv: &edwards::Point<E, PrimeOrder>,
+    cmu: &E::Fr,
+    epk: &edwards::Point<E, PrimeOrder>
+) -> [u8; 32]
+{
+    let mut h = Blake2b::with_params(32, &[], &[], constants::PRF_OCK_PERSONALIZATION);
+    h.update(ovk);
+
+    let mut buf = vec![];
+    cv.write(&mut buf).unwrap();
+    cmu.into_repr().write_le(&mut buf).unwrap();
+    epk.write(&mut buf).unwrap();
+    h.update(&buf);
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(h.finalize().as_ref());
+
+    result
+}
+
+/// KDF^Sapling, the key encrypting the note plaintext for the
+/// shared secret `dhsecret = [esk] pk_d = [ivk] epk`.
+pub fn kdf_sapling<E: JubjubEngine>(
+    dhsecret: &edwards::Point<E, PrimeOrder>,
+    epk: &edwards::Point<E, PrimeOrder>
+) -> [u8; 32]
+{
+    let mut buf = vec![];
+    dhsecret.write(&mut buf).unwrap();
+    epk.write(&mut buf).unwrap();
+
+    let mut h = Blake2b::with_params(32, &[], &[], constants::KDF_SAPLING_PERSONALIZATION);
+    h.update(&buf);
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(h.finalize().as_ref());
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bls12_381::Bls12;
+    use jubjub::{JubjubBls12, JubjubParams, FixedGenerators};
+    use super::*;
+
+    #[test]
+    fn test_note_encryption_keys_agree() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &JubjubBls12::new();
+
+        let sk: [u8; 32] = rng.gen();
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&sk);
+        let vk = expsk.proof_generation_key(params).into_viewing_key(params);
+
+        let address = loop {
+            let diversifier = Diversifier(rng.gen());
+            if let Some(address) = vk.into_payment_address(diversifier, params) {
+                break address;
+            }
+        };
+
+        // the sender and the recipient derive the same key
+        let rseed: [u8; 32] = rng.gen();
+        let esk = derive_esk::<Bls12>(&rseed);
+        let epk = address.g_d(params).unwrap().mul(esk, params);
+
+        let sender = kdf_sapling(&address.pk_d.mul(esk, params), &epk);
+        let recipient = kdf_sapling(&epk.mul(vk.ivk(), params), &epk);
+        assert_eq!(sender, recipient);
+
+        // the derivations are domain separated
+        assert!(derive_esk::<Bls12>(&rseed) != derive_rcm::<Bls12>(&rseed));
+        assert!(&prf_expand(&sk, &[0x00])[..] != &prf_expand(&sk, &[0x01])[..]);
+
+        let cv = params.generator(FixedGenerators::ValueCommitmentRandomness).mul(esk, params);
+        let cmu = address.create_note(1, derive_rcm::<Bls12>(&rseed), params).unwrap().cm(params);
+        assert!(prf_ock(&expsk.ovk, &cv, &cmu, &epk) != prf_ock(&[0u8; 32], &cv, &cmu, &epk));
+    }
+}
@@ -5,20 +5,26 @@ use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 use rand::{Rng, Rand};
 use std::io::{self, Read, Write};
 
+use fields::{from_bytes_le_ct, read_le_ct};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+use secret::{scrub_bytes, scrub_field};
+
 use babyjubjub::{FixedGenerators, JubjubEngine, JubjubParams, Unknown, edwards::Point};
 use baby_util::{hash_to_scalar};
 
 fn read_scalar<E: JubjubEngine, R: Read>(reader: R) -> io::Result<E::Fs> {
-    let mut s_repr = <E::Fs as PrimeField>::Repr::default();
-    s_repr.read_le(reader)?;
-
-    match E::Fs::from_repr(s_repr) {
-        Ok(s) => Ok(s),
-        Err(_) => Err(io::Error::new(
+    let (s, is_canonical) = read_le_ct::<E::Fs, R>(reader)?;
+    if !is_canonical {
+        return Err(io::Error::new(
             io::ErrorKind::InvalidInput,
             "scalar is not in field",
-        )),
+        ));
     }
+
+    Ok(s)
 }
 
 fn write_scalar<E: JubjubEngine, W: Write>(s: &E::Fs, writer: W) -> io::Result<()> {
@@ -37,6 +43,20 @@ pub struct Signature {
 
 pub struct PrivateKey<E: JubjubEngine>(pub E::Fs);
 
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Zeroize for PrivateKey<E> {
+    fn zeroize(&mut self) {
+        scrub_field(&mut self.0);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: JubjubEngine> Drop for PrivateKey<E> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 pub struct PublicKey<E: JubjubEngine>(pub Point<E, Unknown>);
 
 impl Signature {
@@ -83,7 +103,8 @@ impl<E: JubjubEngine> PrivateKey<E> {
         rng.fill_bytes(&mut t[..]);
 
         // r = H*(T || M)
-        let r = h_star::<E>(&t[..], msg);
+        let mut r = h_star::<E>(&t[..], msg);
+        scrub_bytes(&mut t[..]);
 
         // R = r . P_G
         let r_g = params.generator(p_g).mul(r, params);
@@ -95,6 +116,7 @@ impl<E: JubjubEngine> PrivateKey<E> {
         let mut s = h_star::<E>(&rbar[..], msg);
         s.mul_assign(&self.0);
         s.add_assign(&r);
+        scrub_field(&mut r);
         let mut sbar = [0u8; 32];
         write_scalar::<E, &mut [u8]>(&s, &mut sbar[..])
             .expect("Jubjub scalars should serialize to 32 bytes");
@@ -140,17 +162,15 @@ impl<E: JubjubEngine> PublicKey<E> {
             Ok(r) => r,
             Err(_) => return false,
         };
-        // S < order(G)
-        // (E::Fs guarantees its representation is in the field)
-        let s = match read_scalar::<E, &[u8]>(&sig.sbar[..]) {
-            Ok(s) => s,
-            Err(_) => return false,
-        };
+        // S < order(G), checked together with the equation
+        let (s, s_is_canonical) = from_bytes_le_ct::<E::Fs>(&sig.sbar[..]);
         // 0 = h_G(-S . P_G + R + c . vk)
-        self.0.mul(c, params).add(&r, params).add(
+        let is_valid = self.0.mul(c, params).add(&r, params).add(
             &params.generator(p_g).mul(s, params).negate().into(),
             params
-        ).mul_by_cofactor(params).eq(&Point::zero())
+        ).mul_by_cofactor(params).eq(&Point::zero());
+
+        is_valid & s_is_canonical
     }
 }
 
@@ -170,16 +190,15 @@ pub fn batch_verify<'a, E: JubjubEngine, R: Rng>(
 ) -> bool
 {
     let mut acc = Point::<E, Unknown>::zero();
+    let mut all_canonical = true;
 
     for entry in batch {
         let mut r = match Point::<E, Unknown>::read(&entry.sig.rbar[..], params) {
             Ok(r) => r,
             Err(_) => return false,
         };
-        let mut s = match read_scalar::<E, &[u8]>(&entry.sig.sbar[..]) {
-            Ok(s) => s,
-            Err(_) => return false,
-        };
+        let (mut s, s_is_canonical) = from_bytes_le_ct::<E::Fs>(&entry.sig.sbar[..]);
+        all_canonical &= s_is_canonical;
 
         let mut c = h_star::<E>(&entry.sig.rbar[..], entry.msg);
 
@@ -199,7 +218,7 @@ pub fn batch_verify<'a, E: JubjubEngine, R: Rng>(
 
     acc = acc.mul_by_cofactor(params).into();
 
-    acc.eq(&Point::zero())
+    acc.eq(&Point::zero()) & all_canonical
 }
 
 #[cfg(test)]
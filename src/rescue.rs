@@ -0,0 +1,426 @@
+//! Rescue-Prime (https://eprint.iacr.org/2020/1143) with parameters
+//! derived for any prime field in the same way as the reference
+//! implementation: the S-box exponent is the smallest `alpha` coprime
+//! to `p - 1`, the number of rounds is set against Gröbner basis
+//! attacks with a 50% margin and the round constants are expanded
+//! with SHAKE256 from the parameter string.
+//!
+//! The MDS matrix is derived from the systematic generator matrix of
+//! a Vandermonde code over `F::multiplicative_generator()`, which
+//! matches the reference when it is the smallest primitive element.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use tiny_keccak::{Shake, Hasher};
+
+use std::io::{
+    self,
+    Write
+};
+
+use jubjub::serialization::{
+    write_fr,
+    write_vec
+};
+
+use params_digest::{
+    ParamsDigest,
+    write_u64,
+    write_usize
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RescueParams<F: PrimeField> {
+    pub state_width: usize,
+    pub capacity: usize,
+    pub security_level: usize,
+    pub num_rounds: usize,
+    pub alpha: u64,
+    /// Little-endian limbs of `alpha^-1 mod (p - 1)`.
+    pub alpha_inv: Vec<u64>,
+    /// Rows of the MDS matrix.
+    pub mds_matrix: Vec<Vec<F>>,
+    /// `2 * state_width` constants per round.
+    pub round_constants: Vec<F>
+}
+
+impl<F: PrimeField> RescueParams<F> {
+    pub fn rate(&self) -> usize {
+        self.state_width - self.capacity
+    }
+}
+
+impl<F: PrimeField> ParamsDigest for RescueParams<F> {
+    const TAG: &'static [u8; 8] = b"Rescue\0\0";
+
+    fn write_body<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.state_width)?;
+        write_usize(writer, self.capacity)?;
+        write_usize(writer, self.security_level)?;
+        write_usize(writer, self.num_rounds)?;
+        write_u64(writer, self.alpha)?;
+        write_vec(writer, &self.alpha_inv, |w, limb| write_u64(w, *limb))?;
+        write_vec(writer, &self.mds_matrix, |w, row| write_vec(w, row, |w, el| write_fr(w, el)))?;
+        write_vec(writer, &self.round_constants, |w, el| write_fr(w, el))
+    }
+}
+
+fn modulus_minus_one<F: PrimeField>() -> Vec<u64> {
+    let mut repr = F::char();
+    repr.sub_noborrow(&F::Repr::from(1));
+
+    repr.as_ref().to_vec()
+}
+
+fn div_rem_small(limbs: &mut [u64], divisor: u64) -> u64 {
+    let mut rem = 0u128;
+    for limb in limbs.iter_mut().rev() {
+        let cur = (rem << 64) | (*limb as u128);
+        *limb = (cur / divisor as u128) as u64;
+        rem = cur % divisor as u128;
+    }
+
+    rem as u64
+}
+
+fn mul_add_small(limbs: &mut Vec<u64>, mul: u64, add: u64) {
+    let mut carry = add as u128;
+    for limb in limbs.iter_mut() {
+        let cur = (*limb as u128) * (mul as u128) + carry;
+        *limb = cur as u64;
+        carry = cur >> 64;
+    }
+    if carry != 0 {
+        limbs.push(carry as u64);
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+
+    a
+}
+
+pub(crate) fn to_decimal(limbs: &[u64]) -> String {
+    let mut limbs = limbs.to_vec();
+    let mut digits = vec![];
+    loop {
+        digits.push(b'0' + div_rem_small(&mut limbs, 10) as u8);
+        if limbs.iter().all(|l| *l == 0) {
+            break;
+        }
+    }
+    digits.reverse();
+
+    String::from_utf8(digits).unwrap()
+}
+
+/// Returns `alpha^-1 mod (p - 1)` as little-endian limbs if `x^alpha`
+/// is a permutation of the field, i.e. `gcd(alpha, p - 1) = 1`.
+pub fn exponent_inverse<F: PrimeField>(alpha: u64) -> Option<Vec<u64>> {
+    if alpha < 2 {
+        return None;
+    }
+
+    let p_minus_one = modulus_minus_one::<F>();
+
+    let rem = div_rem_small(&mut p_minus_one.clone(), alpha);
+    if gcd(alpha, rem) != 1 {
+        return None;
+    }
+
+    // alpha^-1 = (k * (p - 1) + 1) / alpha for the k making it integral
+    let k = (1..alpha).find(|k| (k * rem + 1) % alpha == 0).unwrap();
+
+    let mut alpha_inv = p_minus_one;
+    mul_add_small(&mut alpha_inv, k, 1);
+    assert_eq!(div_rem_small(&mut alpha_inv, alpha), 0);
+
+    Some(alpha_inv)
+}
+
+/// Returns the smallest `alpha >= 3` coprime to `p - 1`.
+pub fn smallest_exponent<F: PrimeField>() -> u64 {
+    (3..).find(|&alpha| exponent_inverse::<F>(alpha).is_some()).unwrap()
+}
+
+pub(crate) fn log2_binomial(n: u64, k: u64) -> f64 {
+    (1..(k + 1)).map(|i| (((n - k + i) as f64) / (i as f64)).log2()).sum()
+}
+
+fn get_number_of_rounds(state_width: usize, capacity: usize, security_level: usize, alpha: u64) -> usize {
+    let m = state_width as u64;
+    let rate = (state_width - capacity) as u64;
+
+    let dcon = |n: u64| ((alpha - 1) * m * (n - 1)) / 2 + 2;
+    let v = |n: u64| m * (n - 1) + rate;
+
+    let mut l1 = 1;
+    while l1 < 25 {
+        if 2.0 * log2_binomial(v(l1) + dcon(l1), v(l1)) > security_level as f64 {
+            break;
+        }
+        l1 += 1;
+    }
+
+    // set a minimum value for sanity and add 50%
+    (3 * std::cmp::max(5, l1) as usize + 1) / 2
+}
+
+fn get_round_constants<F: PrimeField>(
+    state_width: usize,
+    capacity: usize,
+    security_level: usize,
+    num_rounds: usize
+) -> Vec<F>
+{
+    let seed = format!(
+        "Rescue-XLIX({},{},{},{})",
+        to_decimal(F::char().as_ref()),
+        state_width,
+        capacity,
+        security_level
+    );
+
+    expand_constants(&seed, 2 * state_width * num_rounds)
+}
+
+/// Expands `seed` with SHAKE256 into `num_constants` field elements,
+/// each taken from one more byte than the modulus has.
+pub(crate) fn expand_constants<F: PrimeField>(seed: &str, num_constants: usize) -> Vec<F> {
+    let bytes_per_int = (F::NUM_BITS as usize + 7) / 8 + 1;
+
+    let mut bytes = vec![0u8; bytes_per_int * num_constants];
+    let mut shake = Shake::v256();
+    shake.update(seed.as_bytes());
+    shake.finalize(&mut bytes);
+
+    let base = F::from_str("256").unwrap();
+
+    bytes.chunks(bytes_per_int).map(|chunk| {
+        // little-endian integer reduced modulo p
+        let mut acc = F::zero();
+        for b in chunk.iter().rev() {
+            acc.mul_assign(&base);
+            acc.add_assign(&F::from_str(&b.to_string()).unwrap());
+        }
+
+        acc
+    }).collect()
+}
+
+fn get_mds_matrix<F: PrimeField>(state_width: usize) -> Vec<Vec<F>> {
+    let m = state_width;
+    let g = F::multiplicative_generator();
+
+    let mut v: Vec<Vec<F>> = (0..m).map(|i| {
+        (0..(2 * m)).map(|j| g.pow(&[(i * j) as u64])).collect()
+    }).collect();
+
+    // reduced row echelon form, the left half is an invertible
+    // Vandermonde matrix, so it becomes the identity
+    for col in 0..m {
+        let pivot = (col..m).find(|&row| !v[row][col].is_zero()).expect("Vandermonde matrix is invertible");
+        v.swap(col, pivot);
+
+        let inv = v[col][col].inverse().unwrap();
+        for el in v[col].iter_mut() {
+            el.mul_assign(&inv);
+        }
+
+        for row in 0..m {
+            if row != col && !v[row][col].is_zero() {
+                let factor = v[row][col];
+                for j in 0..(2 * m) {
+                    let mut tmp = v[col][j];
+                    tmp.mul_assign(&factor);
+                    v[row][j].sub_assign(&tmp);
+                }
+            }
+        }
+    }
+
+    // the MDS matrix is the transpose of the right half
+    (0..m).map(|i| (0..m).map(|j| v[j][m + i]).collect()).collect()
+}
+
+/// Derives the parameters for the given state width and security
+/// level in bits with the smallest valid S-box exponent. The capacity
+/// is the smallest one that holds twice the security level.
+pub fn generate_params<F: PrimeField>(state_width: usize, security_level: usize) -> RescueParams<F> {
+    generate_params_with_exponent(state_width, security_level, smallest_exponent::<F>())
+        .expect("the smallest exponent is valid")
+}
+
+/// Same as `generate_params` with the given S-box exponent, returns
+/// `None` if `x^alpha` isn't a permutation of the field.
+pub fn generate_params_with_exponent<F: PrimeField>(
+    state_width: usize,
+    security_level: usize,
+    alpha: u64
+) -> Option<RescueParams<F>>
+{
+    let capacity = std::cmp::max(1, (2 * security_level + F::CAPACITY as usize - 1) / F::CAPACITY as usize);
+    assert!(capacity < state_width, "state is too narrow for the security level");
+
+    let alpha_inv = exponent_inverse::<F>(alpha)?;
+    let num_rounds = get_number_of_rounds(state_width, capacity, security_level, alpha);
+
+    Some(RescueParams {
+        state_width,
+        capacity,
+        security_level,
+        num_rounds,
+        alpha,
+        alpha_inv,
+        mds_matrix: get_mds_matrix(state_width),
+        round_constants: get_round_constants(state_width, capacity, security_level, num_rounds)
+    })
+}
+
+fn mds_mul<F: PrimeField>(matrix: &[Vec<F>], state: &mut [F]) {
+    let result: Vec<F> = matrix.iter().map(|row| {
+        let mut acc = F::zero();
+        for (a, b) in row.iter().zip(state.iter()) {
+            let mut tmp = *a;
+            tmp.mul_assign(b);
+            acc.add_assign(&tmp);
+        }
+
+        acc
+    }).collect();
+
+    state.copy_from_slice(&result);
+}
+
+pub fn rescue_permutation<F: PrimeField>(params: &RescueParams<F>, state: &mut [F]) {
+    let m = params.state_width;
+    assert_eq!(state.len(), m);
+
+    for round in 0..params.num_rounds {
+        let constants = &params.round_constants[(2 * m * round)..(2 * m * (round + 1))];
+
+        for s in state.iter_mut() {
+            *s = s.pow(&[params.alpha]);
+        }
+        mds_mul(&params.mds_matrix, state);
+        for (s, c) in state.iter_mut().zip(constants[..m].iter()) {
+            s.add_assign(c);
+        }
+
+        for s in state.iter_mut() {
+            *s = s.pow(&params.alpha_inv);
+        }
+        mds_mul(&params.mds_matrix, state);
+        for (s, c) in state.iter_mut().zip(constants[m..].iter()) {
+            s.add_assign(c);
+        }
+    }
+}
+
+/// Sponge hash, the input is padded with a one and zeroes up to a
+/// multiple of the rate and `rate` elements are squeezed.
+pub fn rescue_hash<F: PrimeField>(params: &RescueParams<F>, input: &[F]) -> Vec<F> {
+    let rate = params.rate();
+
+    let mut input = input.to_vec();
+    input.push(F::one());
+    while input.len() % rate != 0 {
+        input.push(F::zero());
+    }
+
+    let mut state = vec![F::zero(); params.state_width];
+    for chunk in input.chunks(rate) {
+        for (s, i) in state.iter_mut().zip(chunk.iter()) {
+            s.add_assign(i);
+        }
+        rescue_permutation(params, &mut state);
+    }
+
+    state.truncate(rate);
+
+    state
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::ff::{Field, PrimeField};
+    use bellman::pairing::{bn256, bls12_381};
+    use super::*;
+
+    #[test]
+    fn test_alphas() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        // 3 divides p - 1 for both fields
+        let params = generate_params::<bn256::Fr>(3, 128);
+        assert_eq!(params.alpha, 5);
+
+        let x: bn256::Fr = rng.gen();
+        assert_eq!(x.pow(&[params.alpha]).pow(&params.alpha_inv), x);
+
+        let params = generate_params::<bls12_381::Fr>(3, 128);
+        assert_eq!(params.alpha, 5);
+
+        assert!(generate_params_with_exponent::<bls12_381::Fr>(3, 128, 3).is_none());
+        assert!(generate_params_with_exponent::<bls12_381::Fr>(3, 128, 7).is_some());
+
+        let x: bls12_381::Fr = rng.gen();
+        assert_eq!(x.pow(&params.alpha_inv).pow(&[params.alpha]), x);
+    }
+
+    #[test]
+    fn test_params() {
+        let params = generate_params::<bn256::Fr>(3, 128);
+
+        assert_eq!(params, generate_params::<bn256::Fr>(3, 128));
+        assert_eq!(params.capacity, 2);
+        assert_eq!(params.rate(), 1);
+        assert!(params.num_rounds >= 8);
+        assert_eq!(params.round_constants.len(), 2 * 3 * params.num_rounds);
+
+        // all the 1x1 and 2x2 minors of an MDS matrix are non-zero
+        let mds = &params.mds_matrix;
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!(!mds[i][j].is_zero());
+            }
+        }
+        for (i1, i2) in [(0, 1), (0, 2), (1, 2)].iter() {
+            for (j1, j2) in [(0, 1), (0, 2), (1, 2)].iter() {
+                let mut a = mds[*i1][*j1];
+                a.mul_assign(&mds[*i2][*j2]);
+                let mut b = mds[*i1][*j2];
+                b.mul_assign(&mds[*i2][*j1]);
+                assert!(a != b);
+            }
+        }
+
+        // wider states get a smaller capacity relative to the width
+        let params = generate_params::<bn256::Fr>(5, 128);
+        assert_eq!(params.rate(), 3);
+    }
+
+    #[test]
+    fn test_hash() {
+        let params = generate_params::<bn256::Fr>(3, 128);
+
+        let a = rescue_hash(&params, &[bn256::Fr::from_str("1").unwrap()]);
+        let b = rescue_hash(&params, &[bn256::Fr::from_str("1").unwrap(), bn256::Fr::zero()]);
+        let c = rescue_hash(&params, &[bn256::Fr::from_str("2").unwrap()]);
+
+        assert_eq!(a.len(), 1);
+        assert!(a != b);
+        assert!(a != c);
+        assert_eq!(a, rescue_hash(&params, &[bn256::Fr::from_str("1").unwrap()]));
+    }
+}
@@ -0,0 +1,98 @@
+//! Ring buffer of the most recent roots of a state tree, so that a
+//! spend can use an anchor that is a few updates old.
+//!
+//! The buffer is committed to as the Poseidon2 Merkle root of its
+//! slots (see `poseidon2::merkle_root`), which `circuit::root_history`
+//! checks the anchor against. All the slots start with the initial
+//! root, and every new root overwrites the oldest one.
+
+use bellman::pairing::ff::PrimeField;
+
+use poseidon2::{
+    Poseidon2Params,
+    merkle_root,
+    merkle_path
+};
+
+pub struct RootHistory<'a, F: PrimeField> {
+    params: &'a Poseidon2Params<F>,
+    roots: Vec<F>,
+    /// Slot of the next root.
+    next: usize
+}
+
+impl<'a, F: PrimeField> RootHistory<'a, F> {
+    /// Keeps the last `2^log_capacity` roots.
+    pub fn new(params: &'a Poseidon2Params<F>, log_capacity: usize, initial_root: F) -> Self {
+        assert!(log_capacity < 32);
+
+        RootHistory {
+            params,
+            roots: vec![initial_root; 1 << log_capacity],
+            next: 0
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn push(&mut self, root: F) {
+        self.roots[self.next] = root;
+        self.next = (self.next + 1) % self.roots.len();
+    }
+
+    pub fn latest(&self) -> F {
+        self.roots[(self.next + self.roots.len() - 1) % self.roots.len()]
+    }
+
+    pub fn contains(&self, root: &F) -> bool {
+        self.roots.contains(root)
+    }
+
+    pub fn commitment(&self) -> F {
+        merkle_root(self.params, &self.roots)
+    }
+
+    /// Slot of `root` and its authentication path in the commitment,
+    /// if it's still in the history.
+    pub fn witness(&self, root: &F) -> Option<(usize, Vec<F>)> {
+        let slot = self.roots.iter().position(|r| r == root)?;
+
+        Some((slot, merkle_path(self.params, &self.roots, slot)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::bn256::Fr;
+    use poseidon2::generate_params;
+    use circuit::test::fr;
+    use super::*;
+
+    #[test]
+    fn test_root_history() {
+        let params = generate_params::<Fr>(3);
+        let mut history = RootHistory::new(&params, 2, fr(0));
+        assert_eq!(history.capacity(), 4);
+
+        for i in 1..6 {
+            let before = history.commitment();
+            history.push(fr(i));
+
+            assert!(history.commitment() != before);
+            assert_eq!(history.latest(), fr(i));
+        }
+
+        // 1 was evicted by 5
+        assert!(!history.contains(&fr(1)));
+        assert!(history.witness(&fr(1)).is_none());
+        for i in 2..6 {
+            assert!(history.contains(&fr(i)));
+        }
+
+        let (slot, path) = history.witness(&fr(3)).unwrap();
+        assert_eq!(path.len(), 2);
+        assert_eq!(slot, 2);
+    }
+}
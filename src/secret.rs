@@ -0,0 +1,64 @@
+//! Scrubbing of secret material, enabled by the `zeroize` feature.
+//!
+//! With the feature, private keys, expanded spending keys and note
+//! randomness implement `Zeroize` and are zeroized when dropped, and
+//! the temporaries secrets are derived from are scrubbed after use.
+//! Without it the functions here do nothing.
+
+use bellman::pairing::ff::Field;
+
+#[cfg(feature = "zeroize")]
+use std::{ptr, sync::atomic};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Overwrites `el` with zero. Field elements don't implement `Zeroize`,
+/// so this writes them the way `zeroize` writes integers.
+#[cfg(feature = "zeroize")]
+pub fn scrub_field<F: Field>(el: &mut F) {
+    unsafe {
+        ptr::write_volatile(el, F::zero());
+    }
+    atomic::compiler_fence(atomic::Ordering::SeqCst);
+}
+
+#[cfg(not(feature = "zeroize"))]
+pub fn scrub_field<F: Field>(_el: &mut F) {}
+
+/// Overwrites `bytes` with zeros.
+#[cfg(feature = "zeroize")]
+pub fn scrub_bytes(bytes: &mut [u8]) {
+    bytes.zeroize();
+}
+
+#[cfg(not(feature = "zeroize"))]
+pub fn scrub_bytes(_bytes: &mut [u8]) {}
+
+#[cfg(all(test, feature = "zeroize"))]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::ff::Field;
+    use bellman::pairing::bn256::Bn256;
+    use alt_babyjubjub::fs::Fs;
+    use zeroize::Zeroize;
+    use eddsa::PrivateKey;
+    use super::*;
+
+    #[test]
+    fn test_scrub() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut el: Fs = rng.gen();
+        scrub_field(&mut el);
+        assert!(el.is_zero());
+
+        let mut bytes: [u8; 32] = rng.gen();
+        scrub_bytes(&mut bytes[..]);
+        assert_eq!(bytes, [0u8; 32]);
+
+        let mut sk = PrivateKey::<Bn256>(rng.gen());
+        sk.zeroize();
+        assert!(sk.0.is_zero());
+    }
+}
@@ -0,0 +1,467 @@
+//! Phase 2 of the Groth16 trusted setup ceremony.
+//!
+//! Circuit specific parameters are randomized by a sequence of
+//! participants, each of them multiplying `delta` by a secret scalar.
+//! The parameters are sound as long as at least one participant has
+//! destroyed the secret. Every contribution is accompanied by a public
+//! key that proves knowledge of the secret and binds it to the previous
+//! contributions, so the whole chain can be verified afterwards.
+//!
+//! The initial parameters must be produced from the output of a phase 1
+//! (powers of tau) ceremony. Parameters from `generate_random_parameters`
+//! have known toxic waste and are only good for testing.
+
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use bellman::pairing::{
+    Engine,
+    CurveAffine,
+    CurveProjective,
+    EncodedPoint
+};
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField
+};
+
+use bellman::groth16::{
+    Parameters
+};
+
+use blake2_rfc::blake2b::Blake2b;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use rand::{Rng, SeedableRng, ChaChaRng};
+
+/// Public key of a single contribution.
+#[derive(Clone)]
+pub struct PublicKey<E: Engine> {
+    /// `delta` after the contribution
+    delta_after: E::G1Affine,
+    /// random `s` and `s * delta'`
+    s: E::G1Affine,
+    s_delta: E::G1Affine,
+    /// `r * delta'`, where `r` is derived from the transcript
+    r_delta: E::G2Affine,
+    /// hash of the circuit and all the previous contributions
+    transcript: [u8; 64]
+}
+
+impl<E: Engine> PartialEq for PublicKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.delta_after == other.delta_after
+            && self.s == other.s
+            && self.s_delta == other.s_delta
+            && self.r_delta == other.r_delta
+            && &self.transcript[..] == &other.transcript[..]
+    }
+}
+
+impl<E: Engine> PublicKey<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.delta_after.into_uncompressed().as_ref())?;
+        writer.write_all(self.s.into_uncompressed().as_ref())?;
+        writer.write_all(self.s_delta.into_uncompressed().as_ref())?;
+        writer.write_all(self.r_delta.into_uncompressed().as_ref())?;
+        writer.write_all(&self.transcript)?;
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let delta_after = read_g1::<E, _>(&mut reader)?;
+        let s = read_g1::<E, _>(&mut reader)?;
+        let s_delta = read_g1::<E, _>(&mut reader)?;
+        let r_delta = read_g2::<E, _>(&mut reader)?;
+        let mut transcript = [0u8; 64];
+        reader.read_exact(&mut transcript)?;
+
+        Ok(PublicKey {
+            delta_after: delta_after,
+            s: s,
+            s_delta: s_delta,
+            r_delta: r_delta,
+            transcript: transcript
+        })
+    }
+
+    /// Hash identifying the contribution, that participants
+    /// publish to attest that their contribution is in the chain.
+    pub fn hash(&self) -> [u8; 64] {
+        let mut bytes = vec![];
+        self.write(&mut bytes).expect("must write into a vector");
+
+        blake2b(&[&bytes[..]])
+    }
+}
+
+/// Groth16 parameters together with the chain of contributions.
+#[derive(Clone)]
+pub struct MPCParameters<E: Engine> {
+    params: Parameters<E>,
+    cs_hash: [u8; 64],
+    contributions: Vec<PublicKey<E>>
+}
+
+impl<E: Engine> PartialEq for MPCParameters<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.params == other.params
+            && &self.cs_hash[..] == &other.cs_hash[..]
+            && self.contributions == other.contributions
+    }
+}
+
+fn blake2b(chunks: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Blake2b::new(64);
+    for chunk in chunks.iter() {
+        hasher.update(chunk);
+    }
+
+    let mut result = [0u8; 64];
+    result.copy_from_slice(hasher.finalize().as_ref());
+
+    result
+}
+
+fn read_g1<E: Engine, R: Read>(mut reader: R) -> io::Result<E::G1Affine> {
+    let mut repr = <E::G1Affine as CurveAffine>::Uncompressed::empty();
+    reader.read_exact(repr.as_mut())?;
+
+    repr.into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        .and_then(|p| if p.is_zero() {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "point at infinity"))
+        } else {
+            Ok(p)
+        })
+}
+
+fn read_g2<E: Engine, R: Read>(mut reader: R) -> io::Result<E::G2Affine> {
+    let mut repr = <E::G2Affine as CurveAffine>::Uncompressed::empty();
+    reader.read_exact(repr.as_mut())?;
+
+    repr.into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        .and_then(|p| if p.is_zero() {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "point at infinity"))
+        } else {
+            Ok(p)
+        })
+}
+
+/// Deterministically maps the transcript to a point of G2.
+fn hash_to_g2<E: Engine>(transcript: &[u8]) -> E::G2Affine {
+    let digest = blake2b(&[transcript]);
+
+    let mut seed = [0u32; 8];
+    for (i, chunk) in digest[..32].chunks(4).enumerate() {
+        seed[i] = (&chunk[..]).read_u32::<BigEndian>().expect("chunk is 4 bytes");
+    }
+
+    ChaChaRng::from_seed(&seed[..]).gen::<E::G2>().into_affine()
+}
+
+/// Checks that `g1.1 = g1.0 * x` and `g2.1 = g2.0 * x` for the same `x`.
+fn same_ratio<E: Engine>(
+    g1: (E::G1Affine, E::G1Affine),
+    g2: (E::G2Affine, E::G2Affine)
+) -> bool
+{
+    E::pairing(g1.0, g2.1) == E::pairing(g1.1, g2.0)
+}
+
+/// Random linear combinations of two vectors with the same
+/// coefficients, that preserve the ratio between the vectors.
+fn merge_pairs<E: Engine, R: Rng>(
+    v1: &[E::G1Affine],
+    v2: &[E::G1Affine],
+    rng: &mut R
+) -> (E::G1Affine, E::G1Affine)
+{
+    assert_eq!(v1.len(), v2.len());
+
+    let mut s1 = E::G1::zero();
+    let mut s2 = E::G1::zero();
+
+    for (a, b) in v1.iter().zip(v2.iter()) {
+        let rho: E::Fr = rng.gen();
+        s1.add_assign(&a.mul(rho.into_repr()));
+        s2.add_assign(&b.mul(rho.into_repr()));
+    }
+
+    (s1.into_affine(), s2.into_affine())
+}
+
+fn batch_mul<E: Engine>(points: &[E::G1Affine], scalar: &E::Fr) -> Vec<E::G1Affine> {
+    let mut projective: Vec<E::G1> = points.iter().map(|p| p.mul(scalar.into_repr())).collect();
+    E::G1::batch_normalization(&mut projective);
+
+    projective.into_iter().map(|p| p.into_affine()).collect()
+}
+
+impl<E: Engine> MPCParameters<E> {
+    /// Starts the ceremony from the circuit specific parameters.
+    pub fn new(params: Parameters<E>) -> Self {
+        let cs_hash = Self::params_hash(&params);
+
+        MPCParameters {
+            params: params,
+            cs_hash: cs_hash,
+            contributions: vec![]
+        }
+    }
+
+    fn params_hash(params: &Parameters<E>) -> [u8; 64] {
+        let mut bytes = vec![];
+        params.write(&mut bytes).expect("must write into a vector");
+
+        blake2b(&[&bytes[..]])
+    }
+
+    /// Current parameters, usable for proving and verification.
+    pub fn get_params(&self) -> &Parameters<E> {
+        &self.params
+    }
+
+    /// Hash of the initial parameters, that identifies the circuit.
+    pub fn cs_hash(&self) -> [u8; 64] {
+        self.cs_hash
+    }
+
+    pub fn contributions(&self) -> &[PublicKey<E>] {
+        &self.contributions
+    }
+
+    fn transcript(&self) -> Vec<u8> {
+        let mut transcript = self.cs_hash.to_vec();
+        for pubkey in self.contributions.iter() {
+            pubkey.write(&mut transcript).expect("must write into a vector");
+        }
+
+        transcript
+    }
+
+    /// Randomizes the parameters with a fresh secret drawn from `rng`
+    /// and returns the hash of the contribution. The secret
+    /// does not outlive this call.
+    pub fn contribute<R: Rng>(&mut self, rng: &mut R) -> [u8; 64] {
+        let delta: E::Fr = rng.gen();
+        let delta_inv = delta.inverse().expect("secret is non-zero with overwhelming probability");
+
+        let s = rng.gen::<E::G1>().into_affine();
+        let s_delta = s.mul(delta.into_repr()).into_affine();
+
+        let mut transcript = self.transcript();
+        transcript.extend_from_slice(s.into_uncompressed().as_ref());
+        transcript.extend_from_slice(s_delta.into_uncompressed().as_ref());
+        let transcript = blake2b(&[&transcript[..]]);
+
+        let r = hash_to_g2::<E>(&transcript[..]);
+        let r_delta = r.mul(delta.into_repr()).into_affine();
+
+        self.params.vk.delta_g1 = self.params.vk.delta_g1.mul(delta.into_repr()).into_affine();
+        self.params.vk.delta_g2 = self.params.vk.delta_g2.mul(delta.into_repr()).into_affine();
+        self.params.h = Arc::new(batch_mul::<E>(&self.params.h, &delta_inv));
+        self.params.l = Arc::new(batch_mul::<E>(&self.params.l, &delta_inv));
+
+        let pubkey = PublicKey {
+            delta_after: self.params.vk.delta_g1,
+            s: s,
+            s_delta: s_delta,
+            r_delta: r_delta,
+            transcript: transcript
+        };
+        let hash = pubkey.hash();
+        self.contributions.push(pubkey);
+
+        hash
+    }
+
+    /// Verifies the chain of contributions against the initial
+    /// parameters and returns the hashes of all contributions.
+    /// `rng` is only used for batching the checks.
+    pub fn verify<R: Rng>(&self, initial: &Parameters<E>, rng: &mut R) -> Result<Vec<[u8; 64]>, ()> {
+        if &Self::params_hash(initial)[..] != &self.cs_hash[..] {
+            return Err(());
+        }
+
+        let initial_vk = &initial.vk;
+        let vk = &self.params.vk;
+
+        // everything except for delta and the elements depending on it is fixed
+        if initial_vk.alpha_g1 != vk.alpha_g1
+            || initial_vk.beta_g1 != vk.beta_g1
+            || initial_vk.beta_g2 != vk.beta_g2
+            || initial_vk.gamma_g2 != vk.gamma_g2
+            || initial_vk.ic != vk.ic
+            || initial.a != self.params.a
+            || initial.b_g1 != self.params.b_g1
+            || initial.b_g2 != self.params.b_g2
+            || initial.h.len() != self.params.h.len()
+            || initial.l.len() != self.params.l.len()
+        {
+            return Err(());
+        }
+
+        let mut transcript = self.cs_hash.to_vec();
+        let mut delta = initial_vk.delta_g1;
+        let mut hashes = vec![];
+
+        for pubkey in self.contributions.iter() {
+            let mut expected = transcript.clone();
+            expected.extend_from_slice(pubkey.s.into_uncompressed().as_ref());
+            expected.extend_from_slice(pubkey.s_delta.into_uncompressed().as_ref());
+            if &blake2b(&[&expected[..]])[..] != &pubkey.transcript[..] {
+                return Err(());
+            }
+
+            let r = hash_to_g2::<E>(&pubkey.transcript[..]);
+
+            // knowledge of the secret
+            if !same_ratio::<E>((pubkey.s, pubkey.s_delta), (r, pubkey.r_delta)) {
+                return Err(());
+            }
+
+            // the same secret was applied to delta
+            if !same_ratio::<E>((delta, pubkey.delta_after), (r, pubkey.r_delta)) {
+                return Err(());
+            }
+
+            pubkey.write(&mut transcript).expect("must write into a vector");
+            delta = pubkey.delta_after;
+            hashes.push(pubkey.hash());
+        }
+
+        if vk.delta_g1 != delta {
+            return Err(());
+        }
+
+        if !same_ratio::<E>((E::G1Affine::one(), vk.delta_g1), (E::G2Affine::one(), vk.delta_g2)) {
+            return Err(());
+        }
+
+        // h and l are divided by the same factor as delta is multiplied by
+        for (before, after) in [(&initial.h, &self.params.h), (&initial.l, &self.params.l)].iter() {
+            let (after, before) = merge_pairs::<E, _>(&after[..], &before[..], rng);
+            if E::pairing(after, vk.delta_g2) != E::pairing(before, initial_vk.delta_g2) {
+                return Err(());
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.params.write(&mut writer)?;
+        writer.write_all(&self.cs_hash)?;
+        writer.write_u32::<BigEndian>(self.contributions.len() as u32)?;
+        for pubkey in self.contributions.iter() {
+            pubkey.write(&mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> io::Result<Self> {
+        let params = Parameters::read(&mut reader, checked)?;
+        let mut cs_hash = [0u8; 64];
+        reader.read_exact(&mut cs_hash)?;
+
+        let num_contributions = reader.read_u32::<BigEndian>()? as usize;
+        let mut contributions = Vec::with_capacity(num_contributions);
+        for _ in 0..num_contributions {
+            contributions.push(PublicKey::read(&mut reader)?);
+        }
+
+        Ok(MPCParameters {
+            params: params,
+            cs_hash: cs_hash,
+            contributions: contributions
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use bellman::pairing::ff::Field;
+    use bellman::{Circuit, ConstraintSystem, SynthesisError};
+    use bellman::groth16::{
+        generate_random_parameters,
+        create_random_proof,
+        prepare_verifying_key,
+        verify_proof
+    };
+    use super::MPCParameters;
+
+    struct SquareCircuit {
+        x: Option<Fr>
+    }
+
+    impl Circuit<Bn256> for SquareCircuit {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(|| "y", || {
+                let mut tmp = x_value.ok_or(SynthesisError::AssignmentMissing)?;
+                tmp.square();
+
+                Ok(tmp)
+            })?;
+
+            cs.enforce(|| "square", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_contribution_chain() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let initial = generate_random_parameters::<Bn256, _, _>(SquareCircuit { x: None }, rng).unwrap();
+
+        let mut mpc = MPCParameters::new(initial.clone());
+        let h1 = mpc.contribute(rng);
+        let h2 = mpc.contribute(rng);
+
+        let hashes = mpc.verify(&initial, rng).unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(&hashes[0][..], &h1[..]);
+        assert_eq!(&hashes[1][..], &h2[..]);
+
+        let mut bytes = vec![];
+        mpc.write(&mut bytes).unwrap();
+        assert!(MPCParameters::<Bn256>::read(&bytes[..], true).unwrap() == mpc);
+
+        let x: Fr = rng.gen();
+        let mut y = x;
+        y.square();
+
+        let params = mpc.get_params();
+        let proof = create_random_proof(SquareCircuit { x: Some(x) }, params, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+    }
+
+    #[test]
+    fn test_tampered_chain() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let initial = generate_random_parameters::<Bn256, _, _>(SquareCircuit { x: None }, rng).unwrap();
+
+        let mut mpc = MPCParameters::new(initial.clone());
+        mpc.contribute(rng);
+
+        // the contribution is dropped but its effect on the parameters is kept
+        let mut tampered = mpc.clone();
+        tampered.contributions.clear();
+        assert!(tampered.verify(&initial, rng).is_err());
+
+        // parameters of some other circuit
+        let other = generate_random_parameters::<Bn256, _, _>(SquareCircuit { x: None }, rng).unwrap();
+        assert!(mpc.verify(&other, rng).is_err());
+    }
+}
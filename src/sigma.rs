@@ -0,0 +1,361 @@
+//! Non-interactive sigma protocols on Jubjub curves: Schnorr proofs
+//! of knowledge of a discrete log, Chaum-Pedersen proofs that two
+//! points have the same discrete log to two bases (a DH tuple) and
+//! proofs that two Pedersen commitments with different bases, e.g.
+//! a value commitment and one published by another system, hide the
+//! same value.
+//!
+//! Challenges are the Fiat-Shamir hash with Rescue of a protocol tag
+//! and the coordinates of all the points, so that `circuit::sigma` can
+//! verify the proofs without bit decompositions of the hash input.
+
+use bellman::pairing::ff::{Field, PrimeField};
+
+use rand::Rng;
+
+use jubjub::{
+    JubjubEngine,
+    Unknown,
+    PrimeOrder,
+    edwards::Point
+};
+
+use rescue::RescueParams;
+
+use util::rescue_hash_to_scalar;
+
+pub const DLOG_TAG: u64 = 1;
+
+pub const DH_TUPLE_TAG: u64 = 2;
+
+pub const EQUAL_VALUE_TAG: u64 = 3;
+
+/// Proof of knowledge of x with P = x . G.
+#[derive(Clone)]
+pub struct DlogProof<E: JubjubEngine> {
+    /// A = k . G
+    pub commitment: Point<E, Unknown>,
+    /// s = k + c . x
+    pub response: E::Fs
+}
+
+/// Proof of knowledge of x with P = x . G and Q = x . H.
+#[derive(Clone)]
+pub struct DhTupleProof<E: JubjubEngine> {
+    /// A = k . G
+    pub commitment_g: Point<E, Unknown>,
+    /// B = k . H
+    pub commitment_h: Point<E, Unknown>,
+    /// s = k + c . x
+    pub response: E::Fs
+}
+
+/// The bases of a Pedersen commitment v . G + r . H.
+#[derive(Clone)]
+pub struct PedersenBases<E: JubjubEngine> {
+    /// G
+    pub value: Point<E, PrimeOrder>,
+    /// H
+    pub randomness: Point<E, PrimeOrder>
+}
+
+impl<E: JubjubEngine> PedersenBases<E> {
+    pub fn commit(&self, v: E::Fs, r: E::Fs, params: &E::Params) -> Point<E, Unknown> {
+        self.value.mul(v, params).add(&self.randomness.mul(r, params), params).into()
+    }
+}
+
+/// Proof of knowledge of v, r_1 and r_2 with C_1 = v . G_1 + r_1 . H_1
+/// and C_2 = v . G_2 + r_2 . H_2.
+#[derive(Clone)]
+pub struct EqualValueProof<E: JubjubEngine> {
+    /// A_1 = k . G_1 + k_1 . H_1
+    pub commitment_1: Point<E, Unknown>,
+    /// A_2 = k . G_2 + k_2 . H_2
+    pub commitment_2: Point<E, Unknown>,
+    /// s = k + c . v
+    pub response_value: E::Fs,
+    /// s_1 = k_1 + c . r_1
+    pub response_1: E::Fs,
+    /// s_2 = k_2 + c . r_2
+    pub response_2: E::Fs
+}
+
+pub fn challenge<E: JubjubEngine>(
+    rescue_params: &RescueParams<E::Fr>,
+    tag: u64,
+    points: &[&Point<E, Unknown>]
+) -> E::Fs {
+    let mut input = vec![E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(tag)).unwrap()];
+    for p in points.iter() {
+        let (x, y) = p.into_xy();
+        input.push(x);
+        input.push(y);
+    }
+
+    rescue_hash_to_scalar::<E>(rescue_params, &input)
+}
+
+fn is_in_prime_order_subgroup<E: JubjubEngine>(p: &Point<E, Unknown>, params: &E::Params) -> bool {
+    p.mul(E::Fs::char(), params) == Point::zero()
+}
+
+/// s . base == commitment + c . point
+fn check_response<E: JubjubEngine>(
+    base: &Point<E, Unknown>,
+    point: &Point<E, Unknown>,
+    commitment: &Point<E, Unknown>,
+    c: E::Fs,
+    s: E::Fs,
+    params: &E::Params
+) -> bool {
+    base.mul(s, params) == commitment.add(&point.mul(c, params), params)
+}
+
+fn response<E: JubjubEngine>(k: E::Fs, c: E::Fs, x: E::Fs) -> E::Fs {
+    let mut s = c;
+    s.mul_assign(&x);
+    s.add_assign(&k);
+
+    s
+}
+
+pub fn prove_dlog<E: JubjubEngine, R: Rng>(
+    x: E::Fs,
+    g: &Point<E, Unknown>,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    rng: &mut R
+) -> DlogProof<E> {
+    let p = g.mul(x, params);
+
+    let k: E::Fs = rng.gen();
+    let a = g.mul(k, params);
+    let c = challenge::<E>(rescue_params, DLOG_TAG, &[g, &p, &a]);
+
+    DlogProof {
+        commitment: a,
+        response: response::<E>(k, c, x)
+    }
+}
+
+/// Checks a proof of knowledge of the discrete log of `p` to the base
+/// `g`. Both are expected to be in the prime order subgroup.
+pub fn verify_dlog<E: JubjubEngine>(
+    g: &Point<E, Unknown>,
+    p: &Point<E, Unknown>,
+    proof: &DlogProof<E>,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>
+) -> bool {
+    if !is_in_prime_order_subgroup(&proof.commitment, params) {
+        return false;
+    }
+
+    let c = challenge::<E>(rescue_params, DLOG_TAG, &[g, p, &proof.commitment]);
+
+    check_response(g, p, &proof.commitment, c, proof.response, params)
+}
+
+pub fn prove_dh_tuple<E: JubjubEngine, R: Rng>(
+    x: E::Fs,
+    g: &Point<E, Unknown>,
+    h: &Point<E, Unknown>,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    rng: &mut R
+) -> DhTupleProof<E> {
+    let p = g.mul(x, params);
+    let q = h.mul(x, params);
+
+    let k: E::Fs = rng.gen();
+    let a = g.mul(k, params);
+    let b = h.mul(k, params);
+    let c = challenge::<E>(rescue_params, DH_TUPLE_TAG, &[g, h, &p, &q, &a, &b]);
+
+    DhTupleProof {
+        commitment_g: a,
+        commitment_h: b,
+        response: response::<E>(k, c, x)
+    }
+}
+
+/// Checks that `p` and `q` have the same discrete log to the bases `g`
+/// and `h`. All four are expected to be in the prime order subgroup.
+pub fn verify_dh_tuple<E: JubjubEngine>(
+    g: &Point<E, Unknown>,
+    h: &Point<E, Unknown>,
+    p: &Point<E, Unknown>,
+    q: &Point<E, Unknown>,
+    proof: &DhTupleProof<E>,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>
+) -> bool {
+    if !is_in_prime_order_subgroup(&proof.commitment_g, params)
+        || !is_in_prime_order_subgroup(&proof.commitment_h, params) {
+        return false;
+    }
+
+    let c = challenge::<E>(
+        rescue_params,
+        DH_TUPLE_TAG,
+        &[g, h, p, q, &proof.commitment_g, &proof.commitment_h]
+    );
+
+    check_response(g, p, &proof.commitment_g, c, proof.response, params)
+        && check_response(h, q, &proof.commitment_h, c, proof.response, params)
+}
+
+fn equal_value_challenge<E: JubjubEngine>(
+    rescue_params: &RescueParams<E::Fr>,
+    bases_1: &PedersenBases<E>,
+    bases_2: &PedersenBases<E>,
+    c_1: &Point<E, Unknown>,
+    c_2: &Point<E, Unknown>,
+    a_1: &Point<E, Unknown>,
+    a_2: &Point<E, Unknown>
+) -> E::Fs {
+    let bases: Vec<Point<E, Unknown>> = vec![
+        bases_1.value.clone().into(),
+        bases_1.randomness.clone().into(),
+        bases_2.value.clone().into(),
+        bases_2.randomness.clone().into()
+    ];
+
+    challenge::<E>(
+        rescue_params,
+        EQUAL_VALUE_TAG,
+        &[&bases[0], &bases[1], &bases[2], &bases[3], c_1, c_2, a_1, a_2]
+    )
+}
+
+/// Proves that `bases_1.commit(v, r_1)` and `bases_2.commit(v, r_2)`
+/// hide the same value.
+pub fn prove_equal_value<E: JubjubEngine, R: Rng>(
+    v: E::Fs,
+    r_1: E::Fs,
+    r_2: E::Fs,
+    bases_1: &PedersenBases<E>,
+    bases_2: &PedersenBases<E>,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>,
+    rng: &mut R
+) -> EqualValueProof<E> {
+    let c_1 = bases_1.commit(v, r_1, params);
+    let c_2 = bases_2.commit(v, r_2, params);
+
+    let k: E::Fs = rng.gen();
+    let k_1: E::Fs = rng.gen();
+    let k_2: E::Fs = rng.gen();
+    let a_1 = bases_1.commit(k, k_1, params);
+    let a_2 = bases_2.commit(k, k_2, params);
+    let c = equal_value_challenge(rescue_params, bases_1, bases_2, &c_1, &c_2, &a_1, &a_2);
+
+    EqualValueProof {
+        commitment_1: a_1,
+        commitment_2: a_2,
+        response_value: response::<E>(k, c, v),
+        response_1: response::<E>(k_1, c, r_1),
+        response_2: response::<E>(k_2, c, r_2)
+    }
+}
+
+/// Checks that `c_1` and `c_2` are commitments to the same value with
+/// `bases_1` and `bases_2`. Both are expected to be in the prime order
+/// subgroup.
+pub fn verify_equal_value<E: JubjubEngine>(
+    bases_1: &PedersenBases<E>,
+    bases_2: &PedersenBases<E>,
+    c_1: &Point<E, Unknown>,
+    c_2: &Point<E, Unknown>,
+    proof: &EqualValueProof<E>,
+    params: &E::Params,
+    rescue_params: &RescueParams<E::Fr>
+) -> bool {
+    if !is_in_prime_order_subgroup(&proof.commitment_1, params)
+        || !is_in_prime_order_subgroup(&proof.commitment_2, params) {
+        return false;
+    }
+
+    let c = equal_value_challenge(
+        rescue_params,
+        bases_1,
+        bases_2,
+        c_1,
+        c_2,
+        &proof.commitment_1,
+        &proof.commitment_2
+    );
+
+    // s . G_i + s_i . H_i == A_i + c . C_i
+    bases_1.commit(proof.response_value, proof.response_1, params)
+        == proof.commitment_1.add(&c_1.mul(c, params), params)
+        && bases_2.commit(proof.response_value, proof.response_2, params)
+        == proof.commitment_2.add(&c_2.mul(c, params), params)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::bn256::{Bn256, Fr};
+    use alt_babyjubjub::{AltJubjubBn256, fs::Fs};
+    use jubjub::{FixedGenerators, JubjubParams};
+    use rescue::generate_params;
+    use super::*;
+
+    #[test]
+    fn test_sigma_proofs() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let g: Point<Bn256, Unknown> = params.generator(FixedGenerators::SpendingKeyGenerator).clone().into();
+        let h: Point<Bn256, Unknown> = params.generator(FixedGenerators::NullifierPosition).clone().into();
+
+        let x: Fs = rng.gen();
+        let p = g.mul(x, params);
+        let q = h.mul(x, params);
+
+        let proof = prove_dlog(x, &g, params, &rescue_params, rng);
+        assert!(verify_dlog(&g, &p, &proof, params, &rescue_params));
+        assert!(!verify_dlog(&g, &q, &proof, params, &rescue_params));
+        assert!(!verify_dlog(&h, &p, &proof, params, &rescue_params));
+
+        let proof = prove_dh_tuple(x, &g, &h, params, &rescue_params, rng);
+        assert!(verify_dh_tuple(&g, &h, &p, &q, &proof, params, &rescue_params));
+
+        let other = h.mul(rng.gen::<Fs>(), params);
+        assert!(!verify_dh_tuple(&g, &h, &p, &other, &proof, params, &rescue_params));
+        assert!(!verify_dh_tuple(&h, &g, &q, &p, &proof, params, &rescue_params));
+    }
+
+    #[test]
+    fn test_equal_value_proof() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = &AltJubjubBn256::new();
+        let rescue_params = generate_params::<Fr>(3, 128);
+
+        let bases_1 = PedersenBases::<Bn256> {
+            value: params.generator(FixedGenerators::ValueCommitmentValue).clone(),
+            randomness: params.generator(FixedGenerators::ValueCommitmentRandomness).clone()
+        };
+        let bases_2 = PedersenBases::<Bn256> {
+            value: params.generator(FixedGenerators::SpendingKeyGenerator).clone(),
+            randomness: params.generator(FixedGenerators::NullifierPosition).clone()
+        };
+
+        let v: Fs = rng.gen();
+        let r_1: Fs = rng.gen();
+        let r_2: Fs = rng.gen();
+        let c_1 = bases_1.commit(v, r_1, params);
+        let c_2 = bases_2.commit(v, r_2, params);
+
+        let proof = prove_equal_value(v, r_1, r_2, &bases_1, &bases_2, params, &rescue_params, rng);
+        assert!(verify_equal_value(&bases_1, &bases_2, &c_1, &c_2, &proof, params, &rescue_params));
+        assert!(!verify_equal_value(&bases_2, &bases_1, &c_2, &c_1, &proof, params, &rescue_params));
+
+        // a commitment to another value
+        let other = bases_2.commit(rng.gen(), r_2, params);
+        assert!(!verify_equal_value(&bases_1, &bases_2, &c_1, &other, &proof, params, &rescue_params));
+    }
+}
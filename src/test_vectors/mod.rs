@@ -0,0 +1,460 @@
+//! Test vectors from fixture files, checked against both the native
+//! and the circuit implementations.
+//!
+//! Every fixture is a JSON array of vectors with a `source` field naming
+//! the independent implementation or the document the expected output
+//! comes from. Byte strings are hex encoded, field elements are the hex
+//! of their little-endian representation.
+
+use serde_json;
+
+use bellman::ConstraintSystem;
+use bellman::pairing::bls12_381::{Bls12, Fr, FrRepr};
+use bellman::pairing::ff::{BitIterator, Field, PrimeField, PrimeFieldRepr};
+
+use blake2_rfc::blake2s::Blake2s;
+
+use circuit::boolean::{AllocatedBit, Boolean};
+use circuit::ecc::EdwardsPoint;
+use circuit::num::AllocatedNum;
+use circuit::test::TestConstraintSystem;
+use circuit;
+
+use group_hash::group_hash;
+use jubjub::{edwards, FixedGenerators, JubjubBls12, JubjubParams, PrimeOrder, Unknown};
+use jubjub::fs::{Fs, FsRepr};
+use pedersen_hash::{pedersen_hash, Personalization};
+use primitives::Note;
+use redjubjub::{PrivateKey, PublicKey, Signature};
+use util::hash_to_scalar;
+
+#[derive(Deserialize)]
+struct Blake2sVector {
+    source: String,
+    personalization: String,
+    input: String,
+    output: String
+}
+
+#[derive(Deserialize)]
+struct EmptyRootVector {
+    source: String,
+    depth: usize,
+    root: String
+}
+
+#[derive(Deserialize)]
+struct PedersenHashVector {
+    source: String,
+    personalization: String,
+    #[serde(default)]
+    layer: usize,
+    input: String,
+    u: String,
+    v: String
+}
+
+#[derive(Deserialize)]
+struct GroupHashVector {
+    source: String,
+    generator: Option<String>,
+    personalization: String,
+    tag: String,
+    point: Option<String>,
+    scalar: Option<String>,
+    product: Option<String>
+}
+
+#[derive(Deserialize)]
+struct RedJubjubVector {
+    source: String,
+    generator: String,
+    sk: String,
+    vk: String,
+    message: String,
+    signature: String,
+    valid: bool
+}
+
+#[derive(Deserialize)]
+struct NoteCommitmentVector {
+    source: String,
+    value: u64,
+    g_d: String,
+    pk_d: String,
+    rcm: String,
+    cmu: String
+}
+
+#[derive(Deserialize)]
+struct StorageProofVector {
+    source: String,
+    storage_hash: String,
+    slot: String,
+    proof: Vec<String>,
+    leaf: String,
+    exists: bool,
+    value: String
+}
+
+fn from_hex(s: &str) -> Vec<u8> {
+    assert!(s.len() % 2 == 0, "odd length hex string");
+
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..(i + 2)], 16).expect("is hex")).collect()
+}
+
+fn fr_from_hex(s: &str) -> Fr {
+    let mut repr = FrRepr::default();
+    repr.read_le(&from_hex(s)[..]).unwrap();
+
+    Fr::from_repr(repr).expect("is in the field")
+}
+
+fn fs_from_bytes(bytes: &[u8]) -> Fs {
+    let mut repr = FsRepr::default();
+    repr.read_le(bytes).unwrap();
+
+    Fs::from_repr(repr).expect("is in the field")
+}
+
+fn point_from_hex(s: &str, params: &JubjubBls12) -> edwards::Point<Bls12, Unknown> {
+    edwards::Point::read(&from_hex(s)[..], params).expect("is on the curve")
+}
+
+fn point_to_bytes<Order>(p: &edwards::Point<Bls12, Order>) -> Vec<u8> {
+    let mut bytes = vec![];
+    p.write(&mut bytes).unwrap();
+
+    bytes
+}
+
+fn generator<'a>(name: &str, params: &'a JubjubBls12) -> &'a edwards::Point<Bls12, PrimeOrder> {
+    match fixed_generator(name) {
+        Some(base) => params.generator(base),
+        None => {
+            let index: usize = name.trim_start_matches("PedersenHash").parse().expect("is a generator");
+            &params.pedersen_hash_generators()[index]
+        }
+    }
+}
+
+fn fixed_generator(name: &str) -> Option<FixedGenerators> {
+    match name {
+        "ProofGenerationKey" => Some(FixedGenerators::ProofGenerationKey),
+        "NoteCommitmentRandomness" => Some(FixedGenerators::NoteCommitmentRandomness),
+        "NullifierPosition" => Some(FixedGenerators::NullifierPosition),
+        "ValueCommitmentValue" => Some(FixedGenerators::ValueCommitmentValue),
+        "ValueCommitmentRandomness" => Some(FixedGenerators::ValueCommitmentRandomness),
+        "SpendingKeyGenerator" => Some(FixedGenerators::SpendingKeyGenerator),
+        _ => None
+    }
+}
+
+fn alloc_bits<CS: ConstraintSystem<Bls12>>(mut cs: CS, bits: &[bool]) -> Vec<Boolean> {
+    bits.iter().enumerate().map(|(i, &bit)| {
+        Boolean::from(AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(bit)).unwrap())
+    }).collect()
+}
+
+/// Allocates the bytes padded with zeroes to `length` bytes.
+fn alloc_bytes<CS: ConstraintSystem<Bls12>>(cs: CS, bytes: &[u8], length: usize) -> Vec<Boolean> {
+    assert!(bytes.len() <= length);
+
+    let bits: Vec<bool> = (0..(8 * length)).map(|i| {
+        (bytes.get(i / 8).cloned().unwrap_or(0) >> (i % 8)) & 1 == 1
+    }).collect();
+
+    alloc_bits(cs, &bits)
+}
+
+fn bytes_to_booleans(bytes: &[u8]) -> Vec<Boolean> {
+    bytes.iter().flat_map(|&b| (0..8).map(move |i| Boolean::constant((b >> i) & 1 == 1))).collect()
+}
+
+fn booleans_to_bytes(bits: &[Boolean]) -> Vec<u8> {
+    bits.chunks(8).map(|chunk| {
+        chunk.iter().enumerate().fold(0u8, |acc, (i, bit)| {
+            acc | ((bit.get_value().unwrap() as u8) << i)
+        })
+    }).collect()
+}
+
+fn into_bits_le<F: PrimeField>(el: &F) -> Vec<bool> {
+    let mut bits: Vec<bool> = BitIterator::new(el.into_repr()).collect();
+    bits.reverse();
+    bits.truncate(F::NUM_BITS as usize);
+
+    bits
+}
+
+#[test]
+fn test_blake2s_vectors() {
+    let vectors: Vec<Blake2sVector> = serde_json::from_str(include_str!("blake2s.json")).unwrap();
+
+    for vector in vectors.iter() {
+        let personalization = from_hex(&vector.personalization);
+        let input = from_hex(&vector.input);
+        let expected = from_hex(&vector.output);
+
+        let mut h = Blake2s::with_params(32, &[], &[], &personalization);
+        h.update(&input);
+        assert_eq!(h.finalize().as_ref(), &expected[..], "native: {}", vector.source);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = bytes_to_booleans(&input);
+        let hash = circuit::blake2s::blake2s(cs.namespace(|| "blake2s"), &input_bits, &personalization).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(booleans_to_bytes(&hash), expected, "circuit: {}", vector.source);
+    }
+}
+
+#[test]
+fn test_sapling_empty_root_vectors() {
+    let vectors: Vec<EmptyRootVector> = serde_json::from_str(include_str!("sapling_empty_roots.json")).unwrap();
+    let params = &JubjubBls12::new();
+
+    for vector in vectors.iter() {
+        let expected = fr_from_hex(&vector.root);
+
+        let mut root = Fr::one();
+        for layer in 0..vector.depth {
+            let bits = into_bits_le(&root).into_iter().chain(into_bits_le(&root).into_iter());
+            root = pedersen_hash::<Bls12, _>(Personalization::MerkleTree(layer), bits, params).into_xy().0;
+        }
+        assert_eq!(root, expected, "native: {}", vector.source);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut root = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(Fr::one())).unwrap();
+        for layer in 0..vector.depth {
+            let mut cs = cs.namespace(|| format!("layer {}", layer));
+
+            let mut bits = root.into_bits_le_strict(cs.namespace(|| "bits")).unwrap();
+            let rhs = bits.clone();
+            bits.extend(rhs);
+
+            let hash = circuit::pedersen_hash::pedersen_hash(
+                cs.namespace(|| "hash"),
+                Personalization::MerkleTree(layer),
+                &bits,
+                params
+            ).unwrap();
+
+            root = hash.get_x().clone();
+        }
+
+        assert!(cs.is_satisfied());
+        assert_eq!(root.get_value().unwrap(), expected, "circuit: {}", vector.source);
+    }
+}
+
+#[test]
+fn test_pedersen_hash_vectors() {
+    let vectors: Vec<PedersenHashVector> = serde_json::from_str(include_str!("pedersen_hash.json")).unwrap();
+    let params = &JubjubBls12::new();
+
+    for vector in vectors.iter() {
+        let personalization = match vector.personalization.as_str() {
+            "NoteCommitment" => Personalization::NoteCommitment,
+            "MerkleTree" => Personalization::MerkleTree(vector.layer),
+            other => panic!("unknown personalization {}", other)
+        };
+        let input: Vec<bool> = vector.input.chars().map(|c| c == '1').collect();
+        let expected = (fr_from_hex(&vector.u), fr_from_hex(&vector.v));
+
+        let hash = pedersen_hash::<Bls12, _>(personalization, input.iter().cloned(), params);
+        assert_eq!(hash.into_xy(), expected, "native: {}", vector.source);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_bits(cs.namespace(|| "input"), &input);
+        let hash = circuit::pedersen_hash::pedersen_hash(
+            cs.namespace(|| "hash"),
+            personalization,
+            &input_bits,
+            params
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            (hash.get_x().get_value().unwrap(), hash.get_y().get_value().unwrap()),
+            expected,
+            "circuit: {}", vector.source
+        );
+    }
+}
+
+#[test]
+fn test_group_hash_vectors() {
+    let vectors: Vec<GroupHashVector> = serde_json::from_str(include_str!("group_hash.json")).unwrap();
+    let params = &JubjubBls12::new();
+
+    for vector in vectors.iter() {
+        let point = group_hash::<Bls12>(&from_hex(&vector.tag), &from_hex(&vector.personalization), params);
+        assert_eq!(
+            point.as_ref().map(point_to_bytes),
+            vector.point.as_ref().map(|p| from_hex(p)),
+            "native: {}", vector.source
+        );
+
+        if let Some(ref name) = vector.generator {
+            assert_eq!(
+                point_to_bytes(generator(name, params)),
+                from_hex(vector.point.as_ref().unwrap()),
+                "generator {}: {}", name, vector.source
+            );
+        }
+
+        let (point, scalar, product) = match (point, vector.scalar.as_ref(), vector.product.as_ref()) {
+            (Some(point), Some(scalar), Some(product)) => (point, fs_from_bytes(&from_hex(scalar)), product),
+            _ => continue
+        };
+        let expected = point_from_hex(product, params).into_xy();
+        assert_eq!(point.mul(scalar, params).into_xy(), expected, "native: {}", vector.source);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let scalar_bits = alloc_bits(cs.namespace(|| "scalar"), &into_bits_le(&scalar));
+        let base = EdwardsPoint::witness(cs.namespace(|| "point"), Some(point), params).unwrap();
+        let mut products = vec![base.mul(cs.namespace(|| "mul"), &scalar_bits, params).unwrap()];
+        if let Some(base) = vector.generator.as_ref().and_then(|name| fixed_generator(name)) {
+            products.push(circuit::ecc::fixed_base_multiplication(
+                cs.namespace(|| "fixed base mul"),
+                base,
+                &scalar_bits,
+                params
+            ).unwrap());
+        }
+
+        assert!(cs.is_satisfied());
+        for result in products.iter() {
+            assert_eq!(
+                (result.get_x().get_value().unwrap(), result.get_y().get_value().unwrap()),
+                expected,
+                "circuit: {}", vector.source
+            );
+        }
+    }
+}
+
+#[test]
+fn test_redjubjub_vectors() {
+    let vectors: Vec<RedJubjubVector> = serde_json::from_str(include_str!("redjubjub.json")).unwrap();
+    let params = &JubjubBls12::new();
+
+    for vector in vectors.iter() {
+        let p_g = fixed_generator(&vector.generator).expect("is a fixed generator");
+        let message = from_hex(&vector.message);
+        let signature = from_hex(&vector.signature);
+
+        let sk = PrivateKey::<Bls12>(fs_from_bytes(&from_hex(&vector.sk)));
+        let vk = PublicKey::from_private(&sk, p_g, params);
+        assert_eq!(point_to_bytes(&vk.0), from_hex(&vector.vk), "native: {}", vector.source);
+
+        let sig = Signature::read(&signature[..]).unwrap();
+        assert_eq!(vk.verify(&message, &sig, p_g, params), vector.valid, "native: {}", vector.source);
+
+        // S . P_G = R + H*(Rbar || M) . vk
+        let r = edwards::Point::<Bls12, Unknown>::read(&signature[..32], params).unwrap();
+        let s = fs_from_bytes(&signature[32..]);
+        let c = hash_to_scalar::<Bls12>(b"Zcash_RedJubjubH", &signature[..32], &message);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let s_bits = alloc_bits(cs.namespace(|| "s"), &into_bits_le(&s));
+        let c_bits = alloc_bits(cs.namespace(|| "c"), &into_bits_le(&c));
+        let r = EdwardsPoint::witness(cs.namespace(|| "r"), Some(r), params).unwrap();
+        let vk = EdwardsPoint::witness(cs.namespace(|| "vk"), Some(vk.0.clone()), params).unwrap();
+
+        let lhs = circuit::ecc::fixed_base_multiplication(cs.namespace(|| "s.P_G"), p_g, &s_bits, params).unwrap();
+        let rhs = vk.mul(cs.namespace(|| "c.vk"), &c_bits, params).unwrap()
+                    .add(cs.namespace(|| "R + c.vk"), &r, params).unwrap();
+
+        assert!(cs.is_satisfied());
+        let holds = lhs.get_x().get_value() == rhs.get_x().get_value()
+            && lhs.get_y().get_value() == rhs.get_y().get_value();
+        assert_eq!(holds, vector.valid, "circuit: {}", vector.source);
+    }
+}
+
+#[test]
+fn test_note_commitment_vectors() {
+    let vectors: Vec<NoteCommitmentVector> = serde_json::from_str(include_str!("note_commitment.json")).unwrap();
+    let params = &JubjubBls12::new();
+
+    for vector in vectors.iter() {
+        let note = Note::<Bls12> {
+            value: vector.value,
+            g_d: point_from_hex(&vector.g_d, params).as_prime_order(params).unwrap(),
+            pk_d: point_from_hex(&vector.pk_d, params).as_prime_order(params).unwrap(),
+            r: fs_from_bytes(&from_hex(&vector.rcm))
+        };
+        let expected = fr_from_hex(&vector.cmu);
+        assert_eq!(note.cm(params), expected, "native: {}", vector.source);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let value_bits: Vec<bool> = (0..64).map(|i| (vector.value >> i) & 1 == 1).collect();
+        let mut contents = alloc_bits(cs.namespace(|| "value"), &value_bits);
+        let g_d = EdwardsPoint::witness(cs.namespace(|| "g_d"), Some(note.g_d.clone()), params).unwrap();
+        contents.extend(g_d.repr(cs.namespace(|| "g_d repr")).unwrap());
+        let pk_d = EdwardsPoint::witness(cs.namespace(|| "pk_d"), Some(note.pk_d.clone()), params).unwrap();
+        contents.extend(pk_d.repr(cs.namespace(|| "pk_d repr")).unwrap());
+        let rcm = alloc_bits(cs.namespace(|| "rcm"), &into_bits_le(&note.r));
+
+        let cm = circuit::pedersen_hash::pedersen_commitment(
+            cs.namespace(|| "note commitment"),
+            Personalization::NoteCommitment,
+            &contents,
+            &rcm,
+            params
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cm.get_x().get_value().unwrap(), expected, "circuit: {}", vector.source);
+    }
+}
+
+#[test]
+fn test_storage_proof_vectors() {
+    let vectors: Vec<StorageProofVector> = serde_json::from_str(include_str!("storage_proof.json")).unwrap();
+
+    // the longest branch node, with all the 16 children
+    let branch_length = 532;
+    let leaf_length = 70;
+
+    let synthesize = |vector: &StorageProofVector, proof: &[Vec<u8>]| {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let root = alloc_bytes(cs.namespace(|| "storage root"), &from_hex(&vector.storage_hash), 32);
+        let slot = alloc_bytes(cs.namespace(|| "slot"), &from_hex(&vector.slot), 32);
+        let branches: Vec<Vec<Boolean>> = proof.iter().enumerate().map(|(i, node)| {
+            alloc_bytes(cs.namespace(|| format!("branch {}", i)), node, branch_length)
+        }).collect();
+        let leaf = alloc_bytes(cs.namespace(|| "leaf"), &from_hex(&vector.leaf), leaf_length);
+
+        let (exists, value) = circuit::ethereum::verify_storage_proof(
+            cs.namespace(|| "proof"),
+            &root,
+            &slot,
+            &branches,
+            &leaf
+        ).unwrap();
+
+        (cs, exists.get_value().unwrap(), booleans_to_bytes(&value))
+    };
+
+    for vector in vectors.iter() {
+        let mut proof: Vec<Vec<u8>> = vector.proof.iter().map(|node| from_hex(node)).collect();
+
+        let (cs, exists, value) = synthesize(vector, &proof);
+        assert!(cs.is_satisfied(), "{}", vector.source);
+        assert_eq!(exists, vector.exists, "{}", vector.source);
+        assert_eq!(value, from_hex(&vector.value), "{}", vector.source);
+        drop(cs);
+
+        // a byte of a hash in the last but one node, that keeps the
+        // node well-formed but breaks the link from its parent
+        let depth = proof.len() - 2;
+        proof[depth][100] ^= 1;
+
+        let (cs, _, _) = synthesize(vector, &proof);
+        let unsatisfied = cs.which_is_unsatisfied().unwrap().to_string();
+        assert!(unsatisfied.starts_with(&format!("proof/branch {}/reference", depth)), "{}: {}", vector.source, unsatisfied);
+    }
+}
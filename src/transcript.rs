@@ -0,0 +1,232 @@
+//! Fiat-Shamir transcripts. The absorption order, padding and
+//! squeezing match `circuit::transcript` exactly, so that a prover
+//! derives the same challenges as a recursive verifier.
+//!
+//! `Blake2sTranscript` chains the state as
+//! `state = BLAKE2s(state || tag || data)` with a one byte tag for
+//! absorbing and squeezing, and a challenge is the state read as a
+//! little-endian integer truncated to `F::CAPACITY` bits.
+//!
+//! `RescueTranscript` is a duplex sponge: committed elements are
+//! buffered, and squeezing pads the buffer like `rescue::rescue_hash`,
+//! absorbs it and returns the first element of the state.
+
+use bellman::pairing::ff::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use blake2_rfc::blake2s::Blake2s;
+
+use std::marker::PhantomData;
+
+use rescue::{RescueParams, rescue_permutation};
+
+use params_digest::ParamsDigest;
+
+pub const BLAKE2S_TRANSCRIPT_PERSONALIZATION: &'static [u8; 8] = b"Sapling_";
+
+pub const BLAKE2S_TRANSCRIPT_STATE_SIZE: usize = 32;
+
+pub(crate) const ABSORB_TAG: u8 = 0;
+pub(crate) const SQUEEZE_TAG: u8 = 1;
+
+/// `1, c, c^2, ..., c^(n-1)`.
+pub fn powers<F: Field>(c: &F, n: usize) -> Vec<F> {
+    let mut result = Vec::with_capacity(n);
+    let mut power = F::one();
+    for _ in 0..n {
+        result.push(power);
+        power.mul_assign(c);
+    }
+
+    result
+}
+
+pub trait Transcript<F: PrimeField> {
+    fn commit_field_element(&mut self, el: &F);
+
+    fn get_challenge(&mut self) -> F;
+
+    /// Samples an index in `0..bound` for a power of two `bound`
+    /// from the low bits of a challenge.
+    fn get_index(&mut self, bound: usize) -> usize {
+        assert!(bound.is_power_of_two());
+        assert!(bound.trailing_zeros() <= F::CAPACITY.min(64));
+
+        let challenge = self.get_challenge();
+
+        (challenge.into_repr().as_ref()[0] as usize) & (bound - 1)
+    }
+
+    /// Samples a challenge `c` and returns `1, c, ..., c^(n-1)`, e.g.
+    /// to combine `n` claims.
+    fn get_challenge_powers(&mut self, n: usize) -> Vec<F> {
+        let challenge = self.get_challenge();
+
+        powers(&challenge, n)
+    }
+
+    /// Binds the following challenges to the parameters of the proof.
+    fn commit_params<P: ParamsDigest>(&mut self, params: &P) {
+        self.commit_field_element(&params.digest_element());
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2sTranscript<F: PrimeField> {
+    state: [u8; BLAKE2S_TRANSCRIPT_STATE_SIZE],
+    _marker: PhantomData<F>
+}
+
+impl<F: PrimeField> Blake2sTranscript<F> {
+    pub fn new(label: &[u8]) -> Self {
+        let mut t = Self {
+            state: [0u8; BLAKE2S_TRANSCRIPT_STATE_SIZE],
+            _marker: PhantomData
+        };
+        t.commit_bytes(label);
+
+        t
+    }
+
+    pub fn state(&self) -> &[u8; BLAKE2S_TRANSCRIPT_STATE_SIZE] {
+        &self.state
+    }
+
+    fn update(&mut self, tag: u8, data: &[u8]) {
+        let mut h = Blake2s::with_params(BLAKE2S_TRANSCRIPT_STATE_SIZE, &[], &[], BLAKE2S_TRANSCRIPT_PERSONALIZATION);
+        h.update(&self.state);
+        h.update(&[tag]);
+        h.update(data);
+        self.state.copy_from_slice(h.finalize().as_ref());
+    }
+
+    pub fn commit_bytes(&mut self, bytes: &[u8]) {
+        self.update(ABSORB_TAG, bytes);
+    }
+}
+
+impl<F: PrimeField> Transcript<F> for Blake2sTranscript<F> {
+    /// Absorbs the little-endian encoding of `el`.
+    fn commit_field_element(&mut self, el: &F) {
+        let mut buf = vec![];
+        el.into_repr().write_le(&mut buf).unwrap();
+        self.commit_bytes(&buf);
+    }
+
+    fn get_challenge(&mut self) -> F {
+        self.update(SQUEEZE_TAG, &[]);
+
+        field_from_le_bytes(&self.state)
+    }
+}
+
+/// Reads the bytes as a little-endian integer truncated to
+/// `F::CAPACITY` bits.
+pub(crate) fn field_from_le_bytes<F: PrimeField>(bytes: &[u8]) -> F {
+    let mut repr = F::Repr::default();
+    let num_bytes = repr.as_ref().len() * 8;
+    let mut bytes = bytes.to_vec();
+    bytes.resize(num_bytes, 0);
+    repr.read_le(&bytes[..num_bytes]).unwrap();
+
+    let capacity = F::CAPACITY as usize;
+    for (i, limb) in repr.as_mut().iter_mut().enumerate() {
+        let low = i * 64;
+        if low >= capacity {
+            *limb = 0;
+        } else if capacity - low < 64 {
+            *limb &= (1u64 << (capacity - low)) - 1;
+        }
+    }
+
+    F::from_repr(repr).expect("is in the field")
+}
+
+#[derive(Clone, Debug)]
+pub struct RescueTranscript<'a, F: PrimeField> {
+    params: &'a RescueParams<F>,
+    state: Vec<F>,
+    buffer: Vec<F>
+}
+
+impl<'a, F: PrimeField> RescueTranscript<'a, F> {
+    pub fn new(params: &'a RescueParams<F>) -> Self {
+        Self {
+            params: params,
+            state: vec![F::zero(); params.state_width],
+            buffer: vec![]
+        }
+    }
+}
+
+impl<'a, F: PrimeField> Transcript<F> for RescueTranscript<'a, F> {
+    fn commit_field_element(&mut self, el: &F) {
+        self.buffer.push(*el);
+    }
+
+    fn get_challenge(&mut self) -> F {
+        let rate = self.params.rate();
+
+        self.buffer.push(F::one());
+        while self.buffer.len() % rate != 0 {
+            self.buffer.push(F::zero());
+        }
+
+        for chunk in self.buffer.chunks(rate) {
+            for (s, el) in self.state.iter_mut().zip(chunk.iter()) {
+                s.add_assign(el);
+            }
+            rescue_permutation(self.params, &mut self.state);
+        }
+        self.buffer.clear();
+
+        self.state[0]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+    use bellman::pairing::bn256::Fr;
+    use rescue::{generate_params, rescue_hash};
+    use super::*;
+
+    #[test]
+    fn test_rescue_transcript_matches_hash() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = generate_params::<Fr>(3, 128);
+
+        // the first squeeze from a fresh transcript is the sponge hash
+        let input: Vec<Fr> = (0..5).map(|_| rng.gen()).collect();
+        let mut transcript = RescueTranscript::new(&params);
+        for el in input.iter() {
+            transcript.commit_field_element(el);
+        }
+
+        assert_eq!(transcript.get_challenge(), rescue_hash(&params, &input)[0]);
+
+        // consecutive squeezes differ
+        let a = transcript.get_challenge();
+        let b = transcript.get_challenge();
+        assert!(a != b);
+    }
+
+    #[test]
+    fn test_blake2s_challenge_is_truncated() {
+        let mut transcript = Blake2sTranscript::<Fr>::new(b"test");
+        for i in 0..100u64 {
+            transcript.commit_field_element(&Fr::from_str(&i.to_string()).unwrap());
+            let challenge = transcript.get_challenge();
+            assert!(challenge.into_repr().num_bits() <= Fr::CAPACITY);
+        }
+
+        // the label separates transcripts
+        let mut a = Blake2sTranscript::<Fr>::new(b"a");
+        let mut b = Blake2sTranscript::<Fr>::new(b"b");
+        assert!(a.get_challenge() != b.get_challenge());
+    }
+}
@@ -2,8 +2,12 @@ use blake2_rfc::blake2b::Blake2b;
 use blake2_rfc::blake2s::Blake2s;
 use sha2::{Sha256, Digest};
 
+use bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+
 use jubjub::{JubjubEngine, ToUniform};
 
+use rescue::{RescueParams, rescue_hash};
+
 pub fn hash_to_scalar<E: JubjubEngine>(persona: &[u8], a: &[u8], b: &[u8]) -> E::Fs {
     let mut hasher = Blake2b::with_params(64, &[], &[], persona);
     hasher.update(a);
@@ -27,4 +31,36 @@ pub fn sha256_hash_to_scalar<E: JubjubEngine>(persona: &[u8], a: &[u8], b: &[u8]
     hasher.input(b);
     let result = hasher.result();
     E::Fs::to_uniform_32(result.as_slice())
-}
\ No newline at end of file
+}
+
+/// First element of the Rescue hash of `input`, truncated to
+/// `E::Fs::CAPACITY` bits so that circuits can use the low bits of the
+/// hash directly as the scalar.
+pub fn rescue_hash_to_scalar<E: JubjubEngine>(params: &RescueParams<E::Fr>, input: &[E::Fr]) -> E::Fs {
+    let h = rescue_hash(params, input)[0];
+
+    let mut h_bytes = vec![];
+    h.into_repr().write_le(&mut h_bytes).expect("has serialized h");
+    for i in (E::Fs::CAPACITY as usize)..(h_bytes.len() * 8) {
+        h_bytes[i / 8] &= !(1 << (i % 8));
+    }
+
+    let mut repr = <E::Fs as PrimeField>::Repr::default();
+    repr.read_le(&h_bytes[..]).expect("has read h");
+
+    E::Fs::from_repr(repr).expect("is below the modulus")
+}
+
+/// `value` as an element of a field of more than 64 bits.
+pub fn fr_from_u64<F: PrimeField>(value: u64) -> F {
+    F::from_repr(F::Repr::from(value)).expect("is in the field")
+}
+
+/// `value` as an element of a field of more than 128 bits.
+pub fn fr_from_u128<F: PrimeField>(value: u128) -> F {
+    let mut repr = F::Repr::default();
+    repr.as_mut()[0] = value as u64;
+    repr.as_mut()[1] = (value >> 64) as u64;
+
+    F::from_repr(repr).expect("is in the field")
+}
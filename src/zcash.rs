@@ -0,0 +1,382 @@
+//! Zcash Sapling compatibility layer.
+//!
+//! Everything here is pinned to the Zcash protocol specification:
+//! the Jubjub curve over the BLS12-381 scalar field, the BLAKE2s
+//! personalizations of the group hash and the PRFs, and the
+//! note commitment, nullifier and Merkle tree formulas.
+//!
+//! The Sapling primitives of this crate (`primitives`, `pedersen_hash`,
+//! `circuit::sapling`) follow the specification when instantiated with
+//! `JubjubBls12`. The rest of the crate diverges from it: Baby Jubjub
+//! over BN256 (`alt_babyjubjub`) uses the same personalizations but
+//! different curve parameters, and `eddsa`, `redbabyjubjub` and the
+//! Poseidon based trees are not a part of Sapling at all.
+
+use bellman::pairing::bls12_381::{Bls12, Fr};
+use bellman::pairing::ff::{BitIterator, Field, PrimeField};
+
+use constants;
+use jubjub::{JubjubBls12, JubjubParams};
+use pedersen_hash::{pedersen_hash, Personalization};
+use primitives::{Note, ViewingKey};
+
+/// Depth of the Sapling note commitment tree.
+pub const SAPLING_COMMITMENT_TREE_DEPTH: usize = 32;
+
+/// Personalizations used by Sapling, as listed in the specification.
+pub const SAPLING_PERSONALIZATIONS: [&'static [u8; 8]; 8] = [
+    b"Zcashivk",
+    b"Zcash_nf",
+    b"Zcash_PH",
+    b"Zcash_gd",
+    b"Zcash_G_",
+    b"Zcash_H_",
+    b"Zcash_cv",
+    b"Zcash_J_"
+];
+
+/// First block of the group hash input, the "randomness beacon"
+/// of the specification.
+pub const URS: &'static [u8; 64] = b"096b36a5804bfacef1691e173c366a47ff5ba84a44f26ddd7e8d9f79d5b42df0";
+
+/// Creates the Jubjub parameters and checks that they match the specification.
+pub fn params() -> JubjubBls12 {
+    let params = JubjubBls12::new();
+    assert!(check_params(&params), "Jubjub parameters diverge from the Sapling specification");
+
+    params
+}
+
+/// Checks the curve constants and the personalizations used to derive
+/// the generators against the specification.
+pub fn check_params(params: &JubjubBls12) -> bool {
+    // d = -(10240/10241)
+    let mut d = Fr::from_str("10241").unwrap().inverse().unwrap();
+    d.mul_assign(&Fr::from_str("10240").unwrap());
+    d.negate();
+
+    let used_personalizations = [
+        constants::CRH_IVK_PERSONALIZATION,
+        constants::PRF_NF_PERSONALIZATION,
+        constants::PEDERSEN_HASH_GENERATORS_PERSONALIZATION,
+        constants::KEY_DIVERSIFICATION_PERSONALIZATION,
+        constants::SPENDING_KEY_GENERATOR_PERSONALIZATION,
+        constants::PROOF_GENERATION_KEY_BASE_GENERATOR_PERSONALIZATION,
+        constants::VALUE_COMMITMENT_GENERATOR_PERSONALIZATION,
+        constants::NULLIFIER_POSITION_IN_TREE_GENERATOR_PERSONALIZATION
+    ];
+
+    *params.edwards_d() == d
+        && *params.montgomery_a() == Fr::from_str("40962").unwrap()
+        && &constants::GH_FIRST_BLOCK[..] == &URS[..]
+        && used_personalizations.iter().zip(SAPLING_PERSONALIZATIONS.iter()).all(|(a, b)| a == b)
+}
+
+fn into_bits_le(el: &Fr) -> Vec<bool> {
+    let mut bits: Vec<bool> = BitIterator::new(el.into_repr()).collect();
+    bits.reverse();
+    bits.truncate(Fr::NUM_BITS as usize);
+
+    bits
+}
+
+/// MerkleCRH^Sapling of two nodes at the given layer,
+/// counted from the leaves.
+pub fn merkle_hash(layer: usize, lhs: &Fr, rhs: &Fr, params: &JubjubBls12) -> Fr {
+    let bits = into_bits_le(lhs).into_iter().chain(into_bits_le(rhs).into_iter());
+
+    pedersen_hash::<Bls12, _>(Personalization::MerkleTree(layer), bits, params).into_xy().0
+}
+
+/// Value of an empty leaf of the note commitment tree.
+pub fn uncommitted_leaf() -> Fr {
+    Fr::one()
+}
+
+/// Root of an empty tree of the given depth.
+pub fn empty_root(depth: usize, params: &JubjubBls12) -> Fr {
+    let mut cur = uncommitted_leaf();
+    for layer in 0..depth {
+        cur = merkle_hash(layer, &cur, &cur, params);
+    }
+
+    cur
+}
+
+/// Note commitment `cm_u` as placed into the commitment tree.
+pub fn note_commitment(note: &Note<Bls12>, params: &JubjubBls12) -> Fr {
+    note.cm(params)
+}
+
+/// Nullifier of the note at the given position in the tree.
+pub fn nullifier(
+    note: &Note<Bls12>,
+    viewing_key: &ViewingKey<Bls12>,
+    position: u64,
+    params: &JubjubBls12
+) -> Vec<u8>
+{
+    note.nf(viewing_key, position, params)
+}
+
+#[cfg(test)]
+mod test {
+    use bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+    use jubjub::edwards;
+    use jubjub::fs::{Fs, FsRepr};
+    use primitives::{prf_expand, Diversifier, ExpandedSpendingKey};
+    use super::*;
+
+    struct TestVector {
+        sk: [u8; 32],
+        ask: [u8; 32],
+        nsk: [u8; 32],
+        ovk: [u8; 32],
+        ak: [u8; 32],
+        nk: [u8; 32],
+        ivk: [u8; 32],
+        default_d: [u8; 11],
+        default_pk_d: [u8; 32],
+        note_v: u64,
+        note_r: [u8; 32],
+        note_cmu: [u8; 32],
+        note_pos: u64,
+        note_nf: [u8; 32]
+    }
+
+    fn repr_bytes<F: PrimeField>(el: &F) -> Vec<u8> {
+        let mut bytes = vec![];
+        el.into_repr().write_le(&mut bytes).unwrap();
+
+        bytes
+    }
+
+    fn point_bytes<Order>(p: &edwards::Point<Bls12, Order>) -> Vec<u8> {
+        let mut bytes = vec![];
+        p.write(&mut bytes).unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn test_params() {
+        let params = JubjubBls12::new();
+        assert!(check_params(&params));
+    }
+
+    #[test]
+    fn test_empty_root() {
+        // root of the empty Sapling commitment tree, as hardcoded in zcashd
+        // (displayed there in the reversed byte order)
+        let params = params();
+        let root = empty_root(SAPLING_COMMITMENT_TREE_DEPTH, &params);
+
+        let mut bytes = vec![];
+        root.into_repr().write_le(&mut bytes).unwrap();
+
+        assert_eq!(
+            bytes,
+            hex!("fbc2f4300c01f0b7820d00e3347c8da4ee614674376cbc45359daa54f9b5493e").to_vec()
+        );
+    }
+
+    #[test]
+    fn test_key_components() {
+        // the vectors of zcash-test-vectors sapling_key_components:
+        // sk = [i; 32] and the note fields derived from i by its recipe
+        let test_vectors = vec![
+            TestVector {
+                sk: hex!("0000000000000000000000000000000000000000000000000000000000000000"),
+                ask: hex!("8548a14a473ea547aa2378402044f818cf1911cf5dd2054f678345f00d0e8806"),
+                nsk: hex!("30114ea0dd0bb61cf0eaeab6ec3331f581b0425e27338501262d7eac745e6e05"),
+                ovk: hex!("98d16913d99b04177caba44f6e4d224e03b5ac031d7ce45e865138e1b996d63b"),
+                ak: hex!("f344ec380fe1273e3098c2588c5d3a791fd7ba958032760777fd0efa8ef11620"),
+                nk: hex!("f7cf9e77f2e58683383c1519ac7b062d30040e27a725fb88fb19a978bd3fd6ba"),
+                ivk: hex!("b70b7cd0ed03cbdfd7ada9502ee245b13e569d54a5719d2daa0f5f1451479204"),
+                default_d: hex!("f19d9b797e39f337445839"),
+                default_pk_d: hex!("db4cd2b0aac4f7eb8ca131f16567c445a9555126d3c29f14e3d776e841ae7415"),
+                note_v: 0,
+                note_r: hex!("39176dac39ace4980ecc8d778e89860255ec3615060000000000000000000000"),
+                note_cmu: hex!("cb3cf9153270d57eb914c6c2bcc01850c9fed44fce0806278f083ef2dd076439"),
+                note_pos: 0,
+                note_nf: hex!("44fad6564ffdec9fa19c43a28f861d5ebf602346007de76267d9752747ab4063")
+            },
+            TestVector {
+                sk: hex!("0101010101010101010101010101010101010101010101010101010101010101"),
+                ask: hex!("c9435629bf8bffe55e7335ec077718ba60ba28d7ac3794b74f512c31af0a5304"),
+                nsk: hex!("11acc2ead07b5f008c1f0f090cc8ddf335236ff4b253c6495695e9d639dacd08"),
+                ovk: hex!("3b946210ce6d1b1692d7392ac84a8bc8f03b72723c7d36721b809a79c9d6e45b"),
+                ak: hex!("82ff5effc527ae84020bf2d35201c10219131947ff4b96f881a45f2e8ae30518"),
+                nk: hex!("c4534d848bb918cf4a7f8b98740ab3ccee586795ff4df64547a8888a6c7415d2"),
+                ivk: hex!("c518384466b26988b5109067418d192d9d6bd0d9232205d77418c240fc68a406"),
+                default_d: hex!("aef180f6e34e354b888f81"),
+                default_pk_d: hex!("a6b13ea336ddb7a67bb09a0e68e9d3cfb39210831ea3a296ba09a922060fd38b"),
+                note_v: 12227227834928555328,
+                note_r: hex!("478ba0ee6e1a75b600036f26f18b7015ab556beddf8b960238869f89dd804e06"),
+                note_cmu: hex!("b57893500bfb85df2e8b01ac452f89e10e266bcfa31c31b29a53ae72cad46950"),
+                note_pos: 763714296,
+                note_nf: hex!("679eb0c3a757e2ae83cdb42a1ab259d78388315419adc71d2e3763174c2e9d93")
+            },
+            TestVector {
+                sk: hex!("0202020202020202020202020202020202020202020202020202020202020202"),
+                ask: hex!("ee1c3d7efe0a78063d6af3d9d81212af47b7c1b761f85ccb066fc11a6a421703"),
+                nsk: hex!("1d3b713755d74875e8ea38fd166e76c62a4250216e6bbfe48a5e2eabad117f0b"),
+                ovk: hex!("8bf4390e28ddc95b8302c381d5810b84ba8e6096e5a76822774fd49f491e8f49"),
+                ak: hex!("ab83574eb5de859a0ab8629dec34c7bee8c3fc74dfa0b19a3a7468d15dca64c6"),
+                nk: hex!("95d58053e0592e4a169cc0b7928aaac3de24ef1531aa9eb6f4ab93914da8a06e"),
+                ivk: hex!("471c24a3dc8730e75036c0a95f3e2f7dd1be6fb93ad29592203def3041954505"),
+                default_d: hex!("7599f0bf9b57cd2dc299b6"),
+                default_pk_d: hex!("66141739514b28f05def8a18eeee5eed4d44c6225c3c65d88dd9907708012f5a"),
+                note_v: 6007711596147559040,
+                note_r: hex!("147cf2b51b4c7c63cb77b99e8b783e5b5111db0a7ca04d6c014a1d7da83bae0a"),
+                note_cmu: hex!("db85a70a98437f73167fc332d5b7b7408296661770b101b0aa87839f4e55f151"),
+                note_pos: 1527428592,
+                note_nf: hex!("e98f6a8f34ff498059b3c731b91f451108c4954d919484361cf9b48f59ae1d14")
+            },
+            TestVector {
+                sk: hex!("0303030303030303030303030303030303030303030303030303030303030303"),
+                ask: hex!("00c3a1e1ca8f4e0480ee1ee90ca7517879d3fc5c815c0903e5eebc94bb809503"),
+                nsk: hex!("e66285a5e9b65e157ad2fcd543dad98c67a58abdf287e05506bd1c2e59b0720b"),
+                ovk: hex!("147678e0553b97829347647c5bc7dab4cc2202b54ec29fd31a3de6be0825fc5e"),
+                ak: hex!("3c9cde7e5d0d38a8610faadbcf4c343f5d3cfa3155a5b94661a6753e96e884ea"),
+                nk: hex!("b77d36f508941dbd61cfd0f159ee05cfaa78a26c9492903806d83b598d3c1c2a"),
+                ivk: hex!("636aa964bfc23ce4b1fcf7dfc99179ddc406ff55400c9295acfc14f031c72600"),
+                default_d: hex!("1b81614f1dadea0f8d0a58"),
+                default_pk_d: hex!("25eb55fccf761fc64e85a588efe6ead7832fb1f0f7a83165895bdff942925f5c"),
+                note_v: 18234939431076114368,
+                note_r: hex!("34a4b2a9144ff5ea54efee87cf901b5bed5e35d21fbbd788d5bd9d833e112804"),
+                note_cmu: hex!("e08ce482b3a8fb3b35ccdbe34337bd105d8839212e0d1644b9d55caa60d19b6c"),
+                note_pos: 2291142888,
+                note_nf: hex!("5547aa12ff80a6b3304e3b058656472abd2c8183b59d0737b93cee758bec47a1")
+            },
+            TestVector {
+                sk: hex!("0404040404040404040404040404040404040404040404040404040404040404"),
+                ask: hex!("8236d19d3205d85543a06811343f827b6563770a49aa4d0ca0081805d4c8ea0d"),
+                nsk: hex!("7ec1ef0bed82718272f0f44f017c484174513d661dd168af02d2092a1d8a0507"),
+                ovk: hex!("1b6e75ece3ace8dba6a5410d9ad4755668e4b39585d635ec1da7c8dcfd5fc4ed"),
+                ak: hex!("55e88389bb7e41de130cfa51a8715fde01ff9c6876647f0175ad34f058dde01a"),
+                nk: hex!("725d4ad6a15021cd1c48c5ee19de6c1e768a2cc0a9a730a01bb21c95e3d9e43c"),
+                ivk: hex!("67fa2bf7c67d4658243c317c0cb41fd32064dfd3709fe0dcb724f14bb01a1d04"),
+                default_d: hex!("fcfb68a40d4bc6a04b09c4"),
+                default_pk_d: hex!("8b2a337f03622c24ff381d4c546f6977f90522e92fde44c9d1bb099714b9db2b"),
+                note_v: 12015423192295118080,
+                note_r: hex!("e557851355747c09ac59013cbde85980964ec1844d9c6967ca0c029c8457bb04"),
+                note_cmu: hex!("bdc854bf3e7b00821f3b8b85238ccf1e6715bfe70b632d044b26fb2bc71b7f36"),
+                note_pos: 3054857184,
+                note_nf: hex!("8a9abda3d4ef85caf22bfaf2c48f62382a73a1624eb8eb2bd00d270301bf3d13")
+            },
+            TestVector {
+                sk: hex!("0505050505050505050505050505050505050505050505050505050505050505"),
+                ask: hex!("eae6884d764a054061a8f1c0076c624dcb738789f7ad1e7408e31f24dfc82607"),
+                nsk: hex!("fbe610f42a41749f9b6e6e4a54b5a32ebfe8f43800881ba6cd13ed0b05294601"),
+                ovk: hex!("c6bc1f39f0d786314cb20bf9ab228540913555f970696b6d7c77bb332328372a"),
+                ak: hex!("e682765914e3864c339e5782b855c0fdf40e0dfcedb9e7b47bc94b90b3a4c988"),
+                nk: hex!("82256b95623c67024b4424d91400a370e7ac8e4d15482a3759e00d219749daee"),
+                ivk: hex!("ea3f1d80e4307ca73b9f37801f91fba810cc41d279fc29f564235654a2178e03"),
+                default_d: hex!("eb519882ad1e5cc654cd59"),
+                default_pk_d: hex!("6b27daccb5a8207f532d10ca238f9786648a11b5966e51a2f7d89e15d29b8fdf"),
+                note_v: 5795906953514121792,
+                note_r: hex!("68f06104606b0c5449845ff4c65f73e90f45ef5a43c9d74cb2c85cf56c94c002"),
+                note_cmu: hex!("e8267d30ac11c100bc7a0fdf91f71d74c5bcf2e1ef95669044730169de1a5b4c"),
+                note_pos: 3818571480,
+                note_nf: hex!("332ad99eb9e977eb627a122dbfb2f25fe588e597753ec5580ff2be20b6c9a7e1")
+            },
+            TestVector {
+                sk: hex!("0606060606060606060606060606060606060606060606060606060606060606"),
+                ask: hex!("e8f816b4bc08a7e566750cc28afe82a4cea9c2bef244fa4b13c4739b28074c0d"),
+                nsk: hex!("32615b137f2801ed446e48781ab0634572e18cfb0693721b8803c05b8227d107"),
+                ovk: hex!("f62c05e848a873ef885e12b08c5e7ca2f32424bacc754cb69750444d355f5106"),
+                ak: hex!("ff27db0751945d3ee4be9cf15c2ea211b24b164d5f2d7ddff5e4a0708f10b95e"),
+                nk: hex!("943885959d4ef8a9cfca07c457f09ec74b96f993d8e0fa32b19c03e3b07a420f"),
+                ivk: hex!("b5c5894943956933c0e5c12d311fc12cba58354b5c389edc03da55084f74c205"),
+                default_d: hex!("bebb0fb46b8aaff89040f6"),
+                default_pk_d: hex!("d11da01f0b43bdd5288d32385b8771d223493c69802544043f77cf1d71c1cb8c"),
+                note_v: 18023134788442677120,
+                note_r: hex!("49f90b47fd52fee7c1c81f0dcb5b74c3fb9b3e03976f8b7524eabad008892107"),
+                note_cmu: hex!("572ba20525b0ac4d6dc01ac2ea1090b6e0f2f4bf4ec4a0db5bbccb5b783a1e55"),
+                note_pos: 287318480,
+                note_nf: hex!("fc74cd0e4be04957b196cf8734ae992396af4cfa8fecbb86f961e6b407d51e11")
+            },
+            TestVector {
+                sk: hex!("0707070707070707070707070707070707070707070707070707070707070707"),
+                ask: hex!("74b44a37f15023c060427e1daea3f64312dd8feb7b2cedf0dd5544493f872c06"),
+                nsk: hex!("075c35db8b1b25754223ecee34ab730dddd1f14a6a54f4c6f468453c3c6ed60b"),
+                ovk: hex!("e9e0dc1ed311daed64bd74da5d94fe88a6ea414b7312de3d2a78f64632bbe373"),
+                ak: hex!("283f9aafa9bcb3e6ce17e63212634cb3ee550c476b676bd356a6df8adf51d25e"),
+                nk: hex!("dc4c67b10d4b0a218dc6e1487066740a409317866c32e664b50e397aa80389d4"),
+                ivk: hex!("8716c82880e13683e1bb059dd06c80c90134a96d5afca8aac2bbf68bb05f8402"),
+                default_d: hex!("ad6e2e185a3100e3a6a8b3"),
+                default_pk_d: hex!("32cb2806b882f1368b0d4a898f72c4c8f728132cc12456946e7f4cb0fb058da9"),
+                note_v: 11803618549661680832,
+                note_r: hex!("5165aff22dd4ed56b4d81d1f171cc3d6432fed1bebf20a7beab12db142f94a0c"),
+                note_cmu: hex!("ab7fc566873ccde671f59827678560a006f82bb7adcd75223fa85936f78c2b23"),
+                note_pos: 1051032776,
+                note_nf: hex!("d2e887bd854a802bce857053020f5d3e7c8ae5267c5b6583b3d212cc8bb69890")
+            },
+            TestVector {
+                sk: hex!("0808080808080808080808080808080808080808080808080808080808080808"),
+                ask: hex!("039dd93df311ff8fbab3fe230219cd42ac879484f30b903a3c1e67ccca5a7b0d"),
+                nsk: hex!("049fa14f486c75b9fad7e3b673a443dd074eaa96edcb2a53eaaabdaf70ffbb08"),
+                ovk: hex!("147dd11d77eba1b1636fd6190c62b9a5d0481bee7e917fab02e21858063ab504"),
+                ak: hex!("364048eedbe8ca205eb7e7ba0a9012166c7c7bd9eb228e08481448c488aa21d2"),
+                nk: hex!("ed60af1ce7df38070d3851432a96480db0b417c3682a1d68e3e89334235c0bdf"),
+                ivk: hex!("99c9b4b84f4b4e350f787d1cf7051d50ecc34b1a5b20d2d2139b4af1f160e001"),
+                default_d: hex!("21c90e1c658b3efe86af58"),
+                default_pk_d: hex!("9e64174b4ab981405c323b5e12475945a46d4fedf8060828041cd20e62fd2cef"),
+                note_v: 5584102310880684544,
+                note_r: hex!("8c3e56449dc86354d33b025ef2793460bcb169f3324e4a6b64baa60832315704"),
+                note_cmu: hex!("7b48a8375d3ebd56bc649bb5b5242336c2a05a0803239b5b88fd92078fea4d04"),
+                note_pos: 1814747072,
+                note_nf: hex!("a82f1750cc5b2bee649a365c0420ed87075b8871fda4a7f5840d6bbeb17cd620")
+            },
+            TestVector {
+                sk: hex!("0909090909090909090909090909090909090909090909090909090909090909"),
+                ask: hex!("ebbb40a980ba3b8860948d011e1bfb4affe16c652e90e98258302f4464c91e0c"),
+                nsk: hex!("68431b199104215200b95ee5cb71bf8b883a3e95b7989cad197063141ebbfd00"),
+                ovk: hex!("573467a7b30ead6ccc504744ca9e1a281a0d1a08738b06a0684feacd1e9d126d"),
+                ak: hex!("71c3523eeca35311fbd5d7e7d70b709d6c35a24f262b34bf64059bf2c02e0ba8"),
+                nk: hex!("624400103b6569b7358fe80f6f6cad4325defda9d9499c2b8f886a6269a2aa52"),
+                ivk: hex!("db95ea8bd9f93d41b5ab2bebc91a38edd527083e2a6ef9f3c29702d5ff89ed00"),
+                default_d: hex!("233c4ab886a55e3ba374c0"),
+                default_pk_d: hex!("b68e9ee0c0678d7b3036931c831a25255f7ee487385a30316e15f6482b874fda"),
+                note_v: 17811330145809239872,
+                note_r: hex!("6ebbed743619a256f9ad2e85880cfaa9098a5fdb1629990d9a7d3bb93fc90003"),
+                note_cmu: hex!("d376a7bee8ce67f4efde56aa77cf64419b0e550abbcb8e2bcbda8b63e41deb37"),
+                note_pos: 2578461368,
+                note_nf: hex!("653674873b3c670c58858473e7fe721972fb96e215b87377a17ca3710d93c9e9")
+            }
+        ];
+
+        let params = params();
+
+        for tv in test_vectors.iter() {
+            let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&tv.sk);
+            assert_eq!(repr_bytes(&expsk.ask), tv.ask);
+            assert_eq!(repr_bytes(&expsk.nsk), tv.nsk);
+            assert_eq!(expsk.ovk, tv.ovk);
+
+            let viewing_key = expsk.proof_generation_key(&params).into_viewing_key(&params);
+            assert_eq!(point_bytes(&viewing_key.ak), tv.ak);
+            assert_eq!(point_bytes(&viewing_key.nk), tv.nk);
+            assert_eq!(repr_bytes(&viewing_key.ivk()), tv.ivk);
+
+            // the default diversifier is the first valid PRF^expand(sk, [3, i])
+            let diversifier = (0u8..).map(|i| {
+                let mut d = [0u8; 11];
+                d.copy_from_slice(&prf_expand(&tv.sk, &[3, i])[..11]);
+                Diversifier(d)
+            }).find(|d| d.g_d::<Bls12>(&params).is_some()).unwrap();
+            assert_eq!(diversifier.0, tv.default_d);
+
+            let address = viewing_key.into_payment_address(diversifier, &params).unwrap();
+            assert_eq!(point_bytes(&address.pk_d), tv.default_pk_d);
+
+            let mut note_r = FsRepr::default();
+            note_r.read_le(&tv.note_r[..]).unwrap();
+            let note = address.create_note(tv.note_v, Fs::from_repr(note_r).unwrap(), &params).unwrap();
+
+            assert_eq!(repr_bytes(&note_commitment(&note, &params)), tv.note_cmu);
+            assert_eq!(nullifier(&note, &viewing_key, tv.note_pos, &params), tv.note_nf);
+        }
+    }
+}